@@ -0,0 +1,191 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+use mpl_bubblegum::hash::{hash_creators, hash_metadata};
+use mpl_bubblegum::instructions::{
+    CreateTreeConfigCpi, CreateTreeConfigCpiAccounts, CreateTreeConfigInstructionArgs, DelegateCpi,
+    DelegateCpiAccounts, DelegateInstructionArgs, MintV1Cpi, MintV1CpiAccounts,
+    MintV1InstructionArgs,
+};
+use mpl_bubblegum::types::{MetadataArgs, TokenProgramVersion, TokenStandard};
+
+use crate::{CompressedLeaf, ErrorCode, InsuranceNFT};
+
+pub use mpl_bubblegum::ID as BUBBLEGUM_ID;
+
+// Seed of the PDA that creates the policy tree and signs every mint into it
+pub const TREE_AUTHORITY_SEED: &[u8] = b"cnft_tree_authority";
+// Seed of the PDA compressed policies are delegated to while a claim is open
+pub const ESCROW_SEED: &[u8] = b"cnft_escrow";
+
+pub const POLICY_NAME: &str = "IRIS Policy";
+pub const POLICY_SYMBOL: &str = "IRIS";
+// The policy terms hash follows this prefix in each leaf's metadata uri
+pub const POLICY_URI_PREFIX: &str = "iris:policy:";
+
+// Accounts shared by every Bubblegum CPI on the policy tree
+pub struct TreeAccounts<'a, 'info> {
+    pub bubblegum_program: &'a AccountInfo<'info>,
+    pub tree_config: &'a AccountInfo<'info>,
+    pub merkle_tree: &'a AccountInfo<'info>,
+    pub log_wrapper: &'a AccountInfo<'info>,
+    pub compression_program: &'a AccountInfo<'info>,
+    pub system_program: &'a AccountInfo<'info>,
+}
+
+// Hash of the terms a compressed policy was sold on, so the leaf commits to them
+pub fn terms_hash(policy: &InsuranceNFT) -> [u8; 32] {
+    let mut terms = Vec::with_capacity(1 + 8 + 8 + 8 + 32 + 32);
+    terms.push(policy.tier);
+    terms.extend_from_slice(&policy.payout_cap.to_le_bytes());
+    terms.extend_from_slice(&policy.coverage_starts_at.to_le_bytes());
+    terms.extend_from_slice(&policy.expiry.to_le_bytes());
+    terms.extend_from_slice(policy.pool.as_ref());
+    terms.extend_from_slice(policy.owner.as_ref());
+    hash(&terms).to_bytes()
+}
+
+// Leaf metadata for a policy: immutable, no creators or royalties, terms in the uri
+pub fn policy_metadata(terms_hash: &[u8; 32]) -> MetadataArgs {
+    let hex: String = terms_hash
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    MetadataArgs {
+        name: POLICY_NAME.to_string(),
+        symbol: POLICY_SYMBOL.to_string(),
+        uri: format!("{}{}", POLICY_URI_PREFIX, hex),
+        seller_fee_basis_points: 0,
+        primary_sale_happened: true,
+        is_mutable: false,
+        edition_nonce: None,
+        token_standard: Some(TokenStandard::NonFungible),
+        collection: None,
+        uses: None,
+        token_program_version: TokenProgramVersion::Original,
+        creators: vec![],
+    }
+}
+
+// Create the Bubblegum tree config for a merkle tree the admin allocated, with the
+// tree authority PDA as its creator
+pub fn create_tree<'info>(
+    tree: &TreeAccounts<'_, 'info>,
+    payer: &AccountInfo<'info>,
+    tree_authority: &AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+    max_depth: u32,
+    max_buffer_size: u32,
+) -> Result<()> {
+    CreateTreeConfigCpi::new(
+        tree.bubblegum_program,
+        CreateTreeConfigCpiAccounts {
+            tree_config: tree.tree_config,
+            merkle_tree: tree.merkle_tree,
+            payer,
+            tree_creator: tree_authority,
+            log_wrapper: tree.log_wrapper,
+            compression_program: tree.compression_program,
+            system_program: tree.system_program,
+        },
+        CreateTreeConfigInstructionArgs {
+            max_depth,
+            max_buffer_size,
+            public: Some(false),
+        },
+    )
+    .invoke_signed(signer_seeds)?;
+    Ok(())
+}
+
+// Mint the policy leaf to `owner` and return what later proofs need to rebuild it
+pub fn mint_policy<'info>(
+    tree: &TreeAccounts<'_, 'info>,
+    owner: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    tree_authority: &AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+    nonce: u64,
+    metadata: MetadataArgs,
+) -> Result<CompressedLeaf> {
+    let leaf = CompressedLeaf {
+        merkle_tree: tree.merkle_tree.key(),
+        nonce,
+        data_hash: hash_metadata(&metadata).map_err(|_| ErrorCode::InvalidPolicyLeaf)?,
+        creator_hash: hash_creators(&metadata.creators),
+    };
+    MintV1Cpi::new(
+        tree.bubblegum_program,
+        MintV1CpiAccounts {
+            tree_config: tree.tree_config,
+            leaf_owner: owner,
+            leaf_delegate: owner,
+            merkle_tree: tree.merkle_tree,
+            payer,
+            tree_creator_or_delegate: tree_authority,
+            log_wrapper: tree.log_wrapper,
+            compression_program: tree.compression_program,
+            system_program: tree.system_program,
+        },
+        MintV1InstructionArgs { metadata },
+    )
+    .invoke_signed(signer_seeds)?;
+    Ok(leaf)
+}
+
+// Delegate the owner's policy leaf to the escrow PDA. Bubblegum checks `proof` (leaf to
+// root, as accounts) against the owner's leaf, so this also proves ownership.
+pub fn delegate_to_escrow<'info>(
+    tree: &TreeAccounts<'_, 'info>,
+    owner: &AccountInfo<'info>,
+    previous_delegate: &AccountInfo<'info>,
+    escrow: &AccountInfo<'info>,
+    proof: &[AccountInfo<'info>],
+    leaf: &CompressedLeaf,
+    root: [u8; 32],
+) -> Result<()> {
+    let index = u32::try_from(leaf.nonce).map_err(|_| ErrorCode::InvalidPolicyLeaf)?;
+    let proof: Vec<(&AccountInfo<'info>, bool, bool)> =
+        proof.iter().map(|node| (node, false, false)).collect();
+    DelegateCpi::new(
+        tree.bubblegum_program,
+        DelegateCpiAccounts {
+            tree_config: tree.tree_config,
+            leaf_owner: owner,
+            previous_leaf_delegate: previous_delegate,
+            new_leaf_delegate: escrow,
+            merkle_tree: tree.merkle_tree,
+            log_wrapper: tree.log_wrapper,
+            compression_program: tree.compression_program,
+            system_program: tree.system_program,
+        },
+        DelegateInstructionArgs {
+            root,
+            data_hash: leaf.data_hash,
+            creator_hash: leaf.creator_hash,
+            nonce: leaf.nonce,
+            index,
+        },
+    )
+    .invoke_signed_with_remaining_accounts(&[], &proof)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn policy_metadata_commits_to_terms() {
+        let metadata = policy_metadata(&[0xab; 32]);
+        assert_eq!(
+            metadata.uri,
+            format!("{}{}", POLICY_URI_PREFIX, "ab".repeat(32))
+        );
+        assert!(!metadata.is_mutable);
+        assert!(metadata.creators.is_empty());
+        assert_ne!(
+            hash_metadata(&metadata).unwrap(),
+            hash_metadata(&policy_metadata(&[0xac; 32])).unwrap()
+        );
+    }
+}
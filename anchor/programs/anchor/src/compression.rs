@@ -0,0 +1,179 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::keccak;
+use anchor_lang::solana_program::program::{invoke, invoke_signed};
+
+use crate::ActionLogEntry;
+
+// SPL account compression and its noop log wrapper. The instructions are built by hand
+// because spl-account-compression is tied to an older anchor-lang.
+pub const ACCOUNT_COMPRESSION_ID: Pubkey = pubkey!("cmtDvXumGCrqC1Age74AVPhSRVXJMd8PJS91L8KbNCK");
+pub const NOOP_ID: Pubkey = pubkey!("noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV");
+
+// Seed of the PDA that owns the protocol's action log tree
+pub const LOG_AUTHORITY_SEED: &[u8] = b"action_log_authority";
+
+// Anchor instruction data for `name` on the compression program
+pub fn instruction_data(name: &str, args: &[u8]) -> Vec<u8> {
+    let mut data = hash(format!("global:{}", name).as_bytes()).to_bytes()[..8].to_vec();
+    data.extend_from_slice(args);
+    data
+}
+
+// Leaf stored in the tree for an entry: keccak of its serialized form, which indexers
+// read back from the noop instruction
+pub fn leaf_hash(entry: &ActionLogEntry) -> Result<[u8; 32]> {
+    Ok(keccak::hash(&entry.try_to_vec()?).to_bytes())
+}
+
+// Set up an empty tree in an account pre-allocated (zeroed, owned by the compression
+// program) by the admin, with `authority` as its only writer
+pub fn init_tree<'info>(
+    compression_program: &AccountInfo<'info>,
+    merkle_tree: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    noop_program: &AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+    max_depth: u32,
+    max_buffer_size: u32,
+) -> Result<()> {
+    let mut args = max_depth.to_le_bytes().to_vec();
+    args.extend_from_slice(&max_buffer_size.to_le_bytes());
+    invoke_signed(
+        &Instruction {
+            program_id: ACCOUNT_COMPRESSION_ID,
+            accounts: vec![
+                AccountMeta::new(merkle_tree.key(), false),
+                AccountMeta::new_readonly(authority.key(), true),
+                AccountMeta::new_readonly(NOOP_ID, false),
+            ],
+            data: instruction_data("init_empty_merkle_tree", &args),
+        },
+        &[
+            merkle_tree.clone(),
+            authority.clone(),
+            noop_program.clone(),
+            compression_program.clone(),
+        ],
+        signer_seeds,
+    )?;
+    Ok(())
+}
+
+// Log the full entry through the noop program and append its leaf to the tree
+pub fn append_entry<'info>(
+    compression_program: &AccountInfo<'info>,
+    merkle_tree: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    noop_program: &AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+    entry: &ActionLogEntry,
+) -> Result<[u8; 32]> {
+    invoke(
+        &Instruction {
+            program_id: NOOP_ID,
+            accounts: vec![],
+            data: entry.try_to_vec()?,
+        },
+        std::slice::from_ref(noop_program),
+    )?;
+
+    let leaf = leaf_hash(entry)?;
+    invoke_signed(
+        &Instruction {
+            program_id: ACCOUNT_COMPRESSION_ID,
+            accounts: vec![
+                AccountMeta::new(merkle_tree.key(), false),
+                AccountMeta::new_readonly(authority.key(), true),
+                AccountMeta::new_readonly(NOOP_ID, false),
+            ],
+            data: instruction_data("append", &leaf),
+        },
+        &[
+            merkle_tree.clone(),
+            authority.clone(),
+            noop_program.clone(),
+            compression_program.clone(),
+        ],
+        signer_seeds,
+    )?;
+    Ok(leaf)
+}
+
+// Fails unless `proof` (one account per node, leaf to root) places `leaf` at `index`
+// under `root`
+pub fn verify_leaf<'info>(
+    compression_program: &AccountInfo<'info>,
+    merkle_tree: &AccountInfo<'info>,
+    proof: &[AccountInfo<'info>],
+    root: [u8; 32],
+    leaf: [u8; 32],
+    index: u32,
+) -> Result<()> {
+    let mut args = Vec::with_capacity(68);
+    args.extend_from_slice(&root);
+    args.extend_from_slice(&leaf);
+    args.extend_from_slice(&index.to_le_bytes());
+
+    let mut metas = vec![AccountMeta::new_readonly(merkle_tree.key(), false)];
+    let mut infos = vec![merkle_tree.clone()];
+    for node in proof {
+        metas.push(AccountMeta::new_readonly(node.key(), false));
+        infos.push(node.clone());
+    }
+    infos.push(compression_program.clone());
+
+    invoke(
+        &Instruction {
+            program_id: ACCOUNT_COMPRESSION_ID,
+            accounts: metas,
+            data: instruction_data("verify_leaf", &args),
+        },
+        &infos,
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProtectionAction;
+
+    fn entry(score: u8) -> ActionLogEntry {
+        ActionLogEntry {
+            wallet: Pubkey::new_from_array([1; 32]),
+            timestamp: 1_700_000_000,
+            trigger_type: ProtectionAction::Swap,
+            token: Pubkey::new_from_array([2; 32]),
+            score,
+            leaf_index: 0,
+        }
+    }
+
+    #[test]
+    fn instruction_data_uses_anchor_discriminators() {
+        assert_eq!(
+            instruction_data("append", &[]),
+            [149, 120, 18, 222, 236, 225, 88, 203]
+        );
+        assert_eq!(
+            instruction_data("verify_leaf", &[9])[..],
+            [124, 220, 22, 223, 104, 10, 250, 224, 9]
+        );
+        assert_eq!(
+            instruction_data("init_empty_merkle_tree", &[])[..],
+            [191, 11, 119, 7, 180, 107, 220, 110]
+        );
+    }
+
+    #[test]
+    fn leaf_hash_commits_to_every_field() {
+        let base = leaf_hash(&entry(70)).unwrap();
+        assert_eq!(base, leaf_hash(&entry(70)).unwrap());
+        assert_ne!(base, leaf_hash(&entry(71)).unwrap());
+        let mut moved = entry(70);
+        moved.leaf_index = 1;
+        assert_ne!(base, leaf_hash(&moved).unwrap());
+    }
+}
@@ -0,0 +1,226 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar::instructions::load_instruction_at_checked;
+
+use crate::KeeperFeeCaps;
+
+// Compute budget instructions in the current transaction, read through the instructions
+// sysvar so keepers are reimbursed for what they actually bid rather than a flat rate
+pub const COMPUTE_BUDGET_ID: Pubkey = pubkey!("ComputeBudget111111111111111111111111111111");
+
+// Units each instruction gets when the transaction sets no limit, and the most any
+// transaction may request
+pub const DEFAULT_INSTRUCTION_UNITS: u32 = 200_000;
+pub const MAX_TRANSACTION_UNITS: u32 = 1_400_000;
+pub const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+const MICRO_LAMPORTS_PER_LAMPORT: u128 = 1_000_000;
+
+// ComputeBudgetInstruction tags
+const SET_COMPUTE_UNIT_LIMIT: u8 = 2;
+const SET_COMPUTE_UNIT_PRICE: u8 = 3;
+
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct ComputeBudgetRequest {
+    pub unit_limit: Option<u32>,
+    // Micro-lamports per compute unit
+    pub unit_price: Option<u64>,
+    // Instructions other than compute budget ones, which the default limit scales with
+    pub other_instructions: u32,
+}
+
+impl ComputeBudgetRequest {
+    pub fn add(&mut self, program_id: &Pubkey, data: &[u8]) {
+        if *program_id != COMPUTE_BUDGET_ID {
+            self.other_instructions += 1;
+            return;
+        }
+        match data {
+            [SET_COMPUTE_UNIT_LIMIT, units @ ..] if units.len() == 4 => {
+                self.unit_limit = Some(u32::from_le_bytes(units.try_into().unwrap()));
+            }
+            [SET_COMPUTE_UNIT_PRICE, price @ ..] if price.len() == 8 => {
+                self.unit_price = Some(u64::from_le_bytes(price.try_into().unwrap()));
+            }
+            _ => {}
+        }
+    }
+
+    pub fn compute_units(&self) -> u32 {
+        self.unit_limit
+            .unwrap_or(DEFAULT_INSTRUCTION_UNITS.saturating_mul(self.other_instructions))
+            .min(MAX_TRANSACTION_UNITS)
+    }
+}
+
+// Every instruction of the current transaction, folded into its compute budget
+pub fn read(instructions: &AccountInfo) -> Result<ComputeBudgetRequest> {
+    let count = {
+        let data = instructions.try_borrow_data()?;
+        u16::from_le_bytes([data[0], data[1]]) as usize
+    };
+    let mut request = ComputeBudgetRequest::default();
+    for index in 0..count {
+        let ix = load_instruction_at_checked(index, instructions)?;
+        request.add(&ix.program_id, &ix.data);
+    }
+    Ok(request)
+}
+
+// What a keeper's transaction cost, as recorded in KeeperReimbursed
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KeeperCost {
+    pub base_fee: u64,
+    pub cu_price: u64,
+    pub compute_units: u32,
+    pub priority_fee: u64,
+    // The bid price or units exceeded the caps and were cut to them
+    pub clamped: bool,
+}
+
+impl KeeperCost {
+    pub fn total(&self) -> u64 {
+        self.base_fee.saturating_add(self.priority_fee)
+    }
+}
+
+// Estimated real cost of the transaction: the signature fee plus the priority fee on
+// the requested units, with price and units held to the config caps
+pub fn estimate_cost(request: &ComputeBudgetRequest, caps: &KeeperFeeCaps) -> KeeperCost {
+    let bid_price = request.unit_price.unwrap_or(0);
+    let bid_units = request.compute_units();
+    let cu_price = bid_price.min(caps.max_cu_price);
+    let compute_units = bid_units.min(caps.max_compute_units);
+    let priority_fee =
+        (cu_price as u128 * compute_units as u128).div_ceil(MICRO_LAMPORTS_PER_LAMPORT) as u64;
+    KeeperCost {
+        base_fee: LAMPORTS_PER_SIGNATURE,
+        cu_price,
+        compute_units,
+        priority_fee,
+        clamped: cu_price < bid_price || compute_units < bid_units,
+    }
+}
+
+// Extra units trigger_protection spends appending to the compressed action log (the
+// compression and noop CPIs) over writing an ActionLog account
+pub const COMPRESSED_LOG_UNITS: u32 = 25_000;
+// Headroom added to an estimate, out of 10_000
+pub const SAFETY_MARGIN_BPS: u32 = 2_000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ProtectionUnitsEstimate {
+    pub estimated: u32,
+    pub safety_margin: u32,
+    pub recommended_limit: u32,
+}
+
+// Expected units of a protection action from its configured baseline
+pub fn estimate_protection_units(baseline: u32, compressed_log: bool) -> ProtectionUnitsEstimate {
+    let estimated = if compressed_log {
+        baseline.saturating_add(COMPRESSED_LOG_UNITS)
+    } else {
+        baseline
+    };
+    let safety_margin = (estimated as u64 * SAFETY_MARGIN_BPS as u64 / 10_000) as u32;
+    ProtectionUnitsEstimate {
+        estimated,
+        safety_margin,
+        recommended_limit: estimated
+            .saturating_add(safety_margin)
+            .min(MAX_TRANSACTION_UNITS),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn caps() -> KeeperFeeCaps {
+        KeeperFeeCaps {
+            max_cu_price: 100_000,
+            max_compute_units: 400_000,
+            epoch_budget_lamports: 1_000_000,
+        }
+    }
+
+    fn request(limit: Option<u32>, price: Option<u64>) -> ComputeBudgetRequest {
+        let mut request = ComputeBudgetRequest::default();
+        if let Some(limit) = limit {
+            request.add(
+                &COMPUTE_BUDGET_ID,
+                &[&[SET_COMPUTE_UNIT_LIMIT][..], &limit.to_le_bytes()].concat(),
+            );
+        }
+        if let Some(price) = price {
+            request.add(
+                &COMPUTE_BUDGET_ID,
+                &[&[SET_COMPUTE_UNIT_PRICE][..], &price.to_le_bytes()].concat(),
+            );
+        }
+        request.add(&crate::ID, &[0; 8]);
+        request
+    }
+
+    #[test]
+    fn reads_compute_budget_instructions() {
+        let parsed = request(Some(300_000), Some(50_000));
+        assert_eq!(parsed.unit_limit, Some(300_000));
+        assert_eq!(parsed.unit_price, Some(50_000));
+        assert_eq!(parsed.other_instructions, 1);
+
+        // Without a limit each other instruction gets the default
+        assert_eq!(
+            request(None, None).compute_units(),
+            DEFAULT_INSTRUCTION_UNITS
+        );
+        // Malformed budget instructions are ignored
+        let mut malformed = ComputeBudgetRequest::default();
+        malformed.add(&COMPUTE_BUDGET_ID, &[SET_COMPUTE_UNIT_PRICE, 1]);
+        assert_eq!(malformed.unit_price, None);
+    }
+
+    #[test]
+    fn cost_is_base_fee_plus_priority_on_requested_units() {
+        let cost = estimate_cost(&request(Some(300_000), Some(50_000)), &caps());
+        // 50_000 micro-lamports on 300_000 units is 15_000 lamports
+        assert_eq!(
+            (cost.base_fee, cost.priority_fee, cost.total()),
+            (5_000, 15_000, 20_000)
+        );
+        assert!(!cost.clamped);
+
+        let unpriced = estimate_cost(&request(None, None), &caps());
+        assert_eq!(unpriced.total(), LAMPORTS_PER_SIGNATURE);
+    }
+
+    #[test]
+    fn absurd_bids_are_clamped_to_the_caps() {
+        let cost = estimate_cost(
+            &request(Some(MAX_TRANSACTION_UNITS), Some(u64::MAX)),
+            &caps(),
+        );
+        assert_eq!((cost.cu_price, cost.compute_units), (100_000, 400_000));
+        assert_eq!(cost.priority_fee, 40_000);
+        assert!(cost.clamped);
+    }
+
+    #[test]
+    fn protection_estimates_add_corrections_and_margin() {
+        let plain = estimate_protection_units(100_000, false);
+        assert_eq!(
+            plain,
+            ProtectionUnitsEstimate {
+                estimated: 100_000,
+                safety_margin: 20_000,
+                recommended_limit: 120_000,
+            }
+        );
+
+        let compressed = estimate_protection_units(100_000, true);
+        assert_eq!(compressed.estimated, 100_000 + COMPRESSED_LOG_UNITS);
+        assert!(compressed.recommended_limit > plain.recommended_limit);
+
+        // Never more than a transaction may request
+        let huge = estimate_protection_units(MAX_TRANSACTION_UNITS, true);
+        assert_eq!(huge.recommended_limit, MAX_TRANSACTION_UNITS);
+    }
+}
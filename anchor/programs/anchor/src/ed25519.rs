@@ -0,0 +1,295 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::ed25519_program;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::sysvar::instructions::{
+    load_current_index_checked, load_instruction_at_checked,
+};
+
+use crate::wire;
+use crate::{ErrorCode, ScoreMessage};
+
+// Ed25519 signatures are checked by introspection: the transaction carries an ed25519
+// program instruction just before ours, which the runtime has already verified, and we
+// confirm it covers the expected signer and message.
+
+// The offsets record follows the signature count and a padding byte
+const OFFSETS_START: usize = 2;
+const OFFSETS_LEN: usize = 14;
+const PUBKEY_LEN: usize = 32;
+const SIGNATURE_LEN: usize = 64;
+// Instruction index meaning the ed25519 instruction's own data
+const CURRENT_INSTRUCTION: u16 = u16::MAX;
+
+// (signer, signature, message) of ed25519 instruction data holding exactly one
+// signature with its key and message inline; None for any other layout
+pub fn signed_message(data: &[u8]) -> Option<(Pubkey, &[u8], &[u8])> {
+    if data.len() < OFFSETS_START + OFFSETS_LEN || data[0] != 1 {
+        return None;
+    }
+    let field = |i: usize| {
+        let at = OFFSETS_START + 2 * i;
+        u16::from_le_bytes([data[at], data[at + 1]])
+    };
+    let (signature_offset, signature_ix) = (field(0) as usize, field(1));
+    let (pubkey_offset, pubkey_ix) = (field(2) as usize, field(3));
+    let (message_offset, message_len, message_ix) =
+        (field(4) as usize, field(5) as usize, field(6));
+    if [signature_ix, pubkey_ix, message_ix]
+        .iter()
+        .any(|ix| *ix != CURRENT_INSTRUCTION)
+    {
+        return None;
+    }
+
+    let signature = data.get(signature_offset..signature_offset + SIGNATURE_LEN)?;
+    let pubkey = data.get(pubkey_offset..pubkey_offset + PUBKEY_LEN)?;
+    let message = data.get(message_offset..message_offset + message_len)?;
+    Some((Pubkey::try_from(pubkey).ok()?, signature, message))
+}
+
+// Fail unless the instruction before the current one verifies `signer`'s signature
+// over `message`
+pub fn require_signed(instructions: &AccountInfo, signer: &Pubkey, message: &[u8]) -> Result<()> {
+    let index = load_current_index_checked(instructions)?;
+    require!(index > 0, ErrorCode::InvalidQuoteSignature);
+    let ix = load_instruction_at_checked(index as usize - 1, instructions)?;
+    require_keys_eq!(
+        ix.program_id,
+        ed25519_program::ID,
+        ErrorCode::InvalidQuoteSignature
+    );
+
+    let (verified_signer, _, verified_message) =
+        signed_message(&ix.data).ok_or(ErrorCode::InvalidQuoteSignature)?;
+    require!(
+        verified_signer == *signer && verified_message == message,
+        ErrorCode::InvalidQuoteSignature
+    );
+    Ok(())
+}
+
+// The instruction `back` places before the current one; None past the start of the
+// transaction
+pub fn instruction_before(instructions: &AccountInfo, back: usize) -> Result<Option<Instruction>> {
+    let index = load_current_index_checked(instructions)? as usize;
+    if back == 0 || back > index {
+        return Ok(None);
+    }
+    Ok(Some(load_instruction_at_checked(
+        index - back,
+        instructions,
+    )?))
+}
+
+// Check that `ix` is an ed25519 instruction verifying `oracle`'s `signature` over the
+// canonical encoding of `expected`. Each failure has its own error so oracle
+// operators can tell them apart.
+pub fn verify_score(
+    ix: Option<&Instruction>,
+    oracle: &Pubkey,
+    expected: &ScoreMessage,
+    signature: &[u8; 64],
+) -> Result<()> {
+    let (signer, verified_signature, payload) = ix
+        .filter(|ix| ix.program_id == ed25519_program::ID)
+        .and_then(|ix| signed_message(&ix.data))
+        .ok_or(ErrorCode::MissingEd25519Instruction)?;
+    require_keys_eq!(signer, *oracle, ErrorCode::WrongOracleKey);
+
+    let (program_id, signed) =
+        wire::decode_score_message(payload).ok_or(ErrorCode::MessageMismatch)?;
+    require_keys_eq!(
+        signed.wallet,
+        expected.wallet,
+        ErrorCode::ScoreWalletMismatch
+    );
+    require!(
+        program_id == crate::ID && signed == *expected && verified_signature == signature,
+        ErrorCode::MessageMismatch
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // Instruction data as the ed25519 program expects it, everything inline
+    pub(crate) fn instruction_data(signer: &Pubkey, message: &[u8]) -> Vec<u8> {
+        let pubkey_offset = OFFSETS_START + OFFSETS_LEN;
+        let signature_offset = pubkey_offset + PUBKEY_LEN;
+        let message_offset = signature_offset + SIGNATURE_LEN;
+        let mut data = vec![1, 0];
+        for field in [
+            signature_offset as u16,
+            CURRENT_INSTRUCTION,
+            pubkey_offset as u16,
+            CURRENT_INSTRUCTION,
+            message_offset as u16,
+            message.len() as u16,
+            CURRENT_INSTRUCTION,
+        ] {
+            data.extend_from_slice(&field.to_le_bytes());
+        }
+        data.extend_from_slice(signer.as_ref());
+        data.extend_from_slice(&[7; SIGNATURE_LEN]);
+        data.extend_from_slice(message);
+        data
+    }
+
+    #[test]
+    fn reads_the_signer_and_message() {
+        let signer = Pubkey::new_unique();
+        let data = instruction_data(&signer, b"quote");
+        assert_eq!(
+            signed_message(&data),
+            Some((signer, &[7; SIGNATURE_LEN][..], &b"quote"[..]))
+        );
+    }
+
+    #[test]
+    fn rejects_other_layouts() {
+        let signer = Pubkey::new_unique();
+        let data = instruction_data(&signer, b"quote");
+
+        // More than one signature
+        let mut multi = data.clone();
+        multi[0] = 2;
+        assert_eq!(signed_message(&multi), None);
+
+        // Message taken from another instruction
+        let mut elsewhere = data.clone();
+        elsewhere[OFFSETS_START + 12..OFFSETS_START + 14].copy_from_slice(&0u16.to_le_bytes());
+        assert_eq!(signed_message(&elsewhere), None);
+
+        // Truncated before the end of the message
+        assert_eq!(signed_message(&data[..data.len() - 1]), None);
+        assert_eq!(signed_message(&[1, 0]), None);
+    }
+
+    fn score() -> ScoreMessage {
+        ScoreMessage {
+            wallet: Pubkey::new_unique(),
+            score: 64,
+            category: wire::SCORE_CATEGORY_OVERALL,
+            token: None,
+            timestamp: 1_700_000_000,
+            nonce: 1_700_000_000,
+            chain_id: 1,
+        }
+    }
+
+    fn ed25519_ix(signer: &Pubkey, message: &[u8]) -> Instruction {
+        Instruction {
+            program_id: ed25519_program::ID,
+            accounts: vec![],
+            data: instruction_data(signer, message),
+        }
+    }
+
+    #[test]
+    fn accepts_the_oracle_signature_over_the_canonical_message() {
+        let (oracle, message) = (Pubkey::new_unique(), score());
+        let ix = ed25519_ix(&oracle, &wire::encode_score_message(&crate::ID, &message));
+        assert!(verify_score(Some(&ix), &oracle, &message, &[7; 64]).is_ok());
+    }
+
+    #[test]
+    fn missing_ed25519_instruction() {
+        let (oracle, message) = (Pubkey::new_unique(), score());
+        let payload = wire::encode_score_message(&crate::ID, &message);
+        // Nothing before the score update
+        assert_eq!(
+            verify_score(None, &oracle, &message, &[7; 64]).unwrap_err(),
+            ErrorCode::MissingEd25519Instruction.into()
+        );
+        // The right data under another program
+        let mut elsewhere = ed25519_ix(&oracle, &payload);
+        elsewhere.program_id = Pubkey::new_unique();
+        assert_eq!(
+            verify_score(Some(&elsewhere), &oracle, &message, &[7; 64]).unwrap_err(),
+            ErrorCode::MissingEd25519Instruction.into()
+        );
+    }
+
+    #[test]
+    fn wrong_oracle_key() {
+        let (oracle, message) = (Pubkey::new_unique(), score());
+        let ix = ed25519_ix(
+            &Pubkey::new_unique(),
+            &wire::encode_score_message(&crate::ID, &message),
+        );
+        assert_eq!(
+            verify_score(Some(&ix), &oracle, &message, &[7; 64]).unwrap_err(),
+            ErrorCode::WrongOracleKey.into()
+        );
+    }
+
+    #[test]
+    fn message_mismatch() {
+        let (oracle, message) = (Pubkey::new_unique(), score());
+        let submitted = ScoreMessage {
+            score: 10,
+            ..message.clone()
+        };
+        let ix = ed25519_ix(&oracle, &wire::encode_score_message(&crate::ID, &message));
+        // Signed score differs from the submitted one
+        assert_eq!(
+            verify_score(Some(&ix), &oracle, &submitted, &[7; 64]).unwrap_err(),
+            ErrorCode::MessageMismatch.into()
+        );
+        // Signature argument differs from the verified one
+        assert_eq!(
+            verify_score(Some(&ix), &oracle, &message, &[8; 64]).unwrap_err(),
+            ErrorCode::MessageMismatch.into()
+        );
+        // Signed for another program, or not in the wire format at all
+        let other_program = ed25519_ix(
+            &oracle,
+            &wire::encode_score_message(&Pubkey::new_unique(), &message),
+        );
+        assert_eq!(
+            verify_score(Some(&other_program), &oracle, &message, &[7; 64]).unwrap_err(),
+            ErrorCode::MessageMismatch.into()
+        );
+        let borsh = ed25519_ix(&oracle, &message.try_to_vec().unwrap());
+        assert_eq!(
+            verify_score(Some(&borsh), &oracle, &message, &[7; 64]).unwrap_err(),
+            ErrorCode::MessageMismatch.into()
+        );
+    }
+
+    #[test]
+    fn score_wallet_mismatch() {
+        let (oracle, message) = (Pubkey::new_unique(), score());
+        let other_wallet = ScoreMessage {
+            wallet: Pubkey::new_unique(),
+            ..message.clone()
+        };
+        let ix = ed25519_ix(
+            &oracle,
+            &wire::encode_score_message(&crate::ID, &other_wallet),
+        );
+        assert_eq!(
+            verify_score(Some(&ix), &oracle, &message, &[7; 64]).unwrap_err(),
+            ErrorCode::ScoreWalletMismatch.into()
+        );
+    }
+
+    // The frontend and oracle alerting key off these numbers; new errors are appended
+    // to ErrorCode so they never move
+    #[test]
+    fn oracle_error_codes_are_stable() {
+        let codes = [
+            (ErrorCode::StaleScore, 6066),
+            (ErrorCode::MissingEd25519Instruction, 6184),
+            (ErrorCode::WrongOracleKey, 6185),
+            (ErrorCode::MessageMismatch, 6186),
+            (ErrorCode::ReplayedScore, 6187),
+            (ErrorCode::ScoreWalletMismatch, 6188),
+        ];
+        for (error, code) in codes {
+            assert_eq!(u32::from(error), code);
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use anchor_lang::prelude::*;
+
+use crate::{ErrorCode, FeeStake};
+
+// Fixed-point scale for the rewards-per-share accumulator
+pub const ACC_PRECISION: u128 = 1_000_000_000_000;
+
+pub fn bps_of(amount: u64, bps: u16) -> u64 {
+    ((amount as u128) * (bps as u128) / 10_000) as u64
+}
+
+// Add `reward` spread over `total_staked` to the accumulator
+pub fn accrue(acc_reward_per_share: u128, reward: u64, total_staked: u64) -> Result<u128> {
+    let increment = (reward as u128)
+        .checked_mul(ACC_PRECISION)
+        .ok_or(ErrorCode::MathOverflow)?
+        / (total_staked as u128);
+    acc_reward_per_share
+        .checked_add(increment)
+        .ok_or_else(|| ErrorCode::MathOverflow.into())
+}
+
+// Rewards a position of `amount` would have earned since the accumulator started
+pub fn reward_debt(amount: u64, acc_reward_per_share: u128) -> Result<u128> {
+    (amount as u128)
+        .checked_mul(acc_reward_per_share)
+        .map(|scaled| scaled / ACC_PRECISION)
+        .ok_or_else(|| ErrorCode::MathOverflow.into())
+}
+
+// Move everything earned since the last settlement into `pending_rewards`
+pub fn settle(stake: &mut FeeStake, acc_reward_per_share: u128) -> Result<()> {
+    let accumulated = reward_debt(stake.amount, acc_reward_per_share)?;
+    let earned = u64::try_from(accumulated.saturating_sub(stake.reward_debt))
+        .map_err(|_| ErrorCode::MathOverflow)?;
+    stake.pending_rewards = stake
+        .pending_rewards
+        .checked_add(earned)
+        .ok_or(ErrorCode::MathOverflow)?;
+    stake.reward_debt = accumulated;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accrued_rewards_split_by_stake() {
+        let acc = accrue(0, 1_000, 4_000).unwrap();
+        assert_eq!(reward_debt(1_000, acc).unwrap(), 250);
+        assert_eq!(reward_debt(3_000, acc).unwrap(), 750);
+    }
+
+    fn stake(amount: u64, acc_reward_per_share: u128) -> FeeStake {
+        FeeStake {
+            owner: Pubkey::new_unique(),
+            amount,
+            reward_debt: reward_debt(amount, acc_reward_per_share).unwrap(),
+            pending_rewards: 0,
+            unstaking_amount: 0,
+            unstake_available_at: 0,
+            stake_start: 0,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn late_stakers_earn_only_later_rewards() {
+        let mut early = stake(1_000, 0);
+        let acc = accrue(0, 1_000, 1_000).unwrap();
+        let mut late = stake(1_000, acc);
+        let acc = accrue(acc, 1_000, 2_000).unwrap();
+
+        settle(&mut early, acc).unwrap();
+        settle(&mut late, acc).unwrap();
+        assert_eq!((early.pending_rewards, late.pending_rewards), (1_500, 500));
+
+        // Settling again without new fees pays nothing twice
+        settle(&mut early, acc).unwrap();
+        assert_eq!(early.pending_rewards, 1_500);
+    }
+
+    #[test]
+    fn large_scale_accrual_never_overpays() {
+        // A whole-supply stake and a one-token stake sharing a max-size fee
+        let total = u64::MAX / 2;
+        let acc = accrue(0, u64::MAX, total).unwrap();
+        let whale = reward_debt(total - 1, acc).unwrap();
+        let minnow = reward_debt(1, acc).unwrap();
+        assert!(whale + minnow <= u64::MAX as u128);
+        assert_eq!(minnow, 2);
+
+        // Dust below the precision is dropped rather than credited
+        assert_eq!(accrue(0, 1, u64::MAX).unwrap(), 0);
+    }
+
+    #[test]
+    fn accumulator_overflow_is_an_error() {
+        assert!(accrue(u128::MAX, 1, 1).is_err());
+        assert!(reward_debt(u64::MAX, u128::MAX / 2).is_err());
+
+        // Earnings that no longer fit a u64 are refused rather than truncated
+        let mut whale = stake(u64::MAX, 0);
+        assert!(settle(&mut whale, 2 * ACC_PRECISION).is_err());
+    }
+
+    #[test]
+    fn bps_of_rounds_down() {
+        assert_eq!(bps_of(999, 100), 9);
+        assert_eq!(bps_of(u64::MAX, 10_000), u64::MAX);
+    }
+}
@@ -1,7 +1,84 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, TokenAccount, Mint};
+use anchor_lang::solana_program::bpf_loader_upgradeable;
+use anchor_lang::solana_program::hash::hash;
+use anchor_lang::solana_program::instruction::{
+    get_stack_height, Instruction, TRANSACTION_LEVEL_STACK_HEIGHT,
+};
+use anchor_lang::solana_program::program::{invoke_signed, set_return_data};
+use anchor_lang::solana_program::sysvar::instructions::{
+    self as sysvar_instructions, load_current_index_checked, load_instruction_at_checked,
+};
+use anchor_lang::system_program;
+use anchor_lang::Discriminator;
 use anchor_spl::associated_token::AssociatedToken;
-use std::collections::BTreeMap;
+use anchor_spl::memo::Memo;
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface};
+
+// Indexing protocol: every wallet-scoped event is preceded by a log line of the form
+// `IRIS_INDEX wallet=<base58 pubkey>`. Subscribers can filter transaction logs on that
+// prefix to find a wallet's events without decoding every event payload; the event
+// itself follows on the next `Program data:` line.
+pub const INDEX_LOG_PREFIX: &str = "IRIS_INDEX";
+
+// Emit `$event` stamped with the instruction's event sequence (see `EventSeq`)
+macro_rules! emit_seq {
+    ($events:expr, $event:ident { $($field:tt)* }) => {{
+        emit!($event {
+            seq: $events.seq,
+            seq_index: $events.next_index(),
+            $($field)*
+        });
+    }};
+}
+
+macro_rules! emit_indexed {
+    ($events:expr, $wallet:expr, $event:ident { $($field:tt)* }) => {{
+        msg!("{} wallet={}", $crate::INDEX_LOG_PREFIX, $wallet);
+        emit_seq!($events, $event { $($field)* });
+    }};
+}
+
+// emit_seq! through a self-CPI (as emit_cpi! does) instead of the log, for events
+// downstream accounting can't afford to lose when a busy transaction's logs are
+// truncated. Indexers read them from the inner instruction. The instruction's accounts
+// need #[event_cpi]; the CPI costs compute, so informational events stay on emit!.
+macro_rules! emit_cpi_seq {
+    ($ctx:ident, $events:expr, $event:ident { $($field:tt)* }) => {{
+        let event = $event {
+            seq: $events.seq,
+            seq_index: $events.next_index(),
+            $($field)*
+        };
+        $crate::emit_event_cpi(&$ctx.accounts.event_authority, $ctx.bumps.event_authority, &event)?;
+    }};
+}
+
+macro_rules! emit_cpi_indexed {
+    ($ctx:ident, $events:expr, $wallet:expr, $event:ident { $($field:tt)* }) => {{
+        msg!("{} wallet={}", $crate::INDEX_LOG_PREFIX, $wallet);
+        emit_cpi_seq!($ctx, $events, $event { $($field)* });
+    }};
+}
+
+// Public so clients can find the policy tree's Bubblegum accounts and escrow
+pub mod cnft;
+// Public so clients can find the action log tree's programs and authority
+pub mod compression;
+mod compute_budget;
+mod ed25519;
+mod fee_share;
+mod logic;
+mod memo;
+mod pool;
+mod slot_hashes;
+// Public so clients can find the TVL feed's publisher and program
+pub mod switchboard;
+#[cfg(test)]
+mod test_utils;
+mod tokens;
+mod treasury;
+// Public so off-chain Rust clients can encode and check signed scores
+pub mod wire;
 
 declare_id!("CyU7VZwLetQ2sCGqhj7gBbS2rojWrobNGGbQHFchNWFM");
 
@@ -11,553 +88,14265 @@ pub mod iris_anchor {
 
     // Initialize a new user account with default preferences
     pub fn initialize_user(ctx: Context<InitializeUser>, preferences: RiskParams) -> Result<()> {
+        require!(
+            preferences.watchlist.len() <= MAX_WATCHLIST_TOKENS,
+            ErrorCode::WatchlistTooLong
+        );
         let user_account = &mut ctx.accounts.user_account;
+        user_account.version = UserAccount::CURRENT_VERSION;
         user_account.wallet = ctx.accounts.user.key();
         user_account.preferences = preferences;
         user_account.active_sub = false;
         user_account.score_history = Vec::new();
+        user_account.bump = ctx.bumps.user_account;
+        user_account.rent_payer = ctx.accounts.payer.key();
+        user_account.max_auto_swap_amount_bps = UserAccount::DEFAULT_MAX_AUTO_SWAP_AMOUNT_BPS;
+        Ok(())
+    }
+
+    // Cap automatic swaps at a share of the swapped token's balance
+    pub fn set_max_auto_swap_bps(
+        ctx: Context<SetMaxAutoSwapBps>,
+        max_auto_swap_amount_bps: u16,
+    ) -> Result<()> {
+        require!(
+            max_auto_swap_amount_bps > 0 && max_auto_swap_amount_bps <= 10_000,
+            ErrorCode::InvalidBps
+        );
+        ctx.accounts.user_account.max_auto_swap_amount_bps = max_auto_swap_amount_bps;
+        Ok(())
+    }
+
+    // Silence watchlist alerts for `duration` seconds; they resume on their own once
+    // the pause runs out
+    pub fn pause_watchlist_alerts(
+        ctx: Context<SetWatchlistAlertsPaused>,
+        duration: i64,
+    ) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        require!(duration > 0, ErrorCode::InvalidDuration);
+        let until = Clock::get()?
+            .unix_timestamp
+            .checked_add(duration)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        let user_account = &mut ctx.accounts.user_account;
+        user_account.watchlist_alerts_paused = true;
+        user_account.paused_until = until;
+        emit_indexed!(
+            events,
+            user_account.wallet,
+            WatchlistAlertsPaused {
+                wallet: user_account.wallet,
+                until,
+            }
+        );
+        Ok(())
+    }
+
+    pub fn resume_watchlist_alerts(ctx: Context<SetWatchlistAlertsPaused>) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let now = Clock::get()?.unix_timestamp;
+        resume_watchlist(&mut ctx.accounts.user_account, now, &mut events);
+        Ok(())
+    }
+
+    // Alert the user that a token on their watchlist needs attention. Skipped while
+    // alerts are paused; a pause that has run out is lifted here.
+    pub fn watchlist_alert(ctx: Context<WatchlistAlert>, token: Pubkey) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let clock = Clock::get()?;
+        let user_account = &mut ctx.accounts.user_account;
+        require!(
+            user_account.preferences.watchlist.contains(&token),
+            ErrorCode::TokenNotOnWatchlist
+        );
+        require_subscription_active(user_account, &ctx.accounts.config, clock.unix_timestamp)?;
+
+        if user_account.watchlist_alerts_paused {
+            if logic::watchlist_alerts_suppressed(
+                true,
+                user_account.paused_until,
+                clock.unix_timestamp,
+            ) {
+                return Ok(());
+            }
+            resume_watchlist(user_account, clock.unix_timestamp, &mut events);
+        }
+
+        emit_indexed!(
+            events,
+            user_account.wallet,
+            WatchlistAlertRaised {
+                wallet: user_account.wallet,
+                token,
+                timestamp: clock.unix_timestamp,
+            }
+        );
+        Ok(())
+    }
+
+    // Make `token` eligible for automatic protection, moving at most `max_amount` per
+    // action. Adding a covered token again updates its cap.
+    pub fn add_covered_token(
+        ctx: Context<AddCoveredToken>,
+        token: Pubkey,
+        max_amount: u64,
+    ) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        require!(max_amount > 0, ErrorCode::InvalidAmount);
+        let list = &mut ctx.accounts.covered_tokens;
+        if list.wallet == Pubkey::default() {
+            list.wallet = ctx.accounts.user.key();
+            list.bump = ctx.bumps.covered_tokens;
+        }
+
+        match list
+            .covered_tokens
+            .iter_mut()
+            .find(|covered| covered.mint == token)
+        {
+            Some(covered) => covered.max_amount = max_amount,
+            None => {
+                require!(
+                    list.covered_tokens.len() < MAX_COVERED_TOKENS,
+                    ErrorCode::CoveredTokenListFull
+                );
+                list.covered_tokens.push(CoveredToken {
+                    mint: token,
+                    max_amount,
+                });
+            }
+        }
+
+        emit_indexed!(
+            events,
+            list.wallet,
+            CoveredTokenAdded {
+                wallet: list.wallet,
+                token,
+                max_amount,
+            }
+        );
+        Ok(())
+    }
+
+    pub fn remove_covered_token(ctx: Context<RemoveCoveredToken>, token: Pubkey) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let list = &mut ctx.accounts.covered_tokens;
+        let index = list
+            .covered_tokens
+            .iter()
+            .position(|covered| covered.mint == token)
+            .ok_or(ErrorCode::TokenNotCovered)?;
+        list.covered_tokens.remove(index);
+
+        emit_indexed!(
+            events,
+            list.wallet,
+            CoveredTokenRemoved {
+                wallet: list.wallet,
+                token,
+            }
+        );
+        Ok(())
+    }
+
+    // Move a legacy keypair-based user account, in any layout version, into the
+    // wallet's PDA. The legacy account is closed to the wallet, so this runs once.
+    pub fn migrate_legacy_user(ctx: Context<MigrateLegacyUser>, wallet: Pubkey) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        require!(
+            wallet == ctx.accounts.user.key(),
+            ErrorCode::NotAccountOwner
+        );
+
+        let legacy_info = ctx.accounts.legacy_user_account.to_account_info();
+        require!(
+            legacy_info.owner == &crate::ID,
+            ErrorCode::InvalidAccountLayout
+        );
+        let legacy = {
+            let upgraded = logic::upgraded_layout(
+                &legacy_info.try_borrow_data()?,
+                &UserAccount::DISCRIMINATOR,
+                8 + UserAccount::INIT_SPACE,
+                UserAccount::CURRENT_VERSION,
+                UserAccount::LAYOUT_GROWTH,
+            )?;
+            UserAccount::try_deserialize(&mut upgraded.as_slice())?
+        };
+        require!(legacy.wallet == wallet, ErrorCode::NotAccountOwner);
+
+        // Only the most recent scores fit the history buffer
+        let skip = legacy.score_history.len().saturating_sub(MAX_SCORE_HISTORY);
+        let user_account = &mut ctx.accounts.user_account;
+        user_account.version = UserAccount::CURRENT_VERSION;
+        user_account.wallet = legacy.wallet;
+        user_account.preferences = legacy.preferences;
+        user_account.active_sub = legacy.active_sub;
+        user_account.subscription_expiry = legacy.subscription_expiry;
+        user_account.score_history = legacy.score_history[skip..].to_vec();
+        user_account.pending_score = legacy.pending_score;
+        user_account.subscription_started_at = legacy.subscription_started_at;
+        user_account.plan_price = legacy.plan_price;
+        user_account.rebate_claimed = legacy.rebate_claimed;
+        user_account.smoothed_score = legacy.smoothed_score;
+        user_account.linked_nfts = legacy.linked_nfts;
+        user_account.linked_claims = legacy.linked_claims;
+        user_account.bump = ctx.bumps.user_account;
+        user_account.rent_payer = ctx.accounts.user.key();
+        user_account.last_action_log = legacy.last_action_log;
+
+        // Close the legacy account to the wallet
+        let lamports = legacy_info.lamports();
+        legacy_info.sub_lamports(lamports)?;
+        ctx.accounts.user.add_lamports(lamports)?;
+        legacy_info.assign(&system_program::ID);
+        legacy_info.realloc(0, false)?;
+
+        emit_indexed!(
+            events,
+            wallet,
+            LegacyAccountMigrated {
+                wallet,
+                legacy_account: legacy_info.key(),
+                user_account: user_account.key(),
+            }
+        );
+
+        Ok(())
+    }
+
+    // Upgrade a UserAccount from an older layout to the current one. Permissionless; the
+    // payer covers the extra rent.
+    pub fn migrate_user_account_layout(ctx: Context<MigrateAccountLayout>) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let from_version = migrate_layout(
+            ctx.accounts,
+            &UserAccount::DISCRIMINATOR,
+            8 + UserAccount::INIT_SPACE,
+            UserAccount::CURRENT_VERSION,
+            UserAccount::LAYOUT_GROWTH,
+        )?;
+        emit_seq!(
+            events,
+            AccountMigrated {
+                kind: AccountKind::UserAccount,
+                account: ctx.accounts.account.key(),
+                from_version,
+                to_version: UserAccount::CURRENT_VERSION,
+            }
+        );
+        Ok(())
+    }
+
+    pub fn migrate_insurance_nft_layout(ctx: Context<MigrateAccountLayout>) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let from_version = migrate_layout(
+            ctx.accounts,
+            &InsuranceNFT::DISCRIMINATOR,
+            8 + InsuranceNFT::INIT_SPACE,
+            InsuranceNFT::CURRENT_VERSION,
+            InsuranceNFT::LAYOUT_GROWTH,
+        )?;
+        emit_seq!(
+            events,
+            AccountMigrated {
+                kind: AccountKind::InsuranceNft,
+                account: ctx.accounts.account.key(),
+                from_version,
+                to_version: InsuranceNFT::CURRENT_VERSION,
+            }
+        );
+        Ok(())
+    }
+
+    pub fn migrate_claim_layout(ctx: Context<MigrateAccountLayout>) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let from_version = migrate_layout(
+            ctx.accounts,
+            &Claim::DISCRIMINATOR,
+            8 + Claim::INIT_SPACE,
+            Claim::CURRENT_VERSION,
+            Claim::LAYOUT_GROWTH,
+        )?;
+        emit_seq!(
+            events,
+            AccountMigrated {
+                kind: AccountKind::Claim,
+                account: ctx.accounts.account.key(),
+                from_version,
+                to_version: Claim::CURRENT_VERSION,
+            }
+        );
+        Ok(())
+    }
+
+    // Close a user account once nothing depends on it, refunding its rent to whoever
+    // funded it (and the claim history PDA's, if passed, to the wallet). Each blocking condition fails with
+    // its own error so the client knows what to resolve first.
+    pub fn close_user_account(ctx: Context<CloseUserAccount>) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let clock = Clock::get()?;
+        let user_account = &ctx.accounts.user_account;
+
+        require!(
+            !user_account.active_sub
+                || logic::is_expired(clock.unix_timestamp, user_account.subscription_expiry),
+            ErrorCode::SubscriptionStillActive
+        );
+
+        // Name the blocking account in the logs so the client knows what to clean up
+        if let Some(nft) = user_account.linked_nfts.first() {
+            emit_seq!(
+                events,
+                CloseBlockedByActiveNft {
+                    wallet: user_account.wallet,
+                    nft: *nft,
+                    linked_nfts: user_account.linked_nfts.len() as u8,
+                }
+            );
+            return Err(ErrorCode::ActiveNftsLinked.into());
+        }
+        if let Some(claim) = user_account.linked_claims.first() {
+            emit_seq!(
+                events,
+                CloseBlockedByOpenClaim {
+                    wallet: user_account.wallet,
+                    claim: *claim,
+                    linked_claims: user_account.linked_claims.len() as u8,
+                }
+            );
+            return Err(ErrorCode::OpenClaimsLinked.into());
+        }
+
+        emit_seq!(
+            events,
+            UserAccountClosed {
+                wallet: user_account.wallet,
+                rent_recipient: ctx.accounts.rent_recipient.key(),
+                rent_refunded: user_account.to_account_info().lamports(),
+                claim_history_refunded: ctx
+                    .accounts
+                    .claim_history
+                    .as_ref()
+                    .map_or(0, |history| history.to_account_info().lamports()),
+            }
+        );
         Ok(())
     }
 
     // Subscribe to a protection plan
+    #[allow(clippy::too_many_arguments)]
     pub fn subscribe(
         ctx: Context<Subscribe>,
         plan_id: u8,
         duration: u64,
         payment_amount: u64,
+        promo_code: Option<[u8; 16]>,
+        burn_for_waiver: Option<u64>,
+        reference: Option<[u8; 16]>,
+        quote: Option<PriceQuote>,
     ) -> Result<()> {
+        require_direct_call(&ctx.accounts.config, ctx.accounts.instructions.as_ref())?;
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        ctx.accounts
+            .config
+            .require_not_paused(ProtocolConfig::PAUSE_SUBSCRIBE)?;
         let user_account = &mut ctx.accounts.user_account;
         let clock = Clock::get()?;
-        
+
         // Verify payment matches the plan requirements
         let plan = SubscriptionPlan::get_plan(plan_id)?;
-        require!(payment_amount >= plan.price, ErrorCode::InsufficientPayment);
-        
+        let mut price = plan.price;
+
+        // A signed quote holds its price until its slot bound, whatever the plan costs
+        // by then; an expired one only lets a lower current price through
+        if let Some(quote) = quote {
+            require!(quote.plan_id == plan_id, ErrorCode::InvalidPriceQuote);
+            let config = &ctx.accounts.config;
+            require!(
+                config.quote_authority != Pubkey::default(),
+                ErrorCode::InvalidQuoteSignature
+            );
+            let message = PriceQuoteMessage {
+                quote: quote.clone(),
+                payer: ctx.accounts.user.key(),
+                chain_id: config.protocol_chain_id,
+            };
+            let instructions = ctx
+                .accounts
+                .instructions
+                .as_ref()
+                .ok_or(ErrorCode::InvalidQuoteSignature)?;
+            ed25519::require_signed(
+                instructions,
+                &config.quote_authority,
+                &message.try_to_vec()?,
+            )?;
+            price =
+                logic::quoted_plan_price(price, quote.price, clock.slot <= quote.valid_until_slot)
+                    .ok_or(ErrorCode::PriceQuoteExpired)?;
+        }
+
+        // Apply a promo code discount if one was supplied
+        match (promo_code, ctx.accounts.promo_code_account.as_mut()) {
+            (Some(code), Some(promo)) => {
+                price = promo.redeem(&code, clock.unix_timestamp, price)?;
+
+                emit_indexed!(
+                    events,
+                    user_account.wallet,
+                    PromoCodeUsed {
+                        code_hash: hash(&code).to_bytes(),
+                        wallet: user_account.wallet,
+                        discount_bps: promo.discount_bps,
+                    }
+                );
+            }
+            (None, None) => {}
+            _ => return Err(ErrorCode::PromoCodeMismatch.into()),
+        }
+
+        if let Some(burn) = burn_for_waiver {
+            price -= burn_for_fee_waiver(
+                &ctx.accounts.config,
+                ctx.accounts.governance_mint.as_ref(),
+                ctx.accounts.governance_token_account.as_ref(),
+                &ctx.accounts.user,
+                &ctx.accounts.token_program,
+                price,
+                burn,
+                &mut events,
+            )?;
+        }
+
+        require!(payment_amount >= price, ErrorCode::InsufficientPayment);
+
         // Update user subscription status
         user_account.active_sub = true;
         user_account.subscription_expiry = clock.unix_timestamp + duration as i64;
-        
+        user_account.subscription_started_at = clock.unix_timestamp;
+        user_account.plan_price = price;
+        user_account.rebate_claimed = false;
+        user_account.subscription_reference = reference;
+        ctx.accounts.config.total_subscriptions += 1;
+
         // Transfer payment to IRIS treasury
-        token::transfer(
+        tokens::transfer_in(
+            &ctx.accounts.token_program,
+            &ctx.accounts.payment_account,
+            &mut ctx.accounts.treasury_account,
+            &ctx.accounts.payment_mint,
+            ctx.accounts.user.to_account_info(),
+            payment_amount,
+        )?;
+        memo::attach(
+            ctx.accounts.memo_program.as_ref(),
+            memo::SUBSCRIBE,
+            reference,
+        )?;
+
+        treasury::credit(
+            &mut ctx.accounts.treasury,
+            &mut ctx.accounts.config,
+            &mut events,
+            user_account.wallet,
+            payment_amount,
+            LedgerInstruction::Subscribe,
+        )?;
+
+        emit_cpi_indexed!(
+            ctx,
+            events,
+            user_account.wallet,
+            SubscriptionEvent {
+                wallet: user_account.wallet,
+                plan_id,
+                expiry: user_account.subscription_expiry,
+                timestamp: clock.unix_timestamp,
+            }
+        );
+
+        let payer = ctx
+            .accounts
+            .system_program
+            .as_ref()
+            .map(|program| (&ctx.accounts.user, program));
+        record_activity(
+            ctx.accounts.activity.as_ref(),
+            payer,
+            user_account.wallet,
+            ActivityKind::Subscribed,
+            payment_amount,
+            ctx.accounts.payment_mint.key(),
+            clock.unix_timestamp,
+        )?;
+
+        Ok(())
+    }
+
+    // Refund part of the plan price to a subscriber whose period ended without
+    // any claims or risk threshold breaches
+    pub fn claim_no_claim_rebate(
+        ctx: Context<ClaimNoClaimRebate>,
+        reference: Option<[u8; 16]>,
+    ) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let clock = Clock::get()?;
+        let user_account = &mut ctx.accounts.user_account;
+
+        require!(
+            logic::is_expired(clock.unix_timestamp, user_account.subscription_expiry),
+            ErrorCode::SubscriptionStillActive
+        );
+        require!(
+            !user_account.rebate_claimed,
+            ErrorCode::RebateAlreadyClaimed
+        );
+
+        let started_at = user_account.subscription_started_at;
+        let expiry = user_account.subscription_expiry;
+        let threshold = user_account.preferences.risk_threshold;
+        require!(
+            !user_account
+                .score_history
+                .iter()
+                .any(|s| s.timestamp >= started_at
+                    && s.timestamp <= expiry
+                    && s.value >= threshold),
+            ErrorCode::RiskThresholdCrossed
+        );
+
+        // No history account means the wallet has never filed a claim
+        let history = &ctx.accounts.claim_history;
+        if !history.data_is_empty() {
+            let history = ClaimHistory::try_deserialize(&mut &history.try_borrow_data()?[..])?;
+            require!(
+                history.last_claim_at < started_at,
+                ErrorCode::ClaimsFiledInPeriod
+            );
+        }
+
+        let rebate_amount =
+            fee_share::bps_of(user_account.plan_price, ctx.accounts.config.rebate_bps);
+        let wallet = user_account.wallet;
+        require!(rebate_amount > 0, ErrorCode::NothingToClaim);
+        user_account.rebate_claimed = true;
+
+        treasury::debit(
+            &mut ctx.accounts.treasury,
+            &ctx.accounts.treasury_vault,
+            &ctx.accounts.treasury_mint,
+            ctx.accounts.user_token_account.to_account_info(),
+            &ctx.accounts.token_program,
+            &mut ctx.accounts.config,
+            &mut events,
+            rebate_amount,
+            LedgerInstruction::ClaimNoClaimRebate,
+            reference,
+        )?;
+        memo::attach(
+            ctx.accounts.memo_program.as_ref(),
+            memo::TREASURY_WITHDRAWAL,
+            reference,
+        )?;
+
+        emit_indexed!(
+            events,
+            wallet,
+            NoClaimRebatePaid {
+                wallet,
+                rebate_amount,
+            }
+        );
+
+        Ok(())
+    }
+
+    // Add SOL to the maintenance fund that pays keepers for housekeeping work
+    pub fn fund_maintenance(ctx: Context<FundMaintenance>, amount: u64) -> Result<()> {
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        system_program::transfer(
             CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                token::Transfer {
-                    from: ctx.accounts.payment_account.to_account_info(),
-                    to: ctx.accounts.treasury_account.to_account_info(),
-                    authority: ctx.accounts.user.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.funder.to_account_info(),
+                    to: ctx.accounts.config.to_account_info(),
                 },
             ),
-            payment_amount,
+            amount,
         )?;
-        
-        emit!(SubscriptionEvent {
-            wallet: user_account.wallet,
-            plan_id,
-            expiry: user_account.subscription_expiry,
-            timestamp: clock.unix_timestamp,
-        });
-        
+        ctx.accounts.config.maintenance_fund_lamports += amount;
+        Ok(())
+    }
+
+    // Deactivate lapsed subscriptions passed as remaining accounts. Accounts that are
+    // still valid or already inactive are left untouched. A keeper that expires any is
+    // reimbursed its transaction cost from the maintenance fund while it lasts. An
+    // automation thread may drive this; the next useful run time is returned.
+    // Remaining accounts: UserAccount per item (stride EXPIRY_BATCH_STRIDE).
+    pub fn batch_expire_subscriptions<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BatchExpireSubscriptions<'info>>,
+    ) -> Result<()> {
+        require_automation_caller(&ctx.accounts.config, ctx.accounts.instructions.as_ref())?;
+        require!(
+            ctx.remaining_accounts.len() <= ProtocolConfig::MAX_EXPIRY_BATCH,
+            ErrorCode::InvalidRemainingAccounts
+        );
+        let now = Clock::get()?.unix_timestamp;
+        if ctx.remaining_accounts.is_empty() {
+            schedule_next_run(now + ProtocolConfig::CRANK_IDLE_INTERVAL_SECS);
+            return Ok(());
+        }
+        batch_items(&ctx, ProtocolConfig::EXPIRY_BATCH_STRIDE)?;
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+
+        let grace_secs = ctx.accounts.config.subscription_grace_secs;
+        let mut next_run = now + ProtocolConfig::CRANK_IDLE_INTERVAL_SECS;
+        let mut count: u32 = 0;
+        for info in ctx.remaining_accounts.iter() {
+            let mut user_account = Account::<UserAccount>::try_from(info)?;
+            require!(
+                user_account.version_supported(),
+                ErrorCode::UnsupportedAccountVersion
+            );
+            // Subscriptions stay active through their grace period
+            if !user_account.active_sub {
+                continue;
+            }
+            if user_account.subscription_state(now, grace_secs) != logic::CoverageState::Expired {
+                next_run =
+                    next_run.min(user_account.subscription_expiry.saturating_add(grace_secs));
+                continue;
+            }
+
+            user_account.active_sub = false;
+            user_account.exit(&crate::ID)?;
+            count += 1;
+
+            let wallet = user_account.wallet;
+            emit_indexed!(events, wallet, SubscriptionAutoExpired { wallet });
+        }
+
+        let keeper_reward = reimburse_keeper(
+            &mut ctx.accounts.config,
+            &mut ctx.accounts.keeper_stats,
+            ctx.bumps.keeper_stats,
+            &ctx.accounts.keeper,
+            ctx.accounts.instructions.as_ref(),
+            count,
+            &mut events,
+        )?;
+
+        emit_seq!(
+            events,
+            BatchExpirationCompleted {
+                count,
+                keeper_reward,
+            }
+        );
+        schedule_next_run(next_run);
+        Ok(())
+    }
+
+    // Deactivate one lapsed subscription so the stored flag matches its expiry. Anyone
+    // may crank this; the keeper is reimbursed as for a batched expiry.
+    pub fn expire_subscription(ctx: Context<ExpireSubscription>) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let now = Clock::get()?.unix_timestamp;
+        let user_account = &mut ctx.accounts.user_account;
+        require!(user_account.active_sub, ErrorCode::NoActiveSubscription);
+        require!(
+            user_account.subscription_state(now, ctx.accounts.config.subscription_grace_secs)
+                == logic::CoverageState::Expired,
+            ErrorCode::SubscriptionStillActive
+        );
+
+        user_account.active_sub = false;
+        let wallet = user_account.wallet;
+        let expiry = user_account.subscription_expiry;
+        let keeper_reward = reimburse_keeper(
+            &mut ctx.accounts.config,
+            &mut ctx.accounts.keeper_stats,
+            ctx.bumps.keeper_stats,
+            &ctx.accounts.keeper,
+            ctx.accounts.instructions.as_ref(),
+            1,
+            &mut events,
+        )?;
+
+        emit_indexed!(
+            events,
+            wallet,
+            SubscriptionLapsed {
+                wallet,
+                expiry,
+                keeper: ctx.accounts.keeper.key(),
+                keeper_reward,
+            }
+        );
         Ok(())
     }
 
-    // Update risk score from off-chain oracle
+    // Submit a risk score from the off-chain oracle. It is held for the dispute window,
+    // then applied by apply_pending_score or the wallet's next submission. A submission
+    // arriving while it is still held, or disputed, replaces it.
     pub fn update_risk_score(
         ctx: Context<UpdateRiskScore>,
         score: u8,
         timestamp: i64,
+        chain_id: u8,
         signature: [u8; 64],
     ) -> Result<()> {
-        // Verify the signature from the IRIS risk oracle
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        // Verify the signature from the IRIS risk oracle over a wallet-level score; the
+        // timestamp doubles as the nonce (see store_score)
         let message = ScoreMessage {
             wallet: ctx.accounts.user_account.wallet,
             score,
+            category: wire::SCORE_CATEGORY_OVERALL,
+            token: None,
             timestamp,
+            nonce: timestamp as u64,
+            chain_id,
         };
-        
-        verify_iris_signature(&message, &signature)?;
-        
-        // Store the score
+
+        ctx.accounts
+            .config
+            .require_not_paused(ProtocolConfig::PAUSE_SCORES)?;
+        verify_iris_signature(
+            ctx.accounts.instructions.as_ref(),
+            &ctx.accounts.oracle.key(),
+            &message,
+            &signature,
+            1,
+        )?;
+        require!(
+            message.chain_id == ctx.accounts.config.protocol_chain_id,
+            ErrorCode::ChainIdMismatch
+        );
+
+        // Only one oracle's scores are accepted at a time: the primary while it is live,
+        // otherwise the first live fallback. A submission counts as the signer's heartbeat.
+        let now = Clock::get()?.unix_timestamp;
+        let registry = &mut ctx.accounts.oracle_registry;
+        let oracle = ctx.accounts.oracle.key();
+        require!(
+            registry.record_heartbeat(&oracle, now),
+            ErrorCode::UnknownOracle
+        );
+        let active = registry.live_oracle(now).ok_or(ErrorCode::NoLiveOracle)?;
+        require!(oracle == active, ErrorCode::InactiveOracle);
+        update_active_oracle(registry, active, &mut events);
+
+        let max_delta = ctx.accounts.config.max_score_delta_per_update;
         let user_account = &mut ctx.accounts.user_account;
-        user_account.score_history.push(Score {
+        let breach = settle_held_score(
+            user_account,
+            ctx.accounts.score_archive.as_mut(),
+            ctx.accounts.covered_tokens.as_deref(),
+            max_delta,
+            now,
+            &mut events,
+        )?;
+        if let Some(score) = breach {
+            let wallet = user_account.wallet;
+            record_activity(
+                ctx.accounts.activity.as_ref(),
+                None,
+                wallet,
+                ActivityKind::ScoreBreach,
+                score as u64,
+                Pubkey::default(),
+                now,
+            )?;
+        }
+
+        // The score is held through the dispute window; protection triggers run when it
+        // is applied
+        logic::check_score_timestamp(
+            timestamp,
+            user_account.score_history.last().map(|last| last.timestamp),
+        )?;
+        user_account.held_score = Some(HeldScore {
             value: score,
+            oracle,
             timestamp,
+            submitted_at: now,
+            disputed: false,
+            accepted: false,
         });
-        
-        // Check if protection triggers are needed
-        check_protection_triggers(user_account)?;
-        
+        emit_indexed!(
+            events,
+            user_account.wallet,
+            ScorePendingApplication {
+                wallet: user_account.wallet,
+                oracle,
+                value: score,
+                submitted_at: now,
+                applies_at: now + ProtocolConfig::SCORE_DISPUTE_WINDOW_SECS,
+            }
+        );
+
+        if let Some(insurance_nft) = &ctx.accounts.insurance_nft {
+            let alert_days = ctx.accounts.config.coverage_gap_alert_days;
+            check_coverage_gap(
+                &ctx.accounts.user_account,
+                insurance_nft,
+                alert_days,
+                &mut events,
+            );
+        }
+
         Ok(())
     }
 
-    // Mint an insurance NFT
-    pub fn mint_insurance_nft(
-        ctx: Context<MintInsuranceNft>,
-        tier: u8,
-        payout_cap: u64,
-        duration: u64,
+    // Multi-oracle path: several registered oracles report on the same wallet in one
+    // transaction, signing as remaining accounts in the order of `scores`. If any two
+    // reports differ by more than oracle_disagreement_threshold nothing is stored, so a
+    // single compromised oracle can't move the score. Otherwise the median is applied.
+    // Passing the wallet's next aggregation report PDA records who reported what.
+    pub fn update_risk_score_consensus<'info>(
+        ctx: Context<'_, '_, 'info, 'info, UpdateRiskScoreConsensus<'info>>,
+        scores: Vec<OracleScore>,
+        timestamp: i64,
+        chain_id: u8,
     ) -> Result<()> {
-        let clock = Clock::get()?;
-        let user_account = &ctx.accounts.user_account;
-        
-        // Verify user has active subscription
-        require!(user_account.active_sub, ErrorCode::NoActiveSubscription);
-        require!(clock.unix_timestamp < user_account.subscription_expiry, ErrorCode::SubscriptionExpired);
-        
-        // Mint NFT
-        let cpi_ctx = CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            token::MintTo {
-                mint: ctx.accounts.mint.to_account_info(),
-                to: ctx.accounts.insurance_nft_account.to_account_info(),
-                authority: ctx.accounts.iris_authority.to_account_info(),
-            },
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let config = &ctx.accounts.config;
+        config.require_not_paused(ProtocolConfig::PAUSE_SCORES)?;
+        require!(
+            chain_id == config.protocol_chain_id,
+            ErrorCode::ChainIdMismatch
         );
-        token::mint_to(cpi_ctx, 1)?;
-        
-        // Store NFT metadata
-        let insurance_nft = &mut ctx.accounts.insurance_nft;
-        insurance_nft.tier = tier;
-        insurance_nft.expiry = clock.unix_timestamp + duration as i64;
-        insurance_nft.payout_cap = payout_cap;
-        insurance_nft.token_mint = ctx.accounts.mint.key();
-        insurance_nft.owner = user_account.wallet;
-        
-        emit!(InsuranceNftMinted {
-            wallet: user_account.wallet,
-            mint: ctx.accounts.mint.key(),
-            tier,
-            expiry: insurance_nft.expiry,
-            payout_cap: payout_cap,
-        });
-        
+        require!(
+            scores.len() >= 2
+                && scores.len() <= MAX_CONSENSUS_ORACLES
+                && scores.len() == ctx.remaining_accounts.len(),
+            ErrorCode::InvalidRemainingAccounts
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        let wallet = ctx.accounts.user_account.wallet;
+        let registry = &mut ctx.accounts.oracle_registry;
+        let mut reports: Vec<(Pubkey, u8)> = Vec::with_capacity(scores.len());
+        // Each report's ed25519 instruction precedes this one, in the order of `scores`
+        for (i, (report, oracle)) in scores.iter().zip(ctx.remaining_accounts.iter()).enumerate() {
+            require!(oracle.is_signer, ErrorCode::InvalidOracle);
+            require!(
+                !reports.iter().any(|(seen, _)| seen == oracle.key),
+                ErrorCode::InvalidOracle
+            );
+            require!(
+                registry.record_heartbeat(oracle.key, now),
+                ErrorCode::UnknownOracle
+            );
+
+            let message = ScoreMessage {
+                wallet,
+                score: report.score,
+                category: wire::SCORE_CATEGORY_OVERALL,
+                token: None,
+                timestamp,
+                nonce: timestamp as u64,
+                chain_id,
+            };
+            verify_iris_signature(
+                ctx.accounts.instructions.as_ref(),
+                oracle.key,
+                &message,
+                &report.signature,
+                scores.len() - i,
+            )?;
+            reports.push((*oracle.key, report.score));
+        }
+
+        if let Some((a, b)) =
+            logic::find_oracle_disagreement(&reports, config.oracle_disagreement_threshold)
+        {
+            let (oracle_a, score_a) = reports[a];
+            let (oracle_b, score_b) = reports[b];
+            emit_indexed!(
+                events,
+                wallet,
+                OracleDisagreementDetected {
+                    oracle_a,
+                    score_a,
+                    oracle_b,
+                    score_b,
+                    delta: score_a.abs_diff(score_b),
+                }
+            );
+            return Err(ErrorCode::OracleDisagreement.into());
+        }
+
+        let values: Vec<u8> = reports.iter().map(|(_, score)| *score).collect();
+        let score = logic::consensus_score(&values);
+
+        if let Some(report_account) = ctx.accounts.aggregation_report.as_ref() {
+            let (Some(archive), Some(payer), Some(system_program)) = (
+                ctx.accounts.score_archive.as_mut(),
+                ctx.accounts.payer.as_ref(),
+                ctx.accounts.system_program.as_ref(),
+            ) else {
+                return err!(ErrorCode::MissingReportAccounts);
+            };
+            let registered_oracles = 1 + registry.fallback_oracles.len() as u8;
+            write_aggregation_report(
+                report_account,
+                archive,
+                payer,
+                system_program,
+                &reports,
+                registered_oracles,
+                score,
+                now,
+            )?;
+        }
+
+        let max_delta = config.max_score_delta_per_update;
+        let user_account = &mut ctx.accounts.user_account;
+        // Consensus scores apply at once, after any held score that is due
+        let covered = ctx.accounts.covered_tokens.as_deref();
+        let held_breach = settle_held_score(
+            user_account,
+            ctx.accounts.score_archive.as_mut(),
+            covered,
+            max_delta,
+            now,
+            &mut events,
+        )?;
+        store_score(
+            user_account,
+            ctx.accounts.score_archive.as_mut(),
+            score,
+            timestamp,
+            max_delta,
+            &mut events,
+        )?;
+        let breach = check_protection_triggers(user_account, covered, &mut events)?;
+        for score in [held_breach, breach].into_iter().flatten() {
+            record_activity(
+                ctx.accounts.activity.as_ref(),
+                None,
+                wallet,
+                ActivityKind::ScoreBreach,
+                score as u64,
+                Pubkey::default(),
+                now,
+            )?;
+        }
+
         Ok(())
     }
 
-    // Trigger a protection action (swap, freeze, etc.)
-    pub fn trigger_protection(
-        ctx: Context<TriggerProtection>,
-        action_type: ProtectionAction,
-        token: Pubkey,
-        amount: u64,
+    // Apply the wallet's held score once its dispute window has passed, or once
+    // governance has rejected a dispute against it. Anyone may crank this.
+    pub fn apply_pending_score(ctx: Context<ApplyPendingScore>) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        ctx.accounts
+            .config
+            .require_not_paused(ProtocolConfig::PAUSE_SCORES)?;
+        let now = Clock::get()?.unix_timestamp;
+        let held = ctx
+            .accounts
+            .user_account
+            .held_score
+            .as_ref()
+            .ok_or(ErrorCode::NoHeldScore)?;
+        require!(
+            !held.disputed || held.accepted,
+            ErrorCode::ScoreUnderDispute
+        );
+        require!(
+            logic::held_score_applicable(held, now, ProtocolConfig::SCORE_DISPUTE_WINDOW_SECS),
+            ErrorCode::DisputeWindowOpen
+        );
+
+        let max_delta = ctx.accounts.config.max_score_delta_per_update;
+        let breach = apply_held_score(
+            &mut ctx.accounts.user_account,
+            ctx.accounts.score_archive.as_mut(),
+            ctx.accounts.covered_tokens.as_deref(),
+            max_delta,
+            &mut events,
+        )?;
+        if let Some(score) = breach {
+            let wallet = ctx.accounts.user_account.wallet;
+            record_activity(
+                ctx.accounts.activity.as_ref(),
+                None,
+                wallet,
+                ActivityKind::ScoreBreach,
+                score as u64,
+                Pubkey::default(),
+                now,
+            )?;
+        }
+        Ok(())
+    }
+
+    // Object to the held score within its dispute window. It then waits for
+    // governance to rule on the dispute.
+    pub fn dispute_pending_score(
+        ctx: Context<DisputePendingScore>,
+        reason: [u8; 64],
     ) -> Result<()> {
-        let user_account = &ctx.accounts.user_account;
-        let clock = Clock::get()?;
-        
-        // Verify user has active protection
-        require!(user_account.active_sub, ErrorCode::NoActiveSubscription);
-        require!(clock.unix_timestamp < user_account.subscription_expiry, ErrorCode::SubscriptionExpired);
-        
-        // Execute the protection action
-        match action_type {
-            ProtectionAction::Swap => {
-                // Would integrate with DEX like Orca or Saber
-                // Simplified for this example
-                emit!(ProtectionTriggered {
-                    wallet: user_account.wallet,
-                    action: "SWAP".to_string(),
-                    token,
-                    amount,
-                    timestamp: clock.unix_timestamp,
-                });
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let now = Clock::get()?.unix_timestamp;
+        let user_account = &mut ctx.accounts.user_account;
+        let wallet = user_account.wallet;
+        // One dispute per cooldown, so a wallet can't hold off every score it is sent
+        require!(
+            logic::score_dispute_allowed(
+                user_account.last_score_dispute_at,
+                now,
+                ProtocolConfig::SCORE_DISPUTE_COOLDOWN_SECS,
+            ),
+            ErrorCode::ScoreDisputeCooldown
+        );
+        user_account.last_score_dispute_at = now;
+        let held = user_account
+            .held_score
+            .as_mut()
+            .ok_or(ErrorCode::NoHeldScore)?;
+        require!(!held.disputed, ErrorCode::ScoreUnderDispute);
+        require!(
+            !logic::held_score_applicable(held, now, ProtocolConfig::SCORE_DISPUTE_WINDOW_SECS),
+            ErrorCode::DisputeWindowClosed
+        );
+        held.disputed = true;
+
+        let dispute = &mut ctx.accounts.oracle_dispute;
+        dispute.wallet = wallet;
+        dispute.oracle = held.oracle;
+        dispute.score = held.value;
+        dispute.submitted_at = held.submitted_at;
+        dispute.reason = reason;
+        dispute.opened_at = now;
+        dispute.bump = ctx.bumps.oracle_dispute;
+
+        emit_indexed!(
+            events,
+            dispute.wallet,
+            OracleScoreDisputed {
+                wallet: dispute.wallet,
+                oracle: dispute.oracle,
+                score: dispute.score,
+                reason,
             }
-            ProtectionAction::Freeze => {
-                emit!(ProtectionTriggered {
-                    wallet: user_account.wallet,
-                    action: "FREEZE".to_string(),
-                    token,
-                    amount,
-                    timestamp: clock.unix_timestamp,
-                });
+        );
+        Ok(())
+    }
+
+    // Rule on a score dispute. An upheld dispute discards the held score; otherwise
+    // the score may be applied straight away. The dispute account is closed to the
+    // wallet.
+    pub fn resolve_score_dispute(ctx: Context<ResolveScoreDispute>, upheld: bool) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        require!(
+            ctx.accounts
+                .governance
+                .is_authorized(&ctx.accounts.authority.key()),
+            ErrorCode::UnauthorizedGovernance
+        );
+        let dispute = &ctx.accounts.oracle_dispute;
+        let user_account = &mut ctx.accounts.user_account;
+        // The disputed score may already have been superseded by a newer one
+        let still_held = user_account
+            .held_score
+            .as_ref()
+            .is_some_and(|held| held.disputed && held.submitted_at == dispute.submitted_at);
+        if still_held {
+            if upheld {
+                user_account.held_score = None;
+            } else if let Some(held) = user_account.held_score.as_mut() {
+                held.accepted = true;
             }
-            _ => return Err(ErrorCode::InvalidAction.into()),
         }
-        
-        // Log the action
-        let action_log = &mut ctx.accounts.action_log;
-        action_log.timestamp = clock.unix_timestamp;
-        action_log.trigger_type = action_type;
-        action_log.token = token;
-        action_log.score = user_account.score_history.last().unwrap().value;
-        
+
+        emit_indexed!(
+            events,
+            dispute.wallet,
+            OracleScoreDisputeResolved {
+                wallet: dispute.wallet,
+                oracle: dispute.oracle,
+                score: dispute.score,
+                upheld,
+            }
+        );
         Ok(())
     }
 
-    // Initiate a claim process
-    pub fn initiate_claim(
-        ctx: Context<InitiateClaim>,
-        claim_amount: u64,
-        proof: Vec<u8>,
+    // Emit a stored aggregation report so auditors can replay a consensus update
+    pub fn get_aggregation_report(ctx: Context<GetAggregationReport>, index: u32) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let report = &ctx.accounts.aggregation_report;
+        emit_indexed!(
+            events,
+            report.wallet,
+            AggregationReportRead {
+                wallet: report.wallet,
+                report_index: index,
+                participating_oracles: report.participating_oracles.clone(),
+                submitted_scores: report.submitted_scores.clone(),
+                registered_oracles: report.registered_oracles,
+                consensus_score: report.consensus_score,
+                timestamp: report.timestamp,
+            }
+        );
+        Ok(())
+    }
+
+    // Read-only quotes. Each runs the same helpers as the instruction it previews and
+    // returns its result as return data, so clients read authoritative values by
+    // simulating rather than re-implementing the math.
+
+    // Score the wallet's protection triggers compare against its risk threshold
+    pub fn get_effective_score(ctx: Context<ReadUserAccount>) -> Result<u8> {
+        ctx.accounts
+            .user_account
+            .effective_score()
+            .ok_or(error!(ErrorCode::NoScoreHistory))
+    }
+
+    // Premium and term mint_insurance_nft would charge and set right now, before any
+    // fee waiver. Remaining accounts: the wallet's token accounts, as for minting.
+    pub fn quote_premium<'info>(
+        ctx: Context<'_, '_, 'info, 'info, QuotePremium<'info>>,
+        tier: u8,
+        payout_cap: u64,
+        duration: u64,
+    ) -> Result<PremiumQuote> {
+        let portfolio_value = portfolio_value(
+            ctx.remaining_accounts,
+            &ctx.accounts.user_account.wallet,
+            &ctx.accounts.pool.payout_mint,
+            payout_cap,
+        )?;
+
+        let (premium_rate_bps, premium) = pool::quote_premium(
+            &ctx.accounts.pool,
+            ctx.accounts.pool_vault.amount,
+            ctx.accounts.config.tier(tier)?,
+            payout_cap,
+            portfolio_value,
+            duration,
+        )?;
+        let (coverage_starts_at, expiry) = ctx
+            .accounts
+            .config
+            .policy_term(Clock::get()?.unix_timestamp, duration)?;
+        Ok(PremiumQuote {
+            portfolio_value,
+            premium_rate_bps,
+            premium,
+            coverage_starts_at,
+            expiry,
+        })
+    }
+
+    // Where the policy's coverage stands now, as claims see it
+    pub fn get_coverage_state(ctx: Context<ReadPolicy>) -> Result<logic::CoverageState> {
+        Ok(ctx
+            .accounts
+            .insurance_nft
+            .coverage_state(Clock::get()?.unix_timestamp))
+    }
+
+    // What execute_payout would pay the claim right now, with the reinsurance tranche
+    // counted when it is passed
+    pub fn quote_payout(ctx: Context<QuotePayout>) -> Result<PayoutQuote> {
+        payout_quote(
+            &ctx.accounts.config,
+            &ctx.accounts.pool,
+            &ctx.accounts.claim,
+            ctx.accounts.vault.amount,
+            ctx.accounts.reinsurance_pool.as_deref(),
+        )
+    }
+
+    // Create the archive of a wallet's raw scores. Scores stored before it existed are
+    // only in events.
+    pub fn init_score_history(ctx: Context<InitScoreHistory>) -> Result<()> {
+        let archive = &mut ctx.accounts.score_archive;
+        archive.wallet = ctx.accounts.user_account.wallet;
+        archive.scores = Vec::new();
+        archive.bump = ctx.bumps.score_archive;
+        Ok(())
+    }
+
+    // Check that `score` is leaf `leaf_index` of the wallet's score hash chain. The
+    // proof is the running hash just before that leaf followed by every later leaf.
+    pub fn verify_score_inclusion(
+        ctx: Context<VerifyScoreInclusion>,
+        score: Score,
+        merkle_proof: Vec<[u8; 32]>,
+        leaf_index: u32,
     ) -> Result<()> {
-        let insurance_nft = &ctx.accounts.insurance_nft;
+        let user_account = &ctx.accounts.user_account;
+        require!(
+            logic::verify_score_inclusion(
+                &user_account.score_history_hash,
+                user_account.score_hash_count,
+                &score,
+                &merkle_proof,
+                leaf_index,
+            ),
+            ErrorCode::InvalidScoreProof
+        );
+        Ok(())
+    }
+
+    // Create the oracle registry with its primary score oracle
+    pub fn initialize_oracle_registry(
+        ctx: Context<InitializeOracleRegistry>,
+        primary_oracle: Pubkey,
+        heartbeat_interval: i64,
+    ) -> Result<()> {
+        require_direct_call(&ctx.accounts.config, ctx.accounts.instructions.as_ref())?;
+        require!(heartbeat_interval > 0, ErrorCode::InvalidDuration);
+
+        let registry = &mut ctx.accounts.oracle_registry;
+        registry.primary_oracle = primary_oracle;
+        registry.primary_heartbeat = Clock::get()?.unix_timestamp;
+        registry.fallback_oracles = Vec::new();
+        registry.fallback_heartbeats = Vec::new();
+        registry.current_active_oracle = primary_oracle;
+        registry.heartbeat_interval = heartbeat_interval;
+        registry.oracle_count = 1;
+        registry.bump = ctx.bumps.oracle_registry;
+        Ok(())
+    }
+
+    // Replace the ordered list of backup oracles tried when the primary goes quiet
+    pub fn set_fallback_oracles(
+        ctx: Context<SetFallbackOracles>,
+        fallback_oracles: Vec<Pubkey>,
+    ) -> Result<()> {
+        require_direct_call(&ctx.accounts.config, ctx.accounts.instructions.as_ref())?;
+        let registry = &mut ctx.accounts.oracle_registry;
+        require!(
+            fallback_oracles.len() <= MAX_FALLBACK_ORACLES,
+            ErrorCode::TooManyFallbackOracles
+        );
+        require!(
+            !fallback_oracles.contains(&registry.primary_oracle),
+            ErrorCode::InvalidOracle
+        );
+        let oracle_count = fallback_oracles.len() as u8 + 1;
+        require!(
+            !logic::oracle_registry_full(oracle_count - 1, ctx.accounts.config.max_oracle_count),
+            ErrorCode::OracleRegistryFull
+        );
+
+        // New fallbacks must heartbeat before they count as live
+        registry.fallback_heartbeats = vec![0; fallback_oracles.len()];
+        registry.fallback_oracles = fallback_oracles;
+        registry.oracle_count = oracle_count;
+        Ok(())
+    }
+
+    // Add one backup oracle to the end of the fallback order
+    pub fn register_oracle(ctx: Context<SetFallbackOracles>, oracle: Pubkey) -> Result<()> {
+        require_direct_call(&ctx.accounts.config, ctx.accounts.instructions.as_ref())?;
+        let registry = &mut ctx.accounts.oracle_registry;
+        require!(
+            oracle != registry.primary_oracle && !registry.fallback_oracles.contains(&oracle),
+            ErrorCode::InvalidOracle
+        );
+        require!(
+            !logic::oracle_registry_full(
+                registry.oracle_count,
+                ctx.accounts.config.max_oracle_count
+            ),
+            ErrorCode::OracleRegistryFull
+        );
+        require!(
+            registry.fallback_oracles.len() < MAX_FALLBACK_ORACLES,
+            ErrorCode::TooManyFallbackOracles
+        );
+
+        // Like set_fallback_oracles, it must heartbeat before it counts as live
+        registry.fallback_oracles.push(oracle);
+        registry.fallback_heartbeats.push(0);
+        registry.oracle_count += 1;
+        Ok(())
+    }
+
+    // Remove a backup oracle. The primary can only be replaced, not removed.
+    pub fn deregister_oracle(ctx: Context<SetFallbackOracles>, oracle: Pubkey) -> Result<()> {
+        require_direct_call(&ctx.accounts.config, ctx.accounts.instructions.as_ref())?;
+        let registry = &mut ctx.accounts.oracle_registry;
+        let index = registry
+            .fallback_oracles
+            .iter()
+            .position(|fallback| fallback == &oracle)
+            .ok_or(ErrorCode::UnknownOracle)?;
+
+        registry.fallback_oracles.remove(index);
+        registry.fallback_heartbeats.remove(index);
+        registry.oracle_count = registry.oracle_count.saturating_sub(1);
+        Ok(())
+    }
+
+    // Liveness ping from a registered oracle
+    pub fn oracle_heartbeat(ctx: Context<OracleHeartbeat>) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let now = Clock::get()?.unix_timestamp;
+        let registry = &mut ctx.accounts.oracle_registry;
+        require!(
+            registry.record_heartbeat(&ctx.accounts.oracle.key(), now),
+            ErrorCode::UnknownOracle
+        );
+
+        if let Some(active) = registry.live_oracle(now) {
+            update_active_oracle(registry, active, &mut events);
+        }
+
+        if let (Some(user_account), Some(insurance_nft)) =
+            (&ctx.accounts.user_account, &ctx.accounts.insurance_nft)
+        {
+            require!(
+                insurance_nft.owner == user_account.wallet,
+                ErrorCode::NotNftOwner
+            );
+            let alert_days = ctx.accounts.config.coverage_gap_alert_days;
+            check_coverage_gap(user_account, insurance_nft, alert_days, &mut events);
+        }
+        Ok(())
+    }
+
+    // Warn if the wallet's policy expires shortly before its subscription does. Anyone
+    // may call this; the user or a keeper typically does.
+    pub fn coverage_gap_check(ctx: Context<CoverageGapCheck>) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let alert_days = ctx.accounts.config.coverage_gap_alert_days;
+        check_coverage_gap(
+            &ctx.accounts.user_account,
+            &ctx.accounts.insurance_nft,
+            alert_days,
+            &mut events,
+        );
+        Ok(())
+    }
+
+    // Set the key allowed to post observed pool APYs
+    pub fn set_apy_oracle(ctx: Context<SetFallbackOracles>, apy_oracle: Pubkey) -> Result<()> {
+        require_direct_call(&ctx.accounts.config, ctx.accounts.instructions.as_ref())?;
+        ctx.accounts.oracle_registry.apy_oracle = apy_oracle;
+        Ok(())
+    }
+
+    // Replace the bots allowed to invalidate claims. Score oracles can't hold the role.
+    pub fn set_invalidator_oracles(
+        ctx: Context<SetFallbackOracles>,
+        oracles: Vec<Pubkey>,
+    ) -> Result<()> {
+        require_direct_call(&ctx.accounts.config, ctx.accounts.instructions.as_ref())?;
+        let registry = &mut ctx.accounts.oracle_registry;
+        require!(
+            oracles.len() <= MAX_INVALIDATOR_ORACLES,
+            ErrorCode::TooManyInvalidatorOracles
+        );
+        require!(
+            oracles
+                .iter()
+                .all(|oracle| registry.role_of(oracle) != Some(OracleRole::ScoreOracle)),
+            ErrorCode::InvalidatorIsScoreOracle
+        );
+        registry.invalidator_oracles = oracles;
+        Ok(())
+    }
+
+    // Post the pool's observed yield from the external APY feed
+    pub fn update_pool_apy(ctx: Context<UpdatePoolApy>, new_apy_bps: u16) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        pool.observed_apy_bps = new_apy_bps;
+        pool.apy_last_updated = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    // Publish the pool's TVL to the protocol's Switchboard feed for other programs to
+    // read. Anyone may crank this, at most once per TVL_PUBLISH_INTERVAL_SECS.
+    pub fn publish_pool_tvl(ctx: Context<PublishPoolTvl>) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let now = Clock::get()?.unix_timestamp;
+        let pool = &mut ctx.accounts.pool;
+        require!(
+            now.saturating_sub(pool.last_tvl_published_at)
+                >= InsurancePool::TVL_PUBLISH_INTERVAL_SECS,
+            ErrorCode::TvlPublishCooldown
+        );
+
+        let tvl = pool::total_value_locked(pool, ctx.accounts.vault.amount);
+        let bump = [ctx.bumps.tvl_publisher];
+        switchboard::publish(
+            &ctx.accounts.switchboard_program,
+            &ctx.accounts.tvl_feed,
+            &ctx.accounts.tvl_publisher,
+            &[&[switchboard::PUBLISHER_SEED, &bump]],
+            tvl,
+            now,
+        )?;
+        pool.last_tvl_published_at = now;
+
+        emit_seq!(
+            events,
+            TvlPublished {
+                pool: pool.key(),
+                tvl,
+                feed_pubkey: ctx.accounts.tvl_feed.key(),
+                timestamp: now,
+            }
+        );
+        Ok(())
+    }
+
+    // Mint an insurance NFT
+    pub fn mint_insurance_nft<'info>(
+        ctx: Context<'_, '_, 'info, 'info, MintInsuranceNft<'info>>,
+        tier: u8,
+        payout_cap: u64,
+        duration: u64,
+        max_premium: u64,
+        burn_for_waiver: Option<u64>,
+        reference: Option<[u8; 16]>,
+    ) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        ctx.accounts
+            .config
+            .require_not_paused(ProtocolConfig::PAUSE_MINT)?;
         let clock = Clock::get()?;
-        
-        // Verify NFT is valid and not expired
-        require!(clock.unix_timestamp < insurance_nft.expiry, ErrorCode::InsuranceExpired);
-        require!(claim_amount <= insurance_nft.payout_cap, ErrorCode::ClaimExceedsCap);
-        
-        // Create claim account
-        let claim = &mut ctx.accounts.claim;
-        claim.claimant = ctx.accounts.user.key();
-        claim.amount = claim_amount;
-        claim.timestamp = clock.unix_timestamp;
-        claim.status = ClaimStatus::Pending;
-        claim.proof = proof;
-        claim.insurance_nft = insurance_nft.key();
-        
-        // Lock the NFT
+        let user_account = &ctx.accounts.user_account;
+
+        // Verify user has active subscription
+        require_subscription_active(user_account, &ctx.accounts.config, clock.unix_timestamp)?;
+        require!(!ctx.accounts.pool.minting_halted, ErrorCode::MintingHalted);
+
+        // Coverage can't exceed what the wallet holds (token accounts in remaining_accounts)
+        let portfolio_value_at_mint = portfolio_value(
+            ctx.remaining_accounts,
+            &user_account.wallet,
+            &ctx.accounts.pool.payout_mint,
+            payout_cap,
+        )?;
+
+        // Price off live utilization; max_premium guards against it moving since the quote
+        let (premium_rate_bps, mut premium) = pool::quote_premium(
+            &ctx.accounts.pool,
+            ctx.accounts.pool_vault.amount,
+            ctx.accounts.config.tier(tier)?,
+            payout_cap,
+            portfolio_value_at_mint,
+            duration,
+        )?;
+        if let Some(burn) = burn_for_waiver {
+            premium -= burn_for_fee_waiver(
+                &ctx.accounts.config,
+                ctx.accounts.governance_mint.as_ref(),
+                ctx.accounts.governance_token_account.as_ref(),
+                &ctx.accounts.user,
+                &ctx.accounts.token_program,
+                premium,
+                burn,
+                &mut events,
+            )?;
+        }
+        require!(premium <= max_premium, ErrorCode::PremiumExceedsMax);
+
+        collect_premium(
+            &ctx.accounts.token_program,
+            &ctx.accounts.premium_token_account,
+            &mut ctx.accounts.pool_vault,
+            &ctx.accounts.payout_mint,
+            &ctx.accounts.user,
+            &mut ctx.accounts.pool,
+            &mut ctx.accounts.config,
+            &mut events,
+            premium,
+            tier,
+            LedgerInstruction::MintInsuranceNft,
+        )?;
+        memo::attach(ctx.accounts.memo_program.as_ref(), memo::PREMIUM, reference)?;
+
+        // Mint NFT
         let cpi_ctx = CpiContext::new(
             ctx.accounts.token_program.to_account_info(),
-            token::Transfer {
-                from: ctx.accounts.insurance_nft_account.to_account_info(),
-                to: ctx.accounts.claim_escrow.to_account_info(),
-                authority: ctx.accounts.user.to_account_info(),
+            token_interface::MintTo {
+                mint: ctx.accounts.mint.to_account_info(),
+                to: ctx.accounts.insurance_nft_account.to_account_info(),
+                authority: ctx.accounts.iris_authority.to_account_info(),
             },
         );
-        token::transfer(cpi_ctx, 1)?;
-        
-        emit!(ClaimInitiated {
-            wallet: ctx.accounts.user.key(),
-            claim_id: claim.key(),
-            amount: claim_amount,
-            timestamp: clock.unix_timestamp,
-        });
-        
+        token_interface::mint_to(cpi_ctx, 1)?;
+
+        let user_account = &mut ctx.accounts.user_account;
+        require!(
+            user_account.linked_nfts.len() < MAX_LINKED_NFTS,
+            ErrorCode::TooManyLinkedNfts
+        );
+        user_account
+            .linked_nfts
+            .push(ctx.accounts.insurance_nft.key());
+
+        let pool = &mut ctx.accounts.pool;
+        pool.outstanding_coverage = pool
+            .outstanding_coverage
+            .checked_add(payout_cap)
+            .ok_or(ErrorCode::MathOverflow)?;
+        ctx.accounts.config.total_nfts_minted += 1;
+        let policy_number = take_number(&mut ctx.accounts.config.next_policy_number)?;
+        ctx.accounts
+            .policy_number_index
+            .set_inner(PolicyNumberIndex {
+                number: policy_number,
+                policy: ctx.accounts.insurance_nft.key(),
+                bump: ctx.bumps.policy_number_index,
+            });
+
+        // Store NFT metadata
+        let insurance_nft = &mut ctx.accounts.insurance_nft;
+        insurance_nft.version = InsuranceNFT::CURRENT_VERSION;
+        insurance_nft.tier = tier;
+        insurance_nft.pool = pool.key();
+        (insurance_nft.coverage_starts_at, insurance_nft.expiry) = ctx
+            .accounts
+            .config
+            .policy_term(clock.unix_timestamp, duration)?;
+        insurance_nft.payout_cap = payout_cap;
+        insurance_nft.token_mint = ctx.accounts.mint.key();
+        insurance_nft.owner = user_account.wallet;
+        insurance_nft.rent_payer = ctx.accounts.payer.key();
+        insurance_nft.portfolio_value_at_mint = portfolio_value_at_mint;
+        insurance_nft.premium_reference = reference;
+        insurance_nft.policy_number = policy_number;
+
+        emit_indexed!(
+            events,
+            user_account.wallet,
+            InsuranceNftMinted {
+                wallet: user_account.wallet,
+                mint: ctx.accounts.mint.key(),
+                tier,
+                expiry: insurance_nft.expiry,
+                payout_cap,
+                portfolio_value_at_mint,
+                premium_rate_bps,
+                premium,
+                policy_number,
+            }
+        );
+
         Ok(())
     }
 
-    // DAO governance vote on claim (v2+)
-    pub fn vote_on_claim(
-        ctx: Context<VoteOnClaim>,
-        claim_id: Pubkey,
-        approve: bool,
+    // Create the Bubblegum tree compressed policies are minted into. The admin allocates
+    // `merkle_tree` for the compression program beforehand; the tree authority PDA is
+    // its creator and the only minter.
+    pub fn init_policy_tree(
+        ctx: Context<InitPolicyTree>,
+        max_depth: u32,
+        max_buffer_size: u32,
     ) -> Result<()> {
-        let claim = &mut ctx.accounts.claim;
-        let governance = &ctx.accounts.governance;
-        
-        // Verify governance authority
-        require!(governance.is_authorized(&ctx.accounts.voter.key()), ErrorCode::UnauthorizedGovernance);
-        
-        // Process vote
-        if approve {
-            claim.approval_votes += 1;
-        } else {
-            claim.rejection_votes += 1;
-        }
-        
-        // Check if quorum reached
-        if claim.approval_votes >= governance.quorum {
-            claim.status = ClaimStatus::Approved;
-            process_payout(claim)?;
-        } else if claim.rejection_votes >= governance.quorum {
-            claim.status = ClaimStatus::Rejected;
-            // Return NFT to owner
-            return_nft_to_owner(claim)?;
-        }
-        
-        emit!(ClaimVoted {
-            claim_id,
-            voter: ctx.accounts.voter.key(),
-            approve,
-            timestamp: Clock::get()?.unix_timestamp,
-        });
-        
+        require_direct_call(&ctx.accounts.config, ctx.accounts.instructions.as_ref())?;
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let bump = [ctx.bumps.tree_authority];
+        cnft::create_tree(
+            &cnft::TreeAccounts {
+                bubblegum_program: &ctx.accounts.bubblegum_program,
+                tree_config: &ctx.accounts.tree_config,
+                merkle_tree: &ctx.accounts.merkle_tree,
+                log_wrapper: &ctx.accounts.log_wrapper,
+                compression_program: &ctx.accounts.compression_program,
+                system_program: &ctx.accounts.system_program,
+            },
+            &ctx.accounts.admin,
+            &ctx.accounts.tree_authority,
+            &[&[cnft::TREE_AUTHORITY_SEED, &bump]],
+            max_depth,
+            max_buffer_size,
+        )?;
+
+        ctx.accounts.config.policy_tree = ctx.accounts.merkle_tree.key();
+        emit_seq!(
+            events,
+            PolicyTreeInitialized {
+                merkle_tree: ctx.accounts.merkle_tree.key(),
+                max_depth,
+                max_buffer_size,
+            }
+        );
         Ok(())
     }
-}
 
-// Helper functions
-impl IRISAnchor {
-    fn verify_iris_signature(message: &ScoreMessage, signature: &[u8; 64]) -> Result<()> {
-        // Implementation would verify the signature against IRIS oracle public key
-        Ok(())
-    }
-    
-    fn check_protection_triggers(user_account: &mut Account<UserAccount>) -> Result<()> {
-        let latest_score = user_account.score_history.last().unwrap().value;
-        if latest_score >= user_account.preferences.risk_threshold {
-            // Would trigger protection logic based on user preferences
-            // This is simplified for the example
-            emit!(RiskThresholdBreached {
-                wallet: user_account.wallet,
-                score: latest_score,
-                threshold: user_account.preferences.risk_threshold,
-                timestamp: Clock::get()?.unix_timestamp,
+    // Sell a policy as a compressed NFT in the protocol's policy tree. Pricing and the
+    // policy state match mint_insurance_nft; the state PDA is keyed by `policy_id`, the
+    // Bubblegum asset id of the leaf about to be minted.
+    pub fn mint_insurance_cnft<'info>(
+        ctx: Context<'_, '_, 'info, 'info, MintInsuranceCnft<'info>>,
+        tier: u8,
+        payout_cap: u64,
+        duration: u64,
+        max_premium: u64,
+        policy_id: Pubkey,
+        reference: Option<[u8; 16]>,
+    ) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        ctx.accounts
+            .config
+            .require_not_paused(ProtocolConfig::PAUSE_MINT)?;
+        let clock = Clock::get()?;
+        let user_account = &ctx.accounts.user_account;
+
+        require_subscription_active(user_account, &ctx.accounts.config, clock.unix_timestamp)?;
+        require!(!ctx.accounts.pool.minting_halted, ErrorCode::MintingHalted);
+
+        let nonce = {
+            let data = ctx.accounts.tree_config.try_borrow_data()?;
+            mpl_bubblegum::accounts::TreeConfig::from_bytes(&data)
+                .map_err(|_| ErrorCode::InvalidPolicyLeaf)?
+                .num_minted
+        };
+        require!(
+            policy_id == mpl_bubblegum::utils::get_asset_id(&ctx.accounts.merkle_tree.key(), nonce),
+            ErrorCode::InvalidPolicyId
+        );
+
+        let portfolio_value_at_mint = portfolio_value(
+            ctx.remaining_accounts,
+            &user_account.wallet,
+            &ctx.accounts.pool.payout_mint,
+            payout_cap,
+        )?;
+
+        let (premium_rate_bps, premium) = pool::quote_premium(
+            &ctx.accounts.pool,
+            ctx.accounts.pool_vault.amount,
+            ctx.accounts.config.tier(tier)?,
+            payout_cap,
+            portfolio_value_at_mint,
+            duration,
+        )?;
+        require!(premium <= max_premium, ErrorCode::PremiumExceedsMax);
+
+        collect_premium(
+            &ctx.accounts.token_program,
+            &ctx.accounts.premium_token_account,
+            &mut ctx.accounts.pool_vault,
+            &ctx.accounts.payout_mint,
+            &ctx.accounts.user,
+            &mut ctx.accounts.pool,
+            &mut ctx.accounts.config,
+            &mut events,
+            premium,
+            tier,
+            LedgerInstruction::MintInsuranceCnft,
+        )?;
+        memo::attach(ctx.accounts.memo_program.as_ref(), memo::PREMIUM, reference)?;
+
+        let user_account = &mut ctx.accounts.user_account;
+        require!(
+            user_account.linked_nfts.len() < MAX_LINKED_NFTS,
+            ErrorCode::TooManyLinkedNfts
+        );
+        user_account
+            .linked_nfts
+            .push(ctx.accounts.insurance_nft.key());
+
+        let pool = &mut ctx.accounts.pool;
+        pool.outstanding_coverage = pool
+            .outstanding_coverage
+            .checked_add(payout_cap)
+            .ok_or(ErrorCode::MathOverflow)?;
+        ctx.accounts.config.total_nfts_minted += 1;
+        let policy_number = take_number(&mut ctx.accounts.config.next_policy_number)?;
+        ctx.accounts
+            .policy_number_index
+            .set_inner(PolicyNumberIndex {
+                number: policy_number,
+                policy: ctx.accounts.insurance_nft.key(),
+                bump: ctx.bumps.policy_number_index,
             });
-        }
+
+        let insurance_nft = &mut ctx.accounts.insurance_nft;
+        insurance_nft.version = InsuranceNFT::CURRENT_VERSION;
+        insurance_nft.tier = tier;
+        insurance_nft.pool = pool.key();
+        (insurance_nft.coverage_starts_at, insurance_nft.expiry) = ctx
+            .accounts
+            .config
+            .policy_term(clock.unix_timestamp, duration)?;
+        insurance_nft.payout_cap = payout_cap;
+        insurance_nft.token_mint = policy_id;
+        insurance_nft.owner = user_account.wallet;
+        insurance_nft.rent_payer = ctx.accounts.payer.key();
+        insurance_nft.portfolio_value_at_mint = portfolio_value_at_mint;
+        insurance_nft.premium_reference = reference;
+        insurance_nft.policy_number = policy_number;
+
+        // The leaf's metadata commits to the terms recorded above
+        let metadata = cnft::policy_metadata(&cnft::terms_hash(insurance_nft));
+        let bump = [ctx.bumps.tree_authority];
+        let leaf = cnft::mint_policy(
+            &cnft::TreeAccounts {
+                bubblegum_program: &ctx.accounts.bubblegum_program,
+                tree_config: &ctx.accounts.tree_config,
+                merkle_tree: &ctx.accounts.merkle_tree,
+                log_wrapper: &ctx.accounts.log_wrapper,
+                compression_program: &ctx.accounts.compression_program,
+                system_program: &ctx.accounts.system_program,
+            },
+            &ctx.accounts.user,
+            &ctx.accounts.payer,
+            &ctx.accounts.tree_authority,
+            &[&[cnft::TREE_AUTHORITY_SEED, &bump]],
+            nonce,
+            metadata,
+        )?;
+        insurance_nft.compressed_leaf = Some(leaf);
+
+        emit_indexed!(
+            events,
+            insurance_nft.owner,
+            InsuranceNftMinted {
+                wallet: insurance_nft.owner,
+                mint: policy_id,
+                tier,
+                expiry: insurance_nft.expiry,
+                payout_cap,
+                portfolio_value_at_mint,
+                premium_rate_bps,
+                premium,
+                policy_number,
+            }
+        );
+
         Ok(())
     }
-    
-    fn process_payout(claim: &mut Account<Claim>) -> Result<()> {
-        // Implementation would transfer funds from insurance pool to claimant
+
+    // Burn an expired insurance NFT and unlink it from the owner's account
+    pub fn burn_expired_nft(ctx: Context<BurnExpiredNft>) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let insurance_nft = &ctx.accounts.insurance_nft;
+        require!(!insurance_nft.collateralized, ErrorCode::NftCollateralized);
+        require!(
+            logic::is_expired(Clock::get()?.unix_timestamp, insurance_nft.expiry),
+            ErrorCode::InsuranceNotExpired
+        );
+
+        token_interface::burn(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::Burn {
+                    mint: ctx.accounts.nft_mint.to_account_info(),
+                    from: ctx.accounts.owner_nft_account.to_account_info(),
+                    authority: ctx.accounts.owner.to_account_info(),
+                },
+            ),
+            1,
+        )?;
+
+        let nft_key = insurance_nft.key();
+        let pool = &mut ctx.accounts.pool;
+        pool.outstanding_coverage = pool
+            .outstanding_coverage
+            .saturating_sub(insurance_nft.payout_cap);
+        ctx.accounts
+            .user_account
+            .linked_nfts
+            .retain(|linked| linked != &nft_key);
+
+        emit_indexed!(
+            events,
+            insurance_nft.owner,
+            PolicyClosed {
+                wallet: insurance_nft.owner,
+                policy: nft_key,
+                burned: true,
+                rent_recipient: ctx.accounts.rent_recipient.key(),
+                rent_refunded: insurance_nft.to_account_info().lamports(),
+            }
+        );
         Ok(())
     }
-    
-    fn return_nft_to_owner(claim: &mut Account<Claim>) -> Result<()> {
-        // Implementation would return NFT from escrow to original owner
+
+    // Close an expired NFT's policy record when the token itself is no longer held, so
+    // it stops blocking close_user_account
+    pub fn force_close_expired_nft_before_close(ctx: Context<ForceCloseExpiredNft>) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let insurance_nft = &ctx.accounts.insurance_nft;
+        require!(!insurance_nft.collateralized, ErrorCode::NftCollateralized);
+        require!(
+            logic::is_expired(Clock::get()?.unix_timestamp, insurance_nft.expiry),
+            ErrorCode::InsuranceNotExpired
+        );
+
+        let nft_key = insurance_nft.key();
+        let pool = &mut ctx.accounts.pool;
+        pool.outstanding_coverage = pool
+            .outstanding_coverage
+            .saturating_sub(insurance_nft.payout_cap);
+        ctx.accounts
+            .user_account
+            .linked_nfts
+            .retain(|linked| linked != &nft_key);
+
+        emit_indexed!(
+            events,
+            insurance_nft.owner,
+            PolicyClosed {
+                wallet: insurance_nft.owner,
+                policy: nft_key,
+                burned: false,
+                rent_recipient: ctx.accounts.rent_recipient.key(),
+                rent_refunded: insurance_nft.to_account_info().lamports(),
+            }
+        );
         Ok(())
     }
-}
 
-// Accounts
-#[derive(Accounts)]
-pub struct InitializeUser<'info> {
-    #[account(init, payer = user, space = 8 + UserAccount::LEN)]
+    // Issue `recipient` (e.g. a lending protocol) a certificate of the policy's coverage
+    // that it can read directly. It lapses with the policy.
+    pub fn generate_coverage_certificate(
+        ctx: Context<GenerateCoverageCertificate>,
+        recipient: Pubkey,
+    ) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let insurance_nft = &ctx.accounts.insurance_nft;
+        require_policy_active(insurance_nft, Clock::get()?.unix_timestamp)?;
+
+        let certificate = &mut ctx.accounts.coverage_certificate;
+        certificate.insured_wallet = insurance_nft.owner;
+        certificate.policy = insurance_nft.key();
+        certificate.nft_mint = insurance_nft.token_mint;
+        certificate.recipient = recipient;
+        certificate.payout_cap = insurance_nft.payout_cap;
+        certificate.tier = insurance_nft.tier;
+        certificate.valid_until = insurance_nft.expiry;
+        certificate.certificate_hash = logic::certificate_hash(certificate);
+        certificate.bump = ctx.bumps.coverage_certificate;
+
+        emit_indexed!(
+            events,
+            certificate.insured_wallet,
+            CertificateGenerated {
+                wallet: certificate.insured_wallet,
+                recipient,
+                certificate_hash: certificate.certificate_hash,
+            }
+        );
+        Ok(())
+    }
+
+    // Withdraw a coverage certificate, refunding its rent to the wallet
+    pub fn revoke_coverage_certificate(ctx: Context<RevokeCoverageCertificate>) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let certificate = &ctx.accounts.coverage_certificate;
+        emit_indexed!(
+            events,
+            certificate.insured_wallet,
+            CertificateRevoked {
+                wallet: certificate.insured_wallet,
+                recipient: certificate.recipient,
+                certificate_hash: certificate.certificate_hash,
+            }
+        );
+        Ok(())
+    }
+
+    // Whether the certificate is still backed by its policy, returned as return data
+    pub fn verify_certificate(ctx: Context<VerifyCertificate>) -> Result<bool> {
+        Ok(ctx
+            .accounts
+            .coverage_certificate
+            .is_valid(&ctx.accounts.insurance_nft, Clock::get()?.unix_timestamp))
+    }
+
+    // Create a pool's mint waitlist and the vault escrowing queued premiums
+    pub fn initialize_mint_waitlist(ctx: Context<InitializeMintWaitlist>) -> Result<()> {
+        require!(
+            ctx.accounts
+                .governance
+                .is_authorized(&ctx.accounts.authority.key()),
+            ErrorCode::UnauthorizedGovernance
+        );
+
+        let waitlist = &mut ctx.accounts.mint_waitlist;
+        waitlist.pool = ctx.accounts.pool.key();
+        waitlist.vault = ctx.accounts.vault.key();
+        waitlist.entries = Vec::new();
+        waitlist.bump = ctx.bumps.mint_waitlist;
+        Ok(())
+    }
+
+    // Queue for an insurance NFT while new coverage is halted, escrowing the premium
+    // quoted now. The NFT is minted by process_waitlist once capacity returns.
+    pub fn join_mint_waitlist<'info>(
+        ctx: Context<'_, '_, 'info, 'info, JoinMintWaitlist<'info>>,
+        tier: u8,
+        payout_cap: u64,
+        duration: u64,
+    ) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let clock = Clock::get()?;
+        check_rate_limit(
+            &mut ctx.accounts.user_account,
+            &ctx.accounts.config,
+            RateLimitedAction::JoinMintWaitlist,
+            clock.unix_timestamp,
+        )?;
+        let user_account = &ctx.accounts.user_account;
+        require!(
+            ctx.accounts.pool.minting_halted,
+            ErrorCode::MintingNotHalted
+        );
+        require_subscription_active(user_account, &ctx.accounts.config, clock.unix_timestamp)?;
+        require!(
+            ctx.accounts.mint_waitlist.entries.len() < MAX_WAITLIST_ENTRIES,
+            ErrorCode::WaitlistFull
+        );
+
+        let portfolio_value_at_mint = portfolio_value(
+            ctx.remaining_accounts,
+            &user_account.wallet,
+            &ctx.accounts.pool.payout_mint,
+            payout_cap,
+        )?;
+
+        let (_, premium) = pool::quote_premium(
+            &ctx.accounts.pool,
+            ctx.accounts.pool_vault.amount,
+            ctx.accounts.config.tier(tier)?,
+            payout_cap,
+            portfolio_value_at_mint,
+            duration,
+        )?;
+
+        if premium > 0 {
+            tokens::transfer_in(
+                &ctx.accounts.token_program,
+                &ctx.accounts.premium_token_account,
+                &mut ctx.accounts.waitlist_vault,
+                &ctx.accounts.payout_mint,
+                ctx.accounts.user.to_account_info(),
+                premium,
+            )?;
+        }
+
+        let entry = WaitlistEntry {
+            wallet: user_account.wallet,
+            tier,
+            payout_cap,
+            queued_at: clock.unix_timestamp,
+            duration,
+            premium,
+            portfolio_value_at_mint,
+        };
+        let waitlist = &mut ctx.accounts.mint_waitlist;
+        waitlist.entries.push(entry);
+
+        emit_seq!(
+            events,
+            WaitlistEntryAdded {
+                pool: waitlist.pool,
+                wallet: user_account.wallet,
+                tier,
+                payout_cap,
+                premium,
+                position: waitlist.entries.len() as u8,
+            }
+        );
+
+        Ok(())
+    }
+
+    // Keeper crank: mint the NFT for the entry at the head of the waitlist if it fits
+    // within the pool's restored solvency capacity. Call repeatedly to work down the queue.
+    pub fn process_waitlist(ctx: Context<ProcessWaitlist>) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let clock = Clock::get()?;
+        let entry = ctx
+            .accounts
+            .mint_waitlist
+            .entries
+            .first()
+            .cloned()
+            .ok_or(ErrorCode::WaitlistEmpty)?;
+        require!(!ctx.accounts.pool.minting_halted, ErrorCode::MintingHalted);
+        require!(
+            clock.unix_timestamp < entry.queued_at + MintWaitlist::ENTRY_TTL_SECS,
+            ErrorCode::WaitlistEntryExpired
+        );
+        require!(
+            ctx.accounts.user_account.wallet == entry.wallet,
+            ErrorCode::NotAccountOwner
+        );
+        require!(
+            ctx.accounts.insurance_nft_account.owner == entry.wallet,
+            ErrorCode::InvalidTokenOwner
+        );
+
+        // The entry's coverage must fit without dropping the pool below its requirement
+        let config = &ctx.accounts.config;
+        let coverage = ctx
+            .accounts
+            .pool
+            .outstanding_coverage
+            .checked_add(entry.payout_cap)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let (_, required) = pool::solvency_requirement(
+            &ctx.accounts.pool,
+            coverage,
+            config.expected_loss_bps,
+            config.solvency_buffer_bps,
+        );
+        let assets = ctx.accounts.pool_vault.amount as u128 + entry.premium as u128;
+        require!(assets >= required, ErrorCode::InsufficientCoverageCapacity);
+
+        // Release the escrowed premium into the pool
+        if entry.premium > 0 {
+            let pool_key = ctx.accounts.pool.key();
+            let bump = [ctx.accounts.mint_waitlist.bump];
+            let signer_seeds: &[&[&[u8]]] = &[&[b"mint_waitlist", pool_key.as_ref(), &bump]];
+            tokens::transfer(
+                &ctx.accounts.token_program,
+                ctx.accounts.waitlist_vault.to_account_info(),
+                ctx.accounts.pool_vault.to_account_info(),
+                &ctx.accounts.payout_mint,
+                ctx.accounts.mint_waitlist.to_account_info(),
+                entry.premium,
+                signer_seeds,
+            )?;
+        }
+
+        token_interface::mint_to(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::MintTo {
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.insurance_nft_account.to_account_info(),
+                    authority: ctx.accounts.iris_authority.to_account_info(),
+                },
+            ),
+            1,
+        )?;
+
+        let user_account = &mut ctx.accounts.user_account;
+        require!(
+            user_account.linked_nfts.len() < MAX_LINKED_NFTS,
+            ErrorCode::TooManyLinkedNfts
+        );
+        user_account
+            .linked_nfts
+            .push(ctx.accounts.insurance_nft.key());
+
+        let pool = &mut ctx.accounts.pool;
+        pool.outstanding_coverage = coverage;
+        pool.premiums_accrued = pool
+            .premiums_accrued
+            .checked_add(entry.premium)
+            .ok_or(ErrorCode::MathOverflow)?;
+        pool.reserved_rewards = pool
+            .reserved_rewards
+            .checked_add(entry.premium)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        let policy_number = take_number(&mut ctx.accounts.config.next_policy_number)?;
+        ctx.accounts
+            .policy_number_index
+            .set_inner(PolicyNumberIndex {
+                number: policy_number,
+                policy: ctx.accounts.insurance_nft.key(),
+                bump: ctx.bumps.policy_number_index,
+            });
+
+        let insurance_nft = &mut ctx.accounts.insurance_nft;
+        insurance_nft.version = InsuranceNFT::CURRENT_VERSION;
+        insurance_nft.tier = entry.tier;
+        (insurance_nft.coverage_starts_at, insurance_nft.expiry) = ctx
+            .accounts
+            .config
+            .policy_term(clock.unix_timestamp, entry.duration)?;
+        insurance_nft.payout_cap = entry.payout_cap;
+        insurance_nft.token_mint = ctx.accounts.mint.key();
+        insurance_nft.owner = entry.wallet;
+        insurance_nft.portfolio_value_at_mint = entry.portfolio_value_at_mint;
+        insurance_nft.pool = pool.key();
+        insurance_nft.rent_payer = ctx.accounts.keeper.key();
+        insurance_nft.policy_number = policy_number;
+
+        ctx.accounts.mint_waitlist.entries.remove(0);
+
+        if entry.premium > 0 {
+            emit_seq!(
+                events,
+                PremiumReceived {
+                    ledger_seq: next_ledger_seq(&mut ctx.accounts.config)?,
+                    instruction: LedgerInstruction::ProcessWaitlist,
+                    mint: pool.payout_mint,
+                    counterparty: entry.wallet,
+                    amount: entry.premium,
+                    tier: entry.tier,
+                    timestamp: clock.unix_timestamp,
+                }
+            );
+        }
+        emit_seq!(
+            events,
+            WaitlistMintProcessed {
+                pool: pool.key(),
+                wallet: entry.wallet,
+                mint: ctx.accounts.mint.key(),
+                tier: entry.tier,
+                payout_cap: entry.payout_cap,
+                premium: entry.premium,
+                waited_secs: clock.unix_timestamp - entry.queued_at,
+                policy_number,
+            }
+        );
+
+        Ok(())
+    }
+
+    // Drop the head entry once it has waited past the waitlist TTL, refunding its premium
+    pub fn expire_waitlist_entry(ctx: Context<ExpireWaitlistEntry>) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let clock = Clock::get()?;
+        let entry = ctx
+            .accounts
+            .mint_waitlist
+            .entries
+            .first()
+            .cloned()
+            .ok_or(ErrorCode::WaitlistEmpty)?;
+        require!(
+            clock.unix_timestamp >= entry.queued_at + MintWaitlist::ENTRY_TTL_SECS,
+            ErrorCode::WaitlistEntryNotExpired
+        );
+        require!(
+            ctx.accounts.refund_token_account.owner == entry.wallet,
+            ErrorCode::InvalidTokenOwner
+        );
+
+        if entry.premium > 0 {
+            let pool_key = ctx.accounts.mint_waitlist.pool;
+            let bump = [ctx.accounts.mint_waitlist.bump];
+            let signer_seeds: &[&[&[u8]]] = &[&[b"mint_waitlist", pool_key.as_ref(), &bump]];
+            tokens::transfer(
+                &ctx.accounts.token_program,
+                ctx.accounts.waitlist_vault.to_account_info(),
+                ctx.accounts.refund_token_account.to_account_info(),
+                &ctx.accounts.payout_mint,
+                ctx.accounts.mint_waitlist.to_account_info(),
+                entry.premium,
+                signer_seeds,
+            )?;
+        }
+
+        let waitlist = &mut ctx.accounts.mint_waitlist;
+        waitlist.entries.remove(0);
+
+        emit_seq!(
+            events,
+            WaitlistEntryExpired {
+                pool: waitlist.pool,
+                wallet: entry.wallet,
+                refunded: entry.premium,
+                queued_at: entry.queued_at,
+            }
+        );
+
+        Ok(())
+    }
+
+    // Trigger a protection action (swap, freeze, etc.)
+    pub fn trigger_protection(
+        ctx: Context<TriggerProtection>,
+        action_type: ProtectionAction,
+        token: Pubkey,
+        amount: u64,
+        i_know_what_im_doing: bool,
+    ) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        ctx.accounts
+            .config
+            .require_not_paused(ProtocolConfig::PAUSE_PROTECTION)?;
+        let user_account = &ctx.accounts.user_account;
+        let wallet = user_account.wallet;
+        let clock = Clock::get()?;
+
+        // Verify user has active protection
+        require_subscription_active(user_account, &ctx.accounts.config, clock.unix_timestamp)?;
+        if let Some(mint) = ctx.accounts.covered_mint.as_ref() {
+            tokens::require_approved_hook(mint, &ctx.accounts.config.transfer_hook_allowlist)?;
+        }
+
+        // Swaps and freezes only act on the wallet's covered tokens, up to each one's cap
+        let mut amount = amount;
+        if logic::acts_on_covered_token(&action_type) {
+            let covered = ctx
+                .accounts
+                .covered_tokens
+                .as_ref()
+                .and_then(|list| list.get(&token))
+                .ok_or(ErrorCode::TokenNotCovered)?;
+            amount = amount.min(covered.max_amount);
+        }
+
+        // Swaps sell no more than the user's cap of the token balance
+        if matches!(action_type, ProtectionAction::Swap) {
+            let balance = ctx
+                .accounts
+                .swap_token_account
+                .as_ref()
+                .ok_or(ErrorCode::SwapTokenAccountMissing)?
+                .amount;
+            let bps = user_account.max_auto_swap_bps();
+            let capped = amount.min(logic::auto_swap_cap(balance, bps));
+            require!(capped > 0, ErrorCode::ZeroAmountAfterCap);
+            if capped < amount {
+                emit_indexed!(
+                    events,
+                    wallet,
+                    SwapAmountCapped {
+                        wallet,
+                        requested: amount,
+                        capped,
+                        bps,
+                    }
+                );
+            }
+            amount = capped;
+        }
+
+        // Swaps only route through allowlisted DEX programs, unless the owner signs and
+        // explicitly accepts the risk of another
+        let mut allowlist_bypassed = false;
+        if matches!(action_type, ProtectionAction::Swap) {
+            let program = ctx
+                .accounts
+                .swap_program
+                .as_ref()
+                .ok_or(ErrorCode::SwapProgramMissing)?
+                .key();
+            let allowlist = &ctx.accounts.config.swap_program_allowlist;
+            let owner_signed = ctx.accounts.user.key() == wallet;
+            require!(
+                logic::swap_program_permitted(
+                    &program,
+                    allowlist,
+                    owner_signed,
+                    i_know_what_im_doing
+                ),
+                ErrorCode::SwapProgramNotAllowed
+            );
+            if !allowlist.contains(&program) {
+                allowlist_bypassed = true;
+                emit_indexed!(
+                    events,
+                    wallet,
+                    SwapAllowlistBypassed {
+                        wallet,
+                        program,
+                        timestamp: clock.unix_timestamp,
+                    }
+                );
+            }
+        }
+
+        // Execute the protection action. Swaps would integrate with a DEX like Orca or
+        // Saber; this is simplified for the example.
+        let action =
+            logic::protection_action_label(&action_type).ok_or(ErrorCode::InvalidAction)?;
+        emit_cpi_indexed!(
+            ctx,
+            events,
+            user_account.wallet,
+            ProtectionTriggered {
+                wallet: user_account.wallet,
+                action: action.to_string(),
+                token,
+                amount,
+                timestamp: clock.unix_timestamp,
+            }
+        );
+
+        ctx.accounts.config.total_protection_actions += 1;
+        record_activity(
+            ctx.accounts.activity.as_ref(),
+            Some((&ctx.accounts.user, &ctx.accounts.system_program)),
+            wallet,
+            ActivityKind::ProtectionTriggered,
+            amount,
+            token,
+            clock.unix_timestamp,
+        )?;
+
+        // Log the action, as a leaf of the action log tree when compression is on
+        let score = user_account.score_history.last().unwrap().value;
+        if !ctx.accounts.config.action_log_tree.compressed {
+            let action_log = ctx
+                .accounts
+                .action_log
+                .as_mut()
+                .ok_or(ErrorCode::ActionLogAccountMissing)?;
+            action_log.timestamp = clock.unix_timestamp;
+            action_log.trigger_type = action_type;
+            action_log.token = token;
+            action_log.score = score;
+            action_log.allowlist_bypassed = allowlist_bypassed;
+            return Ok(());
+        }
+
+        let (Some(merkle_tree), Some(log_authority), Some(compression_program), Some(noop_program)) = (
+            ctx.accounts.merkle_tree.as_ref(),
+            ctx.accounts.log_authority.as_ref(),
+            ctx.accounts.compression_program.as_ref(),
+            ctx.accounts.noop_program.as_ref(),
+        ) else {
+            return err!(ErrorCode::ActionLogTreeAccountsMissing);
+        };
+        let entry = ActionLogEntry {
+            wallet,
+            timestamp: clock.unix_timestamp,
+            trigger_type: action_type,
+            token,
+            score,
+            leaf_index: ctx.accounts.config.action_log_tree.leaf_count,
+        };
+        let bump = [ctx
+            .bumps
+            .log_authority
+            .ok_or(ErrorCode::ActionLogTreeAccountsMissing)?];
+        let leaf = compression::append_entry(
+            compression_program,
+            merkle_tree,
+            log_authority,
+            noop_program,
+            &[&[compression::LOG_AUTHORITY_SEED, &bump]],
+            &entry,
+        )?;
+
+        let tree = &mut ctx.accounts.config.action_log_tree;
+        tree.leaf_count = tree
+            .leaf_count
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        emit_indexed!(
+            events,
+            wallet,
+            ActionLogCompressed {
+                wallet,
+                merkle_tree: tree.merkle_tree,
+                leaf_index: entry.leaf_index,
+                leaf,
+            }
+        );
+        // Latest entry stays readable on-chain for claim linkage
+        ctx.accounts.user_account.last_action_log = Some(entry);
+
+        Ok(())
+    }
+
+    // Compute units a trigger_protection call is expected to use, so clients can size
+    // its compute budget instruction before sending it. Also returned as the
+    // recommended limit (u32 LE) in return data.
+    pub fn estimate_protection_cost(
+        ctx: Context<EstimateProtectionCost>,
+        action_type: ProtectionAction,
+        token: Pubkey,
+        amount: u64,
+    ) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let config = &ctx.accounts.config;
+        let estimate = compute_budget::estimate_protection_units(
+            config.protection_cu_baselines.for_action(&action_type),
+            config.action_log_tree.compressed,
+        );
+        set_return_data(&estimate.recommended_limit.to_le_bytes());
+
+        emit_seq!(
+            events,
+            ComputeUnitEstimate {
+                action_type,
+                token,
+                amount,
+                estimated_cus: estimate.estimated,
+                safety_margin_cus: estimate.safety_margin,
+                recommended_limit: estimate.recommended_limit,
+            }
+        );
+        Ok(())
+    }
+
+    // Start the custody log for evidence that will back the claim at `claim_id` (the
+    // claim account's address, generated by the client before submission)
+    pub fn open_claim_evidence(
+        ctx: Context<OpenClaimEvidence>,
+        claim_id: Pubkey,
+        proof: Vec<u8>,
+    ) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        require!(proof.len() <= MAX_CLAIM_PROOF_LEN, ErrorCode::ProofTooLarge);
+
+        let custody = &mut ctx.accounts.evidence_custody;
+        custody.claim_id = claim_id;
+        custody.custodian = ctx.accounts.handler.key();
+        custody.bump = ctx.bumps.evidence_custody;
+        let digest = hash(&proof).to_bytes();
+        custody.proof = proof;
+        append_custody_entry(custody, CustodyAction::Created, digest, &mut events)
+    }
+
+    // Replace the evidence before submission; logged as a modification by the custodian
+    pub fn update_claim_evidence(
+        ctx: Context<UpdateClaimEvidence>,
+        new_proofs: Vec<u8>,
+    ) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        require!(
+            new_proofs.len() <= MAX_CLAIM_PROOF_LEN,
+            ErrorCode::ProofTooLarge
+        );
+
+        let custody = &mut ctx.accounts.evidence_custody;
+        require!(!custody.submitted, ErrorCode::EvidenceAlreadySubmitted);
+        let digest = hash(&new_proofs).to_bytes();
+        custody.proof = new_proofs;
+        append_custody_entry(custody, CustodyAction::Modified, digest, &mut events)
+    }
+
+    // Hand the evidence to another wallet, e.g. from an investigator to the claimant
+    pub fn transfer_claim_evidence(
+        ctx: Context<UpdateClaimEvidence>,
+        new_custodian: Pubkey,
+    ) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let custody = &mut ctx.accounts.evidence_custody;
+        require!(!custody.submitted, ErrorCode::EvidenceAlreadySubmitted);
+
+        custody.custodian = new_custodian;
+        let digest = hash(&custody.proof).to_bytes();
+        append_custody_entry(custody, CustodyAction::Transferred, digest, &mut events)
+    }
+
+    // Create the registry of third parties trusted to attest to losses
+    pub fn initialize_attestor_registry(
+        ctx: Context<InitializeAttestorRegistry>,
+        attestors: Vec<Pubkey>,
+    ) -> Result<()> {
+        require_direct_call(&ctx.accounts.config, ctx.accounts.instructions.as_ref())?;
+        require!(
+            attestors.len() <= MAX_ATTESTORS,
+            ErrorCode::TooManyAttestors
+        );
+        let registry = &mut ctx.accounts.attestor_registry;
+        registry.attestors = attestors;
+        registry.bump = ctx.bumps.attestor_registry;
+        Ok(())
+    }
+
+    // Replace the registered attestors
+    pub fn set_attestors(ctx: Context<SetAttestors>, attestors: Vec<Pubkey>) -> Result<()> {
+        require_direct_call(&ctx.accounts.config, ctx.accounts.instructions.as_ref())?;
+        require!(
+            attestors.len() <= MAX_ATTESTORS,
+            ErrorCode::TooManyAttestors
+        );
+        ctx.accounts.attestor_registry.attestors = attestors;
+        Ok(())
+    }
+
+    // Attest to a wallet's loss ahead of its claim. A newer attestation replaces the
+    // wallet's previous one.
+    pub fn attest_loss(
+        ctx: Context<AttestLoss>,
+        wallet: Pubkey,
+        loss_type: LossType,
+        evidence_hash: [u8; 32],
+        claim_amount: u64,
+    ) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let attestor = ctx.accounts.attestor.key();
+        require!(
+            ctx.accounts.attestor_registry.attestors.contains(&attestor),
+            ErrorCode::NotAttestor
+        );
+        require!(claim_amount > 0, ErrorCode::InvalidAmount);
+
+        let now = Clock::get()?.unix_timestamp;
+        let attestation = &mut ctx.accounts.loss_attestation;
+        attestation.wallet = wallet;
+        attestation.attestor = attestor;
+        attestation.loss_type = loss_type.clone();
+        attestation.evidence_hash = evidence_hash;
+        attestation.claim_amount = claim_amount;
+        attestation.attested_at = now;
+        attestation.expires_at = now + LossAttestation::TTL_SECS;
+        attestation.consumed = false;
+        attestation.bump = ctx.bumps.loss_attestation;
+
+        emit_indexed!(
+            events,
+            wallet,
+            LossAttested {
+                wallet,
+                attestor,
+                loss_type,
+            }
+        );
+        Ok(())
+    }
+
+    // Initiate a claim. Compressed policies pass `cnft_root`, the policy tree's
+    // current root, and the leaf's proof nodes as remaining accounts. Losses visible
+    // only on-chain may pass `slot_anchor`, a recent slot and its hash, which the
+    // off-chain evidence bundle's hash incorporates; it is checked against SlotHashes.
+    pub fn initiate_claim<'info>(
+        ctx: Context<'_, '_, 'info, 'info, InitiateClaim<'info>>,
+        claim_amount: u64,
+        proof: Vec<u8>,
+        cnft_root: Option<[u8; 32]>,
+        slot_anchor: Option<SlotAnchor>,
+    ) -> Result<()> {
+        require_wallet_call(&ctx.accounts.config, ctx.accounts.instructions.as_ref())?;
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        ctx.accounts
+            .config
+            .require_not_paused(ProtocolConfig::PAUSE_CLAIMS)?;
+        let clock = Clock::get()?;
+        check_rate_limit(
+            &mut ctx.accounts.user_account,
+            &ctx.accounts.config,
+            RateLimitedAction::InitiateClaim,
+            clock.unix_timestamp,
+        )?;
+        let insurance_nft = &ctx.accounts.insurance_nft;
+
+        // Verify NFT is valid and not expired
+        require!(!insurance_nft.collateralized, ErrorCode::NftCollateralized);
+        require!(
+            ctx.accounts.user_account.penalty_owed == 0,
+            ErrorCode::ClaimPenaltyOutstanding
+        );
+        require_policy_active(insurance_nft, clock.unix_timestamp)?;
+        require!(
+            logic::claim_within_cap(claim_amount, insurance_nft.payout_cap),
+            ErrorCode::ClaimExceedsCap
+        );
+        require!(proof.len() <= MAX_CLAIM_PROOF_LEN, ErrorCode::ProofTooLarge);
+        if let Some(anchor) = slot_anchor.as_ref() {
+            verify_slot_anchor(ctx.accounts.slot_hashes.as_ref(), anchor, clock.slot)?;
+        }
+
+        // Evidence with a custody log must be submitted by its custodian exactly as logged,
+        // after which the log is sealed
+        if let Some(custody) = ctx.accounts.evidence_custody.as_mut() {
+            submit_custody(custody, &ctx.accounts.user.key(), &proof, &mut events)?;
+        }
+
+        // A fresh attestation covering the amount halves the voting window, once
+        let fast_track = match ctx.accounts.loss_attestation.as_mut() {
+            Some(attestation)
+                if logic::attestation_fast_tracks(
+                    clock.unix_timestamp,
+                    attestation.expires_at,
+                    attestation.consumed,
+                    attestation.claim_amount,
+                    claim_amount,
+                ) =>
+            {
+                attestation.consumed = true;
+                true
+            }
+            _ => false,
+        };
+
+        let claim_number = take_number(&mut ctx.accounts.config.next_claim_number)?;
+        ctx.accounts.claim_number_index.set_inner(ClaimNumberIndex {
+            number: claim_number,
+            claim: ctx.accounts.claim.key(),
+            bump: ctx.bumps.claim_number_index,
+        });
+
+        // Create claim account
+        let claim = &mut ctx.accounts.claim;
+        claim.version = Claim::CURRENT_VERSION;
+        claim.claimant = ctx.accounts.user.key();
+        claim.amount = claim_amount;
+        claim.timestamp = clock.unix_timestamp;
+        claim.status = ClaimStatus::Pending;
+        claim.proof = proof;
+        claim.insurance_nft = insurance_nft.key();
+        claim.pool = ctx.accounts.pool.key();
+        claim.voting_ends_at = clock.unix_timestamp
+            + logic::claim_voting_window(ctx.accounts.governance.voting_duration, fast_track);
+        claim.fast_tracked = fast_track;
+        claim.claim_number = claim_number;
+        claim.expected_resolution_at =
+            clock.unix_timestamp + ctx.accounts.governance.voting_duration;
+        claim.slot_anchor = slot_anchor;
+        ctx.accounts.config.total_claims += 1;
+
+        let user_account = &mut ctx.accounts.user_account;
+        require!(
+            user_account.linked_claims.len() < MAX_LINKED_CLAIMS,
+            ErrorCode::TooManyOpenClaims
+        );
+        user_account.linked_claims.push(claim.key());
+
+        let history = &mut ctx.accounts.claim_history;
+        history.wallet = ctx.accounts.user.key();
+        history.claims_filed += 1;
+        history.last_claim_at = clock.unix_timestamp;
+        history.bump = ctx.bumps.claim_history;
+
+        // Lock the policy: SPL policies move into escrow, compressed ones are delegated to
+        // the escrow PDA, which Bubblegum only allows for the leaf's owner
+        match ctx.accounts.insurance_nft.compressed_leaf.clone() {
+            None => {
+                let (Some(insurance_nft_account), Some(claim_escrow), Some(nft_mint)) = (
+                    ctx.accounts.insurance_nft_account.as_ref(),
+                    ctx.accounts.claim_escrow.as_ref(),
+                    ctx.accounts.nft_mint.as_ref(),
+                ) else {
+                    return err!(ErrorCode::PolicyAccountsMissing);
+                };
+                tokens::transfer(
+                    &ctx.accounts.token_program,
+                    insurance_nft_account.to_account_info(),
+                    claim_escrow.to_account_info(),
+                    nft_mint,
+                    ctx.accounts.user.to_account_info(),
+                    1,
+                    &[],
+                )?;
+            }
+            Some(leaf) => {
+                let (
+                    Some(root),
+                    Some(cnft_escrow),
+                    Some(previous_delegate),
+                    Some(tree_config),
+                    Some(merkle_tree),
+                    Some(log_wrapper),
+                    Some(compression_program),
+                    Some(bubblegum_program),
+                ) = (
+                    cnft_root,
+                    ctx.accounts.cnft_escrow.as_ref(),
+                    ctx.accounts.previous_leaf_delegate.as_ref(),
+                    ctx.accounts.tree_config.as_ref(),
+                    ctx.accounts.merkle_tree.as_ref(),
+                    ctx.accounts.log_wrapper.as_ref(),
+                    ctx.accounts.compression_program.as_ref(),
+                    ctx.accounts.bubblegum_program.as_ref(),
+                )
+                else {
+                    return err!(ErrorCode::PolicyAccountsMissing);
+                };
+                require!(
+                    merkle_tree.key() == leaf.merkle_tree,
+                    ErrorCode::InvalidPolicyLeaf
+                );
+                cnft::delegate_to_escrow(
+                    &cnft::TreeAccounts {
+                        bubblegum_program,
+                        tree_config,
+                        merkle_tree,
+                        log_wrapper,
+                        compression_program,
+                        system_program: &ctx.accounts.system_program,
+                    },
+                    &ctx.accounts.user,
+                    previous_delegate,
+                    cnft_escrow,
+                    ctx.remaining_accounts,
+                    &leaf,
+                    root,
+                )?;
+            }
+        }
+
+        emit_indexed!(
+            events,
+            ctx.accounts.user.key(),
+            ClaimInitiated {
+                wallet: ctx.accounts.user.key(),
+                claim_id: claim.key(),
+                amount: claim_amount,
+                timestamp: clock.unix_timestamp,
+                claim_number,
+                evidence_class: claim.evidence_class(),
+            }
+        );
+        record_activity(
+            ctx.accounts.activity.as_ref(),
+            Some((&ctx.accounts.user, &ctx.accounts.system_program)),
+            ctx.accounts.user.key(),
+            ActivityKind::ClaimInitiated,
+            claim_amount,
+            claim.key(),
+            clock.unix_timestamp,
+        )?;
+
+        Ok(())
+    }
+
+    // Drop a settled claim from the claimant's open claims
+    pub fn unlink_resolved_claim(ctx: Context<UnlinkResolvedClaim>) -> Result<()> {
+        let claim = &ctx.accounts.claim;
+        require!(
+            logic::claim_settled(
+                &claim.status,
+                claim.shortfall,
+                claim.written_off,
+                claim.installments_remaining(),
+            ),
+            ErrorCode::ClaimStillOpen
+        );
+
+        let claim_key = claim.key();
+        ctx.accounts
+            .user_account
+            .linked_claims
+            .retain(|linked| linked != &claim_key);
+        Ok(())
+    }
+
+    // DAO governance vote on claim (v2+)
+    // Vote on a pending claim. Approving voters may propose a reduced `approved_amount`;
+    // once a quorum of votes carry one, the claim is approved for their median. The
+    // vote that reaches that quorum passes the other amount-carrying ClaimVoteRecords
+    // as remaining accounts.
+    pub fn vote_on_claim<'info>(
+        ctx: Context<'_, '_, 'info, 'info, VoteOnClaim<'info>>,
+        claim_id: Pubkey,
+        vote: VoteChoice,
+        approved_amount: Option<u64>,
+    ) -> Result<()> {
+        require_direct_call(&ctx.accounts.config, ctx.accounts.instructions.as_ref())?;
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        ctx.accounts
+            .config
+            .require_not_paused(ProtocolConfig::PAUSE_CLAIMS)?;
+        let claim = &mut ctx.accounts.claim;
+        let governance = &ctx.accounts.governance;
+
+        require!(
+            claim.status == ClaimStatus::Pending,
+            ErrorCode::ClaimNotPending
+        );
+        require!(
+            logic::claim_voting_open(Clock::get()?.unix_timestamp, claim.voting_ends_at),
+            ErrorCode::VotingClosed
+        );
+
+        // Verify governance authority
+        require!(
+            governance.is_authorized(&ctx.accounts.voter.key()),
+            ErrorCode::UnauthorizedGovernance
+        );
+
+        // One record per voter; it also makes the voter eligible for vote incentives
+        let vote_record = &mut ctx.accounts.vote_record;
+        vote_record.claim = claim.key();
+        vote_record.voter = ctx.accounts.voter.key();
+        vote_record.vote_direction = vote.clone();
+        vote_record.approved_amount = approved_amount;
+        vote_record.bump = ctx.bumps.vote_record;
+        vote_record.voted_at = Clock::get()?.unix_timestamp;
+        claim.voter_count += 1;
+
+        // A vote weighs the voter's claim-voting stake, discounted while it is young
+        let stake = &ctx.accounts.stake;
+        require!(stake.amount > 0, ErrorCode::NoClaimVoteStake);
+        let (days_staked, weight) = stake.vote_weight(vote_record.voted_at);
+        emit_seq!(
+            events,
+            ClaimVoteCastWithDecay {
+                claim_id,
+                voter: ctx.accounts.voter.key(),
+                nominal_weight: stake.amount,
+                effective_weight: weight,
+                days_staked,
+            }
+        );
+
+        if let Some(amount) = approved_amount {
+            require!(
+                vote == VoteChoice::Approve,
+                ErrorCode::InvalidApprovedAmount
+            );
+            require!(
+                amount > 0 && amount <= claim.amount,
+                ErrorCode::InvalidApprovedAmount
+            );
+            claim.partial_votes += 1;
+        }
+
+        // Process vote. Abstentions count toward quorum but toward neither outcome.
+        match vote {
+            VoteChoice::Approve => claim.approval_votes += weight,
+            VoteChoice::Reject => claim.rejection_votes += weight,
+            VoteChoice::Abstain => claim.abstain_weight += weight,
+        }
+
+        if claim.partial_votes >= governance.quorum {
+            let mut amounts = proposed_claim_amounts(claim, ctx.remaining_accounts)?;
+            amounts.extend(approved_amount);
+            require!(
+                amounts.len() as u64 == claim.partial_votes,
+                ErrorCode::InvalidRemainingAccounts
+            );
+            claim.approved_amount = logic::median_amount(&mut amounts);
+        }
+
+        tally_claim_votes(
+            claim,
+            governance,
+            ctx.accounts.staking.quorum_weight,
+            &mut ctx.accounts.pool,
+            &mut ctx.accounts.config,
+            &mut events,
+        )?;
+
+        let timestamp = Clock::get()?.unix_timestamp;
+        emit_seq!(
+            events,
+            ClaimVoted {
+                claim_id,
+                voter: ctx.accounts.voter.key(),
+                vote: logic::vote_choice_label(&vote).to_string(),
+                approved_amount,
+                timestamp,
+                evidence_class: claim.evidence_class(),
+            }
+        );
+        if claim.status == ClaimStatus::PartiallyApproved {
+            emit_seq!(
+                events,
+                ClaimPartiallyApproved {
+                    claim_id,
+                    requested: claim.amount,
+                    approved: claim.approved_amount,
+                    timestamp,
+                }
+            );
+        }
+        if claim.status != ClaimStatus::Pending {
+            emit_cpi_seq!(
+                ctx,
+                events,
+                ClaimResolved {
+                    claim_id,
+                    status: claim.status.clone(),
+                    payable_amount: claim.payable_amount(),
+                    timestamp,
+                    claim_number: claim.claim_number,
+                }
+            );
+        }
+
+        Ok(())
+    }
+
+    // Reject a provably false claim without a vote. Signed by an invalidator oracle;
+    // the claimant owes false_claim_penalty_bps of the claimed amount before filing again.
+    pub fn invalidate_claim(
+        ctx: Context<InvalidateClaim>,
+        claim_id: Pubkey,
+        reason_hash: [u8; 32],
+    ) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let oracle = ctx.accounts.oracle.key();
+        require!(
+            logic::may_invalidate_claims(ctx.accounts.oracle_registry.role_of(&oracle)),
+            ErrorCode::NotInvalidatorOracle
+        );
+        let claim = &mut ctx.accounts.claim;
+        require!(claim.key() == claim_id, ErrorCode::ClaimMismatch);
+
+        resolve_claim(
+            claim,
+            ClaimStatus::Rejected,
+            &mut ctx.accounts.pool,
+            &mut ctx.accounts.config,
+            &mut events,
+        )?;
+        let penalty =
+            logic::false_claim_penalty(claim.amount, ctx.accounts.config.false_claim_penalty_bps);
+        let claimant = &mut ctx.accounts.claimant_account;
+        claimant.penalty_owed = claimant
+            .penalty_owed
+            .checked_add(penalty)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit_indexed!(
+            events,
+            claim.claimant,
+            ClaimAutoInvalidated {
+                claim_id,
+                oracle,
+                reason_hash,
+                penalty,
+            }
+        );
+        Ok(())
+    }
+
+    // Settle the penalty from an invalidated claim into the treasury
+    pub fn pay_claim_penalty(ctx: Context<PayClaimPenalty>) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let amount = ctx.accounts.user_account.penalty_owed;
+        require!(amount > 0, ErrorCode::NoPenaltyOwed);
+
+        tokens::transfer_in(
+            &ctx.accounts.token_program,
+            &ctx.accounts.payment_account,
+            &mut ctx.accounts.treasury_account,
+            &ctx.accounts.payment_mint,
+            ctx.accounts.user.to_account_info(),
+            amount,
+        )?;
+        let wallet = ctx.accounts.user_account.wallet;
+        treasury::credit(
+            &mut ctx.accounts.treasury,
+            &mut ctx.accounts.config,
+            &mut events,
+            wallet,
+            amount,
+            LedgerInstruction::PayClaimPenalty,
+        )?;
+        ctx.accounts.user_account.penalty_owed = 0;
+
+        emit_indexed!(events, wallet, ClaimPenaltyPaid { wallet, amount });
+        Ok(())
+    }
+
+    // Reject a claim whose voting window closed without a decision, returning the policy
+    pub fn close_claim_voting(ctx: Context<CloseClaimVoting>) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let claim = &mut ctx.accounts.claim;
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            claim.status == ClaimStatus::Pending,
+            ErrorCode::ClaimNotPending
+        );
+        require!(
+            claim.voting_ends_at != 0 && !logic::claim_voting_open(now, claim.voting_ends_at),
+            ErrorCode::ClaimVotingStillOpen
+        );
+
+        resolve_claim(
+            claim,
+            ClaimStatus::Rejected,
+            &mut ctx.accounts.pool,
+            &mut ctx.accounts.config,
+            &mut events,
+        )?;
+        emit_seq!(
+            events,
+            ClaimVotingClosed {
+                claim_id: claim.key(),
+                timestamp: now,
+            }
+        );
+        emit_cpi_seq!(
+            ctx,
+            events,
+            ClaimResolved {
+                claim_id: claim.key(),
+                status: ClaimStatus::Rejected,
+                payable_amount: 0,
+                timestamp: now,
+                claim_number: claim.claim_number,
+            }
+        );
+        Ok(())
+    }
+
+    // Pay each voter on a resolved claim an equal slice of vote_incentive_per_claim in
+    // IRIS from the vote incentive vault. Remaining accounts: (ClaimVoteRecord, voter
+    // IRIS token account) per item (stride VOTE_INCENTIVE_BATCH_STRIDE); voters may be
+    // split across calls. Division dust stays in the
+    // vault, and payouts stop once max_vote_incentive_total has been paid overall.
+    pub fn distribute_vote_incentives<'info>(
+        ctx: Context<'_, '_, 'info, 'info, DistributeVoteIncentives<'info>>,
+        claim_id: Pubkey,
+    ) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        batch_items(&ctx, ProtocolConfig::VOTE_INCENTIVE_BATCH_STRIDE)?;
+
+        let claim = &ctx.accounts.claim;
+        require!(claim.key() == claim_id, ErrorCode::ClaimIdMismatch);
+        require!(
+            claim.status != ClaimStatus::Pending,
+            ErrorCode::ClaimNotResolved
+        );
+
+        let config = &mut ctx.accounts.config;
+        let share = logic::vote_incentive_share(config.vote_incentive_per_claim, claim.voter_count);
+        let bump = [ctx.accounts.governance.bump];
+        let signer_seeds: &[&[&[u8]]] = &[&[b"governance", &bump]];
+
+        for pair in ctx
+            .remaining_accounts
+            .chunks(ProtocolConfig::VOTE_INCENTIVE_BATCH_STRIDE)
+        {
+            let mut record = Account::<ClaimVoteRecord>::try_from(&pair[0])?;
+            let destination = InterfaceAccount::<TokenAccount>::try_from(&pair[1])?;
+
+            require!(
+                pair[0].is_writable && pair[1].is_writable,
+                ErrorCode::InvalidRemainingAccounts
+            );
+            require!(
+                record.claim == claim_id,
+                ErrorCode::InvalidRemainingAccounts
+            );
+            require!(
+                destination.owner == record.voter,
+                ErrorCode::InvalidTokenOwner
+            );
+            require!(
+                destination.mint == ctx.accounts.vault.mint,
+                ErrorCode::InvalidMint
+            );
+            if record.incentive_paid {
+                continue;
+            }
+
+            // Votes cast in the last tenth of the window earn a reduced share
+            let late = logic::late_vote(record.voted_at, claim.timestamp, claim.voting_ends_at);
+            let budget_left = config
+                .max_vote_incentive_total
+                .saturating_sub(config.vote_incentives_paid);
+            let amount = logic::vote_incentive_after_penalty(share, late).min(budget_left);
+            if amount == 0 {
+                break;
+            }
+
+            tokens::transfer(
+                &ctx.accounts.token_program,
+                ctx.accounts.vault.to_account_info(),
+                pair[1].clone(),
+                &ctx.accounts.incentive_mint,
+                ctx.accounts.governance.to_account_info(),
+                amount,
+                signer_seeds,
+            )?;
+
+            record.incentive_paid = true;
+            record.exit(&crate::ID)?;
+            config.vote_incentives_paid += amount;
+
+            emit_seq!(
+                events,
+                VoteIncentiveDistributed {
+                    claim_id,
+                    voter: record.voter,
+                    amount,
+                    late,
+                }
+            );
+        }
+
+        Ok(())
+    }
+
+    // Set the IRIS paid to the voters of each resolved claim and the lifetime cap on
+    // vote incentives
+    pub fn set_vote_incentives(
+        ctx: Context<SetSubPoolMode>,
+        vote_incentive_per_claim: u64,
+        max_vote_incentive_total: u64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts
+                .governance
+                .is_authorized(&ctx.accounts.authority.key()),
+            ErrorCode::UnauthorizedGovernance
+        );
+
+        let config = &mut ctx.accounts.config;
+        config.vote_incentive_per_claim = vote_incentive_per_claim;
+        config.max_vote_incentive_total = max_vote_incentive_total;
+        Ok(())
+    }
+
+    // Set how many oracles (primary included) the registry may hold
+    pub fn update_max_oracle_count(ctx: Context<SetSubPoolMode>, new_max: u8) -> Result<()> {
+        require!(
+            ctx.accounts
+                .governance
+                .is_authorized(&ctx.accounts.authority.key()),
+            ErrorCode::UnauthorizedGovernance
+        );
+        require!(new_max >= 1, ErrorCode::InvalidAmount);
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+
+        let config = &mut ctx.accounts.config;
+        let old_cap = config.max_oracle_count;
+        config.max_oracle_count = new_max;
+
+        emit_seq!(
+            events,
+            OracleRegistryCapUpdated {
+                old_cap,
+                new_cap: new_max,
+            }
+        );
+        Ok(())
+    }
+
+    // Create the governance-owned IRIS vault that funds vote incentives
+    pub fn initialize_vote_incentive_vault(
+        ctx: Context<InitializeVoteIncentiveVault>,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts
+                .governance
+                .is_authorized(&ctx.accounts.authority.key()),
+            ErrorCode::UnauthorizedGovernance
+        );
+        Ok(())
+    }
+
+    // Create the protocol-wide configuration account
+    pub fn initialize_config(ctx: Context<InitializeConfig>, protocol_chain_id: u8) -> Result<()> {
+        // Only the key that can upgrade the program may claim its config
+        let upgrade_authority = ctx.accounts.program_data.upgrade_authority_address;
+        require!(
+            logic::is_upgrade_authority(upgrade_authority, ctx.accounts.admin.key()),
+            ErrorCode::AdminNotUpgradeAuthority
+        );
+
+        let config = &mut ctx.accounts.config;
+        config.admin = ctx.accounts.admin.key();
+        config.protocol_chain_id = protocol_chain_id;
+        config.max_score_delta_per_update = ProtocolConfig::DEFAULT_MAX_SCORE_DELTA;
+        config.oracle_disagreement_threshold =
+            ProtocolConfig::DEFAULT_ORACLE_DISAGREEMENT_THRESHOLD;
+        config.coverage_gap_alert_days = ProtocolConfig::DEFAULT_COVERAGE_GAP_ALERT_DAYS;
+        config.max_oracle_count = ProtocolConfig::DEFAULT_MAX_ORACLE_COUNT;
+        config.subscription_grace_secs = ProtocolConfig::DEFAULT_SUBSCRIPTION_GRACE_SECS;
+        config.min_subscription_age_for_governance_secs =
+            ProtocolConfig::DEFAULT_MIN_SUBSCRIPTION_AGE_FOR_GOVERNANCE_SECS;
+        config.cpi_guard_enabled = true;
+        config.lp_lockup_secs = ProtocolConfig::DEFAULT_LP_LOCKUP_SECS;
+        config.tiers = ProtocolConfig::DEFAULT_TIERS;
+        config.min_pool_epoch_secs = ProtocolConfig::DEFAULT_POOL_EPOCH_SECS;
+        config.max_withdrawal_bps_per_epoch = 10_000;
+        config.installment_count = ProtocolConfig::DEFAULT_INSTALLMENT_COUNT;
+        config.next_policy_number = 1;
+        config.next_claim_number = 1;
+        config.keeper_fee_caps = ProtocolConfig::DEFAULT_KEEPER_FEE_CAPS;
+        config.marketplace_fee_bps = ProtocolConfig::DEFAULT_MARKETPLACE_FEE_BPS;
+        config.protection_cu_baselines = ProtocolConfig::DEFAULT_PROTECTION_CU_BASELINES;
+        config.false_claim_penalty_bps = ProtocolConfig::DEFAULT_FALSE_CLAIM_PENALTY_BPS;
+        config.bump = ctx.bumps.config;
+
+        let mut events = next_event_seq(config)?;
+        emit_seq!(
+            events,
+            ConfigInitialized {
+                admin: config.admin,
+                upgrade_authority,
+                protocol_chain_id,
+            }
+        );
+        Ok(())
+    }
+
+    // Admin path for config fields that don't need a governance vote
+    pub fn set_config_param(ctx: Context<SetConfigParam>, param: ConfigParam) -> Result<()> {
+        require_direct_call(&ctx.accounts.config, ctx.accounts.instructions.as_ref())?;
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let config = &mut ctx.accounts.config;
+        match param {
+            ConfigParam::PauseFlags(flags) => {
+                require!(
+                    flags & !ProtocolConfig::PAUSE_ALL == 0,
+                    ErrorCode::InvalidPauseFlags
+                );
+                config.paused = flags;
+            }
+            ConfigParam::MaxScoreDeltaPerUpdate(delta) => {
+                require!(delta > 0 && delta <= 100, ErrorCode::InvalidAmount);
+                config.max_score_delta_per_update = delta;
+            }
+            ConfigParam::RebateBps(bps) => {
+                require!(bps <= 10_000, ErrorCode::InvalidBps);
+                config.rebate_bps = bps;
+            }
+            ConfigParam::OracleDisagreementThreshold(threshold) => {
+                require!(threshold <= 100, ErrorCode::InvalidAmount);
+                config.oracle_disagreement_threshold = threshold;
+            }
+            ConfigParam::CoverageGapAlertDays(days) => {
+                config.coverage_gap_alert_days = days;
+            }
+            ConfigParam::SubscriptionGraceSecs(secs) => {
+                require!(secs >= 0, ErrorCode::InvalidDuration);
+                config.subscription_grace_secs = secs;
+            }
+            ConfigParam::PolicyWaitingSecs(secs) => {
+                require!(secs >= 0, ErrorCode::InvalidDuration);
+                config.policy_waiting_secs = secs;
+            }
+            ConfigParam::MinSubscriptionAgeForGovernanceSecs(secs) => {
+                require!(secs >= 0, ErrorCode::InvalidDuration);
+                config.min_subscription_age_for_governance_secs = secs;
+            }
+            ConfigParam::AutomationProgram(program) => {
+                config.thread_config.automation_program = program;
+            }
+            ConfigParam::CompressedActionLogs(compressed) => {
+                require!(
+                    !compressed || config.action_log_tree.merkle_tree != Pubkey::default(),
+                    ErrorCode::ActionLogTreeNotInitialized
+                );
+                config.action_log_tree.compressed = compressed;
+            }
+            ConfigParam::InstallmentThreshold(threshold) => {
+                config.installment_threshold = threshold;
+            }
+            ConfigParam::InstallmentCount(count) => {
+                require!(
+                    (2..=ProtocolConfig::MAX_INSTALLMENTS).contains(&count),
+                    ErrorCode::InvalidInstallmentCount
+                );
+                config.installment_count = count;
+            }
+            ConfigParam::RateLimit(action, limit) => {
+                require!(limit.window_secs >= 0, ErrorCode::InvalidDuration);
+                config.rate_limits[action as usize] = limit;
+            }
+            ConfigParam::QuoteAuthority(authority) => {
+                config.quote_authority = authority;
+            }
+            ConfigParam::TvlFeed(feed) => {
+                config.tvl_feed = feed;
+            }
+            ConfigParam::KeeperFeeCaps(caps) => {
+                config.keeper_fee_caps = caps;
+            }
+            ConfigParam::MarketplaceFeeBps(bps) => {
+                require!(bps <= 10_000, ErrorCode::InvalidBps);
+                config.marketplace_fee_bps = bps;
+            }
+            ConfigParam::ProtectionCuBaselines(baselines) => {
+                let units = [
+                    baselines.swap,
+                    baselines.freeze,
+                    baselines.alert,
+                    baselines.claim,
+                ];
+                require!(
+                    units
+                        .iter()
+                        .all(|u| *u > 0 && *u <= compute_budget::MAX_TRANSACTION_UNITS),
+                    ErrorCode::InvalidAmount
+                );
+                config.protection_cu_baselines = baselines;
+            }
+            ConfigParam::FalseClaimPenaltyBps(bps) => {
+                require!(bps <= 10_000, ErrorCode::InvalidBps);
+                config.false_claim_penalty_bps = bps;
+            }
+        }
+
+        emit_seq!(
+            events,
+            ConfigParamUpdated {
+                param,
+                admin: ctx.accounts.admin.key(),
+            }
+        );
+        Ok(())
+    }
+
+    // Point compressed action logging at a fresh tree. The admin allocates `merkle_tree`
+    // for the compression program beforehand; the protocol's log authority PDA becomes
+    // its only writer. Logging stays uncompressed until enabled via set_config_param.
+    pub fn init_action_log_tree(
+        ctx: Context<InitActionLogTree>,
+        max_depth: u32,
+        max_buffer_size: u32,
+    ) -> Result<()> {
+        require_direct_call(&ctx.accounts.config, ctx.accounts.instructions.as_ref())?;
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let bump = [ctx.bumps.log_authority];
+        compression::init_tree(
+            &ctx.accounts.compression_program,
+            &ctx.accounts.merkle_tree,
+            &ctx.accounts.log_authority,
+            &ctx.accounts.noop_program,
+            &[&[compression::LOG_AUTHORITY_SEED, &bump]],
+            max_depth,
+            max_buffer_size,
+        )?;
+
+        let tree = &mut ctx.accounts.config.action_log_tree;
+        tree.merkle_tree = ctx.accounts.merkle_tree.key();
+        tree.leaf_count = 0;
+        emit_seq!(
+            events,
+            ActionLogTreeInitialized {
+                merkle_tree: tree.merkle_tree,
+                max_depth,
+                max_buffer_size,
+            }
+        );
+        Ok(())
+    }
+
+    // Check that `entry` is in the action log tree under `root`, e.g. while adjudicating
+    // a claim. The proof nodes are passed as remaining accounts, leaf to root.
+    pub fn verify_action_log<'info>(
+        ctx: Context<'_, '_, 'info, 'info, VerifyActionLog<'info>>,
+        root: [u8; 32],
+        entry: ActionLogEntry,
+    ) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let index =
+            u32::try_from(entry.leaf_index).map_err(|_| ErrorCode::InvalidActionLogProof)?;
+        let leaf = compression::leaf_hash(&entry)?;
+        compression::verify_leaf(
+            &ctx.accounts.compression_program,
+            &ctx.accounts.merkle_tree,
+            ctx.remaining_accounts,
+            root,
+            leaf,
+            index,
+        )?;
+
+        emit_indexed!(
+            events,
+            entry.wallet,
+            ActionLogVerified {
+                wallet: entry.wallet,
+                merkle_tree: ctx.accounts.merkle_tree.key(),
+                leaf_index: entry.leaf_index,
+                timestamp: entry.timestamp,
+            }
+        );
+        Ok(())
+    }
+
+    // Start a two-step admin handover. The current admin keeps full control until the
+    // nominee accepts.
+    pub fn nominate_admin(ctx: Context<SetConfigParam>, new_admin: Pubkey) -> Result<()> {
+        require_direct_call(&ctx.accounts.config, ctx.accounts.instructions.as_ref())?;
+        require!(
+            new_admin != ctx.accounts.config.admin,
+            ErrorCode::InvalidAdmin
+        );
+        ctx.accounts.config.pending_admin = Some(new_admin);
+        Ok(())
+    }
+
+    // Complete the handover; must be signed by the nominated key
+    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+        require_direct_call(&ctx.accounts.config, ctx.accounts.instructions.as_ref())?;
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let config = &mut ctx.accounts.config;
+        require!(
+            config.pending_admin == Some(ctx.accounts.new_admin.key()),
+            ErrorCode::NotPendingAdmin
+        );
+        require!(
+            logic::is_upgrade_authority(
+                ctx.accounts.program_data.upgrade_authority_address,
+                ctx.accounts.new_admin.key()
+            ),
+            ErrorCode::AdminNotUpgradeAuthority
+        );
+
+        let previous_admin = config.admin;
+        config.admin = ctx.accounts.new_admin.key();
+        config.pending_admin = None;
+
+        emit_seq!(
+            events,
+            AdminChanged {
+                previous_admin,
+                new_admin: config.admin,
+            }
+        );
+        Ok(())
+    }
+
+    pub fn cancel_admin_nomination(ctx: Context<SetConfigParam>) -> Result<()> {
+        require_direct_call(&ctx.accounts.config, ctx.accounts.instructions.as_ref())?;
+        require!(
+            ctx.accounts.config.pending_admin.is_some(),
+            ErrorCode::NotPendingAdmin
+        );
+        ctx.accounts.config.pending_admin = None;
+        Ok(())
+    }
+
+    // Create the protocol governance account
+    pub fn initialize_governance(
+        ctx: Context<InitializeGovernance>,
+        quorum: u64,
+        voting_duration: i64,
+        timelock_duration: i64,
+        emergency_quorum: u64,
+    ) -> Result<()> {
+        require!(timelock_duration >= 0, ErrorCode::InvalidDuration);
+        // Emergency votes are short, so they must clear a higher bar
+        require!(
+            emergency_quorum >= quorum,
+            ErrorCode::InvalidEmergencyQuorum
+        );
+
+        let governance = &mut ctx.accounts.governance;
+        governance.authority = ctx.accounts.admin.key();
+        governance.quorum = quorum;
+        governance.voting_duration = voting_duration;
+        governance.timelock_duration = timelock_duration;
+        governance.emergency_quorum = emergency_quorum;
+        governance.bump = ctx.bumps.governance;
+        Ok(())
+    }
+
+    // Change the claim and proposal quorums. Not while any proposal is being voted on,
+    // so a vote can't be carried by moving its quorum.
+    pub fn update_governance_quorum(
+        ctx: Context<UpdateGovernanceParams>,
+        quorum: u64,
+        emergency_quorum: u64,
+    ) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let governance = &mut ctx.accounts.governance;
+        require!(
+            governance.is_authorized(&ctx.accounts.authority.key()),
+            ErrorCode::UnauthorizedGovernance
+        );
+        let voting_duration = governance.voting_duration;
+        apply_governance_params(
+            governance,
+            quorum,
+            emergency_quorum,
+            voting_duration,
+            None,
+            &mut events,
+        )
+    }
+
+    // Change how long proposals and claims stay open for votes; likewise not during a vote
+    pub fn update_voting_duration(
+        ctx: Context<UpdateGovernanceParams>,
+        voting_duration: i64,
+    ) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let governance = &mut ctx.accounts.governance;
+        require!(
+            governance.is_authorized(&ctx.accounts.authority.key()),
+            ErrorCode::UnauthorizedGovernance
+        );
+        let (quorum, emergency_quorum) = (governance.quorum, governance.emergency_quorum);
+        apply_governance_params(
+            governance,
+            quorum,
+            emergency_quorum,
+            voting_duration,
+            None,
+            &mut events,
+        )
+    }
+
+    // Create the insurance pool and its payout vault. Tier 0 is the shared pool;
+    // tiers 1-3 are the isolated sub-pools used when config.isolated_tiers is set.
+    pub fn initialize_pool(ctx: Context<InitializePool>, tier: u8) -> Result<()> {
+        require!(
+            ctx.accounts
+                .governance
+                .is_authorized(&ctx.accounts.authority.key()),
+            ErrorCode::UnauthorizedGovernance
+        );
+        require!(
+            tier as usize <= ProtocolConfig::DEFAULT_TIERS.len(),
+            ErrorCode::InvalidTier
+        );
+
+        let pool = &mut ctx.accounts.pool;
+        pool.tier = tier;
+        pool.payout_mint = ctx.accounts.payout_mint.key();
+        pool.vault = ctx.accounts.vault.key();
+        pool.bump = ctx.bumps.pool;
+
+        let now = Clock::get()?.unix_timestamp;
+        pool.bonus_epoch_started_at = now;
+        pool.epoch_started_at = now;
+        pool.weight_updated_at = now;
+        Ok(())
+    }
+
+    // Set the premium rate curve for policies of a tier
+    pub fn set_tier_config(
+        ctx: Context<SetTierConfig>,
+        tier: u8,
+        tier_config: TierConfig,
+    ) -> Result<()> {
+        require_direct_call(&ctx.accounts.config, ctx.accounts.instructions.as_ref())?;
+        require!(
+            tier_config.base_rate_bps <= 10_000
+                && tier_config.kink_utilization_bps <= 10_000
+                && tier_config.slope_below_kink_bps <= 10_000
+                && tier_config.slope_above_kink_bps <= 10_000,
+            ErrorCode::InvalidBps
+        );
+        *ctx.accounts.config.tier_mut(tier)? = tier_config;
+        Ok(())
+    }
+
+    // Set the governance token and burn rate used for fee waivers
+    pub fn set_fee_waiver(
+        ctx: Context<SetFeeWaiver>,
+        governance_mint: Pubkey,
+        fee_waiver_burn_rate: u64,
+    ) -> Result<()> {
+        require_direct_call(&ctx.accounts.config, ctx.accounts.instructions.as_ref())?;
+        let config = &mut ctx.accounts.config;
+        config.governance_mint = governance_mint;
+        config.fee_waiver_burn_rate = fee_waiver_burn_rate;
+        Ok(())
+    }
+
+    // Set the share of the plan price refunded to claim-free subscribers
+    pub fn set_rebate_bps(ctx: Context<SetRebateBps>, rebate_bps: u16) -> Result<()> {
+        require_direct_call(&ctx.accounts.config, ctx.accounts.instructions.as_ref())?;
+        require!(rebate_bps <= 10_000, ErrorCode::InvalidBps);
+        ctx.accounts.config.rebate_bps = rebate_bps;
+        Ok(())
+    }
+
+    // Set the minimum length of a pool reward epoch
+    pub fn set_pool_epoch_length(
+        ctx: Context<SetPoolEpochLength>,
+        min_pool_epoch_secs: i64,
+    ) -> Result<()> {
+        require_direct_call(&ctx.accounts.config, ctx.accounts.instructions.as_ref())?;
+        require!(min_pool_epoch_secs > 0, ErrorCode::InvalidDuration);
+        ctx.accounts.config.min_pool_epoch_secs = min_pool_epoch_secs;
+        Ok(())
+    }
+
+    // Cap how much an LP can withdraw per pool epoch: max_withdrawal_bps_per_epoch of the
+    // position's own shares, and optionally max_pool_withdrawal_bps_per_epoch of the pool's
+    // assets across all LPs (0 disables the pool-wide cap)
+    pub fn set_withdrawal_limits(
+        ctx: Context<SetPoolEpochLength>,
+        max_withdrawal_bps_per_epoch: u16,
+        max_pool_withdrawal_bps_per_epoch: u16,
+    ) -> Result<()> {
+        require_direct_call(&ctx.accounts.config, ctx.accounts.instructions.as_ref())?;
+        require!(
+            max_withdrawal_bps_per_epoch > 0
+                && max_withdrawal_bps_per_epoch <= 10_000
+                && max_pool_withdrawal_bps_per_epoch <= 10_000,
+            ErrorCode::InvalidBps
+        );
+        let config = &mut ctx.accounts.config;
+        config.max_withdrawal_bps_per_epoch = max_withdrawal_bps_per_epoch;
+        config.max_pool_withdrawal_bps_per_epoch = max_pool_withdrawal_bps_per_epoch;
+        Ok(())
+    }
+
+    // Set how long deposits are locked and the penalty for withdrawing before then
+    pub fn set_lp_lockup(
+        ctx: Context<SetPoolEpochLength>,
+        lp_lockup_secs: i64,
+        early_withdrawal_penalty_bps: u16,
+    ) -> Result<()> {
+        require_direct_call(&ctx.accounts.config, ctx.accounts.instructions.as_ref())?;
+        require!(lp_lockup_secs >= 0, ErrorCode::InvalidAmount);
+        require!(
+            early_withdrawal_penalty_bps <= 10_000,
+            ErrorCode::InvalidBps
+        );
+        let config = &mut ctx.accounts.config;
+        config.lp_lockup_secs = lp_lockup_secs;
+        config.early_withdrawal_penalty_bps = early_withdrawal_penalty_bps;
+        Ok(())
+    }
+
+    // Configure the bonus-share curve for deposits made while utilization is high
+    pub fn set_lp_bonus_config(
+        ctx: Context<SetLpBonusConfig>,
+        lp_bonus: LpBonusConfig,
+    ) -> Result<()> {
+        require_direct_call(&ctx.accounts.config, ctx.accounts.instructions.as_ref())?;
+        require!(
+            lp_bonus.utilization_threshold_bps <= 10_000
+                && lp_bonus.min_bonus_bps <= lp_bonus.max_bonus_bps
+                && lp_bonus.max_bonus_bps <= 10_000,
+            ErrorCode::InvalidBps
+        );
+        require!(lp_bonus.epoch_secs > 0, ErrorCode::InvalidDuration);
+        ctx.accounts.config.lp_bonus = lp_bonus;
+        Ok(())
+    }
+
+    // Deposit payout tokens into the pool for shares, with bonus shares when reserves are thin
+    pub fn deposit_to_pool(ctx: Context<DepositToPool>, amount: u64) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        require!(amount > 0, ErrorCode::InvalidAmount);
+
+        let clock = Clock::get()?;
+        let lp_bonus = ctx.accounts.config.lp_bonus.clone();
+        let pool = &mut ctx.accounts.pool;
+        let position = &mut ctx.accounts.position;
+        let assets = ctx.accounts.vault.amount;
+
+        let minted =
+            pool::shares_for_deposit(amount, pool::net_assets(pool, assets), pool.total_shares)
+                .ok_or(ErrorCode::PoolInsolvent)?;
+        let (shares, dead_shares) =
+            pool::depositor_shares(minted, pool.total_shares).ok_or(ErrorCode::DepositTooSmall)?;
+
+        if position.owner == Pubkey::default() {
+            position.owner = ctx.accounts.depositor.key();
+            position.pool = pool.key();
+            position.last_update_epoch = pool.current_epoch;
+            position.last_update_ts = clock.unix_timestamp;
+            position.bump = ctx.bumps.position;
+        }
+        pool::accrue_pool_weight(pool, clock.unix_timestamp)?;
+        pool::settle_position(
+            pool,
+            position,
+            ctx.accounts.last_epoch.as_deref(),
+            clock.unix_timestamp,
+        )?;
+
+        // Bonus shares are budgeted per epoch so dilution of existing LPs stays bounded
+        if clock.unix_timestamp >= pool.bonus_epoch_started_at + lp_bonus.epoch_secs {
+            pool.bonus_epoch_started_at = clock.unix_timestamp;
+            pool.bonus_shares_this_epoch = 0;
+        }
+        let utilization_bps = pool::utilization_bps(pool, assets);
+        let bonus_bps = pool::deposit_bonus_bps(utilization_bps, &lp_bonus);
+        let budget_left = lp_bonus
+            .epoch_bonus_budget
+            .saturating_sub(pool.bonus_shares_this_epoch);
+        let bonus_shares = pool::bonus_shares(shares, bonus_bps, budget_left);
+        pool.bonus_shares_this_epoch += bonus_shares;
+
+        tokens::transfer_in(
+            &ctx.accounts.token_program,
+            &ctx.accounts.depositor_token_account,
+            &mut ctx.accounts.vault,
+            &ctx.accounts.payout_mint,
+            ctx.accounts.depositor.to_account_info(),
+            amount,
+        )?;
+
+        // Every deposit restarts the lockup on the whole position
+        position.deposit_timestamp = clock.unix_timestamp;
+        position.lockup_period = ctx.accounts.config.lp_lockup_secs;
+
+        let credited = shares + bonus_shares;
+        position.shares = position
+            .shares
+            .checked_add(credited)
+            .ok_or(ErrorCode::MathOverflow)?;
+        pool.total_shares = pool
+            .total_shares
+            .checked_add(credited + dead_shares)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit_seq!(
+            events,
+            PoolDeposit {
+                ledger_seq: next_ledger_seq(&mut ctx.accounts.config)?,
+                instruction: LedgerInstruction::DepositToPool,
+                mint: pool.payout_mint,
+                counterparty: ctx.accounts.depositor.key(),
+                amount,
+                shares,
+                bonus_shares,
+                utilization_bps,
+                timestamp: clock.unix_timestamp,
+            }
+        );
+
+        Ok(())
+    }
+
+    // Donate payout tokens to the pool without taking shares, so the whole amount
+    // accrues to existing LPs and improves collateralization
+    pub fn donate_to_pool(ctx: Context<DonateToPool>, amount: u64) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        require!(
+            pool::accepts_donations(&ctx.accounts.pool),
+            ErrorCode::PoolSupplyTooLow
+        );
+
+        let clock = Clock::get()?;
+        tokens::transfer_in(
+            &ctx.accounts.token_program,
+            &ctx.accounts.donor_token_account,
+            &mut ctx.accounts.vault,
+            &ctx.accounts.payout_mint,
+            ctx.accounts.donor.to_account_info(),
+            amount,
+        )?;
+
+        let pool = &mut ctx.accounts.pool;
+        pool.total_donations = pool
+            .total_donations
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit_seq!(
+            events,
+            PoolDeposit {
+                ledger_seq: next_ledger_seq(&mut ctx.accounts.config)?,
+                instruction: LedgerInstruction::DonateToPool,
+                mint: pool.payout_mint,
+                counterparty: ctx.accounts.donor.key(),
+                amount,
+                shares: 0,
+                bonus_shares: 0,
+                utilization_bps: pool::utilization_bps(
+                    pool,
+                    ctx.accounts.vault.amount.saturating_add(amount)
+                ),
+                timestamp: clock.unix_timestamp,
+            }
+        );
+        emit_seq!(
+            events,
+            PoolDonation {
+                donor: ctx.accounts.donor.key(),
+                amount,
+            }
+        );
+
+        Ok(())
+    }
+
+    // Burn pool shares for their share of net assets. Requests are added to any shares
+    // still queued from an earlier epoch; whatever the per-epoch limits don't allow stays
+    // queued and is filled by the next call once the pool epoch advances.
+    pub fn withdraw_from_pool(ctx: Context<WithdrawFromPool>, shares: u64) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        require!(
+            ctx.accounts
+                .position
+                .lockup_elapsed(Clock::get()?.unix_timestamp),
+            ErrorCode::LpLockupNotElapsed
+        );
+        withdraw_lp_shares(ctx.accounts, shares, 0, &mut events)
+    }
+
+    // Withdraw before the deposit lockup has elapsed. Costs early_withdrawal_penalty_bps
+    // of the payout, which stays in the pool for the remaining LPs; once the lockup is
+    // over this behaves like withdraw_from_pool.
+    pub fn request_early_withdrawal(
+        ctx: Context<WithdrawFromPool>,
+        shares: u64,
+        penalty_accepted: bool,
+    ) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        require!(
+            penalty_accepted,
+            ErrorCode::EarlyWithdrawalPenaltyNotAccepted
+        );
+
+        let penalty_bps = if ctx
+            .accounts
+            .position
+            .lockup_elapsed(Clock::get()?.unix_timestamp)
+        {
+            0
+        } else {
+            ctx.accounts.config.early_withdrawal_penalty_bps
+        };
+        withdraw_lp_shares(ctx.accounts, shares, penalty_bps, &mut events)
+    }
+
+    // Close the current pool epoch and make its premium income claimable by LPs.
+    // Permissionless; anyone may crank once the minimum epoch length has passed.
+    // Earlier calls do nothing, so an automation thread can poll it; either way the
+    // time the following epoch can close is returned.
+    pub fn advance_pool_epoch(ctx: Context<AdvancePoolEpoch>) -> Result<()> {
+        let clock = Clock::get()?;
+        let min_epoch_secs = ctx.accounts.config.min_pool_epoch_secs;
+        let closes_at = ctx.accounts.pool.epoch_started_at + min_epoch_secs;
+        if clock.unix_timestamp < closes_at {
+            schedule_next_run(closes_at);
+            return Ok(());
+        }
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let pool = &mut ctx.accounts.pool;
+
+        pool::accrue_pool_weight(pool, clock.unix_timestamp)?;
+        let epoch = pool.current_epoch;
+        let total_weight = pool.epoch_weight;
+        let (premiums, reward_per_weight) = pool::close_epoch(pool, clock.unix_timestamp)?;
+
+        let record = &mut ctx.accounts.epoch_record;
+        record.pool = pool.key();
+        record.epoch = epoch;
+        record.closed_at = clock.unix_timestamp;
+        record.premiums = premiums;
+        record.total_weight = total_weight;
+        record.reward_per_weight = reward_per_weight;
+        record.acc_reward_per_share = pool.acc_reward_per_share;
+        record.bump = ctx.bumps.epoch_record;
+
+        emit_seq!(
+            events,
+            PoolEpochClosed {
+                epoch,
+                premiums,
+                total_shares: pool.total_shares,
+                timestamp: clock.unix_timestamp,
+            }
+        );
+        schedule_next_run(clock.unix_timestamp + min_epoch_secs);
+
+        Ok(())
+    }
+
+    // Claim premium income earned by an LP position in closed epochs
+    pub fn claim_lp_rewards(ctx: Context<ClaimLpRewards>) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let clock = Clock::get()?;
+        let pool = &mut ctx.accounts.pool;
+        let position = &mut ctx.accounts.position;
+
+        pool::settle_position(
+            pool,
+            position,
+            ctx.accounts.last_epoch.as_deref(),
+            clock.unix_timestamp,
+        )?;
+
+        let amount = position.pending_rewards;
+        require!(amount > 0, ErrorCode::NothingToClaim);
+        position.pending_rewards = 0;
+        pool.reserved_rewards = pool.reserved_rewards.saturating_sub(amount);
+
+        process_payout(
+            pool,
+            &ctx.accounts.vault,
+            &ctx.accounts.owner_token_account,
+            &ctx.accounts.payout_mint,
+            &ctx.accounts.token_program,
+            amount,
+        )?;
+
+        emit_seq!(
+            events,
+            PoolWithdrawal {
+                ledger_seq: next_ledger_seq(&mut ctx.accounts.config)?,
+                instruction: LedgerInstruction::ClaimLpRewards,
+                mint: pool.payout_mint,
+                counterparty: position.owner,
+                amount,
+                shares: 0,
+                queued_shares: 0,
+                timestamp: clock.unix_timestamp,
+            }
+        );
+        emit_seq!(
+            events,
+            LpRewardsClaimed {
+                owner: position.owner,
+                amount,
+                timestamp: clock.unix_timestamp,
+            }
+        );
+
+        Ok(())
+    }
+
+    // Emit the pool's current figures for clients. The APY is the observed feed value
+    // when it was posted during the current epoch, otherwise the yield implied by
+    // premiums accrued so far this epoch. Fails if the feed has gone stale.
+    pub fn read_insurance_pool_stats(ctx: Context<ReadInsurancePoolStats>) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let now = Clock::get()?.unix_timestamp;
+        let pool = &ctx.accounts.pool;
+        if pool.apy_last_updated > 0 {
+            require!(
+                now - pool.apy_last_updated <= InsurancePool::APY_MAX_AGE_SECS,
+                ErrorCode::StaleApyOracle
+            );
+        }
+
+        let net_assets = pool::net_assets(pool, ctx.accounts.vault.amount);
+        let computed_apy_bps = pool::computed_apy_bps(
+            pool.premiums_accrued,
+            net_assets,
+            now.saturating_sub(pool.epoch_started_at),
+        );
+        let observed = pool.apy_last_updated > 0 && pool.apy_last_updated >= pool.epoch_started_at;
+
+        emit_seq!(
+            events,
+            InsurancePoolStats {
+                pool: pool.key(),
+                net_assets,
+                total_shares: pool.total_shares,
+                utilization_bps: pool::utilization_bps(pool, ctx.accounts.vault.amount),
+                outstanding_coverage: pool.outstanding_coverage,
+                computed_apy_bps,
+                observed_apy_bps: pool.observed_apy_bps,
+                apy_bps: if observed {
+                    pool.observed_apy_bps
+                } else {
+                    computed_apy_bps
+                },
+                apy_observed: observed,
+                timestamp: now,
+            }
+        );
+        Ok(())
+    }
+
+    // Pay an approved claim from the pool vault, applying the haircut ratio when insolvent
+    pub fn execute_payout(ctx: Context<ExecutePayout>, reference: Option<[u8; 16]>) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let clock = Clock::get()?;
+        let pool = &mut ctx.accounts.pool;
+        let claim = &mut ctx.accounts.claim;
+
+        let assets = ctx.accounts.vault.amount;
+        pool::assert_pool_solvency(pool, assets, clock.unix_timestamp, &mut events);
+
+        // Reinsurance is only drawn on with its vault there to pay from
+        let reinsurance = match (
+            ctx.accounts.reinsurance_pool.as_ref(),
+            ctx.accounts.reinsurance_vault.as_ref(),
+        ) {
+            (Some(reinsurance), Some(reinsurance_vault)) => {
+                require_keys_eq!(
+                    reinsurance_vault.key(),
+                    reinsurance.vault,
+                    ErrorCode::InvalidReinsuranceVault
+                );
+                Some(&**reinsurance)
+            }
+            _ => None,
+        };
+        let PayoutQuote {
+            payable,
+            due,
+            paid,
+            reinsured,
+            shortfall,
+            installment_amount,
+            installments_remaining,
+        } = payout_quote(&ctx.accounts.config, pool, claim, assets, reinsurance)?;
+        let primary_shortfall = shortfall + reinsured;
+
+        // Whatever the pool can't pay falls through to the reinsurance tranche
+        if let (Some(reinsurance), Some(reinsurance_vault)) = (
+            ctx.accounts.reinsurance_pool.as_mut(),
+            ctx.accounts.reinsurance_vault.as_ref(),
+        ) {
+            if reinsured > 0 {
+                reinsurance.pool_balance -= reinsured;
+                reinsurance.reinsurance_used = reinsurance
+                    .reinsurance_used
+                    .checked_add(reinsured)
+                    .ok_or(ErrorCode::MathOverflow)?;
+
+                let bump = [reinsurance.bump];
+                let signer_seeds: &[&[&[u8]]] = &[&[b"reinsurance_pool", &bump]];
+                tokens::transfer(
+                    &ctx.accounts.token_program,
+                    reinsurance_vault.to_account_info(),
+                    ctx.accounts.claimant_token_account.to_account_info(),
+                    &ctx.accounts.payout_mint,
+                    reinsurance.to_account_info(),
+                    reinsured,
+                    signer_seeds,
+                )?;
+
+                let reinsurance_used = reinsurance.reinsurance_used;
+                emit_cpi_seq!(
+                    ctx,
+                    events,
+                    ReinsuranceActivated {
+                        ledger_seq: next_ledger_seq(&mut ctx.accounts.config)?,
+                        instruction: LedgerInstruction::ExecutePayout,
+                        mint: pool.payout_mint,
+                        counterparty: claim.claimant,
+                        amount: reinsured,
+                        claim_id: claim.key(),
+                        shortfall: primary_shortfall,
+                        reinsurance_used,
+                        timestamp: clock.unix_timestamp,
+                    }
+                );
+            }
+        }
+
+        pool.approved_liabilities = pool.approved_liabilities.saturating_sub(due);
+        pool.pending_shortfalls = pool
+            .pending_shortfalls
+            .checked_add(shortfall)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        ClaimStatus::try_transition(&claim.status, &ClaimStatus::Paid)?;
+        claim.status = ClaimStatus::Paid;
+        claim.paid_amount = paid + reinsured;
+        claim.shortfall = shortfall;
+        // Shortfalls recorded mid-round wait for the next top-up round
+        claim.shortfall_round = pool.shortfall_round;
+        claim.payout_reference = reference;
+        if installments_remaining > 0 {
+            claim.payout_schedule = Some(PayoutSchedule {
+                installment_amount,
+                installments_remaining,
+                last_installment_at: clock.unix_timestamp,
+                installment_interval: PayoutSchedule::INSTALLMENT_INTERVAL_SECS,
+                installments_paid: 1,
+            });
+        }
+
+        if paid > 0 {
+            process_payout(
+                pool,
+                &ctx.accounts.vault,
+                &ctx.accounts.claimant_token_account,
+                &ctx.accounts.payout_mint,
+                &ctx.accounts.token_program,
+                paid,
+            )?;
+        }
+        memo::attach(ctx.accounts.memo_program.as_ref(), memo::PAYOUT, reference)?;
+
+        emit_cpi_seq!(
+            ctx,
+            events,
+            PayoutExecuted {
+                ledger_seq: next_ledger_seq(&mut ctx.accounts.config)?,
+                instruction: LedgerInstruction::ExecutePayout,
+                mint: pool.payout_mint,
+                counterparty: claim.claimant,
+                amount: paid,
+                claim_id: claim.key(),
+                claim_amount: payable,
+                shortfall,
+                timestamp: clock.unix_timestamp,
+                claim_number: claim.claim_number,
+            }
+        );
+        if installments_remaining > 0 {
+            emit_cpi_seq!(
+                ctx,
+                events,
+                InstallmentPaid {
+                    claim_id: claim.key(),
+                    installment_number: 1,
+                    amount: claim.paid_amount,
+                    installments_remaining,
+                }
+            );
+        }
+        record_activity(
+            ctx.accounts.activity.as_ref(),
+            None,
+            claim.claimant,
+            ActivityKind::PayoutReceived,
+            claim.paid_amount,
+            claim.key(),
+            clock.unix_timestamp,
+        )?;
+
+        Ok(())
+    }
+
+    // Pay the next installment of a scheduled payout once its interval has passed.
+    // Installments the pool can't cover are recorded as shortfall like any payout.
+    pub fn claim_next_installment(ctx: Context<ClaimNextInstallment>) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let now = Clock::get()?.unix_timestamp;
+        let pool = &mut ctx.accounts.pool;
+        let claim = &mut ctx.accounts.claim;
+
+        let Some(schedule) = claim.payout_schedule.as_mut() else {
+            return err!(ErrorCode::NoInstallmentsRemaining);
+        };
+        require!(
+            schedule.installments_remaining > 0,
+            ErrorCode::NoInstallmentsRemaining
+        );
+        require!(
+            logic::installment_due(
+                now,
+                schedule.last_installment_at,
+                schedule.installment_interval
+            ),
+            ErrorCode::InstallmentNotDue
+        );
+        let amount = schedule.installment_amount;
+        schedule.installments_remaining -= 1;
+        schedule.installments_paid += 1;
+        schedule.last_installment_at = now;
+        let installment_number = schedule.installments_paid;
+        let installments_remaining = schedule.installments_remaining;
+
+        let assets = ctx.accounts.vault.amount;
+        pool::assert_pool_solvency(pool, assets, now, &mut events);
+        let (paid, shortfall) = pool::split_payout(pool, amount, assets);
+
+        pool.approved_liabilities = pool.approved_liabilities.saturating_sub(amount);
+        pool.pending_shortfalls = pool
+            .pending_shortfalls
+            .checked_add(shortfall)
+            .ok_or(ErrorCode::MathOverflow)?;
+        claim.paid_amount = claim
+            .paid_amount
+            .checked_add(paid)
+            .ok_or(ErrorCode::MathOverflow)?;
+        if shortfall > 0 {
+            claim.shortfall = claim
+                .shortfall
+                .checked_add(shortfall)
+                .ok_or(ErrorCode::MathOverflow)?;
+            claim.shortfall_round = pool.shortfall_round;
+        }
+
+        if paid > 0 {
+            process_payout(
+                pool,
+                &ctx.accounts.vault,
+                &ctx.accounts.claimant_token_account,
+                &ctx.accounts.payout_mint,
+                &ctx.accounts.token_program,
+                paid,
+            )?;
+        }
+
+        emit_seq!(
+            events,
+            PayoutExecuted {
+                ledger_seq: next_ledger_seq(&mut ctx.accounts.config)?,
+                instruction: LedgerInstruction::ClaimNextInstallment,
+                mint: pool.payout_mint,
+                counterparty: claim.claimant,
+                amount: paid,
+                claim_id: claim.key(),
+                claim_amount: amount,
+                shortfall,
+                timestamp: now,
+                claim_number: claim.claim_number,
+            }
+        );
+        emit_seq!(
+            events,
+            InstallmentPaid {
+                claim_id: claim.key(),
+                installment_number,
+                amount: paid,
+                installments_remaining,
+            }
+        );
+        record_activity(
+            ctx.accounts.activity.as_ref(),
+            None,
+            claim.claimant,
+            ActivityKind::PayoutReceived,
+            paid,
+            claim.key(),
+            now,
+        )?;
+        Ok(())
+    }
+
+    // Pay down recorded shortfalls from capital that arrived after the haircut.
+    // Remaining accounts: (Claim, claimant token account) per item (stride
+    // TOP_UP_BATCH_STRIDE).
+    pub fn top_up_shortfalls<'info>(
+        ctx: Context<'_, '_, 'info, 'info, TopUpShortfalls<'info>>,
+    ) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let clock = Clock::get()?;
+        batch_items(&ctx, ProtocolConfig::TOP_UP_BATCH_STRIDE)?;
+        let pool = &mut ctx.accounts.pool;
+
+        require!(
+            pool::open_shortfall_round(pool, ctx.accounts.vault.amount),
+            ErrorCode::NoShortfallToTopUp
+        );
+
+        for pair in ctx
+            .remaining_accounts
+            .chunks(ProtocolConfig::TOP_UP_BATCH_STRIDE)
+        {
+            let mut claim = Account::<Claim>::try_from(&pair[0])?;
+            require!(
+                claim.version_supported(),
+                ErrorCode::UnsupportedAccountVersion
+            );
+            let destination = InterfaceAccount::<TokenAccount>::try_from(&pair[1])?;
+
+            require!(
+                pair[0].is_writable && pair[1].is_writable,
+                ErrorCode::InvalidRemainingAccounts
+            );
+            require!(
+                destination.owner == claim.claimant,
+                ErrorCode::InvalidClaimantAccount
+            );
+            require!(
+                destination.mint == pool.payout_mint,
+                ErrorCode::InvalidClaimantAccount
+            );
+            require!(claim.pool == pool.key(), ErrorCode::WrongSubPool);
+
+            if claim.written_off
+                || claim.shortfall == 0
+                || claim.shortfall_round >= pool.shortfall_round
+            {
+                continue;
+            }
+
+            let amount = pool::top_up_amount(pool, claim.shortfall);
+            pool.round_remaining = pool.round_remaining.saturating_sub(claim.shortfall);
+            pool.pending_shortfalls = pool.pending_shortfalls.saturating_sub(amount);
+            claim.shortfall -= amount;
+            claim.paid_amount += amount;
+            claim.shortfall_round = pool.shortfall_round;
+            claim.exit(&crate::ID)?;
+
+            if amount > 0 {
+                process_payout(
+                    pool,
+                    &ctx.accounts.vault,
+                    &destination,
+                    &ctx.accounts.payout_mint,
+                    &ctx.accounts.token_program,
+                    amount,
+                )?;
+
+                emit_cpi_seq!(
+                    ctx,
+                    events,
+                    PayoutExecuted {
+                        ledger_seq: next_ledger_seq(&mut ctx.accounts.config)?,
+                        instruction: LedgerInstruction::TopUpShortfalls,
+                        mint: pool.payout_mint,
+                        counterparty: claim.claimant,
+                        amount,
+                        claim_id: claim.key(),
+                        claim_amount: claim.payable_amount(),
+                        shortfall: claim.shortfall,
+                        timestamp: clock.unix_timestamp,
+                        claim_number: claim.claim_number,
+                    }
+                );
+            }
+
+            emit_seq!(
+                events,
+                ShortfallToppedUp {
+                    claim_id: claim.key(),
+                    round: pool.shortfall_round,
+                    amount,
+                    remaining_shortfall: claim.shortfall,
+                    timestamp: clock.unix_timestamp,
+                }
+            );
+        }
+
+        Ok(())
+    }
+
+    // Set the protocol-managed delegate and the rules it votes by. While stake is delegated
+    // to the protocol, new rules wait out the governance timelock.
+    pub fn configure_protocol_delegate(
+        ctx: Context<ConfigureProtocolDelegate>,
+        protocol_delegate: Pubkey,
+        auto_vote_rules: AutoVoteRules,
+    ) -> Result<()> {
+        let governance = &mut ctx.accounts.governance;
+        require!(
+            governance.is_authorized(&ctx.accounts.authority.key()),
+            ErrorCode::UnauthorizedGovernance
+        );
+        require!(
+            auto_vote_rules.approve_threshold <= auto_vote_rules.reject_threshold,
+            ErrorCode::InvalidAutoVoteRules
+        );
+
+        governance.protocol_delegate = protocol_delegate;
+        governance.set_auto_vote_rules(auto_vote_rules, Clock::get()?.unix_timestamp);
+        Ok(())
+    }
+
+    // Delegate the caller's claim votes to another wallet or to the protocol
+    pub fn delegate_vote(ctx: Context<DelegateVote>, delegatee: Pubkey) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let delegation = &mut ctx.accounts.delegation;
+        delegation.delegator = ctx.accounts.delegator.key();
+        delegation.delegatee = delegatee;
+        delegation.bump = ctx.bumps.delegation;
+
+        // Only claim-voting stake can be delegated, so empty wallets add no weight
+        let stake = &ctx.accounts.stake;
+        require!(stake.amount > 0, ErrorCode::NoClaimVoteStake);
+
+        // The protocol votes with its delegators' stake, weighed as of delegation
+        let governance = &mut ctx.accounts.governance;
+        if delegatee == governance.protocol_delegate {
+            let (_, weight) = stake.vote_weight(Clock::get()?.unix_timestamp);
+            require!(weight > 0, ErrorCode::NoClaimVoteStake);
+            delegation.protocol_weight = weight;
+            governance.protocol_delegations = governance
+                .protocol_delegations
+                .checked_add(1)
+                .ok_or(ErrorCode::MathOverflow)?;
+            governance.protocol_delegated_weight = governance
+                .protocol_delegated_weight
+                .checked_add(weight)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+
+        emit_seq!(
+            events,
+            VoteDelegated {
+                delegator: delegation.delegator,
+                delegatee,
+                timestamp: Clock::get()?.unix_timestamp,
+            }
+        );
+
+        Ok(())
+    }
+
+    // Withdraw a delegation and reclaim its rent
+    pub fn revoke_delegation(ctx: Context<RevokeDelegation>) -> Result<()> {
+        let governance = &mut ctx.accounts.governance;
+        let weight = ctx.accounts.delegation.protocol_weight;
+        if weight > 0 {
+            governance.protocol_delegations = governance.protocol_delegations.saturating_sub(1);
+            governance.protocol_delegated_weight =
+                governance.protocol_delegated_weight.saturating_sub(weight);
+        }
+        Ok(())
+    }
+
+    // Record the risk engine's fraud assessment of a pending claim
+    pub fn assess_claim_fraud(ctx: Context<AssessClaimFraud>, fraud_score: u8) -> Result<()> {
+        require!(
+            ctx.accounts
+                .governance
+                .is_authorized(&ctx.accounts.authority.key()),
+            ErrorCode::UnauthorizedGovernance
+        );
+
+        let claim = &mut ctx.accounts.claim;
+        require!(
+            claim.status == ClaimStatus::Pending,
+            ErrorCode::ClaimNotPending
+        );
+        claim.fraud_score = fraud_score;
+        claim.fraud_assessed = true;
+        Ok(())
+    }
+
+    // Keeper crank casting the protocol delegate's votes on a claim per the auto-vote rules
+    pub fn execute_protocol_vote(
+        ctx: Context<ExecuteProtocolVote>,
+        claim_id: Pubkey,
+    ) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let claim = &mut ctx.accounts.claim;
+        let governance = &ctx.accounts.governance;
+
+        require!(claim.key() == claim_id, ErrorCode::ClaimMismatch);
+        require!(
+            claim.status == ClaimStatus::Pending,
+            ErrorCode::ClaimNotPending
+        );
+        require!(!claim.protocol_voted, ErrorCode::ProtocolAlreadyVoted);
+        require!(claim.fraud_assessed, ErrorCode::ClaimNotAssessed);
+        require!(
+            governance.protocol_delegated_weight > 0,
+            ErrorCode::NoProtocolDelegations
+        );
+
+        let approve = governance
+            .auto_vote_rules_at(Clock::get()?.unix_timestamp)
+            .decide(claim.fraud_score)
+            .ok_or(ErrorCode::NoAutoVoteRuleApplies)?;
+
+        let weight = governance.protocol_delegated_weight;
+        if approve {
+            claim.approval_votes = claim.approval_votes.saturating_add(weight);
+        } else {
+            claim.rejection_votes = claim.rejection_votes.saturating_add(weight);
+        }
+        claim.protocol_voted = true;
+
+        tally_claim_votes(
+            claim,
+            governance,
+            ctx.accounts.staking.quorum_weight,
+            &mut ctx.accounts.pool,
+            &mut ctx.accounts.config,
+            &mut events,
+        )?;
+
+        emit_seq!(
+            events,
+            ProtocolVoteCast {
+                claim_id,
+                delegate: governance.protocol_delegate,
+                approve,
+                weight,
+                fraud_score: claim.fraud_score,
+                timestamp: Clock::get()?.unix_timestamp,
+            }
+        );
+        if claim.status != ClaimStatus::Pending {
+            emit_cpi_seq!(
+                ctx,
+                events,
+                ClaimResolved {
+                    claim_id,
+                    status: claim.status.clone(),
+                    payable_amount: claim.payable_amount(),
+                    timestamp: Clock::get()?.unix_timestamp,
+                    claim_number: claim.claim_number,
+                }
+            );
+        }
+
+        Ok(())
+    }
+
+    // Create a limited-use subscription discount code
+    pub fn create_promo_code(
+        ctx: Context<CreatePromoCode>,
+        code: [u8; 16],
+        discount_bps: u16,
+        max_uses: u32,
+        expiry: i64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts
+                .governance
+                .is_authorized(&ctx.accounts.authority.key()),
+            ErrorCode::UnauthorizedGovernance
+        );
+        require!(discount_bps <= 10_000, ErrorCode::InvalidBps);
+        require!(max_uses > 0, ErrorCode::InvalidAmount);
+        require!(
+            expiry > Clock::get()?.unix_timestamp,
+            ErrorCode::InvalidDuration
+        );
+
+        let promo = &mut ctx.accounts.promo_code;
+        promo.code = code;
+        promo.discount_bps = discount_bps;
+        promo.max_uses = max_uses;
+        promo.uses = 0;
+        promo.expiry = expiry;
+        promo.bump = ctx.bumps.promo_code;
+        Ok(())
+    }
+
+    // Create the protocol treasury and its token vault
+    pub fn initialize_treasury(ctx: Context<InitializeTreasury>) -> Result<()> {
+        require!(
+            ctx.accounts
+                .governance
+                .is_authorized(&ctx.accounts.authority.key()),
+            ErrorCode::UnauthorizedGovernance
+        );
+
+        let treasury = &mut ctx.accounts.treasury;
+        treasury.mint = ctx.accounts.mint.key();
+        treasury.vault = ctx.accounts.vault.key();
+        treasury.bump = ctx.bumps.treasury;
+        Ok(())
+    }
+
+    // Create the fee distributor that shares treasury inflows with IRIS stakers
+    pub fn initialize_fee_distributor(
+        ctx: Context<InitializeFeeDistributor>,
+        fee_share_bps: u16,
+        unstake_cooldown: i64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts
+                .governance
+                .is_authorized(&ctx.accounts.authority.key()),
+            ErrorCode::UnauthorizedGovernance
+        );
+        require!(fee_share_bps <= 10_000, ErrorCode::InvalidBps);
+        require!(unstake_cooldown >= 0, ErrorCode::InvalidDuration);
+
+        let distributor = &mut ctx.accounts.distributor;
+        distributor.iris_mint = ctx.accounts.iris_mint.key();
+        distributor.stake_vault = ctx.accounts.stake_vault.key();
+        distributor.reward_vault = ctx.accounts.reward_vault.key();
+        distributor.fee_share_bps = fee_share_bps;
+        distributor.unstake_cooldown = unstake_cooldown;
+        // Only inflows after creation are shared
+        distributor.accounted_inflows = ctx.accounts.treasury.total_inflows;
+        distributor.bump = ctx.bumps.distributor;
+        Ok(())
+    }
+
+    // Stake IRIS for a share of protocol fees
+    pub fn stake_iris(ctx: Context<StakeIris>, amount: u64) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        require!(amount > 0, ErrorCode::InvalidAmount);
+
+        // Governance weight needs a settled subscription, so a wallet can't subscribe,
+        // stake, vote and leave within one window
+        let user_account = &ctx.accounts.user_account;
+        require!(
+            user_account.active_sub
+                && logic::subscription_age_reached(
+                    Clock::get()?.unix_timestamp,
+                    user_account.subscription_started_at,
+                    ctx.accounts.config.min_subscription_age_for_governance_secs,
+                ),
+            ErrorCode::SubscriptionTooNewForGovernance
+        );
+
+        let distributor = &mut ctx.accounts.distributor;
+        let stake = &mut ctx.accounts.stake;
+
+        if stake.owner == Pubkey::default() {
+            stake.owner = ctx.accounts.owner.key();
+            stake.bump = ctx.bumps.stake;
+        }
+
+        fee_share::settle(stake, distributor.acc_reward_per_share)?;
+        let now = Clock::get()?.unix_timestamp;
+        stake.stake_start =
+            logic::blended_stake_start(stake.amount, stake.stake_start, amount, now);
+        stake.amount = stake
+            .amount
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        stake.reward_debt = fee_share::reward_debt(stake.amount, distributor.acc_reward_per_share)?;
+        distributor.total_staked = distributor
+            .total_staked
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        tokens::transfer_in(
+            &ctx.accounts.token_program,
+            &ctx.accounts.owner_iris_account,
+            &mut ctx.accounts.stake_vault,
+            &ctx.accounts.iris_mint,
+            ctx.accounts.owner.to_account_info(),
+            amount,
+        )?;
+
+        emit_seq!(
+            events,
+            IrisStaked {
+                owner: stake.owner,
+                amount,
+                total_staked: distributor.total_staked,
+                timestamp: now,
+            }
+        );
+
+        Ok(())
+    }
+
+    // Permissionless crank moving the stakers' slice of new treasury inflows into the reward vault
+    pub fn distribute_fees(ctx: Context<DistributeFees>) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let distributor = &mut ctx.accounts.distributor;
+        let treasury = &mut ctx.accounts.treasury;
+
+        let new_inflows = treasury
+            .total_inflows
+            .saturating_sub(distributor.accounted_inflows);
+        let share = fee_share::bps_of(new_inflows, distributor.fee_share_bps);
+        require!(share > 0, ErrorCode::NothingToDistribute);
+
+        distributor.accounted_inflows = treasury.total_inflows;
+        // Rewards accrued while nobody was staked carry over to the next distribution
+        let reward = share
+            .checked_add(distributor.undistributed)
+            .ok_or(ErrorCode::MathOverflow)?;
+        if distributor.total_staked == 0 {
+            distributor.undistributed = reward;
+        } else {
+            distributor.acc_reward_per_share = fee_share::accrue(
+                distributor.acc_reward_per_share,
+                reward,
+                distributor.total_staked,
+            )?;
+            distributor.undistributed = 0;
+        }
+        treasury::debit(
+            treasury,
+            &ctx.accounts.treasury_vault,
+            &ctx.accounts.treasury_mint,
+            ctx.accounts.reward_vault.to_account_info(),
+            &ctx.accounts.token_program,
+            &mut ctx.accounts.config,
+            &mut events,
+            share,
+            LedgerInstruction::DistributeFees,
+            None,
+        )?;
+
+        emit_seq!(
+            events,
+            FeesDistributed {
+                amount: share,
+                total_staked: distributor.total_staked,
+                acc_reward_per_share: distributor.acc_reward_per_share,
+                timestamp: Clock::get()?.unix_timestamp,
+            }
+        );
+
+        Ok(())
+    }
+
+    // Pay out the caller's accrued fee share
+    pub fn claim_fee_share(ctx: Context<ClaimFeeShare>) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let distributor = &ctx.accounts.distributor;
+        let stake = &mut ctx.accounts.stake;
+
+        fee_share::settle(stake, distributor.acc_reward_per_share)?;
+        let amount = stake.pending_rewards;
+        require!(amount > 0, ErrorCode::NothingToClaim);
+        stake.pending_rewards = 0;
+
+        let bump = [distributor.bump];
+        let signer_seeds: &[&[&[u8]]] = &[&[b"fee_distributor", &bump]];
+        tokens::transfer(
+            &ctx.accounts.token_program,
+            ctx.accounts.reward_vault.to_account_info(),
+            ctx.accounts.destination.to_account_info(),
+            &ctx.accounts.reward_mint,
+            distributor.to_account_info(),
+            amount,
+            signer_seeds,
+        )?;
+
+        emit_seq!(
+            events,
+            FeeShareClaimed {
+                owner: stake.owner,
+                amount,
+                timestamp: Clock::get()?.unix_timestamp,
+            }
+        );
+
+        Ok(())
+    }
+
+    // Stop earning on `amount` and start its unstake cooldown
+    pub fn request_unstake(ctx: Context<RequestUnstake>, amount: u64) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let clock = Clock::get()?;
+        let distributor = &mut ctx.accounts.distributor;
+        let stake = &mut ctx.accounts.stake;
+
+        require!(
+            amount > 0 && amount <= stake.amount,
+            ErrorCode::InvalidAmount
+        );
+
+        fee_share::settle(stake, distributor.acc_reward_per_share)?;
+        stake.amount -= amount;
+        stake.reward_debt = fee_share::reward_debt(stake.amount, distributor.acc_reward_per_share)?;
+        stake.unstaking_amount = stake
+            .unstaking_amount
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        stake.unstake_available_at = clock.unix_timestamp + distributor.unstake_cooldown;
+        distributor.total_staked -= amount;
+
+        emit_seq!(
+            events,
+            UnstakeRequested {
+                owner: stake.owner,
+                amount,
+                available_at: stake.unstake_available_at,
+            }
+        );
+
+        Ok(())
+    }
+
+    // Return IRIS whose cooldown has elapsed
+    pub fn complete_unstake(ctx: Context<CompleteUnstake>) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let clock = Clock::get()?;
+        let distributor = &ctx.accounts.distributor;
+        let stake = &mut ctx.accounts.stake;
+
+        let amount = stake.unstaking_amount;
+        require!(amount > 0, ErrorCode::NothingToClaim);
+        require!(
+            clock.unix_timestamp >= stake.unstake_available_at,
+            ErrorCode::UnstakeCooldownActive
+        );
+        stake.unstaking_amount = 0;
+
+        let bump = [distributor.bump];
+        let signer_seeds: &[&[&[u8]]] = &[&[b"fee_distributor", &bump]];
+        tokens::transfer(
+            &ctx.accounts.token_program,
+            ctx.accounts.stake_vault.to_account_info(),
+            ctx.accounts.owner_iris_account.to_account_info(),
+            &ctx.accounts.iris_mint,
+            distributor.to_account_info(),
+            amount,
+            signer_seeds,
+        )?;
+
+        emit_seq!(
+            events,
+            IrisUnstaked {
+                owner: stake.owner,
+                amount,
+                timestamp: clock.unix_timestamp,
+            }
+        );
+
+        Ok(())
+    }
+
+    // Create the pool of IRIS staked to vote on claims. It is separate from the fee-share
+    // stake, so claim votes can't be bought with stake earning protocol fees elsewhere.
+    pub fn initialize_claim_staking(
+        ctx: Context<InitializeClaimStaking>,
+        quorum_weight: u64,
+        unstake_cooldown: i64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts
+                .governance
+                .is_authorized(&ctx.accounts.authority.key()),
+            ErrorCode::UnauthorizedGovernance
+        );
+        require!(quorum_weight > 0, ErrorCode::InvalidAmount);
+        require!(unstake_cooldown >= 0, ErrorCode::InvalidDuration);
+
+        let staking = &mut ctx.accounts.staking;
+        staking.iris_mint = ctx.accounts.iris_mint.key();
+        staking.vault = ctx.accounts.vault.key();
+        staking.quorum_weight = quorum_weight;
+        staking.unstake_cooldown = unstake_cooldown;
+        staking.bump = ctx.bumps.staking;
+        Ok(())
+    }
+
+    // Change the stake a claim vote needs to resolve and the claim unstake cooldown
+    pub fn update_claim_staking(
+        ctx: Context<UpdateClaimStaking>,
+        quorum_weight: u64,
+        unstake_cooldown: i64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts
+                .governance
+                .is_authorized(&ctx.accounts.authority.key()),
+            ErrorCode::UnauthorizedGovernance
+        );
+        require!(quorum_weight > 0, ErrorCode::InvalidAmount);
+        require!(unstake_cooldown >= 0, ErrorCode::InvalidDuration);
+
+        let staking = &mut ctx.accounts.staking;
+        staking.quorum_weight = quorum_weight;
+        staking.unstake_cooldown = unstake_cooldown;
+        Ok(())
+    }
+
+    // Stake IRIS as claim-voting weight
+    pub fn stake_for_claim_votes(ctx: Context<StakeForClaimVotes>, amount: u64) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        require!(amount > 0, ErrorCode::InvalidAmount);
+
+        // As for fee-share stake, a wallet can't subscribe, stake and vote in one window
+        let user_account = &ctx.accounts.user_account;
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            user_account.active_sub
+                && logic::subscription_age_reached(
+                    now,
+                    user_account.subscription_started_at,
+                    ctx.accounts.config.min_subscription_age_for_governance_secs,
+                ),
+            ErrorCode::SubscriptionTooNewForGovernance
+        );
+
+        let staking = &mut ctx.accounts.staking;
+        let stake = &mut ctx.accounts.stake;
+        if stake.owner == Pubkey::default() {
+            stake.owner = ctx.accounts.owner.key();
+            stake.bump = ctx.bumps.stake;
+        }
+
+        stake.stake_start =
+            logic::blended_stake_start(stake.amount, stake.stake_start, amount, now);
+        stake.amount = stake
+            .amount
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        staking.total_staked = staking
+            .total_staked
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        tokens::transfer_in(
+            &ctx.accounts.token_program,
+            &ctx.accounts.owner_iris_account,
+            &mut ctx.accounts.vault,
+            &ctx.accounts.iris_mint,
+            ctx.accounts.owner.to_account_info(),
+            amount,
+        )?;
+
+        emit_seq!(
+            events,
+            ClaimVoteStaked {
+                owner: stake.owner,
+                amount,
+                total_staked: staking.total_staked,
+                timestamp: now,
+            }
+        );
+
+        Ok(())
+    }
+
+    // Withdraw `amount` of claim-voting stake from voting and start its cooldown.
+    // Delegated stake must be taken back first, as its weight was counted at delegation.
+    pub fn request_claim_unstake(ctx: Context<RequestClaimUnstake>, amount: u64) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let clock = Clock::get()?;
+        let staking = &mut ctx.accounts.staking;
+        let stake = &mut ctx.accounts.stake;
+
+        require!(
+            amount > 0 && amount <= stake.amount,
+            ErrorCode::InvalidAmount
+        );
+
+        stake.amount -= amount;
+        stake.unstaking_amount = stake
+            .unstaking_amount
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        stake.unstake_available_at = clock.unix_timestamp + staking.unstake_cooldown;
+        staking.total_staked -= amount;
+
+        emit_seq!(
+            events,
+            ClaimUnstakeRequested {
+                owner: stake.owner,
+                amount,
+                available_at: stake.unstake_available_at,
+            }
+        );
+
+        Ok(())
+    }
+
+    // Return claim-voting IRIS whose cooldown has elapsed
+    pub fn complete_claim_unstake(ctx: Context<CompleteClaimUnstake>) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let clock = Clock::get()?;
+        let staking = &ctx.accounts.staking;
+        let stake = &mut ctx.accounts.stake;
+
+        let amount = stake.unstaking_amount;
+        require!(amount > 0, ErrorCode::NothingToClaim);
+        require!(
+            clock.unix_timestamp >= stake.unstake_available_at,
+            ErrorCode::UnstakeCooldownActive
+        );
+        stake.unstaking_amount = 0;
+
+        let bump = [staking.bump];
+        let signer_seeds: &[&[&[u8]]] = &[&[b"claim_staking", &bump]];
+        tokens::transfer(
+            &ctx.accounts.token_program,
+            ctx.accounts.vault.to_account_info(),
+            ctx.accounts.owner_iris_account.to_account_info(),
+            &ctx.accounts.iris_mint,
+            staking.to_account_info(),
+            amount,
+            signer_seeds,
+        )?;
+
+        emit_seq!(
+            events,
+            ClaimVoteUnstaked {
+                owner: stake.owner,
+                amount,
+                timestamp: clock.unix_timestamp,
+            }
+        );
+
+        Ok(())
+    }
+
+    // Replace the DEX programs treasury buybacks may route through
+    pub fn set_dex_allowlist(ctx: Context<SetDexAllowlist>, programs: Vec<Pubkey>) -> Result<()> {
+        require!(
+            ctx.accounts
+                .governance
+                .is_authorized(&ctx.accounts.authority.key()),
+            ErrorCode::UnauthorizedGovernance
+        );
+        require!(programs.len() <= MAX_DEX_PROGRAMS, ErrorCode::AllowlistFull);
+
+        ctx.accounts.treasury.dex_allowlist = programs;
+        Ok(())
+    }
+
+    // Replace the members allowed to open emergency proposals
+    pub fn set_emergency_council(
+        ctx: Context<SetEmergencyCouncil>,
+        members: Vec<Pubkey>,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts
+                .governance
+                .is_authorized(&ctx.accounts.authority.key()),
+            ErrorCode::UnauthorizedGovernance
+        );
+        require!(
+            members.len() <= MAX_EMERGENCY_COUNCIL,
+            ErrorCode::TooManyCouncilMembers
+        );
+
+        let council = &mut ctx.accounts.emergency_council;
+        council.members = members;
+        council.bump = ctx.bumps.emergency_council;
+        Ok(())
+    }
+
+    // Start replacing a compromised council key. The other members confirm the swap,
+    // which happens once a majority minus the replaced key agrees; the initiator's
+    // confirmation counts.
+    pub fn rotate_multisig_signer(
+        ctx: Context<RotateMultisigSigner>,
+        old_signer: Pubkey,
+        new_signer: Pubkey,
+    ) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let council = &mut ctx.accounts.emergency_council;
+        let initiator = ctx.accounts.signer.key();
+        require!(
+            council.members.contains(&old_signer),
+            ErrorCode::NotCouncilMember
+        );
+        require!(
+            initiator != old_signer && council.members.contains(&initiator),
+            ErrorCode::NotCouncilMember
+        );
+        require!(
+            new_signer != Pubkey::default() && !council.members.contains(&new_signer),
+            ErrorCode::InvalidRotationSigner
+        );
+
+        let rotation = &mut ctx.accounts.rotation;
+        rotation.old_signer = old_signer;
+        rotation.new_signer = new_signer;
+        rotation.initiated_by = initiator;
+        rotation.confirmations = vec![initiator];
+        rotation.created_at = Clock::get()?.unix_timestamp;
+        rotation.bump = ctx.bumps.rotation;
+
+        emit_seq!(
+            events,
+            MultisigSignerRotationProposed {
+                old: old_signer,
+                new: new_signer,
+                initiated_by: initiator,
+            }
+        );
+        try_execute_signer_rotation(council, rotation, &mut events)
+    }
+
+    pub fn confirm_signer_rotation(ctx: Context<ConfirmSignerRotation>) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let council = &mut ctx.accounts.emergency_council;
+        let rotation = &mut ctx.accounts.rotation;
+        let signer = ctx.accounts.signer.key();
+        require!(!rotation.executed, ErrorCode::RotationAlreadyExecuted);
+        require!(
+            signer != rotation.old_signer && council.members.contains(&signer),
+            ErrorCode::NotCouncilMember
+        );
+        require!(
+            !rotation.confirmations.contains(&signer),
+            ErrorCode::RotationAlreadyConfirmed
+        );
+        rotation.confirmations.push(signer);
+        try_execute_signer_rotation(council, rotation, &mut events)
+    }
+
+    // Open a governance proposal
+    pub fn create_proposal(
+        ctx: Context<CreateProposal>,
+        proposal_type: ProposalType,
+        snapshot_min_stake_bps: Option<u16>,
+        urgency: ProposalUrgency,
+    ) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let clock = Clock::get()?;
+        let governance = &mut ctx.accounts.governance;
+
+        let proposer = ctx.accounts.proposer.key();
+        let council = ctx
+            .accounts
+            .emergency_council
+            .as_ref()
+            .map(|council| council.members.as_slice())
+            .unwrap_or(&[]);
+        require!(
+            logic::may_propose(
+                &urgency,
+                governance.is_authorized(&proposer),
+                council,
+                &proposer
+            ),
+            match urgency {
+                ProposalUrgency::Normal => ErrorCode::UnauthorizedGovernance,
+                ProposalUrgency::Emergency => ErrorCode::EmergencyProposalUnauthorized,
+            }
+        );
+
+        if let Some(dex_program) = proposal_type.swap_program() {
+            require!(
+                ctx.accounts.treasury.dex_allowlist.contains(&dex_program),
+                ErrorCode::RouteNotAllowed
+            );
+        }
+        let upgrade_target = match proposal_type {
+            ProposalType::UpgradeProgram {
+                new_program_data,
+                upgrade_authority,
+            } => {
+                require!(
+                    ctx.accounts.config.upgrades_governed,
+                    ErrorCode::UpgradesNotGoverned
+                );
+                require!(
+                    upgrade_authority == governed_upgrade_authority().0,
+                    ErrorCode::InvalidUpgradeAuthority
+                );
+                Some(new_program_data)
+            }
+            _ => None,
+        };
+
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.id = governance.proposal_count;
+        proposal.proposer = ctx.accounts.proposer.key();
+        proposal.proposal_type = proposal_type;
+        proposal.status = ProposalStatus::Voting;
+        proposal.created_at = clock.unix_timestamp;
+        proposal.voting_ends_at = clock.unix_timestamp
+            + logic::proposal_voting_duration(&urgency, governance.voting_duration);
+        proposal.urgency = urgency;
+        proposal.snapshot = match snapshot_min_stake_bps {
+            Some(minimum_stake_for_snapshot_bps) => {
+                require!(
+                    minimum_stake_for_snapshot_bps <= 10_000,
+                    ErrorCode::InvalidBps
+                );
+                Some(SnapshotProposal {
+                    minimum_stake_for_snapshot_bps,
+                    snapshot_taken: false,
+                    total_weight: 0,
+                })
+            }
+            None => None,
+        };
+        proposal.cancellation_authority = ctx.accounts.config.admin;
+        proposal.bump = ctx.bumps.proposal;
+        governance.proposal_count += 1;
+        governance.active_vote_count += 1;
+
+        emit_seq!(
+            events,
+            ProposalCreated {
+                proposal_id: proposal.id,
+                proposer: proposal.proposer,
+                voting_ends_at: proposal.voting_ends_at,
+            }
+        );
+        if let Some(target_data_account) = upgrade_target {
+            emit_seq!(
+                events,
+                ProgramUpgradeProposed {
+                    proposal_id: proposal.id,
+                    current_version: ctx.accounts.config.program_version,
+                    target_data_account,
+                }
+            );
+        }
+        if proposal.urgency == ProposalUrgency::Emergency {
+            emit_seq!(
+                events,
+                EmergencyProposalCreated {
+                    proposal_id: proposal.id,
+                    proposer: proposal.proposer,
+                    voting_ends_at: proposal.voting_ends_at,
+                    quorum: governance.emergency_quorum,
+                }
+            );
+        }
+
+        Ok(())
+    }
+
+    // Record the stake of every large staker before a sensitive vote opens. Remaining
+    // accounts: (FeeStake, Snapshot PDA) per item (stride SNAPSHOT_BATCH_STRIDE); stakes
+    // below the proposal's minimum share of total staked supply are skipped.
+    pub fn take_vote_snapshot<'info>(
+        ctx: Context<'_, '_, 'info, 'info, TakeVoteSnapshot<'info>>,
+    ) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        require!(
+            ctx.accounts
+                .governance
+                .is_authorized(&ctx.accounts.authority.key()),
+            ErrorCode::UnauthorizedGovernance
+        );
+        batch_items(&ctx, ProtocolConfig::SNAPSHOT_BATCH_STRIDE)?;
+
+        let proposal_key = ctx.accounts.proposal.key();
+        let proposal = &mut ctx.accounts.proposal;
+        require!(
+            proposal.status == ProposalStatus::Voting,
+            ErrorCode::ProposalNotVoting
+        );
+        let snapshot = proposal
+            .snapshot
+            .as_mut()
+            .ok_or(ErrorCode::NotSnapshotProposal)?;
+        require!(!snapshot.snapshot_taken, ErrorCode::SnapshotAlreadyTaken);
+
+        let total_staked = ctx.accounts.distributor.total_staked as u128;
+        let minimum_bps = snapshot.minimum_stake_for_snapshot_bps as u128;
+        let rent = Rent::get()?;
+        let space = 8 + Snapshot::INIT_SPACE;
+        let mut snapshot_count: u32 = 0;
+
+        for pair in ctx
+            .remaining_accounts
+            .chunks(ProtocolConfig::SNAPSHOT_BATCH_STRIDE)
+        {
+            let stake = Account::<FeeStake>::try_from(&pair[0])?;
+            if (stake.amount as u128) * 10_000 < total_staked * minimum_bps || stake.amount == 0 {
+                continue;
+            }
+
+            let (address, bump) = Pubkey::find_program_address(
+                &[b"snapshot", proposal_key.as_ref(), stake.owner.as_ref()],
+                &crate::ID,
+            );
+            require!(
+                pair[1].key() == address,
+                ErrorCode::InvalidRemainingAccounts
+            );
+
+            let bump_seed = [bump];
+            let signer_seeds: &[&[&[u8]]] = &[&[
+                b"snapshot",
+                proposal_key.as_ref(),
+                stake.owner.as_ref(),
+                &bump_seed,
+            ]];
+            system_program::create_account(
+                CpiContext::new_with_signer(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::CreateAccount {
+                        from: ctx.accounts.authority.to_account_info(),
+                        to: pair[1].clone(),
+                    },
+                    signer_seeds,
+                ),
+                rent.minimum_balance(space),
+                space as u64,
+                &crate::ID,
+            )?;
+
+            let record = Snapshot {
+                proposal: proposal_key,
+                voter: stake.owner,
+                weight: stake.amount,
+                bump,
+            };
+            record.try_serialize(&mut &mut pair[1].try_borrow_mut_data()?[..])?;
+
+            snapshot.total_weight = snapshot
+                .total_weight
+                .checked_add(stake.amount)
+                .ok_or(ErrorCode::MathOverflow)?;
+            snapshot_count += 1;
+        }
+
+        snapshot.snapshot_taken = true;
+
+        emit_seq!(
+            events,
+            VoteSnapshotTaken {
+                proposal_id: proposal.id,
+                snapshot_count,
+            }
+        );
+
+        Ok(())
+    }
+
+    // Vote on an open governance proposal
+    pub fn vote_on_proposal(ctx: Context<VoteOnProposal>, vote: VoteChoice) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let clock = Clock::get()?;
+        let governance = &ctx.accounts.governance;
+        let proposal = &mut ctx.accounts.proposal;
+        let voter = ctx.accounts.voter.key();
+
+        require!(
+            proposal.status == ProposalStatus::Voting,
+            ErrorCode::ProposalNotVoting
+        );
+        require!(
+            clock.unix_timestamp < proposal.voting_ends_at,
+            ErrorCode::VotingClosed
+        );
+
+        // Snapshot proposals weigh votes by the stake captured before voting opened,
+        // discounted while that stake is young, and pass on a majority of the captured
+        // stake; others are one vote per governance member
+        let (weight, quorum) = match &proposal.snapshot {
+            Some(snapshot) => {
+                require!(snapshot.snapshot_taken, ErrorCode::SnapshotNotTaken);
+                let record = ctx
+                    .accounts
+                    .snapshot
+                    .as_ref()
+                    .ok_or(ErrorCode::NoVoteSnapshot)?;
+                require!(
+                    record.proposal == proposal.key() && record.voter == voter,
+                    ErrorCode::NoVoteSnapshot
+                );
+                let stake = ctx
+                    .accounts
+                    .stake
+                    .as_ref()
+                    .ok_or(ErrorCode::VoterStakeMissing)?;
+                let days_staked = logic::staked_days(clock.unix_timestamp, stake.stake_start);
+                let effective_weight = logic::decayed_vote_weight(record.weight, days_staked);
+                emit_seq!(
+                    events,
+                    VoteCastWithDecay {
+                        proposal_id: proposal.id,
+                        voter,
+                        nominal_weight: record.weight,
+                        effective_weight,
+                        days_staked,
+                    }
+                );
+                (effective_weight, snapshot.total_weight / 2 + 1)
+            }
+            None => {
+                require!(
+                    governance.is_authorized(&voter),
+                    ErrorCode::UnauthorizedGovernance
+                );
+                (
+                    1,
+                    logic::proposal_quorum(
+                        &proposal.urgency,
+                        governance.quorum,
+                        governance.emergency_quorum,
+                    ),
+                )
+            }
+        };
+
+        let record = &mut ctx.accounts.vote_record;
+        record.proposal = proposal.key();
+        record.voter = voter;
+        record.vote_direction = vote.clone();
+
+        match vote {
+            VoteChoice::Approve => proposal.approval_votes += weight,
+            VoteChoice::Reject => proposal.rejection_votes += weight,
+            VoteChoice::Abstain => proposal.abstain_weight += weight,
+        }
+
+        match logic::vote_outcome(
+            proposal.approval_votes,
+            proposal.rejection_votes,
+            proposal.abstain_weight,
+            quorum,
+        ) {
+            Some(true) => {
+                proposal.status = ProposalStatus::Approved;
+                proposal.quorum_reached_at = clock.unix_timestamp;
+                ctx.accounts.governance.close_vote();
+            }
+            Some(false) => {
+                proposal.status = ProposalStatus::Rejected;
+                ctx.accounts.governance.close_vote();
+            }
+            None => {}
+        }
+
+        emit_seq!(
+            events,
+            ProposalVoted {
+                proposal_id: proposal.id,
+                voter: record.voter,
+                vote: logic::vote_choice_label(&vote).to_string(),
+                weight,
+                timestamp: clock.unix_timestamp,
+            }
+        );
+
+        Ok(())
+    }
+
+    // Reject a proposal whose voting window ended without reaching quorum, so it stops
+    // counting as an active vote. Anyone may call it.
+    pub fn close_expired_proposal(ctx: Context<CloseExpiredProposal>) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let proposal = &mut ctx.accounts.proposal;
+        require!(
+            proposal.status == ProposalStatus::Voting,
+            ErrorCode::ProposalNotVoting
+        );
+        require!(
+            Clock::get()?.unix_timestamp >= proposal.voting_ends_at,
+            ErrorCode::VotingStillOpen
+        );
+
+        proposal.status = ProposalStatus::Rejected;
+        ctx.accounts.governance.close_vote();
+        emit_seq!(
+            events,
+            ProposalExpired {
+                proposal_id: proposal.id,
+                approval_votes: proposal.approval_votes,
+                rejection_votes: proposal.rejection_votes,
+                abstain_weight: proposal.abstain_weight,
+            }
+        );
+        Ok(())
+    }
+
+    // Emergency stop for an approved proposal whose execution path turns out to be
+    // broken. Only the proposal's cancellation authority may call it, and only while
+    // the timelock is still running.
+    pub fn cancel_execution(ctx: Context<CancelExecution>, reason: [u8; 128]) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let now = Clock::get()?.unix_timestamp;
+        let proposal = &mut ctx.accounts.proposal;
+
+        require!(
+            proposal.status == ProposalStatus::Approved,
+            ErrorCode::ProposalNotApproved
+        );
+        require!(!proposal.is_cancelled, ErrorCode::ExecutionCancelled);
+        require!(
+            !logic::timelock_elapsed(
+                now,
+                proposal.quorum_reached_at,
+                ctx.accounts.governance.timelock_duration
+            ),
+            ErrorCode::CancellationWindowClosed
+        );
+
+        proposal.is_cancelled = true;
+
+        emit_seq!(
+            events,
+            ExecutionCancelled {
+                proposal_id: proposal.id,
+                reason_hash: hash(&reason).to_bytes(),
+                cancelled_by: ctx.accounts.cancellation_authority.key(),
+            }
+        );
+        Ok(())
+    }
+
+    // Swap treasury funds for IRIS through the proposal's pinned route and burn the proceeds.
+    // Remaining accounts are the DEX route accounts, which must include the pinned pool.
+    pub fn buyback_and_burn<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BuybackAndBurn<'info>>,
+        max_amount_in: u64,
+        min_iris_out: u64,
+        route_data: Vec<u8>,
+    ) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let clock = Clock::get()?;
+        let proposal = &mut ctx.accounts.proposal;
+        require_executable(proposal, &ctx.accounts.governance, clock.unix_timestamp)?;
+
+        let (dex_program, pool, iris_mint, cap_in, floor_out) = match proposal.proposal_type {
+            ProposalType::BuybackAndBurn {
+                dex_program,
+                pool,
+                iris_mint,
+                max_amount_in,
+                min_iris_out,
+            } => (dex_program, pool, iris_mint, max_amount_in, min_iris_out),
+            _ => return Err(ErrorCode::WrongProposalType.into()),
+        };
+
+        // Slippage bounds are mandatory and may only tighten the approved ones
+        require!(
+            max_amount_in > 0 && min_iris_out > 0,
+            ErrorCode::SlippageBoundsRequired
+        );
+        require!(
+            max_amount_in <= cap_in && min_iris_out >= floor_out,
+            ErrorCode::SlippageBoundsRequired
+        );
+        require!(
+            ctx.accounts.treasury.dex_allowlist.contains(&dex_program)
+                && ctx.accounts.dex_program.key() == dex_program,
+            ErrorCode::RouteNotAllowed
+        );
+        require!(
+            ctx.accounts.iris_mint.key() == iris_mint,
+            ErrorCode::InvalidMint
+        );
+        require!(
+            ctx.remaining_accounts
+                .iter()
+                .any(|account| account.key() == pool),
+            ErrorCode::RouteNotAllowed
+        );
+
+        let treasury_key = ctx.accounts.treasury.key();
+        let spend_before = ctx.accounts.treasury_vault.amount;
+        treasury::ensure_reserve(
+            &ctx.accounts.config,
+            spend_before.saturating_sub(max_amount_in),
+        )?;
+        let iris_before = ctx.accounts.treasury_iris_account.amount;
+
+        let bump = [ctx.accounts.treasury.bump];
+        let signer_seeds: &[&[&[u8]]] = &[&[b"treasury", &bump]];
+
+        invoke_external(
+            &ctx.accounts.dex_program,
+            ctx.remaining_accounts,
+            route_data,
+            treasury_key,
+            signer_seeds,
+        )?;
+
+        ctx.accounts.treasury_vault.reload()?;
+        ctx.accounts.treasury_iris_account.reload()?;
+        let spent = spend_before.saturating_sub(ctx.accounts.treasury_vault.amount);
+        let burned = ctx
+            .accounts
+            .treasury_iris_account
+            .amount
+            .saturating_sub(iris_before);
+
+        require!(spent <= max_amount_in, ErrorCode::SlippageExceeded);
+        require!(burned >= min_iris_out, ErrorCode::SlippageExceeded);
+        treasury::ensure_reserve(&ctx.accounts.config, ctx.accounts.treasury_vault.amount)?;
+
+        token_interface::burn(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::Burn {
+                    mint: ctx.accounts.iris_mint.to_account_info(),
+                    from: ctx.accounts.treasury_iris_account.to_account_info(),
+                    authority: ctx.accounts.treasury.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            burned,
+        )?;
+
+        let treasury = &mut ctx.accounts.treasury;
+        treasury.total_outflows = treasury
+            .total_outflows
+            .checked_add(spent)
+            .ok_or(ErrorCode::MathOverflow)?;
+        proposal.status = ProposalStatus::Executed;
+
+        // The swap moved funds directly, so the ledger entry is recorded here rather than in treasury::debit
+        emit_seq!(
+            events,
+            TreasuryDebit {
+                ledger_seq: next_ledger_seq(&mut ctx.accounts.config)?,
+                instruction: LedgerInstruction::BuybackAndBurn,
+                mint: treasury.mint,
+                counterparty: dex_program,
+                amount: spent,
+                reference: None,
+                timestamp: clock.unix_timestamp,
+            }
+        );
+
+        emit_seq!(
+            events,
+            BuybackExecuted {
+                proposal_id: proposal.id,
+                spent,
+                burned,
+                timestamp: clock.unix_timestamp,
+            }
+        );
+
+        Ok(())
+    }
+
+    // Apply an approved treasury reserve floor change once its timelock has passed
+    pub fn execute_reserve_floor_proposal(ctx: Context<ExecuteReserveFloorProposal>) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let clock = Clock::get()?;
+        let proposal = &mut ctx.accounts.proposal;
+        require_executable(proposal, &ctx.accounts.governance, clock.unix_timestamp)?;
+
+        let ProposalType::SetTreasuryReserveFloor { floor } = proposal.proposal_type else {
+            return Err(ErrorCode::WrongProposalType.into());
+        };
+
+        let config = &mut ctx.accounts.config;
+        let old_floor = config.treasury_reserve_floor;
+        config.treasury_reserve_floor = floor;
+        proposal.status = ProposalStatus::Executed;
+
+        emit_seq!(
+            events,
+            ReserveFloorUpdated {
+                proposal_id: proposal.id,
+                old_floor,
+                new_floor: floor,
+                timestamp: clock.unix_timestamp,
+            }
+        );
+
+        Ok(())
+    }
+
+    // Add or remove a protection swap program once its proposal has passed the timelock
+    pub fn execute_swap_allowlist_proposal(
+        ctx: Context<ExecuteReserveFloorProposal>,
+    ) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let clock = Clock::get()?;
+        let proposal = &mut ctx.accounts.proposal;
+        require_executable(proposal, &ctx.accounts.governance, clock.unix_timestamp)?;
+
+        let ProposalType::SetSwapProgramAllowed { program, allowed } = proposal.proposal_type
+        else {
+            return Err(ErrorCode::WrongProposalType.into());
+        };
+
+        let allowlist = &mut ctx.accounts.config.swap_program_allowlist;
+        allowlist.retain(|listed| *listed != program);
+        if allowed {
+            require!(
+                allowlist.len() < MAX_SWAP_PROGRAMS,
+                ErrorCode::AllowlistFull
+            );
+            allowlist.push(program);
+        }
+        proposal.status = ProposalStatus::Executed;
+
+        emit_seq!(
+            events,
+            SwapProgramAllowlistUpdated {
+                proposal_id: proposal.id,
+                program,
+                allowed,
+            }
+        );
+
+        Ok(())
+    }
+
+    // Apply a passed UpdateQuorum or UpdateVotingDuration proposal once no other vote is open
+    pub fn execute_governance_params_proposal(
+        ctx: Context<ExecuteGovernanceParamsProposal>,
+    ) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let proposal = &mut ctx.accounts.proposal;
+        require_executable(
+            proposal,
+            &ctx.accounts.governance,
+            Clock::get()?.unix_timestamp,
+        )?;
+
+        let governance = &mut ctx.accounts.governance;
+        let (quorum, emergency_quorum, voting_duration) = match proposal.proposal_type {
+            ProposalType::UpdateQuorum {
+                quorum,
+                emergency_quorum,
+            } => (quorum, emergency_quorum, governance.voting_duration),
+            ProposalType::UpdateVotingDuration { voting_duration } => (
+                governance.quorum,
+                governance.emergency_quorum,
+                voting_duration,
+            ),
+            _ => return Err(ErrorCode::WrongProposalType.into()),
+        };
+        apply_governance_params(
+            governance,
+            quorum,
+            emergency_quorum,
+            voting_duration,
+            Some(proposal.id),
+            &mut events,
+        )?;
+        proposal.status = ProposalStatus::Executed;
+        Ok(())
+    }
+
+    // Hand the config to a new admin by governance vote. Only for once the upgrade
+    // authority is burned or held by governance, since accept_admin can't be
+    // satisfied after that.
+    pub fn execute_set_admin_proposal(ctx: Context<ExecuteSetAdminProposal>) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let proposal = &mut ctx.accounts.proposal;
+        require_executable(
+            proposal,
+            &ctx.accounts.governance,
+            Clock::get()?.unix_timestamp,
+        )?;
+
+        let ProposalType::SetAdminUnchecked { new_admin } = proposal.proposal_type else {
+            return Err(ErrorCode::WrongProposalType.into());
+        };
+        require!(
+            logic::upgrade_authority_relinquished(
+                ctx.accounts.program_data.upgrade_authority_address,
+                governed_upgrade_authority().0
+            ),
+            ErrorCode::UpgradeAuthorityNotBurned
+        );
+
+        let config = &mut ctx.accounts.config;
+        let previous_admin = config.admin;
+        config.admin = new_admin;
+        config.pending_admin = None;
+        proposal.status = ProposalStatus::Executed;
+
+        emit_seq!(
+            events,
+            AdminChanged {
+                previous_admin,
+                new_admin,
+            }
+        );
+        Ok(())
+    }
+
+    // Hand program upgrades to governance for good. The upgrade authority must already
+    // have been moved to the governed upgrade authority PDA.
+    pub fn enable_governed_upgrades(ctx: Context<EnableGovernedUpgrades>) -> Result<()> {
+        require_direct_call(&ctx.accounts.config, ctx.accounts.instructions.as_ref())?;
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let config = &mut ctx.accounts.config;
+        require!(
+            !config.upgrades_governed,
+            ErrorCode::UpgradesAlreadyGoverned
+        );
+        let (upgrade_authority, _) = governed_upgrade_authority();
+        require!(
+            logic::is_upgrade_authority(
+                ctx.accounts.program_data.upgrade_authority_address,
+                upgrade_authority
+            ),
+            ErrorCode::InvalidUpgradeAuthority
+        );
+        config.upgrades_governed = true;
+
+        emit_seq!(
+            events,
+            GovernedUpgradesEnabled {
+                admin: config.admin,
+                upgrade_authority,
+            }
+        );
+        Ok(())
+    }
+
+    // Upgrade the program from an approved UpgradeProgram proposal's buffer
+    pub fn execute_program_upgrade(ctx: Context<ExecuteProgramUpgrade>) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        require!(
+            ctx.accounts.config.upgrades_governed,
+            ErrorCode::UpgradesNotGoverned
+        );
+        let proposal = &mut ctx.accounts.proposal;
+        require_executable(
+            proposal,
+            &ctx.accounts.governance,
+            Clock::get()?.unix_timestamp,
+        )?;
+
+        let ProposalType::UpgradeProgram {
+            new_program_data,
+            upgrade_authority,
+        } = proposal.proposal_type
+        else {
+            return Err(ErrorCode::WrongProposalType.into());
+        };
+        let (authority, bump) = governed_upgrade_authority();
+        require!(
+            upgrade_authority == authority,
+            ErrorCode::InvalidUpgradeAuthority
+        );
+        require!(
+            ctx.accounts.buffer.key() == new_program_data,
+            ErrorCode::UpgradeBufferMismatch
+        );
+
+        let ix = bpf_loader_upgradeable::upgrade(
+            &crate::ID,
+            &new_program_data,
+            &authority,
+            &ctx.accounts.spill.key(),
+        );
+        invoke_signed(
+            &ix,
+            &[
+                ctx.accounts.program_data.to_account_info(),
+                ctx.accounts.program.to_account_info(),
+                ctx.accounts.buffer.to_account_info(),
+                ctx.accounts.spill.to_account_info(),
+                ctx.accounts.rent.to_account_info(),
+                ctx.accounts.clock.to_account_info(),
+                ctx.accounts.upgrade_authority.to_account_info(),
+                ctx.accounts.loader.to_account_info(),
+            ],
+            &[&[ProtocolConfig::UPGRADE_AUTHORITY_SEED, &[bump]]],
+        )?;
+
+        let config = &mut ctx.accounts.config;
+        config.program_version = config
+            .program_version
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        proposal.status = ProposalStatus::Executed;
+
+        emit_seq!(
+            events,
+            ProgramUpgradeExecuted {
+                proposal_id: proposal.id,
+                new_version: config.program_version,
+            }
+        );
+        Ok(())
+    }
+
+    // Write off an unrecoverable claim shortfall once governance approves it. Only
+    // allowed when the vault's free capital couldn't cover the shortfall anyway.
+    pub fn write_off_shortfall(ctx: Context<WriteOffShortfall>) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let clock = Clock::get()?;
+        let proposal = &mut ctx.accounts.proposal;
+        require_executable(proposal, &ctx.accounts.governance, clock.unix_timestamp)?;
+
+        let ProposalType::WriteOffShortfall { claim: claim_key } = proposal.proposal_type else {
+            return Err(ErrorCode::WrongProposalType.into());
+        };
+
+        let claim = &mut ctx.accounts.claim;
+        require!(claim.key() == claim_key, ErrorCode::ClaimMismatch);
+        require!(
+            !claim.written_off && claim.shortfall > 0,
+            ErrorCode::NoShortfallToWriteOff
+        );
+
+        let pool = &mut ctx.accounts.pool;
+        let free = ctx
+            .accounts
+            .vault
+            .amount
+            .saturating_sub(pool.approved_liabilities);
+        require!(free < claim.shortfall, ErrorCode::ShortfallPayable);
+
+        let amount = claim.shortfall;
+        pool.pending_shortfalls = pool.pending_shortfalls.saturating_sub(amount);
+        // A claim still waiting in the open round no longer counts toward it
+        if claim.shortfall_round < pool.shortfall_round {
+            pool.round_remaining = pool.round_remaining.saturating_sub(amount);
+        }
+        claim.shortfall = 0;
+        claim.written_off = true;
+        proposal.status = ProposalStatus::Executed;
+
+        emit_seq!(
+            events,
+            ShortfallWrittenOff {
+                claim_id: claim.key(),
+                proposal_id: proposal.id,
+                amount,
+                timestamp: clock.unix_timestamp,
+            }
+        );
+
+        Ok(())
+    }
+
+    // Replace the lending programs insurance NFTs may be pledged to
+    pub fn set_lending_allowlist(
+        ctx: Context<SetLendingAllowlist>,
+        programs: Vec<Pubkey>,
+    ) -> Result<()> {
+        require_direct_call(&ctx.accounts.config, ctx.accounts.instructions.as_ref())?;
+        require!(
+            programs.len() <= MAX_LENDING_PROGRAMS,
+            ErrorCode::AllowlistFull
+        );
+        ctx.accounts.config.lending_allowlist = programs;
+        Ok(())
+    }
+
+    // Replace the payment stream programs policies may renew through
+    pub fn set_stream_allowlist(ctx: Context<SetConfigParam>, programs: Vec<Pubkey>) -> Result<()> {
+        require_direct_call(&ctx.accounts.config, ctx.accounts.instructions.as_ref())?;
+        require!(
+            programs.len() <= MAX_STREAM_PROGRAMS,
+            ErrorCode::AllowlistFull
+        );
+        ctx.accounts.config.stream_allowlist = programs;
+        Ok(())
+    }
+
+    // Replace the transfer hook programs accepted on Token-2022 covered assets
+    pub fn set_transfer_hook_allowlist(
+        ctx: Context<SetConfigParam>,
+        programs: Vec<Pubkey>,
+    ) -> Result<()> {
+        require_direct_call(&ctx.accounts.config, ctx.accounts.instructions.as_ref())?;
+        require!(
+            programs.len() <= MAX_TRANSFER_HOOK_PROGRAMS,
+            ErrorCode::AllowlistFull
+        );
+        ctx.accounts.config.transfer_hook_allowlist = programs;
+        Ok(())
+    }
+
+    // Replace the multisig programs whose wallets may act on their own accounts via CPI
+    pub fn set_multisig_allowlist(
+        ctx: Context<SetConfigParam>,
+        programs: Vec<Pubkey>,
+    ) -> Result<()> {
+        require_direct_call(&ctx.accounts.config, ctx.accounts.instructions.as_ref())?;
+        require!(
+            programs.len() <= MAX_MULTISIG_PROGRAMS,
+            ErrorCode::AllowlistFull
+        );
+        ctx.accounts.config.multisig_allowlist = programs;
+        Ok(())
+    }
+
+    // Replace the yield programs claim coverage bonds may be deposited into
+    pub fn set_yield_allowlist(ctx: Context<SetConfigParam>, programs: Vec<Pubkey>) -> Result<()> {
+        require_direct_call(&ctx.accounts.config, ctx.accounts.instructions.as_ref())?;
+        require!(
+            programs.len() <= MAX_YIELD_PROGRAMS,
+            ErrorCode::AllowlistFull
+        );
+        ctx.accounts.config.yield_allowlist = programs;
+        Ok(())
+    }
+
+    // Open a payment stream from the owner to the treasury that keeps the policy renewed.
+    // Remaining accounts and `stream_data` form the stream program's create instruction;
+    // the rate must cover the policy's current premium every renewal period.
+    pub fn setup_renewal_stream<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SetupRenewalStream<'info>>,
+        stream_program: Pubkey,
+        rate_per_second: u64,
+        stream_data: Vec<u8>,
+    ) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let clock = Clock::get()?;
+        let insurance_nft = &ctx.accounts.insurance_nft;
+
+        require!(
+            !logic::is_expired(clock.unix_timestamp, insurance_nft.expiry),
+            ErrorCode::InsuranceExpired
+        );
+        require!(
+            ctx.accounts
+                .config
+                .stream_allowlist
+                .contains(&stream_program)
+                && ctx.accounts.stream_program.key() == stream_program,
+            ErrorCode::StreamProgramNotAllowed
+        );
+        require!(
+            ctx.remaining_accounts
+                .iter()
+                .any(|account| account.key() == ctx.accounts.stream.key()),
+            ErrorCode::InvalidStream
+        );
+
+        let (_, premium) = pool::quote_premium(
+            &ctx.accounts.pool,
+            ctx.accounts.pool_vault.amount,
+            ctx.accounts.config.tier(insurance_nft.tier)?,
+            insurance_nft.payout_cap,
+            insurance_nft.portfolio_value_at_mint,
+            InsuranceNFT::STREAM_RENEWAL_PERIOD_SECS as u64,
+        )?;
+        require!(
+            rate_per_second > 0
+                && rate_per_second
+                    >= logic::min_stream_rate(premium, InsuranceNFT::STREAM_RENEWAL_PERIOD_SECS),
+            ErrorCode::StreamRateTooLow
+        );
+
+        invoke_external(
+            &ctx.accounts.stream_program,
+            ctx.remaining_accounts,
+            stream_data,
+            ctx.accounts.owner.key(),
+            &[],
+        )?;
+
+        let insurance_nft = &mut ctx.accounts.insurance_nft;
+        insurance_nft.stream_config = StreamConfig {
+            stream_program,
+            stream_id: ctx.accounts.stream.key(),
+            rate_per_second,
+            active: true,
+        };
+
+        emit_indexed!(
+            events,
+            insurance_nft.owner,
+            RenewalStreamCreated {
+                wallet: insurance_nft.owner,
+                mint: insurance_nft.token_mint,
+                stream_program,
+                stream_id: insurance_nft.stream_config.stream_id,
+                rate_per_second,
+            }
+        );
+
+        Ok(())
+    }
+
+    // Keeper crank: withdraw what the policy's stream has paid into the treasury and
+    // extend the policy by the time it bought. Remaining accounts and `withdraw_data`
+    // form the stream program's withdraw instruction.
+    pub fn process_stream_renewal<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ProcessStreamRenewal<'info>>,
+        withdraw_data: Vec<u8>,
+    ) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let clock = Clock::get()?;
+        let insurance_nft = &ctx.accounts.insurance_nft;
+
+        require!(
+            insurance_nft.stream_config.active,
+            ErrorCode::StreamNotActive
+        );
+        require!(
+            !logic::is_expired(clock.unix_timestamp, insurance_nft.expiry),
+            ErrorCode::InsuranceExpired
+        );
+        require!(
+            logic::stream_renewal_due(
+                clock.unix_timestamp,
+                insurance_nft.expiry,
+                InsuranceNFT::RENEWAL_TRIGGER_SECS
+            ),
+            ErrorCode::RenewalNotDue
+        );
+        require!(
+            ctx.remaining_accounts
+                .iter()
+                .any(|account| account.key() == ctx.accounts.stream.key()),
+            ErrorCode::InvalidStream
+        );
+
+        // Only what actually lands in the treasury counts as paid
+        let balance_before = ctx.accounts.treasury_account.amount;
+        invoke_external(
+            &ctx.accounts.stream_program,
+            ctx.remaining_accounts,
+            withdraw_data,
+            ctx.accounts.keeper.key(),
+            &[],
+        )?;
+        ctx.accounts.treasury_account.reload()?;
+        let payment_streamed = ctx
+            .accounts
+            .treasury_account
+            .amount
+            .saturating_sub(balance_before);
+        require!(payment_streamed > 0, ErrorCode::NothingStreamed);
+
+        let extension = logic::stream_extension_secs(
+            payment_streamed,
+            insurance_nft.stream_config.rate_per_second,
+        )?;
+        let insurance_nft = &mut ctx.accounts.insurance_nft;
+        insurance_nft.expiry = insurance_nft
+            .expiry
+            .checked_add(extension)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        treasury::credit(
+            &mut ctx.accounts.treasury,
+            &mut ctx.accounts.config,
+            &mut events,
+            insurance_nft.owner,
+            payment_streamed,
+            LedgerInstruction::ProcessStreamRenewal,
+        )?;
+
+        emit_indexed!(
+            events,
+            insurance_nft.owner,
+            StreamRenewalProcessed {
+                wallet: insurance_nft.owner,
+                mint: insurance_nft.token_mint,
+                new_expiry: insurance_nft.expiry,
+                payment_streamed,
+            }
+        );
+
+        Ok(())
+    }
+
+    // Lock an insurance NFT in escrow and open a loan against it with an allowlisted lender.
+    // Remaining accounts and `loan_data` form the lender's open-loan instruction.
+    pub fn collateralize_nft<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CollateralizeNft<'info>>,
+        lending_protocol: Pubkey,
+        loan_amount: u64,
+        loan_data: Vec<u8>,
+    ) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let clock = Clock::get()?;
+        let insurance_nft = &ctx.accounts.insurance_nft;
+
+        require!(!insurance_nft.collateralized, ErrorCode::NftCollateralized);
+        require_policy_active(insurance_nft, clock.unix_timestamp)?;
+        require!(loan_amount > 0, ErrorCode::InvalidAmount);
+        require!(
+            ctx.accounts
+                .config
+                .lending_allowlist
+                .contains(&lending_protocol)
+                && ctx.accounts.lending_program.key() == lending_protocol,
+            ErrorCode::LendingProtocolNotAllowed
+        );
+
+        // Move the NFT into the program-controlled escrow
+        tokens::transfer(
+            &ctx.accounts.token_program,
+            ctx.accounts.owner_nft_account.to_account_info(),
+            ctx.accounts.collateral_escrow.to_account_info(),
+            &ctx.accounts.nft_mint,
+            ctx.accounts.owner.to_account_info(),
+            1,
+            &[],
+        )?;
+
+        let nft_key = insurance_nft.key();
+        let bump = [ctx.bumps.collateral_authority];
+        let signer_seeds: &[&[&[u8]]] = &[&[b"collateral", nft_key.as_ref(), &bump]];
+        invoke_external(
+            &ctx.accounts.lending_program,
+            ctx.remaining_accounts,
+            loan_data,
+            ctx.accounts.collateral_authority.key(),
+            signer_seeds,
+        )?;
+
+        let insurance_nft = &mut ctx.accounts.insurance_nft;
+        insurance_nft.collateralized = true;
+        insurance_nft.collateral_loan = ctx.accounts.loan.key();
+        insurance_nft.lending_protocol = lending_protocol;
+
+        emit_indexed!(
+            events,
+            insurance_nft.owner,
+            NftCollateralized {
+                wallet: insurance_nft.owner,
+                mint: insurance_nft.token_mint,
+                lending_protocol,
+                loan: insurance_nft.collateral_loan,
+                loan_amount,
+                timestamp: clock.unix_timestamp,
+            }
+        );
+
+        Ok(())
+    }
+
+    // Repay (or have the lender release) the loan and return the NFT to its owner.
+    // Remaining accounts and `repay_data` form the lender's repay/release instruction.
+    pub fn release_nft_collateral<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ReleaseNftCollateral<'info>>,
+        repay_data: Vec<u8>,
+    ) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let insurance_nft = &ctx.accounts.insurance_nft;
+        require!(
+            insurance_nft.collateralized,
+            ErrorCode::NftNotCollateralized
+        );
+        require!(
+            ctx.accounts.lending_program.key() == insurance_nft.lending_protocol,
+            ErrorCode::LendingProtocolNotAllowed
+        );
+
+        let nft_key = insurance_nft.key();
+        let bump = [ctx.bumps.collateral_authority];
+        let signer_seeds: &[&[&[u8]]] = &[&[b"collateral", nft_key.as_ref(), &bump]];
+
+        if !repay_data.is_empty() {
+            invoke_external(
+                &ctx.accounts.lending_program,
+                ctx.remaining_accounts,
+                repay_data,
+                ctx.accounts.collateral_authority.key(),
+                signer_seeds,
+            )?;
+        }
+
+        // The lender must have released the NFT back to escrow
+        ctx.accounts.collateral_escrow.reload()?;
+        require!(
+            ctx.accounts.collateral_escrow.amount == 1,
+            ErrorCode::CollateralNotReleased
+        );
+
+        tokens::transfer(
+            &ctx.accounts.token_program,
+            ctx.accounts.collateral_escrow.to_account_info(),
+            ctx.accounts.owner_nft_account.to_account_info(),
+            &ctx.accounts.nft_mint,
+            ctx.accounts.collateral_authority.to_account_info(),
+            1,
+            signer_seeds,
+        )?;
+
+        let insurance_nft = &mut ctx.accounts.insurance_nft;
+        let loan = insurance_nft.collateral_loan;
+        insurance_nft.collateralized = false;
+        insurance_nft.collateral_loan = Pubkey::default();
+        insurance_nft.lending_protocol = Pubkey::default();
+
+        emit_indexed!(
+            events,
+            insurance_nft.owner,
+            CollateralReleased {
+                wallet: insurance_nft.owner,
+                mint: insurance_nft.token_mint,
+                loan,
+                timestamp: Clock::get()?.unix_timestamp,
+            }
+        );
+
+        Ok(())
+    }
+
+    // List a policy NFT for sale at `price` (payment mint units) for `duration` seconds.
+    // The NFT sits in the listing's vault until it is bought or the listing cancelled.
+    pub fn list_nft_for_sale(
+        ctx: Context<ListNftForSale>,
+        price: u64,
+        duration: i64,
+    ) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let now = Clock::get()?.unix_timestamp;
+        let insurance_nft = &ctx.accounts.insurance_nft;
+        require!(!insurance_nft.collateralized, ErrorCode::NftCollateralized);
+        require!(
+            insurance_nft.compressed_leaf.is_none(),
+            ErrorCode::CompressedPolicyNotListable
+        );
+        require_policy_active(insurance_nft, now)?;
+        require!(price > 0, ErrorCode::InvalidAmount);
+        require!(duration > 0, ErrorCode::InvalidDuration);
+
+        tokens::transfer(
+            &ctx.accounts.token_program,
+            ctx.accounts.seller_nft_account.to_account_info(),
+            ctx.accounts.listing_vault.to_account_info(),
+            &ctx.accounts.nft_mint,
+            ctx.accounts.seller.to_account_info(),
+            1,
+            &[],
+        )?;
+
+        let listing = &mut ctx.accounts.listing;
+        listing.nft_mint = insurance_nft.token_mint;
+        listing.price = price;
+        listing.listed_at = now;
+        listing.expiry = now.checked_add(duration).ok_or(ErrorCode::MathOverflow)?;
+        listing.seller = ctx.accounts.seller.key();
+        listing.bump = ctx.bumps.listing;
+
+        emit_indexed!(
+            events,
+            listing.seller,
+            NftListed {
+                mint: listing.nft_mint,
+                price,
+                seller: listing.seller,
+                expiry: listing.expiry,
+            }
+        );
+        Ok(())
+    }
+
+    // Buy a listed policy NFT. The seller is paid the price less the marketplace fee,
+    // which goes to the treasury, and the policy moves to the buyer.
+    pub fn buy_listed_nft(ctx: Context<BuyListedNft>) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let listing = &ctx.accounts.listing;
+        require!(
+            logic::listing_open(Clock::get()?.unix_timestamp, listing.expiry),
+            ErrorCode::ListingExpired
+        );
+        let (price, seller, mint) = (listing.price, listing.seller, listing.nft_mint);
+        let buyer = ctx.accounts.buyer.key();
+        let fee = logic::marketplace_fee(price, ctx.accounts.config.marketplace_fee_bps);
+
+        let buyer_user_account = &mut ctx.accounts.buyer_user_account;
+        require!(
+            buyer_user_account.linked_nfts.len() < MAX_LINKED_NFTS,
+            ErrorCode::TooManyLinkedNfts
+        );
+
+        tokens::transfer(
+            &ctx.accounts.token_program,
+            ctx.accounts.buyer_payment_account.to_account_info(),
+            ctx.accounts.seller_payment_account.to_account_info(),
+            &ctx.accounts.payment_mint,
+            ctx.accounts.buyer.to_account_info(),
+            price - fee,
+            &[],
+        )?;
+        if fee > 0 {
+            tokens::transfer_in(
+                &ctx.accounts.token_program,
+                &ctx.accounts.buyer_payment_account,
+                &mut ctx.accounts.treasury_account,
+                &ctx.accounts.payment_mint,
+                ctx.accounts.buyer.to_account_info(),
+                fee,
+            )?;
+            treasury::credit(
+                &mut ctx.accounts.treasury,
+                &mut ctx.accounts.config,
+                &mut events,
+                buyer,
+                fee,
+                LedgerInstruction::BuyListedNft,
+            )?;
+        }
+
+        let nft_key = ctx.accounts.insurance_nft.key();
+        release_listed_nft(
+            &ctx.accounts.listing,
+            &nft_key,
+            &ctx.accounts.listing_vault,
+            ctx.accounts.buyer_nft_account.to_account_info(),
+            &ctx.accounts.nft_mint,
+            ctx.accounts.seller.to_account_info(),
+            &ctx.accounts.token_program,
+        )?;
+
+        ctx.accounts.insurance_nft.owner = buyer;
+        ctx.accounts
+            .seller_user_account
+            .linked_nfts
+            .retain(|linked| linked != &nft_key);
+        ctx.accounts.buyer_user_account.linked_nfts.push(nft_key);
+
+        emit_indexed!(
+            events,
+            buyer,
+            NftSold {
+                mint,
+                price,
+                buyer,
+                seller,
+                fee,
+            }
+        );
+        Ok(())
+    }
+
+    // Withdraw a listing, live or expired, and return the NFT to the seller
+    pub fn cancel_nft_listing(ctx: Context<CancelNftListing>) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let nft_key = ctx.accounts.insurance_nft.key();
+        release_listed_nft(
+            &ctx.accounts.listing,
+            &nft_key,
+            &ctx.accounts.listing_vault,
+            ctx.accounts.seller_nft_account.to_account_info(),
+            &ctx.accounts.nft_mint,
+            ctx.accounts.seller.to_account_info(),
+            &ctx.accounts.token_program,
+        )?;
+
+        let listing = &ctx.accounts.listing;
+        emit_indexed!(
+            events,
+            listing.seller,
+            NftListingCancelled {
+                mint: listing.nft_mint,
+                seller: listing.seller,
+            }
+        );
+        Ok(())
+    }
+
+    // Post a coverage bond for a pending claim: move the policy's face value from the
+    // pool's free capital into a per-claim escrow and deposit it with an allowlisted
+    // yield program. Remaining accounts and `deposit_data` form that program's deposit
+    // instruction, signed by the escrow PDA. The bond still counts toward net assets.
+    pub fn open_yield_escrow<'info>(
+        ctx: Context<'_, '_, 'info, 'info, OpenYieldEscrow<'info>>,
+        yield_protocol: Pubkey,
+        deposit_data: Vec<u8>,
+    ) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        require!(
+            ctx.accounts
+                .governance
+                .is_authorized(&ctx.accounts.authority.key()),
+            ErrorCode::UnauthorizedGovernance
+        );
+        require!(
+            ctx.accounts
+                .config
+                .yield_allowlist
+                .contains(&yield_protocol)
+                && ctx.accounts.yield_program.key() == yield_protocol,
+            ErrorCode::YieldProtocolNotAllowed
+        );
+        require!(
+            ctx.accounts.claim.status == ClaimStatus::Pending,
+            ErrorCode::ClaimNotPending
+        );
+
+        let clock = Clock::get()?;
+        let amount = ctx.accounts.insurance_nft.payout_cap;
+        let pool = &mut ctx.accounts.pool;
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        require!(
+            amount <= pool::net_assets(pool, ctx.accounts.vault.amount),
+            ErrorCode::InsufficientFreeCapital
+        );
+
+        process_payout(
+            pool,
+            &ctx.accounts.vault,
+            &ctx.accounts.escrow_vault,
+            &ctx.accounts.payout_mint,
+            &ctx.accounts.token_program,
+            amount,
+        )?;
+        pool.yield_escrowed = pool
+            .yield_escrowed
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        let claim_key = ctx.accounts.claim.key();
+        let bump = [ctx.bumps.yield_escrow];
+        let signer_seeds: &[&[&[u8]]] = &[&[b"yield_escrow", claim_key.as_ref(), &bump]];
+        invoke_external(
+            &ctx.accounts.yield_program,
+            ctx.remaining_accounts,
+            deposit_data,
+            ctx.accounts.yield_escrow.key(),
+            signer_seeds,
+        )?;
+
+        let escrow = &mut ctx.accounts.yield_escrow;
+        escrow.claim = claim_key;
+        escrow.pool = pool.key();
+        escrow.vault = ctx.accounts.escrow_vault.key();
+        escrow.yield_protocol = yield_protocol;
+        escrow.deposited_at = clock.unix_timestamp;
+        escrow.deposited_amount = amount;
+        escrow.settled = false;
+        escrow.bump = ctx.bumps.yield_escrow;
+
+        emit_seq!(
+            events,
+            PoolWithdrawal {
+                ledger_seq: next_ledger_seq(&mut ctx.accounts.config)?,
+                instruction: LedgerInstruction::OpenYieldEscrow,
+                mint: pool.payout_mint,
+                counterparty: escrow.key(),
+                amount,
+                shares: 0,
+                queued_shares: 0,
+                timestamp: clock.unix_timestamp,
+            }
+        );
+        emit_seq!(
+            events,
+            YieldEscrowCreated {
+                claim_id: claim_key,
+                yield_protocol,
+                amount,
+                timestamp: clock.unix_timestamp,
+            }
+        );
+        Ok(())
+    }
+
+    // Recall a claim's coverage bond once the claim is resolved and return principal and
+    // yield to the pool vault, which pays the claim if it was approved. Remaining accounts
+    // and `withdraw_data` form the yield program's withdraw instruction; pass empty data
+    // if the funds are already back in the escrow vault.
+    pub fn settle_yield_escrow<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SettleYieldEscrow<'info>>,
+        withdraw_data: Vec<u8>,
+    ) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        require!(
+            ctx.accounts
+                .governance
+                .is_authorized(&ctx.accounts.authority.key()),
+            ErrorCode::UnauthorizedGovernance
+        );
+        require!(
+            ctx.accounts.yield_program.key() == ctx.accounts.yield_escrow.yield_protocol,
+            ErrorCode::YieldProtocolNotAllowed
+        );
+        require!(
+            !ctx.accounts.yield_escrow.settled,
+            ErrorCode::YieldEscrowSettled
+        );
+
+        let claim_key = ctx.accounts.claim.key();
+        let bump = [ctx.accounts.yield_escrow.bump];
+        let signer_seeds: &[&[&[u8]]] = &[&[b"yield_escrow", claim_key.as_ref(), &bump]];
+        if !withdraw_data.is_empty() {
+            invoke_external(
+                &ctx.accounts.yield_program,
+                ctx.remaining_accounts,
+                withdraw_data,
+                ctx.accounts.yield_escrow.key(),
+                signer_seeds,
+            )?;
+        }
+        ctx.accounts.escrow_vault.reload()?;
+        let returned = ctx.accounts.escrow_vault.amount;
+        let escrow = &mut ctx.accounts.yield_escrow;
+        let (principal, yield_earned) = logic::yield_escrow_settlement(
+            &ctx.accounts.claim.status,
+            escrow.deposited_amount,
+            returned,
+        )
+        .ok_or(ErrorCode::ClaimNotResolved)?;
+
+        if returned > 0 {
+            tokens::transfer(
+                &ctx.accounts.token_program,
+                ctx.accounts.escrow_vault.to_account_info(),
+                ctx.accounts.vault.to_account_info(),
+                &ctx.accounts.payout_mint,
+                escrow.to_account_info(),
+                returned,
+                signer_seeds,
+            )?;
+        }
+        let pool = &mut ctx.accounts.pool;
+        pool.yield_escrowed = pool.yield_escrowed.saturating_sub(escrow.deposited_amount);
+        escrow.settled = true;
+
+        let now = Clock::get()?.unix_timestamp;
+        emit_seq!(
+            events,
+            PoolDeposit {
+                ledger_seq: next_ledger_seq(&mut ctx.accounts.config)?,
+                instruction: LedgerInstruction::SettleYieldEscrow,
+                mint: pool.payout_mint,
+                counterparty: escrow.key(),
+                amount: returned,
+                shares: 0,
+                bonus_shares: 0,
+                utilization_bps: pool::utilization_bps(
+                    pool,
+                    ctx.accounts.vault.amount.saturating_add(returned)
+                ),
+                timestamp: now,
+            }
+        );
+        emit_seq!(
+            events,
+            YieldEscrowSettled {
+                claim_id: claim_key,
+                status: ctx.accounts.claim.status.clone(),
+                principal,
+                yield_earned,
+                timestamp: now,
+            }
+        );
+        Ok(())
+    }
+
+    // Leave haircut mode once governance deems the pool recapitalized
+    pub fn exit_haircut_mode(ctx: Context<ExitHaircutMode>) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        require!(
+            ctx.accounts
+                .governance
+                .is_authorized(&ctx.accounts.authority.key()),
+            ErrorCode::UnauthorizedGovernance
+        );
+
+        let pool = &mut ctx.accounts.pool;
+        require!(pool.haircut_mode, ErrorCode::NotInHaircutMode);
+
+        pool.haircut_mode = false;
+        pool.haircut_assets = 0;
+        pool.haircut_liabilities = 0;
+
+        emit_seq!(
+            events,
+            HaircutModeExited {
+                authority: ctx.accounts.authority.key(),
+                timestamp: Clock::get()?.unix_timestamp,
+            }
+        );
+
+        Ok(())
+    }
+
+    // Switch between one shared pool and isolated per-tier sub-pools, and set how much
+    // of a sub-pool's net assets may backstop another one in haircut mode
+    pub fn set_sub_pool_mode(
+        ctx: Context<SetSubPoolMode>,
+        isolated_tiers: bool,
+        cross_subsidy_bps: u16,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts
+                .governance
+                .is_authorized(&ctx.accounts.authority.key()),
+            ErrorCode::UnauthorizedGovernance
+        );
+        require!(cross_subsidy_bps <= 10_000, ErrorCode::InvalidBps);
+
+        let config = &mut ctx.accounts.config;
+        config.isolated_tiers = isolated_tiers;
+        config.cross_subsidy_bps = cross_subsidy_bps;
+        Ok(())
+    }
+
+    // Turn the CPI guard on sensitive instructions on or off, and set the programs that
+    // may still wrap them
+    pub fn set_cpi_guard(
+        ctx: Context<SetSubPoolMode>,
+        enabled: bool,
+        allowlist: Vec<Pubkey>,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts
+                .governance
+                .is_authorized(&ctx.accounts.authority.key()),
+            ErrorCode::UnauthorizedGovernance
+        );
+        require!(
+            allowlist.len() <= MAX_CPI_ALLOWLIST,
+            ErrorCode::AllowlistFull
+        );
+
+        let config = &mut ctx.accounts.config;
+        config.cpi_guard_enabled = enabled;
+        config.cpi_allowlist = allowlist;
+        Ok(())
+    }
+
+    // Move capital from a healthy sub-pool into one in haircut mode, limited to
+    // cross_subsidy_bps of the donor's net assets per call
+    pub fn backstop_sub_pool(ctx: Context<BackstopSubPool>, amount: u64) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        require!(
+            ctx.accounts
+                .governance
+                .is_authorized(&ctx.accounts.authority.key()),
+            ErrorCode::UnauthorizedGovernance
+        );
+        require!(amount > 0, ErrorCode::InvalidAmount);
+
+        let clock = Clock::get()?;
+        let donor = &ctx.accounts.donor_pool;
+        let recipient = &ctx.accounts.recipient_pool;
+        require!(
+            donor.tier != 0 && recipient.tier != 0 && donor.tier != recipient.tier,
+            ErrorCode::WrongSubPool
+        );
+        require!(
+            donor.payout_mint == recipient.payout_mint,
+            ErrorCode::InvalidMint
+        );
+        require!(recipient.haircut_mode, ErrorCode::NotInHaircutMode);
+
+        let limit = fee_share::bps_of(
+            pool::net_assets(donor, ctx.accounts.donor_vault.amount),
+            ctx.accounts.config.cross_subsidy_bps,
+        );
+        require!(amount <= limit, ErrorCode::CrossSubsidyExceeded);
+
+        process_payout(
+            donor,
+            &ctx.accounts.donor_vault,
+            &ctx.accounts.recipient_vault,
+            &ctx.accounts.payout_mint,
+            &ctx.accounts.token_program,
+            amount,
+        )?;
+
+        let config = &mut ctx.accounts.config;
+        emit_seq!(
+            events,
+            PoolWithdrawal {
+                ledger_seq: next_ledger_seq(config)?,
+                instruction: LedgerInstruction::BackstopSubPool,
+                mint: donor.payout_mint,
+                counterparty: recipient.key(),
+                amount,
+                shares: 0,
+                queued_shares: 0,
+                timestamp: clock.unix_timestamp,
+            }
+        );
+        emit_seq!(
+            events,
+            PoolDeposit {
+                ledger_seq: next_ledger_seq(config)?,
+                instruction: LedgerInstruction::BackstopSubPool,
+                mint: recipient.payout_mint,
+                counterparty: donor.key(),
+                amount,
+                shares: 0,
+                bonus_shares: 0,
+                utilization_bps: pool::utilization_bps(
+                    recipient,
+                    ctx.accounts.recipient_vault.amount.saturating_add(amount),
+                ),
+                timestamp: clock.unix_timestamp,
+            }
+        );
+        emit_seq!(
+            events,
+            SubPoolBackstopped {
+                from_tier: donor.tier,
+                to_tier: recipient.tier,
+                amount,
+                timestamp: clock.unix_timestamp,
+            }
+        );
+
+        Ok(())
+    }
+
+    // Create the reinsurance tranche that covers what the primary pool can't pay. It
+    // only activates while it holds at least `activation_threshold`.
+    pub fn initialize_reinsurance_pool(
+        ctx: Context<InitializeReinsurancePool>,
+        activation_threshold: u64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts
+                .governance
+                .is_authorized(&ctx.accounts.authority.key()),
+            ErrorCode::UnauthorizedGovernance
+        );
+
+        let reinsurance = &mut ctx.accounts.reinsurance_pool;
+        reinsurance.payout_mint = ctx.accounts.payout_mint.key();
+        reinsurance.vault = ctx.accounts.vault.key();
+        reinsurance.activation_threshold = activation_threshold;
+        reinsurance.bump = ctx.bumps.reinsurance_pool;
+        Ok(())
+    }
+
+    // Fund the reinsurance tranche from the governance authority's token account
+    pub fn deposit_to_reinsurance(ctx: Context<FundReinsurance>, amount: u64) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        require!(
+            ctx.accounts
+                .governance
+                .is_authorized(&ctx.accounts.authority.key()),
+            ErrorCode::UnauthorizedGovernance
+        );
+        require!(amount > 0, ErrorCode::InvalidAmount);
+
+        tokens::transfer_in(
+            &ctx.accounts.token_program,
+            &ctx.accounts.authority_token_account,
+            &mut ctx.accounts.vault,
+            &ctx.accounts.payout_mint,
+            ctx.accounts.authority.to_account_info(),
+            amount,
+        )?;
+
+        let reinsurance = &mut ctx.accounts.reinsurance_pool;
+        reinsurance.pool_balance = reinsurance
+            .pool_balance
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit_seq!(
+            events,
+            ReinsuranceDeposit {
+                ledger_seq: next_ledger_seq(&mut ctx.accounts.config)?,
+                instruction: LedgerInstruction::DepositToReinsurance,
+                mint: reinsurance.payout_mint,
+                counterparty: ctx.accounts.authority.key(),
+                amount,
+                pool_balance: reinsurance.pool_balance,
+                timestamp: Clock::get()?.unix_timestamp,
+            }
+        );
+        Ok(())
+    }
+
+    // Return capital from the reinsurance tranche to the governance authority
+    pub fn withdraw_from_reinsurance(ctx: Context<FundReinsurance>, amount: u64) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        require!(
+            ctx.accounts
+                .governance
+                .is_authorized(&ctx.accounts.authority.key()),
+            ErrorCode::UnauthorizedGovernance
+        );
+        require!(amount > 0, ErrorCode::InvalidAmount);
+
+        let reinsurance = &mut ctx.accounts.reinsurance_pool;
+        require!(
+            amount <= reinsurance.pool_balance,
+            ErrorCode::InsufficientReinsurance
+        );
+        reinsurance.pool_balance -= amount;
+
+        let bump = [reinsurance.bump];
+        let signer_seeds: &[&[&[u8]]] = &[&[b"reinsurance_pool", &bump]];
+        tokens::transfer(
+            &ctx.accounts.token_program,
+            ctx.accounts.vault.to_account_info(),
+            ctx.accounts.authority_token_account.to_account_info(),
+            &ctx.accounts.payout_mint,
+            reinsurance.to_account_info(),
+            amount,
+            signer_seeds,
+        )?;
+
+        emit_seq!(
+            events,
+            ReinsuranceWithdrawal {
+                ledger_seq: next_ledger_seq(&mut ctx.accounts.config)?,
+                instruction: LedgerInstruction::WithdrawFromReinsurance,
+                mint: reinsurance.payout_mint,
+                counterparty: ctx.accounts.authority.key(),
+                amount,
+                pool_balance: reinsurance.pool_balance,
+                timestamp: Clock::get()?.unix_timestamp,
+            }
+        );
+        Ok(())
+    }
+
+    // Set the expected-loss ratio applied to outstanding coverage and the margin the
+    // pool must hold above its requirement before new coverage is halted
+    pub fn set_solvency_buffer(
+        ctx: Context<SetSubPoolMode>,
+        expected_loss_bps: u16,
+        solvency_buffer_bps: u16,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts
+                .governance
+                .is_authorized(&ctx.accounts.authority.key()),
+            ErrorCode::UnauthorizedGovernance
+        );
+        require!(expected_loss_bps <= 10_000, ErrorCode::InvalidBps);
+
+        let config = &mut ctx.accounts.config;
+        config.expected_loss_bps = expected_loss_bps;
+        config.solvency_buffer_bps = solvency_buffer_bps;
+        Ok(())
+    }
+
+    // Permissionless crank: compare the pool's assets to what it needs to cover and halt
+    // or resume new coverage accordingly. Every input is on-chain state.
+    pub fn assert_and_gate(ctx: Context<AssertAndGate>) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        let config = &ctx.accounts.config;
+        let pool = &mut ctx.accounts.pool;
+        let assets = ctx.accounts.vault.amount;
+        let (expected_losses, required) = pool::solvency_requirement(
+            pool,
+            pool.outstanding_coverage,
+            config.expected_loss_bps,
+            config.solvency_buffer_bps,
+        );
+        let healthy = assets as u128 >= required;
+
+        if healthy {
+            // A recovered margin clears both the halt and any governance override
+            pool.halt_overridden = false;
+            if pool.minting_halted {
+                pool.minting_halted = false;
+                emit_seq!(
+                    events,
+                    CoverageMintingResumed {
+                        pool: pool.key(),
+                        assets,
+                        required: required.min(u64::MAX as u128) as u64,
+                        overridden: false,
+                        timestamp: Clock::get()?.unix_timestamp,
+                    }
+                );
+            }
+        } else if !pool.minting_halted && !pool.halt_overridden {
+            pool.minting_halted = true;
+            emit_seq!(
+                events,
+                CoverageMintingHalted {
+                    pool: pool.key(),
+                    assets,
+                    outstanding_coverage: pool.outstanding_coverage,
+                    expected_losses: expected_losses.min(u64::MAX as u128) as u64,
+                    liabilities: pool::liabilities(pool),
+                    required: required.min(u64::MAX as u128) as u64,
+                    timestamp: Clock::get()?.unix_timestamp,
+                }
+            );
+        }
+
+        Ok(())
+    }
+
+    // Governance override: resume minting despite a short margin. The crank will not
+    // halt again until the margin has recovered once.
+    pub fn override_minting_halt(ctx: Context<OverrideMintingHalt>) -> Result<()> {
+        let mut events = next_event_seq(&mut ctx.accounts.config)?;
+        require!(
+            ctx.accounts
+                .governance
+                .is_authorized(&ctx.accounts.authority.key()),
+            ErrorCode::UnauthorizedGovernance
+        );
+
+        let pool = &mut ctx.accounts.pool;
+        require!(pool.minting_halted, ErrorCode::MintingNotHalted);
+        pool.minting_halted = false;
+        pool.halt_overridden = true;
+
+        emit_seq!(
+            events,
+            CoverageMintingResumed {
+                pool: pool.key(),
+                assets: ctx.accounts.vault.amount,
+                required: 0,
+                overridden: true,
+                timestamp: Clock::get()?.unix_timestamp,
+            }
+        );
+        Ok(())
+    }
+}
+
+// Helper functions
+
+// Switch the registry to `active`, announcing a move to a fallback or back to the primary
+fn update_active_oracle(registry: &mut OracleRegistry, active: Pubkey, events: &mut EventSeq) {
+    if registry.current_active_oracle == active {
+        return;
+    }
+
+    registry.current_active_oracle = active;
+    if active == registry.primary_oracle {
+        emit_seq!(events, PrimaryOracleRestored { oracle: active });
+    } else {
+        emit_seq!(
+            events,
+            OracleFallbackActivated {
+                primary: registry.primary_oracle,
+                fallback_used: active,
+            }
+        );
+    }
+}
+// Verify `oracle`'s signature over `message` in the ed25519 instruction `back` places
+// before this one
+fn verify_iris_signature(
+    instructions: Option<&UncheckedAccount>,
+    oracle: &Pubkey,
+    message: &ScoreMessage,
+    signature: &[u8; 64],
+    back: usize,
+) -> Result<()> {
+    let instructions = instructions.ok_or(ErrorCode::MissingEd25519Instruction)?;
+    let ix = ed25519::instruction_before(instructions, back)?;
+    ed25519::verify_score(ix.as_ref(), oracle, message, signature)
+}
+
+// Settle the wallet's held score before another is submitted. One past its dispute
+// window is applied; one still inside it, or under dispute, is superseded by the newer
+// submission. Returns the score if it breached the wallet's threshold.
+fn settle_held_score(
+    user_account: &mut Account<UserAccount>,
+    archive: Option<&mut Account<ScoreHistory>>,
+    covered: Option<&CoveredTokenList>,
+    max_delta: u8,
+    now: i64,
+    events: &mut EventSeq,
+) -> Result<Option<u8>> {
+    let Some(held) = user_account.held_score.as_ref() else {
+        return Ok(None);
+    };
+    if !logic::held_score_applicable(held, now, ProtocolConfig::SCORE_DISPUTE_WINDOW_SECS) {
+        let held = user_account
+            .held_score
+            .take()
+            .ok_or(ErrorCode::NoHeldScore)?;
+        emit_indexed!(
+            events,
+            user_account.wallet,
+            HeldScoreSuperseded {
+                wallet: user_account.wallet,
+                oracle: held.oracle,
+                value: held.value,
+                submitted_at: held.submitted_at,
+                disputed: held.disputed,
+            }
+        );
+        return Ok(None);
+    }
+    apply_held_score(user_account, archive, covered, max_delta, events)
+}
+
+// Store the held score and run the protection triggers on it
+fn apply_held_score(
+    user_account: &mut Account<UserAccount>,
+    archive: Option<&mut Account<ScoreHistory>>,
+    covered: Option<&CoveredTokenList>,
+    max_delta: u8,
+    events: &mut EventSeq,
+) -> Result<Option<u8>> {
+    let held = user_account
+        .held_score
+        .take()
+        .ok_or(ErrorCode::NoHeldScore)?;
+    store_score(
+        user_account,
+        archive,
+        held.value,
+        held.timestamp,
+        max_delta,
+        events,
+    )?;
+    emit_indexed!(
+        events,
+        user_account.wallet,
+        HeldScoreApplied {
+            wallet: user_account.wallet,
+            oracle: held.oracle,
+            value: held.value,
+            submitted_at: held.submitted_at,
+        }
+    );
+    check_protection_triggers(user_account, covered, events)
+}
+
+// Append a verified score to the wallet's history, moving at most `max_delta` from the
+// previous one. The signed (chain_id, wallet, timestamp) tuple acts as the replay nonce:
+// a wallet's timestamps must strictly increase, and chain_id keeps them per deployment.
+fn store_score(
+    user_account: &mut Account<UserAccount>,
+    archive: Option<&mut Account<ScoreHistory>>,
+    score: u8,
+    timestamp: i64,
+    max_delta: u8,
+    events: &mut EventSeq,
+) -> Result<()> {
+    logic::check_score_timestamp(
+        timestamp,
+        user_account.score_history.last().map(|last| last.timestamp),
+    )?;
+
+    // A previously queued target is applied first and the fresh score queues behind it
+    let previous = user_account.score_history.last().map(|last| last.value);
+    let queued = user_account.pending_score;
+    let (stored, pending) = logic::next_score(previous, queued, score, max_delta);
+    user_account.pending_score = pending;
+
+    if queued.is_some() {
+        emit_indexed!(
+            events,
+            user_account.wallet,
+            PendingScoreApplied {
+                wallet: user_account.wallet,
+                applied_delta: previous.map_or(0, |previous| previous.abs_diff(stored)),
+                remaining_pending: pending,
+            }
+        );
+    }
+
+    // The first score seeds the moving average
+    user_account.smoothed_score = if user_account.score_history.is_empty() {
+        stored
+    } else {
+        logic::smooth_score(
+            user_account.smoothed_score,
+            stored,
+            user_account.preferences.score_ema_alpha,
+        )
+    };
+    // Only the most recent scores are kept
+    if user_account.score_history.len() >= MAX_SCORE_HISTORY {
+        user_account.score_history.remove(0);
+    }
+    let entry = Score {
+        value: stored,
+        timestamp,
+    };
+    user_account.score_history.push(entry.clone());
+
+    // Commit the score to the tamper-evident chain and archive it
+    let leaf_index = user_account.score_hash_count;
+    user_account.score_history_hash =
+        logic::next_score_root(&user_account.score_history_hash, &logic::score_leaf(&entry));
+    user_account.score_hash_count = leaf_index.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+    if let Some(archive) = archive {
+        if archive.scores.len() >= MAX_ARCHIVED_SCORES {
+            archive.scores.remove(0);
+        }
+        archive.scores.push(ArchivedScore {
+            leaf_index,
+            score: entry,
+        });
+    }
+    emit_indexed!(
+        events,
+        user_account.wallet,
+        ScoreHashUpdated {
+            wallet: user_account.wallet,
+            new_root: user_account.score_history_hash,
+            leaf_index,
+        }
+    );
+
+    if stored != score {
+        emit_indexed!(
+            events,
+            user_account.wallet,
+            ScoreClamped {
+                wallet: user_account.wallet,
+                requested: score,
+                stored,
+                pending: user_account.pending_score,
+            }
+        );
+    }
+
+    emit_indexed!(
+        events,
+        user_account.wallet,
+        SmoothedScoreUpdated {
+            wallet: user_account.wallet,
+            raw: stored,
+            smoothed: user_account.smoothed_score,
+        }
+    );
+    Ok(())
+}
+
+// Rewrite an account of an older layout in place as `version`, returning the version it
+// had. Pre-version accounts lack the version byte, so their body shifts up by one;
+// `growth` lists the bytes each later version appended, and those fields start zeroed.
+fn migrate_layout(
+    accounts: &MigrateAccountLayout,
+    discriminator: &[u8],
+    current_len: usize,
+    version: u8,
+    growth: &[usize],
+) -> Result<u8> {
+    let account = accounts.account.to_account_info();
+    require!(account.owner == &crate::ID, ErrorCode::InvalidAccountLayout);
+    require!(
+        account.try_borrow_data()?[..8] == *discriminator,
+        ErrorCode::InvalidAccountLayout
+    );
+
+    let old_len = account.data_len();
+    let from_version =
+        logic::stored_layout_version(&account.try_borrow_data()?, current_len, growth)?;
+    require!(from_version < version, ErrorCode::InvalidAccountLayout);
+
+    let shortfall = Rent::get()?
+        .minimum_balance(current_len)
+        .saturating_sub(account.lamports());
+    if shortfall > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: accounts.payer.to_account_info(),
+                    to: account.clone(),
+                },
+            ),
+            shortfall,
+        )?;
+    }
+
+    account.realloc(current_len, true)?;
+    let mut data = account.try_borrow_mut_data()?;
+    if from_version == 0 {
+        data.copy_within(8..old_len, 9);
+    }
+    data[8] = version;
+    Ok(from_version)
+}
+
+// Fail unless the wallet's subscription is in force, naming why it isn't. Handlers
+// check protection only through here or UserAccount::is_protected.
+fn require_subscription_active(
+    user_account: &UserAccount,
+    config: &ProtocolConfig,
+    now: i64,
+) -> Result<()> {
+    require!(user_account.active_sub, ErrorCode::NoActiveSubscription);
+    user_account
+        .subscription_state(now, config.subscription_grace_secs)
+        .require_active(ErrorCode::SubscriptionExpired)
+}
+
+// Items in a batch instruction. Batches take only invariant accounts as named fields;
+// each item's accounts follow in remaining accounts in groups of `stride`, so clients
+// can load them from an address lookup table. Fails unless they form whole items that
+// fit in one transaction.
+fn batch_items<'info, T: anchor_lang::Bumps + ToAccountInfos<'info>>(
+    ctx: &Context<'_, '_, '_, 'info, T>,
+    stride: usize,
+) -> Result<usize> {
+    logic::batch_items(
+        ctx.accounts.to_account_infos().len(),
+        ctx.remaining_accounts.len(),
+        stride,
+    )
+}
+
+// Hand out the next value of a sequential number counter
+fn take_number(counter: &mut u64) -> Result<u64> {
+    let number = *counter;
+    *counter = number.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+    Ok(number)
+}
+
+// Create the wallet's next aggregation report PDA, numbered by its score archive
+#[allow(clippy::too_many_arguments)]
+fn write_aggregation_report<'info>(
+    report_account: &UncheckedAccount<'info>,
+    archive: &mut Account<'info, ScoreHistory>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    reports: &[(Pubkey, u8)],
+    registered_oracles: u8,
+    consensus_score: u8,
+    now: i64,
+) -> Result<()> {
+    let report_index = archive.reports_count;
+    let index_seed = report_index.to_le_bytes();
+    let (address, bump) = Pubkey::find_program_address(
+        &[b"aggregation_report", archive.wallet.as_ref(), &index_seed],
+        &crate::ID,
+    );
+    require!(
+        report_account.key() == address,
+        ErrorCode::InvalidAggregationReport
+    );
+
+    let space = 8 + AggregationReport::INIT_SPACE;
+    let bump_seed = [bump];
+    let signer_seeds: &[&[&[u8]]] = &[&[
+        b"aggregation_report",
+        archive.wallet.as_ref(),
+        &index_seed,
+        &bump_seed,
+    ]];
+    system_program::create_account(
+        CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            system_program::CreateAccount {
+                from: payer.to_account_info(),
+                to: report_account.to_account_info(),
+            },
+            signer_seeds,
+        ),
+        Rent::get()?.minimum_balance(space),
+        space as u64,
+        &crate::ID,
+    )?;
+
+    let report = AggregationReport::new(
+        archive.wallet,
+        report_index,
+        reports,
+        registered_oracles,
+        consensus_score,
+        now,
+        bump,
+    );
+    report.try_serialize(&mut &mut report_account.try_borrow_mut_data()?[..])?;
+    archive.reports_count = report_index.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+    Ok(())
+}
+
+// Append an entry to the wallet's activity buffer. A wallet without one gets it created
+// when `payer`, the wallet itself, signs; oracle and keeper writes skip it. Passing no
+// activity account skips the write.
+fn record_activity<'info>(
+    activity: Option<&UncheckedAccount<'info>>,
+    payer: Option<(&Signer<'info>, &Program<'info, System>)>,
+    wallet: Pubkey,
+    kind: ActivityKind,
+    amount: u64,
+    reference: Pubkey,
+    now: i64,
+) -> Result<()> {
+    let Some(activity) = activity else {
+        return Ok(());
+    };
+    let (address, bump) =
+        Pubkey::find_program_address(&[WalletActivity::SEED, wallet.as_ref()], &crate::ID);
+    require!(activity.key() == address, ErrorCode::InvalidActivityAccount);
+
+    let mut buffer = if activity.data_is_empty() {
+        let Some((payer, system_program)) = payer else {
+            return Ok(());
+        };
+        let space = 8 + WalletActivity::INIT_SPACE;
+        let bump_seed = [bump];
+        let signer_seeds: &[&[&[u8]]] = &[&[WalletActivity::SEED, wallet.as_ref(), &bump_seed]];
+        system_program::create_account(
+            CpiContext::new_with_signer(
+                system_program.to_account_info(),
+                system_program::CreateAccount {
+                    from: payer.to_account_info(),
+                    to: activity.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            Rent::get()?.minimum_balance(space),
+            space as u64,
+            &crate::ID,
+        )?;
+        WalletActivity::new(wallet, bump)
+    } else {
+        require!(
+            activity.owner == &crate::ID,
+            ErrorCode::InvalidActivityAccount
+        );
+        WalletActivity::try_deserialize(&mut &activity.try_borrow_data()?[..])?
+    };
+
+    buffer.push(ActivityEntry {
+        kind,
+        amount,
+        reference,
+        timestamp: now,
+    });
+    buffer.try_serialize(&mut &mut activity.try_borrow_mut_data()?[..])
+}
+
+// Lift a watchlist alert pause, by request or once it has run out
+fn resume_watchlist(user_account: &mut UserAccount, now: i64, events: &mut EventSeq) {
+    if !user_account.watchlist_alerts_paused {
+        return;
+    }
+    user_account.watchlist_alerts_paused = false;
+    user_account.paused_until = 0;
+    emit_indexed!(
+        events,
+        user_account.wallet,
+        WatchlistAlertsResumed {
+            wallet: user_account.wallet,
+            timestamp: now,
+        }
+    );
+}
+
+// Move a listed NFT out of its vault to `to` and close the vault, refunding its rent
+// to the seller. The listing PDA signs for the vault.
+fn release_listed_nft<'info>(
+    listing: &Account<'info, NftListing>,
+    policy: &Pubkey,
+    vault: &InterfaceAccount<'info, TokenAccount>,
+    to: AccountInfo<'info>,
+    nft_mint: &InterfaceAccount<'info, Mint>,
+    seller: AccountInfo<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+) -> Result<()> {
+    let bump = [listing.bump];
+    let signer_seeds: &[&[&[u8]]] = &[&[b"nft_listing", policy.as_ref(), &bump]];
+    tokens::transfer(
+        token_program,
+        vault.to_account_info(),
+        to,
+        nft_mint,
+        listing.to_account_info(),
+        1,
+        signer_seeds,
+    )?;
+    token_interface::close_account(CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        token_interface::CloseAccount {
+            account: vault.to_account_info(),
+            destination: seller,
+            authority: listing.to_account_info(),
+        },
+        signer_seeds,
+    ))
+}
+
+// Check a claim's slot anchor against the SlotHashes sysvar: the slot must be recent
+// and the hash the one the cluster recorded for it
+fn verify_slot_anchor(
+    slot_hashes: Option<&UncheckedAccount>,
+    anchor: &SlotAnchor,
+    current_slot: u64,
+) -> Result<()> {
+    let slot_hashes = slot_hashes.ok_or(ErrorCode::SlotHashesMissing)?;
+    require!(
+        logic::slot_anchor_fresh(current_slot, anchor.slot, Claim::SLOT_ANCHOR_MAX_AGE_SLOTS),
+        ErrorCode::SlotAnchorTooOld
+    );
+    let recorded = slot_hashes::find(&slot_hashes.try_borrow_data()?, anchor.slot);
+    require!(recorded == Some(anchor.hash), ErrorCode::SlotHashMismatch);
+    Ok(())
+}
+
+// Set the governance vote parameters, refused while any proposal is still being voted on
+fn apply_governance_params(
+    governance: &mut Governance,
+    quorum: u64,
+    emergency_quorum: u64,
+    voting_duration: i64,
+    proposal_id: Option<u64>,
+    events: &mut EventSeq,
+) -> Result<()> {
+    governance.require_no_active_votes()?;
+    require!(quorum > 0, ErrorCode::InvalidAmount);
+    require!(
+        emergency_quorum >= quorum,
+        ErrorCode::InvalidEmergencyQuorum
+    );
+    require!(voting_duration > 0, ErrorCode::InvalidDuration);
+
+    governance.quorum = quorum;
+    governance.emergency_quorum = emergency_quorum;
+    governance.voting_duration = voting_duration;
+    emit_seq!(
+        events,
+        GovernanceParamsUpdated {
+            quorum,
+            emergency_quorum,
+            voting_duration,
+            proposal_id,
+        }
+    );
+    Ok(())
+}
+
+// Count one use of a rate-limited action against the wallet's window for it. Only
+// spam-prone user actions are limited; protection itself never is.
+fn check_rate_limit(
+    user_account: &mut UserAccount,
+    config: &ProtocolConfig,
+    action: RateLimitedAction,
+    now: i64,
+) -> Result<()> {
+    let slot = action as usize;
+    require!(
+        logic::consume_rate_limit(
+            &mut user_account.rate_windows[slot],
+            &config.rate_limits[slot],
+            now
+        ),
+        ErrorCode::RateLimited
+    );
+    Ok(())
+}
+
+// Reimburse a keeper that expired `count` subscriptions for what its transaction cost:
+// the signature fee plus its priority fee bid, read from the transaction's compute
+// budget instructions and held to the config caps. Paid from the maintenance fund,
+// within the keeper's budget for the epoch. Returns what was paid.
+fn reimburse_keeper(
+    config: &mut Account<ProtocolConfig>,
+    stats: &mut Account<KeeperStats>,
+    stats_bump: u8,
+    keeper: &AccountInfo,
+    instructions: Option<&UncheckedAccount>,
+    count: u32,
+    events: &mut EventSeq,
+) -> Result<u64> {
+    if stats.keeper == Pubkey::default() {
+        stats.keeper = keeper.key();
+        stats.bump = stats_bump;
+    }
+    if count == 0 {
+        return Ok(0);
+    }
+    let request = match instructions {
+        Some(instructions) => compute_budget::read(instructions)?,
+        None => compute_budget::ComputeBudgetRequest::default(),
+    };
+    let cost = compute_budget::estimate_cost(&request, &config.keeper_fee_caps);
+
+    let epoch = Clock::get()?.epoch;
+    if stats.epoch != epoch {
+        stats.epoch = epoch;
+        stats.reimbursed_this_epoch = 0;
+    }
+    let budget_left = config
+        .keeper_fee_caps
+        .epoch_budget_lamports
+        .saturating_sub(stats.reimbursed_this_epoch);
+    let amount = cost
+        .total()
+        .min(budget_left)
+        .min(config.maintenance_fund_lamports);
+    if amount > 0 {
+        config.maintenance_fund_lamports -= amount;
+        config.sub_lamports(amount)?;
+        keeper.add_lamports(amount)?;
+        stats.reimbursed_this_epoch += amount;
+        stats.total_reimbursed = stats.total_reimbursed.saturating_add(amount);
+    }
+
+    emit_seq!(
+        events,
+        KeeperReimbursed {
+            keeper: keeper.key(),
+            base_fee: cost.base_fee,
+            cu_price: cost.cu_price,
+            compute_units: cost.compute_units,
+            priority_fee: cost.priority_fee,
+            clamped: cost.clamped,
+            amount,
+            reimbursed_this_epoch: stats.reimbursed_this_epoch,
+        }
+    );
+    Ok(amount)
+}
+
+// Fail unless the policy's coverage has started and not yet expired
+fn require_policy_active(insurance_nft: &InsuranceNFT, now: i64) -> Result<()> {
+    insurance_nft
+        .coverage_state(now)
+        .require_active(ErrorCode::InsuranceExpired)
+}
+
+// How execute_payout settles an approved claim against the pool as it stands: the
+// installment due now, what the vault pays of it, what reinsurance covers and the
+// shortfall left
+fn payout_quote(
+    config: &ProtocolConfig,
+    pool: &InsurancePool,
+    claim: &Claim,
+    assets: u64,
+    reinsurance: Option<&ReinsurancePool>,
+) -> Result<PayoutQuote> {
+    require!(
+        logic::claim_transition_allowed(&claim.status, &ClaimStatus::Paid),
+        ErrorCode::ClaimNotApproved
+    );
+    let payable = claim.payable_amount();
+    // Large claims pay their first installment now; the rest stays owed by the pool
+    let installments = logic::installment_plan(
+        payable,
+        config.installment_threshold,
+        config.installment_count,
+    );
+    let due = installments.map_or(payable, |(first, _)| first);
+
+    let (paid, primary_shortfall) = pool::preview_payout(pool, due, assets);
+    let reinsured = match reinsurance {
+        Some(reinsurance) => {
+            require_keys_eq!(
+                reinsurance.payout_mint,
+                pool.payout_mint,
+                ErrorCode::InvalidMint
+            );
+            pool::reinsurance_draw(
+                primary_shortfall,
+                reinsurance.pool_balance,
+                reinsurance.activation_threshold,
+            )
+        }
+        None => 0,
+    };
+    Ok(PayoutQuote {
+        payable,
+        due,
+        paid,
+        reinsured,
+        shortfall: primary_shortfall - reinsured,
+        installment_amount: installments.map_or(0, |(_, later)| later),
+        installments_remaining: if installments.is_some() {
+            config.installment_count - 1
+        } else {
+            0
+        },
+    })
+}
+
+// Warn when the wallet's policy lapses up to `alert_days` whole days before its
+// subscription does, leaving it subscribed but unprotected for the remainder
+fn check_coverage_gap(
+    user_account: &UserAccount,
+    insurance_nft: &InsuranceNFT,
+    alert_days: u16,
+    events: &mut EventSeq,
+) {
+    if !user_account.active_sub {
+        return;
+    }
+    let gap_days = logic::coverage_gap_days(user_account.subscription_expiry, insurance_nft.expiry);
+    if gap_days > 0 && gap_days <= alert_days as i64 {
+        emit_indexed!(
+            events,
+            user_account.wallet,
+            CoverageGapWarning {
+                wallet: user_account.wallet,
+                subscription_expiry: user_account.subscription_expiry,
+                nft_expiry: insurance_nft.expiry,
+                gap_days,
+            }
+        );
+    }
+}
+
+// Returns the score if it breached the wallet's threshold. The breach names the
+// wallet's covered tokens, the ones automatic protection would act on.
+fn check_protection_triggers(
+    user_account: &mut Account<UserAccount>,
+    covered: Option<&CoveredTokenList>,
+    events: &mut EventSeq,
+) -> Result<Option<u8>> {
+    let latest_score = user_account
+        .effective_score()
+        .ok_or(ErrorCode::NoScoreHistory)?;
+    if latest_score < user_account.preferences.risk_threshold {
+        return Ok(None);
+    }
+    // Would trigger protection logic based on user preferences
+    // This is simplified for the example
+    emit_indexed!(
+        events,
+        user_account.wallet,
+        RiskThresholdBreached {
+            wallet: user_account.wallet,
+            score: latest_score,
+            threshold: user_account.preferences.risk_threshold,
+            covered_tokens: covered.map_or_else(Vec::new, CoveredTokenList::mints),
+            timestamp: Clock::get()?.unix_timestamp,
+        }
+    );
+    Ok(Some(latest_score))
+}
+
+// Swap the rotated key into the council once enough members have confirmed
+fn try_execute_signer_rotation(
+    council: &mut EmergencyCouncil,
+    rotation: &mut SignerRotationProposal,
+    events: &mut EventSeq,
+) -> Result<()> {
+    let required = logic::rotation_confirmations_required(council.members.len());
+    if rotation.confirmations.len() < required {
+        return Ok(());
+    }
+    require!(
+        council.rotate(&rotation.old_signer, rotation.new_signer),
+        ErrorCode::NotCouncilMember
+    );
+    rotation.executed = true;
+    emit_seq!(
+        events,
+        MultisigSignerRotated {
+            old: rotation.old_signer,
+            new: rotation.new_signer,
+            initiated_by: rotation.initiated_by,
+        }
+    );
+    Ok(())
+}
+
+// PDA that holds the program's upgrade authority once upgrades are governed
+fn governed_upgrade_authority() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[ProtocolConfig::UPGRADE_AUTHORITY_SEED], &crate::ID)
+}
+
+// Position of an event in the protocol-wide event stream. Every emitting instruction
+// takes the next `seq` from config once; its events share that seq and are numbered
+// from 0 by `seq_index`, so (seq, seq_index) orders all events strictly.
+pub struct EventSeq {
+    pub seq: u64,
+    index: u8,
+}
+
+impl EventSeq {
+    pub fn next_index(&mut self) -> u8 {
+        let index = self.index;
+        self.index += 1;
+        index
+    }
+}
+
+pub(crate) fn next_event_seq(config: &mut ProtocolConfig) -> Result<EventSeq> {
+    config.event_seq = config
+        .event_seq
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+    Ok(EventSeq {
+        seq: config.event_seq,
+        index: 0,
+    })
+}
+
+// Invoke this program with `event` as instruction data, signed by the event authority
+// PDA, which is how emit_cpi! events are delivered
+pub(crate) fn emit_event_cpi<'info, E: anchor_lang::Event>(
+    event_authority: &AccountInfo<'info>,
+    bump: u8,
+    event: &E,
+) -> Result<()> {
+    let data: Vec<u8> = anchor_lang::event::EVENT_IX_TAG_LE
+        .into_iter()
+        .chain(event.data())
+        .collect();
+    invoke_signed(
+        &Instruction::new_with_bytes(
+            crate::ID,
+            &data,
+            vec![AccountMeta::new_readonly(event_authority.key(), true)],
+        ),
+        std::slice::from_ref(event_authority),
+        &[&[b"__event_authority", &[bump]]],
+    )?;
+    Ok(())
+}
+
+// Reject a cross-program invocation unless the transaction's top-level program is on
+// the CPI allowlist. Direct calls always pass, so the instructions sysvar is only
+// required when the instruction arrives nested.
+fn require_direct_call(
+    config: &ProtocolConfig,
+    instructions: Option<&UncheckedAccount>,
+) -> Result<()> {
+    if !config.cpi_guard_enabled || get_stack_height() <= TRANSACTION_LEVEL_STACK_HEIGHT {
+        return Ok(());
+    }
+    let instructions = instructions.ok_or(ErrorCode::CpiNotAllowed)?;
+    let index = load_current_index_checked(instructions)?;
+    let outer = load_instruction_at_checked(index as usize, instructions)?;
+    require!(
+        config.cpi_allowlist.contains(&outer.program_id),
+        ErrorCode::CpiNotAllowed
+    );
+    Ok(())
+}
+
+// require_direct_call for instructions a wallet signs for itself. A multisig wallet is
+// a PDA of its multisig program, which signs for it by CPI; allowlisted multisig
+// programs may do so even with the CPI guard on. The wallet must still be a signer.
+fn require_wallet_call(
+    config: &ProtocolConfig,
+    instructions: Option<&UncheckedAccount>,
+) -> Result<()> {
+    if get_stack_height() <= TRANSACTION_LEVEL_STACK_HEIGHT {
+        return Ok(());
+    }
+    let outer = match instructions {
+        Some(instructions) => {
+            let index = load_current_index_checked(instructions)?;
+            Some(load_instruction_at_checked(index as usize, instructions)?.program_id)
+        }
+        None => None,
+    };
+    require!(
+        logic::wallet_caller_allowed(
+            config.cpi_guard_enabled,
+            outer.as_ref(),
+            &config.cpi_allowlist,
+            &config.multisig_allowlist,
+        ),
+        ErrorCode::CpiNotAllowed
+    );
+    Ok(())
+}
+
+// Reward-paying cranks may be driven by the configured automation program; any
+// other program calling in through CPI is refused
+fn require_automation_caller(
+    config: &ProtocolConfig,
+    instructions: Option<&UncheckedAccount>,
+) -> Result<()> {
+    if get_stack_height() <= TRANSACTION_LEVEL_STACK_HEIGHT {
+        return Ok(());
+    }
+    let instructions = instructions.ok_or(ErrorCode::CpiNotAllowed)?;
+    let index = load_current_index_checked(instructions)?;
+    let outer = load_instruction_at_checked(index as usize, instructions)?;
+    let automation_program = config.thread_config.automation_program;
+    require!(
+        automation_program != Pubkey::default() && outer.program_id == automation_program,
+        ErrorCode::CpiNotAllowed
+    );
+    Ok(())
+}
+
+// Hand an automation thread the unix time a crank is next worth running
+fn schedule_next_run(timestamp: i64) {
+    set_return_data(&timestamp.to_le_bytes());
+}
+
+// Take the next accounting ledger sequence number. Numbers start at 1 and every
+// pool or treasury movement consumes exactly one, so gaps reveal missed events.
+pub(crate) fn next_ledger_seq(config: &mut ProtocolConfig) -> Result<u64> {
+    config.ledger_seq = config
+        .ledger_seq
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+    Ok(config.ledger_seq)
+}
+
+// Collect a policy premium into the pool, where it accrues to LPs at the next epoch close
+#[allow(clippy::too_many_arguments)]
+fn collect_premium<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    source: &InterfaceAccount<'info, TokenAccount>,
+    pool_vault: &mut InterfaceAccount<'info, TokenAccount>,
+    payout_mint: &InterfaceAccount<'info, Mint>,
+    buyer: &Signer<'info>,
+    pool: &mut Account<'info, InsurancePool>,
+    config: &mut ProtocolConfig,
+    events: &mut EventSeq,
+    premium: u64,
+    tier: u8,
+    instruction: LedgerInstruction,
+) -> Result<()> {
+    if premium == 0 {
+        return Ok(());
+    }
+    tokens::transfer_in(
+        token_program,
+        source,
+        pool_vault,
+        payout_mint,
+        buyer.to_account_info(),
+        premium,
+    )?;
+    pool.premiums_accrued = pool
+        .premiums_accrued
+        .checked_add(premium)
+        .ok_or(ErrorCode::MathOverflow)?;
+    pool.reserved_rewards = pool
+        .reserved_rewards
+        .checked_add(premium)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    emit_seq!(
+        events,
+        PremiumReceived {
+            ledger_seq: next_ledger_seq(config)?,
+            instruction,
+            mint: pool.payout_mint,
+            counterparty: buyer.key(),
+            amount: premium,
+            tier,
+            timestamp: Clock::get()?.unix_timestamp,
+        }
+    );
+    Ok(())
+}
+
+// Burn the caller's governance tokens for a fee discount and return the amount waived
+#[allow(clippy::too_many_arguments)]
+fn burn_for_fee_waiver<'info>(
+    config: &ProtocolConfig,
+    governance_mint: Option<&InterfaceAccount<'info, Mint>>,
+    source: Option<&InterfaceAccount<'info, TokenAccount>>,
+    authority: &Signer<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+    fee: u64,
+    burn: u64,
+    events: &mut EventSeq,
+) -> Result<u64> {
+    require!(
+        config.fee_waiver_burn_rate > 0,
+        ErrorCode::FeeWaiverDisabled
+    );
+    let (Some(governance_mint), Some(source)) = (governance_mint, source) else {
+        return Err(ErrorCode::InsufficientGovernanceTokensForWaiver.into());
+    };
+    require!(
+        governance_mint.key() == config.governance_mint,
+        ErrorCode::InvalidMint
+    );
+    require!(
+        source.mint == config.governance_mint,
+        ErrorCode::InvalidMint
+    );
+    require!(
+        source.owner == authority.key(),
+        ErrorCode::InvalidTokenOwner
+    );
+
+    let (tokens_burned, fee_waived) =
+        logic::fee_waiver_amounts(fee, burn, config.fee_waiver_burn_rate);
+    require!(
+        source.amount >= tokens_burned,
+        ErrorCode::InsufficientGovernanceTokensForWaiver
+    );
+    if tokens_burned == 0 {
+        return Ok(0);
+    }
+
+    token_interface::burn(
+        CpiContext::new(
+            token_program.to_account_info(),
+            token_interface::Burn {
+                mint: governance_mint.to_account_info(),
+                from: source.to_account_info(),
+                authority: authority.to_account_info(),
+            },
+        ),
+        tokens_burned,
+    )?;
+
+    emit_indexed!(
+        events,
+        authority.key(),
+        FeeWaiverBurnApplied {
+            wallet: authority.key(),
+            tokens_burned,
+            fee_waived,
+        }
+    );
+
+    Ok(fee_waived)
+}
+
+// Value of the wallet's portfolio in payout-mint units, refusing a `payout_cap` above it.
+// Only payout-mint token accounts count: the protocol has no price feed to value other
+// tokens, so they are left out rather than guessed at.
+fn portfolio_value<'info>(
+    accounts: &'info [AccountInfo<'info>],
+    wallet: &Pubkey,
+    payout_mint: &Pubkey,
+    payout_cap: u64,
+) -> Result<u64> {
+    let mut total: u64 = 0;
+    for (i, info) in accounts.iter().enumerate() {
+        require!(
+            !accounts[..i].iter().any(|seen| seen.key == info.key),
+            ErrorCode::InvalidRemainingAccounts
+        );
+        let token_account = InterfaceAccount::<TokenAccount>::try_from(info)?;
+        require!(token_account.owner == *wallet, ErrorCode::InvalidTokenOwner);
+        require!(token_account.mint == *payout_mint, ErrorCode::InvalidMint);
+        total = total
+            .checked_add(token_account.amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+    }
+    require!(payout_cap <= total, ErrorCode::PayoutCapExceedsPortfolio);
+    Ok(total)
+}
+
+// CPI into an external (allowlisted) program with caller-supplied accounts and data,
+// marking `pda` as a signer wherever it appears
+fn invoke_external<'info>(
+    program: &AccountInfo<'info>,
+    accounts: &[AccountInfo<'info>],
+    data: Vec<u8>,
+    pda: Pubkey,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let mut metas = Vec::with_capacity(accounts.len());
+    let mut infos = Vec::with_capacity(accounts.len() + 1);
+    for account in accounts {
+        let is_signer = account.is_signer || account.key() == pda;
+        metas.push(if account.is_writable {
+            AccountMeta::new(account.key(), is_signer)
+        } else {
+            AccountMeta::new_readonly(account.key(), is_signer)
+        });
+        infos.push(account.clone());
+    }
+    infos.push(program.clone());
+
+    invoke_signed(
+        &Instruction {
+            program_id: program.key(),
+            accounts: metas,
+            data,
+        },
+        &infos,
+        signer_seeds,
+    )?;
+    Ok(())
+}
+
+// An approved proposal may execute once its timelock has elapsed, unless it was
+// cancelled during the timelock
+fn require_executable(proposal: &Proposal, governance: &Governance, now: i64) -> Result<()> {
+    require!(
+        proposal.status == ProposalStatus::Approved,
+        ErrorCode::ProposalNotApproved
+    );
+    require!(!proposal.is_cancelled, ErrorCode::ExecutionCancelled);
+    require!(
+        logic::timelock_elapsed(
+            now,
+            proposal.quorum_reached_at,
+            governance.timelock_duration
+        ),
+        ErrorCode::TimelockActive
+    );
+    Ok(())
+}
+
+// Resolve a pending claim once its votes, abstentions included, reach quorum
+fn tally_claim_votes(
+    claim: &mut Account<Claim>,
+    governance: &Governance,
+    quorum_weight: u64,
+    pool: &mut Account<InsurancePool>,
+    config: &mut ProtocolConfig,
+    events: &mut EventSeq,
+) -> Result<()> {
+    let resolution = logic::claim_resolution(
+        claim.approval_votes,
+        claim.rejection_votes,
+        claim.abstain_weight,
+        claim.partial_votes,
+        governance.quorum,
+        quorum_weight,
+    );
+    if let Some(status) = resolution {
+        resolve_claim(claim, status, pool, config, events)?;
+    }
+    Ok(())
+}
+
+// Move a pending claim to the status its vote resolved to. Rejections hand the NFT
+// back; approvals record the liability that execute_payout settles. The resolution
+// time is measured against the claim's target and folded into the protocol average.
+fn resolve_claim(
+    claim: &mut Account<Claim>,
+    status: ClaimStatus,
+    pool: &mut Account<InsurancePool>,
+    config: &mut ProtocolConfig,
+    events: &mut EventSeq,
+) -> Result<()> {
+    ClaimStatus::try_transition(&claim.status, &status)?;
+    claim.status = status;
+    claim.resolved_at = Clock::get()?.unix_timestamp;
+    config.avg_resolution_time_secs = logic::average_resolution_secs(
+        config.avg_resolution_time_secs,
+        logic::resolution_time_secs(claim.timestamp, claim.resolved_at),
+    );
+    // Claims filed before resolution targets have no SLA to report against
+    if claim.expected_resolution_at != 0 {
+        let (claim_id, expected, actual) =
+            (claim.key(), claim.expected_resolution_at, claim.resolved_at);
+        let delta_secs = actual - expected;
+        if delta_secs <= 0 {
+            emit_seq!(
+                events,
+                SlaSatisfied {
+                    claim_id,
+                    expected,
+                    actual,
+                    delta_secs,
+                }
+            );
+        } else {
+            emit_seq!(
+                events,
+                SlaViolated {
+                    claim_id,
+                    expected,
+                    actual,
+                    delta_secs,
+                }
+            );
+        }
+    }
+    match claim.status {
+        ClaimStatus::Rejected => {
+            // Return NFT to owner
+            return_nft_to_owner(claim)?;
+        }
+        ClaimStatus::Approved | ClaimStatus::PartiallyApproved => {
+            // Funds move in execute_payout
+            pool.approved_liabilities = pool
+                .approved_liabilities
+                .checked_add(claim.payable_amount())
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+        // Ruled out by the transition check
+        ClaimStatus::Pending | ClaimStatus::Paid => return err!(ErrorCode::InvalidClaimTransition),
+    }
+    Ok(())
+}
+
+// Amounts proposed in the given ClaimVoteRecords for `claim`, each record counted once
+fn proposed_claim_amounts<'info>(
+    claim: &Account<Claim>,
+    records: &'info [AccountInfo<'info>],
+) -> Result<Vec<u64>> {
+    let mut seen: Vec<Pubkey> = Vec::with_capacity(records.len());
+    let mut amounts = Vec::with_capacity(records.len() + 1);
+    for info in records {
+        let record = Account::<ClaimVoteRecord>::try_from(info)?;
+        require!(
+            record.claim == claim.key(),
+            ErrorCode::InvalidRemainingAccounts
+        );
+        require!(
+            !seen.contains(info.key),
+            ErrorCode::InvalidRemainingAccounts
+        );
+        seen.push(*info.key);
+        amounts.push(
+            record
+                .approved_amount
+                .ok_or(ErrorCode::InvalidRemainingAccounts)?,
+        );
+    }
+    Ok(amounts)
+}
+
+// Burn `shares` (plus any queued remainder) from the position within the per-epoch
+// withdrawal limits and pay them out, less `penalty_bps` which stays in the vault
+fn withdraw_lp_shares(
+    accounts: &mut WithdrawFromPool,
+    shares: u64,
+    penalty_bps: u16,
+    events: &mut EventSeq,
+) -> Result<()> {
+    let clock = Clock::get()?;
+    let max_position_bps = accounts.config.max_withdrawal_bps_per_epoch;
+    let max_pool_bps = accounts.config.max_pool_withdrawal_bps_per_epoch;
+    let pool = &mut accounts.pool;
+    let position = &mut accounts.position;
+
+    let requested = position
+        .queued_withdrawal
+        .checked_add(shares)
+        .ok_or(ErrorCode::MathOverflow)?;
+    require!(requested > 0, ErrorCode::InvalidAmount);
+    require!(position.shares >= requested, ErrorCode::InsufficientShares);
+
+    pool::accrue_pool_weight(pool, clock.unix_timestamp)?;
+    pool::settle_position(
+        pool,
+        position,
+        accounts.last_epoch.as_deref(),
+        clock.unix_timestamp,
+    )?;
+    pool::open_withdrawal_window(pool, position);
+
+    let net_assets = pool::net_assets(pool, accounts.vault.amount);
+    let filled = pool::withdrawal_fill(
+        pool,
+        position,
+        requested,
+        max_position_bps,
+        max_pool_bps,
+        net_assets,
+    );
+    position.queued_withdrawal = requested - filled;
+
+    if position.queued_withdrawal > 0 {
+        emit_seq!(
+            events,
+            WithdrawalQueued {
+                pool: pool.key(),
+                owner: position.owner,
+                queued_shares: position.queued_withdrawal,
+                epoch: pool.current_epoch,
+                timestamp: clock.unix_timestamp,
+            }
+        );
+    }
+    if filled == 0 {
+        return Ok(());
+    }
+
+    let gross = pool::withdrawal_amount(filled, net_assets, pool.total_shares);
+    require!(gross > 0, ErrorCode::PoolInsolvent);
+    // The penalty is simply not paid out, so it accrues to the remaining shares
+    let penalty = fee_share::bps_of(gross, penalty_bps);
+    let amount = gross - penalty;
+
+    position.shares -= filled;
+    position.withdrawn_this_epoch += filled;
+    pool.total_shares -= filled;
+    pool.epoch_withdrawn = pool.epoch_withdrawn.saturating_add(amount);
+
+    process_payout(
+        pool,
+        &accounts.vault,
+        &accounts.owner_token_account,
+        &accounts.payout_mint,
+        &accounts.token_program,
+        amount,
+    )?;
+
+    if penalty > 0 {
+        emit_seq!(
+            events,
+            EarlyWithdrawalPenalty {
+                pool: pool.key(),
+                owner: position.owner,
+                penalty,
+                timestamp: clock.unix_timestamp,
+            }
+        );
+    }
+
+    emit_seq!(
+        events,
+        PoolWithdrawal {
+            ledger_seq: next_ledger_seq(&mut accounts.config)?,
+            instruction: LedgerInstruction::WithdrawFromPool,
+            mint: pool.payout_mint,
+            counterparty: position.owner,
+            amount,
+            shares: filled,
+            queued_shares: position.queued_withdrawal,
+            timestamp: clock.unix_timestamp,
+        }
+    );
+
+    Ok(())
+}
+
+fn process_payout<'info>(
+    pool: &Account<'info, InsurancePool>,
+    vault: &InterfaceAccount<'info, TokenAccount>,
+    destination: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    token_program: &Interface<'info, TokenInterface>,
+    amount: u64,
+) -> Result<()> {
+    let bump = [pool.bump];
+    let signer_seeds: &[&[&[u8]]] = &[&[b"pool", InsurancePool::tier_seed(&pool.tier), &bump]];
+    tokens::transfer(
+        token_program,
+        vault.to_account_info(),
+        destination.to_account_info(),
+        mint,
+        pool.to_account_info(),
+        amount,
+        signer_seeds,
+    )
+}
+
+// Append a custody entry by the current custodian and announce it
+fn append_custody_entry(
+    custody: &mut EvidenceChainOfCustody,
+    action: CustodyAction,
+    hash: [u8; 32],
+    events: &mut EventSeq,
+) -> Result<()> {
+    require!(
+        custody.entries.len() < MAX_CUSTODY_ENTRIES,
+        ErrorCode::CustodyLogFull
+    );
+    custody.entries.push(CustodyEntry {
+        handler: custody.custodian,
+        action,
+        timestamp: Clock::get()?.unix_timestamp,
+        hash,
+    });
+    emit_seq!(
+        events,
+        CustodyEntryAdded {
+            claim_id: custody.claim_id,
+            handler: custody.custodian,
+            action,
+        }
+    );
+    Ok(())
+}
+
+// Seal the custody log as `submitter` files the claim, which must be the current
+// custodian submitting exactly the logged evidence
+fn submit_custody(
+    custody: &mut EvidenceChainOfCustody,
+    submitter: &Pubkey,
+    proof: &[u8],
+    events: &mut EventSeq,
+) -> Result<()> {
+    require!(!custody.submitted, ErrorCode::EvidenceAlreadySubmitted);
+    require!(
+        custody.custodian == *submitter,
+        ErrorCode::NotEvidenceCustodian
+    );
+    let digest = hash(proof).to_bytes();
+    require!(
+        digest == hash(&custody.proof).to_bytes(),
+        ErrorCode::EvidenceMismatch
+    );
+    custody.submitted = true;
+    append_custody_entry(custody, CustodyAction::Submitted, digest, events)
+}
+
+fn return_nft_to_owner(_claim: &mut Account<Claim>) -> Result<()> {
+    // Implementation would return NFT from escrow to original owner
+    Ok(())
+}
+
+// Accounts
+#[derive(Accounts)]
+pub struct InitializeUser<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + UserAccount::INIT_SPACE,
+        seeds = [b"user", user.key().as_ref()],
+        bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    // Funds the new accounts' rent; the user again unless a sponsor pays
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetMaxAutoSwapBps<'info> {
+    #[account(
+        mut,
+        constraint = user_account.wallet == user.key() @ ErrorCode::NotAccountOwner,
+        constraint = user_account.is_pda(&user_account.key()) @ ErrorCode::MigrationRequired,
+        constraint = user_account.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetWatchlistAlertsPaused<'info> {
+    #[account(
+        mut,
+        constraint = user_account.wallet == user.key() @ ErrorCode::NotAccountOwner,
+        constraint = user_account.is_pda(&user_account.key()) @ ErrorCode::MigrationRequired,
+        constraint = user_account.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AddCoveredToken<'info> {
+    #[account(
+        constraint = user_account.wallet == user.key() @ ErrorCode::NotAccountOwner,
+        constraint = user_account.is_pda(&user_account.key()) @ ErrorCode::MigrationRequired,
+        constraint = user_account.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + CoveredTokenList::INIT_SPACE,
+        seeds = [b"covered_tokens", user.key().as_ref()],
+        bump,
+    )]
+    pub covered_tokens: Account<'info, CoveredTokenList>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveCoveredToken<'info> {
+    #[account(mut, seeds = [b"covered_tokens", user.key().as_ref()], bump = covered_tokens.bump)]
+    pub covered_tokens: Account<'info, CoveredTokenList>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WatchlistAlert<'info> {
+    #[account(
+        mut,
+        constraint = user_account.wallet == user.key() @ ErrorCode::NotAccountOwner,
+        constraint = user_account.is_pda(&user_account.key()) @ ErrorCode::MigrationRequired,
+        constraint = user_account.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitScoreHistory<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + ScoreHistory::INIT_SPACE,
+        seeds = [b"score_history", user_account.wallet.as_ref()],
+        bump,
+    )]
+    pub score_archive: Account<'info, ScoreHistory>,
+    #[account(constraint = user_account.is_pda(&user_account.key()) @ ErrorCode::MigrationRequired)]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(index: u32)]
+pub struct GetAggregationReport<'info> {
+    #[account(
+        seeds = [b"aggregation_report", aggregation_report.wallet.as_ref(), &index.to_le_bytes()],
+        bump = aggregation_report.bump,
+    )]
+    pub aggregation_report: Account<'info, AggregationReport>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+}
+
+#[derive(Accounts)]
+pub struct ReadUserAccount<'info> {
+    #[account(constraint = user_account.version_supported() @ ErrorCode::UnsupportedAccountVersion)]
+    pub user_account: Account<'info, UserAccount>,
+}
+
+#[derive(Accounts)]
+#[instruction(tier: u8)]
+pub struct QuotePremium<'info> {
+    #[account(constraint = user_account.version_supported() @ ErrorCode::UnsupportedAccountVersion)]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(
+        seeds = [b"pool", InsurancePool::tier_seed(&pool.tier)],
+        bump = pool.bump,
+        constraint = pool.tier == config.pool_tier_for(tier) @ ErrorCode::WrongSubPool,
+    )]
+    pub pool: Account<'info, InsurancePool>,
+    #[account(address = pool.vault @ ErrorCode::InvalidPoolVault)]
+    pub pool_vault: InterfaceAccount<'info, TokenAccount>,
+}
+
+#[derive(Accounts)]
+pub struct ReadPolicy<'info> {
+    #[account(constraint = insurance_nft.version_supported() @ ErrorCode::UnsupportedAccountVersion)]
+    pub insurance_nft: Account<'info, InsuranceNFT>,
+}
+
+#[derive(Accounts)]
+pub struct QuotePayout<'info> {
+    #[account(
+        seeds = [b"pool", InsurancePool::tier_seed(&pool.tier)],
+        bump = pool.bump,
+        address = claim.pool @ ErrorCode::WrongSubPool,
+    )]
+    pub pool: Account<'info, InsurancePool>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(address = pool.vault @ ErrorCode::InvalidPoolVault)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(constraint = claim.version_supported() @ ErrorCode::UnsupportedAccountVersion)]
+    pub claim: Account<'info, Claim>,
+    #[account(seeds = [b"reinsurance_pool"], bump = reinsurance_pool.bump)]
+    pub reinsurance_pool: Option<Account<'info, ReinsurancePool>>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyScoreInclusion<'info> {
+    #[account(constraint = user_account.version_supported() @ ErrorCode::UnsupportedAccountVersion)]
+    pub user_account: Account<'info, UserAccount>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateLegacyUser<'info> {
+    /// CHECK: a UserAccount of any layout version; owner, discriminator, layout and
+    /// wallet are checked in migrate_legacy_user
+    #[account(mut)]
+    pub legacy_user_account: UncheckedAccount<'info>,
+    #[account(
+        init,
+        payer = user,
+        space = 8 + UserAccount::INIT_SPACE,
+        seeds = [b"user", user.key().as_ref()],
+        bump,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateAccountLayout<'info> {
+    /// CHECK: owner, discriminator and legacy length are checked in migrate_layout
+    #[account(mut)]
+    pub account: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseUserAccount<'info> {
+    #[account(
+        mut,
+        close = rent_recipient,
+        constraint = user_account.wallet == user.key() @ ErrorCode::NotAccountOwner,
+        constraint = user_account.is_pda(&user_account.key()) @ ErrorCode::MigrationRequired,
+        constraint = user_account.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    // Closed alongside the user account when the wallet has ever filed a claim
+    #[account(
+        mut,
+        close = user,
+        seeds = [b"claim_history", user.key().as_ref()],
+        bump = claim_history.bump,
+    )]
+    pub claim_history: Option<Account<'info, ClaimHistory>>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    // Receives the rent: whoever funded the account, or the wallet for accounts
+    // created before the payer was recorded. Must be a system account.
+    #[account(mut, address = user_account.rent_recipient() @ ErrorCode::InvalidRentRecipient)]
+    pub rent_recipient: SystemAccount<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct Subscribe<'info> {
+    #[account(
+        mut,
+        constraint = user_account.wallet == user.key() @ ErrorCode::NotAccountOwner,
+        constraint = user_account.is_pda(&user_account.key()) @ ErrorCode::MigrationRequired,
+        constraint = user_account.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        constraint = payment_account.mint == treasury.mint @ ErrorCode::InvalidMint,
+        constraint = payment_account.owner == user.key() @ ErrorCode::InvalidTokenOwner,
+    )]
+    pub payment_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut, seeds = [b"treasury"], bump = treasury.bump)]
+    pub treasury: Account<'info, Treasury>,
+    #[account(
+        mut,
+        address = treasury.vault @ ErrorCode::InvalidTreasuryVault,
+        constraint = treasury::is_treasury(&treasury, &treasury_account, &treasury.mint) @ ErrorCode::InvalidTreasury,
+    )]
+    pub treasury_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = treasury.mint @ ErrorCode::InvalidMint)]
+    pub payment_mint: InterfaceAccount<'info, Mint>,
+    #[account(mut)]
+    pub promo_code_account: Option<Account<'info, PromoCode>>,
+    #[account(mut)]
+    pub governance_mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(mut)]
+    pub governance_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    // Only needed when a compliance reference is passed
+    pub memo_program: Option<Program<'info, Memo>>,
+    /// CHECK: instructions sysvar, needed to identify the caller of a CPI and to read
+    /// a price quote's signature
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+    /// CHECK: the wallet's WalletActivity PDA, checked and created by the handler
+    #[account(mut)]
+    pub activity: Option<UncheckedAccount<'info>>,
+    // Only needed to create the activity buffer
+    pub system_program: Option<Program<'info, System>>,
+}
+
+#[derive(Accounts)]
+pub struct FundMaintenance<'info> {
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub funder: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct BatchExpireSubscriptions<'info> {
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+    #[account(
+        init_if_needed,
+        payer = keeper,
+        space = 8 + KeeperStats::INIT_SPACE,
+        seeds = [b"keeper_stats", keeper.key().as_ref()],
+        bump,
+    )]
+    pub keeper_stats: Account<'info, KeeperStats>,
+    /// CHECK: instructions sysvar, needed to identify the caller of a CPI and to read
+    /// the keeper's compute budget
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExpireSubscription<'info> {
+    #[account(
+        mut,
+        constraint = user_account.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+    #[account(
+        init_if_needed,
+        payer = keeper,
+        space = 8 + KeeperStats::INIT_SPACE,
+        seeds = [b"keeper_stats", keeper.key().as_ref()],
+        bump,
+    )]
+    pub keeper_stats: Account<'info, KeeperStats>,
+    /// CHECK: instructions sysvar; without it only the signature fee is reimbursed
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimNoClaimRebate<'info> {
+    #[account(
+        mut,
+        constraint = user_account.wallet == user.key() @ ErrorCode::NotAccountOwner,
+        constraint = user_account.is_pda(&user_account.key()) @ ErrorCode::MigrationRequired,
+        constraint = user_account.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    /// CHECK: the wallet's ClaimHistory PDA, which only exists once a claim has been filed
+    #[account(seeds = [b"claim_history", user.key().as_ref()], bump)]
+    pub claim_history: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut, seeds = [b"treasury"], bump = treasury.bump)]
+    pub treasury: Account<'info, Treasury>,
+    #[account(mut, address = treasury.vault @ ErrorCode::InvalidTreasuryVault)]
+    pub treasury_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = user_token_account.mint == treasury.mint @ ErrorCode::InvalidMint,
+        constraint = user_token_account.owner == user.key() @ ErrorCode::InvalidTokenOwner,
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub user: Signer<'info>,
+    #[account(address = treasury.mint @ ErrorCode::InvalidMint)]
+    pub treasury_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    // Only needed when a compliance reference is passed
+    pub memo_program: Option<Program<'info, Memo>>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateRiskScore<'info> {
+    #[account(
+        mut,
+        constraint = user_account.is_pda(&user_account.key()) @ ErrorCode::MigrationRequired,
+        constraint = user_account.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut, seeds = [b"oracle_registry"], bump = oracle_registry.bump)]
+    pub oracle_registry: Account<'info, OracleRegistry>,
+    // Archive of raw scores, if the wallet has one
+    #[account(mut, seeds = [b"score_history", user_account.wallet.as_ref()], bump = score_archive.bump)]
+    pub score_archive: Option<Account<'info, ScoreHistory>>,
+    // Tokens a breach would act on, reported with it
+    #[account(seeds = [b"covered_tokens", user_account.wallet.as_ref()], bump = covered_tokens.bump)]
+    pub covered_tokens: Option<Account<'info, CoveredTokenList>>,
+    // Checked for a coverage gap after the score is stored
+    #[account(
+        constraint = insurance_nft.owner == user_account.wallet @ ErrorCode::NotNftOwner,
+        constraint = insurance_nft.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub insurance_nft: Option<Account<'info, InsuranceNFT>>,
+    /// CHECK: the wallet's WalletActivity PDA, checked and created by the handler
+    #[account(mut)]
+    pub activity: Option<UncheckedAccount<'info>>,
+    /// CHECK: instructions sysvar, read for the oracle's ed25519 signature
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+    pub oracle: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ApplyPendingScore<'info> {
+    #[account(
+        mut,
+        constraint = user_account.is_pda(&user_account.key()) @ ErrorCode::MigrationRequired,
+        constraint = user_account.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    // Archive of raw scores, if the wallet has one
+    #[account(mut, seeds = [b"score_history", user_account.wallet.as_ref()], bump = score_archive.bump)]
+    pub score_archive: Option<Account<'info, ScoreHistory>>,
+    // Tokens a breach would act on, reported with it
+    #[account(seeds = [b"covered_tokens", user_account.wallet.as_ref()], bump = covered_tokens.bump)]
+    pub covered_tokens: Option<Account<'info, CoveredTokenList>>,
+    /// CHECK: the wallet's WalletActivity PDA, checked and created by the handler
+    #[account(mut)]
+    pub activity: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct DisputePendingScore<'info> {
+    #[account(
+        mut,
+        constraint = user_account.wallet == user.key() @ ErrorCode::NotAccountOwner,
+        constraint = user_account.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        init,
+        payer = user,
+        space = 8 + OracleDispute::INIT_SPACE,
+        seeds = [b"oracle_dispute", user.key().as_ref()],
+        bump,
+    )]
+    pub oracle_dispute: Account<'info, OracleDispute>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ResolveScoreDispute<'info> {
+    #[account(
+        mut,
+        constraint = user_account.wallet == oracle_dispute.wallet @ ErrorCode::NotAccountOwner,
+        constraint = user_account.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        seeds = [b"oracle_dispute", oracle_dispute.wallet.as_ref()],
+        bump = oracle_dispute.bump,
+        close = wallet,
+    )]
+    pub oracle_dispute: Account<'info, OracleDispute>,
+    /// CHECK: the disputing wallet, refunded the dispute account's rent
+    #[account(mut, address = oracle_dispute.wallet @ ErrorCode::NotAccountOwner)]
+    pub wallet: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(seeds = [b"governance"], bump = governance.bump)]
+    pub governance: Account<'info, Governance>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateRiskScoreConsensus<'info> {
+    #[account(
+        mut,
+        constraint = user_account.is_pda(&user_account.key()) @ ErrorCode::MigrationRequired,
+        constraint = user_account.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut, seeds = [b"oracle_registry"], bump = oracle_registry.bump)]
+    pub oracle_registry: Account<'info, OracleRegistry>,
+    // Archive of raw scores, if the wallet has one
+    #[account(mut, seeds = [b"score_history", user_account.wallet.as_ref()], bump = score_archive.bump)]
+    pub score_archive: Option<Account<'info, ScoreHistory>>,
+    // Tokens a breach would act on, reported with it
+    #[account(seeds = [b"covered_tokens", user_account.wallet.as_ref()], bump = covered_tokens.bump)]
+    pub covered_tokens: Option<Account<'info, CoveredTokenList>>,
+    /// CHECK: the wallet's next AggregationReport PDA, created by the handler; needs
+    /// the score archive, payer and system program
+    #[account(mut)]
+    pub aggregation_report: Option<UncheckedAccount<'info>>,
+    /// CHECK: the wallet's WalletActivity PDA, checked and created by the handler
+    #[account(mut)]
+    pub activity: Option<UncheckedAccount<'info>>,
+    /// CHECK: instructions sysvar, read for each oracle's ed25519 signature
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+    #[account(mut)]
+    pub payer: Option<Signer<'info>>,
+    pub system_program: Option<Program<'info, System>>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeOracleRegistry<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + OracleRegistry::INIT_SPACE,
+        seeds = [b"oracle_registry"],
+        bump,
+    )]
+    pub oracle_registry: Account<'info, OracleRegistry>,
+    #[account(seeds = [b"config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: instructions sysvar, only needed to identify the caller of a CPI
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePoolApy<'info> {
+    #[account(mut, seeds = [b"pool", InsurancePool::tier_seed(&pool.tier)], bump = pool.bump)]
+    pub pool: Account<'info, InsurancePool>,
+    #[account(seeds = [b"oracle_registry"], bump = oracle_registry.bump, has_one = apy_oracle @ ErrorCode::UnknownOracle)]
+    pub oracle_registry: Account<'info, OracleRegistry>,
+    pub apy_oracle: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PublishPoolTvl<'info> {
+    #[account(mut, seeds = [b"pool", InsurancePool::tier_seed(&pool.tier)], bump = pool.bump)]
+    pub pool: Account<'info, InsurancePool>,
+    #[account(address = pool.vault @ ErrorCode::InvalidPoolVault)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    /// CHECK: the configured Switchboard feed, validated by Switchboard
+    #[account(mut, address = config.tvl_feed @ ErrorCode::InvalidTvlFeed)]
+    pub tvl_feed: UncheckedAccount<'info>,
+    /// CHECK: PDA set as the feed's publishing authority
+    #[account(seeds = [switchboard::PUBLISHER_SEED], bump)]
+    pub tvl_publisher: UncheckedAccount<'info>,
+    /// CHECK: Switchboard V2 program
+    #[account(address = switchboard::SWITCHBOARD_V2_ID)]
+    pub switchboard_program: UncheckedAccount<'info>,
+    pub keeper: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetFallbackOracles<'info> {
+    #[account(mut, seeds = [b"oracle_registry"], bump = oracle_registry.bump)]
+    pub oracle_registry: Account<'info, OracleRegistry>,
+    #[account(seeds = [b"config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub admin: Signer<'info>,
+    /// CHECK: instructions sysvar, only needed to identify the caller of a CPI
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct OracleHeartbeat<'info> {
+    #[account(mut, seeds = [b"oracle_registry"], bump = oracle_registry.bump)]
+    pub oracle_registry: Account<'info, OracleRegistry>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    // Optional wallet and policy to check for a coverage gap on this ping
+    #[account(
+        constraint = user_account.is_pda(&user_account.key()) @ ErrorCode::MigrationRequired,
+        constraint = user_account.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub user_account: Option<Account<'info, UserAccount>>,
+    #[account(
+        constraint = insurance_nft.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub insurance_nft: Option<Account<'info, InsuranceNFT>>,
+    pub oracle: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CoverageGapCheck<'info> {
+    #[account(
+        constraint = user_account.is_pda(&user_account.key()) @ ErrorCode::MigrationRequired,
+        constraint = user_account.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        constraint = insurance_nft.owner == user_account.wallet @ ErrorCode::NotNftOwner,
+        constraint = insurance_nft.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub insurance_nft: Account<'info, InsuranceNFT>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub caller: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(tier: u8)]
+pub struct MintInsuranceNft<'info> {
+    #[account(
+        mut,
+        constraint = user_account.wallet == user.key() @ ErrorCode::NotAccountOwner,
+        constraint = user_account.is_pda(&user_account.key()) @ ErrorCode::MigrationRequired,
+        constraint = user_account.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(init, payer = payer, space = 8 + InsuranceNFT::INIT_SPACE)]
+    pub insurance_nft: Account<'info, InsuranceNFT>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + PolicyNumberIndex::INIT_SPACE,
+        seeds = [b"policy_number", config.next_policy_number.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub policy_number_index: Account<'info, PolicyNumberIndex>,
+    #[account(mut)]
+    pub insurance_nft_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(
+        mut,
+        seeds = [b"pool", InsurancePool::tier_seed(&pool.tier)],
+        bump = pool.bump,
+        constraint = pool.tier == config.pool_tier_for(tier) @ ErrorCode::WrongSubPool,
+    )]
+    pub pool: Account<'info, InsurancePool>,
+    #[account(mut, address = pool.vault @ ErrorCode::InvalidPoolVault)]
+    pub pool_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = premium_token_account.mint == pool.payout_mint @ ErrorCode::InvalidMint,
+        constraint = premium_token_account.owner == user.key() @ ErrorCode::InvalidTokenOwner,
+    )]
+    pub premium_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub governance_mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(mut)]
+    pub governance_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    // Funds the new accounts' rent; the user again unless a sponsor pays
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: mint authority for insurance NFTs
+    pub iris_authority: AccountInfo<'info>,
+    #[account(address = pool.payout_mint @ ErrorCode::InvalidMint)]
+    pub payout_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    // Only needed when a compliance reference is passed
+    pub memo_program: Option<Program<'info, Memo>>,
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+#[derive(Accounts)]
+pub struct InitPolicyTree<'info> {
+    #[account(mut, seeds = [b"config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    /// CHECK: Bubblegum tree config PDA, created by Bubblegum
+    #[account(mut, seeds = [merkle_tree.key().as_ref()], bump, seeds::program = cnft::BUBBLEGUM_ID)]
+    pub tree_config: UncheckedAccount<'info>,
+    /// CHECK: zeroed and owned by the compression program, which validates its size
+    #[account(mut)]
+    pub merkle_tree: UncheckedAccount<'info>,
+    /// CHECK: PDA that creates and mints into the policy tree
+    #[account(seeds = [cnft::TREE_AUTHORITY_SEED], bump)]
+    pub tree_authority: UncheckedAccount<'info>,
+    /// CHECK: SPL noop program
+    #[account(address = compression::NOOP_ID)]
+    pub log_wrapper: UncheckedAccount<'info>,
+    /// CHECK: SPL account compression program
+    #[account(address = compression::ACCOUNT_COMPRESSION_ID)]
+    pub compression_program: UncheckedAccount<'info>,
+    /// CHECK: Bubblegum program
+    #[account(address = cnft::BUBBLEGUM_ID)]
+    pub bubblegum_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: instructions sysvar, only needed to identify the caller of a CPI
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+#[instruction(tier: u8, payout_cap: u64, duration: u64, max_premium: u64, policy_id: Pubkey)]
+pub struct MintInsuranceCnft<'info> {
+    #[account(
+        mut,
+        constraint = user_account.wallet == user.key() @ ErrorCode::NotAccountOwner,
+        constraint = user_account.is_pda(&user_account.key()) @ ErrorCode::MigrationRequired,
+        constraint = user_account.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + InsuranceNFT::INIT_SPACE,
+        seeds = [b"policy", policy_id.as_ref()],
+        bump,
+    )]
+    pub insurance_nft: Account<'info, InsuranceNFT>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + PolicyNumberIndex::INIT_SPACE,
+        seeds = [b"policy_number", config.next_policy_number.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub policy_number_index: Account<'info, PolicyNumberIndex>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(
+        mut,
+        seeds = [b"pool", InsurancePool::tier_seed(&pool.tier)],
+        bump = pool.bump,
+        constraint = pool.tier == config.pool_tier_for(tier) @ ErrorCode::WrongSubPool,
+    )]
+    pub pool: Account<'info, InsurancePool>,
+    #[account(mut, address = pool.vault @ ErrorCode::InvalidPoolVault)]
+    pub pool_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = premium_token_account.mint == pool.payout_mint @ ErrorCode::InvalidMint,
+        constraint = premium_token_account.owner == user.key() @ ErrorCode::InvalidTokenOwner,
+    )]
+    pub premium_token_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: Bubblegum tree config of the policy tree
+    #[account(mut, seeds = [merkle_tree.key().as_ref()], bump, seeds::program = cnft::BUBBLEGUM_ID)]
+    pub tree_config: UncheckedAccount<'info>,
+    /// CHECK: the protocol's policy tree
+    #[account(mut, address = config.policy_tree @ ErrorCode::PolicyTreeNotInitialized)]
+    pub merkle_tree: UncheckedAccount<'info>,
+    /// CHECK: PDA that mints into the policy tree
+    #[account(seeds = [cnft::TREE_AUTHORITY_SEED], bump)]
+    pub tree_authority: UncheckedAccount<'info>,
+    /// CHECK: SPL noop program
+    #[account(address = compression::NOOP_ID)]
+    pub log_wrapper: UncheckedAccount<'info>,
+    /// CHECK: SPL account compression program
+    #[account(address = compression::ACCOUNT_COMPRESSION_ID)]
+    pub compression_program: UncheckedAccount<'info>,
+    /// CHECK: Bubblegum program
+    #[account(address = cnft::BUBBLEGUM_ID)]
+    pub bubblegum_program: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    // Funds the new accounts' rent; the user again unless a sponsor pays
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(address = pool.payout_mint @ ErrorCode::InvalidMint)]
+    pub payout_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    // Only needed when a compliance reference is passed
+    pub memo_program: Option<Program<'info, Memo>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct BurnExpiredNft<'info> {
+    #[account(
+        mut,
+        constraint = user_account.wallet == owner.key() @ ErrorCode::NotAccountOwner,
+        constraint = user_account.is_pda(&user_account.key()) @ ErrorCode::MigrationRequired,
+        constraint = user_account.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        close = rent_recipient,
+        has_one = owner @ ErrorCode::NotNftOwner,
+        constraint = insurance_nft.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub insurance_nft: Account<'info, InsuranceNFT>,
+    #[account(mut, address = insurance_nft.pool @ ErrorCode::WrongSubPool)]
+    pub pool: Account<'info, InsurancePool>,
+    #[account(mut, address = insurance_nft.token_mint)]
+    pub nft_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        constraint = owner_nft_account.mint == insurance_nft.token_mint @ ErrorCode::InvalidMint,
+        constraint = owner_nft_account.owner == owner.key() @ ErrorCode::NotNftOwner,
+    )]
+    pub owner_nft_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    // Receives the rent: whoever funded the policy, or the owner for policies minted
+    // before the payer was recorded. Must be a system account.
+    #[account(mut, address = insurance_nft.rent_recipient() @ ErrorCode::InvalidRentRecipient)]
+    pub rent_recipient: SystemAccount<'info>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct ForceCloseExpiredNft<'info> {
+    #[account(
+        mut,
+        constraint = user_account.wallet == owner.key() @ ErrorCode::NotAccountOwner,
+        constraint = user_account.is_pda(&user_account.key()) @ ErrorCode::MigrationRequired,
+        constraint = user_account.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        close = rent_recipient,
+        has_one = owner @ ErrorCode::NotNftOwner,
+        constraint = insurance_nft.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub insurance_nft: Account<'info, InsuranceNFT>,
+    #[account(mut, address = insurance_nft.pool @ ErrorCode::WrongSubPool)]
+    pub pool: Account<'info, InsurancePool>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    // Receives the rent: whoever funded the policy, or the owner for policies minted
+    // before the payer was recorded. Must be a system account.
+    #[account(mut, address = insurance_nft.rent_recipient() @ ErrorCode::InvalidRentRecipient)]
+    pub rent_recipient: SystemAccount<'info>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(recipient: Pubkey)]
+pub struct GenerateCoverageCertificate<'info> {
+    #[account(
+        has_one = owner @ ErrorCode::NotNftOwner,
+        constraint = insurance_nft.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub insurance_nft: Account<'info, InsuranceNFT>,
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + CoverageCertificate::INIT_SPACE,
+        seeds = [b"coverage_certificate", insurance_nft.key().as_ref(), recipient.as_ref()],
+        bump,
+    )]
+    pub coverage_certificate: Account<'info, CoverageCertificate>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeCoverageCertificate<'info> {
+    #[account(
+        mut,
+        close = owner,
+        seeds = [
+            b"coverage_certificate",
+            coverage_certificate.policy.as_ref(),
+            coverage_certificate.recipient.as_ref(),
+        ],
+        bump = coverage_certificate.bump,
+        constraint = coverage_certificate.insured_wallet == owner.key() @ ErrorCode::NotNftOwner,
+    )]
+    pub coverage_certificate: Account<'info, CoverageCertificate>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyCertificate<'info> {
+    #[account(
+        seeds = [
+            b"coverage_certificate",
+            coverage_certificate.policy.as_ref(),
+            coverage_certificate.recipient.as_ref(),
+        ],
+        bump = coverage_certificate.bump,
+    )]
+    pub coverage_certificate: Account<'info, CoverageCertificate>,
+    #[account(
+        address = coverage_certificate.policy @ ErrorCode::InvalidCertificate,
+        constraint = insurance_nft.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub insurance_nft: Account<'info, InsuranceNFT>,
+}
+
+#[derive(Accounts)]
+pub struct UnlinkResolvedClaim<'info> {
+    #[account(
+        mut,
+        constraint = user_account.wallet == claimant.key() @ ErrorCode::NotAccountOwner,
+        constraint = user_account.is_pda(&user_account.key()) @ ErrorCode::MigrationRequired,
+        constraint = user_account.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        has_one = claimant @ ErrorCode::InvalidClaimantAccount,
+        constraint = claim.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub claim: Account<'info, Claim>,
+    pub claimant: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeMintWaitlist<'info> {
+    #[account(seeds = [b"pool", InsurancePool::tier_seed(&pool.tier)], bump = pool.bump)]
+    pub pool: Account<'info, InsurancePool>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + MintWaitlist::INIT_SPACE,
+        seeds = [b"mint_waitlist", pool.key().as_ref()],
+        bump,
+    )]
+    pub mint_waitlist: Account<'info, MintWaitlist>,
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"waitlist_vault", pool.key().as_ref()],
+        bump,
+        token::mint = payout_mint,
+        token::authority = mint_waitlist,
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = pool.payout_mint @ ErrorCode::InvalidMint)]
+    pub payout_mint: InterfaceAccount<'info, Mint>,
+    #[account(seeds = [b"governance"], bump = governance.bump)]
+    pub governance: Account<'info, Governance>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(tier: u8)]
+pub struct JoinMintWaitlist<'info> {
+    #[account(
+        mut,
+        constraint = user_account.wallet == user.key() @ ErrorCode::NotAccountOwner,
+        constraint = user_account.is_pda(&user_account.key()) @ ErrorCode::MigrationRequired,
+        constraint = user_account.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(
+        seeds = [b"pool", InsurancePool::tier_seed(&pool.tier)],
+        bump = pool.bump,
+        constraint = pool.tier == config.pool_tier_for(tier) @ ErrorCode::WrongSubPool,
+    )]
+    pub pool: Account<'info, InsurancePool>,
+    #[account(address = pool.vault @ ErrorCode::InvalidPoolVault)]
+    pub pool_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, seeds = [b"mint_waitlist", pool.key().as_ref()], bump = mint_waitlist.bump)]
+    pub mint_waitlist: Account<'info, MintWaitlist>,
+    #[account(mut, address = mint_waitlist.vault @ ErrorCode::InvalidWaitlistVault)]
+    pub waitlist_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = premium_token_account.mint == pool.payout_mint @ ErrorCode::InvalidMint,
+        constraint = premium_token_account.owner == user.key() @ ErrorCode::InvalidTokenOwner,
+    )]
+    pub premium_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub user: Signer<'info>,
+    #[account(address = pool.payout_mint @ ErrorCode::InvalidMint)]
+    pub payout_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct ProcessWaitlist<'info> {
+    #[account(mut, seeds = [b"pool", InsurancePool::tier_seed(&pool.tier)], bump = pool.bump)]
+    pub pool: Account<'info, InsurancePool>,
+    #[account(mut, address = pool.vault @ ErrorCode::InvalidPoolVault)]
+    pub pool_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut, seeds = [b"mint_waitlist", pool.key().as_ref()], bump = mint_waitlist.bump)]
+    pub mint_waitlist: Account<'info, MintWaitlist>,
+    #[account(mut, address = mint_waitlist.vault @ ErrorCode::InvalidWaitlistVault)]
+    pub waitlist_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = user_account.is_pda(&user_account.key()) @ ErrorCode::MigrationRequired,
+        constraint = user_account.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(init, payer = keeper, space = 8 + InsuranceNFT::INIT_SPACE)]
+    pub insurance_nft: Account<'info, InsuranceNFT>,
+    #[account(
+        init,
+        payer = keeper,
+        space = 8 + PolicyNumberIndex::INIT_SPACE,
+        seeds = [b"policy_number", config.next_policy_number.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub policy_number_index: Account<'info, PolicyNumberIndex>,
+    #[account(mut, constraint = insurance_nft_account.mint == mint.key() @ ErrorCode::InvalidMint)]
+    pub insurance_nft_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+    /// CHECK: mint authority for insurance NFTs
+    pub iris_authority: AccountInfo<'info>,
+    #[account(address = pool.payout_mint @ ErrorCode::InvalidMint)]
+    pub payout_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExpireWaitlistEntry<'info> {
+    #[account(mut, seeds = [b"mint_waitlist", mint_waitlist.pool.as_ref()], bump = mint_waitlist.bump)]
+    pub mint_waitlist: Account<'info, MintWaitlist>,
+    #[account(mut, address = mint_waitlist.vault @ ErrorCode::InvalidWaitlistVault)]
+    pub waitlist_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, constraint = refund_token_account.mint == waitlist_vault.mint @ ErrorCode::InvalidMint)]
+    pub refund_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(address = waitlist_vault.mint @ ErrorCode::InvalidMint)]
+    pub payout_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(action_type: ProtectionAction, token: Pubkey)]
+pub struct TriggerProtection<'info> {
+    #[account(
+        mut,
+        constraint = user_account.wallet == user.key() @ ErrorCode::NotAccountOwner,
+        constraint = user_account.is_pda(&user_account.key()) @ ErrorCode::MigrationRequired,
+        constraint = user_account.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    // Tokens the wallet's protection may act on; required for swaps and freezes
+    #[account(seeds = [b"covered_tokens", user_account.wallet.as_ref()], bump = covered_tokens.bump)]
+    pub covered_tokens: Option<Account<'info, CoveredTokenList>>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    // Only when action logs are not compressed
+    #[account(init, payer = payer, space = 8 + ActionLog::INIT_SPACE)]
+    pub action_log: Option<Account<'info, ActionLog>>,
+    /// CHECK: the protocol's action log tree, validated by the compression program
+    #[account(mut, address = config.action_log_tree.merkle_tree @ ErrorCode::InvalidActionLogTree)]
+    pub merkle_tree: Option<UncheckedAccount<'info>>,
+    /// CHECK: PDA holding write authority over the action log tree
+    #[account(seeds = [compression::LOG_AUTHORITY_SEED], bump)]
+    pub log_authority: Option<UncheckedAccount<'info>>,
+    /// CHECK: SPL account compression program
+    #[account(address = compression::ACCOUNT_COMPRESSION_ID)]
+    pub compression_program: Option<UncheckedAccount<'info>>,
+    /// CHECK: SPL noop program, carries the full entry for indexers
+    #[account(address = compression::NOOP_ID)]
+    pub noop_program: Option<UncheckedAccount<'info>>,
+    // Mint of the covered asset, checked for unapproved transfer hooks. Legacy and
+    // Token-2022 mints are both accepted.
+    #[account(address = token @ ErrorCode::InvalidMint)]
+    pub covered_mint: Option<InterfaceAccount<'info, Mint>>,
+    // The user's account of the token being swapped; required for swaps
+    #[account(
+        constraint = swap_token_account.mint == token @ ErrorCode::InvalidMint,
+        constraint = swap_token_account.owner == user_account.wallet @ ErrorCode::InvalidTokenOwner,
+    )]
+    pub swap_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: DEX program the swap routes through, checked against the swap allowlist;
+    /// required for swaps
+    #[account(executable)]
+    pub swap_program: Option<UncheckedAccount<'info>>,
+    /// CHECK: the wallet's WalletActivity PDA, checked and created by the handler
+    #[account(mut)]
+    pub activity: Option<UncheckedAccount<'info>>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    // Funds the new accounts' rent; the user again unless a sponsor pays
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(claim_id: Pubkey)]
+pub struct OpenClaimEvidence<'info> {
+    #[account(
+        init,
+        payer = handler,
+        space = 8 + EvidenceChainOfCustody::INIT_SPACE,
+        seeds = [b"evidence_custody", claim_id.as_ref()],
+        bump,
+    )]
+    pub evidence_custody: Account<'info, EvidenceChainOfCustody>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub handler: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateClaimEvidence<'info> {
+    #[account(
+        mut,
+        seeds = [b"evidence_custody", evidence_custody.claim_id.as_ref()],
+        bump = evidence_custody.bump,
+        has_one = custodian @ ErrorCode::NotEvidenceCustodian,
+    )]
+    pub evidence_custody: Account<'info, EvidenceChainOfCustody>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub custodian: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InvalidateClaim<'info> {
+    #[account(
+        mut,
+        constraint = claim.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub claim: Account<'info, Claim>,
+    #[account(
+        mut,
+        seeds = [b"user", claim.claimant.as_ref()],
+        bump = claimant_account.bump,
+        constraint = claimant_account.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub claimant_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        seeds = [b"pool", InsurancePool::tier_seed(&pool.tier)],
+        bump = pool.bump,
+        address = claim.pool @ ErrorCode::WrongSubPool,
+    )]
+    pub pool: Account<'info, InsurancePool>,
+    #[account(seeds = [b"oracle_registry"], bump = oracle_registry.bump)]
+    pub oracle_registry: Account<'info, OracleRegistry>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub oracle: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PayClaimPenalty<'info> {
+    #[account(
+        mut,
+        constraint = user_account.wallet == user.key() @ ErrorCode::NotAccountOwner,
+        constraint = user_account.is_pda(&user_account.key()) @ ErrorCode::MigrationRequired,
+        constraint = user_account.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        constraint = payment_account.mint == treasury.mint @ ErrorCode::InvalidMint,
+        constraint = payment_account.owner == user.key() @ ErrorCode::InvalidTokenOwner,
+    )]
+    pub payment_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut, seeds = [b"treasury"], bump = treasury.bump)]
+    pub treasury: Account<'info, Treasury>,
+    #[account(
+        mut,
+        address = treasury.vault @ ErrorCode::InvalidTreasuryVault,
+        constraint = treasury::is_treasury(&treasury, &treasury_account, &treasury.mint) @ ErrorCode::InvalidTreasury,
+    )]
+    pub treasury_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = treasury.mint @ ErrorCode::InvalidMint)]
+    pub payment_mint: InterfaceAccount<'info, Mint>,
+    pub user: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct InitiateClaim<'info> {
+    #[account(
+        mut,
+        constraint = insurance_nft.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub insurance_nft: Account<'info, InsuranceNFT>,
+    // SPL policies only
+    #[account(mut)]
+    pub insurance_nft_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(init, payer = payer, space = 8 + Claim::INIT_SPACE)]
+    pub claim: Account<'info, Claim>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + ClaimNumberIndex::INIT_SPACE,
+        seeds = [b"claim_number", config.next_claim_number.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub claim_number_index: Account<'info, ClaimNumberIndex>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + ClaimHistory::INIT_SPACE,
+        seeds = [b"claim_history", user.key().as_ref()],
+        bump,
+    )]
+    pub claim_history: Account<'info, ClaimHistory>,
+    // Custody log for the evidence, if one was kept before submission
+    #[account(mut, seeds = [b"evidence_custody", claim.key().as_ref()], bump = evidence_custody.bump)]
+    pub evidence_custody: Option<Account<'info, EvidenceChainOfCustody>>,
+    #[account(mut)]
+    pub claim_escrow: Option<InterfaceAccount<'info, TokenAccount>>,
+    // Compressed policies only
+    /// CHECK: PDA the policy leaf is delegated to while the claim is open
+    #[account(seeds = [cnft::ESCROW_SEED], bump)]
+    pub cnft_escrow: Option<UncheckedAccount<'info>>,
+    /// CHECK: the leaf's current delegate, verified by Bubblegum as part of the leaf
+    pub previous_leaf_delegate: Option<UncheckedAccount<'info>>,
+    /// CHECK: Bubblegum tree config of the policy tree
+    #[account(mut)]
+    pub tree_config: Option<UncheckedAccount<'info>>,
+    /// CHECK: checked against the policy's leaf in the handler
+    #[account(mut)]
+    pub merkle_tree: Option<UncheckedAccount<'info>>,
+    /// CHECK: SPL noop program
+    #[account(address = compression::NOOP_ID)]
+    pub log_wrapper: Option<UncheckedAccount<'info>>,
+    /// CHECK: SPL account compression program
+    #[account(address = compression::ACCOUNT_COMPRESSION_ID)]
+    pub compression_program: Option<UncheckedAccount<'info>>,
+    /// CHECK: Bubblegum program
+    #[account(address = cnft::BUBBLEGUM_ID)]
+    pub bubblegum_program: Option<UncheckedAccount<'info>>,
+    #[account(
+        mut,
+        constraint = user_account.wallet == user.key() @ ErrorCode::NotAccountOwner,
+        constraint = user_account.is_pda(&user_account.key()) @ ErrorCode::MigrationRequired,
+        constraint = user_account.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(seeds = [b"governance"], bump = governance.bump)]
+    pub governance: Account<'info, Governance>,
+    // Optional; a fresh one fast-tracks the claim
+    #[account(mut, seeds = [b"loss_attestation", user.key().as_ref()], bump = loss_attestation.bump)]
+    pub loss_attestation: Option<Account<'info, LossAttestation>>,
+    /// CHECK: the wallet's WalletActivity PDA, checked and created by the handler
+    #[account(mut)]
+    pub activity: Option<UncheckedAccount<'info>>,
+    // Pool that covers the NFT's tier; payouts for this claim come from it
+    #[account(
+        seeds = [b"pool", InsurancePool::tier_seed(&pool.tier)],
+        bump = pool.bump,
+        constraint = pool.tier == config.pool_tier_for(insurance_nft.tier) @ ErrorCode::WrongSubPool,
+    )]
+    pub pool: Account<'info, InsurancePool>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    // Funds the new accounts' rent; the user again unless a sponsor pays
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    // Required for SPL policies, alongside insurance_nft_account and claim_escrow
+    #[account(address = insurance_nft.token_mint @ ErrorCode::InvalidMint)]
+    pub nft_mint: Option<InterfaceAccount<'info, Mint>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: instructions sysvar, only needed to identify the caller of a CPI
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+    /// CHECK: SlotHashes sysvar, read raw; needed for a slot anchor
+    #[account(address = slot_hashes::SLOT_HASHES_ID)]
+    pub slot_hashes: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeAttestorRegistry<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + AttestorRegistry::INIT_SPACE,
+        seeds = [b"attestor_registry"],
+        bump,
+    )]
+    pub attestor_registry: Account<'info, AttestorRegistry>,
+    #[account(seeds = [b"config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: instructions sysvar, only needed to identify the caller of a CPI
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct SetAttestors<'info> {
+    #[account(mut, seeds = [b"attestor_registry"], bump = attestor_registry.bump)]
+    pub attestor_registry: Account<'info, AttestorRegistry>,
+    #[account(seeds = [b"config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub admin: Signer<'info>,
+    /// CHECK: instructions sysvar, only needed to identify the caller of a CPI
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+#[instruction(wallet: Pubkey)]
+pub struct AttestLoss<'info> {
+    #[account(seeds = [b"attestor_registry"], bump = attestor_registry.bump)]
+    pub attestor_registry: Account<'info, AttestorRegistry>,
+    #[account(
+        init_if_needed,
+        payer = attestor,
+        space = 8 + LossAttestation::INIT_SPACE,
+        seeds = [b"loss_attestation", wallet.as_ref()],
+        bump,
+    )]
+    pub loss_attestation: Account<'info, LossAttestation>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub attestor: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CloseClaimVoting<'info> {
+    #[account(
+        mut,
+        constraint = claim.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub claim: Account<'info, Claim>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(
+        mut,
+        seeds = [b"pool", InsurancePool::tier_seed(&pool.tier)],
+        bump = pool.bump,
+        address = claim.pool @ ErrorCode::WrongSubPool,
+    )]
+    pub pool: Account<'info, InsurancePool>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct VoteOnClaim<'info> {
+    #[account(
+        mut,
+        constraint = claim.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub claim: Account<'info, Claim>,
+    #[account(
+        init,
+        payer = voter,
+        space = 8 + ClaimVoteRecord::INIT_SPACE,
+        seeds = [b"claim_vote", claim.key().as_ref(), voter.key().as_ref()],
+        bump,
+    )]
+    pub vote_record: Account<'info, ClaimVoteRecord>,
+    #[account(seeds = [b"governance"], bump = governance.bump)]
+    pub governance: Account<'info, Governance>,
+    #[account(seeds = [b"claim_stake", voter.key().as_ref()], bump = stake.bump)]
+    pub stake: Account<'info, ClaimVoteStake>,
+    #[account(seeds = [b"claim_staking"], bump = staking.bump)]
+    pub staking: Account<'info, ClaimStaking>,
+    /// CHECK: the voter's vote delegation; delegated stake votes through its delegate
+    #[account(
+        seeds = [b"delegation", voter.key().as_ref()],
+        bump,
+        constraint = delegation.data_is_empty() @ ErrorCode::ClaimStakeDelegated,
+    )]
+    pub delegation: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(
+        mut,
+        seeds = [b"pool", InsurancePool::tier_seed(&pool.tier)],
+        bump = pool.bump,
+        address = claim.pool @ ErrorCode::WrongSubPool,
+    )]
+    pub pool: Account<'info, InsurancePool>,
+    #[account(mut)]
+    pub voter: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: instructions sysvar, only needed to identify the caller of a CPI
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct DistributeVoteIncentives<'info> {
+    #[account(constraint = claim.version_supported() @ ErrorCode::UnsupportedAccountVersion)]
+    pub claim: Account<'info, Claim>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(seeds = [b"governance"], bump = governance.bump)]
+    pub governance: Account<'info, Governance>,
+    #[account(mut, seeds = [b"vote_incentive_vault"], bump)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = vault.mint @ ErrorCode::InvalidMint)]
+    pub incentive_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeVoteIncentiveVault<'info> {
+    #[account(seeds = [b"governance"], bump = governance.bump)]
+    pub governance: Account<'info, Governance>,
+    #[account(seeds = [b"fee_distributor"], bump = distributor.bump)]
+    pub distributor: Account<'info, FeeDistributor>,
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"vote_incentive_vault"],
+        bump,
+        token::mint = iris_mint,
+        token::authority = governance,
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = distributor.iris_mint @ ErrorCode::InvalidMint)]
+    pub iris_mint: InterfaceAccount<'info, Mint>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(init, payer = admin, space = 8 + ProtocolConfig::INIT_SPACE, seeds = [b"config"], bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    /// The program's ProgramData account under the upgradeable loader
+    #[account(seeds = [crate::ID.as_ref()], bump, seeds::program = bpf_loader_upgradeable::ID)]
+    pub program_data: Account<'info, ProgramData>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct EstimateProtectionCost<'info> {
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+}
+
+#[derive(Accounts)]
+pub struct SetConfigParam<'info> {
+    #[account(mut, seeds = [b"config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub admin: Signer<'info>,
+    /// CHECK: instructions sysvar, only needed to identify the caller of a CPI
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct InitActionLogTree<'info> {
+    #[account(mut, seeds = [b"config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub admin: Signer<'info>,
+    /// CHECK: zeroed and owned by the compression program, which validates its size
+    #[account(mut)]
+    pub merkle_tree: UncheckedAccount<'info>,
+    /// CHECK: PDA holding write authority over the action log tree
+    #[account(seeds = [compression::LOG_AUTHORITY_SEED], bump)]
+    pub log_authority: UncheckedAccount<'info>,
+    /// CHECK: SPL account compression program
+    #[account(address = compression::ACCOUNT_COMPRESSION_ID)]
+    pub compression_program: UncheckedAccount<'info>,
+    /// CHECK: SPL noop program
+    #[account(address = compression::NOOP_ID)]
+    pub noop_program: UncheckedAccount<'info>,
+    /// CHECK: instructions sysvar, only needed to identify the caller of a CPI
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyActionLog<'info> {
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    /// CHECK: the protocol's action log tree, validated by the compression program
+    #[account(address = config.action_log_tree.merkle_tree @ ErrorCode::InvalidActionLogTree)]
+    pub merkle_tree: UncheckedAccount<'info>,
+    /// CHECK: SPL account compression program
+    #[account(address = compression::ACCOUNT_COMPRESSION_ID)]
+    pub compression_program: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub new_admin: Signer<'info>,
+    /// The program's ProgramData account under the upgradeable loader
+    #[account(seeds = [crate::ID.as_ref()], bump, seeds::program = bpf_loader_upgradeable::ID)]
+    pub program_data: Account<'info, ProgramData>,
+    /// CHECK: instructions sysvar, only needed to identify the caller of a CPI
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeGovernance<'info> {
+    #[account(init, payer = admin, space = 8 + Governance::INIT_SPACE, seeds = [b"governance"], bump)]
+    pub governance: Account<'info, Governance>,
+    // Only the config admin may create governance, so nobody can claim it ahead of the deploy
+    #[account(seeds = [b"config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateGovernanceParams<'info> {
+    #[account(mut, seeds = [b"governance"], bump = governance.bump)]
+    pub governance: Account<'info, Governance>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(tier: u8)]
+pub struct InitializePool<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + InsurancePool::INIT_SPACE,
+        seeds = [b"pool", InsurancePool::tier_seed(&tier)],
+        bump,
+    )]
+    pub pool: Account<'info, InsurancePool>,
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"pool_vault", InsurancePool::tier_seed(&tier)],
+        bump,
+        token::mint = payout_mint,
+        token::authority = pool,
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    pub payout_mint: InterfaceAccount<'info, Mint>,
+    #[account(seeds = [b"governance"], bump = governance.bump)]
+    pub governance: Account<'info, Governance>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetLpBonusConfig<'info> {
+    #[account(mut, seeds = [b"config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub admin: Signer<'info>,
+    /// CHECK: instructions sysvar, only needed to identify the caller of a CPI
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct SetTierConfig<'info> {
+    #[account(mut, seeds = [b"config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub admin: Signer<'info>,
+    /// CHECK: instructions sysvar, only needed to identify the caller of a CPI
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct SetFeeWaiver<'info> {
+    #[account(mut, seeds = [b"config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub admin: Signer<'info>,
+    /// CHECK: instructions sysvar, only needed to identify the caller of a CPI
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct SetRebateBps<'info> {
+    #[account(mut, seeds = [b"config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub admin: Signer<'info>,
+    /// CHECK: instructions sysvar, only needed to identify the caller of a CPI
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct SetPoolEpochLength<'info> {
+    #[account(mut, seeds = [b"config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub admin: Signer<'info>,
+    /// CHECK: instructions sysvar, only needed to identify the caller of a CPI
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct DepositToPool<'info> {
+    #[account(mut, seeds = [b"pool", InsurancePool::tier_seed(&pool.tier)], bump = pool.bump)]
+    pub pool: Account<'info, InsurancePool>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut, address = pool.vault @ ErrorCode::InvalidPoolVault)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = depositor,
+        space = 8 + LpPosition::INIT_SPACE,
+        seeds = [b"lp_position", pool.key().as_ref(), depositor.key().as_ref()],
+        bump,
+    )]
+    pub position: Account<'info, LpPosition>,
+    // Record of the epoch the position last touched, required once that epoch has closed
+    pub last_epoch: Option<Account<'info, PoolEpoch>>,
+    #[account(
+        mut,
+        constraint = depositor_token_account.mint == pool.payout_mint @ ErrorCode::InvalidMint,
+        constraint = depositor_token_account.owner == depositor.key() @ ErrorCode::InvalidTokenOwner,
+    )]
+    pub depositor_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+    #[account(address = pool.payout_mint @ ErrorCode::InvalidMint)]
+    pub payout_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DonateToPool<'info> {
+    #[account(mut, seeds = [b"pool", InsurancePool::tier_seed(&pool.tier)], bump = pool.bump)]
+    pub pool: Account<'info, InsurancePool>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut, address = pool.vault @ ErrorCode::InvalidPoolVault)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = donor_token_account.mint == pool.payout_mint @ ErrorCode::InvalidMint,
+        constraint = donor_token_account.owner == donor.key() @ ErrorCode::InvalidTokenOwner,
+    )]
+    pub donor_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub donor: Signer<'info>,
+    #[account(address = pool.payout_mint @ ErrorCode::InvalidMint)]
+    pub payout_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFromPool<'info> {
+    #[account(mut, seeds = [b"pool", InsurancePool::tier_seed(&pool.tier)], bump = pool.bump)]
+    pub pool: Account<'info, InsurancePool>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut, address = pool.vault @ ErrorCode::InvalidPoolVault)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"lp_position", pool.key().as_ref(), owner.key().as_ref()],
+        bump = position.bump,
+        has_one = owner,
+    )]
+    pub position: Account<'info, LpPosition>,
+    pub last_epoch: Option<Account<'info, PoolEpoch>>,
+    #[account(
+        mut,
+        constraint = owner_token_account.mint == pool.payout_mint @ ErrorCode::InvalidMint,
+        constraint = owner_token_account.owner == owner.key() @ ErrorCode::InvalidTokenOwner,
+    )]
+    pub owner_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub owner: Signer<'info>,
+    #[account(address = pool.payout_mint @ ErrorCode::InvalidMint)]
+    pub payout_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct ReadInsurancePoolStats<'info> {
+    #[account(seeds = [b"pool", InsurancePool::tier_seed(&pool.tier)], bump = pool.bump)]
+    pub pool: Account<'info, InsurancePool>,
+    #[account(address = pool.vault @ ErrorCode::InvalidPoolVault)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+}
+
+#[derive(Accounts)]
+pub struct AdvancePoolEpoch<'info> {
+    #[account(mut, seeds = [b"pool", InsurancePool::tier_seed(&pool.tier)], bump = pool.bump)]
+    pub pool: Account<'info, InsurancePool>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    // Created on the first call of the epoch, which may be an early no-op
+    #[account(
+        init_if_needed,
+        payer = cranker,
+        space = 8 + PoolEpoch::INIT_SPACE,
+        seeds = [b"pool_epoch", pool.key().as_ref(), pool.current_epoch.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub epoch_record: Account<'info, PoolEpoch>,
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimLpRewards<'info> {
+    #[account(mut, seeds = [b"pool", InsurancePool::tier_seed(&pool.tier)], bump = pool.bump)]
+    pub pool: Account<'info, InsurancePool>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut, address = pool.vault @ ErrorCode::InvalidPoolVault)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"lp_position", pool.key().as_ref(), owner.key().as_ref()],
+        bump = position.bump,
+        has_one = owner,
+    )]
+    pub position: Account<'info, LpPosition>,
+    pub last_epoch: Option<Account<'info, PoolEpoch>>,
+    #[account(
+        mut,
+        constraint = owner_token_account.mint == pool.payout_mint @ ErrorCode::InvalidMint,
+        constraint = owner_token_account.owner == owner.key() @ ErrorCode::InvalidTokenOwner,
+    )]
+    pub owner_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub owner: Signer<'info>,
+    #[account(address = pool.payout_mint @ ErrorCode::InvalidMint)]
+    pub payout_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ExecutePayout<'info> {
+    #[account(
+        mut,
+        seeds = [b"pool", InsurancePool::tier_seed(&pool.tier)],
+        bump = pool.bump,
+        address = claim.pool @ ErrorCode::WrongSubPool,
+    )]
+    pub pool: Account<'info, InsurancePool>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut, address = pool.vault @ ErrorCode::InvalidPoolVault)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = claim.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub claim: Account<'info, Claim>,
+    #[account(
+        mut,
+        constraint = claimant_token_account.owner == claim.claimant @ ErrorCode::InvalidClaimantAccount,
+        constraint = claimant_token_account.mint == pool.payout_mint @ ErrorCode::InvalidClaimantAccount,
+    )]
+    pub claimant_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = pool.payout_mint @ ErrorCode::InvalidMint)]
+    pub payout_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    // Only needed when a compliance reference is passed
+    pub memo_program: Option<Program<'info, Memo>>,
+    // Reinsurance tranche drawn on for what the pool can't pay
+    #[account(mut, seeds = [b"reinsurance_pool"], bump = reinsurance_pool.bump)]
+    pub reinsurance_pool: Option<Account<'info, ReinsurancePool>>,
+    #[account(mut)]
+    pub reinsurance_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: the wallet's WalletActivity PDA, checked and created by the handler
+    #[account(mut)]
+    pub activity: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimNextInstallment<'info> {
+    #[account(
+        mut,
+        seeds = [b"pool", InsurancePool::tier_seed(&pool.tier)],
+        bump = pool.bump,
+        address = claim.pool @ ErrorCode::WrongSubPool,
+    )]
+    pub pool: Account<'info, InsurancePool>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut, address = pool.vault @ ErrorCode::InvalidPoolVault)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        has_one = claimant @ ErrorCode::InvalidClaimantAccount,
+        constraint = claim.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub claim: Account<'info, Claim>,
+    pub claimant: Signer<'info>,
+    #[account(
+        mut,
+        constraint = claimant_token_account.owner == claim.claimant @ ErrorCode::InvalidClaimantAccount,
+        constraint = claimant_token_account.mint == pool.payout_mint @ ErrorCode::InvalidClaimantAccount,
+    )]
+    pub claimant_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = pool.payout_mint @ ErrorCode::InvalidMint)]
+    pub payout_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    /// CHECK: the wallet's WalletActivity PDA, checked and created by the handler
+    #[account(mut)]
+    pub activity: Option<UncheckedAccount<'info>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct TopUpShortfalls<'info> {
+    #[account(mut, seeds = [b"pool", InsurancePool::tier_seed(&pool.tier)], bump = pool.bump)]
+    pub pool: Account<'info, InsurancePool>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut, address = pool.vault @ ErrorCode::InvalidPoolVault)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = pool.payout_mint @ ErrorCode::InvalidMint)]
+    pub payout_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct ConfigureProtocolDelegate<'info> {
+    #[account(mut, seeds = [b"governance"], bump = governance.bump)]
+    pub governance: Account<'info, Governance>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct DelegateVote<'info> {
+    #[account(
+        init,
+        payer = delegator,
+        space = 8 + VoteDelegation::INIT_SPACE,
+        seeds = [b"delegation", delegator.key().as_ref()],
+        bump,
+    )]
+    pub delegation: Account<'info, VoteDelegation>,
+    #[account(seeds = [b"claim_stake", delegator.key().as_ref()], bump = stake.bump)]
+    pub stake: Account<'info, ClaimVoteStake>,
+    #[account(mut, seeds = [b"governance"], bump = governance.bump)]
+    pub governance: Account<'info, Governance>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub delegator: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeDelegation<'info> {
+    #[account(
+        mut,
+        close = delegator,
+        seeds = [b"delegation", delegator.key().as_ref()],
+        bump = delegation.bump,
+        has_one = delegator,
+    )]
+    pub delegation: Account<'info, VoteDelegation>,
+    #[account(mut, seeds = [b"governance"], bump = governance.bump)]
+    pub governance: Account<'info, Governance>,
+    #[account(mut)]
+    pub delegator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AssessClaimFraud<'info> {
+    #[account(
+        mut,
+        constraint = claim.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub claim: Account<'info, Claim>,
+    #[account(seeds = [b"governance"], bump = governance.bump)]
+    pub governance: Account<'info, Governance>,
+    pub authority: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ExecuteProtocolVote<'info> {
+    #[account(
+        mut,
+        constraint = claim.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub claim: Account<'info, Claim>,
+    #[account(seeds = [b"governance"], bump = governance.bump)]
+    pub governance: Account<'info, Governance>,
+    #[account(seeds = [b"claim_staking"], bump = staking.bump)]
+    pub staking: Account<'info, ClaimStaking>,
+    #[account(
+        mut,
+        seeds = [b"pool", InsurancePool::tier_seed(&pool.tier)],
+        bump = pool.bump,
+        address = claim.pool @ ErrorCode::WrongSubPool,
+    )]
+    pub pool: Account<'info, InsurancePool>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub keeper: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(code: [u8; 16])]
+pub struct CreatePromoCode<'info> {
+    #[account(init, payer = authority, space = 8 + PromoCode::INIT_SPACE, seeds = [b"promo", code.as_ref()], bump)]
+    pub promo_code: Account<'info, PromoCode>,
+    #[account(seeds = [b"governance"], bump = governance.bump)]
+    pub governance: Account<'info, Governance>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeTreasury<'info> {
+    #[account(init, payer = authority, space = 8 + Treasury::INIT_SPACE, seeds = [b"treasury"], bump)]
+    pub treasury: Account<'info, Treasury>,
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"treasury_vault"],
+        bump,
+        token::mint = mint,
+        token::authority = treasury,
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(seeds = [b"governance"], bump = governance.bump)]
+    pub governance: Account<'info, Governance>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeFeeDistributor<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + FeeDistributor::INIT_SPACE,
+        seeds = [b"fee_distributor"],
+        bump,
+    )]
+    pub distributor: Account<'info, FeeDistributor>,
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"fee_stake_vault"],
+        bump,
+        token::mint = iris_mint,
+        token::authority = distributor,
+    )]
+    pub stake_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"fee_reward_vault"],
+        bump,
+        token::mint = reward_mint,
+        token::authority = distributor,
+    )]
+    pub reward_vault: InterfaceAccount<'info, TokenAccount>,
+    pub iris_mint: InterfaceAccount<'info, Mint>,
+    #[account(address = treasury.mint)]
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+    #[account(seeds = [b"treasury"], bump = treasury.bump)]
+    pub treasury: Account<'info, Treasury>,
+    #[account(seeds = [b"governance"], bump = governance.bump)]
+    pub governance: Account<'info, Governance>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct StakeIris<'info> {
+    #[account(mut, seeds = [b"fee_distributor"], bump = distributor.bump)]
+    pub distributor: Account<'info, FeeDistributor>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + FeeStake::INIT_SPACE,
+        seeds = [b"fee_stake", owner.key().as_ref()],
+        bump,
+    )]
+    pub stake: Account<'info, FeeStake>,
+    #[account(mut, address = distributor.stake_vault @ ErrorCode::InvalidFeeVault)]
+    pub stake_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = owner_iris_account.mint == distributor.iris_mint @ ErrorCode::InvalidMint,
+        constraint = owner_iris_account.owner == owner.key() @ ErrorCode::InvalidTokenOwner,
+    )]
+    pub owner_iris_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        constraint = user_account.wallet == owner.key() @ ErrorCode::NotAccountOwner,
+        constraint = user_account.is_pda(&user_account.key()) @ ErrorCode::MigrationRequired,
+        constraint = user_account.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub user_account: Account<'info, UserAccount>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(address = distributor.iris_mint @ ErrorCode::InvalidMint)]
+    pub iris_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DistributeFees<'info> {
+    #[account(mut, seeds = [b"fee_distributor"], bump = distributor.bump)]
+    pub distributor: Account<'info, FeeDistributor>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut, seeds = [b"treasury"], bump = treasury.bump)]
+    pub treasury: Account<'info, Treasury>,
+    #[account(mut, address = treasury.vault @ ErrorCode::InvalidTreasuryVault)]
+    pub treasury_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, address = distributor.reward_vault @ ErrorCode::InvalidFeeVault)]
+    pub reward_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = treasury.mint @ ErrorCode::InvalidMint)]
+    pub treasury_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimFeeShare<'info> {
+    #[account(seeds = [b"fee_distributor"], bump = distributor.bump)]
+    pub distributor: Account<'info, FeeDistributor>,
+    #[account(mut, seeds = [b"fee_stake", owner.key().as_ref()], bump = stake.bump, has_one = owner)]
+    pub stake: Account<'info, FeeStake>,
+    #[account(mut, address = distributor.reward_vault @ ErrorCode::InvalidFeeVault)]
+    pub reward_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = destination.mint == reward_vault.mint @ ErrorCode::InvalidMint,
+        constraint = destination.owner == owner.key() @ ErrorCode::InvalidTokenOwner,
+    )]
+    pub destination: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub owner: Signer<'info>,
+    #[account(address = reward_vault.mint @ ErrorCode::InvalidMint)]
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct RequestUnstake<'info> {
+    #[account(mut, seeds = [b"fee_distributor"], bump = distributor.bump)]
+    pub distributor: Account<'info, FeeDistributor>,
+    #[account(mut, seeds = [b"fee_stake", owner.key().as_ref()], bump = stake.bump, has_one = owner)]
+    pub stake: Account<'info, FeeStake>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CompleteUnstake<'info> {
+    #[account(seeds = [b"fee_distributor"], bump = distributor.bump)]
+    pub distributor: Account<'info, FeeDistributor>,
+    #[account(mut, seeds = [b"fee_stake", owner.key().as_ref()], bump = stake.bump, has_one = owner)]
+    pub stake: Account<'info, FeeStake>,
+    #[account(mut, address = distributor.stake_vault @ ErrorCode::InvalidFeeVault)]
+    pub stake_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = owner_iris_account.mint == distributor.iris_mint @ ErrorCode::InvalidMint,
+        constraint = owner_iris_account.owner == owner.key() @ ErrorCode::InvalidTokenOwner,
+    )]
+    pub owner_iris_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub owner: Signer<'info>,
+    #[account(address = distributor.iris_mint @ ErrorCode::InvalidMint)]
+    pub iris_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeClaimStaking<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + ClaimStaking::INIT_SPACE,
+        seeds = [b"claim_staking"],
+        bump,
+    )]
+    pub staking: Account<'info, ClaimStaking>,
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"claim_stake_vault"],
+        bump,
+        token::mint = iris_mint,
+        token::authority = staking,
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    pub iris_mint: InterfaceAccount<'info, Mint>,
+    #[account(seeds = [b"governance"], bump = governance.bump)]
+    pub governance: Account<'info, Governance>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateClaimStaking<'info> {
+    #[account(mut, seeds = [b"claim_staking"], bump = staking.bump)]
+    pub staking: Account<'info, ClaimStaking>,
+    #[account(seeds = [b"governance"], bump = governance.bump)]
+    pub governance: Account<'info, Governance>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct StakeForClaimVotes<'info> {
+    #[account(mut, seeds = [b"claim_staking"], bump = staking.bump)]
+    pub staking: Account<'info, ClaimStaking>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + ClaimVoteStake::INIT_SPACE,
+        seeds = [b"claim_stake", owner.key().as_ref()],
+        bump,
+    )]
+    pub stake: Account<'info, ClaimVoteStake>,
+    #[account(mut, address = staking.vault @ ErrorCode::InvalidClaimStakeVault)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = owner_iris_account.mint == staking.iris_mint @ ErrorCode::InvalidMint,
+        constraint = owner_iris_account.owner == owner.key() @ ErrorCode::InvalidTokenOwner,
+    )]
+    pub owner_iris_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        constraint = user_account.wallet == owner.key() @ ErrorCode::NotAccountOwner,
+        constraint = user_account.is_pda(&user_account.key()) @ ErrorCode::MigrationRequired,
+        constraint = user_account.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
     pub user_account: Account<'info, UserAccount>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(address = staking.iris_mint @ ErrorCode::InvalidMint)]
+    pub iris_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RequestClaimUnstake<'info> {
+    #[account(mut, seeds = [b"claim_staking"], bump = staking.bump)]
+    pub staking: Account<'info, ClaimStaking>,
+    #[account(mut, seeds = [b"claim_stake", owner.key().as_ref()], bump = stake.bump, has_one = owner)]
+    pub stake: Account<'info, ClaimVoteStake>,
+    /// CHECK: the owner's vote delegation, which must not exist
+    #[account(
+        seeds = [b"delegation", owner.key().as_ref()],
+        bump,
+        constraint = delegation.data_is_empty() @ ErrorCode::ClaimStakeDelegated,
+    )]
+    pub delegation: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CompleteClaimUnstake<'info> {
+    #[account(seeds = [b"claim_staking"], bump = staking.bump)]
+    pub staking: Account<'info, ClaimStaking>,
+    #[account(mut, seeds = [b"claim_stake", owner.key().as_ref()], bump = stake.bump, has_one = owner)]
+    pub stake: Account<'info, ClaimVoteStake>,
+    #[account(mut, address = staking.vault @ ErrorCode::InvalidClaimStakeVault)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = owner_iris_account.mint == staking.iris_mint @ ErrorCode::InvalidMint,
+        constraint = owner_iris_account.owner == owner.key() @ ErrorCode::InvalidTokenOwner,
+    )]
+    pub owner_iris_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub owner: Signer<'info>,
+    #[account(address = staking.iris_mint @ ErrorCode::InvalidMint)]
+    pub iris_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct SetDexAllowlist<'info> {
+    #[account(mut, seeds = [b"treasury"], bump = treasury.bump)]
+    pub treasury: Account<'info, Treasury>,
+    #[account(seeds = [b"governance"], bump = governance.bump)]
+    pub governance: Account<'info, Governance>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetEmergencyCouncil<'info> {
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + EmergencyCouncil::INIT_SPACE,
+        seeds = [b"emergency_council"],
+        bump,
+    )]
+    pub emergency_council: Account<'info, EmergencyCouncil>,
+    #[account(seeds = [b"governance"], bump = governance.bump)]
+    pub governance: Account<'info, Governance>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(old_signer: Pubkey, new_signer: Pubkey)]
+pub struct RotateMultisigSigner<'info> {
+    #[account(
+        init,
+        payer = signer,
+        space = 8 + SignerRotationProposal::INIT_SPACE,
+        seeds = [b"signer_rotation", old_signer.as_ref(), new_signer.as_ref()],
+        bump,
+    )]
+    pub rotation: Account<'info, SignerRotationProposal>,
+    #[account(mut, seeds = [b"emergency_council"], bump = emergency_council.bump)]
+    pub emergency_council: Account<'info, EmergencyCouncil>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ConfirmSignerRotation<'info> {
+    #[account(
+        mut,
+        seeds = [b"signer_rotation", rotation.old_signer.as_ref(), rotation.new_signer.as_ref()],
+        bump = rotation.bump,
+    )]
+    pub rotation: Account<'info, SignerRotationProposal>,
+    #[account(mut, seeds = [b"emergency_council"], bump = emergency_council.bump)]
+    pub emergency_council: Account<'info, EmergencyCouncil>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub signer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CreateProposal<'info> {
+    #[account(
+        init,
+        payer = proposer,
+        space = 8 + Proposal::INIT_SPACE,
+        seeds = [b"proposal", governance.proposal_count.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub proposal: Account<'info, Proposal>,
+    #[account(mut, seeds = [b"governance"], bump = governance.bump)]
+    pub governance: Account<'info, Governance>,
+    #[account(seeds = [b"treasury"], bump = treasury.bump)]
+    pub treasury: Account<'info, Treasury>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    // Only needed for emergency proposals
+    #[account(seeds = [b"emergency_council"], bump = emergency_council.bump)]
+    pub emergency_council: Option<Account<'info, EmergencyCouncil>>,
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct TakeVoteSnapshot<'info> {
+    #[account(mut, seeds = [b"proposal", proposal.id.to_le_bytes().as_ref()], bump = proposal.bump)]
+    pub proposal: Account<'info, Proposal>,
+    #[account(seeds = [b"governance"], bump = governance.bump)]
+    pub governance: Account<'info, Governance>,
+    #[account(seeds = [b"fee_distributor"], bump = distributor.bump)]
+    pub distributor: Account<'info, FeeDistributor>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct VoteOnProposal<'info> {
+    #[account(mut, seeds = [b"proposal", proposal.id.to_le_bytes().as_ref()], bump = proposal.bump)]
+    pub proposal: Account<'info, Proposal>,
+    #[account(
+        init,
+        payer = voter,
+        space = 8 + ProposalVoteRecord::INIT_SPACE,
+        seeds = [b"proposal_vote", proposal.key().as_ref(), voter.key().as_ref()],
+        bump,
+    )]
+    pub vote_record: Account<'info, ProposalVoteRecord>,
+    #[account(mut, seeds = [b"governance"], bump = governance.bump)]
+    pub governance: Account<'info, Governance>,
+    // The voter's stake snapshot and stake, required on snapshot proposals
+    pub snapshot: Option<Account<'info, Snapshot>>,
+    #[account(seeds = [b"fee_stake", voter.key().as_ref()], bump = stake.bump)]
+    pub stake: Option<Account<'info, FeeStake>>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub voter: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelExecution<'info> {
+    #[account(
+        mut,
+        seeds = [b"proposal", proposal.id.to_le_bytes().as_ref()],
+        bump = proposal.bump,
+        has_one = cancellation_authority @ ErrorCode::NotCancellationAuthority,
+    )]
+    pub proposal: Account<'info, Proposal>,
+    #[account(seeds = [b"governance"], bump = governance.bump)]
+    pub governance: Account<'info, Governance>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub cancellation_authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct BuybackAndBurn<'info> {
+    #[account(mut, seeds = [b"proposal", proposal.id.to_le_bytes().as_ref()], bump = proposal.bump)]
+    pub proposal: Account<'info, Proposal>,
+    #[account(seeds = [b"governance"], bump = governance.bump)]
+    pub governance: Account<'info, Governance>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut, seeds = [b"treasury"], bump = treasury.bump)]
+    pub treasury: Account<'info, Treasury>,
+    #[account(mut, address = treasury.vault @ ErrorCode::InvalidTreasuryVault)]
+    pub treasury_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = treasury::is_treasury(&treasury, &treasury_iris_account, &iris_mint.key()) @ ErrorCode::InvalidTreasury,
+    )]
+    pub treasury_iris_account: InterfaceAccount<'info, TokenAccount>,
     #[account(mut)]
-    pub user: Signer<'info>,
+    pub iris_mint: InterfaceAccount<'info, Mint>,
+    /// CHECK: checked against the proposal's pinned, allowlisted DEX program
+    #[account(executable)]
+    pub dex_program: UncheckedAccount<'info>,
+    pub executor: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteReserveFloorProposal<'info> {
+    #[account(mut, seeds = [b"proposal", proposal.id.to_le_bytes().as_ref()], bump = proposal.bump)]
+    pub proposal: Account<'info, Proposal>,
+    #[account(seeds = [b"governance"], bump = governance.bump)]
+    pub governance: Account<'info, Governance>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub executor: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteGovernanceParamsProposal<'info> {
+    #[account(mut, seeds = [b"proposal", proposal.id.to_le_bytes().as_ref()], bump = proposal.bump)]
+    pub proposal: Account<'info, Proposal>,
+    #[account(mut, seeds = [b"governance"], bump = governance.bump)]
+    pub governance: Account<'info, Governance>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub executor: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteSetAdminProposal<'info> {
+    #[account(mut, seeds = [b"proposal", proposal.id.to_le_bytes().as_ref()], bump = proposal.bump)]
+    pub proposal: Account<'info, Proposal>,
+    #[account(seeds = [b"governance"], bump = governance.bump)]
+    pub governance: Account<'info, Governance>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    /// The program's ProgramData account under the upgradeable loader
+    #[account(seeds = [crate::ID.as_ref()], bump, seeds::program = bpf_loader_upgradeable::ID)]
+    pub program_data: Account<'info, ProgramData>,
+    pub executor: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct EnableGovernedUpgrades<'info> {
+    #[account(mut, seeds = [b"config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub admin: Signer<'info>,
+    /// The program's ProgramData account under the upgradeable loader
+    #[account(seeds = [crate::ID.as_ref()], bump, seeds::program = bpf_loader_upgradeable::ID)]
+    pub program_data: Account<'info, ProgramData>,
+    /// CHECK: instructions sysvar, only needed to identify the caller of a CPI
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteProgramUpgrade<'info> {
+    #[account(mut, seeds = [b"proposal", proposal.id.to_le_bytes().as_ref()], bump = proposal.bump)]
+    pub proposal: Account<'info, Proposal>,
+    #[account(seeds = [b"governance"], bump = governance.bump)]
+    pub governance: Account<'info, Governance>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    /// CHECK: the program's ProgramData account, rewritten by the loader
+    #[account(mut, seeds = [crate::ID.as_ref()], bump, seeds::program = bpf_loader_upgradeable::ID)]
+    pub program_data: UncheckedAccount<'info>,
+    /// CHECK: this program's account
+    #[account(mut, address = crate::ID)]
+    pub program: UncheckedAccount<'info>,
+    /// CHECK: buffer with the new code, checked against the proposal by the handler
+    #[account(mut)]
+    pub buffer: UncheckedAccount<'info>,
+    /// CHECK: receives the buffer's lamports once it is consumed
+    #[account(mut)]
+    pub spill: UncheckedAccount<'info>,
+    /// CHECK: PDA holding the upgrade authority, signs the upgrade
+    #[account(seeds = [ProtocolConfig::UPGRADE_AUTHORITY_SEED], bump)]
+    pub upgrade_authority: UncheckedAccount<'info>,
+    /// CHECK: BPF upgradeable loader
+    #[account(address = bpf_loader_upgradeable::ID)]
+    pub loader: UncheckedAccount<'info>,
+    pub rent: Sysvar<'info, Rent>,
+    pub clock: Sysvar<'info, Clock>,
+    pub executor: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseExpiredProposal<'info> {
+    #[account(mut, seeds = [b"proposal", proposal.id.to_le_bytes().as_ref()], bump = proposal.bump)]
+    pub proposal: Account<'info, Proposal>,
+    #[account(mut, seeds = [b"governance"], bump = governance.bump)]
+    pub governance: Account<'info, Governance>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+}
+
+#[derive(Accounts)]
+pub struct WriteOffShortfall<'info> {
+    #[account(mut, seeds = [b"proposal", proposal.id.to_le_bytes().as_ref()], bump = proposal.bump)]
+    pub proposal: Account<'info, Proposal>,
+    #[account(seeds = [b"governance"], bump = governance.bump)]
+    pub governance: Account<'info, Governance>,
+    #[account(
+        mut,
+        seeds = [b"pool", InsurancePool::tier_seed(&pool.tier)],
+        bump = pool.bump,
+        address = claim.pool @ ErrorCode::WrongSubPool,
+    )]
+    pub pool: Account<'info, InsurancePool>,
+    #[account(address = pool.vault @ ErrorCode::InvalidPoolVault)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = claim.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub claim: Account<'info, Claim>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub executor: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetLendingAllowlist<'info> {
+    #[account(mut, seeds = [b"config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub admin: Signer<'info>,
+    /// CHECK: instructions sysvar, only needed to identify the caller of a CPI
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct SetupRenewalStream<'info> {
+    #[account(
+        mut,
+        constraint = insurance_nft.owner == owner.key() @ ErrorCode::NotNftOwner,
+        constraint = insurance_nft.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub insurance_nft: Account<'info, InsuranceNFT>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(address = insurance_nft.pool @ ErrorCode::WrongSubPool)]
+    pub pool: Account<'info, InsurancePool>,
+    #[account(address = pool.vault @ ErrorCode::InvalidPoolVault)]
+    pub pool_vault: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: stream account created by the stream program
+    pub stream: UncheckedAccount<'info>,
+    /// CHECK: checked against the config stream allowlist
+    #[account(executable)]
+    pub stream_program: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ProcessStreamRenewal<'info> {
+    #[account(
+        mut,
+        constraint = insurance_nft.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub insurance_nft: Account<'info, InsuranceNFT>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut, seeds = [b"treasury"], bump = treasury.bump)]
+    pub treasury: Account<'info, Treasury>,
+    #[account(
+        mut,
+        address = treasury.vault @ ErrorCode::InvalidTreasuryVault,
+        constraint = treasury::is_treasury(&treasury, &treasury_account, &treasury.mint) @ ErrorCode::InvalidTreasury,
+    )]
+    pub treasury_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: the policy's stream, pinned by its stream config
+    #[account(address = insurance_nft.stream_config.stream_id @ ErrorCode::InvalidStream)]
+    pub stream: UncheckedAccount<'info>,
+    /// CHECK: the program the stream was created on
+    #[account(executable, address = insurance_nft.stream_config.stream_program @ ErrorCode::InvalidStream)]
+    pub stream_program: UncheckedAccount<'info>,
+    pub keeper: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CollateralizeNft<'info> {
+    #[account(
+        mut,
+        constraint = insurance_nft.owner == owner.key() @ ErrorCode::NotNftOwner,
+        constraint = insurance_nft.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub insurance_nft: Account<'info, InsuranceNFT>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(
+        mut,
+        constraint = owner_nft_account.mint == insurance_nft.token_mint @ ErrorCode::InvalidMint,
+        constraint = owner_nft_account.owner == owner.key() @ ErrorCode::NotNftOwner,
+    )]
+    pub owner_nft_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        seeds = [b"collateral_escrow", insurance_nft.key().as_ref()],
+        bump,
+        token::mint = nft_mint,
+        token::authority = collateral_authority,
+    )]
+    pub collateral_escrow: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: PDA that signs for the escrow and the loan
+    #[account(seeds = [b"collateral", insurance_nft.key().as_ref()], bump)]
+    pub collateral_authority: UncheckedAccount<'info>,
+    #[account(address = insurance_nft.token_mint)]
+    pub nft_mint: InterfaceAccount<'info, Mint>,
+    /// CHECK: loan account created or tracked by the lending program
+    pub loan: UncheckedAccount<'info>,
+    /// CHECK: checked against the config lending allowlist
+    #[account(executable)]
+    pub lending_program: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseNftCollateral<'info> {
+    #[account(
+        mut,
+        constraint = insurance_nft.owner == owner.key() @ ErrorCode::NotNftOwner,
+        constraint = insurance_nft.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub insurance_nft: Account<'info, InsuranceNFT>,
+    #[account(
+        mut,
+        constraint = owner_nft_account.mint == insurance_nft.token_mint @ ErrorCode::InvalidMint,
+        constraint = owner_nft_account.owner == owner.key() @ ErrorCode::NotNftOwner,
+    )]
+    pub owner_nft_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, seeds = [b"collateral_escrow", insurance_nft.key().as_ref()], bump)]
+    pub collateral_escrow: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: PDA that signs for the escrow and the loan
+    #[account(seeds = [b"collateral", insurance_nft.key().as_ref()], bump)]
+    pub collateral_authority: UncheckedAccount<'info>,
+    /// CHECK: must match the lender recorded at collateralization
+    #[account(executable)]
+    pub lending_program: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub owner: Signer<'info>,
+    #[account(address = insurance_nft.token_mint @ ErrorCode::InvalidMint)]
+    pub nft_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct ListNftForSale<'info> {
+    #[account(
+        constraint = insurance_nft.owner == seller.key() @ ErrorCode::NotNftOwner,
+        constraint = insurance_nft.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub insurance_nft: Account<'info, InsuranceNFT>,
+    #[account(
+        init,
+        payer = seller,
+        space = 8 + NftListing::INIT_SPACE,
+        seeds = [b"nft_listing", insurance_nft.key().as_ref()],
+        bump,
+    )]
+    pub listing: Account<'info, NftListing>,
+    #[account(
+        init,
+        payer = seller,
+        seeds = [b"listing_vault", insurance_nft.key().as_ref()],
+        bump,
+        token::mint = nft_mint,
+        token::authority = listing,
+    )]
+    pub listing_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = seller_nft_account.mint == insurance_nft.token_mint @ ErrorCode::InvalidMint,
+        constraint = seller_nft_account.owner == seller.key() @ ErrorCode::NotNftOwner,
+    )]
+    pub seller_nft_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = insurance_nft.token_mint @ ErrorCode::InvalidMint)]
+    pub nft_mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub seller: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct BuyListedNft<'info> {
+    #[account(
+        mut,
+        seeds = [b"nft_listing", insurance_nft.key().as_ref()],
+        bump = listing.bump,
+        has_one = seller @ ErrorCode::InvalidListingSeller,
+        close = seller,
+    )]
+    pub listing: Account<'info, NftListing>,
+    #[account(
+        mut,
+        constraint = insurance_nft.owner == listing.seller @ ErrorCode::NotNftOwner,
+        constraint = insurance_nft.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub insurance_nft: Account<'info, InsuranceNFT>,
+    #[account(mut, seeds = [b"listing_vault", insurance_nft.key().as_ref()], bump)]
+    pub listing_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = listing.nft_mint @ ErrorCode::InvalidMint)]
+    pub nft_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        constraint = buyer_nft_account.mint == listing.nft_mint @ ErrorCode::InvalidMint,
+        constraint = buyer_nft_account.owner == buyer.key() @ ErrorCode::InvalidTokenOwner,
+    )]
+    pub buyer_nft_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = buyer_payment_account.mint == treasury.mint @ ErrorCode::InvalidMint,
+        constraint = buyer_payment_account.owner == buyer.key() @ ErrorCode::InvalidTokenOwner,
+    )]
+    pub buyer_payment_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = seller_payment_account.mint == treasury.mint @ ErrorCode::InvalidMint,
+        constraint = seller_payment_account.owner == listing.seller @ ErrorCode::InvalidTokenOwner,
+    )]
+    pub seller_payment_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"user", listing.seller.as_ref()],
+        bump = seller_user_account.bump,
+        constraint = seller_user_account.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub seller_user_account: Account<'info, UserAccount>,
+    #[account(
+        mut,
+        seeds = [b"user", buyer.key().as_ref()],
+        bump = buyer_user_account.bump,
+        constraint = buyer_user_account.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub buyer_user_account: Account<'info, UserAccount>,
+    #[account(mut, seeds = [b"treasury"], bump = treasury.bump)]
+    pub treasury: Account<'info, Treasury>,
+    #[account(
+        mut,
+        address = treasury.vault @ ErrorCode::InvalidTreasuryVault,
+        constraint = treasury::is_treasury(&treasury, &treasury_account, &treasury.mint) @ ErrorCode::InvalidTreasury,
+    )]
+    pub treasury_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = treasury.mint @ ErrorCode::InvalidMint)]
+    pub payment_mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    // Receives the listing and vault rent
+    #[account(mut)]
+    pub seller: SystemAccount<'info>,
+    pub buyer: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct CancelNftListing<'info> {
+    #[account(
+        mut,
+        seeds = [b"nft_listing", insurance_nft.key().as_ref()],
+        bump = listing.bump,
+        has_one = seller @ ErrorCode::InvalidListingSeller,
+        close = seller,
+    )]
+    pub listing: Account<'info, NftListing>,
+    pub insurance_nft: Account<'info, InsuranceNFT>,
+    #[account(mut, seeds = [b"listing_vault", insurance_nft.key().as_ref()], bump)]
+    pub listing_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = listing.nft_mint @ ErrorCode::InvalidMint)]
+    pub nft_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        constraint = seller_nft_account.mint == listing.nft_mint @ ErrorCode::InvalidMint,
+        constraint = seller_nft_account.owner == seller.key() @ ErrorCode::InvalidTokenOwner,
+    )]
+    pub seller_nft_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(mut)]
+    pub seller: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct OpenYieldEscrow<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + YieldEscrow::INIT_SPACE,
+        seeds = [b"yield_escrow", claim.key().as_ref()],
+        bump,
+    )]
+    pub yield_escrow: Account<'info, YieldEscrow>,
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"yield_escrow_vault", claim.key().as_ref()],
+        bump,
+        token::mint = payout_mint,
+        token::authority = yield_escrow,
+    )]
+    pub escrow_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(constraint = claim.version_supported() @ ErrorCode::UnsupportedAccountVersion)]
+    pub claim: Account<'info, Claim>,
+    #[account(
+        address = claim.insurance_nft,
+        constraint = insurance_nft.version_supported() @ ErrorCode::UnsupportedAccountVersion,
+    )]
+    pub insurance_nft: Account<'info, InsuranceNFT>,
+    #[account(
+        mut,
+        seeds = [b"pool", InsurancePool::tier_seed(&pool.tier)],
+        bump = pool.bump,
+        address = claim.pool @ ErrorCode::WrongSubPool,
+    )]
+    pub pool: Account<'info, InsurancePool>,
+    #[account(mut, address = pool.vault @ ErrorCode::InvalidPoolVault)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = pool.payout_mint @ ErrorCode::InvalidMint)]
+    pub payout_mint: InterfaceAccount<'info, Mint>,
+    /// CHECK: checked against the config yield allowlist
+    #[account(executable)]
+    pub yield_program: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(seeds = [b"governance"], bump = governance.bump)]
+    pub governance: Account<'info, Governance>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SettleYieldEscrow<'info> {
+    #[account(
+        mut,
+        seeds = [b"yield_escrow", claim.key().as_ref()],
+        bump = yield_escrow.bump,
+        has_one = claim,
+        has_one = pool,
+    )]
+    pub yield_escrow: Account<'info, YieldEscrow>,
+    #[account(mut, address = yield_escrow.vault @ ErrorCode::InvalidYieldEscrowVault)]
+    pub escrow_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(constraint = claim.version_supported() @ ErrorCode::UnsupportedAccountVersion)]
+    pub claim: Account<'info, Claim>,
+    #[account(mut, seeds = [b"pool", InsurancePool::tier_seed(&pool.tier)], bump = pool.bump)]
+    pub pool: Account<'info, InsurancePool>,
+    #[account(mut, address = pool.vault @ ErrorCode::InvalidPoolVault)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = pool.payout_mint @ ErrorCode::InvalidMint)]
+    pub payout_mint: InterfaceAccount<'info, Mint>,
+    /// CHECK: must match the program the bond was deposited with
+    #[account(executable)]
+    pub yield_program: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(seeds = [b"governance"], bump = governance.bump)]
+    pub governance: Account<'info, Governance>,
+    pub authority: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct ExitHaircutMode<'info> {
+    #[account(mut, seeds = [b"pool", InsurancePool::tier_seed(&pool.tier)], bump = pool.bump)]
+    pub pool: Account<'info, InsurancePool>,
+    #[account(seeds = [b"governance"], bump = governance.bump)]
+    pub governance: Account<'info, Governance>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetSubPoolMode<'info> {
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(seeds = [b"governance"], bump = governance.bump)]
+    pub governance: Account<'info, Governance>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AssertAndGate<'info> {
+    #[account(mut, seeds = [b"pool", InsurancePool::tier_seed(&pool.tier)], bump = pool.bump)]
+    pub pool: Account<'info, InsurancePool>,
+    #[account(address = pool.vault @ ErrorCode::InvalidPoolVault)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+}
+
+#[derive(Accounts)]
+pub struct OverrideMintingHalt<'info> {
+    #[account(mut, seeds = [b"pool", InsurancePool::tier_seed(&pool.tier)], bump = pool.bump)]
+    pub pool: Account<'info, InsurancePool>,
+    #[account(address = pool.vault @ ErrorCode::InvalidPoolVault)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(seeds = [b"governance"], bump = governance.bump)]
+    pub governance: Account<'info, Governance>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct BackstopSubPool<'info> {
+    #[account(seeds = [b"pool", InsurancePool::tier_seed(&donor_pool.tier)], bump = donor_pool.bump)]
+    pub donor_pool: Account<'info, InsurancePool>,
+    #[account(mut, address = donor_pool.vault @ ErrorCode::InvalidPoolVault)]
+    pub donor_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(seeds = [b"pool", InsurancePool::tier_seed(&recipient_pool.tier)], bump = recipient_pool.bump)]
+    pub recipient_pool: Account<'info, InsurancePool>,
+    #[account(mut, address = recipient_pool.vault @ ErrorCode::InvalidPoolVault)]
+    pub recipient_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(seeds = [b"governance"], bump = governance.bump)]
+    pub governance: Account<'info, Governance>,
+    pub authority: Signer<'info>,
+    #[account(address = donor_pool.payout_mint @ ErrorCode::InvalidMint)]
+    pub payout_mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeReinsurancePool<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + ReinsurancePool::INIT_SPACE,
+        seeds = [b"reinsurance_pool"],
+        bump,
+    )]
+    pub reinsurance_pool: Account<'info, ReinsurancePool>,
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"reinsurance_vault"],
+        bump,
+        token::mint = payout_mint,
+        token::authority = reinsurance_pool,
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    pub payout_mint: InterfaceAccount<'info, Mint>,
+    #[account(seeds = [b"governance"], bump = governance.bump)]
+    pub governance: Account<'info, Governance>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
 }
 
-#[derive(Accounts)]
-pub struct Subscribe<'info> {
-    #[account(mut)]
-    pub user_account: Account<'info, UserAccount>,
-    #[account(mut)]
-    pub payment_account: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub treasury_account: Account<'info, TokenAccount>,
-    pub payment_mint: Account<'info, Mint>,
-    #[account(mut)]
-    pub user: Signer<'info>,
-    pub token_program: Program<'info, Token>,
+#[derive(Accounts)]
+pub struct FundReinsurance<'info> {
+    #[account(mut, seeds = [b"reinsurance_pool"], bump = reinsurance_pool.bump)]
+    pub reinsurance_pool: Account<'info, ReinsurancePool>,
+    #[account(mut, address = reinsurance_pool.vault @ ErrorCode::InvalidReinsuranceVault)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = authority_token_account.owner == authority.key() @ ErrorCode::InvalidTokenOwner,
+        constraint = authority_token_account.mint == reinsurance_pool.payout_mint @ ErrorCode::InvalidMint,
+    )]
+    pub authority_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = reinsurance_pool.payout_mint @ ErrorCode::InvalidMint)]
+    pub payout_mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+    #[account(seeds = [b"governance"], bump = governance.bump)]
+    pub governance: Account<'info, Governance>,
+    pub authority: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+// Capacities of the variable-length account fields
+pub const MAX_SCORE_HISTORY: usize = 10;
+pub const MAX_ARCHIVED_SCORES: usize = 64;
+pub const MAX_WATCHLIST_TOKENS: usize = 10;
+pub const MAX_COVERED_TOKENS: usize = 20;
+pub const MAX_LINKED_NFTS: usize = 8;
+pub const MAX_LINKED_CLAIMS: usize = 8;
+pub const MAX_LENDING_PROGRAMS: usize = 4;
+pub const MAX_WAITLIST_ENTRIES: usize = 16;
+pub const MAX_FALLBACK_ORACLES: usize = 3;
+pub const MAX_INVALIDATOR_ORACLES: usize = 4;
+pub const MAX_CONSENSUS_ORACLES: usize = MAX_FALLBACK_ORACLES + 1;
+pub const MAX_DEX_PROGRAMS: usize = 8;
+pub const MAX_CLAIM_PROOF_LEN: usize = 1024;
+pub const MAX_CPI_ALLOWLIST: usize = 8;
+pub const MAX_CUSTODY_ENTRIES: usize = 16;
+pub const MAX_STREAM_PROGRAMS: usize = 4;
+pub const MAX_TRANSFER_HOOK_PROGRAMS: usize = 8;
+pub const MAX_ATTESTORS: usize = 16;
+pub const MAX_EMERGENCY_COUNCIL: usize = 7;
+pub const MAX_MULTISIG_PROGRAMS: usize = 4;
+// Room for rate-limited actions, more than RateLimitedAction uses so new ones don't
+// change the account layouts
+pub const RATE_LIMIT_SLOTS: usize = 4;
+pub const MAX_YIELD_PROGRAMS: usize = 4;
+pub const MAX_ACTIVITY_ENTRIES: usize = 16;
+pub const MAX_SWAP_PROGRAMS: usize = 8;
+
+// Structs
+#[account]
+#[derive(InitSpace)]
+pub struct UserAccount {
+    pub version: u8,
+    pub wallet: Pubkey,
+    pub preferences: RiskParams,
+    pub active_sub: bool,
+    pub subscription_expiry: i64,
+    #[max_len(MAX_SCORE_HISTORY)]
+    pub score_history: Vec<Score>,
+    pub pending_score: Option<u8>,
+    pub subscription_started_at: i64,
+    pub plan_price: u64,
+    pub rebate_claimed: bool,
+    pub smoothed_score: u8,
+    #[max_len(MAX_LINKED_NFTS)]
+    pub linked_nfts: Vec<Pubkey>,
+    #[max_len(MAX_LINKED_CLAIMS)]
+    pub linked_claims: Vec<Pubkey>,
+    pub bump: u8,
+    pub rent_payer: Pubkey,
+    pub last_action_log: Option<ActionLogEntry>,
+    // Compliance reference passed with the latest subscription payment
+    pub subscription_reference: Option<[u8; 16]>,
+    // Running hash over every stored score, sha256(previous || score leaf), and how
+    // many scores it covers. Raw scores live in events and the ScoreHistory PDA.
+    pub score_history_hash: [u8; 32],
+    pub score_hash_count: u32,
+    // Uses of each rate-limited action, indexed by RateLimitedAction
+    pub rate_windows: [RateWindow; RATE_LIMIT_SLOTS],
+    // Largest share of the token balance one automatic swap may sell; 0 on accounts
+    // created before the cap, which get the default. Kept here rather than in
+    // RiskParams, which sits mid-layout and can't grow in place.
+    pub max_auto_swap_amount_bps: u16,
+    // Latest oracle score, held through its dispute window before it is applied
+    pub held_score: Option<HeldScore>,
+    // Watchlist alerts are skipped until paused_until, then resume on the next alert
+    pub watchlist_alerts_paused: bool,
+    pub paused_until: i64,
+    // Penalty from an invalidated claim; no new claims until it is paid
+    pub penalty_owed: u64,
+    pub last_score_dispute_at: i64,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct AttestorRegistry {
+    #[max_len(MAX_ATTESTORS)]
+    pub attestors: Vec<Pubkey>,
+    pub bump: u8,
+}
+
+// A registered attestor's confirmation of a wallet's loss, which fast-tracks the
+// wallet's next claim while fresh
+#[account]
+#[derive(InitSpace)]
+pub struct LossAttestation {
+    pub wallet: Pubkey,
+    pub attestor: Pubkey,
+    pub loss_type: LossType,
+    pub evidence_hash: [u8; 32],
+    pub claim_amount: u64,
+    pub attested_at: i64,
+    pub expires_at: i64,
+    pub consumed: bool,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct EmergencyCouncil {
+    #[max_len(MAX_EMERGENCY_COUNCIL)]
+    pub members: Vec<Pubkey>,
+    pub bump: u8,
+}
+
+// Pending replacement of one council key, confirmed by the other members
+#[account]
+#[derive(InitSpace)]
+pub struct SignerRotationProposal {
+    pub old_signer: Pubkey,
+    pub new_signer: Pubkey,
+    pub initiated_by: Pubkey,
+    #[max_len(MAX_EMERGENCY_COUNCIL)]
+    pub confirmations: Vec<Pubkey>,
+    pub created_at: i64,
+    pub executed: bool,
+    pub bump: u8,
+}
+
+// Raw scores behind a wallet's score hash, for indexers and inclusion proofs. Only
+// the most recent are kept; each carries its position in the hash chain.
+#[account]
+#[derive(InitSpace)]
+pub struct ScoreHistory {
+    pub wallet: Pubkey,
+    #[max_len(MAX_ARCHIVED_SCORES)]
+    pub scores: Vec<ArchivedScore>,
+    pub bump: u8,
+    // Aggregation reports written for the wallet, which numbers the next one
+    pub reports_count: u32,
+}
+
+// One multi-oracle consensus update: who reported, what each said and what was applied
+#[account]
+#[derive(InitSpace)]
+pub struct AggregationReport {
+    pub wallet: Pubkey,
+    pub report_index: u32,
+    #[max_len(MAX_CONSENSUS_ORACLES)]
+    pub participating_oracles: Vec<Pubkey>,
+    // In the same order as participating_oracles
+    #[max_len(MAX_CONSENSUS_ORACLES)]
+    pub submitted_scores: Vec<u8>,
+    // Oracles registered at the time, so partial participation shows
+    pub registered_oracles: u8,
+    pub consensus_score: u8,
+    pub timestamp: i64,
+    pub bump: u8,
+}
+
+// Looks a policy up by its sequential number
+#[account]
+#[derive(InitSpace)]
+pub struct PolicyNumberIndex {
+    pub number: u64,
+    pub policy: Pubkey,
+    pub bump: u8,
+}
+
+// Looks a claim up by its sequential number
+#[account]
+#[derive(InitSpace)]
+pub struct ClaimNumberIndex {
+    pub number: u64,
+    pub claim: Pubkey,
+    pub bump: u8,
+}
+
+// A wallet's objection to its held score, open until governance rules on it
+#[account]
+#[derive(InitSpace)]
+pub struct OracleDispute {
+    pub wallet: Pubkey,
+    pub oracle: Pubkey,
+    pub score: u8,
+    pub submitted_at: i64,
+    pub reason: [u8; 64],
+    pub opened_at: i64,
+    pub bump: u8,
+}
+
+// Proof of coverage a wallet hands a third party such as a lender, at
+// [b"coverage_certificate", policy, recipient]. It is valid while the policy it was
+// issued from is active and still held by the insured wallet.
+#[account]
+#[derive(InitSpace)]
+pub struct CoverageCertificate {
+    pub insured_wallet: Pubkey,
+    pub policy: Pubkey,
+    pub nft_mint: Pubkey,
+    pub recipient: Pubkey,
+    pub payout_cap: u64,
+    pub tier: u8,
+    pub valid_until: i64,
+    // sha256 of the fields above, so the terms can be relayed and checked off-chain
+    pub certificate_hash: [u8; 32],
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct ClaimHistory {
+    pub wallet: Pubkey,
+    pub claims_filed: u32,
+    pub last_claim_at: i64,
+    pub bump: u8,
+}
+
+// A keeper's reimbursements, limited per epoch by keeper_fee_caps
+#[account]
+#[derive(InitSpace)]
+pub struct KeeperStats {
+    pub keeper: Pubkey,
+    pub epoch: u64,
+    pub reimbursed_this_epoch: u64,
+    pub total_reimbursed: u64,
+    pub bump: u8,
+}
+
+// Tokens a wallet's protection may swap or freeze, each with the most one action may
+// move. Separate from the watchlist in RiskParams, which only raises alerts.
+#[account]
+#[derive(InitSpace)]
+pub struct CoveredTokenList {
+    pub wallet: Pubkey,
+    #[max_len(MAX_COVERED_TOKENS)]
+    pub covered_tokens: Vec<CoveredToken>,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, InitSpace)]
+pub struct CoveredToken {
+    pub mint: Pubkey,
+    pub max_amount: u64,
+}
+
+// A wallet's latest activity as a circular buffer, newest overwriting oldest, so a
+// client without an event feed can read its recent history in one fetch
+#[account]
+#[derive(InitSpace)]
+pub struct WalletActivity {
+    pub wallet: Pubkey,
+    // Slot the next entry goes into
+    pub head: u8,
+    pub len: u8,
+    pub entries: [ActivityEntry; MAX_ACTIVITY_ENTRIES],
+    pub bump: u8,
+}
+
+impl CoveredTokenList {
+    pub fn get(&self, mint: &Pubkey) -> Option<&CoveredToken> {
+        self.covered_tokens
+            .iter()
+            .find(|covered| covered.mint == *mint)
+    }
+
+    pub fn mints(&self) -> Vec<Pubkey> {
+        self.covered_tokens
+            .iter()
+            .map(|covered| covered.mint)
+            .collect()
+    }
+}
+
+impl WalletActivity {
+    pub const SEED: &'static [u8] = b"wallet_activity";
+
+    pub fn new(wallet: Pubkey, bump: u8) -> Self {
+        Self {
+            wallet,
+            head: 0,
+            len: 0,
+            entries: [ActivityEntry::default(); MAX_ACTIVITY_ENTRIES],
+            bump,
+        }
+    }
+
+    pub fn push(&mut self, entry: ActivityEntry) {
+        self.entries[self.head as usize] = entry;
+        self.head = ((self.head as usize + 1) % MAX_ACTIVITY_ENTRIES) as u8;
+        self.len = (self.len as usize + 1).min(MAX_ACTIVITY_ENTRIES) as u8;
+    }
+
+    // Entries oldest first
+    pub fn recent(&self) -> Vec<ActivityEntry> {
+        let start =
+            (self.head as usize + MAX_ACTIVITY_ENTRIES - self.len as usize) % MAX_ACTIVITY_ENTRIES;
+        (0..self.len as usize)
+            .map(|i| self.entries[(start + i) % MAX_ACTIVITY_ENTRIES])
+            .collect()
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, Debug, PartialEq, InitSpace)]
+pub struct ActivityEntry {
+    pub kind: ActivityKind,
+    // Payment, score, action or payout amount, by kind
+    pub amount: u64,
+    // Payment mint, token, claim or oracle the entry concerns; default when none
+    pub reference: Pubkey,
+    pub timestamp: i64,
+}
+
+#[derive(
+    AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, Debug, PartialEq, Eq, InitSpace,
+)]
+pub enum ActivityKind {
+    #[default]
+    Empty,
+    Subscribed,
+    ScoreBreach,
+    ProtectionTriggered,
+    ClaimInitiated,
+    PayoutReceived,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct ProtocolConfig {
+    pub admin: Pubkey,
+    pub max_score_delta_per_update: u8,
+    pub treasury_reserve_floor: u64,
+    #[max_len(MAX_LENDING_PROGRAMS)]
+    pub lending_allowlist: Vec<Pubkey>,
+    pub lp_bonus: LpBonusConfig,
+    pub tiers: [TierConfig; 3],
+    pub min_pool_epoch_secs: i64,
+    pub rebate_bps: u16,
+    pub ledger_seq: u64,
+    pub protocol_chain_id: u8,
+    pub governance_mint: Pubkey,
+    pub fee_waiver_burn_rate: u64,
+    pub isolated_tiers: bool,
+    pub cross_subsidy_bps: u16,
+    pub max_withdrawal_bps_per_epoch: u16,
+    pub max_pool_withdrawal_bps_per_epoch: u16,
+    pub expected_loss_bps: u16,
+    pub solvency_buffer_bps: u16,
+    pub paused: u8,
+    pub total_subscriptions: u64,
+    pub total_nfts_minted: u64,
+    pub total_claims: u64,
+    pub total_protection_actions: u64,
+    pub vote_incentive_per_claim: u64,
+    pub max_vote_incentive_total: u64,
+    pub vote_incentives_paid: u64,
+    pub oracle_disagreement_threshold: u8,
+    pub pending_admin: Option<Pubkey>,
+    pub maintenance_fund_lamports: u64,
+    pub event_seq: u64,
+    pub coverage_gap_alert_days: u16,
+    pub cpi_guard_enabled: bool,
+    #[max_len(MAX_CPI_ALLOWLIST)]
+    pub cpi_allowlist: Vec<Pubkey>,
+    pub lp_lockup_secs: i64,
+    pub early_withdrawal_penalty_bps: u16,
+    pub max_oracle_count: u8,
+    pub subscription_grace_secs: i64,
+    pub policy_waiting_secs: i64,
+    pub min_subscription_age_for_governance_secs: i64,
+    pub thread_config: ThreadConfig,
+    pub action_log_tree: ActionLogTree,
+    #[max_len(MAX_STREAM_PROGRAMS)]
+    pub stream_allowlist: Vec<Pubkey>,
+    pub policy_tree: Pubkey,
+    // Transfer hook programs a Token-2022 covered asset may run
+    #[max_len(MAX_TRANSFER_HOOK_PROGRAMS)]
+    pub transfer_hook_allowlist: Vec<Pubkey>,
+    // Multisig programs (e.g. Squads) whose wallets may act through CPI
+    #[max_len(MAX_MULTISIG_PROGRAMS)]
+    pub multisig_allowlist: Vec<Pubkey>,
+    // Claims paying more than this are split into installment_count installments; 0 disables
+    pub installment_threshold: u64,
+    pub installment_count: u8,
+    // Yield programs claim coverage bonds may be deposited into
+    #[max_len(MAX_YIELD_PROGRAMS)]
+    pub yield_allowlist: Vec<Pubkey>,
+    // Per-wallet limits, indexed by RateLimitedAction
+    pub rate_limits: [RateLimit; RATE_LIMIT_SLOTS],
+    // Sequential numbers the next policy and claim are given, starting at 1
+    pub next_policy_number: u64,
+    pub next_claim_number: u64,
+    // Signs subscription price quotes; quotes are refused while unset
+    pub quote_authority: Pubkey,
+    // Moving average of filing-to-resolution time over resolved claims
+    pub avg_resolution_time_secs: u64,
+    // DEX programs protection swaps may route through; changed only by proposal
+    #[max_len(MAX_SWAP_PROGRAMS)]
+    pub swap_program_allowlist: Vec<Pubkey>,
+    // Switchboard feed pool TVL is published to
+    pub tvl_feed: Pubkey,
+    pub keeper_fee_caps: KeeperFeeCaps,
+    // Share of a secondary-market NFT sale paid to the treasury
+    pub marketplace_fee_bps: u16,
+    // Compute units each protection action is expected to use, before corrections
+    pub protection_cu_baselines: ProtectionCuBaselines,
+    // Share of an invalidated claim's amount the claimant is charged
+    pub false_claim_penalty_bps: u16,
+    // Once set, the program is upgraded only by UpgradeProgram proposals; never unset
+    pub upgrades_governed: bool,
+    // Number of governed upgrades executed so far
+    pub program_version: u32,
+    pub bump: u8,
+}
+
+// Config fields the admin may change without a governance proposal
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub enum ConfigParam {
+    PauseFlags(u8),
+    MaxScoreDeltaPerUpdate(u8),
+    RebateBps(u16),
+    OracleDisagreementThreshold(u8),
+    CoverageGapAlertDays(u16),
+    SubscriptionGraceSecs(i64),
+    PolicyWaitingSecs(i64),
+    MinSubscriptionAgeForGovernanceSecs(i64),
+    AutomationProgram(Pubkey),
+    CompressedActionLogs(bool),
+    InstallmentThreshold(u64),
+    InstallmentCount(u8),
+    RateLimit(RateLimitedAction, RateLimit),
+    QuoteAuthority(Pubkey),
+    TvlFeed(Pubkey),
+    KeeperFeeCaps(KeeperFeeCaps),
+    MarketplaceFeeBps(u16),
+    ProtectionCuBaselines(ProtectionCuBaselines),
+    FalseClaimPenaltyBps(u16),
+}
+
+// User actions with a per-wallet rate limit; each indexes its slot in
+// ProtocolConfig.rate_limits and UserAccount.rate_windows
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum RateLimitedAction {
+    InitiateClaim,
+    JoinMintWaitlist,
+}
+
+// Bounds on keeper reimbursements: the compute unit price (micro-lamports) and units a
+// keeper's bid is reimbursed up to, and what one keeper may be paid per epoch
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, Debug, InitSpace)]
+pub struct KeeperFeeCaps {
+    pub max_cu_price: u64,
+    pub max_compute_units: u32,
+    pub epoch_budget_lamports: u64,
+}
+
+// Measured compute units of trigger_protection per action, with an uncompressed
+// action log
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, Debug, PartialEq, InitSpace)]
+pub struct ProtectionCuBaselines {
+    pub swap: u32,
+    pub freeze: u32,
+    pub alert: u32,
+    pub claim: u32,
+}
+
+// At most `max_count` uses per `window_secs`; a zero max_count disables the limit
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, Debug, InitSpace)]
+pub struct RateLimit {
+    pub window_secs: i64,
+    pub max_count: u16,
+}
+
+// A wallet's uses of one rate-limited action in the window that opened at window_start
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, Debug, InitSpace)]
+pub struct RateWindow {
+    pub window_start: i64,
+    pub count: u16,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct MintWaitlist {
+    pub pool: Pubkey,
+    pub vault: Pubkey,
+    #[max_len(MAX_WAITLIST_ENTRIES)]
+    pub entries: Vec<WaitlistEntry>,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct WaitlistEntry {
+    pub wallet: Pubkey,
+    pub tier: u8,
+    pub payout_cap: u64,
+    pub queued_at: i64,
+    pub duration: u64,
+    pub premium: u64,
+    pub portfolio_value_at_mint: u64,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct OracleRegistry {
+    pub primary_oracle: Pubkey,
+    pub primary_heartbeat: i64,
+    #[max_len(MAX_FALLBACK_ORACLES)]
+    pub fallback_oracles: Vec<Pubkey>,
+    #[max_len(MAX_FALLBACK_ORACLES)]
+    pub fallback_heartbeats: Vec<i64>,
+    pub current_active_oracle: Pubkey,
+    pub heartbeat_interval: i64,
+    pub apy_oracle: Pubkey,
+    pub oracle_count: u8,
+    pub bump: u8,
+    // Bots allowed to reject provably false claims without a vote
+    #[max_len(MAX_INVALIDATOR_ORACLES)]
+    pub invalidator_oracles: Vec<Pubkey>,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct InsuranceNFT {
+    pub version: u8,
+    pub tier: u8,
+    pub expiry: i64,
+    pub payout_cap: u64,
+    pub token_mint: Pubkey,
+    pub owner: Pubkey,
+    pub collateralized: bool,
+    pub collateral_loan: Pubkey,
+    pub lending_protocol: Pubkey,
+    // Portfolio value of the wallet at mint, which capped payout_cap
+    pub portfolio_value_at_mint: u64,
+    pub pool: Pubkey,
+    pub rent_payer: Pubkey,
+    pub coverage_starts_at: i64,
+    pub stream_config: StreamConfig,
+    // Set for compressed (Bubblegum) policies, whose token_mint is the asset id
+    pub compressed_leaf: Option<CompressedLeaf>,
+    // Compliance reference passed with the premium payment
+    pub premium_reference: Option<[u8; 16]>,
+    // Sequential policy number; 0 on policies minted before numbering
+    pub policy_number: u64,
+}
+
+// A policy NFT offered for sale; the NFT is held in the listing vault meanwhile
+#[account]
+#[derive(InitSpace)]
+pub struct NftListing {
+    pub nft_mint: Pubkey,
+    // Payment mint units
+    pub price: u64,
+    pub listed_at: i64,
+    pub expiry: i64,
+    pub seller: Pubkey,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct ActionLog {
+    pub timestamp: i64,
+    pub trigger_type: ProtectionAction,
+    pub token: Pubkey,
+    pub score: u8,
+    // The owner routed a swap through a program off the swap allowlist
+    pub allowlist_bypassed: bool,
+}
+
+// Leaf data of a compressed action log, at `leaf_index` in the protocol's tree
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, InitSpace)]
+pub struct ActionLogEntry {
+    pub wallet: Pubkey,
+    pub timestamp: i64,
+    pub trigger_type: ProtectionAction,
+    pub token: Pubkey,
+    pub score: u8,
+    pub leaf_index: u64,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Claim {
+    pub version: u8,
+    pub claimant: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+    pub status: ClaimStatus,
+    #[max_len(MAX_CLAIM_PROOF_LEN)]
+    pub proof: Vec<u8>,
+    pub insurance_nft: Pubkey,
+    pub approval_votes: u64,
+    pub rejection_votes: u64,
+    pub paid_amount: u64,
+    pub shortfall: u64,
+    pub shortfall_round: u32,
+    pub fraud_score: u8,
+    pub fraud_assessed: bool,
+    pub protocol_voted: bool,
+    pub written_off: bool,
+    pub pool: Pubkey,
+    pub voter_count: u32,
+    pub partial_votes: u64,
+    pub approved_amount: u64,
+    // Votes are accepted until then; 0 on claims filed before voting windows
+    pub voting_ends_at: i64,
+    pub fast_tracked: bool,
+    // Compliance reference passed with the payout
+    pub payout_reference: Option<[u8; 16]>,
+    // Set when a large claim is paid in installments
+    pub payout_schedule: Option<PayoutSchedule>,
+    // Sequential claim number; 0 on claims filed before numbering
+    pub claim_number: u64,
+    // Resolution target, a full voting window after filing; 0 on claims filed before it
+    pub expected_resolution_at: i64,
+    // When the claim left Pending; 0 while pending
+    pub resolved_at: i64,
+    pub abstain_weight: u64,
+    // Recent (slot, hash) the off-chain evidence bundle commits to, verified at filing
+    pub slot_anchor: Option<SlotAnchor>,
+}
+
+// Returned by quote_premium
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct PremiumQuote {
+    pub portfolio_value: u64,
+    pub premium_rate_bps: u32,
+    pub premium: u64,
+    pub coverage_starts_at: i64,
+    pub expiry: i64,
+}
+
+// Returned by quote_payout, and what execute_payout acts on
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct PayoutQuote {
+    // Everything the claim is owed
+    pub payable: u64,
+    // The part paid now: all of it, or the first installment
+    pub due: u64,
+    pub paid: u64,
+    pub reinsured: u64,
+    pub shortfall: u64,
+    // Later installments, if the claim is paid in installments
+    pub installment_amount: u64,
+    pub installments_remaining: u8,
+}
+
+// A slot and the hash SlotHashes recorded for it. Evidence whose hash incorporates the
+// slot hash can't predate the slot, and anchoring it at filing shows it existed by
+// then; it does not prove the loss itself.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, InitSpace)]
+pub struct SlotAnchor {
+    pub slot: u64,
+    pub hash: [u8; 32],
+}
+
+// How a claim's evidence is backed, so voters can weigh slot-anchored on-chain
+// evidence apart from the rest
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub enum EvidenceClass {
+    Standard,
+    SlotAnchored,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct PayoutSchedule {
+    pub installment_amount: u64,
+    pub installments_remaining: u8,
+    pub last_installment_at: i64,
+    pub installment_interval: i64,
+    pub installments_paid: u8,
+}
+
+// Every wallet that held a claim's evidence before it was submitted
+#[account]
+#[derive(InitSpace)]
+pub struct EvidenceChainOfCustody {
+    pub claim_id: Pubkey,
+    pub custodian: Pubkey,
+    #[max_len(MAX_CLAIM_PROOF_LEN)]
+    pub proof: Vec<u8>,
+    #[max_len(MAX_CUSTODY_ENTRIES)]
+    pub entries: Vec<CustodyEntry>,
+    pub submitted: bool,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct CustodyEntry {
+    pub handler: Pubkey,
+    pub action: CustodyAction,
+    pub timestamp: i64,
+    pub hash: [u8; 32],
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum CustodyAction {
+    Created,
+    Modified,
+    Transferred,
+    Submitted,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct ClaimVoteRecord {
+    pub claim: Pubkey,
+    pub voter: Pubkey,
+    pub vote_direction: VoteChoice,
+    pub incentive_paid: bool,
+    pub bump: u8,
+    pub approved_amount: Option<u64>,
+    pub voted_at: i64,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Governance {
+    pub authority: Pubkey,
+    pub quorum: u64,
+    pub voting_duration: i64,
+    pub bump: u8,
+    pub protocol_delegate: Pubkey,
+    pub auto_vote_rules: AutoVoteRules,
+    pub protocol_delegations: u64,
+    pub proposal_count: u64,
+    pub timelock_duration: i64,
+    // Quorum for emergency proposals, at least the normal quorum
+    pub emergency_quorum: u64,
+    // Proposals still in Voting; quorum and voting_duration are fixed while nonzero
+    pub active_vote_count: u32,
+    // Claim-voting stake delegated to the protocol, weighed when each delegation was made
+    pub protocol_delegated_weight: u64,
+    // Auto-vote rules replacing auto_vote_rules once the timelock set at change elapses
+    pub pending_auto_vote_rules: Option<AutoVoteRules>,
+    pub auto_vote_rules_effective_at: i64,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct PromoCode {
+    pub code: [u8; 16],
+    pub discount_bps: u16,
+    pub max_uses: u32,
+    pub uses: u32,
+    pub expiry: i64,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Treasury {
+    pub mint: Pubkey,
+    pub vault: Pubkey,
+    pub bump: u8,
+    pub total_inflows: u64,
+    pub total_outflows: u64,
+    #[max_len(MAX_DEX_PROGRAMS)]
+    pub dex_allowlist: Vec<Pubkey>,
+}
+
+// Backup capital drawn on when a pool can't cover an approved claim
+#[account]
+#[derive(InitSpace)]
+pub struct ReinsurancePool {
+    pub payout_mint: Pubkey,
+    pub vault: Pubkey,
+    pub pool_balance: u64,
+    pub activation_threshold: u64,
+    pub reinsurance_used: u64,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Proposal {
+    pub id: u64,
+    pub proposer: Pubkey,
+    pub proposal_type: ProposalType,
+    pub status: ProposalStatus,
+    pub approval_votes: u64,
+    pub rejection_votes: u64,
+    pub created_at: i64,
+    pub voting_ends_at: i64,
+    pub quorum_reached_at: i64,
+    pub snapshot: Option<SnapshotProposal>,
+    pub cancellation_authority: Pubkey,
+    pub is_cancelled: bool,
+    pub bump: u8,
+    pub urgency: ProposalUrgency,
+    pub abstain_weight: u64,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Snapshot {
+    pub proposal: Pubkey,
+    pub voter: Pubkey,
+    pub weight: u64,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct ProposalVoteRecord {
+    pub proposal: Pubkey,
+    pub voter: Pubkey,
+    pub vote_direction: VoteChoice,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct FeeDistributor {
+    pub iris_mint: Pubkey,
+    pub stake_vault: Pubkey,
+    pub reward_vault: Pubkey,
+    pub fee_share_bps: u16,
+    pub unstake_cooldown: i64,
+    pub total_staked: u64,
+    pub acc_reward_per_share: u128,
+    pub accounted_inflows: u64,
+    pub undistributed: u64,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct FeeStake {
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub reward_debt: u128,
+    pub pending_rewards: u64,
+    pub unstaking_amount: u64,
+    pub unstake_available_at: i64,
+    // Amount-weighted start of the current stake; new tokens pull it toward the present
+    pub stake_start: i64,
+    pub bump: u8,
+}
+
+// IRIS staked to vote on claims, held apart from the fee-share stake
+#[account]
+#[derive(InitSpace)]
+pub struct ClaimStaking {
+    pub iris_mint: Pubkey,
+    pub vault: Pubkey,
+    // Effective stake that must vote on a claim for it to resolve
+    pub quorum_weight: u64,
+    pub unstake_cooldown: i64,
+    pub total_staked: u64,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct ClaimVoteStake {
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub unstaking_amount: u64,
+    pub unstake_available_at: i64,
+    // Amount-weighted start of the current stake, as on FeeStake
+    pub stake_start: i64,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct VoteDelegation {
+    pub delegator: Pubkey,
+    pub delegatee: Pubkey,
+    pub bump: u8,
+    // Weight this delegation adds to the protocol delegate's votes; zero for other delegates
+    pub protocol_weight: u64,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct InsurancePool {
+    pub payout_mint: Pubkey,
+    pub vault: Pubkey,
+    pub bump: u8,
+    pub approved_liabilities: u64,
+    pub pending_shortfalls: u64,
+    pub haircut_mode: bool,
+    pub haircut_assets: u64,
+    pub haircut_liabilities: u64,
+    pub shortfall_round: u32,
+    pub round_assets: u64,
+    pub round_shortfalls: u64,
+    pub round_remaining: u64,
+    pub bonus_epoch_started_at: i64,
+    pub bonus_shares_this_epoch: u64,
+    pub total_shares: u64,
+    pub current_epoch: u64,
+    pub epoch_started_at: i64,
+    pub epoch_weight: u128,
+    pub weight_updated_at: i64,
+    pub premiums_accrued: u64,
+    pub reserved_rewards: u64,
+    pub acc_reward_per_share: u128,
+    pub total_donations: u64,
+    pub tier: u8,
+    pub epoch_withdrawn: u64,
+    pub outstanding_coverage: u64,
+    pub minting_halted: bool,
+    pub halt_overridden: bool,
+    pub observed_apy_bps: u16,
+    pub apy_last_updated: i64,
+    // Coverage bonds out in yield escrows; still part of the pool's assets
+    pub yield_escrowed: u64,
+    pub last_tvl_published_at: i64,
+}
+
+// Coverage bond posted for a pending claim and deposited with a yield program
+#[account]
+#[derive(InitSpace)]
+pub struct YieldEscrow {
+    pub claim: Pubkey,
+    pub pool: Pubkey,
+    pub vault: Pubkey,
+    pub yield_protocol: Pubkey,
+    pub deposited_at: i64,
+    pub deposited_amount: u64,
+    pub settled: bool,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct LpPosition {
+    pub owner: Pubkey,
+    pub pool: Pubkey,
+    pub shares: u64,
+    pub last_update_epoch: u64,
+    pub last_update_ts: i64,
+    pub epoch_weight: u128,
+    pub pending_rewards: u64,
+    pub withdrawal_epoch: u64,
+    pub epoch_start_shares: u64,
+    pub withdrawn_this_epoch: u64,
+    pub queued_withdrawal: u64,
+    pub deposit_timestamp: i64,
+    pub lockup_period: i64,
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct PoolEpoch {
+    pub pool: Pubkey,
+    pub epoch: u64,
+    pub closed_at: i64,
+    pub premiums: u64,
+    pub total_weight: u128,
+    pub reward_per_weight: u128,
+    pub acc_reward_per_share: u128,
+    pub bump: u8,
+}
+
+// Instruction that caused an accounting ledger entry
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum LedgerInstruction {
+    Subscribe,
+    MintInsuranceNft,
+    DepositToPool,
+    DonateToPool,
+    WithdrawFromPool,
+    ClaimLpRewards,
+    ExecutePayout,
+    TopUpShortfalls,
+    ClaimNoClaimRebate,
+    DistributeFees,
+    BuybackAndBurn,
+    BackstopSubPool,
+    ProcessWaitlist,
+    ProcessStreamRenewal,
+    MintInsuranceCnft,
+    DepositToReinsurance,
+    WithdrawFromReinsurance,
+    ClaimNextInstallment,
+    OpenYieldEscrow,
+    SettleYieldEscrow,
+    BuyListedNft,
+    PayClaimPenalty,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct TierConfig {
+    pub base_rate_bps: u16,
+    pub kink_utilization_bps: u16,
+    pub slope_below_kink_bps: u16,
+    pub slope_above_kink_bps: u16,
+}
+
+// Where a compressed policy's leaf lives and the hashes Bubblegum rebuilds it from
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct CompressedLeaf {
+    pub merkle_tree: Pubkey,
+    pub nonce: u64,
+    pub data_hash: [u8; 32],
+    pub creator_hash: [u8; 32],
+}
+
+// Payment stream (Streamflow, Saber, ...) that renews a policy; `stream_id` is the
+// stream account on `stream_program`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, InitSpace)]
+pub struct StreamConfig {
+    pub stream_program: Pubkey,
+    pub stream_id: Pubkey,
+    pub rate_per_second: u64,
+    pub active: bool,
+}
+
+// Compressed action logging: trigger_protection appends to `merkle_tree` instead of
+// creating an ActionLog account while `compressed` is set
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, InitSpace)]
+pub struct ActionLogTree {
+    pub merkle_tree: Pubkey,
+    pub compressed: bool,
+    pub leaf_count: u64,
+}
+
+// Automation (Clockwork-style thread) program allowed to drive the reward-paying cranks
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, InitSpace)]
+pub struct ThreadConfig {
+    pub automation_program: Pubkey,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, InitSpace)]
+pub struct LpBonusConfig {
+    pub utilization_threshold_bps: u16,
+    pub min_bonus_bps: u16,
+    pub max_bonus_bps: u16,
+    pub epoch_bonus_budget: u64,
+    pub epoch_secs: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct RiskParams {
+    pub risk_threshold: u8,
+    #[max_len(MAX_WATCHLIST_TOKENS)]
+    pub watchlist: Vec<Pubkey>,
+    pub auto_swap: bool,
+    pub auto_freeze: bool,
+    pub score_ema_alpha: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, InitSpace)]
+pub struct AutoVoteRules {
+    pub approve_threshold: u8,
+    pub reject_threshold: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct Score {
+    pub value: u8,
+    pub timestamp: i64,
+}
+
+// An oracle score waiting out its dispute window. `timestamp` is the oracle's signed
+// time; `accepted` is set when governance rejects a dispute against it.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, InitSpace)]
+pub struct HeldScore {
+    pub value: u8,
+    pub oracle: Pubkey,
+    pub timestamp: i64,
+    pub submitted_at: i64,
+    pub disputed: bool,
+    pub accepted: bool,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct ArchivedScore {
+    pub leaf_index: u32,
+    pub score: Score,
+}
+
+// One oracle's signed report in a multi-oracle score update
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct OracleScore {
+    pub score: u8,
+    pub signature: [u8; 64],
+}
+
+// What a registered oracle key may do
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OracleRole {
+    ScoreOracle,
+    InvalidatorOracle,
+}
+
+// Account types with a versioned layout
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum AccountKind {
+    UserAccount,
+    InsuranceNft,
+    Claim,
+}
+
+// Plan price offered to a subscriber, valid through `valid_until_slot`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct PriceQuote {
+    pub plan_id: u8,
+    pub price: u64,
+    pub valid_until_slot: u64,
+}
+
+// What the quote authority signs: the quote bound to the paying wallet, so it can't be
+// replayed by another, and to the deployment
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct PriceQuoteMessage {
+    pub quote: PriceQuote,
+    pub payer: Pubkey,
+    pub chain_id: u8,
+}
+
+// A score as the oracle signs it. The signed bytes are wire::encode_score_message, not
+// the Borsh encoding of this struct.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct ScoreMessage {
+    pub wallet: Pubkey,
+    pub score: u8,
+    pub category: u8,
+    // Set for scores on one of the wallet's tokens rather than the wallet as a whole
+    pub token: Option<Pubkey>,
+    pub timestamp: i64,
+    pub nonce: u64,
+    pub chain_id: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, InitSpace)]
+pub enum ProtectionAction {
+    Swap,
+    Freeze,
+    Alert,
+    Claim,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct SubscriptionPlan {
+    pub id: u8,
+    pub duration: i64,
+    pub price: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, InitSpace)]
+pub enum ProposalType {
+    BuybackAndBurn {
+        dex_program: Pubkey,
+        pool: Pubkey,
+        iris_mint: Pubkey,
+        max_amount_in: u64,
+        min_iris_out: u64,
+    },
+    SetTreasuryReserveFloor {
+        floor: u64,
+    },
+    WriteOffShortfall {
+        claim: Pubkey,
+    },
+    SetSwapProgramAllowed {
+        program: Pubkey,
+        allowed: bool,
+    },
+    UpdateQuorum {
+        quorum: u64,
+        emergency_quorum: u64,
+    },
+    UpdateVotingDuration {
+        voting_duration: i64,
+    },
+    // Admin change that skips the upgrade-authority check; see execute_set_admin_proposal
+    SetAdminUnchecked {
+        new_admin: Pubkey,
+    },
+    // Replace the program's code with the buffer at `new_program_data`, signed for by
+    // the governed `upgrade_authority`
+    UpgradeProgram {
+        new_program_data: Pubkey,
+        upgrade_authority: Pubkey,
+    },
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct SnapshotProposal {
+    pub minimum_stake_for_snapshot_bps: u16,
+    pub snapshot_taken: bool,
+    pub total_weight: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, InitSpace)]
+pub enum ProposalUrgency {
+    Normal,
+    // Critical security patches: a short vote at the emergency quorum, opened only by
+    // the emergency council
+    Emergency,
+}
+
+// Reject and Approve keep the encoding of the bool vote records used before
+// abstentions, false and true
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, InitSpace)]
+pub enum VoteChoice {
+    Reject,
+    Approve,
+    Abstain,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, InitSpace)]
+pub enum ProposalStatus {
+    Voting,
+    Approved,
+    Rejected,
+    Executed,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, InitSpace)]
+pub enum LossType {
+    Exploit,
+    Phishing,
+    RugPull,
+    Depeg,
+    Other,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, InitSpace)]
+pub enum ClaimStatus {
+    Pending,
+    Approved,
+    PartiallyApproved,
+    Rejected,
+    Paid,
+}
+
+// Implementation of constants and helper methods
+impl UserAccount {
+    pub const CURRENT_VERSION: u8 = 11;
+    // Bytes appended by each version after 1: v2 rent_payer, v3 last_action_log, v4
+    // subscription_reference, v5 score_history_hash and score_hash_count, v6 rate_windows,
+    // v7 max_auto_swap_amount_bps, v8 held_score, v9 watchlist_alerts_paused and
+    // paused_until, v10 penalty_owed, v11 last_score_dispute_at
+    pub const LAYOUT_GROWTH: &'static [usize] = &[
+        32,
+        1 + ActionLogEntry::INIT_SPACE,
+        17,
+        36,
+        RATE_LIMIT_SLOTS * RateWindow::INIT_SPACE,
+        2,
+        1 + HeldScore::INIT_SPACE,
+        9,
+        8,
+        8,
+    ];
+    // Automatic swaps sell at most half the balance unless the user sets otherwise
+    pub const DEFAULT_MAX_AUTO_SWAP_AMOUNT_BPS: u16 = 5_000;
+
+    pub fn version_supported(&self) -> bool {
+        self.version <= Self::CURRENT_VERSION
+    }
+
+    // Where closing the account refunds rent: whoever funded it, or the wallet for
+    // accounts created before the payer was recorded
+    pub fn rent_recipient(&self) -> Pubkey {
+        if self.rent_payer == Pubkey::default() {
+            self.wallet
+        } else {
+            self.rent_payer
+        }
+    }
+
+    // Score protection triggers compare against the risk threshold; None before the
+    // first score
+    pub fn effective_score(&self) -> Option<u8> {
+        let latest = self.score_history.last()?;
+        Some(logic::protection_score(
+            latest.value,
+            self.smoothed_score,
+            self.preferences.score_ema_alpha,
+        ))
+    }
+
+    // Auto-swap cap in effect, the default for accounts that predate the setting
+    pub fn max_auto_swap_bps(&self) -> u16 {
+        if self.max_auto_swap_amount_bps == 0 {
+            Self::DEFAULT_MAX_AUTO_SWAP_AMOUNT_BPS
+        } else {
+            self.max_auto_swap_amount_bps
+        }
+    }
+
+    // Coverage state of the subscription, with a cleared active_sub flag read as expired
+    pub fn subscription_state(&self, now: i64, grace_secs: i64) -> logic::CoverageState {
+        logic::subscription_state(
+            self.active_sub,
+            now,
+            self.subscription_started_at,
+            self.subscription_expiry,
+            grace_secs,
+        )
+    }
+
+    // Whether the wallet is covered right now
+    pub fn is_protected(&self, now: i64, grace_secs: i64) -> bool {
+        self.subscription_state(now, grace_secs) == logic::CoverageState::Active
+    }
+
+    // Whether `key` is the wallet's user PDA; legacy keypair accounts are not
+    pub fn is_pda(&self, key: &Pubkey) -> bool {
+        Pubkey::create_program_address(&[b"user", self.wallet.as_ref(), &[self.bump]], &crate::ID)
+            .is_ok_and(|pda| pda == *key)
+    }
+}
+
+impl ProtocolConfig {
+    pub const DEFAULT_MAX_SCORE_DELTA: u8 = 30;
+    pub const DEFAULT_ORACLE_DISAGREEMENT_THRESHOLD: u8 = 20;
+    pub const DEFAULT_COVERAGE_GAP_ALERT_DAYS: u16 = 7;
+    pub const DEFAULT_MAX_ORACLE_COUNT: u8 = 10;
+    pub const DEFAULT_SUBSCRIPTION_GRACE_SECS: i64 = 3 * 24 * 60 * 60;
+    pub const DEFAULT_MIN_SUBSCRIPTION_AGE_FOR_GOVERNANCE_SECS: i64 = 7 * 24 * 60 * 60;
+    pub const DEFAULT_TIERS: [TierConfig; 3] = [
+        TierConfig::curve(100, 8_000, 100, 1_000),
+        TierConfig::curve(200, 8_000, 200, 2_000),
+        TierConfig::curve(300, 8_000, 300, 3_000),
+    ];
+    pub const DEFAULT_POOL_EPOCH_SECS: i64 = 7 * 24 * 60 * 60;
+    pub const DEFAULT_LP_LOCKUP_SECS: i64 = 30 * 24 * 60 * 60;
+    pub const CHAIN_ID_MAINNET: u8 = 1;
+    pub const CHAIN_ID_DEVNET: u8 = 2;
+    pub const CHAIN_ID_TESTNET: u8 = 3;
+    // One dollar in payout-mint base units (6-decimal stablecoin)
+    pub const FEE_WAIVER_UNIT: u64 = 1_000_000;
+    // Tier rates price this much cover; premiums scale linearly with the policy term
+    pub const PREMIUM_TERM_SECS: u64 = 90 * 24 * 60 * 60;
+    pub const MAX_POLICY_DURATION_SECS: u64 = 365 * 24 * 60 * 60;
+    pub const MAX_EXPIRY_BATCH: usize = 20;
+    // Remaining accounts per item in each batch instruction
+    pub const EXPIRY_BATCH_STRIDE: usize = 1;
+    pub const TOP_UP_BATCH_STRIDE: usize = 2;
+    pub const VOTE_INCENTIVE_BATCH_STRIDE: usize = 2;
+    pub const SNAPSHOT_BATCH_STRIDE: usize = 2;
+    // 0.1 lamports per unit over at most 400k units, and 0.05 SOL per keeper per epoch
+    pub const DEFAULT_KEEPER_FEE_CAPS: KeeperFeeCaps = KeeperFeeCaps {
+        max_cu_price: 100_000,
+        max_compute_units: 400_000,
+        epoch_budget_lamports: 50_000_000,
+    };
+    pub const DEFAULT_MARKETPLACE_FEE_BPS: u16 = 250;
+    pub const DEFAULT_FALSE_CLAIM_PENALTY_BPS: u16 = 500;
+    // Seed of the PDA that holds the upgrade authority under governed upgrades
+    pub const UPGRADE_AUTHORITY_SEED: &'static [u8] = b"upgrade_authority";
+    pub const DEFAULT_PROTECTION_CU_BASELINES: ProtectionCuBaselines = ProtectionCuBaselines {
+        swap: 120_000,
+        freeze: 45_000,
+        alert: 35_000,
+        claim: 60_000,
+    };
+    // Claim votes cast in this final share of the window are late, and late voters'
+    // incentives are cut to LATE_VOTE_INCENTIVE_BPS of the share
+    pub const LATE_VOTE_WINDOW_BPS: u16 = 1_000;
+    pub const LATE_VOTE_INCENTIVE_BPS: u16 = 5_000;
+    // Weight of each new sample in the resolution time average, out of 256
+    pub const RESOLUTION_TIME_EMA_WEIGHT: u64 = 32;
+    pub const DEFAULT_INSTALLMENT_COUNT: u8 = 3;
+    pub const MAX_INSTALLMENTS: u8 = 12;
+    // A signed score is held this long before it applies, so the wallet can dispute it
+    pub const SCORE_DISPUTE_WINDOW_SECS: i64 = 5 * 60;
+    // A wallet may open one score dispute per cooldown
+    pub const SCORE_DISPUTE_COOLDOWN_SECS: i64 = 7 * 24 * 60 * 60;
+    // Polling interval suggested to automation threads when a crank had nothing to do
+    pub const CRANK_IDLE_INTERVAL_SECS: i64 = 60 * 60;
+    pub const PAUSE_SUBSCRIBE: u8 = 1 << 0;
+    pub const PAUSE_SCORES: u8 = 1 << 1;
+    pub const PAUSE_MINT: u8 = 1 << 2;
+    pub const PAUSE_PROTECTION: u8 = 1 << 3;
+    pub const PAUSE_CLAIMS: u8 = 1 << 4;
+    pub const PAUSE_ALL: u8 = (1 << 5) - 1;
+
+    pub fn require_not_paused(&self, flag: u8) -> Result<()> {
+        require!(self.paused & flag == 0, ErrorCode::ProtocolPaused);
+        Ok(())
+    }
+
+    // (coverage start, expiry) of a policy of `duration` seconds bought at `now`
+    pub fn policy_term(&self, now: i64, duration: u64) -> Result<(i64, i64)> {
+        require!(
+            duration > 0 && duration <= Self::MAX_POLICY_DURATION_SECS,
+            ErrorCode::InvalidDuration
+        );
+        let coverage_starts_at = now
+            .checked_add(self.policy_waiting_secs)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let expiry = now
+            .checked_add(duration as i64)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok((coverage_starts_at, expiry))
+    }
+
+    // Tiers are numbered from 1
+    pub fn tier(&self, tier: u8) -> Result<&TierConfig> {
+        (tier as usize)
+            .checked_sub(1)
+            .and_then(|index| self.tiers.get(index))
+            .ok_or_else(|| ErrorCode::InvalidTier.into())
+    }
+
+    // Pool tier that covers policies of `tier`: the tier's own sub-pool when tiers are
+    // isolated, otherwise the shared pool
+    pub fn pool_tier_for(&self, tier: u8) -> u8 {
+        if self.isolated_tiers {
+            tier
+        } else {
+            0
+        }
+    }
+
+    pub fn tier_mut(&mut self, tier: u8) -> Result<&mut TierConfig> {
+        (tier as usize)
+            .checked_sub(1)
+            .and_then(|index| self.tiers.get_mut(index))
+            .ok_or_else(|| ErrorCode::InvalidTier.into())
+    }
+}
+
+impl ProtectionCuBaselines {
+    pub fn for_action(&self, action: &ProtectionAction) -> u32 {
+        match action {
+            ProtectionAction::Swap => self.swap,
+            ProtectionAction::Freeze => self.freeze,
+            ProtectionAction::Alert => self.alert,
+            ProtectionAction::Claim => self.claim,
+        }
+    }
+}
+
+impl TierConfig {
+    pub const fn curve(
+        base_rate_bps: u16,
+        kink_utilization_bps: u16,
+        slope_below_kink_bps: u16,
+        slope_above_kink_bps: u16,
+    ) -> Self {
+        TierConfig {
+            base_rate_bps,
+            kink_utilization_bps,
+            slope_below_kink_bps,
+            slope_above_kink_bps,
+        }
+    }
+}
+
+impl LossAttestation {
+    // How long an attestation can fast-track a claim
+    pub const TTL_SECS: i64 = 24 * 60 * 60;
+}
+
+impl InsuranceNFT {
+    pub const CURRENT_VERSION: u8 = 7;
+    // Bytes appended by each version after 1: v2 rent_payer, v3 coverage_starts_at,
+    // v4 stream_config, v5 compressed_leaf, v6 premium_reference, v7 policy_number
+    pub const LAYOUT_GROWTH: &'static [usize] = &[
+        32,
+        8,
+        StreamConfig::INIT_SPACE,
+        1 + CompressedLeaf::INIT_SPACE,
+        17,
+        8,
+    ];
+    // A streamed policy may be renewed by a keeper once it is this close to expiry
+    pub const RENEWAL_TRIGGER_SECS: i64 = 3 * 24 * 60 * 60;
+    // A renewal stream must pay the policy's premium at least once per period
+    pub const STREAM_RENEWAL_PERIOD_SECS: i64 = 90 * 24 * 60 * 60;
+
+    pub fn version_supported(&self) -> bool {
+        self.version <= Self::CURRENT_VERSION
+    }
+
+    // Where closing the policy refunds rent: whoever funded it, or the owner for
+    // policies minted before the payer was recorded
+    pub fn rent_recipient(&self) -> Pubkey {
+        if self.rent_payer == Pubkey::default() {
+            self.owner
+        } else {
+            self.rent_payer
+        }
+    }
+
+    // Coverage state at `now`; policies have no grace period
+    pub fn coverage_state(&self, now: i64) -> logic::CoverageState {
+        logic::coverage_state(now, self.coverage_starts_at, self.expiry, 0)
+    }
+}
+
+impl CoverageCertificate {
+    // Whether the certificate is intact and `policy` still backs it at `now`
+    pub fn is_valid(&self, policy: &InsuranceNFT, now: i64) -> bool {
+        self.certificate_hash == logic::certificate_hash(self)
+            && now < self.valid_until
+            && policy.owner == self.insured_wallet
+            && policy.coverage_state(now) == logic::CoverageState::Active
+    }
+}
+
+impl Claim {
+    pub const CURRENT_VERSION: u8 = 9;
+    // Bytes appended by each version after 1: v2 partial_votes, approved_amount; v3
+    // voting_ends_at, fast_tracked; v4 payout_reference; v5 payout_schedule; v6
+    // claim_number; v7 expected_resolution_at, resolved_at; v8 abstain_weight; v9
+    // slot_anchor
+    pub const LAYOUT_GROWTH: &'static [usize] = &[
+        16,
+        9,
+        17,
+        1 + PayoutSchedule::INIT_SPACE,
+        8,
+        16,
+        8,
+        1 + SlotAnchor::INIT_SPACE,
+    ];
+    // How far back a slot anchor may reach, about a minute; SlotHashes keeps 512 slots
+    pub const SLOT_ANCHOR_MAX_AGE_SLOTS: u64 = 150;
+
+    pub fn version_supported(&self) -> bool {
+        self.version <= Self::CURRENT_VERSION
+    }
+
+    // Installments still owed on a scheduled payout
+    pub fn installments_remaining(&self) -> u8 {
+        self.payout_schedule
+            .as_ref()
+            .map_or(0, |schedule| schedule.installments_remaining)
+    }
+
+    // What the claim pays out: the voters' median when partially approved, else the request
+    pub fn payable_amount(&self) -> u64 {
+        if self.approved_amount > 0 {
+            self.approved_amount
+        } else {
+            self.amount
+        }
+    }
+
+    pub fn evidence_class(&self) -> EvidenceClass {
+        if self.slot_anchor.is_some() {
+            EvidenceClass::SlotAnchored
+        } else {
+            EvidenceClass::Standard
+        }
+    }
+}
+
+impl ClaimStatus {
+    // Every status change goes through here; see logic::claim_transition_allowed
+    pub fn try_transition(from: &ClaimStatus, to: &ClaimStatus) -> Result<()> {
+        require!(
+            logic::claim_transition_allowed(from, to),
+            ErrorCode::InvalidClaimTransition
+        );
+        Ok(())
+    }
+}
+
+impl PayoutSchedule {
+    // Installments are paid monthly
+    pub const INSTALLMENT_INTERVAL_SECS: i64 = 30 * 24 * 60 * 60;
+}
+
+impl ProposalType {
+    // DEX program a proposal would CPI into, if any
+    pub fn swap_program(&self) -> Option<Pubkey> {
+        match self {
+            ProposalType::BuybackAndBurn { dex_program, .. } => Some(*dex_program),
+            _ => None,
+        }
+    }
+}
+
+impl ClaimVoteStake {
+    // Days staked at `now`, and the stake's claim vote weight after age decay
+    pub fn vote_weight(&self, now: i64) -> (u64, u64) {
+        let days_staked = logic::staked_days(now, self.stake_start);
+        (
+            days_staked,
+            logic::decayed_vote_weight(self.amount, days_staked),
+        )
+    }
+}
+
+impl AutoVoteRules {
+    // Some(true) to approve, Some(false) to reject, None when the score sits between thresholds
+    pub fn decide(&self, fraud_score: u8) -> Option<bool> {
+        if fraud_score < self.approve_threshold {
+            Some(true)
+        } else if fraud_score >= self.reject_threshold {
+            Some(false)
+        } else {
+            None
+        }
+    }
+}
+
+impl MintWaitlist {
+    pub const ENTRY_TTL_SECS: i64 = 7 * 24 * 60 * 60;
+}
+
+impl AggregationReport {
+    pub fn new(
+        wallet: Pubkey,
+        report_index: u32,
+        reports: &[(Pubkey, u8)],
+        registered_oracles: u8,
+        consensus_score: u8,
+        timestamp: i64,
+        bump: u8,
+    ) -> Self {
+        Self {
+            wallet,
+            report_index,
+            participating_oracles: reports.iter().map(|(oracle, _)| *oracle).collect(),
+            submitted_scores: reports.iter().map(|(_, score)| *score).collect(),
+            registered_oracles,
+            consensus_score,
+            timestamp,
+            bump,
+        }
+    }
+
+    // (oracle, score) pairs as they were reported
+    pub fn reports(&self) -> Vec<(Pubkey, u8)> {
+        self.participating_oracles
+            .iter()
+            .copied()
+            .zip(self.submitted_scores.iter().copied())
+            .collect()
+    }
+}
+
+impl OracleRegistry {
+    // An oracle is down once it has missed this many heartbeats in a row
+    pub const MISSED_HEARTBEATS_BEFORE_FALLBACK: i64 = 2;
+
+    fn is_live(&self, last_heartbeat: i64, now: i64) -> bool {
+        now - last_heartbeat <= self.heartbeat_interval * Self::MISSED_HEARTBEATS_BEFORE_FALLBACK
+    }
+
+    // Record a heartbeat from `oracle`; false if it is not registered
+    pub fn record_heartbeat(&mut self, oracle: &Pubkey, now: i64) -> bool {
+        if *oracle == self.primary_oracle {
+            self.primary_heartbeat = now;
+            return true;
+        }
+        match self
+            .fallback_oracles
+            .iter()
+            .position(|fallback| fallback == oracle)
+        {
+            Some(index) => {
+                self.fallback_heartbeats[index] = now;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // The primary if live, otherwise the first live fallback in order
+    pub fn role_of(&self, oracle: &Pubkey) -> Option<OracleRole> {
+        if *oracle == self.primary_oracle || self.fallback_oracles.contains(oracle) {
+            Some(OracleRole::ScoreOracle)
+        } else if self.invalidator_oracles.contains(oracle) {
+            Some(OracleRole::InvalidatorOracle)
+        } else {
+            None
+        }
+    }
+
+    pub fn live_oracle(&self, now: i64) -> Option<Pubkey> {
+        if self.is_live(self.primary_heartbeat, now) {
+            return Some(self.primary_oracle);
+        }
+        self.fallback_oracles
+            .iter()
+            .zip(self.fallback_heartbeats.iter())
+            .find(|(_, heartbeat)| self.is_live(**heartbeat, now))
+            .map(|(oracle, _)| *oracle)
+    }
+}
+
+impl InsurancePool {
+    pub const APY_MAX_AGE_SECS: i64 = 24 * 60 * 60;
+    // TVL is published at most this often; keepers publish about as often
+    pub const TVL_PUBLISH_INTERVAL_SECS: i64 = 60 * 60;
+
+    // Seed suffix for a pool PDA: empty for the shared pool, the tier byte for an
+    // isolated sub-pool. An empty seed adds nothing to the derivation, so the shared
+    // pool keeps its original [b"pool"] address.
+    pub fn tier_seed(tier: &u8) -> &[u8] {
+        if *tier == 0 {
+            &[]
+        } else {
+            std::slice::from_ref(tier)
+        }
+    }
+}
+
+impl LpPosition {
+    // Whether the deposit lockup is over, so the position can withdraw without penalty
+    pub fn lockup_elapsed(&self, now: i64) -> bool {
+        now >= self.deposit_timestamp.saturating_add(self.lockup_period)
+    }
+}
+
+impl Proposal {
+    pub const EMERGENCY_VOTING_DURATION_SECS: i64 = 2 * 60 * 60;
+}
+
+impl EmergencyCouncil {
+    // Replace `old` with `new` in place; false if `old` isn't a member
+    pub fn rotate(&mut self, old: &Pubkey, new: Pubkey) -> bool {
+        match self.members.iter_mut().find(|member| *member == old) {
+            Some(member) => {
+                *member = new;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Governance {
+    pub fn is_authorized(&self, voter: &Pubkey) -> bool {
+        // Simplified - would check if voter has governance tokens
+        voter == &self.authority
+    }
+
+    // A proposal left Voting, by resolution or expiry
+    pub fn close_vote(&mut self) {
+        self.active_vote_count = self.active_vote_count.saturating_sub(1);
+    }
+
+    pub fn require_no_active_votes(&self) -> Result<()> {
+        require!(
+            self.active_vote_count == 0,
+            ErrorCode::ActiveVotesPreventParameterChange
+        );
+        Ok(())
+    }
+
+    // Rules the protocol delegate votes by at `now`
+    pub fn auto_vote_rules_at(&self, now: i64) -> &AutoVoteRules {
+        match &self.pending_auto_vote_rules {
+            Some(pending) if now >= self.auto_vote_rules_effective_at => pending,
+            _ => &self.auto_vote_rules,
+        }
+    }
+
+    // Replace the auto-vote rules. With stake delegated to the protocol they take effect
+    // after the timelock, so delegators who disagree can revoke first.
+    pub fn set_auto_vote_rules(&mut self, rules: AutoVoteRules, now: i64) {
+        self.auto_vote_rules = self.auto_vote_rules_at(now).clone();
+        if self.protocol_delegations == 0 {
+            self.auto_vote_rules = rules;
+            self.pending_auto_vote_rules = None;
+        } else {
+            self.pending_auto_vote_rules = Some(rules);
+            self.auto_vote_rules_effective_at = now.saturating_add(self.timelock_duration);
+        }
+    }
+}
+
+impl PromoCode {
+    // Use up one redemption of `code` at `now`, returning `price` after the discount
+    pub fn redeem(&mut self, code: &[u8; 16], now: i64, price: u64) -> Result<u64> {
+        require!(self.code == *code, ErrorCode::PromoCodeMismatch);
+        require!(
+            !logic::is_expired(now, self.expiry),
+            ErrorCode::PromoCodeExpired
+        );
+        require!(self.uses < self.max_uses, ErrorCode::PromoCodeExhausted);
+
+        self.uses += 1;
+        Ok(price - (price as u128 * self.discount_bps as u128 / 10_000) as u64)
+    }
+}
+
+impl SubscriptionPlan {
+    pub fn get_plan(plan_id: u8) -> Result<Self> {
+        match plan_id {
+            1 => Ok(SubscriptionPlan {
+                id: 1,
+                duration: 30 * 24 * 60 * 60, // 30 days
+                price: 10 * 10u64.pow(6),    // 10 USDC (6 decimals)
+            }),
+            2 => Ok(SubscriptionPlan {
+                id: 2,
+                duration: 90 * 24 * 60 * 60, // 90 days
+                price: 25 * 10u64.pow(6),    // 25 USDC
+            }),
+            _ => Err(ErrorCode::InvalidPlan.into()),
+        }
+    }
+}
+
+// Events
+#[event]
+pub struct FeeWaiverBurnApplied {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+    pub tokens_burned: u64,
+    pub fee_waived: u64,
+}
+
+#[event]
+pub struct AccountMigrated {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub kind: AccountKind,
+    pub account: Pubkey,
+    pub from_version: u8,
+    pub to_version: u8,
+}
+
+#[event]
+pub struct LegacyAccountMigrated {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+    pub legacy_account: Pubkey,
+    pub user_account: Pubkey,
+}
+
+#[event]
+pub struct CertificateGenerated {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+    pub recipient: Pubkey,
+    pub certificate_hash: [u8; 32],
+}
+
+#[event]
+pub struct CertificateRevoked {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+    pub recipient: Pubkey,
+    pub certificate_hash: [u8; 32],
+}
+
+#[event]
+pub struct UserAccountClosed {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+    pub rent_recipient: Pubkey,
+    pub rent_refunded: u64,
+    // Refunded to the wallet from the claim history PDA, if it was closed too
+    pub claim_history_refunded: u64,
+}
+
+#[event]
+pub struct PolicyClosed {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+    pub policy: Pubkey,
+    // False when the record was closed without the token
+    pub burned: bool,
+    pub rent_recipient: Pubkey,
+    pub rent_refunded: u64,
+}
+
+#[event]
+pub struct CloseBlockedByActiveNft {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+    pub nft: Pubkey,
+    pub linked_nfts: u8,
+}
+
+#[event]
+pub struct CloseBlockedByOpenClaim {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+    pub claim: Pubkey,
+    pub linked_claims: u8,
+}
+
+#[event]
+pub struct ConfigInitialized {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub admin: Pubkey,
+    // Upgrade authority the admin was checked against at initialization
+    pub upgrade_authority: Option<Pubkey>,
+    pub protocol_chain_id: u8,
+}
+
+#[event]
+pub struct MultisigSignerRotationProposed {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub old: Pubkey,
+    pub new: Pubkey,
+    pub initiated_by: Pubkey,
+}
+
+#[event]
+pub struct MultisigSignerRotated {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub old: Pubkey,
+    pub new: Pubkey,
+    pub initiated_by: Pubkey,
+}
+
+#[event]
+pub struct ClaimAutoInvalidated {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub claim_id: Pubkey,
+    pub oracle: Pubkey,
+    // Hash of the invalidator's evidence that the claim is false
+    pub reason_hash: [u8; 32],
+    pub penalty: u64,
+}
+
+#[event]
+pub struct ClaimPenaltyPaid {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct GovernedUpgradesEnabled {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub admin: Pubkey,
+    pub upgrade_authority: Pubkey,
+}
+
+#[event]
+pub struct ProgramUpgradeProposed {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub proposal_id: u64,
+    pub current_version: u32,
+    pub target_data_account: Pubkey,
+}
+
+#[event]
+pub struct ProgramUpgradeExecuted {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub proposal_id: u64,
+    pub new_version: u32,
+}
+
+#[event]
+pub struct AdminChanged {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub previous_admin: Pubkey,
+    pub new_admin: Pubkey,
+}
+
+#[event]
+pub struct ComputeUnitEstimate {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub action_type: ProtectionAction,
+    pub token: Pubkey,
+    pub amount: u64,
+    pub estimated_cus: u32,
+    pub safety_margin_cus: u32,
+    // Estimate plus margin, capped at the transaction maximum
+    pub recommended_limit: u32,
+}
+
+#[event]
+pub struct ConfigParamUpdated {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub param: ConfigParam,
+    pub admin: Pubkey,
+}
+
+#[event]
+pub struct OracleDisagreementDetected {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub oracle_a: Pubkey,
+    pub score_a: u8,
+    pub oracle_b: Pubkey,
+    pub score_b: u8,
+    pub delta: u8,
+}
+
+#[event]
+pub struct OracleFallbackActivated {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub primary: Pubkey,
+    pub fallback_used: Pubkey,
+}
+
+#[event]
+pub struct PrimaryOracleRestored {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub oracle: Pubkey,
+}
+
+#[event]
+pub struct AggregationReportRead {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+    pub report_index: u32,
+    pub participating_oracles: Vec<Pubkey>,
+    pub submitted_scores: Vec<u8>,
+    pub registered_oracles: u8,
+    pub consensus_score: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct OracleRegistryCapUpdated {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub old_cap: u8,
+    pub new_cap: u8,
+}
+
+#[event]
+pub struct NoClaimRebatePaid {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+    pub rebate_amount: u64,
+}
+
+#[event]
+pub struct SubscriptionEvent {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+    pub plan_id: u8,
+    pub expiry: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SubscriptionAutoExpired {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+}
+
+// Components of a keeper reimbursement, so priority fee bids can be audited
+#[event]
+pub struct KeeperReimbursed {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub keeper: Pubkey,
+    pub base_fee: u64,
+    pub cu_price: u64,
+    pub compute_units: u32,
+    pub priority_fee: u64,
+    pub clamped: bool,
+    pub amount: u64,
+    pub reimbursed_this_epoch: u64,
+}
+
+#[event]
+pub struct SubscriptionLapsed {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+    pub expiry: i64,
+    pub keeper: Pubkey,
+    pub keeper_reward: u64,
+}
+
+#[event]
+pub struct BatchExpirationCompleted {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub count: u32,
+    pub keeper_reward: u64,
+}
+
+#[event]
+pub struct CoverageGapWarning {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+    pub subscription_expiry: i64,
+    pub nft_expiry: i64,
+    pub gap_days: i64,
+}
+
+#[event]
+pub struct InsuranceNftMinted {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+    pub mint: Pubkey,
+    pub tier: u8,
+    pub expiry: i64,
+    pub payout_cap: u64,
+    pub portfolio_value_at_mint: u64,
+    pub premium_rate_bps: u32,
+    pub premium: u64,
+    pub policy_number: u64,
+}
+
+#[event]
+pub struct PolicyTreeInitialized {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub merkle_tree: Pubkey,
+    pub max_depth: u32,
+    pub max_buffer_size: u32,
+}
+
+#[event]
+pub struct ActionLogCompressed {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+    pub merkle_tree: Pubkey,
+    pub leaf_index: u64,
+    pub leaf: [u8; 32],
+}
+
+#[event]
+pub struct ActionLogTreeInitialized {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub merkle_tree: Pubkey,
+    pub max_depth: u32,
+    pub max_buffer_size: u32,
+}
+
+#[event]
+pub struct ActionLogVerified {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+    pub merkle_tree: Pubkey,
+    pub leaf_index: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WatchlistAlertsPaused {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+    pub until: i64,
+}
+
+#[event]
+pub struct WatchlistAlertsResumed {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CoveredTokenAdded {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+    pub token: Pubkey,
+    pub max_amount: u64,
+}
+
+#[event]
+pub struct CoveredTokenRemoved {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+    pub token: Pubkey,
+}
+
+#[event]
+pub struct WatchlistAlertRaised {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+    pub token: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SwapAllowlistBypassed {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+    pub program: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SwapProgramAllowlistUpdated {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub proposal_id: u64,
+    pub program: Pubkey,
+    pub allowed: bool,
+}
+
+#[event]
+pub struct SwapAmountCapped {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+    pub requested: u64,
+    pub capped: u64,
+    pub bps: u16,
+}
+
+#[event]
+pub struct ProtectionTriggered {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+    pub action: String,
+    pub token: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RiskThresholdBreached {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+    pub score: u8,
+    pub threshold: u8,
+    pub covered_tokens: Vec<Pubkey>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CustodyEntryAdded {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub claim_id: Pubkey,
+    pub handler: Pubkey,
+    pub action: CustodyAction,
+}
+
+#[event]
+pub struct ClaimInitiated {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+    pub claim_id: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+    pub claim_number: u64,
+    pub evidence_class: EvidenceClass,
+}
+
+#[event]
+pub struct VoteIncentiveDistributed {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub claim_id: Pubkey,
+    pub voter: Pubkey,
+    pub amount: u64,
+    // Cut for voting in the last tenth of the window
+    pub late: bool,
+}
+
+#[event]
+pub struct ClaimVoted {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub claim_id: Pubkey,
+    pub voter: Pubkey,
+    pub vote: String,
+    pub approved_amount: Option<u64>,
+    pub timestamp: i64,
+    pub evidence_class: EvidenceClass,
+}
+
+#[event]
+pub struct LossAttested {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+    pub attestor: Pubkey,
+    pub loss_type: LossType,
+}
+
+// A claim resolved by its target time; delta_secs is how early (zero or negative)
+#[event]
+pub struct SlaSatisfied {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub claim_id: Pubkey,
+    pub expected: i64,
+    pub actual: i64,
+    pub delta_secs: i64,
+}
+
+// A claim resolved after its target time; delta_secs is how late
+#[event]
+pub struct SlaViolated {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub claim_id: Pubkey,
+    pub expected: i64,
+    pub actual: i64,
+    pub delta_secs: i64,
+}
+
+#[event]
+pub struct ClaimResolved {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub claim_id: Pubkey,
+    pub status: ClaimStatus,
+    // What an approved claim will pay; 0 once rejected
+    pub payable_amount: u64,
+    pub timestamp: i64,
+    pub claim_number: u64,
+}
+
+#[event]
+pub struct ClaimVotingClosed {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub claim_id: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ClaimPartiallyApproved {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub claim_id: Pubkey,
+    pub requested: u64,
+    pub approved: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ScoreHashUpdated {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+    pub new_root: [u8; 32],
+    pub leaf_index: u32,
+}
+
+#[event]
+pub struct SmoothedScoreUpdated {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+    pub raw: u8,
+    pub smoothed: u8,
+}
+
+#[event]
+pub struct ScoreClamped {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+    pub requested: u8,
+    pub stored: u8,
+    pub pending: Option<u8>,
+}
+
+#[event]
+pub struct ScorePendingApplication {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+    pub oracle: Pubkey,
+    pub value: u8,
+    pub submitted_at: i64,
+    pub applies_at: i64,
+}
+
+#[event]
+pub struct HeldScoreApplied {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+    pub oracle: Pubkey,
+    pub value: u8,
+    pub submitted_at: i64,
+}
+
+#[event]
+pub struct OracleScoreDisputed {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+    pub oracle: Pubkey,
+    pub score: u8,
+    pub reason: [u8; 64],
+}
+
+#[event]
+pub struct HeldScoreSuperseded {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+    pub oracle: Pubkey,
+    pub value: u8,
+    pub submitted_at: i64,
+    pub disputed: bool,
+}
+
+#[event]
+pub struct OracleScoreDisputeResolved {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+    pub oracle: Pubkey,
+    pub score: u8,
+    pub upheld: bool,
+}
+
+#[event]
+pub struct PendingScoreApplied {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+    pub applied_delta: u8,
+    pub remaining_pending: Option<u8>,
+}
+
+#[event]
+pub struct PayoutExecuted {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub ledger_seq: u64,
+    pub instruction: LedgerInstruction,
+    pub mint: Pubkey,
+    pub counterparty: Pubkey,
+    pub amount: u64,
+    pub claim_id: Pubkey,
+    pub claim_amount: u64,
+    pub shortfall: u64,
+    pub timestamp: i64,
+    pub claim_number: u64,
+}
+
+#[event]
+pub struct InstallmentPaid {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub claim_id: Pubkey,
+    pub installment_number: u8,
+    pub amount: u64,
+    pub installments_remaining: u8,
+}
+
+#[event]
+pub struct PremiumReceived {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub ledger_seq: u64,
+    pub instruction: LedgerInstruction,
+    pub mint: Pubkey,
+    pub counterparty: Pubkey,
+    pub amount: u64,
+    pub tier: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TreasuryCredit {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub ledger_seq: u64,
+    pub instruction: LedgerInstruction,
+    pub mint: Pubkey,
+    pub counterparty: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TreasuryDebit {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub ledger_seq: u64,
+    pub instruction: LedgerInstruction,
+    pub mint: Pubkey,
+    pub counterparty: Pubkey,
+    pub amount: u64,
+    pub reference: Option<[u8; 16]>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PoolDeposit {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub ledger_seq: u64,
+    pub instruction: LedgerInstruction,
+    pub mint: Pubkey,
+    pub counterparty: Pubkey,
+    pub amount: u64,
+    pub shares: u64,
+    pub bonus_shares: u64,
+    pub utilization_bps: u16,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PoolWithdrawal {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub ledger_seq: u64,
+    pub instruction: LedgerInstruction,
+    pub mint: Pubkey,
+    pub counterparty: Pubkey,
+    pub amount: u64,
+    pub shares: u64,
+    pub queued_shares: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WaitlistEntryAdded {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub pool: Pubkey,
+    pub wallet: Pubkey,
+    pub tier: u8,
+    pub payout_cap: u64,
+    pub premium: u64,
+    pub position: u8,
+}
+
+#[event]
+pub struct WaitlistMintProcessed {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub pool: Pubkey,
+    pub wallet: Pubkey,
+    pub mint: Pubkey,
+    pub tier: u8,
+    pub payout_cap: u64,
+    pub premium: u64,
+    pub waited_secs: i64,
+    pub policy_number: u64,
+}
+
+#[event]
+pub struct WaitlistEntryExpired {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub pool: Pubkey,
+    pub wallet: Pubkey,
+    pub refunded: u64,
+    pub queued_at: i64,
+}
+
+#[event]
+pub struct InsurancePoolStats {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub pool: Pubkey,
+    pub net_assets: u64,
+    pub total_shares: u64,
+    pub utilization_bps: u16,
+    pub outstanding_coverage: u64,
+    pub computed_apy_bps: u16,
+    pub observed_apy_bps: u16,
+    pub apy_bps: u16,
+    pub apy_observed: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CoverageMintingHalted {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub pool: Pubkey,
+    pub assets: u64,
+    pub outstanding_coverage: u64,
+    pub expected_losses: u64,
+    pub liabilities: u64,
+    pub required: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CoverageMintingResumed {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub pool: Pubkey,
+    pub assets: u64,
+    pub required: u64,
+    pub overridden: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EarlyWithdrawalPenalty {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub pool: Pubkey,
+    pub owner: Pubkey,
+    pub penalty: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WithdrawalQueued {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub pool: Pubkey,
+    pub owner: Pubkey,
+    pub queued_shares: u64,
+    pub epoch: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PoolEpochClosed {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub epoch: u64,
+    pub premiums: u64,
+    pub total_shares: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct LpRewardsClaimed {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PoolDonation {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub donor: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct ShortfallWrittenOff {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub claim_id: Pubkey,
+    pub proposal_id: u64,
+    pub amount: u64,
+    pub timestamp: i64,
 }
 
-#[derive(Accounts)]
-pub struct UpdateRiskScore<'info> {
-    #[account(mut)]
-    pub user_account: Account<'info, UserAccount>,
-    pub oracle: Signer<'info>,
+#[event]
+pub struct ReinsuranceDeposit {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub ledger_seq: u64,
+    pub instruction: LedgerInstruction,
+    pub mint: Pubkey,
+    pub counterparty: Pubkey,
+    pub amount: u64,
+    pub pool_balance: u64,
+    pub timestamp: i64,
 }
 
-#[derive(Accounts)]
-pub struct MintInsuranceNft<'info> {
-    #[account(mut)]
-    pub user_account: Account<'info, UserAccount>,
-    #[account(init, payer = user, space = 8 + InsuranceNFT::LEN)]
-    pub insurance_nft: Account<'info, InsuranceNFT>,
-    #[account(mut)]
-    pub insurance_nft_account: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub mint: Account<'info, Mint>,
-    #[account(mut)]
-    pub user: Signer<'info>,
-    pub iris_authority: AccountInfo<'info>,
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
+#[event]
+pub struct ReinsuranceWithdrawal {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub ledger_seq: u64,
+    pub instruction: LedgerInstruction,
+    pub mint: Pubkey,
+    pub counterparty: Pubkey,
+    pub amount: u64,
+    pub pool_balance: u64,
+    pub timestamp: i64,
 }
 
-#[derive(Accounts)]
-pub struct TriggerProtection<'info> {
-    #[account(mut)]
-    pub user_account: Account<'info, UserAccount>,
-    #[account(init, payer = user, space = 8 + ActionLog::LEN)]
-    pub action_log: Account<'info, ActionLog>,
-    #[account(mut)]
-    pub user: Signer<'info>,
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct ReinsuranceActivated {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub ledger_seq: u64,
+    pub instruction: LedgerInstruction,
+    pub mint: Pubkey,
+    pub counterparty: Pubkey,
+    pub amount: u64,
+    pub claim_id: Pubkey,
+    pub shortfall: u64,
+    pub reinsurance_used: u64,
+    pub timestamp: i64,
 }
 
-#[derive(Accounts)]
-pub struct InitiateClaim<'info> {
-    #[account(mut)]
-    pub insurance_nft: Account<'info, InsuranceNFT>,
-    #[account(mut)]
-    pub insurance_nft_account: Account<'info, TokenAccount>,
-    #[account(init, payer = user, space = 8 + Claim::LEN)]
-    pub claim: Account<'info, Claim>,
-    #[account(mut)]
-    pub claim_escrow: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub user: Signer<'info>,
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct SubPoolBackstopped {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub from_tier: u8,
+    pub to_tier: u8,
+    pub amount: u64,
+    pub timestamp: i64,
 }
 
-#[derive(Accounts)]
-pub struct VoteOnClaim<'info> {
-    #[account(mut)]
-    pub claim: Account<'info, Claim>,
-    pub governance: Account<'info, Governance>,
-    pub voter: Signer<'info>,
+#[event]
+pub struct HaircutModeEntered {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub assets: u64,
+    pub liabilities: u64,
+    pub timestamp: i64,
 }
 
-// Structs
-#[account]
-pub struct UserAccount {
-    pub wallet: Pubkey,
-    pub preferences: RiskParams,
-    pub active_sub: bool,
-    pub subscription_expiry: i64,
-    pub score_history: Vec<Score>,
+#[event]
+pub struct HaircutModeExited {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub authority: Pubkey,
+    pub timestamp: i64,
 }
 
-#[account]
-pub struct InsuranceNFT {
-    pub tier: u8,
-    pub expiry: i64,
-    pub payout_cap: u64,
-    pub token_mint: Pubkey,
-    pub owner: Pubkey,
+#[event]
+pub struct ShortfallToppedUp {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub claim_id: Pubkey,
+    pub round: u32,
+    pub amount: u64,
+    pub remaining_shortfall: u64,
+    pub timestamp: i64,
 }
 
-#[account]
-pub struct ActionLog {
+#[event]
+pub struct VoteDelegated {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub delegator: Pubkey,
+    pub delegatee: Pubkey,
     pub timestamp: i64,
-    pub trigger_type: ProtectionAction,
-    pub token: Pubkey,
-    pub score: u8,
 }
 
-#[account]
-pub struct Claim {
-    pub claimant: Pubkey,
-    pub amount: u64,
+#[event]
+pub struct ProtocolVoteCast {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub claim_id: Pubkey,
+    pub delegate: Pubkey,
+    pub approve: bool,
+    pub weight: u64,
+    pub fraud_score: u8,
     pub timestamp: i64,
-    pub status: ClaimStatus,
-    pub proof: Vec<u8>,
-    pub insurance_nft: Pubkey,
+}
+
+#[event]
+pub struct PromoCodeUsed {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub code_hash: [u8; 32],
+    pub wallet: Pubkey,
+    pub discount_bps: u16,
+}
+
+#[event]
+pub struct ProposalExpired {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub proposal_id: u64,
     pub approval_votes: u64,
     pub rejection_votes: u64,
+    pub abstain_weight: u64,
 }
 
-#[account]
-pub struct Governance {
-    pub authority: Pubkey,
+#[event]
+pub struct GovernanceParamsUpdated {
+    pub seq: u64,
+    pub seq_index: u8,
     pub quorum: u64,
+    pub emergency_quorum: u64,
     pub voting_duration: i64,
+    // Set when a proposal made the change
+    pub proposal_id: Option<u64>,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-pub struct RiskParams {
-    pub risk_threshold: u8,
-    pub watchlist: Vec<Pubkey>,
-    pub auto_swap: bool,
-    pub auto_freeze: bool,
+#[event]
+pub struct ProposalCreated {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub proposal_id: u64,
+    pub proposer: Pubkey,
+    pub voting_ends_at: i64,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-pub struct Score {
-    pub value: u8,
+#[event]
+pub struct EmergencyProposalCreated {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub proposal_id: u64,
+    pub proposer: Pubkey,
+    pub voting_ends_at: i64,
+    pub quorum: u64,
+}
+
+#[event]
+pub struct ClaimVoteCastWithDecay {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub claim_id: Pubkey,
+    pub voter: Pubkey,
+    pub nominal_weight: u64,
+    pub effective_weight: u64,
+    pub days_staked: u64,
+}
+
+#[event]
+pub struct VoteCastWithDecay {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub proposal_id: u64,
+    pub voter: Pubkey,
+    pub nominal_weight: u64,
+    pub effective_weight: u64,
+    pub days_staked: u64,
+}
+
+#[event]
+pub struct ProposalVoted {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub proposal_id: u64,
+    pub voter: Pubkey,
+    pub vote: String,
+    pub weight: u64,
     pub timestamp: i64,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-pub struct ScoreMessage {
-    pub wallet: Pubkey,
-    pub score: u8,
+#[event]
+pub struct ExecutionCancelled {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub proposal_id: u64,
+    pub reason_hash: [u8; 32],
+    pub cancelled_by: Pubkey,
+}
+
+#[event]
+pub struct VoteSnapshotTaken {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub proposal_id: u64,
+    pub snapshot_count: u32,
+}
+
+#[event]
+pub struct BuybackExecuted {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub proposal_id: u64,
+    pub spent: u64,
+    pub burned: u64,
     pub timestamp: i64,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq)]
-pub enum ProtectionAction {
-    Swap,
-    Freeze,
-    Alert,
-    Claim,
+#[event]
+pub struct TvlPublished {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub pool: Pubkey,
+    pub tvl: u64,
+    pub feed_pubkey: Pubkey,
+    pub timestamp: i64,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq)]
-pub enum ClaimStatus {
-    Pending,
-    Approved,
-    Rejected,
+#[event]
+pub struct ReserveFloorUpdated {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub proposal_id: u64,
+    pub old_floor: u64,
+    pub new_floor: u64,
+    pub timestamp: i64,
 }
 
-// Implementation of constants and helper methods
-impl UserAccount {
-    pub const LEN: usize = 32 + RiskParams::LEN + 1 + 8 + (4 + Score::LEN * 10); // Assuming max 10 scores stored
+#[event]
+pub struct RenewalStreamCreated {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+    pub mint: Pubkey,
+    pub stream_program: Pubkey,
+    pub stream_id: Pubkey,
+    pub rate_per_second: u64,
 }
 
-impl RiskParams {
-    pub const LEN: usize = 1 + (4 + 32 * 10) + 1 + 1; // Assuming max 10 tokens in watchlist
+#[event]
+pub struct StreamRenewalProcessed {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub wallet: Pubkey,
+    pub mint: Pubkey,
+    pub new_expiry: i64,
+    pub payment_streamed: u64,
 }
 
-impl InsuranceNFT {
-    pub const LEN: usize = 1 + 8 + 8 + 32 + 32;
+#[event]
+pub struct YieldEscrowCreated {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub claim_id: Pubkey,
+    pub yield_protocol: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
 }
 
-impl ActionLog {
-    pub const LEN: usize = 8 + 1 + 32 + 1;
+#[event]
+pub struct YieldEscrowSettled {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub claim_id: Pubkey,
+    pub status: ClaimStatus,
+    pub principal: u64,
+    pub yield_earned: u64,
+    pub timestamp: i64,
 }
 
-impl Claim {
-    pub const LEN: usize = 32 + 8 + 8 + 1 + (4 + 1024) + 32 + 8 + 8; // Proof limited to 1KB
+#[event]
+pub struct NftListed {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub mint: Pubkey,
+    pub price: u64,
+    pub seller: Pubkey,
+    pub expiry: i64,
 }
 
-impl Governance {
-    pub fn is_authorized(&self, voter: &Pubkey) -> bool {
-        // Simplified - would check if voter has governance tokens
-        voter == &self.authority
-    }
+#[event]
+pub struct NftSold {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub mint: Pubkey,
+    pub price: u64,
+    pub buyer: Pubkey,
+    pub seller: Pubkey,
+    pub fee: u64,
 }
 
-impl SubscriptionPlan {
-    pub fn get_plan(plan_id: u8) -> Result<Self> {
-        match plan_id {
-            1 => Ok(SubscriptionPlan {
-                id: 1,
-                duration: 30 * 24 * 60 * 60, // 30 days
-                price: 10 * 10u64.pow(6), // 10 USDC (6 decimals)
-            }),
-            2 => Ok(SubscriptionPlan {
-                id: 2,
-                duration: 90 * 24 * 60 * 60, // 90 days
-                price: 25 * 10u64.pow(6), // 25 USDC
-            }),
-            _ => Err(ErrorCode::InvalidPlan.into()),
-        }
-    }
+#[event]
+pub struct NftListingCancelled {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub mint: Pubkey,
+    pub seller: Pubkey,
 }
 
-// Events
 #[event]
-pub struct SubscriptionEvent {
+pub struct NftCollateralized {
+    pub seq: u64,
+    pub seq_index: u8,
     pub wallet: Pubkey,
-    pub plan_id: u8,
-    pub expiry: i64,
+    pub mint: Pubkey,
+    pub lending_protocol: Pubkey,
+    pub loan: Pubkey,
+    pub loan_amount: u64,
     pub timestamp: i64,
 }
 
 #[event]
-pub struct InsuranceNftMinted {
+pub struct CollateralReleased {
+    pub seq: u64,
+    pub seq_index: u8,
     pub wallet: Pubkey,
     pub mint: Pubkey,
-    pub tier: u8,
-    pub expiry: i64,
-    pub payout_cap: u64,
+    pub loan: Pubkey,
+    pub timestamp: i64,
 }
 
 #[event]
-pub struct ProtectionTriggered {
-    pub wallet: Pubkey,
-    pub action: String,
-    pub token: Pubkey,
+pub struct IrisStaked {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub owner: Pubkey,
     pub amount: u64,
+    pub total_staked: u64,
     pub timestamp: i64,
 }
 
 #[event]
-pub struct RiskThresholdBreached {
-    pub wallet: Pubkey,
-    pub score: u8,
-    pub threshold: u8,
+pub struct ClaimVoteStaked {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub total_staked: u64,
     pub timestamp: i64,
 }
 
 #[event]
-pub struct ClaimInitiated {
-    pub wallet: Pubkey,
-    pub claim_id: Pubkey,
+pub struct ClaimUnstakeRequested {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub available_at: i64,
+}
+
+#[event]
+pub struct ClaimVoteUnstaked {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub owner: Pubkey,
     pub amount: u64,
     pub timestamp: i64,
 }
 
 #[event]
-pub struct ClaimVoted {
-    pub claim_id: Pubkey,
-    pub voter: Pubkey,
-    pub approve: bool,
+pub struct FeesDistributed {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub amount: u64,
+    pub total_staked: u64,
+    pub acc_reward_per_share: u128,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FeeShareClaimed {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct UnstakeRequested {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub available_at: i64,
+}
+
+#[event]
+pub struct IrisUnstaked {
+    pub seq: u64,
+    pub seq_index: u8,
+    pub owner: Pubkey,
+    pub amount: u64,
     pub timestamp: i64,
 }
 
@@ -582,4 +14371,430 @@ pub enum ErrorCode {
     InvalidPlan,
     #[msg("Invalid oracle signature")]
     InvalidSignature,
-}
\ No newline at end of file
+    #[msg("Claim is not pending")]
+    ClaimNotPending,
+    #[msg("Claim is not approved")]
+    ClaimNotApproved,
+    #[msg("Token account does not belong to the claimant")]
+    InvalidClaimantAccount,
+    #[msg("No shortfall can be topped up")]
+    NoShortfallToTopUp,
+    #[msg("Pool is not in haircut mode")]
+    NotInHaircutMode,
+    #[msg("Invalid remaining accounts")]
+    InvalidRemainingAccounts,
+    #[msg("Arithmetic overflow")]
+    MathOverflow,
+    #[msg("Approve threshold must not exceed reject threshold")]
+    InvalidAutoVoteRules,
+    #[msg("Protocol delegate still has active delegations")]
+    ProtocolDelegateInUse,
+    #[msg("Claim account does not match claim id")]
+    ClaimMismatch,
+    #[msg("Protocol delegate already voted on this claim")]
+    ProtocolAlreadyVoted,
+    #[msg("Claim has no fraud assessment yet")]
+    ClaimNotAssessed,
+    #[msg("No votes are delegated to the protocol")]
+    NoProtocolDelegations,
+    #[msg("Fraud score falls between auto-vote thresholds")]
+    NoAutoVoteRuleApplies,
+    #[msg("Basis points must not exceed 10000")]
+    InvalidBps,
+    #[msg("Invalid duration")]
+    InvalidDuration,
+    #[msg("Invalid amount")]
+    InvalidAmount,
+    #[msg("Token account has the wrong mint")]
+    InvalidMint,
+    #[msg("No new fees to distribute")]
+    NothingToDistribute,
+    #[msg("Nothing to claim")]
+    NothingToClaim,
+    #[msg("Unstake cooldown has not elapsed")]
+    UnstakeCooldownActive,
+    #[msg("Promo code does not match the supplied account")]
+    PromoCodeMismatch,
+    #[msg("Promo code has expired")]
+    PromoCodeExpired,
+    #[msg("Promo code has no uses left")]
+    PromoCodeExhausted,
+    #[msg("Allowlist is full")]
+    AllowlistFull,
+    #[msg("Swap route is not on the allowlist")]
+    RouteNotAllowed,
+    #[msg("Proposal is not open for voting")]
+    ProposalNotVoting,
+    #[msg("Voting period has ended")]
+    VotingClosed,
+    #[msg("Proposal has not been approved")]
+    ProposalNotApproved,
+    #[msg("Proposal type does not match this instruction")]
+    WrongProposalType,
+    #[msg("Slippage bounds are missing or looser than approved")]
+    SlippageBoundsRequired,
+    #[msg("Swap result is outside slippage bounds")]
+    SlippageExceeded,
+    #[msg("Token account is not owned by the treasury")]
+    InvalidTreasury,
+    #[msg("Transfer would take the treasury below its reserve floor")]
+    ReserveFloorBreached,
+    #[msg("Proposal timelock has not elapsed")]
+    TimelockActive,
+    #[msg("Insurance NFT is pledged as loan collateral")]
+    NftCollateralized,
+    #[msg("Insurance NFT is not pledged as collateral")]
+    NftNotCollateralized,
+    #[msg("Lending protocol is not on the allowlist")]
+    LendingProtocolNotAllowed,
+    #[msg("Lending protocol has not released the collateral")]
+    CollateralNotReleased,
+    #[msg("Signer does not own this insurance NFT")]
+    NotNftOwner,
+    #[msg("Pool has no net assets to price shares against")]
+    PoolInsolvent,
+    #[msg("Invalid policy tier")]
+    InvalidTier,
+    #[msg("Pool epoch has not reached its minimum length")]
+    EpochTooShort,
+    #[msg("Record of the position's last epoch is required")]
+    EpochRecordRequired,
+    #[msg("Epoch record does not match the position's last epoch")]
+    EpochRecordMismatch,
+    #[msg("Not enough pool shares")]
+    InsufficientShares,
+    #[msg("Subscription period has not ended")]
+    SubscriptionStillActive,
+    #[msg("Rebate already claimed for this subscription")]
+    RebateAlreadyClaimed,
+    #[msg("Risk score crossed the alert threshold during the period")]
+    RiskThresholdCrossed,
+    #[msg("Claims were filed during the period")]
+    ClaimsFiledInPeriod,
+    #[msg("Signer does not own this user account")]
+    NotAccountOwner,
+    #[msg("Token account is not the pool vault")]
+    InvalidPoolVault,
+    #[msg("Token account is not the treasury vault")]
+    InvalidTreasuryVault,
+    #[msg("Token account is not a fee distributor vault")]
+    InvalidFeeVault,
+    #[msg("Token account is not owned by the expected wallet")]
+    InvalidTokenOwner,
+    #[msg("Payout cap exceeds the wallet's portfolio value")]
+    PayoutCapExceedsPortfolio,
+    #[msg("Score was signed for a different chain")]
+    ChainIdMismatch,
+    #[msg("Score is not newer than the last stored score")]
+    StaleScore,
+    #[msg("Premium exceeds the caller's maximum")]
+    PremiumExceedsMax,
+    #[msg("Not enough governance tokens for the requested fee waiver")]
+    InsufficientGovernanceTokensForWaiver,
+    #[msg("Fee waivers are not enabled")]
+    FeeWaiverDisabled,
+    #[msg("Claim has no outstanding shortfall to write off")]
+    NoShortfallToWriteOff,
+    #[msg("Vault holds enough free capital to pay this shortfall")]
+    ShortfallPayable,
+    #[msg("Proposal does not use a vote snapshot")]
+    NotSnapshotProposal,
+    #[msg("Vote snapshot already taken")]
+    SnapshotAlreadyTaken,
+    #[msg("Voting opens once the vote snapshot is taken")]
+    SnapshotNotTaken,
+    #[msg("Voter has no stake snapshot for this proposal")]
+    NoVoteSnapshot,
+    #[msg("Pool does not cover this tier or claim")]
+    WrongSubPool,
+    #[msg("Backstop exceeds the cross-subsidy limit")]
+    CrossSubsidyExceeded,
+    #[msg("Account still has insurance NFTs linked")]
+    ActiveNftsLinked,
+    #[msg("Account still has open claims linked")]
+    OpenClaimsLinked,
+    #[msg("Too many insurance NFTs linked to this account")]
+    TooManyLinkedNfts,
+    #[msg("Too many open claims linked to this account")]
+    TooManyOpenClaims,
+    #[msg("Insurance has not expired yet")]
+    InsuranceNotExpired,
+    #[msg("Claim is not settled yet")]
+    ClaimStillOpen,
+    #[msg("Signer is not a registered oracle")]
+    UnknownOracle,
+    #[msg("No registered oracle is live")]
+    NoLiveOracle,
+    #[msg("Signer is not the active oracle")]
+    InactiveOracle,
+    #[msg("Too many fallback oracles")]
+    TooManyFallbackOracles,
+    #[msg("Invalid oracle")]
+    InvalidOracle,
+    #[msg("New coverage is halted for this pool")]
+    MintingHalted,
+    #[msg("New coverage is not halted")]
+    MintingNotHalted,
+    #[msg("Mint waitlist is full")]
+    WaitlistFull,
+    #[msg("Mint waitlist is empty")]
+    WaitlistEmpty,
+    #[msg("Waitlist entry has expired")]
+    WaitlistEntryExpired,
+    #[msg("Waitlist entry has not expired yet")]
+    WaitlistEntryNotExpired,
+    #[msg("Invalid waitlist vault")]
+    InvalidWaitlistVault,
+    #[msg("Pool lacks solvency capacity for this coverage")]
+    InsufficientCoverageCapacity,
+    #[msg("This instruction is paused")]
+    ProtocolPaused,
+    #[msg("Unknown pause flags")]
+    InvalidPauseFlags,
+    #[msg("Claim is not resolved yet")]
+    ClaimNotResolved,
+    #[msg("Claim account does not match claim_id")]
+    ClaimIdMismatch,
+    #[msg("Oracles disagree by more than the allowed threshold")]
+    OracleDisagreement,
+    #[msg("Invalid admin")]
+    InvalidAdmin,
+    #[msg("Signer is not the pending admin")]
+    NotPendingAdmin,
+    #[msg("Account version is newer than this program supports")]
+    UnsupportedAccountVersion,
+    #[msg("Account is not a legacy layout of the expected type")]
+    InvalidAccountLayout,
+    #[msg("APY oracle has not updated in over a day")]
+    StaleApyOracle,
+    #[msg("Watchlist exceeds the maximum number of tokens")]
+    WatchlistTooLong,
+    #[msg("Claim proof exceeds the maximum size")]
+    ProofTooLarge,
+    #[msg("Instruction may not be invoked via CPI")]
+    CpiNotAllowed,
+    #[msg("LP deposit lockup has not elapsed")]
+    LpLockupNotElapsed,
+    #[msg("Early withdrawal requires accepting the penalty")]
+    EarlyWithdrawalPenaltyNotAccepted,
+    #[msg("Rent must be refunded to the account's original payer")]
+    InvalidRentRecipient,
+    #[msg("Claim evidence has already been submitted")]
+    EvidenceAlreadySubmitted,
+    #[msg("Signer is not the evidence custodian")]
+    NotEvidenceCustodian,
+    #[msg("Submitted proof does not match the logged evidence")]
+    EvidenceMismatch,
+    #[msg("Evidence custody log is full")]
+    CustodyLogFull,
+    #[msg(
+        "Approved amount must be positive, no more than the claim, and only on an approving vote"
+    )]
+    InvalidApprovedAmount,
+    #[msg("Oracle registry is at its configured capacity")]
+    OracleRegistryFull,
+    #[msg("Coverage has not started yet")]
+    CoverageNotStarted,
+    #[msg("Subscription has lapsed and is only in its grace period")]
+    SubscriptionInGraceOnly,
+    #[msg("Legacy user account must be migrated with migrate_legacy_user first")]
+    MigrationRequired,
+    #[msg("Proposal execution was cancelled")]
+    ExecutionCancelled,
+    #[msg("Execution can only be cancelled while the timelock is running")]
+    CancellationWindowClosed,
+    #[msg("Signer is not the proposal's cancellation authority")]
+    NotCancellationAuthority,
+    #[msg("Claim cannot move to that status")]
+    InvalidClaimTransition,
+    #[msg("Action log tree has not been initialized")]
+    ActionLogTreeNotInitialized,
+    #[msg("Uncompressed action logging needs an action log account")]
+    ActionLogAccountMissing,
+    #[msg(
+        "Compressed action logging needs the tree, log authority, compression and noop accounts"
+    )]
+    ActionLogTreeAccountsMissing,
+    #[msg("Account is not the protocol's action log tree")]
+    InvalidActionLogTree,
+    #[msg("Action log proof is invalid")]
+    InvalidActionLogProof,
+    #[msg("Stream program is not on the allowlist")]
+    StreamProgramNotAllowed,
+    #[msg("Stream rate does not cover the premium each renewal period")]
+    StreamRateTooLow,
+    #[msg("Policy has no active renewal stream")]
+    StreamNotActive,
+    #[msg("Policy is not close enough to expiry to renew")]
+    RenewalNotDue,
+    #[msg("Stream withdrawal paid nothing into the treasury")]
+    NothingStreamed,
+    #[msg("Account is not the policy's renewal stream")]
+    InvalidStream,
+    #[msg("Compressed policy tree has not been initialized")]
+    PolicyTreeNotInitialized,
+    #[msg("Policy id is not the asset id of the next leaf in the policy tree")]
+    InvalidPolicyId,
+    #[msg("Compressed policy leaf does not match the policy tree")]
+    InvalidPolicyLeaf,
+    #[msg("Accounts needed to lock this kind of policy are missing")]
+    PolicyAccountsMissing,
+    #[msg("Subscription is too new to stake for governance")]
+    SubscriptionTooNewForGovernance,
+    #[msg("Transfer delivered less than the amount sent; transfer fee mints are not supported")]
+    TransferFeeNotSupported,
+    #[msg("Mint's transfer hook program is not on the allowlist")]
+    UnapprovedTransferHook,
+    #[msg("Signer is not a registered attestor")]
+    NotAttestor,
+    #[msg("Too many attestors")]
+    TooManyAttestors,
+    #[msg("Claim voting window has not closed")]
+    ClaimVotingStillOpen,
+    #[msg("A compliance reference needs the memo program account")]
+    MemoProgramMissing,
+    #[msg("Only the emergency council may open emergency proposals")]
+    EmergencyProposalUnauthorized,
+    #[msg("Too many emergency council members")]
+    TooManyCouncilMembers,
+    #[msg("Emergency quorum must be at least the normal quorum")]
+    InvalidEmergencyQuorum,
+    #[msg("Score is not in the wallet's score history")]
+    InvalidScoreProof,
+    #[msg("Token account is not the reinsurance vault")]
+    InvalidReinsuranceVault,
+    #[msg("Reinsurance tranche holds less than requested")]
+    InsufficientReinsurance,
+    #[msg("Claim has no installments left to pay")]
+    NoInstallmentsRemaining,
+    #[msg("Next installment is not due yet")]
+    InstallmentNotDue,
+    #[msg("Installment count must be between 2 and 12")]
+    InvalidInstallmentCount,
+    #[msg("Batch has more items than fit in one transaction")]
+    BatchTooLarge,
+    #[msg("Account is not the wallet's next aggregation report")]
+    InvalidAggregationReport,
+    #[msg("Writing an aggregation report needs the score archive, payer and system program")]
+    MissingReportAccounts,
+    #[msg("Too many uses of this instruction in the current window")]
+    RateLimited,
+    #[msg("Yield protocol is not on the allowlist")]
+    YieldProtocolNotAllowed,
+    #[msg("Token account is not the yield escrow vault")]
+    InvalidYieldEscrowVault,
+    #[msg("Yield escrow has already been settled")]
+    YieldEscrowSettled,
+    #[msg("Pool's free capital can't cover the coverage bond")]
+    InsufficientFreeCapital,
+    #[msg("Swaps need the user's token account for the swapped token")]
+    SwapTokenAccountMissing,
+    #[msg("Swap amount is zero after the auto-swap cap")]
+    ZeroAmountAfterCap,
+    #[msg("Wallet has no risk score yet")]
+    NoScoreHistory,
+    #[msg("Wallet has no held score")]
+    NoHeldScore,
+    #[msg("Held score is still inside its dispute window")]
+    DisputeWindowOpen,
+    #[msg("Held score's dispute window has closed")]
+    DisputeWindowClosed,
+    #[msg("Held score is disputed and awaits a governance ruling")]
+    ScoreUnderDispute,
+    #[msg("Wallet's previous score has not been applied yet")]
+    ScoreAwaitingApplication,
+    #[msg("Policy account does not match the certificate")]
+    InvalidCertificate,
+    #[msg("Price quote is not for this plan")]
+    InvalidPriceQuote,
+    #[msg("Price quote is not signed by the quote authority")]
+    InvalidQuoteSignature,
+    #[msg("Price quote has expired and the current price is higher")]
+    PriceQuoteExpired,
+    #[msg("Voter's stake account is required on snapshot proposals")]
+    VoterStakeMissing,
+    #[msg("Activity account is not the wallet's activity buffer")]
+    InvalidActivityAccount,
+    #[msg("Swap program account is required for swaps")]
+    SwapProgramMissing,
+    #[msg("Swap program is not on the swap allowlist")]
+    SwapProgramNotAllowed,
+    #[msg("Pool TVL was published less than an hour ago")]
+    TvlPublishCooldown,
+    #[msg("Feed is not the configured TVL feed")]
+    InvalidTvlFeed,
+    #[msg("Token is not on the user's watchlist")]
+    TokenNotOnWatchlist,
+    #[msg("No ed25519 instruction verifies the oracle's signature")]
+    MissingEd25519Instruction,
+    #[msg("Score was signed by a key other than the submitting oracle")]
+    WrongOracleKey,
+    #[msg("Signed score message does not match the submitted score")]
+    MessageMismatch,
+    #[msg("Score is a replay of the latest stored score")]
+    ReplayedScore,
+    #[msg("Signed score is for another wallet")]
+    ScoreWalletMismatch,
+    #[msg("Listing has expired")]
+    ListingExpired,
+    #[msg("Seller does not match the listing")]
+    InvalidListingSeller,
+    #[msg("Compressed policies can't be listed")]
+    CompressedPolicyNotListable,
+    #[msg("SlotHashes sysvar is required for a slot anchor")]
+    SlotHashesMissing,
+    #[msg("Anchored slot is too old or in the future")]
+    SlotAnchorTooOld,
+    #[msg("Slot hash does not match the SlotHashes sysvar")]
+    SlotHashMismatch,
+    #[msg("Governance parameters can't change while proposals are being voted on")]
+    ActiveVotesPreventParameterChange,
+    #[msg("Proposal voting is still open")]
+    VotingStillOpen,
+    #[msg("Admin must be the program's upgrade authority")]
+    AdminNotUpgradeAuthority,
+    #[msg("Program upgrade authority has not been burned")]
+    UpgradeAuthorityNotBurned,
+    #[msg("Token is not covered by the wallet's protection")]
+    TokenNotCovered,
+    #[msg("Covered token list is full")]
+    CoveredTokenListFull,
+    #[msg("Signer is not an emergency council member")]
+    NotCouncilMember,
+    #[msg("Replacement signer is already on the council or invalid")]
+    InvalidRotationSigner,
+    #[msg("Signer rotation has already been executed")]
+    RotationAlreadyExecuted,
+    #[msg("Signer has already confirmed this rotation")]
+    RotationAlreadyConfirmed,
+    #[msg("Signer is not an invalidator oracle")]
+    NotInvalidatorOracle,
+    #[msg("Too many invalidator oracles")]
+    TooManyInvalidatorOracles,
+    #[msg("Score oracles can't invalidate claims")]
+    InvalidatorIsScoreOracle,
+    #[msg("A false claim penalty must be paid before filing another claim")]
+    ClaimPenaltyOutstanding,
+    #[msg("No claim penalty is owed")]
+    NoPenaltyOwed,
+    #[msg("Program upgrades are not governed")]
+    UpgradesNotGoverned,
+    #[msg("Program upgrades are already governed")]
+    UpgradesAlreadyGoverned,
+    #[msg("Upgrade authority is not the governed upgrade authority")]
+    InvalidUpgradeAuthority,
+    #[msg("Buffer does not match the upgrade proposal")]
+    UpgradeBufferMismatch,
+    #[msg("Deposit is too small to mint pool shares")]
+    DepositTooSmall,
+    #[msg("Pool share supply is too low to accept donations")]
+    PoolSupplyTooLow,
+    #[msg("Wallet disputed a score too recently")]
+    ScoreDisputeCooldown,
+    #[msg("Vault is not the claim-voting stake vault")]
+    InvalidClaimStakeVault,
+    #[msg("Voter has no claim-voting stake")]
+    NoClaimVoteStake,
+    #[msg("Claim-voting stake is delegated")]
+    ClaimStakeDelegated,
+}
@@ -0,0 +1,2158 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+
+use crate::{
+    ClaimStatus, CoverageCertificate, ErrorCode, HeldScore, OracleRole, Proposal, ProposalUrgency,
+    ProtectionAction, ProtocolConfig, RateLimit, RateWindow, Score, VoteChoice,
+};
+
+// Pure protocol rules shared by the instruction handlers. Nothing here touches
+// accounts or sysvars, so it is all covered by plain unit tests.
+
+// Premium for a policy of `payout_cap` at the quoted rate over `duration` seconds.
+// The rate prices PREMIUM_TERM_SECS of cover and scales linearly with the term.
+// Policies covering a small slice of the portfolio pay down to half the rate; full
+// coverage pays all of it.
+pub fn compute_tier_price(
+    rate_bps: u32,
+    payout_cap: u64,
+    portfolio_value_at_mint: u64,
+    duration: u64,
+) -> Result<u64> {
+    require!(
+        duration > 0 && duration <= ProtocolConfig::MAX_POLICY_DURATION_SECS,
+        ErrorCode::InvalidDuration
+    );
+    let coverage_bps = if portfolio_value_at_mint == 0 {
+        10_000
+    } else {
+        ((payout_cap as u128) * 10_000 / (portfolio_value_at_mint as u128)).min(10_000)
+    };
+    let premium = (payout_cap as u128)
+        .checked_mul(rate_bps as u128)
+        .and_then(|premium| premium.checked_mul(10_000 + coverage_bps))
+        .and_then(|premium| premium.checked_mul(duration as u128))
+        .ok_or(ErrorCode::MathOverflow)?
+        / (10_000 * 20_000 * ProtocolConfig::PREMIUM_TERM_SECS as u128);
+    u64::try_from(premium).map_err(|_| ErrorCode::MathOverflow.into())
+}
+
+// Split a governance token burn into (tokens burned, fee waived). Waivers are valued at
+// `burn_rate` tokens per whole payout-mint unit and capped at half the fee; a burn
+// beyond the cap is trimmed so users never burn for nothing.
+pub fn fee_waiver_amounts(fee: u64, burn: u64, burn_rate: u64) -> (u64, u64) {
+    let cap = fee / 2;
+    let waivable = (burn as u128) * (ProtocolConfig::FEE_WAIVER_UNIT as u128) / (burn_rate as u128);
+    if waivable <= cap as u128 {
+        return (burn, waivable as u64);
+    }
+    let needed =
+        ((cap as u128) * (burn_rate as u128)).div_ceil(ProtocolConfig::FEE_WAIVER_UNIT as u128);
+    (needed as u64, cap)
+}
+
+// Exponential moving average with `alpha`/256 weight on the previous value
+pub fn smooth_score(previous: u8, score: u8, alpha: u8) -> u8 {
+    let alpha = alpha as u16;
+    ((alpha * previous as u16 + (256 - alpha) * score as u16) / 256) as u8
+}
+
+// Move `previous` toward `target` by at most `max_delta`
+pub fn clamp_score_delta(previous: u8, target: u8, max_delta: u8) -> u8 {
+    if target > previous {
+        previous.saturating_add(max_delta).min(target)
+    } else {
+        previous.saturating_sub(max_delta).max(target)
+    }
+}
+
+// Score stored for a fresh oracle report and what stays queued, as (stored, pending).
+// A queued target from an earlier clamped update is moved toward first, by at most
+// `max_delta`; the fresh score then queues behind it unless it was reached.
+pub fn next_score(
+    previous: Option<u8>,
+    pending: Option<u8>,
+    fresh: u8,
+    max_delta: u8,
+) -> (u8, Option<u8>) {
+    let target = pending.unwrap_or(fresh);
+    let stored = match previous {
+        Some(previous) => clamp_score_delta(previous, target, max_delta),
+        None => target,
+    };
+    (stored, if stored != fresh { Some(fresh) } else { None })
+}
+
+// Score compared against the risk threshold: the moving average once smoothing is
+// enabled, the latest raw score otherwise
+pub fn protection_score(latest: u8, smoothed: u8, ema_alpha: u8) -> u8 {
+    if ema_alpha > 0 {
+        smoothed
+    } else {
+        latest
+    }
+}
+
+// Whether a held score may be applied at `now`: once `window` has passed since it was
+// submitted, unless it is disputed, in which case only after governance accepts it
+pub fn held_score_applicable(held: &HeldScore, now: i64, window: i64) -> bool {
+    if held.disputed {
+        return held.accepted;
+    }
+    now >= held.submitted_at.saturating_add(window)
+}
+
+// Whether a wallet that last disputed a score at `last_dispute_at` may dispute again
+pub fn score_dispute_allowed(last_dispute_at: i64, now: i64, cooldown: i64) -> bool {
+    now >= last_dispute_at.saturating_add(cooldown)
+}
+
+// Price charged for a plan bought with a quote: the quoted price while the quote is
+// live, afterwards the registry price only if it is no higher. None when an expired
+// quote would be undercut by a higher registry price.
+pub fn quoted_plan_price(registry_price: u64, quoted_price: u64, quote_live: bool) -> Option<u64> {
+    if quote_live {
+        Some(quoted_price)
+    } else if registry_price <= quoted_price {
+        Some(registry_price)
+    } else {
+        None
+    }
+}
+
+// First pair of reports (by index) further apart than `threshold`, if any
+pub fn find_oracle_disagreement(reports: &[(Pubkey, u8)], threshold: u8) -> Option<(usize, usize)> {
+    for a in 0..reports.len() {
+        for b in a + 1..reports.len() {
+            if reports[a].1.abs_diff(reports[b].1) > threshold {
+                return Some((a, b));
+            }
+        }
+    }
+    None
+}
+
+// A wallet's score timestamps strictly increase. Resubmitting the latest one is a
+// replay; anything older is stale.
+pub fn check_score_timestamp(timestamp: i64, last: Option<i64>) -> Result<()> {
+    match last {
+        Some(last) if timestamp == last => err!(ErrorCode::ReplayedScore),
+        Some(last) if timestamp < last => err!(ErrorCode::StaleScore),
+        _ => Ok(()),
+    }
+}
+
+// Score applied from agreeing oracle reports: the median, the lower middle one for an
+// even count
+pub fn consensus_score(scores: &[u8]) -> u8 {
+    let mut sorted = scores.to_vec();
+    sorted.sort_unstable();
+    sorted
+        .get(sorted.len().saturating_sub(1) / 2)
+        .copied()
+        .unwrap_or(0)
+}
+
+// None until all votes cast, abstentions included, reach quorum; then Some(true) if
+// approvals outnumber rejections and Some(false) otherwise. Abstentions dilute: they
+// can bring a vote to quorum without it passing.
+pub fn vote_outcome(
+    approvals: u64,
+    rejections: u64,
+    abstentions: u64,
+    quorum: u64,
+) -> Option<bool> {
+    let total = approvals
+        .saturating_add(rejections)
+        .saturating_add(abstentions);
+    if total < quorum {
+        None
+    } else {
+        Some(approvals > rejections)
+    }
+}
+
+pub fn vote_choice_label(vote: &VoteChoice) -> &'static str {
+    match vote {
+        VoteChoice::Approve => "APPROVE",
+        VoteChoice::Reject => "REJECT",
+        VoteChoice::Abstain => "ABSTAIN",
+    }
+}
+
+// Whether `proposer` may open a proposal of `urgency`. Emergencies are reserved for
+// the emergency council, everything else for governance members.
+pub fn may_propose(
+    urgency: &ProposalUrgency,
+    governance_member: bool,
+    council: &[Pubkey],
+    proposer: &Pubkey,
+) -> bool {
+    match urgency {
+        ProposalUrgency::Normal => governance_member,
+        ProposalUrgency::Emergency => council.contains(proposer),
+    }
+}
+
+// Confirmations a council key rotation needs: a majority of the council, less the
+// key being replaced, and never fewer than one
+pub fn rotation_confirmations_required(members: usize) -> usize {
+    (members / 2 + 1).saturating_sub(1).max(1)
+}
+
+// How long a proposal of `urgency` stays open for votes
+pub fn proposal_voting_duration(urgency: &ProposalUrgency, voting_duration: i64) -> i64 {
+    match urgency {
+        ProposalUrgency::Normal => voting_duration,
+        ProposalUrgency::Emergency => Proposal::EMERGENCY_VOTING_DURATION_SECS,
+    }
+}
+
+// Member votes a proposal of `urgency` needs to pass or fail
+pub fn proposal_quorum(urgency: &ProposalUrgency, quorum: u64, emergency_quorum: u64) -> u64 {
+    match urgency {
+        ProposalUrgency::Normal => quorum,
+        ProposalUrgency::Emergency => emergency_quorum,
+    }
+}
+
+// Whether a protection swap may route through `program`: allowlisted programs always,
+// others only when the wallet's owner signs and explicitly overrides the list
+pub fn swap_program_permitted(
+    program: &Pubkey,
+    allowlist: &[Pubkey],
+    owner_signed: bool,
+    owner_override: bool,
+) -> bool {
+    allowlist.contains(program) || (owner_signed && owner_override)
+}
+
+// Whether a nested call acting for a wallet may run under `outer_program`, the
+// transaction's top-level program (None when it couldn't be read). With the CPI guard
+// on, only CPI-allowlisted programs and multisig programs signing for their wallets pass.
+pub fn wallet_caller_allowed(
+    guard_enabled: bool,
+    outer_program: Option<&Pubkey>,
+    cpi_allowlist: &[Pubkey],
+    multisig_allowlist: &[Pubkey],
+) -> bool {
+    if !guard_enabled {
+        return true;
+    }
+    outer_program.is_some_and(|program| {
+        cpi_allowlist.contains(program) || multisig_allowlist.contains(program)
+    })
+}
+
+// Whether a proposal that reached quorum at `quorum_reached_at` is past its timelock
+pub fn timelock_elapsed(now: i64, quorum_reached_at: i64, timelock_duration: i64) -> bool {
+    now >= quorum_reached_at.saturating_add(timelock_duration)
+}
+
+// Status a pending claim resolves to, if either side has reached quorum. Tallies are
+// effective claim-voting stake, which must reach `quorum_weight`. An approval is
+// partial when `quorum` of the votes, counted per member, also proposed a reduced amount.
+pub fn claim_resolution(
+    approvals: u64,
+    rejections: u64,
+    abstentions: u64,
+    partial_votes: u64,
+    quorum: u64,
+    quorum_weight: u64,
+) -> Option<ClaimStatus> {
+    match vote_outcome(approvals, rejections, abstentions, quorum_weight)? {
+        true if partial_votes >= quorum => Some(ClaimStatus::PartiallyApproved),
+        true => Some(ClaimStatus::Approved),
+        false => Some(ClaimStatus::Rejected),
+    }
+}
+
+// Whether a claim may move from `from` to `to`. Pending claims resolve by vote and
+// approved ones are paid out once; Rejected and Paid are final.
+pub fn claim_transition_allowed(from: &ClaimStatus, to: &ClaimStatus) -> bool {
+    match from {
+        ClaimStatus::Pending => matches!(
+            to,
+            ClaimStatus::Approved | ClaimStatus::PartiallyApproved | ClaimStatus::Rejected
+        ),
+        ClaimStatus::Approved | ClaimStatus::PartiallyApproved => *to == ClaimStatus::Paid,
+        ClaimStatus::Rejected | ClaimStatus::Paid => false,
+    }
+}
+
+// Whether a claim of `claim_amount` fits within the policy's payout cap, which was itself
+// bounded by the portfolio value at mint
+pub fn claim_within_cap(claim_amount: u64, payout_cap: u64) -> bool {
+    claim_amount <= payout_cap
+}
+
+// Whether a claim no longer counts against its claimant's open claims. Paid claims
+// stay open while a shortfall or installments are still owed.
+pub fn claim_settled(
+    status: &ClaimStatus,
+    shortfall: u64,
+    written_off: bool,
+    installments_remaining: u8,
+) -> bool {
+    match status {
+        ClaimStatus::Rejected => true,
+        ClaimStatus::Paid => (shortfall == 0 || written_off) && installments_remaining == 0,
+        ClaimStatus::Pending | ClaimStatus::Approved | ClaimStatus::PartiallyApproved => false,
+    }
+}
+
+// (first, later) installment amounts for a payout of `amount`, or None when it is at
+// or under `threshold` or installments are disabled. The first installment absorbs
+// the rounding remainder so the later ones are equal.
+pub fn installment_plan(amount: u64, threshold: u64, count: u8) -> Option<(u64, u64)> {
+    if threshold == 0 || count < 2 || amount <= threshold {
+        return None;
+    }
+    let installment = amount / count as u64;
+    Some((amount - installment * (count as u64 - 1), installment))
+}
+
+pub fn installment_due(now: i64, last_installment_at: i64, interval: i64) -> bool {
+    now >= last_installment_at.saturating_add(interval)
+}
+
+// (principal, yield) returned from a claim's coverage bond once the claim is resolved,
+// None while it is still pending. Both go back to the pool whatever the outcome; an
+// approved claim is then paid from the principal. A loss at the yield program shows as
+// principal below the deposit.
+pub fn yield_escrow_settlement(
+    status: &ClaimStatus,
+    deposited: u64,
+    returned: u64,
+) -> Option<(u64, u64)> {
+    if *status == ClaimStatus::Pending {
+        return None;
+    }
+    Some((returned.min(deposited), returned.saturating_sub(deposited)))
+}
+
+// Voting window for a new claim; fast-tracked claims get half of it
+pub fn claim_voting_window(voting_duration: i64, fast_track: bool) -> i64 {
+    if fast_track {
+        voting_duration / 2
+    } else {
+        voting_duration
+    }
+}
+
+// Whether votes are still accepted. Claims filed before voting windows have none (0).
+pub fn claim_voting_open(now: i64, voting_ends_at: i64) -> bool {
+    voting_ends_at == 0 || !is_expired(now, voting_ends_at)
+}
+
+// Whether a loss attestation fast-tracks a claim: it must be unexpired, unused and
+// cover the amount claimed
+pub fn attestation_fast_tracks(
+    now: i64,
+    expires_at: i64,
+    consumed: bool,
+    attested_amount: u64,
+    claim_amount: u64,
+) -> bool {
+    !is_expired(now, expires_at) && !consumed && claim_amount <= attested_amount
+}
+
+// Median of the proposed amounts; an even count averages the middle two
+pub fn median_amount(amounts: &mut [u64]) -> u64 {
+    if amounts.is_empty() {
+        return 0;
+    }
+    amounts.sort_unstable();
+    let mid = amounts.len() / 2;
+    if amounts.len() % 2 == 1 {
+        amounts[mid]
+    } else {
+        ((amounts[mid - 1] as u128 + amounts[mid] as u128) / 2) as u64
+    }
+}
+
+// Each voter's equal slice of a claim's vote incentive, rounded down
+pub fn vote_incentive_share(per_claim: u64, voter_count: u32) -> u64 {
+    if voter_count == 0 {
+        return 0;
+    }
+    per_claim / voter_count as u64
+}
+
+// Whether a vote at `voted_at` fell in the late tail of the window from `window_start`
+// to `window_end`. Records from before votes were timestamped (0) are never late.
+pub fn late_vote(voted_at: i64, window_start: i64, window_end: i64) -> bool {
+    let window = window_end.saturating_sub(window_start).max(0);
+    let tail = (window as i128 * ProtocolConfig::LATE_VOTE_WINDOW_BPS as i128 / 10_000) as i64;
+    voted_at != 0 && voted_at >= window_end - tail
+}
+
+// A voter's incentive, cut for a late vote
+pub fn vote_incentive_after_penalty(share: u64, late: bool) -> u64 {
+    if !late {
+        return share;
+    }
+    (share as u128 * ProtocolConfig::LATE_VOTE_INCENTIVE_BPS as u128 / 10_000) as u64
+}
+
+// Seconds from filing to resolution
+pub fn resolution_time_secs(filed_at: i64, resolved_at: i64) -> u64 {
+    resolved_at.saturating_sub(filed_at).max(0) as u64
+}
+
+// Fold one resolution time into the moving average; the first sample seeds it
+pub fn average_resolution_secs(average: u64, sample: u64) -> u64 {
+    if average == 0 {
+        return sample;
+    }
+    let weight = ProtocolConfig::RESOLUTION_TIME_EMA_WEIGHT as u128;
+    ((average as u128 * (256 - weight) + sample as u128 * weight) / 256) as u64
+}
+
+// Whether a registry holding `oracle_count` oracles has reached `max_oracle_count`
+pub fn oracle_registry_full(oracle_count: u8, max_oracle_count: u8) -> bool {
+    oracle_count >= max_oracle_count
+}
+
+// Label recorded for a protection action the program can execute; None for actions
+// that are handled elsewhere (alerts, claims)
+pub fn protection_action_label(action: &ProtectionAction) -> Option<&'static str> {
+    match action {
+        ProtectionAction::Swap => Some("SWAP"),
+        ProtectionAction::Freeze => Some("FREEZE"),
+        _ => None,
+    }
+}
+
+// Watchlist alerts are held back only while a pause is set and has not run out
+pub fn watchlist_alerts_suppressed(paused: bool, paused_until: i64, now: i64) -> bool {
+    paused && now < paused_until
+}
+
+// Whether a claim's anchored slot is in the past and at most `max_age` slots back
+pub fn slot_anchor_fresh(current_slot: u64, slot: u64, max_age: u64) -> bool {
+    slot < current_slot && current_slot - slot <= max_age
+}
+
+// Whether `admin` holds the program's upgrade authority; a burned authority matches no one
+pub fn is_upgrade_authority(upgrade_authority: Option<Pubkey>, admin: Pubkey) -> bool {
+    upgrade_authority == Some(admin)
+}
+
+// Only invalidator oracles may reject claims without a vote
+pub fn may_invalidate_claims(role: Option<OracleRole>) -> bool {
+    role == Some(OracleRole::InvalidatorOracle)
+}
+
+// What a claimant owes when their claim of `amount` is invalidated
+pub fn false_claim_penalty(amount: u64, penalty_bps: u16) -> u64 {
+    ((amount as u128) * (penalty_bps as u128) / 10_000) as u64
+}
+
+// Whether the deploying key has given up the upgrade authority, burning it or handing
+// it to governance
+pub fn upgrade_authority_relinquished(
+    upgrade_authority: Option<Pubkey>,
+    governed_authority: Pubkey,
+) -> bool {
+    upgrade_authority.is_none() || upgrade_authority == Some(governed_authority)
+}
+
+// Treasury's cut of a secondary-market sale at `price`
+pub fn marketplace_fee(price: u64, fee_bps: u16) -> u64 {
+    ((price as u128) * (fee_bps as u128) / 10_000) as u64
+}
+
+// A listing can be bought up to and including its expiry second
+pub fn listing_open(now: i64, expiry: i64) -> bool {
+    now <= expiry
+}
+
+// Whether a protection action moves the token, and so needs it on the covered list
+pub fn acts_on_covered_token(action: &ProtectionAction) -> bool {
+    matches!(action, ProtectionAction::Swap | ProtectionAction::Freeze)
+}
+
+// Most an automatic swap may sell out of `balance` when capped at `max_bps` of it
+pub fn auto_swap_cap(balance: u64, max_bps: u16) -> u64 {
+    ((balance as u128) * (max_bps as u128) / 10_000) as u64
+}
+
+// Where a subscription or policy stands at a point in time. Coverage runs from its
+// start up to (not including) its expiry; a grace period may follow before it is
+// fully expired.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CoverageState {
+    NotStarted,
+    Active,
+    Grace,
+    Expired,
+}
+
+impl CoverageState {
+    // Fail with the error matching the state unless coverage is active. `expired`
+    // lets subscriptions and policies report their own expiry error.
+    pub fn require_active(self, expired: ErrorCode) -> Result<()> {
+        match self {
+            CoverageState::Active => Ok(()),
+            CoverageState::NotStarted => Err(ErrorCode::CoverageNotStarted.into()),
+            CoverageState::Grace => Err(ErrorCode::SubscriptionInGraceOnly.into()),
+            CoverageState::Expired => Err(expired.into()),
+        }
+    }
+}
+
+pub fn coverage_state(now: i64, starts_at: i64, expiry: i64, grace_secs: i64) -> CoverageState {
+    if now < starts_at {
+        CoverageState::NotStarted
+    } else if now < expiry {
+        CoverageState::Active
+    } else if now < expiry.saturating_add(grace_secs) {
+        CoverageState::Grace
+    } else {
+        CoverageState::Expired
+    }
+}
+
+// A subscription's state with its active_sub flag folded in: a cleared flag is expired
+// whatever the timestamps say, so the two can't disagree in handler logic
+pub fn subscription_state(
+    active_sub: bool,
+    now: i64,
+    starts_at: i64,
+    expiry: i64,
+    grace_secs: i64,
+) -> CoverageState {
+    if !active_sub {
+        return CoverageState::Expired;
+    }
+    coverage_state(now, starts_at, expiry, grace_secs)
+}
+
+// Accounts one transaction may lock, whether listed in the message or loaded from an
+// address lookup table
+pub const MAX_TX_ACCOUNT_LOCKS: usize = 64;
+
+// Most items a batch can hold when each takes `stride` remaining accounts after
+// `fixed_accounts` named ones and the program itself
+pub fn batch_max_items(fixed_accounts: usize, stride: usize) -> usize {
+    MAX_TX_ACCOUNT_LOCKS.saturating_sub(fixed_accounts + 1) / stride
+}
+
+// Number of items in `remaining` per-item accounts, failing unless they form whole
+// items and stay within batch_max_items
+pub fn batch_items(fixed_accounts: usize, remaining: usize, stride: usize) -> Result<usize> {
+    require!(
+        remaining > 0 && remaining.is_multiple_of(stride),
+        ErrorCode::InvalidRemainingAccounts
+    );
+    let items = remaining / stride;
+    require!(
+        items <= batch_max_items(fixed_accounts, stride),
+        ErrorCode::BatchTooLarge
+    );
+    Ok(items)
+}
+
+// Count one use against a rate-limit window, or return false if the limit is already
+// reached. A new window opens with the first use after the previous one has run out.
+pub fn consume_rate_limit(window: &mut RateWindow, limit: &RateLimit, now: i64) -> bool {
+    if limit.max_count == 0 {
+        return true;
+    }
+    if now >= window.window_start.saturating_add(limit.window_secs) {
+        window.window_start = now;
+        window.count = 0;
+    }
+    if window.count >= limit.max_count {
+        return false;
+    }
+    window.count += 1;
+    true
+}
+
+// Anything with an expiry (subscriptions, policies, promo codes) is live strictly
+// before it and expired from that second on
+pub fn is_expired(now: i64, expiry: i64) -> bool {
+    now >= expiry
+}
+
+// Whole days by which the subscription outlives the policy; zero or negative when the
+// policy covers the whole subscription
+pub fn coverage_gap_days(subscription_expiry: i64, policy_expiry: i64) -> i64 {
+    (subscription_expiry - policy_expiry) / (24 * 60 * 60)
+}
+
+// Leaf committed to a wallet's score hash chain for one stored score
+pub fn score_leaf(score: &Score) -> [u8; 32] {
+    hashv(&[&[score.value], &score.timestamp.to_le_bytes()]).to_bytes()
+}
+
+// sha256 over a coverage certificate's terms, in field order
+pub fn certificate_hash(certificate: &CoverageCertificate) -> [u8; 32] {
+    hashv(&[
+        certificate.insured_wallet.as_ref(),
+        certificate.policy.as_ref(),
+        certificate.nft_mint.as_ref(),
+        certificate.recipient.as_ref(),
+        &certificate.payout_cap.to_le_bytes(),
+        &[certificate.tier],
+        &certificate.valid_until.to_le_bytes(),
+    ])
+    .to_bytes()
+}
+
+// Running score hash after appending `leaf`: sha256(root || leaf)
+pub fn next_score_root(root: &[u8; 32], leaf: &[u8; 32]) -> [u8; 32] {
+    hashv(&[root, leaf]).to_bytes()
+}
+
+// Whether `score` is leaf `leaf_index` of a chain of `count` leaves ending at `root`.
+// `proof` is the hash just before the leaf, then each later leaf in order.
+pub fn verify_score_inclusion(
+    root: &[u8; 32],
+    count: u32,
+    score: &Score,
+    proof: &[[u8; 32]],
+    leaf_index: u32,
+) -> bool {
+    if leaf_index >= count || proof.len() != (count - leaf_index) as usize {
+        return false;
+    }
+    let mut hash = next_score_root(&proof[0], &score_leaf(score));
+    for leaf in &proof[1..] {
+        hash = next_score_root(&hash, leaf);
+    }
+    hash == *root
+}
+
+// Whether a subscription started at `started_at` has run for at least `min_age` seconds
+pub fn subscription_age_reached(now: i64, started_at: i64, min_age: i64) -> bool {
+    now.saturating_sub(started_at) >= min_age
+}
+
+// Whole days since `stake_start`
+pub fn staked_days(now: i64, stake_start: i64) -> u64 {
+    (now.saturating_sub(stake_start).max(0) / (24 * 60 * 60)) as u64
+}
+
+// Percentage of a stake's weight that counts in a vote, by how long it has been staked:
+// young stake counts for little, so it can't be bought in just before a vote
+pub fn stake_age_weight_multiplier(days_staked: u64) -> u16 {
+    match days_staked {
+        30.. => 100,
+        15..=29 => 50,
+        7..=14 => 25,
+        _ => 10,
+    }
+}
+
+// `staked_amount` scaled by its age multiplier
+pub fn decayed_vote_weight(staked_amount: u64, days_staked: u64) -> u64 {
+    (staked_amount as u128 * stake_age_weight_multiplier(days_staked) as u128 / 100) as u64
+}
+
+// Start of a stake after adding `added` at `now` to `held` staked since `held_start`,
+// weighted by amount so a top-up can't inherit the age of a small early stake
+pub fn blended_stake_start(held: u64, held_start: i64, added: u64, now: i64) -> i64 {
+    let total = held as i128 + added as i128;
+    if total == 0 {
+        return now;
+    }
+    ((held as i128 * held_start as i128 + added as i128 * now as i128) / total) as i64
+}
+
+// Whether a streamed policy is close enough to expiry for a keeper to renew it
+pub fn stream_renewal_due(now: i64, expiry: i64, trigger_secs: i64) -> bool {
+    !is_expired(now, expiry) && expiry - now < trigger_secs
+}
+
+// Lowest stream rate that pays `premium` over `period_secs`, rounded up
+pub fn min_stream_rate(premium: u64, period_secs: i64) -> u64 {
+    premium.div_ceil(period_secs as u64)
+}
+
+// Seconds of coverage a streamed payment buys at the stream's rate, rounded down
+pub fn stream_extension_secs(payment: u64, rate_per_second: u64) -> Result<i64> {
+    require!(rate_per_second > 0, ErrorCode::StreamNotActive);
+    i64::try_from(payment / rate_per_second).map_err(|_| ErrorCode::MathOverflow.into())
+}
+
+// Layout version of an account's data: 0 for pre-version accounts, otherwise the
+// stored version byte, checked against the length that version has
+pub fn stored_layout_version(data: &[u8], current_len: usize, growth: &[usize]) -> Result<u8> {
+    let v1_len = current_len - growth.iter().sum::<usize>();
+    if data.len() + 1 == v1_len {
+        return Ok(0);
+    }
+    let stored = *data.get(8).ok_or(ErrorCode::InvalidAccountLayout)?;
+    require!(
+        stored >= 1 && stored as usize <= growth.len() + 1,
+        ErrorCode::InvalidAccountLayout
+    );
+    let stored_len = v1_len + growth[..(stored - 1) as usize].iter().sum::<usize>();
+    require!(data.len() == stored_len, ErrorCode::InvalidAccountLayout);
+    Ok(stored)
+}
+
+// Copy of an account's data in any layout version, rewritten as the current one the
+// same way migrate_layout does in place
+pub fn upgraded_layout(
+    data: &[u8],
+    discriminator: &[u8],
+    current_len: usize,
+    version: u8,
+    growth: &[usize],
+) -> Result<Vec<u8>> {
+    require!(
+        data.len() >= 8 && data[..8] == *discriminator,
+        ErrorCode::InvalidAccountLayout
+    );
+    let from_version = stored_layout_version(data, current_len, growth)?;
+
+    let mut upgraded = Vec::with_capacity(current_len);
+    upgraded.extend_from_slice(&data[..8]);
+    if from_version == 0 {
+        upgraded.push(version);
+    }
+    upgraded.extend_from_slice(&data[8..]);
+    upgraded.resize(current_len, 0);
+    upgraded[8] = version;
+    Ok(upgraded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ActivityEntry, ActivityKind, AggregationReport, AutoVoteRules, Claim, ClaimVoteStake,
+        CoveredToken, CoveredTokenList, EmergencyCouncil, Governance, InsuranceNFT,
+        LossAttestation, OracleRegistry, PromoCode, WalletActivity, MAX_ACTIVITY_ENTRIES,
+    };
+
+    const DAY: i64 = 24 * 60 * 60;
+
+    const TERM: u64 = ProtocolConfig::PREMIUM_TERM_SECS;
+
+    #[test]
+    fn tier_price_scales_with_coverage_share() {
+        // Full coverage pays the whole rate
+        assert_eq!(
+            compute_tier_price(500, 1_000_000, 1_000_000, TERM).unwrap(),
+            50_000
+        );
+        // An unknown portfolio value is treated as full coverage
+        assert_eq!(compute_tier_price(500, 1_000_000, 0, TERM).unwrap(), 50_000);
+        // A policy far smaller than the portfolio pays close to half
+        assert_eq!(
+            compute_tier_price(500, 1_000_000, 1_000_000_000, TERM).unwrap(),
+            25_025
+        );
+        // Coverage above the portfolio value is capped at 100%
+        assert_eq!(
+            compute_tier_price(500, 2_000_000, 1_000_000, TERM).unwrap(),
+            100_000
+        );
+    }
+
+    #[test]
+    fn tier_price_scales_with_term() {
+        assert_eq!(
+            compute_tier_price(500, 1_000_000, 1_000_000, TERM / 2).unwrap(),
+            25_000
+        );
+        assert_eq!(
+            compute_tier_price(500, 1_000_000, 1_000_000, TERM * 2).unwrap(),
+            100_000
+        );
+    }
+
+    #[test]
+    fn tier_price_rejects_unbounded_terms() {
+        let err = compute_tier_price(500, 1_000_000, 1_000_000, 0).unwrap_err();
+        assert_eq!(err, ErrorCode::InvalidDuration.into());
+        let err = compute_tier_price(
+            500,
+            1_000_000,
+            1_000_000,
+            ProtocolConfig::MAX_POLICY_DURATION_SECS + 1,
+        )
+        .unwrap_err();
+        assert_eq!(err, ErrorCode::InvalidDuration.into());
+    }
+
+    #[test]
+    fn tier_price_overflow_is_an_error() {
+        assert!(compute_tier_price(u32::MAX, u64::MAX, 1, TERM).is_err());
+    }
+
+    #[test]
+    fn policy_term_is_bounded() {
+        let config = ProtocolConfig::try_deserialize_unchecked(
+            &mut &[0u8; 8 + ProtocolConfig::INIT_SPACE][..],
+        )
+        .unwrap();
+        assert_eq!(
+            config.policy_term(100, TERM).unwrap(),
+            (100, 100 + TERM as i64)
+        );
+        assert!(config.policy_term(i64::MAX - 1, TERM).is_err());
+        assert!(config.policy_term(0, u64::MAX).is_err());
+    }
+
+    #[test]
+    fn fee_waiver_below_cap_burns_everything() {
+        let unit = ProtocolConfig::FEE_WAIVER_UNIT;
+        // At 2 tokens per unit, burning 2 tokens waives 1 unit of a 10-unit fee
+        assert_eq!(fee_waiver_amounts(10 * unit, 2, 2), (2, unit));
+    }
+
+    #[test]
+    fn fee_waiver_is_capped_at_half_the_fee() {
+        let unit = ProtocolConfig::FEE_WAIVER_UNIT;
+        let (burned, waived) = fee_waiver_amounts(10 * unit, 100, 2);
+        assert_eq!(waived, 5 * unit);
+        assert_eq!(burned, 10);
+    }
+
+    #[test]
+    fn smooth_score_weights_previous_by_alpha() {
+        assert_eq!(smooth_score(80, 20, 0), 20);
+        assert_eq!(smooth_score(80, 20, 128), 50);
+        assert_eq!(smooth_score(100, 100, 255), 100);
+    }
+
+    #[test]
+    fn clamp_score_delta_limits_moves_both_ways() {
+        assert_eq!(clamp_score_delta(50, 90, 10), 60);
+        assert_eq!(clamp_score_delta(50, 10, 10), 40);
+        assert_eq!(clamp_score_delta(50, 55, 10), 55);
+        assert_eq!(clamp_score_delta(250, 255, 10), 255);
+        assert_eq!(clamp_score_delta(5, 0, 10), 0);
+    }
+
+    #[test]
+    fn clamped_score_reaches_target_over_three_updates() {
+        let (stored, pending) = next_score(Some(10), None, 100, 30);
+        assert_eq!((stored, pending), (40, Some(100)));
+        let (stored, pending) = next_score(Some(stored), pending, 100, 30);
+        assert_eq!((stored, pending), (70, Some(100)));
+        let (stored, pending) = next_score(Some(stored), pending, 100, 30);
+        assert_eq!((stored, pending), (100, None));
+    }
+
+    #[test]
+    fn queued_target_is_applied_before_fresh_score() {
+        // Still climbing toward 100 when the oracle reports 50: the step goes to the
+        // queued target and the fresh score is queued next
+        assert_eq!(next_score(Some(40), Some(100), 50, 30), (70, Some(50)));
+        // The first score is stored unclamped
+        assert_eq!(next_score(None, None, 90, 30), (90, None));
+    }
+
+    #[test]
+    fn protection_score_follows_smoothing_preference() {
+        assert_eq!(protection_score(90, 40, 0), 90);
+        assert_eq!(protection_score(90, 40, 200), 40);
+    }
+
+    #[test]
+    fn held_score_waits_out_the_dispute_window() {
+        let window = ProtocolConfig::SCORE_DISPUTE_WINDOW_SECS;
+        assert_eq!(window, 300);
+        let mut held = HeldScore {
+            value: 80,
+            oracle: Pubkey::new_unique(),
+            timestamp: 1_000,
+            submitted_at: 1_000,
+            disputed: false,
+            accepted: false,
+        };
+        assert!(!held_score_applicable(&held, 1_000, window));
+        assert!(!held_score_applicable(&held, 1_000 + window - 1, window));
+        assert!(held_score_applicable(&held, 1_000 + window, window));
+
+        // A dispute blocks it past the window until governance accepts the score
+        held.disputed = true;
+        assert!(!held_score_applicable(&held, 1_000 + 10 * window, window));
+        held.accepted = true;
+        assert!(held_score_applicable(&held, 1_000 + 10 * window, window));
+    }
+
+    #[test]
+    fn score_disputes_are_rate_limited() {
+        let cooldown = 7 * DAY;
+        // A wallet that has never disputed may do so at once
+        assert!(score_dispute_allowed(0, 1_000 * DAY, cooldown));
+        assert!(!score_dispute_allowed(
+            100 * DAY,
+            100 * DAY + cooldown - 1,
+            cooldown
+        ));
+        assert!(score_dispute_allowed(
+            100 * DAY,
+            100 * DAY + cooldown,
+            cooldown
+        ));
+    }
+
+    #[test]
+    fn coverage_certificate_reads_back_and_verifies() {
+        let wallet = Pubkey::new_unique();
+        let mut certificate = CoverageCertificate {
+            insured_wallet: wallet,
+            policy: Pubkey::new_unique(),
+            nft_mint: Pubkey::new_unique(),
+            recipient: Pubkey::new_unique(),
+            payout_cap: 50_000,
+            tier: 2,
+            valid_until: 10 * DAY,
+            certificate_hash: [0; 32],
+            bump: 255,
+        };
+        certificate.certificate_hash = certificate_hash(&certificate);
+
+        // A third party deserializes the PDA's data as is
+        let mut data = Vec::new();
+        certificate.try_serialize(&mut data).unwrap();
+        let read = CoverageCertificate::try_deserialize(&mut data.as_slice()).unwrap();
+        assert_eq!(read.certificate_hash, certificate_hash(&read));
+        assert_eq!(
+            (read.insured_wallet, read.payout_cap, read.valid_until),
+            (wallet, 50_000, 10 * DAY)
+        );
+
+        let mut policy =
+            InsuranceNFT::try_deserialize_unchecked(&mut &[0u8; 8 + InsuranceNFT::INIT_SPACE][..])
+                .unwrap();
+        policy.owner = wallet;
+        policy.expiry = 10 * DAY;
+        assert!(read.is_valid(&policy, DAY));
+        // Lapses with the policy, on transfer and when its terms are altered
+        assert!(!read.is_valid(&policy, 10 * DAY));
+        policy.owner = Pubkey::new_unique();
+        assert!(!read.is_valid(&policy, DAY));
+        policy.owner = wallet;
+        let mut altered = read.clone();
+        altered.payout_cap += 1;
+        assert!(!altered.is_valid(&policy, DAY));
+    }
+
+    fn promo(max_uses: u32, expiry: i64) -> PromoCode {
+        PromoCode {
+            code: *b"LAUNCH-WEEK-2026",
+            discount_bps: 2_500,
+            max_uses,
+            uses: 0,
+            expiry,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn single_use_promo_code_redeems_once() {
+        let mut code = promo(1, 10 * DAY);
+        assert_eq!(
+            code.redeem(b"LAUNCH-WEEK-2026", DAY, 10_000_000).unwrap(),
+            7_500_000
+        );
+        let err = code
+            .redeem(b"LAUNCH-WEEK-2026", DAY, 10_000_000)
+            .unwrap_err();
+        assert_eq!(err, ErrorCode::PromoCodeExhausted.into());
+        assert_eq!(code.uses, 1);
+    }
+
+    #[test]
+    fn promo_code_stops_at_max_uses_and_expiry() {
+        let mut code = promo(3, 10 * DAY);
+        for _ in 0..3 {
+            code.redeem(b"LAUNCH-WEEK-2026", DAY, 1_000).unwrap();
+        }
+        assert_eq!(
+            code.redeem(b"LAUNCH-WEEK-2026", DAY, 1_000).unwrap_err(),
+            ErrorCode::PromoCodeExhausted.into()
+        );
+
+        let mut code = promo(3, 10 * DAY);
+        assert_eq!(
+            code.redeem(b"LAUNCH-WEEK-2026", 10 * DAY, 1_000)
+                .unwrap_err(),
+            ErrorCode::PromoCodeExpired.into()
+        );
+        // A failed redemption doesn't use the code up
+        assert_eq!(code.uses, 0);
+        assert_eq!(
+            code.redeem(b"LAUNCH-WEEK-2025", DAY, 1_000).unwrap_err(),
+            ErrorCode::PromoCodeMismatch.into()
+        );
+    }
+
+    #[test]
+    fn live_quotes_hold_their_price() {
+        assert_eq!(quoted_plan_price(12, 10, true), Some(10));
+        assert_eq!(quoted_plan_price(8, 10, true), Some(10));
+        // Expired: only a price cut gets through
+        assert_eq!(quoted_plan_price(8, 10, false), Some(8));
+        assert_eq!(quoted_plan_price(10, 10, false), Some(10));
+        assert_eq!(quoted_plan_price(12, 10, false), None);
+    }
+
+    #[test]
+    fn oracle_disagreement_finds_first_pair_over_threshold() {
+        let a = Pubkey::new_unique();
+        let b = Pubkey::new_unique();
+        let c = Pubkey::new_unique();
+        assert_eq!(
+            find_oracle_disagreement(&[(a, 50), (b, 55), (c, 70)], 10),
+            Some((0, 2))
+        );
+        assert_eq!(find_oracle_disagreement(&[(a, 50), (b, 60)], 10), None);
+        assert_eq!(find_oracle_disagreement(&[], 0), None);
+
+        // Against a threshold of 20, a 5-point spread is consensus and a 25-point one
+        // blocks the update
+        assert_eq!(find_oracle_disagreement(&[(a, 60), (b, 65)], 20), None);
+        assert_eq!(
+            find_oracle_disagreement(&[(a, 60), (b, 85)], 20),
+            Some((0, 1))
+        );
+        assert_eq!(
+            find_oracle_disagreement(&[(a, 60), (b, 65), (c, 35)], 20),
+            Some((0, 2))
+        );
+    }
+
+    #[test]
+    fn aggregation_report_replays_consensus() {
+        let oracles = [
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        ];
+        // Three of the four registered oracles reported
+        let reports = [(oracles[0], 62), (oracles[1], 70), (oracles[2], 58)];
+        let values: Vec<u8> = reports.iter().map(|(_, score)| *score).collect();
+        let report = AggregationReport::new(
+            Pubkey::new_unique(),
+            0,
+            &reports,
+            4,
+            consensus_score(&values),
+            1_700_000_000,
+            255,
+        );
+        assert_eq!(report.participating_oracles, oracles);
+        assert_eq!(report.submitted_scores, [62, 70, 58]);
+        assert_eq!(report.registered_oracles, 4);
+
+        // A disputed update can be rechecked from the report alone
+        assert_eq!(report.reports(), reports);
+        assert_eq!(
+            consensus_score(&report.submitted_scores),
+            report.consensus_score
+        );
+        assert_eq!(report.consensus_score, 62);
+        assert_eq!(find_oracle_disagreement(&report.reports(), 20), None);
+        assert_eq!(
+            find_oracle_disagreement(&report.reports(), 10),
+            Some((1, 2))
+        );
+    }
+
+    #[test]
+    fn consensus_takes_the_lower_median() {
+        assert_eq!(consensus_score(&[40, 60]), 40);
+        assert_eq!(consensus_score(&[90, 10, 50, 70]), 50);
+    }
+
+    #[test]
+    fn vote_outcome_needs_quorum() {
+        assert_eq!(vote_outcome(2, 0, 0, 3), None);
+        assert_eq!(vote_outcome(3, 0, 0, 3), Some(true));
+        assert_eq!(vote_outcome(1, 3, 0, 3), Some(false));
+        assert_eq!(vote_outcome(2, 1, 0, 3), Some(true));
+        // A tie at quorum does not pass
+        assert_eq!(vote_outcome(3, 3, 0, 3), Some(false));
+    }
+
+    #[test]
+    fn only_the_council_opens_emergency_proposals() {
+        let member = Pubkey::new_unique();
+        let outsider = Pubkey::new_unique();
+        let council = [member];
+        assert!(may_propose(
+            &ProposalUrgency::Emergency,
+            false,
+            &council,
+            &member
+        ));
+        // Governance membership alone is not enough
+        assert!(!may_propose(
+            &ProposalUrgency::Emergency,
+            true,
+            &council,
+            &outsider
+        ));
+        assert!(!may_propose(
+            &ProposalUrgency::Emergency,
+            true,
+            &[],
+            &member
+        ));
+        assert!(may_propose(
+            &ProposalUrgency::Normal,
+            true,
+            &council,
+            &outsider
+        ));
+        assert!(!may_propose(
+            &ProposalUrgency::Normal,
+            false,
+            &council,
+            &member
+        ));
+    }
+
+    #[test]
+    fn emergency_proposals_use_short_window_and_emergency_quorum() {
+        let (quorum, emergency_quorum) = (3, 5);
+        assert_eq!(
+            proposal_voting_duration(&ProposalUrgency::Emergency, 3 * DAY),
+            7_200
+        );
+        assert_eq!(
+            proposal_voting_duration(&ProposalUrgency::Normal, 3 * DAY),
+            3 * DAY
+        );
+
+        let emergency = proposal_quorum(&ProposalUrgency::Emergency, quorum, emergency_quorum);
+        assert_eq!(emergency, emergency_quorum);
+        assert_eq!(
+            proposal_quorum(&ProposalUrgency::Normal, quorum, emergency_quorum),
+            quorum
+        );
+        // Enough to pass a normal proposal, not an emergency one
+        assert_eq!(vote_outcome(4, 0, 0, emergency), None);
+        assert_eq!(vote_outcome(5, 0, 0, emergency), Some(true));
+    }
+
+    #[test]
+    fn multisig_programs_may_call_for_their_wallets() {
+        let squads = Pubkey::new_unique();
+        let integrator = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+        let (cpi_allowlist, multisig_allowlist) = ([integrator], [squads]);
+        // A multisig executing its vault transaction is the top-level program
+        assert!(wallet_caller_allowed(
+            true,
+            Some(&squads),
+            &cpi_allowlist,
+            &multisig_allowlist
+        ));
+        assert!(wallet_caller_allowed(
+            true,
+            Some(&integrator),
+            &cpi_allowlist,
+            &multisig_allowlist
+        ));
+        assert!(!wallet_caller_allowed(
+            true,
+            Some(&other),
+            &cpi_allowlist,
+            &multisig_allowlist
+        ));
+        // Without the instructions sysvar the caller is unknown
+        assert!(!wallet_caller_allowed(
+            true,
+            None,
+            &cpi_allowlist,
+            &multisig_allowlist
+        ));
+        assert!(wallet_caller_allowed(false, Some(&other), &[], &[]));
+    }
+
+    #[test]
+    fn cancellation_window_is_the_timelock() {
+        let (quorum_at, timelock) = (1_000, 600);
+        // Still cancellable one second before the timelock ends
+        assert!(!timelock_elapsed(
+            quorum_at + timelock - 1,
+            quorum_at,
+            timelock
+        ));
+        assert!(timelock_elapsed(quorum_at + timelock, quorum_at, timelock));
+        // Executable, so no longer cancellable, just after
+        assert!(timelock_elapsed(
+            quorum_at + timelock + 1,
+            quorum_at,
+            timelock
+        ));
+    }
+
+    // Claim tallies are in staked IRIS; three full-weight votes of 100 reach quorum
+    const VOTE: u64 = 100;
+    const QUORUM_WEIGHT: u64 = 3 * VOTE;
+
+    #[test]
+    fn claim_resolution_distinguishes_partial_approval() {
+        assert_eq!(
+            claim_resolution(3 * VOTE, 0, 0, 0, 3, QUORUM_WEIGHT),
+            Some(ClaimStatus::Approved)
+        );
+        assert_eq!(
+            claim_resolution(3 * VOTE, 0, 0, 2, 3, QUORUM_WEIGHT),
+            Some(ClaimStatus::Approved)
+        );
+        assert_eq!(
+            claim_resolution(3 * VOTE, 0, 0, 3, 3, QUORUM_WEIGHT),
+            Some(ClaimStatus::PartiallyApproved)
+        );
+        assert_eq!(
+            claim_resolution(0, 3 * VOTE, 0, 0, 3, QUORUM_WEIGHT),
+            Some(ClaimStatus::Rejected)
+        );
+        assert_eq!(claim_resolution(VOTE, VOTE, 0, 1, 3, QUORUM_WEIGHT), None);
+    }
+
+    #[test]
+    fn young_stake_votes_do_not_reach_claim_quorum() {
+        // Three members staked under a week carry 10% of their stake each
+        let young = decayed_vote_weight(VOTE, 6);
+        assert_eq!(claim_resolution(3 * young, 0, 0, 0, 3, QUORUM_WEIGHT), None);
+        let aged = decayed_vote_weight(VOTE, 30);
+        assert_eq!(
+            claim_resolution(3 * aged, 0, 0, 0, 3, QUORUM_WEIGHT),
+            Some(ClaimStatus::Approved)
+        );
+    }
+
+    #[test]
+    fn claim_votes_weigh_stake_size_and_age() {
+        let stake = |amount, days: i64| {
+            ClaimVoteStake {
+                owner: Pubkey::new_unique(),
+                amount,
+                unstaking_amount: 0,
+                unstake_available_at: 0,
+                stake_start: 0,
+                bump: 0,
+            }
+            .vote_weight(days * DAY)
+        };
+
+        // A small stake held for over a month counts in full...
+        let (small_days, small) = stake(1_000, 40);
+        assert_eq!((small_days, small), (40, 1_000));
+        // ...while a stake five times larger, held ten days, counts at 25%
+        let (large_days, large) = stake(5_000, 10);
+        assert_eq!((large_days, large), (10, 1_250));
+
+        // Neither reaches a 2,000 quorum alone, and the larger stake outvotes the smaller
+        assert_eq!(claim_resolution(small, 0, 0, 0, 3, 2_000), None);
+        assert_eq!(claim_resolution(0, large, 0, 0, 3, 2_000), None);
+        assert_eq!(
+            claim_resolution(small, large, 0, 0, 3, 2_000),
+            Some(ClaimStatus::Rejected)
+        );
+        // Once the larger stake ages past 30 days it counts in full
+        assert_eq!(stake(5_000, 30), (30, 5_000));
+    }
+
+    #[test]
+    fn abstentions_reach_quorum_without_approving() {
+        // A quorum of abstentions resolves the claim, and not in the claimant's favour
+        assert_eq!(
+            claim_resolution(0, 0, 3 * VOTE, 0, 3, QUORUM_WEIGHT),
+            Some(ClaimStatus::Rejected)
+        );
+        assert_eq!(claim_resolution(VOTE, 0, VOTE, 0, 3, QUORUM_WEIGHT), None);
+        // They bring a narrow approval to quorum
+        assert_eq!(
+            claim_resolution(VOTE, 0, 2 * VOTE, 0, 3, QUORUM_WEIGHT),
+            Some(ClaimStatus::Approved)
+        );
+        assert_eq!(vote_outcome(1, 1, 5, 3), Some(false));
+    }
+
+    const ALL_STATUSES: [ClaimStatus; 5] = [
+        ClaimStatus::Pending,
+        ClaimStatus::Approved,
+        ClaimStatus::PartiallyApproved,
+        ClaimStatus::Rejected,
+        ClaimStatus::Paid,
+    ];
+
+    #[test]
+    fn claim_transitions_follow_the_lifecycle() {
+        use ClaimStatus::*;
+        let allowed = [
+            (Pending, Approved),
+            (Pending, PartiallyApproved),
+            (Pending, Rejected),
+            (Approved, Paid),
+            (PartiallyApproved, Paid),
+        ];
+        for from in ALL_STATUSES.iter() {
+            for to in ALL_STATUSES.iter() {
+                let expected = allowed.iter().any(|(f, t)| f == from && t == to);
+                assert_eq!(
+                    claim_transition_allowed(from, to),
+                    expected,
+                    "{:?} -> {:?}",
+                    from,
+                    to
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn illegal_claim_transitions_are_errors() {
+        use ClaimStatus::*;
+        assert!(ClaimStatus::try_transition(&Pending, &Approved).is_ok());
+        assert!(ClaimStatus::try_transition(&Approved, &Paid).is_ok());
+        for (from, to) in [
+            (Rejected, Approved),
+            (Paid, Pending),
+            (Pending, Paid),
+            (Approved, Rejected),
+        ] {
+            assert_eq!(
+                ClaimStatus::try_transition(&from, &to).unwrap_err(),
+                ErrorCode::InvalidClaimTransition.into(),
+                "{:?} -> {:?}",
+                from,
+                to
+            );
+        }
+    }
+
+    #[test]
+    fn final_claim_statuses_cannot_be_reprocessed() {
+        for to in ALL_STATUSES.iter() {
+            assert!(!claim_transition_allowed(&ClaimStatus::Rejected, to));
+            assert!(!claim_transition_allowed(&ClaimStatus::Paid, to));
+        }
+    }
+
+    #[test]
+    fn expired_attestation_does_not_fast_track() {
+        let attested_at = 100 * DAY;
+        let expires_at = attested_at + LossAttestation::TTL_SECS;
+        assert!(attestation_fast_tracks(
+            expires_at - 1,
+            expires_at,
+            false,
+            500,
+            500
+        ));
+        assert!(!attestation_fast_tracks(
+            expires_at, expires_at, false, 500, 500
+        ));
+        assert!(!attestation_fast_tracks(
+            expires_at + DAY,
+            expires_at,
+            false,
+            500,
+            500
+        ));
+        // Used once, or for more than was attested, it doesn't either
+        assert!(!attestation_fast_tracks(
+            attested_at,
+            expires_at,
+            true,
+            500,
+            500
+        ));
+        assert!(!attestation_fast_tracks(
+            attested_at,
+            expires_at,
+            false,
+            500,
+            501
+        ));
+
+        let duration = 4 * DAY;
+        assert_eq!(claim_voting_window(duration, true), 2 * DAY);
+        assert_eq!(claim_voting_window(duration, false), duration);
+    }
+
+    #[test]
+    fn yield_escrow_settles_once_the_claim_resolves() {
+        assert_eq!(
+            yield_escrow_settlement(&ClaimStatus::Pending, 1_000, 1_020),
+            None
+        );
+        // Approved: principal pays the claim, the yield stays with the pool
+        assert_eq!(
+            yield_escrow_settlement(&ClaimStatus::Approved, 1_000, 1_020),
+            Some((1_000, 20))
+        );
+        assert_eq!(
+            yield_escrow_settlement(&ClaimStatus::Paid, 1_000, 1_020),
+            Some((1_000, 20))
+        );
+        // Rejected: principal and yield both return
+        assert_eq!(
+            yield_escrow_settlement(&ClaimStatus::Rejected, 1_000, 1_020),
+            Some((1_000, 20))
+        );
+        assert_eq!(
+            yield_escrow_settlement(&ClaimStatus::Rejected, 1_000, 990),
+            Some((990, 0))
+        );
+    }
+
+    #[test]
+    fn claim_voting_closes_at_window_end() {
+        assert!(claim_voting_open(DAY - 1, DAY));
+        assert!(!claim_voting_open(DAY, DAY));
+        // Older claims without a window stay open
+        assert!(claim_voting_open(100 * DAY, 0));
+    }
+
+    #[test]
+    fn claim_settlement_by_status() {
+        assert!(claim_settled(&ClaimStatus::Rejected, 0, false, 0));
+        assert!(claim_settled(&ClaimStatus::Paid, 0, false, 0));
+        assert!(!claim_settled(&ClaimStatus::Paid, 10, false, 0));
+        assert!(claim_settled(&ClaimStatus::Paid, 10, true, 0));
+        assert!(!claim_settled(&ClaimStatus::Paid, 0, false, 1));
+        for open in [
+            ClaimStatus::Pending,
+            ClaimStatus::Approved,
+            ClaimStatus::PartiallyApproved,
+        ] {
+            assert!(!claim_settled(&open, 0, false, 0));
+        }
+    }
+
+    #[test]
+    fn large_claims_pay_in_three_installments() {
+        assert_eq!(installment_plan(5_000, 5_000, 3), None);
+        assert_eq!(installment_plan(10_000, 0, 3), None);
+        let (first, later) = installment_plan(10_000, 5_000, 3).unwrap();
+        assert_eq!((first, later), (3_334, 3_333));
+        assert_eq!(first + 2 * later, 10_000);
+
+        // Each installment waits a full interval after the previous one
+        let interval = 30 * DAY;
+        let mut last = 1_000;
+        for _ in 0..2 {
+            assert!(!installment_due(last + interval - 1, last, interval));
+            assert!(installment_due(last + interval, last, interval));
+            last += interval;
+        }
+    }
+
+    #[test]
+    fn median_of_three_different_proposals() {
+        let mut amounts = [900, 300, 600];
+        assert_eq!(median_amount(&mut amounts), 600);
+    }
+
+    #[test]
+    fn median_of_even_count_averages_middle_pair() {
+        assert_eq!(median_amount(&mut [100, 400, 200, 300]), 250);
+        assert_eq!(median_amount(&mut [u64::MAX, u64::MAX]), u64::MAX);
+        assert_eq!(median_amount(&mut []), 0);
+    }
+
+    #[test]
+    fn vote_incentive_share_rounds_down() {
+        assert_eq!(vote_incentive_share(100, 3), 33);
+        assert_eq!(vote_incentive_share(100, 0), 0);
+
+        // Five voters on an incentive they can't split evenly: each is paid the same
+        // share, and the shares plus the dust left in the vault make up the whole
+        let (per_claim, voters) = (1_003, 5);
+        let shares: Vec<u64> = (0..voters)
+            .map(|_| vote_incentive_share(per_claim, voters))
+            .collect();
+        assert!(shares.iter().all(|share| *share == 200));
+        let dust = per_claim % voters as u64;
+        assert_eq!(dust, 3);
+        assert_eq!(shares.iter().sum::<u64>() + dust, per_claim);
+    }
+
+    #[test]
+    fn late_votes_earn_a_reduced_incentive() {
+        let (start, end) = (0, 10 * DAY);
+        assert!(!late_vote(9 * DAY - 1, start, end));
+        assert!(late_vote(9 * DAY, start, end));
+        assert!(late_vote(end, start, end));
+        // Untimestamped records are never penalized
+        assert!(!late_vote(0, start, end));
+
+        assert_eq!(vote_incentive_after_penalty(1_000, false), 1_000);
+        assert_eq!(vote_incentive_after_penalty(1_000, true), 500);
+    }
+
+    #[test]
+    fn resolution_time_feeds_the_moving_average() {
+        assert_eq!(resolution_time_secs(DAY, 4 * DAY), 3 * DAY as u64);
+        assert_eq!(resolution_time_secs(DAY, 0), 0);
+
+        let first = average_resolution_secs(0, 800);
+        assert_eq!(first, 800);
+        // A new sample moves the average an eighth of the way
+        assert_eq!(average_resolution_secs(first, 1_600), 900);
+        assert_eq!(average_resolution_secs(first, 0), 700);
+    }
+
+    #[test]
+    fn keeper_swaps_stay_on_the_allowlist() {
+        let listed = Pubkey::new_unique();
+        let unlisted = Pubkey::new_unique();
+        let allowlist = [listed];
+        assert!(swap_program_permitted(&listed, &allowlist, false, false));
+        // A keeper can't route elsewhere, with or without the override
+        assert!(!swap_program_permitted(&unlisted, &allowlist, false, false));
+        assert!(!swap_program_permitted(&unlisted, &allowlist, false, true));
+        // The owner can, only by opting in
+        assert!(!swap_program_permitted(&unlisted, &allowlist, true, false));
+        assert!(swap_program_permitted(&unlisted, &allowlist, true, true));
+        assert!(!swap_program_permitted(&listed, &[], false, false));
+    }
+
+    #[test]
+    fn oracle_registration_stops_at_capacity() {
+        let cap = ProtocolConfig::DEFAULT_MAX_ORACLE_COUNT;
+        // The primary occupies the first slot
+        let mut count = 1;
+        while !oracle_registry_full(count, cap) {
+            count += 1;
+        }
+        assert_eq!(count, cap);
+        // Deregistering one frees a slot for the next registration
+        count -= 1;
+        assert!(!oracle_registry_full(count, cap));
+        // Lowering the cap below the current count blocks further registrations
+        assert!(oracle_registry_full(cap, cap - 2));
+    }
+
+    #[test]
+    fn only_swap_and_freeze_execute_on_chain() {
+        assert_eq!(
+            protection_action_label(&ProtectionAction::Swap),
+            Some("SWAP")
+        );
+        assert_eq!(
+            protection_action_label(&ProtectionAction::Freeze),
+            Some("FREEZE")
+        );
+        assert_eq!(protection_action_label(&ProtectionAction::Alert), None);
+        assert_eq!(protection_action_label(&ProtectionAction::Claim), None);
+    }
+
+    #[test]
+    fn auto_swap_cap_scales_with_bps() {
+        assert_eq!(auto_swap_cap(1_000_000, 5_000), 500_000);
+        assert_eq!(auto_swap_cap(1_000_000, 10_000), 1_000_000);
+        assert_eq!(auto_swap_cap(1_000_000, 2_500), 250_000);
+        assert_eq!(auto_swap_cap(1_000_000, 1), 100);
+        // Rounds down, so a tiny balance can cap to nothing
+        assert_eq!(auto_swap_cap(9_999, 1), 0);
+        assert_eq!(auto_swap_cap(u64::MAX, 10_000), u64::MAX);
+    }
+
+    #[test]
+    fn coverage_state_boundaries() {
+        let (start, expiry, grace) = (100, 200, 50);
+        assert_eq!(
+            coverage_state(start - 1, start, expiry, grace),
+            CoverageState::NotStarted
+        );
+        assert_eq!(
+            coverage_state(start, start, expiry, grace),
+            CoverageState::Active
+        );
+        assert_eq!(
+            coverage_state(expiry - 1, start, expiry, grace),
+            CoverageState::Active
+        );
+        assert_eq!(
+            coverage_state(expiry, start, expiry, grace),
+            CoverageState::Grace
+        );
+        assert_eq!(
+            coverage_state(expiry + grace - 1, start, expiry, grace),
+            CoverageState::Grace
+        );
+        assert_eq!(
+            coverage_state(expiry + grace, start, expiry, grace),
+            CoverageState::Expired
+        );
+    }
+
+    #[test]
+    fn coverage_state_without_grace_expires_at_expiry() {
+        assert_eq!(coverage_state(199, 0, 200, 0), CoverageState::Active);
+        assert_eq!(coverage_state(200, 0, 200, 0), CoverageState::Expired);
+        // An oversized grace period saturates instead of overflowing
+        assert_eq!(
+            coverage_state(i64::MAX - 1, 0, i64::MAX - 10, i64::MAX),
+            CoverageState::Grace
+        );
+    }
+
+    #[test]
+    fn cleared_flag_overrides_live_expiry() {
+        assert_eq!(
+            subscription_state(true, 150, 100, 200, 50),
+            CoverageState::Active
+        );
+        assert_eq!(
+            subscription_state(false, 150, 100, 200, 50),
+            CoverageState::Expired
+        );
+        assert_eq!(
+            subscription_state(true, 220, 100, 200, 50),
+            CoverageState::Grace
+        );
+        assert_eq!(
+            subscription_state(true, 250, 100, 200, 50),
+            CoverageState::Expired
+        );
+    }
+
+    #[test]
+    fn batch_size_follows_stride() {
+        // Seven named accounts leave 56 locks: 28 pairs
+        assert_eq!(batch_max_items(7, 2), 28);
+        assert_eq!(batch_items(7, 16, 2).unwrap(), 8);
+        assert_eq!(batch_items(7, 56, 2).unwrap(), 28);
+        assert!(batch_items(7, 58, 2).is_err());
+        assert!(batch_items(7, 15, 2).is_err());
+        assert!(batch_items(7, 0, 2).is_err());
+    }
+
+    #[test]
+    fn rate_limit_caps_uses_per_window() {
+        let limit = RateLimit {
+            window_secs: DAY,
+            max_count: 2,
+        };
+        let mut window = RateWindow::default();
+        let start = 10 * DAY;
+        assert!(consume_rate_limit(&mut window, &limit, start));
+        assert!(consume_rate_limit(&mut window, &limit, start + 60));
+        assert!(!consume_rate_limit(&mut window, &limit, start + DAY - 1));
+        // Refused uses don't count; the window reopens a full window after it started
+        assert_eq!(window.count, 2);
+        assert!(consume_rate_limit(&mut window, &limit, start + DAY));
+        assert_eq!((window.window_start, window.count), (start + DAY, 1));
+    }
+
+    #[test]
+    fn zero_max_count_disables_the_limit() {
+        let mut window = RateWindow::default();
+        for _ in 0..100 {
+            assert!(consume_rate_limit(&mut window, &RateLimit::default(), 0));
+        }
+    }
+
+    #[test]
+    fn coverage_states_map_to_distinct_errors() {
+        let code = |state: CoverageState| match state.require_active(ErrorCode::InsuranceExpired) {
+            Ok(()) => None,
+            Err(Error::AnchorError(err)) => Some(err.error_name),
+            Err(_) => unreachable!(),
+        };
+        assert_eq!(code(CoverageState::Active), None);
+        assert_eq!(
+            code(CoverageState::NotStarted).as_deref(),
+            Some("CoverageNotStarted")
+        );
+        assert_eq!(
+            code(CoverageState::Grace).as_deref(),
+            Some("SubscriptionInGraceOnly")
+        );
+        assert_eq!(
+            code(CoverageState::Expired).as_deref(),
+            Some("InsuranceExpired")
+        );
+    }
+
+    #[test]
+    fn score_hash_chain_proves_each_score() {
+        let scores: Vec<Score> = [40, 55, 61, 70, 38]
+            .iter()
+            .enumerate()
+            .map(|(i, value)| Score {
+                value: *value,
+                timestamp: 1_700_000_000 + i as i64 * 3_600,
+            })
+            .collect();
+        let leaves: Vec<[u8; 32]> = scores.iter().map(score_leaf).collect();
+        // roots[i] is the hash before leaf i; the last is the stored root
+        let mut roots = vec![[0u8; 32]];
+        for leaf in &leaves {
+            roots.push(next_score_root(roots.last().unwrap(), leaf));
+        }
+        let root = roots[5];
+        // Replaying the same sequence gives the same root
+        assert_eq!(
+            leaves
+                .iter()
+                .fold([0u8; 32], |acc, leaf| next_score_root(&acc, leaf)),
+            root
+        );
+
+        for (i, score) in scores.iter().enumerate() {
+            let mut proof = vec![roots[i]];
+            proof.extend_from_slice(&leaves[i + 1..]);
+            assert!(verify_score_inclusion(&root, 5, score, &proof, i as u32));
+            // The same proof fails for a tampered score or another position
+            let tampered = Score {
+                value: score.value + 1,
+                ..score.clone()
+            };
+            assert!(!verify_score_inclusion(
+                &root, 5, &tampered, &proof, i as u32
+            ));
+            assert!(!verify_score_inclusion(
+                &root,
+                5,
+                score,
+                &proof,
+                (i as u32 + 1) % 5
+            ));
+        }
+        assert!(!verify_score_inclusion(&root, 5, &scores[0], &[], 5));
+    }
+
+    #[test]
+    fn governance_staking_needs_a_week_old_subscription() {
+        let min_age = ProtocolConfig::DEFAULT_MIN_SUBSCRIPTION_AGE_FOR_GOVERNANCE_SECS;
+        let now = 100 * DAY;
+        assert!(!subscription_age_reached(now, now - 6 * DAY, min_age));
+        assert!(subscription_age_reached(now, now - 7 * DAY, min_age));
+        assert!(!subscription_age_reached(now, now, min_age));
+    }
+
+    #[test]
+    fn vote_weight_decays_by_stake_age() {
+        let now = 100 * DAY;
+        let weight =
+            |age_days: i64| decayed_vote_weight(1_000, staked_days(now, now - age_days * DAY));
+        assert_eq!((weight(30), weight(90)), (1_000, 1_000));
+        assert_eq!((weight(15), weight(29)), (500, 500));
+        assert_eq!((weight(7), weight(14)), (250, 250));
+        assert_eq!((weight(0), weight(6)), (100, 100));
+        // Partial days don't count
+        assert_eq!(staked_days(now, now - 30 * DAY + 1), 29);
+    }
+
+    #[test]
+    fn top_up_pulls_stake_start_forward() {
+        assert_eq!(blended_stake_start(0, 0, 500, 40 * DAY), 40 * DAY);
+        assert_eq!(blended_stake_start(100, 0, 100, 40 * DAY), 20 * DAY);
+        assert_eq!(blended_stake_start(100, 0, 900, 40 * DAY), 36 * DAY);
+    }
+
+    #[test]
+    fn stream_rate_must_cover_premium_per_period() {
+        let period = 90 * DAY;
+        let premium = 50_000_000;
+        let rate = min_stream_rate(premium, period);
+        // Rounded up, so a period at the minimum rate pays at least the premium
+        assert!(rate * period as u64 >= premium);
+        assert!((rate - 1) * (period as u64) < premium);
+        assert_eq!(min_stream_rate(0, period), 0);
+    }
+
+    #[test]
+    fn keeper_renews_only_near_expiry() {
+        let (expiry, trigger) = (100 * DAY, 3 * DAY);
+        assert!(!stream_renewal_due(expiry - trigger, expiry, trigger));
+        assert!(stream_renewal_due(expiry - trigger + 1, expiry, trigger));
+        assert!(stream_renewal_due(expiry - 1, expiry, trigger));
+        // An expired policy is not renewed
+        assert!(!stream_renewal_due(expiry, expiry, trigger));
+    }
+
+    #[test]
+    fn streamed_payment_extends_by_time_bought() {
+        let rate = 10;
+        let mut expiry = 100 * DAY;
+        // Two days of streaming withdrawn by the keeper extend coverage by two days
+        expiry += stream_extension_secs(2 * DAY as u64 * rate, rate).unwrap();
+        assert_eq!(expiry, 102 * DAY);
+        // A partial second is not credited
+        assert_eq!(stream_extension_secs(19, rate).unwrap(), 1);
+        assert!(stream_extension_secs(100, 0).is_err());
+    }
+
+    #[test]
+    fn layout_upgrade_from_each_version() {
+        // Current layout is 8 + 4 bytes: v1 had 8 + 2, v2 added 1, v3 added 1
+        let growth = [1, 1];
+        let current_len = 12;
+        let v0 = [7u8, 7, 7, 7, 7, 7, 7, 7, 0xa];
+        assert_eq!(stored_layout_version(&v0, current_len, &growth).unwrap(), 0);
+        assert_eq!(
+            upgraded_layout(&v0, &[7; 8], current_len, 3, &growth).unwrap(),
+            [7, 7, 7, 7, 7, 7, 7, 7, 3, 0xa, 0, 0]
+        );
+
+        let v2 = [7u8, 7, 7, 7, 7, 7, 7, 7, 2, 0xa, 0xb];
+        assert_eq!(stored_layout_version(&v2, current_len, &growth).unwrap(), 2);
+        assert_eq!(
+            upgraded_layout(&v2, &[7; 8], current_len, 3, &growth).unwrap(),
+            [7, 7, 7, 7, 7, 7, 7, 7, 3, 0xa, 0xb, 0]
+        );
+
+        let current = [7u8, 7, 7, 7, 7, 7, 7, 7, 3, 0xa, 0xb, 0xc];
+        assert_eq!(
+            upgraded_layout(&current, &[7; 8], current_len, 3, &growth).unwrap(),
+            current
+        );
+    }
+
+    #[test]
+    fn layout_upgrade_rejects_mismatched_data() {
+        let growth = [1, 1];
+        // Version byte says 2 but the length is v1's
+        assert!(stored_layout_version(&[7, 7, 7, 7, 7, 7, 7, 7, 2, 0xa], 12, &growth).is_err());
+        // Version newer than the program knows
+        assert!(stored_layout_version(&[7, 7, 7, 7, 7, 7, 7, 7, 4, 0, 0, 0], 12, &growth).is_err());
+        // Wrong discriminator
+        assert!(upgraded_layout(
+            &[1, 7, 7, 7, 7, 7, 7, 7, 3, 0, 0, 0],
+            &[7; 8],
+            12,
+            3,
+            &growth
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn expiry_is_exclusive() {
+        assert!(!is_expired(99, 100));
+        assert!(is_expired(100, 100));
+        assert!(is_expired(101, 100));
+    }
+
+    #[test]
+    fn coverage_gap_counts_whole_days() {
+        assert_eq!(coverage_gap_days(10 * DAY, 3 * DAY), 7);
+        assert_eq!(coverage_gap_days(10 * DAY, 3 * DAY + 1), 6);
+        assert!(coverage_gap_days(3 * DAY, 10 * DAY) < 0);
+    }
+
+    fn activity(amount: u64) -> ActivityEntry {
+        ActivityEntry {
+            kind: ActivityKind::PayoutReceived,
+            amount,
+            reference: Pubkey::default(),
+            timestamp: amount as i64,
+        }
+    }
+
+    #[test]
+    fn activity_buffer_overwrites_oldest() {
+        let mut buffer = WalletActivity::new(Pubkey::new_unique(), 255);
+        assert!(buffer.recent().is_empty());
+        for amount in 1..=3 {
+            buffer.push(activity(amount));
+        }
+        let amounts =
+            |buffer: &WalletActivity| buffer.recent().iter().map(|e| e.amount).collect::<Vec<_>>();
+        assert_eq!(amounts(&buffer), vec![1, 2, 3]);
+
+        let total = MAX_ACTIVITY_ENTRIES as u64 + 5;
+        for amount in 4..=total {
+            buffer.push(activity(amount));
+        }
+        assert_eq!(buffer.len as usize, MAX_ACTIVITY_ENTRIES);
+        assert_eq!(amounts(&buffer), (6..=total).collect::<Vec<_>>());
+        // The slot after the newest holds the oldest
+        assert_eq!(buffer.entries[buffer.head as usize].amount, 6);
+    }
+
+    #[test]
+    fn activity_entry_encoding() {
+        let reference = Pubkey::new_unique();
+        let entry = ActivityEntry {
+            kind: ActivityKind::ClaimInitiated,
+            amount: 0x0102,
+            reference,
+            timestamp: -1,
+        };
+        let bytes = entry.try_to_vec().unwrap();
+        assert_eq!(bytes.len(), ActivityEntry::INIT_SPACE);
+        assert_eq!(bytes[0], ActivityKind::ClaimInitiated as u8);
+        assert_eq!(bytes[1..9], 0x0102u64.to_le_bytes());
+        assert_eq!(bytes[9..41], reference.to_bytes());
+        assert_eq!(bytes[41..49], (-1i64).to_le_bytes());
+        assert_eq!(ActivityEntry::try_from_slice(&bytes).unwrap(), entry);
+
+        // A fresh buffer fills the account exactly
+        let buffer = WalletActivity::new(reference, 1).try_to_vec().unwrap();
+        assert_eq!(buffer.len(), WalletActivity::INIT_SPACE);
+    }
+
+    #[test]
+    fn watchlist_pause_expires_on_its_own() {
+        let until = 1_000;
+        assert!(watchlist_alerts_suppressed(true, until, until - 1));
+        // From paused_until on the next alert goes through and lifts the pause
+        assert!(!watchlist_alerts_suppressed(true, until, until));
+        assert!(!watchlist_alerts_suppressed(true, until, until + 3_600));
+        assert!(!watchlist_alerts_suppressed(false, until, until - 1));
+    }
+
+    #[test]
+    fn replayed_and_stale_scores_are_told_apart() {
+        let last = 1_700_000_000;
+        assert!(check_score_timestamp(last, None).is_ok());
+        assert!(check_score_timestamp(last + 1, Some(last)).is_ok());
+        assert_eq!(
+            check_score_timestamp(last, Some(last)).unwrap_err(),
+            ErrorCode::ReplayedScore.into()
+        );
+        assert_eq!(
+            check_score_timestamp(last - 1, Some(last)).unwrap_err(),
+            ErrorCode::StaleScore.into()
+        );
+    }
+
+    #[test]
+    fn marketplace_sale_splits_price_between_seller_and_treasury() {
+        let price = 1_000_000;
+        let fee = marketplace_fee(price, ProtocolConfig::DEFAULT_MARKETPLACE_FEE_BPS);
+        assert_eq!(fee, 25_000);
+        assert_eq!(price - fee, 975_000);
+        assert_eq!(marketplace_fee(price, 0), 0);
+        assert_eq!(marketplace_fee(price, 10_000), price);
+        // Never more than the price, even at the extremes
+        assert_eq!(marketplace_fee(u64::MAX, 10_000), u64::MAX);
+        assert_eq!(marketplace_fee(99, 100), 0);
+    }
+
+    #[test]
+    fn listing_expires_after_its_last_second() {
+        let expiry = 1_700_000_000;
+        assert!(listing_open(expiry - 1, expiry));
+        assert!(listing_open(expiry, expiry));
+        assert!(!listing_open(expiry + 1, expiry));
+    }
+
+    #[test]
+    fn slot_anchors_must_be_recent() {
+        let (current, max_age) = (10_000, Claim::SLOT_ANCHOR_MAX_AGE_SLOTS);
+        assert!(slot_anchor_fresh(current, current - 1, max_age));
+        assert!(slot_anchor_fresh(current, current - max_age, max_age));
+        assert!(!slot_anchor_fresh(current, current - max_age - 1, max_age));
+        // The current slot's hash isn't recorded yet, and later ones can't be known
+        assert!(!slot_anchor_fresh(current, current, max_age));
+        assert!(!slot_anchor_fresh(current, current + 1, max_age));
+    }
+
+    fn governance() -> Governance {
+        Governance {
+            authority: Pubkey::new_unique(),
+            quorum: 3,
+            voting_duration: 3 * DAY,
+            bump: 255,
+            protocol_delegate: Pubkey::default(),
+            auto_vote_rules: AutoVoteRules::default(),
+            protocol_delegations: 0,
+            proposal_count: 0,
+            timelock_duration: DAY,
+            emergency_quorum: 5,
+            active_vote_count: 0,
+            protocol_delegated_weight: 0,
+            pending_auto_vote_rules: None,
+            auto_vote_rules_effective_at: 0,
+        }
+    }
+
+    #[test]
+    fn quorum_is_fixed_while_a_vote_is_open() {
+        let mut governance = governance();
+        assert!(governance.require_no_active_votes().is_ok());
+
+        // Two proposals open; resolving one isn't enough
+        governance.active_vote_count += 2;
+        assert_eq!(
+            governance.require_no_active_votes().unwrap_err(),
+            ErrorCode::ActiveVotesPreventParameterChange.into()
+        );
+        governance.close_vote();
+        assert!(governance.require_no_active_votes().is_err());
+        governance.close_vote();
+        assert!(governance.require_no_active_votes().is_ok());
+
+        // Proposals opened before the count existed can't drive it below zero
+        governance.close_vote();
+        assert_eq!(governance.active_vote_count, 0);
+    }
+
+    const RULES: AutoVoteRules = AutoVoteRules {
+        approve_threshold: 50,
+        reject_threshold: 80,
+    };
+
+    #[test]
+    fn auto_vote_rules_approve_low_scores_and_reject_high_ones() {
+        // Below the approve threshold
+        assert_eq!(RULES.decide(0), Some(true));
+        assert_eq!(RULES.decide(49), Some(true));
+        // Between the thresholds the protocol abstains
+        assert_eq!(RULES.decide(50), None);
+        assert_eq!(RULES.decide(65), None);
+        assert_eq!(RULES.decide(79), None);
+        // At or above the reject threshold
+        assert_eq!(RULES.decide(80), Some(false));
+        assert_eq!(RULES.decide(u8::MAX), Some(false));
+
+        // Equal thresholds leave no abstain band
+        let strict = AutoVoteRules {
+            approve_threshold: 60,
+            reject_threshold: 60,
+        };
+        assert_eq!(strict.decide(59), Some(true));
+        assert_eq!(strict.decide(60), Some(false));
+    }
+
+    #[test]
+    fn auto_vote_rule_changes_wait_out_the_timelock_once_delegated() {
+        let stricter = AutoVoteRules {
+            approve_threshold: 20,
+            reject_threshold: 40,
+        };
+        let now = 1_000 * DAY;
+
+        // Nobody has delegated, so new rules apply at once
+        let mut governance = governance();
+        governance.set_auto_vote_rules(RULES, now);
+        assert_eq!(governance.auto_vote_rules_at(now).decide(30), Some(true));
+
+        // With delegations, a change only applies after the timelock
+        governance.protocol_delegations = 1;
+        governance.set_auto_vote_rules(stricter.clone(), now);
+        assert_eq!(governance.auto_vote_rules_at(now).decide(30), Some(true));
+        assert_eq!(
+            governance.auto_vote_rules_at(now + DAY - 1).decide(30),
+            Some(true)
+        );
+        assert_eq!(governance.auto_vote_rules_at(now + DAY).decide(30), None);
+
+        // A later change keeps the rules that had already taken effect
+        governance.set_auto_vote_rules(RULES, now + 2 * DAY);
+        assert_eq!(
+            governance.auto_vote_rules_at(now + 2 * DAY).decide(30),
+            None
+        );
+        assert_eq!(
+            governance.auto_vote_rules_at(now + 3 * DAY).decide(30),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn admin_must_hold_the_upgrade_authority() {
+        let admin = Pubkey::new_unique();
+        assert!(is_upgrade_authority(Some(admin), admin));
+        assert!(!is_upgrade_authority(Some(Pubkey::new_unique()), admin));
+        assert!(!is_upgrade_authority(None, admin));
+    }
+
+    #[test]
+    fn only_moving_actions_need_a_covered_token() {
+        assert!(acts_on_covered_token(&ProtectionAction::Swap));
+        assert!(acts_on_covered_token(&ProtectionAction::Freeze));
+        assert!(!acts_on_covered_token(&ProtectionAction::Alert));
+        assert!(!acts_on_covered_token(&ProtectionAction::Claim));
+
+        let token = Pubkey::new_unique();
+        let list = CoveredTokenList {
+            wallet: Pubkey::new_unique(),
+            covered_tokens: vec![CoveredToken {
+                mint: token,
+                max_amount: 500,
+            }],
+            bump: 255,
+        };
+        assert_eq!(
+            list.get(&token).map(|covered| covered.max_amount),
+            Some(500)
+        );
+        assert!(list.get(&Pubkey::new_unique()).is_none());
+        assert_eq!(list.mints(), vec![token]);
+    }
+
+    #[test]
+    fn signer_rotation_needs_a_majority_less_the_old_key() {
+        assert_eq!(rotation_confirmations_required(1), 1);
+        assert_eq!(rotation_confirmations_required(3), 1);
+        assert_eq!(rotation_confirmations_required(5), 2);
+        assert_eq!(rotation_confirmations_required(7), 3);
+
+        let members: Vec<Pubkey> = (0..5).map(|_| Pubkey::new_unique()).collect();
+        let mut council = EmergencyCouncil {
+            members: members.clone(),
+            bump: 255,
+        };
+        let (old, new) = (members[0], Pubkey::new_unique());
+        // Exactly threshold - 1 confirmations from the other members are enough
+        let confirmations = &members[1..=rotation_confirmations_required(council.members.len())];
+        assert_eq!(confirmations.len(), 2);
+        assert!(council.rotate(&old, new));
+        assert_eq!(council.members[0], new);
+        assert_eq!(council.members[1..], members[1..]);
+        // The old key is gone, so it can't be rotated again
+        assert!(!council.rotate(&old, Pubkey::new_unique()));
+    }
+
+    #[test]
+    fn only_invalidator_oracles_reject_claims() {
+        let (score_oracle, fallback, invalidator) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let registry = OracleRegistry {
+            primary_oracle: score_oracle,
+            primary_heartbeat: 0,
+            fallback_oracles: vec![fallback],
+            fallback_heartbeats: vec![0],
+            current_active_oracle: score_oracle,
+            heartbeat_interval: 60,
+            apy_oracle: Pubkey::new_unique(),
+            oracle_count: 2,
+            bump: 255,
+            invalidator_oracles: vec![invalidator],
+        };
+        assert!(!may_invalidate_claims(registry.role_of(&score_oracle)));
+        assert!(!may_invalidate_claims(registry.role_of(&fallback)));
+        assert!(!may_invalidate_claims(
+            registry.role_of(&registry.apy_oracle)
+        ));
+        assert!(!may_invalidate_claims(
+            registry.role_of(&Pubkey::new_unique())
+        ));
+        assert!(may_invalidate_claims(registry.role_of(&invalidator)));
+
+        assert_eq!(
+            false_claim_penalty(10_000, ProtocolConfig::DEFAULT_FALSE_CLAIM_PENALTY_BPS),
+            500
+        );
+        assert_eq!(false_claim_penalty(u64::MAX, 10_000), u64::MAX);
+    }
+
+    #[test]
+    fn governed_upgrades_relinquish_the_upgrade_authority() {
+        let (deployer, governed) = (Pubkey::new_unique(), Pubkey::new_unique());
+        assert!(!upgrade_authority_relinquished(Some(deployer), governed));
+        assert!(upgrade_authority_relinquished(Some(governed), governed));
+        assert!(upgrade_authority_relinquished(None, governed));
+        // enable_governed_upgrades wants the authority handed over, not burned
+        assert!(is_upgrade_authority(Some(governed), governed));
+        assert!(!is_upgrade_authority(None, governed));
+    }
+}
@@ -0,0 +1,52 @@
+use anchor_lang::prelude::*;
+use anchor_spl::memo::{self, BuildMemo, Memo};
+
+use crate::ErrorCode;
+
+// Kinds of money movement a compliance reference can be attached to
+pub const SUBSCRIBE: &str = "subscribe";
+pub const PREMIUM: &str = "premium";
+pub const PAYOUT: &str = "payout";
+pub const TREASURY_WITHDRAWAL: &str = "treasury_withdrawal";
+
+// Canonical memo for a reference: "IRIS:<kind>:<hex reference>"
+pub fn memo_text(kind: &str, reference: &[u8; 16]) -> String {
+    let hex: String = reference
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!("IRIS:{}:{}", kind, hex)
+}
+
+// Write the memo for `reference` through the SPL Memo program. Movements without a
+// reference log nothing and don't need the program account.
+pub fn attach<'info>(
+    memo_program: Option<&Program<'info, Memo>>,
+    kind: &str,
+    reference: Option<[u8; 16]>,
+) -> Result<()> {
+    let Some(reference) = reference else {
+        return Ok(());
+    };
+    let memo_program = memo_program.ok_or(ErrorCode::MemoProgramMissing)?;
+    memo::build_memo(
+        CpiContext::new(memo_program.to_account_info(), BuildMemo {}),
+        memo_text(kind, &reference).as_bytes(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memo_text_is_canonical() {
+        let mut reference = [0u8; 16];
+        reference[0] = 0xab;
+        reference[15] = 0x01;
+        assert_eq!(
+            memo_text(PAYOUT, &reference),
+            format!("IRIS:payout:ab{}01", "00".repeat(14))
+        );
+    }
+}
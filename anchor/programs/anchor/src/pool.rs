@@ -0,0 +1,827 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    ErrorCode, EventSeq, HaircutModeEntered, InsurancePool, LpBonusConfig, LpPosition, PoolEpoch,
+    TierConfig,
+};
+
+// Fixed-point scale for reward-per-weight. Weights are share-seconds and get large,
+// so this carries more precision than the fee-share accumulator.
+pub const REWARD_PRECISION: u128 = 1_000_000_000_000_000_000;
+
+pub const SECONDS_PER_YEAR: i64 = 365 * 24 * 60 * 60;
+
+// Shares minted on a pool's first deposit that belong to no position. Tokens sent to
+// the vault without minting shares are spread over these too, which makes inflating
+// the share price of a near-empty pool expensive.
+pub const DEAD_SHARES: u64 = 1_000;
+
+// Share supply a pool needs before it takes donations. Below it, a donation moves
+// the share price enough to round later deposits down to nothing.
+pub const MIN_SHARES_FOR_DONATIONS: u64 = DEAD_SHARES * 1_000;
+
+// Everything the pool owes: approved claims awaiting payout plus unpaid shortfalls
+pub fn liabilities(pool: &InsurancePool) -> u64 {
+    pool.approved_liabilities
+        .saturating_add(pool.pending_shortfalls)
+}
+
+// Scale `amount` by `assets / liabilities`, rounding down. A solvent ratio pays in full.
+pub fn pro_rata(amount: u64, assets: u64, liabilities: u64) -> u64 {
+    if liabilities == 0 || assets >= liabilities {
+        return amount;
+    }
+    ((amount as u128) * (assets as u128) / (liabilities as u128)) as u64
+}
+
+// Enter haircut mode when liabilities exceed assets. The ratio is snapshotted on
+// entry so every approved claim is paid the same fraction regardless of ordering.
+pub fn assert_pool_solvency(
+    pool: &mut InsurancePool,
+    assets: u64,
+    timestamp: i64,
+    events: &mut EventSeq,
+) -> bool {
+    if pool.haircut_mode {
+        return true;
+    }
+    let Some(liabilities) = enter_haircut_mode(pool, assets) else {
+        return false;
+    };
+
+    emit_seq!(
+        events,
+        HaircutModeEntered {
+            assets,
+            liabilities,
+            timestamp,
+        }
+    );
+
+    true
+}
+
+// Snapshot the haircut ratio if liabilities exceed assets, returning the liabilities
+fn enter_haircut_mode(pool: &mut InsurancePool, assets: u64) -> Option<u64> {
+    let liabilities = liabilities(pool);
+    if liabilities <= assets {
+        return None;
+    }
+    pool.haircut_mode = true;
+    pool.haircut_assets = assets;
+    pool.haircut_liabilities = liabilities;
+    Some(liabilities)
+}
+
+// The (paid, shortfall) split_payout gives once assert_pool_solvency has run against
+// `assets`, without changing the pool
+pub fn preview_payout(pool: &InsurancePool, amount: u64, assets: u64) -> (u64, u64) {
+    if pool.haircut_mode {
+        return split_payout(pool, amount, assets);
+    }
+    let mut pool = pool.clone();
+    enter_haircut_mode(&mut pool, assets);
+    split_payout(&pool, amount, assets)
+}
+
+// Split a claim into (paid, shortfall), never paying more than the vault holds
+pub fn split_payout(pool: &InsurancePool, amount: u64, available: u64) -> (u64, u64) {
+    let target = if pool.haircut_mode {
+        pro_rata(amount, pool.haircut_assets, pool.haircut_liabilities)
+    } else {
+        amount
+    };
+    let paid = target.min(available);
+    (paid, amount - paid)
+}
+
+// Part of a pool's shortfall the reinsurance tranche covers. The tranche only
+// activates while it holds at least `activation_threshold`, then pays what it can.
+pub fn reinsurance_draw(shortfall: u64, balance: u64, activation_threshold: u64) -> u64 {
+    if balance < activation_threshold {
+        return 0;
+    }
+    shortfall.min(balance)
+}
+
+// Open a top-up round if none is running. Free capital (vault balance not already
+// owed to approved claims) is snapshotted against outstanding shortfalls so each
+// claim in the round is topped up at the same ratio whatever order it is cranked in.
+pub fn open_shortfall_round(pool: &mut InsurancePool, vault_balance: u64) -> bool {
+    if pool.round_remaining > 0 {
+        return true;
+    }
+
+    let free = vault_balance.saturating_sub(pool.approved_liabilities);
+    let budget = free.min(pool.pending_shortfalls);
+    if budget == 0 {
+        return false;
+    }
+
+    pool.shortfall_round += 1;
+    pool.round_assets = budget;
+    pool.round_shortfalls = pool.pending_shortfalls;
+    pool.round_remaining = pool.pending_shortfalls;
+    true
+}
+
+// Amount a claim with `shortfall` outstanding receives in the current round
+pub fn top_up_amount(pool: &InsurancePool, shortfall: u64) -> u64 {
+    pro_rata(shortfall, pool.round_assets, pool.round_shortfalls)
+}
+
+// Everything the pool holds: the vault plus coverage bonds out in yield escrows
+pub fn total_value_locked(pool: &InsurancePool, vault_balance: u64) -> u64 {
+    vault_balance.saturating_add(pool.yield_escrowed)
+}
+
+// Vault balance, plus coverage bonds out in yield escrows, backing LP shares: approved
+// claims, shortfalls and premiums reserved for epoch rewards are all excluded
+pub fn net_assets(pool: &InsurancePool, vault_balance: u64) -> u64 {
+    total_value_locked(pool, vault_balance)
+        .saturating_sub(liabilities(pool))
+        .saturating_sub(pool.reserved_rewards)
+}
+
+// What the pool must hold to stay open for `coverage`, as (expected losses,
+// requirement). Expected losses are coverage scaled by the expected-loss ratio; the
+// requirement adds approved claims and shortfalls, then the buffer on top.
+pub fn solvency_requirement(
+    pool: &InsurancePool,
+    coverage: u64,
+    expected_loss_bps: u16,
+    buffer_bps: u16,
+) -> (u128, u128) {
+    let expected_losses = (coverage as u128) * (expected_loss_bps as u128) / 10_000;
+    let needed = expected_losses + liabilities(pool) as u128;
+    (
+        expected_losses,
+        needed * (10_000 + buffer_bps as u128) / 10_000,
+    )
+}
+
+// Share of the vault committed to claimants and live coverage, in basis points
+// (capped at 100%)
+pub fn utilization_bps(pool: &InsurancePool, vault_balance: u64) -> u16 {
+    let liabilities = liabilities(pool) as u128 + pool.outstanding_coverage as u128;
+    if liabilities == 0 {
+        return 0;
+    }
+    if vault_balance == 0 {
+        return 10_000;
+    }
+    (liabilities * 10_000 / vault_balance as u128).min(10_000) as u16
+}
+
+// Annualized yield of `premiums` earned on `net_assets` over `elapsed` seconds,
+// capped at u16::MAX bps
+pub fn computed_apy_bps(premiums: u64, net_assets: u64, elapsed: i64) -> u16 {
+    if net_assets == 0 || elapsed <= 0 {
+        return 0;
+    }
+    let apy = (premiums as u128) * 10_000 * (SECONDS_PER_YEAR as u128)
+        / ((net_assets as u128) * (elapsed as u128));
+    apy.min(u16::MAX as u128) as u16
+}
+
+// Premium rate for a tier at the given pool utilization. The rate climbs from the
+// base by slope_below_kink as utilization reaches the kink, then by the steeper
+// slope_above_kink over the rest of the range.
+pub fn premium_rate_bps(tier: &TierConfig, utilization_bps: u16) -> u32 {
+    let utilization = (utilization_bps as u32).min(10_000);
+    let kink = tier.kink_utilization_bps as u32;
+    let base = tier.base_rate_bps as u32;
+    let below = tier.slope_below_kink_bps as u32;
+
+    if utilization <= kink {
+        if kink == 0 {
+            return base;
+        }
+        return base + below * utilization / kink;
+    }
+
+    let above_span = 10_000 - kink;
+    base + below + (tier.slope_above_kink_bps as u32) * (utilization - kink) / above_span
+}
+
+// Shares minted for a deposit at the current share price. None when existing shares
+// have no backing, since pricing against zero assets is undefined.
+pub fn shares_for_deposit(amount: u64, net_assets: u64, share_supply: u64) -> Option<u64> {
+    if share_supply == 0 {
+        return Some(amount);
+    }
+    if net_assets == 0 {
+        return None;
+    }
+    u64::try_from((amount as u128) * (share_supply as u128) / (net_assets as u128)).ok()
+}
+
+// Split the shares minted for a deposit into (depositor's shares, dead shares). The
+// first deposit into an empty pool leaves DEAD_SHARES unowned. None when the
+// depositor would receive no shares.
+pub fn depositor_shares(minted: u64, share_supply: u64) -> Option<(u64, u64)> {
+    let dead = if share_supply == 0 { DEAD_SHARES } else { 0 };
+    let shares = minted.checked_sub(dead).filter(|shares| *shares > 0)?;
+    Some((shares, dead))
+}
+
+pub fn accepts_donations(pool: &InsurancePool) -> bool {
+    pool.total_shares >= MIN_SHARES_FOR_DONATIONS
+}
+
+// Bonus rate for a deposit: zero below the threshold, then linear from min to max
+// bonus as utilization climbs from the threshold to 100%
+pub fn deposit_bonus_bps(utilization_bps: u16, curve: &LpBonusConfig) -> u16 {
+    if curve.max_bonus_bps == 0 || utilization_bps <= curve.utilization_threshold_bps {
+        return 0;
+    }
+
+    let span = 10_000u32.saturating_sub(curve.utilization_threshold_bps as u32);
+    let progress = (utilization_bps - curve.utilization_threshold_bps) as u32;
+    let range = (curve.max_bonus_bps - curve.min_bonus_bps) as u32;
+    if span == 0 {
+        return curve.max_bonus_bps;
+    }
+    curve.min_bonus_bps + (range * progress / span) as u16
+}
+
+// Bonus shares on top of `shares`, limited by what is left of the epoch budget
+pub fn bonus_shares(shares: u64, bonus_bps: u16, budget_left: u64) -> u64 {
+    let bonus = ((shares as u128) * (bonus_bps as u128) / 10_000) as u64;
+    bonus.min(budget_left)
+}
+
+// Tokens paid out when burning `shares` at the current share price
+pub fn withdrawal_amount(shares: u64, net_assets: u64, share_supply: u64) -> u64 {
+    if share_supply == 0 {
+        return 0;
+    }
+    ((shares as u128) * (net_assets as u128) / (share_supply as u128)) as u64
+}
+
+// Start a position's withdrawal allowance for the current epoch if its last
+// withdrawal was in an earlier one
+pub fn open_withdrawal_window(pool: &InsurancePool, position: &mut LpPosition) {
+    if position.withdrawal_epoch == pool.current_epoch && position.epoch_start_shares > 0 {
+        return;
+    }
+    position.withdrawal_epoch = pool.current_epoch;
+    position.epoch_start_shares = position.shares;
+    position.withdrawn_this_epoch = 0;
+}
+
+// Shares of a `requested` withdrawal that can be filled this epoch. A position may
+// burn `max_position_bps` of the shares it held when its window opened. With
+// `max_pool_bps` set, total outflow across LPs is also capped at that fraction of the
+// assets the pool held at the start of the epoch.
+pub fn withdrawal_fill(
+    pool: &InsurancePool,
+    position: &LpPosition,
+    requested: u64,
+    max_position_bps: u16,
+    max_pool_bps: u16,
+    net_assets: u64,
+) -> u64 {
+    let position_cap =
+        ((position.epoch_start_shares as u128) * (max_position_bps as u128) / 10_000) as u64;
+    let mut fill = requested.min(position_cap.saturating_sub(position.withdrawn_this_epoch));
+
+    if max_pool_bps > 0 && fill > 0 {
+        let epoch_assets = net_assets as u128 + pool.epoch_withdrawn as u128;
+        let pool_cap = epoch_assets * (max_pool_bps as u128) / 10_000;
+        let assets_left = pool_cap.saturating_sub(pool.epoch_withdrawn as u128);
+        let shares_left = if net_assets == 0 {
+            0
+        } else {
+            assets_left * (pool.total_shares as u128) / (net_assets as u128)
+        };
+        fill = fill.min(shares_left.min(u64::MAX as u128) as u64);
+    }
+    fill
+}
+
+// Fold the time since the last update into the pool's share-seconds for this epoch.
+// Must run before total_shares changes.
+pub fn accrue_pool_weight(pool: &mut InsurancePool, now: i64) -> Result<()> {
+    let elapsed = now.saturating_sub(pool.weight_updated_at).max(0) as u128;
+    pool.epoch_weight = (pool.total_shares as u128)
+        .checked_mul(elapsed)
+        .and_then(|weight| weight.checked_add(pool.epoch_weight))
+        .ok_or(ErrorCode::MathOverflow)?;
+    pool.weight_updated_at = now;
+    Ok(())
+}
+
+// Close the current epoch, making premiums accrued since the last close claimable
+// pro rata to share-seconds. Returns (premiums distributed, reward per weight).
+// With no weight in the epoch the premiums carry over to the next one.
+pub fn close_epoch(pool: &mut InsurancePool, now: i64) -> Result<(u64, u128)> {
+    let weight = pool.epoch_weight;
+    let length = now.saturating_sub(pool.epoch_started_at).max(0) as u128;
+
+    let mut distributed = 0;
+    let mut reward_per_weight = 0;
+    if weight > 0 && pool.premiums_accrued > 0 {
+        reward_per_weight = (pool.premiums_accrued as u128)
+            .checked_mul(REWARD_PRECISION)
+            .ok_or(ErrorCode::MathOverflow)?
+            / weight;
+        // Rounding dust stays in premiums_accrued for the next epoch
+        distributed = (reward_per_weight * weight / REWARD_PRECISION) as u64;
+        pool.premiums_accrued -= distributed;
+        pool.acc_reward_per_share = reward_per_weight
+            .checked_mul(length)
+            .and_then(|increment| increment.checked_add(pool.acc_reward_per_share))
+            .ok_or(ErrorCode::MathOverflow)?;
+    }
+
+    pool.current_epoch += 1;
+    pool.epoch_started_at = now;
+    pool.epoch_withdrawn = 0;
+    pool.epoch_weight = 0;
+    pool.weight_updated_at = now;
+    Ok((distributed, reward_per_weight))
+}
+
+// Bring a position's rewards and epoch weight up to `now`. Must run before its
+// shares change. When the epoch the position last touched has since closed, `closed`
+// must be that epoch's record: the position earns its partial weight in that epoch,
+// then its full share balance for every whole epoch after it.
+pub fn settle_position(
+    pool: &InsurancePool,
+    position: &mut LpPosition,
+    closed: Option<&PoolEpoch>,
+    now: i64,
+) -> Result<()> {
+    let shares = position.shares as u128;
+
+    if position.last_update_epoch < pool.current_epoch {
+        let record = closed.ok_or(ErrorCode::EpochRecordRequired)?;
+        require!(
+            record.pool == position.pool && record.epoch == position.last_update_epoch,
+            ErrorCode::EpochRecordMismatch
+        );
+
+        let tail = record
+            .closed_at
+            .saturating_sub(position.last_update_ts)
+            .max(0) as u128;
+        let partial = shares
+            .checked_mul(tail)
+            .and_then(|weight| weight.checked_add(position.epoch_weight))
+            .and_then(|weight| weight.checked_mul(record.reward_per_weight))
+            .ok_or(ErrorCode::MathOverflow)?
+            / REWARD_PRECISION;
+        let whole = shares
+            .checked_mul(pool.acc_reward_per_share - record.acc_reward_per_share)
+            .ok_or(ErrorCode::MathOverflow)?
+            / REWARD_PRECISION;
+
+        position.pending_rewards = u64::try_from(partial + whole)
+            .ok()
+            .and_then(|earned| earned.checked_add(position.pending_rewards))
+            .ok_or(ErrorCode::MathOverflow)?;
+        position.last_update_epoch = pool.current_epoch;
+        position.epoch_weight = shares * now.saturating_sub(pool.epoch_started_at).max(0) as u128;
+    } else {
+        let elapsed = now.saturating_sub(position.last_update_ts).max(0) as u128;
+        position.epoch_weight = shares
+            .checked_mul(elapsed)
+            .and_then(|weight| weight.checked_add(position.epoch_weight))
+            .ok_or(ErrorCode::MathOverflow)?;
+    }
+
+    position.last_update_ts = now;
+    Ok(())
+}
+
+// (premium rate, premium) for a policy of `payout_cap` on `portfolio_value` lasting
+// `duration` seconds, at the pool's current utilization
+pub fn quote_premium(
+    pool: &InsurancePool,
+    vault_balance: u64,
+    tier: &TierConfig,
+    payout_cap: u64,
+    portfolio_value: u64,
+    duration: u64,
+) -> Result<(u32, u64)> {
+    let rate_bps = premium_rate_bps(tier, utilization_bps(pool, vault_balance));
+    Ok((
+        rate_bps,
+        crate::logic::compute_tier_price(rate_bps, payout_cap, portfolio_value, duration)?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tier() -> TierConfig {
+        TierConfig {
+            base_rate_bps: 200,
+            kink_utilization_bps: 8_000,
+            slope_below_kink_bps: 400,
+            slope_above_kink_bps: 6_000,
+        }
+    }
+
+    #[test]
+    fn pro_rata_pays_in_full_when_solvent() {
+        assert_eq!(pro_rata(1_000, 500, 0), 1_000);
+        assert_eq!(pro_rata(1_000, 2_000, 1_000), 1_000);
+        assert_eq!(pro_rata(1_000, 750, 1_000), 750);
+    }
+
+    fn empty_pool() -> InsurancePool {
+        InsurancePool::try_deserialize_unchecked(&mut &[0u8; 8 + InsurancePool::INIT_SPACE][..])
+            .unwrap()
+    }
+
+    fn enter_haircut(pool: &mut InsurancePool, assets: u64) -> bool {
+        let mut config = crate::ProtocolConfig::try_deserialize_unchecked(
+            &mut &[0u8; 8 + crate::ProtocolConfig::INIT_SPACE][..],
+        )
+        .unwrap();
+        let mut events = crate::next_event_seq(&mut config).unwrap();
+        assert_pool_solvency(pool, assets, 0, &mut events)
+    }
+
+    // Pay `claims` in order from a vault holding `assets`, as execute_payout does
+    fn pay_in_order(pool: &InsurancePool, claims: &[u64], mut assets: u64) -> Vec<(u64, u64)> {
+        claims
+            .iter()
+            .map(|&amount| {
+                let (paid, shortfall) = split_payout(pool, amount, assets);
+                assets -= paid;
+                (paid, shortfall)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn solvent_pool_stays_out_of_haircut_mode() {
+        let mut pool = empty_pool();
+        pool.approved_liabilities = 5_000;
+        assert!(!enter_haircut(&mut pool, 5_000));
+        assert!(!pool.haircut_mode);
+        assert_eq!(split_payout(&pool, 5_000, 5_000), (5_000, 0));
+    }
+
+    #[test]
+    fn haircut_pays_every_claim_the_same_ratio_in_any_order() {
+        let mut pool = empty_pool();
+        pool.approved_liabilities = 10_000;
+        assert!(enter_haircut(&mut pool, 5_000));
+        assert_eq!(
+            (pool.haircut_assets, pool.haircut_liabilities),
+            (5_000, 10_000)
+        );
+
+        assert_eq!(
+            pay_in_order(&pool, &[6_000, 4_000], 5_000),
+            vec![(3_000, 3_000), (2_000, 2_000)]
+        );
+        assert_eq!(
+            pay_in_order(&pool, &[4_000, 6_000], 5_000),
+            vec![(2_000, 2_000), (3_000, 3_000)]
+        );
+    }
+
+    #[test]
+    fn haircut_ratio_is_not_moved_by_later_deposits_or_claims() {
+        let mut pool = empty_pool();
+        pool.approved_liabilities = 10_000;
+        assert!(enter_haircut(&mut pool, 5_000));
+
+        // Capital arriving and new approvals after entry leave the snapshot alone
+        pool.approved_liabilities = 30_000;
+        assert!(enter_haircut(&mut pool, 50_000));
+        assert_eq!(
+            (pool.haircut_assets, pool.haircut_liabilities),
+            (5_000, 10_000)
+        );
+        assert_eq!(split_payout(&pool, 4_000, 50_000), (2_000, 2_000));
+        // And a drained vault caps the payout below the ratio
+        assert_eq!(split_payout(&pool, 4_000, 500), (500, 3_500));
+    }
+
+    #[test]
+    fn top_up_round_splits_free_capital_at_one_ratio() {
+        let mut pool = empty_pool();
+        pool.pending_shortfalls = 5_000;
+        pool.approved_liabilities = 1_000;
+        // Capital owed to approved claims isn't free for top-ups
+        assert!(!open_shortfall_round(&mut pool, 1_000));
+        assert!(open_shortfall_round(&mut pool, 3_500));
+        assert_eq!(
+            (
+                pool.shortfall_round,
+                pool.round_assets,
+                pool.round_shortfalls
+            ),
+            (1, 2_500, 5_000)
+        );
+
+        // Claims cranked in either order get half their shortfall
+        assert_eq!(top_up_amount(&pool, 3_000), 1_500);
+        assert_eq!(top_up_amount(&pool, 2_000), 1_000);
+
+        // The round stays open, at the same ratio, until every shortfall in it is cranked
+        pool.round_remaining -= 3_000;
+        pool.pending_shortfalls -= 1_500;
+        assert!(open_shortfall_round(&mut pool, 100_000));
+        assert_eq!(
+            (pool.shortfall_round, top_up_amount(&pool, 2_000)),
+            (1, 1_000)
+        );
+        pool.round_remaining -= 2_000;
+        pool.pending_shortfalls -= 1_000;
+
+        // The next round snapshots the capital that has arrived since
+        assert!(open_shortfall_round(&mut pool, 100_000));
+        assert_eq!(
+            (
+                pool.shortfall_round,
+                pool.round_assets,
+                pool.round_shortfalls
+            ),
+            (2, 2_500, 2_500)
+        );
+        assert_eq!(top_up_amount(&pool, 1_500), 1_500);
+    }
+
+    #[test]
+    fn no_round_without_shortfalls() {
+        let mut pool = empty_pool();
+        assert!(!open_shortfall_round(&mut pool, 10_000));
+        assert_eq!(pool.shortfall_round, 0);
+    }
+
+    #[test]
+    fn reinsurance_covers_what_the_pool_cannot() {
+        // The pool holds 10% of a 10_000 claim, leaving a 9_000 shortfall
+        let pool = InsurancePool::try_deserialize_unchecked(
+            &mut &[0u8; 8 + InsurancePool::INIT_SPACE][..],
+        )
+        .unwrap();
+        let (paid, shortfall) = split_payout(&pool, 10_000, 1_000);
+        assert_eq!((paid, shortfall), (1_000, 9_000));
+        // A funded tranche pays the rest, a thin one pays what it holds
+        assert_eq!(reinsurance_draw(shortfall, 50_000, 20_000), 9_000);
+        assert_eq!(reinsurance_draw(shortfall, 6_000, 5_000), 6_000);
+        // Below its activation threshold the tranche stays out
+        assert_eq!(reinsurance_draw(shortfall, 19_999, 20_000), 0);
+        assert_eq!(reinsurance_draw(0, 50_000, 20_000), 0);
+    }
+
+    #[test]
+    fn payout_preview_matches_execution() {
+        let mut pool = InsurancePool::try_deserialize_unchecked(
+            &mut &[0u8; 8 + InsurancePool::INIT_SPACE][..],
+        )
+        .unwrap();
+        pool.approved_liabilities = 10_000;
+        let assets = 5_000;
+        // Previewing leaves the pool solvent-looking, but quotes the haircut
+        let preview = preview_payout(&pool, 4_000, assets);
+        assert_eq!(preview, (2_000, 2_000));
+        assert!(!pool.haircut_mode);
+
+        let mut config = crate::ProtocolConfig::try_deserialize_unchecked(
+            &mut &[0u8; 8 + crate::ProtocolConfig::INIT_SPACE][..],
+        )
+        .unwrap();
+        let mut events = crate::next_event_seq(&mut config).unwrap();
+        assert!(assert_pool_solvency(&mut pool, assets, 0, &mut events));
+        assert_eq!(split_payout(&pool, 4_000, assets), preview);
+        // Once in haircut mode the snapshot holds whatever the vault now has
+        assert_eq!(
+            preview_payout(&pool, 4_000, 3_000),
+            split_payout(&pool, 4_000, 3_000)
+        );
+        assert_eq!(preview_payout(&pool, 4_000, 3_000), (2_000, 2_000));
+    }
+
+    #[test]
+    fn premium_quote_prices_at_current_utilization() {
+        let mut pool = InsurancePool::try_deserialize_unchecked(
+            &mut &[0u8; 8 + InsurancePool::INIT_SPACE][..],
+        )
+        .unwrap();
+        pool.approved_liabilities = 4_000;
+        let (rate_bps, premium) =
+            quote_premium(&pool, 10_000, &tier(), 1_000, 1_000, 1_000).unwrap();
+        assert_eq!(rate_bps, premium_rate_bps(&tier(), 4_000));
+        assert_eq!(
+            premium,
+            crate::logic::compute_tier_price(rate_bps, 1_000, 1_000, 1_000).unwrap()
+        );
+    }
+
+    #[test]
+    fn utilization_counts_outstanding_coverage() {
+        let mut pool = InsurancePool::try_deserialize_unchecked(
+            &mut &[0u8; 8 + InsurancePool::INIT_SPACE][..],
+        )
+        .unwrap();
+        pool.outstanding_coverage = 3_000;
+        assert_eq!(utilization_bps(&pool, 10_000), 3_000);
+        pool.approved_liabilities = 1_000;
+        assert_eq!(utilization_bps(&pool, 10_000), 4_000);
+    }
+
+    #[test]
+    fn first_deposit_mints_one_share_per_token() {
+        assert_eq!(shares_for_deposit(5_000, 0, 0), Some(5_000));
+    }
+
+    #[test]
+    fn deposit_and_withdrawal_follow_share_price() {
+        // 1_000 shares backed by 2_000 tokens: each share is worth 2
+        assert_eq!(shares_for_deposit(500, 2_000, 1_000), Some(250));
+        assert_eq!(withdrawal_amount(250, 2_000, 1_000), 500);
+        assert_eq!(withdrawal_amount(10, 0, 0), 0);
+    }
+
+    #[test]
+    fn deposit_against_unbacked_shares_is_refused() {
+        assert_eq!(shares_for_deposit(500, 0, 1_000), None);
+    }
+
+    #[test]
+    fn first_deposit_locks_dead_shares() {
+        assert_eq!(
+            depositor_shares(5_000, 0),
+            Some((5_000 - DEAD_SHARES, DEAD_SHARES))
+        );
+        assert_eq!(depositor_shares(DEAD_SHARES, 0), None);
+        assert_eq!(depositor_shares(250, 1_000), Some((250, 0)));
+    }
+
+    #[test]
+    fn deposit_rounding_to_zero_shares_is_refused() {
+        // 1_000 shares backed by 1_000_000 tokens: 999 tokens buy nothing
+        assert_eq!(shares_for_deposit(999, 1_000_000, 1_000), Some(0));
+        assert_eq!(depositor_shares(0, 1_000), None);
+    }
+
+    #[test]
+    fn donations_wait_for_share_supply() {
+        let mut pool = InsurancePool::try_deserialize_unchecked(
+            &mut &[0u8; 8 + InsurancePool::INIT_SPACE][..],
+        )
+        .unwrap();
+        assert!(!accepts_donations(&pool));
+        pool.total_shares = DEAD_SHARES + 1;
+        assert!(!accepts_donations(&pool));
+        pool.total_shares = MIN_SHARES_FOR_DONATIONS;
+        assert!(accepts_donations(&pool));
+    }
+
+    #[test]
+    fn apy_annualizes_premiums() {
+        // 10% of net assets earned over half a year is 20% a year
+        assert_eq!(computed_apy_bps(100, 1_000, SECONDS_PER_YEAR / 2), 2_000);
+        assert_eq!(computed_apy_bps(100, 0, SECONDS_PER_YEAR), 0);
+        assert_eq!(computed_apy_bps(100, 1_000, 0), 0);
+        assert_eq!(computed_apy_bps(u64::MAX, 1, 1), u16::MAX);
+    }
+
+    #[test]
+    fn premium_rate_steepens_past_the_kink() {
+        let tier = tier();
+        assert_eq!(premium_rate_bps(&tier, 0), 200);
+        assert_eq!(premium_rate_bps(&tier, 4_000), 400);
+        assert_eq!(premium_rate_bps(&tier, 8_000), 600);
+        assert_eq!(premium_rate_bps(&tier, 9_000), 3_600);
+        assert_eq!(premium_rate_bps(&tier, 10_000), 6_600);
+    }
+
+    #[test]
+    fn deposit_bonus_ramps_above_threshold() {
+        let curve = LpBonusConfig {
+            utilization_threshold_bps: 6_000,
+            min_bonus_bps: 100,
+            max_bonus_bps: 500,
+            ..Default::default()
+        };
+        assert_eq!(deposit_bonus_bps(6_000, &curve), 0);
+        assert_eq!(deposit_bonus_bps(8_000, &curve), 300);
+        assert_eq!(deposit_bonus_bps(10_000, &curve), 500);
+    }
+
+    #[test]
+    fn bonus_shares_respect_the_budget() {
+        assert_eq!(bonus_shares(10_000, 500, u64::MAX), 500);
+        assert_eq!(bonus_shares(10_000, 500, 120), 120);
+    }
+
+    fn position(shares: u64) -> LpPosition {
+        let mut position =
+            LpPosition::try_deserialize_unchecked(&mut &[0u8; 8 + LpPosition::INIT_SPACE][..])
+                .unwrap();
+        position.shares = shares;
+        position
+    }
+
+    // Request `shares` on top of the queue and burn what the limits allow, as
+    // withdraw_lp_shares does, with one share worth one token. Returns the filled shares.
+    fn withdraw(
+        pool: &mut InsurancePool,
+        position: &mut LpPosition,
+        shares: u64,
+        max_pool_bps: u16,
+    ) -> u64 {
+        let requested = position.queued_withdrawal + shares;
+        open_withdrawal_window(pool, position);
+        let filled = withdrawal_fill(
+            pool,
+            position,
+            requested,
+            2_500,
+            max_pool_bps,
+            pool.total_shares,
+        );
+        position.queued_withdrawal = requested - filled;
+        position.shares -= filled;
+        position.withdrawn_this_epoch += filled;
+        pool.total_shares -= filled;
+        pool.epoch_withdrawn += filled;
+        filled
+    }
+
+    fn next_epoch(pool: &mut InsurancePool) {
+        pool.current_epoch += 1;
+        pool.epoch_withdrawn = 0;
+    }
+
+    #[test]
+    fn oversized_withdrawals_fill_partially_and_queue_the_rest() {
+        let mut pool = empty_pool();
+        pool.total_shares = 10_000;
+        let mut lp = position(1_000);
+
+        // A quarter of the position per epoch; the rest waits
+        assert_eq!(withdraw(&mut pool, &mut lp, 400, 0), 250);
+        assert_eq!(lp.queued_withdrawal, 150);
+        // Nothing more until the epoch advances, and new requests join the queue
+        assert_eq!(withdraw(&mut pool, &mut lp, 50, 0), 0);
+        assert_eq!(lp.queued_withdrawal, 200);
+
+        next_epoch(&mut pool);
+        assert_eq!(withdraw(&mut pool, &mut lp, 0, 0), 187);
+        assert_eq!(lp.queued_withdrawal, 13);
+        next_epoch(&mut pool);
+        assert_eq!(withdraw(&mut pool, &mut lp, 0, 0), 13);
+        assert_eq!(lp.queued_withdrawal, 0);
+        assert_eq!(lp.shares, 550);
+    }
+
+    #[test]
+    fn withdrawal_window_resets_when_the_epoch_advances() {
+        let mut pool = empty_pool();
+        pool.total_shares = 10_000;
+        let mut lp = position(1_000);
+        withdraw(&mut pool, &mut lp, 100, 0);
+
+        // The same epoch keeps the allowance measured from the opening balance
+        open_withdrawal_window(&pool, &mut lp);
+        assert_eq!(
+            (lp.epoch_start_shares, lp.withdrawn_this_epoch),
+            (1_000, 100)
+        );
+
+        next_epoch(&mut pool);
+        open_withdrawal_window(&pool, &mut lp);
+        assert_eq!(lp.withdrawal_epoch, 1);
+        assert_eq!((lp.epoch_start_shares, lp.withdrawn_this_epoch), (900, 0));
+    }
+
+    #[test]
+    fn pool_cap_limits_outflow_across_positions() {
+        let mut pool = empty_pool();
+        pool.total_shares = 4_000;
+        let (mut first, mut second) = (position(2_000), position(2_000));
+
+        // 10% of the epoch's assets in total, whoever takes it
+        assert_eq!(withdraw(&mut pool, &mut first, 500, 1_000), 400);
+        assert_eq!(withdraw(&mut pool, &mut second, 500, 1_000), 0);
+        assert_eq!(second.queued_withdrawal, 500);
+
+        next_epoch(&mut pool);
+        assert_eq!(withdraw(&mut pool, &mut second, 0, 1_000), 360);
+    }
+
+    #[test]
+    fn lockup_gates_withdrawals_until_it_elapses() {
+        let mut lp = position(1_000);
+        lp.deposit_timestamp = 1_000;
+        lp.lockup_period = 7 * 24 * 60 * 60;
+        assert!(!lp.lockup_elapsed(1_000));
+        assert!(!lp.lockup_elapsed(1_000 + lp.lockup_period - 1));
+        assert!(lp.lockup_elapsed(1_000 + lp.lockup_period));
+        // A lockup that would overflow never elapses
+        lp.lockup_period = i64::MAX;
+        assert!(!lp.lockup_elapsed(i64::MAX - 1));
+    }
+}
@@ -0,0 +1,74 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar;
+
+// The SlotHashes sysvar, read from its raw data: the runtime won't deserialize it
+// on-chain. It is a u64 entry count followed by (slot, hash) entries, newest first.
+pub const SLOT_HASHES_ID: Pubkey = sysvar::slot_hashes::ID;
+
+const COUNT_LEN: usize = 8;
+const ENTRY_LEN: usize = 8 + 32;
+
+// Hash the sysvar records for `slot`, if it is still in the window
+pub fn find(data: &[u8], slot: u64) -> Option<[u8; 32]> {
+    let count = u64::from_le_bytes(data.get(..COUNT_LEN)?.try_into().ok()?) as usize;
+    let entries = data.get(COUNT_LEN..COUNT_LEN + count.checked_mul(ENTRY_LEN)?)?;
+    let entry_slot = |i: usize| {
+        u64::from_le_bytes(
+            entries[i * ENTRY_LEN..i * ENTRY_LEN + 8]
+                .try_into()
+                .unwrap(),
+        )
+    };
+
+    // Slots descend, so search for the first entry at or below `slot`
+    let (mut low, mut high) = (0, count);
+    while low < high {
+        let mid = (low + high) / 2;
+        if entry_slot(mid) > slot {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    if low == count || entry_slot(low) != slot {
+        return None;
+    }
+    entries[low * ENTRY_LEN + 8..(low + 1) * ENTRY_LEN]
+        .try_into()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Sysvar data for the given slots, newest first, each hashed to [slot as u8; 32]
+    fn sysvar_data(slots: &[u64]) -> Vec<u8> {
+        let mut data = (slots.len() as u64).to_le_bytes().to_vec();
+        for slot in slots {
+            data.extend_from_slice(&slot.to_le_bytes());
+            data.extend_from_slice(&[*slot as u8; 32]);
+        }
+        data
+    }
+
+    #[test]
+    fn finds_recorded_slots() {
+        // Skipped slots have no entry
+        let data = sysvar_data(&[110, 109, 107, 104, 100]);
+        for slot in [110, 109, 107, 104, 100] {
+            assert_eq!(find(&data, slot), Some([slot as u8; 32]));
+        }
+        for slot in [111, 108, 101, 99, 0] {
+            assert_eq!(find(&data, slot), None);
+        }
+    }
+
+    #[test]
+    fn rejects_truncated_data() {
+        let data = sysvar_data(&[5, 4]);
+        assert_eq!(find(&data[..data.len() - 1], 4), None);
+        assert_eq!(find(&[1, 0, 0], 4), None);
+        assert_eq!(find(&sysvar_data(&[]), 0), None);
+    }
+}
@@ -0,0 +1,109 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::program::invoke_signed;
+
+// Switchboard V2, which hosts the public pool TVL feed. The publish instruction is
+// built by hand, like the compression ones, since switchboard-v2 pins its own
+// anchor-lang.
+pub const SWITCHBOARD_V2_ID: Pubkey = pubkey!("SW1TCH7qEPTdLsDHRgPuMQjbQxKdH2aBStViMFnt64f");
+
+// Seed of the PDA set as the feed's publishing authority
+pub const PUBLISHER_SEED: &[u8] = b"tvl_publisher";
+
+// Switchboard's fixed-point value: mantissa * 10^-scale
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct SwitchboardDecimal {
+    pub mantissa: i128,
+    pub scale: u32,
+}
+
+// Arguments of the publish call: the value and when it was observed
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct PublishArgs {
+    pub value: SwitchboardDecimal,
+    pub timestamp: i64,
+}
+
+// Publish `value` (payout-mint base units, so scale 0) to `feed`, signed by `publisher`
+pub fn publish_instruction(
+    program_id: Pubkey,
+    feed: Pubkey,
+    publisher: Pubkey,
+    value: u64,
+    timestamp: i64,
+) -> Result<Instruction> {
+    let mut data = hash(b"global:publish").to_bytes()[..8].to_vec();
+    PublishArgs {
+        value: SwitchboardDecimal {
+            mantissa: value as i128,
+            scale: 0,
+        },
+        timestamp,
+    }
+    .serialize(&mut data)?;
+    Ok(Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(feed, false),
+            AccountMeta::new_readonly(publisher, true),
+        ],
+        data,
+    })
+}
+
+pub fn publish<'info>(
+    switchboard_program: &AccountInfo<'info>,
+    feed: &AccountInfo<'info>,
+    publisher: &AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+    value: u64,
+    timestamp: i64,
+) -> Result<()> {
+    invoke_signed(
+        &publish_instruction(
+            switchboard_program.key(),
+            feed.key(),
+            publisher.key(),
+            value,
+            timestamp,
+        )?,
+        &[feed.clone(), publisher.clone(), switchboard_program.clone()],
+        signer_seeds,
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn publish_targets_the_feed_as_its_authority() {
+        // Stand-in publisher program; nothing here depends on the real id
+        let publisher_program = Pubkey::new_unique();
+        let feed = Pubkey::new_unique();
+        let (publisher, _) = Pubkey::find_program_address(&[PUBLISHER_SEED], &crate::ID);
+
+        let ix = publish_instruction(publisher_program, feed, publisher, 1_250_000, 3_600).unwrap();
+        assert_eq!(ix.program_id, publisher_program);
+        assert_eq!(
+            ix.accounts,
+            vec![
+                AccountMeta::new(feed, false),
+                AccountMeta::new_readonly(publisher, true)
+            ]
+        );
+
+        assert_eq!(ix.data[..8], hash(b"global:publish").to_bytes()[..8]);
+        let args = PublishArgs::try_from_slice(&ix.data[8..]).unwrap();
+        assert_eq!(
+            args.value,
+            SwitchboardDecimal {
+                mantissa: 1_250_000,
+                scale: 0
+            }
+        );
+        assert_eq!(args.timestamp, 3_600);
+    }
+}
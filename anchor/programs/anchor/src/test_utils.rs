@@ -0,0 +1,2245 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::address_lookup_table::AddressLookupTableAccount;
+use anchor_lang::solana_program::entrypoint::ProgramResult;
+use anchor_lang::solana_program::hash::Hash;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::message::{v0, Message, VersionedMessage};
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_lang::solana_program::program_stubs;
+use anchor_lang::solana_program::program_utils::limited_deserialize;
+use anchor_lang::solana_program::system_instruction::SystemInstruction;
+use anchor_lang::solana_program::sysvar::instructions::{
+    self as sysvar_instructions, construct_instructions_data, store_current_index,
+    BorrowedInstruction,
+};
+use anchor_lang::{AccountSerialize, Accounts, Bumps, InstructionData, ToAccountMetas};
+use anchor_spl::token::spl_token;
+use anchor_spl::token::spl_token::instruction::TokenInstruction;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeSet;
+use std::sync::Once;
+
+// Largest serialized transaction the network accepts
+pub const PACKET_DATA_SIZE: usize = 1232;
+
+// Serialized size of a single-signer transaction: signature count, signature, message
+fn transaction_size(message: &VersionedMessage) -> usize {
+    1 + 64 + message.serialize().len()
+}
+
+// v0 transaction for `instructions` with the per-item accounts in `lookup_table`, and
+// its serialized size. Only accounts the lookup table doesn't hold are listed inline.
+pub fn v0_transaction(
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    lookup_table: Vec<Pubkey>,
+) -> (VersionedMessage, usize) {
+    let table = AddressLookupTableAccount {
+        key: Pubkey::new_unique(),
+        addresses: lookup_table,
+    };
+    let message = VersionedMessage::V0(
+        v0::Message::try_compile(payer, instructions, &[table], Hash::default()).unwrap(),
+    );
+    let size = transaction_size(&message);
+    (message, size)
+}
+
+// Serialized size of the same instructions as a legacy transaction
+pub fn legacy_transaction_size(payer: &Pubkey, instructions: &[Instruction]) -> usize {
+    transaction_size(&VersionedMessage::Legacy(Message::new(
+        instructions,
+        Some(payer),
+    )))
+}
+
+// top_up_shortfalls over `items` (claim, claimant token account) pairs
+pub fn top_up_shortfalls_ix(items: usize) -> (Instruction, Vec<Pubkey>) {
+    let (event_authority, _) = Pubkey::find_program_address(&[b"__event_authority"], &crate::ID);
+    let mut accounts = crate::accounts::TopUpShortfalls {
+        pool: Pubkey::new_unique(),
+        config: Pubkey::new_unique(),
+        vault: Pubkey::new_unique(),
+        payout_mint: Pubkey::new_unique(),
+        token_program: anchor_spl::token::ID,
+        event_authority,
+        program: crate::ID,
+    }
+    .to_account_metas(None);
+
+    let per_item: Vec<Pubkey> = (0..items * crate::ProtocolConfig::TOP_UP_BATCH_STRIDE)
+        .map(|_| Pubkey::new_unique())
+        .collect();
+    accounts.extend(per_item.iter().map(|key| AccountMeta::new(*key, false)));
+    let ix = Instruction {
+        program_id: crate::ID,
+        accounts,
+        data: crate::instruction::TopUpShortfalls {}.data(),
+    };
+    (ix, per_item)
+}
+
+// An account as an instruction would receive it, for running a context's account
+// validation without a runtime
+pub struct TestAccount {
+    pub key: Pubkey,
+    pub owner: Pubkey,
+    pub lamports: u64,
+    pub data: Vec<u8>,
+    pub is_signer: bool,
+    pub is_writable: bool,
+    pub executable: bool,
+}
+
+impl TestAccount {
+    // One of this program's accounts holding `account`
+    pub fn program_owned<T: AccountSerialize>(key: Pubkey, account: &T) -> Self {
+        let mut data = Vec::new();
+        account.try_serialize(&mut data).unwrap();
+        Self::with_data(key, crate::ID, data)
+    }
+
+    pub fn token_account(key: Pubkey, mint: Pubkey, owner: Pubkey, amount: u64) -> Self {
+        let account = spl_token::state::Account {
+            mint,
+            owner,
+            amount,
+            state: spl_token::state::AccountState::Initialized,
+            ..Default::default()
+        };
+        let mut data = vec![0; spl_token::state::Account::LEN];
+        account.pack_into_slice(&mut data);
+        Self::with_data(key, spl_token::ID, data)
+    }
+
+    pub fn mint(key: Pubkey) -> Self {
+        let mint = spl_token::state::Mint {
+            decimals: 6,
+            is_initialized: true,
+            ..Default::default()
+        };
+        let mut data = vec![0; spl_token::state::Mint::LEN];
+        mint.pack_into_slice(&mut data);
+        Self::with_data(key, spl_token::ID, data)
+    }
+
+    pub fn signer(key: Pubkey) -> Self {
+        Self {
+            is_signer: true,
+            ..Self::with_data(key, anchor_lang::system_program::ID, Vec::new())
+        }
+    }
+
+    // Not yet created: no lamports or data, owned by the system program
+    pub fn empty(key: Pubkey) -> Self {
+        Self {
+            lamports: 0,
+            ..Self::with_data(key, anchor_lang::system_program::ID, Vec::new())
+        }
+    }
+
+    pub fn program(id: Pubkey) -> Self {
+        Self {
+            is_writable: false,
+            executable: true,
+            ..Self::with_data(
+                id,
+                anchor_lang::solana_program::bpf_loader_upgradeable::ID,
+                Vec::new(),
+            )
+        }
+    }
+
+    // Instructions sysvar for a transaction running `before` and then the instruction
+    // under test, which is the current one
+    pub fn instructions(before: &[Instruction]) -> Self {
+        let current = Instruction::new_with_bytes(crate::ID, &[], Vec::new());
+        let borrowed: Vec<BorrowedInstruction> = before
+            .iter()
+            .chain(std::iter::once(&current))
+            .map(|ix| BorrowedInstruction {
+                program_id: &ix.program_id,
+                accounts: Vec::new(),
+                data: &ix.data,
+            })
+            .collect();
+        let mut data = construct_instructions_data(&borrowed);
+        store_current_index(&mut data, before.len() as u16);
+        Self {
+            is_writable: false,
+            ..Self::with_data(sysvar_instructions::ID, sysvar_instructions::ID, data)
+        }
+    }
+
+    pub fn with_data(key: Pubkey, owner: Pubkey, data: Vec<u8>) -> Self {
+        Self {
+            key,
+            owner,
+            lamports: 1_000_000_000,
+            data,
+            is_signer: false,
+            is_writable: true,
+            executable: false,
+        }
+    }
+}
+
+thread_local! {
+    // Events emitted on this thread, as logged: discriminator then Borsh fields
+    static EMITTED: RefCell<Vec<Vec<u8>>> = const { RefCell::new(Vec::new()) };
+    // Messages logged with msg! on this thread
+    static LOGGED: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    // Unix time the clock sysvar reports on this thread
+    static NOW: Cell<i64> = const { Cell::new(0) };
+}
+
+// Runtime services tests need: the rent sysvar, which `init` sizes new accounts
+// against, a clock the test sets, token transfers for handlers that move funds, and a
+// log that keeps emitted events
+struct TestSyscalls;
+
+impl program_stubs::SyscallStubs for TestSyscalls {
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { *(var_addr as *mut Rent) = Rent::default() };
+        0
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = Clock {
+            unix_timestamp: NOW.with(Cell::get),
+            ..Clock::default()
+        };
+        unsafe { *(var_addr as *mut Clock) = clock };
+        0
+    }
+
+    // Token transfers move the balance between the token accounts passed in, and
+    // system transfers and account creation move lamports from the payer; every other
+    // CPI does nothing
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        _signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        let account = |index: usize| {
+            account_infos
+                .iter()
+                .find(|info| *info.key == instruction.accounts[index].pubkey)
+                .ok_or(ProgramError::NotEnoughAccountKeys)
+        };
+        if instruction.program_id == anchor_lang::system_program::ID {
+            let lamports = match limited_deserialize(&instruction.data, PACKET_DATA_SIZE as u64)
+                .map_err(|_| ProgramError::InvalidInstructionData)?
+            {
+                SystemInstruction::CreateAccount { lamports, .. } => lamports,
+                SystemInstruction::Transfer { lamports } => lamports,
+                _ => return Ok(()),
+            };
+            let (from, to) = (account(0)?, account(1)?);
+            let left = from.lamports().checked_sub(lamports);
+            **from.try_borrow_mut_lamports()? = left.ok_or(ProgramError::InsufficientFunds)?;
+            **to.try_borrow_mut_lamports()? += lamports;
+            return Ok(());
+        }
+        if instruction.program_id != spl_token::ID {
+            return Ok(());
+        }
+        let (amount, from, to) = match TokenInstruction::unpack(&instruction.data)? {
+            TokenInstruction::Transfer { amount } => (amount, 0, 1),
+            TokenInstruction::TransferChecked { amount, .. } => (amount, 0, 2),
+            _ => return Ok(()),
+        };
+        move_balance(account(from)?, |balance| balance.checked_sub(amount))?;
+        move_balance(account(to)?, |balance| balance.checked_add(amount))
+    }
+
+    fn sol_log(&self, message: &str) {
+        LOGGED.with(|logged| logged.borrow_mut().push(message.to_string()));
+    }
+
+    fn sol_log_data(&self, data: &[&[u8]]) {
+        EMITTED.with(|emitted| emitted.borrow_mut().push(data.concat()));
+    }
+}
+
+fn move_balance(info: &AccountInfo, update: impl Fn(u64) -> Option<u64>) -> ProgramResult {
+    let mut data = info.try_borrow_mut_data()?;
+    let mut account = spl_token::state::Account::unpack(&data)?;
+    account.amount = update(account.amount).ok_or(ProgramError::InsufficientFunds)?;
+    account.pack_into_slice(&mut data);
+    Ok(())
+}
+
+fn install_syscalls() {
+    static SYSCALLS: Once = Once::new();
+    SYSCALLS.call_once(|| {
+        program_stubs::set_syscall_stubs(Box::new(TestSyscalls));
+    });
+}
+
+// Set the time handlers on this thread read from the clock
+pub fn set_clock(unix_timestamp: i64) {
+    install_syscalls();
+    NOW.with(|now| now.set(unix_timestamp));
+}
+
+// Start capturing events and log messages on this thread, dropping any captured so far
+pub fn capture_events() {
+    install_syscalls();
+    EMITTED.with(|emitted| emitted.borrow_mut().clear());
+    LOGGED.with(|logged| logged.borrow_mut().clear());
+}
+
+// Events emitted on this thread since capture_events
+pub fn emitted_events() -> Vec<Vec<u8>> {
+    EMITTED.with(|emitted| emitted.borrow().clone())
+}
+
+// Messages logged on this thread since capture_events
+pub fn logged_messages() -> Vec<String> {
+    LOGGED.with(|logged| logged.borrow().clone())
+}
+
+// AccountInfos over `accounts`, as the runtime would pass them. The accounts are
+// leaked so they outlive anything built from them, which is fine in tests.
+pub fn account_infos(accounts: Vec<TestAccount>) -> &'static [AccountInfo<'static>] {
+    let accounts: &'static mut [TestAccount] = Box::leak(accounts.into_boxed_slice());
+    let infos: Vec<AccountInfo<'static>> = accounts
+        .iter_mut()
+        .map(|account| {
+            // The runtime keeps each account's data length in the 8 bytes before its
+            // data, which realloc writes when an account is closed
+            let buffer = [vec![0; 8], std::mem::take(&mut account.data)].concat();
+            let buffer: &'static mut [u8] = Box::leak(buffer.into_boxed_slice());
+            AccountInfo::new(
+                &account.key,
+                account.is_signer,
+                account.is_writable,
+                &mut account.lamports,
+                &mut buffer[8..],
+                &account.owner,
+                account.executable,
+                0,
+            )
+        })
+        .collect();
+    Box::leak(infos.into_boxed_slice())
+}
+
+// Run `T`'s account validation over `accounts`, in the context's field order
+pub fn try_accounts<T>(accounts: Vec<TestAccount>, ix_data: &[u8]) -> Result<T>
+where
+    T: Accounts<'static, <T as Bumps>::Bumps> + Bumps,
+    <T as Bumps>::Bumps: Default,
+{
+    install_syscalls();
+    let mut infos = account_infos(accounts);
+    T::try_accounts(
+        &crate::ID,
+        &mut infos,
+        ix_data,
+        &mut Default::default(),
+        &mut BTreeSet::new(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{logic, pool, ErrorCode, InsurancePool, PoolDonation, ProtocolConfig, UserAccount};
+    use anchor_lang::{AccountsExit, Discriminator};
+    use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+    fn zeroed<T: anchor_lang::AccountDeserialize>(space: usize) -> T {
+        T::try_deserialize_unchecked(&mut &vec![0u8; 8 + space][..]).unwrap()
+    }
+
+    fn pda(seeds: &[&[u8]]) -> (Pubkey, u8) {
+        Pubkey::find_program_address(seeds, &crate::ID)
+    }
+
+    fn config_account(config: &mut ProtocolConfig) -> TestAccount {
+        let (key, bump) = pda(&[b"config"]);
+        config.bump = bump;
+        TestAccount::program_owned(key, config)
+    }
+
+    // The shared pool, paying out `mint` from `vault`
+    fn shared_pool(mint: Pubkey, vault: Pubkey) -> (Pubkey, InsurancePool) {
+        let (key, bump) = pda(&[b"pool", InsurancePool::tier_seed(&0)]);
+        let mut pool: InsurancePool = zeroed(InsurancePool::INIT_SPACE);
+        pool.bump = bump;
+        pool.payout_mint = mint;
+        pool.vault = vault;
+        (key, pool)
+    }
+
+    #[test]
+    fn pool_deposits_reject_spoofed_token_accounts() {
+        let (mint, vault, donor, attacker) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let (pool_key, pool) = shared_pool(mint, vault);
+        let donate = |vault_account: TestAccount, donor_account: TestAccount| {
+            let mut config: ProtocolConfig = zeroed(ProtocolConfig::INIT_SPACE);
+            try_accounts::<crate::DonateToPool>(
+                vec![
+                    TestAccount::program_owned(pool_key, &pool),
+                    config_account(&mut config),
+                    vault_account,
+                    donor_account,
+                    TestAccount::signer(donor),
+                    TestAccount::mint(mint),
+                    TestAccount::program(spl_token::ID),
+                ],
+                &[],
+            )
+            .map(|_| ())
+        };
+        let vault_account = || TestAccount::token_account(vault, mint, pool_key, 0);
+        let donor_account = || TestAccount::token_account(Pubkey::new_unique(), mint, donor, 100);
+        assert!(donate(vault_account(), donor_account()).is_ok());
+
+        // An attacker's account of the right mint in place of the pool vault
+        let spoofed_vault = TestAccount::token_account(Pubkey::new_unique(), mint, attacker, 0);
+        let err = donate(spoofed_vault, donor_account()).unwrap_err();
+        assert_eq!(err, ErrorCode::InvalidPoolVault.into());
+
+        // Paying from an account the signer doesn't own, or of another mint
+        let someone_elses = TestAccount::token_account(Pubkey::new_unique(), mint, attacker, 100);
+        let err = donate(vault_account(), someone_elses).unwrap_err();
+        assert_eq!(err, ErrorCode::InvalidTokenOwner.into());
+        let wrong_mint =
+            TestAccount::token_account(Pubkey::new_unique(), Pubkey::new_unique(), donor, 100);
+        let err = donate(vault_account(), wrong_mint).unwrap_err();
+        assert_eq!(err, ErrorCode::InvalidMint.into());
+    }
+
+    // A depositor's or owner's LP position in the shared pool
+    fn lp_position(pool_key: Pubkey, owner: Pubkey) -> TestAccount {
+        let (key, bump) = pda(&[b"lp_position", pool_key.as_ref(), owner.as_ref()]);
+        let mut position: crate::LpPosition = zeroed(crate::LpPosition::INIT_SPACE);
+        position.owner = owner;
+        position.pool = pool_key;
+        position.bump = bump;
+        TestAccount::program_owned(key, &position)
+    }
+
+    #[test]
+    fn lp_deposits_and_withdrawals_reject_spoofed_token_accounts() {
+        let (mint, vault, lp, attacker) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let (pool_key, pool) = shared_pool(mint, vault);
+        let deposit = |vault_account: TestAccount, lp_account: TestAccount| {
+            check::<crate::DepositToPool>(vec![
+                TestAccount::program_owned(pool_key, &pool),
+                config(),
+                vault_account,
+                lp_position(pool_key, lp),
+                none(),
+                lp_account,
+                TestAccount::signer(lp),
+                TestAccount::mint(mint),
+                TestAccount::program(spl_token::ID),
+                TestAccount::program(anchor_lang::system_program::ID),
+            ])
+        };
+        let withdraw = |vault_account: TestAccount, lp_account: TestAccount| {
+            check::<crate::WithdrawFromPool>(vec![
+                TestAccount::program_owned(pool_key, &pool),
+                config(),
+                vault_account,
+                lp_position(pool_key, lp),
+                none(),
+                lp_account,
+                TestAccount::signer(lp),
+                TestAccount::mint(mint),
+                TestAccount::program(spl_token::ID),
+            ])
+        };
+        let vault_account = || TestAccount::token_account(vault, mint, pool_key, 0);
+        let lp_account = || TestAccount::token_account(Pubkey::new_unique(), mint, lp, 100);
+        let spoofed_vault = || TestAccount::token_account(Pubkey::new_unique(), mint, attacker, 0);
+        let attacker_account =
+            || TestAccount::token_account(Pubkey::new_unique(), mint, attacker, 100);
+        let wrong_mint =
+            || TestAccount::token_account(Pubkey::new_unique(), Pubkey::new_unique(), lp, 100);
+
+        let runs: [&dyn Fn(TestAccount, TestAccount) -> Result<()>; 2] = [&deposit, &withdraw];
+        for run in runs {
+            assert!(run(vault_account(), lp_account()).is_ok());
+            assert_eq!(
+                run(spoofed_vault(), lp_account()).unwrap_err(),
+                ErrorCode::InvalidPoolVault.into()
+            );
+            assert_eq!(
+                run(vault_account(), attacker_account()).unwrap_err(),
+                ErrorCode::InvalidTokenOwner.into()
+            );
+            assert_eq!(
+                run(vault_account(), wrong_mint()).unwrap_err(),
+                ErrorCode::InvalidMint.into()
+            );
+        }
+    }
+
+    #[test]
+    fn payouts_only_reach_the_claimant() {
+        let (mint, vault, claimant, attacker) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let (pool_key, pool) = shared_pool(mint, vault);
+        let mut claim: crate::Claim = zeroed(crate::Claim::INIT_SPACE);
+        claim.version = crate::Claim::CURRENT_VERSION;
+        claim.pool = pool_key;
+        claim.claimant = claimant;
+        let payout = |vault_account: TestAccount, claimant_account: TestAccount| {
+            check::<crate::ExecutePayout>(vec![
+                TestAccount::program_owned(pool_key, &pool),
+                config(),
+                vault_account,
+                TestAccount::program_owned(Pubkey::new_unique(), &claim),
+                claimant_account,
+                TestAccount::mint(mint),
+                TestAccount::program(spl_token::ID),
+                none(),
+                none(),
+                none(),
+                none(),
+                TestAccount::with_data(
+                    pda(&[b"__event_authority"]).0,
+                    anchor_lang::system_program::ID,
+                    Vec::new(),
+                ),
+                TestAccount::program(crate::ID),
+            ])
+        };
+        let vault_account = || TestAccount::token_account(vault, mint, pool_key, 0);
+        let claimant_account =
+            || TestAccount::token_account(Pubkey::new_unique(), mint, claimant, 0);
+        assert!(payout(vault_account(), claimant_account()).is_ok());
+
+        // Paying from an account posing as the pool vault
+        let spoofed_vault = TestAccount::token_account(Pubkey::new_unique(), mint, attacker, 0);
+        assert_eq!(
+            payout(spoofed_vault, claimant_account()).unwrap_err(),
+            ErrorCode::InvalidPoolVault.into()
+        );
+        // Paying into an attacker's account, or the claimant's account of another mint
+        let attacker_account = TestAccount::token_account(Pubkey::new_unique(), mint, attacker, 0);
+        assert_eq!(
+            payout(vault_account(), attacker_account).unwrap_err(),
+            ErrorCode::InvalidClaimantAccount.into()
+        );
+        let wrong_mint =
+            TestAccount::token_account(Pubkey::new_unique(), Pubkey::new_unique(), claimant, 0);
+        assert_eq!(
+            payout(vault_account(), wrong_mint).unwrap_err(),
+            ErrorCode::InvalidClaimantAccount.into()
+        );
+    }
+
+    #[test]
+    fn premiums_only_reach_the_pool_vault() {
+        let (mint, vault, user, attacker) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let (pool_key, pool) = shared_pool(mint, vault);
+        let nft_mint = Pubkey::new_unique();
+        let mint_policy = |vault_account: TestAccount, premium_account: TestAccount| {
+            let policy: crate::InsuranceNFT = zeroed(crate::InsuranceNFT::INIT_SPACE);
+            let index: crate::PolicyNumberIndex = zeroed(crate::PolicyNumberIndex::INIT_SPACE);
+            // `init` accounts are passed already allocated, as the create CPI is a no-op here
+            let index_key = pda(&[b"policy_number", 0u64.to_le_bytes().as_ref()]).0;
+            try_accounts::<crate::MintInsuranceNft>(
+                vec![
+                    user_account(user),
+                    TestAccount {
+                        is_signer: true,
+                        ..TestAccount::program_owned(Pubkey::new_unique(), &policy)
+                    },
+                    TestAccount::program_owned(index_key, &index),
+                    TestAccount::token_account(Pubkey::new_unique(), nft_mint, user, 0),
+                    TestAccount::mint(nft_mint),
+                    config(),
+                    TestAccount::program_owned(pool_key, &pool),
+                    vault_account,
+                    premium_account,
+                    none(),
+                    none(),
+                    TestAccount::signer(user),
+                    TestAccount::signer(user),
+                    TestAccount::with_data(Pubkey::new_unique(), crate::ID, Vec::new()),
+                    TestAccount::mint(mint),
+                    TestAccount::program(spl_token::ID),
+                    none(),
+                    TestAccount::program(anchor_lang::system_program::ID),
+                    TestAccount::program(anchor_spl::associated_token::ID),
+                ],
+                &[0],
+            )
+            .map(|_| ())
+        };
+        let vault_account = || TestAccount::token_account(vault, mint, pool_key, 0);
+        let premium_account = || TestAccount::token_account(Pubkey::new_unique(), mint, user, 100);
+        assert!(mint_policy(vault_account(), premium_account()).is_ok());
+
+        // The premium can't be diverted to an account posing as the pool vault...
+        let spoofed_vault = TestAccount::token_account(Pubkey::new_unique(), mint, attacker, 0);
+        assert_eq!(
+            mint_policy(spoofed_vault, premium_account()).unwrap_err(),
+            ErrorCode::InvalidPoolVault.into()
+        );
+        // ...nor paid out of someone else's account or in another mint
+        let someone_elses = TestAccount::token_account(Pubkey::new_unique(), mint, attacker, 100);
+        assert_eq!(
+            mint_policy(vault_account(), someone_elses).unwrap_err(),
+            ErrorCode::InvalidTokenOwner.into()
+        );
+        let wrong_mint =
+            TestAccount::token_account(Pubkey::new_unique(), Pubkey::new_unique(), user, 100);
+        assert_eq!(
+            mint_policy(vault_account(), wrong_mint).unwrap_err(),
+            ErrorCode::InvalidMint.into()
+        );
+    }
+
+    #[test]
+    fn payout_cap_and_claims_are_bounded_by_portfolio_value() {
+        const DOLLAR: u64 = 1_000_000;
+        let (wallet, usdc) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let holding = |key, amount| TestAccount::token_account(key, usdc, wallet, amount);
+
+        // $500 in tokens can't back a $1000 policy
+        let small = account_infos(vec![holding(Pubkey::new_unique(), 500 * DOLLAR)]);
+        assert_eq!(
+            crate::portfolio_value(small, &wallet, &usdc, 1000 * DOLLAR).unwrap_err(),
+            ErrorCode::PayoutCapExceedsPortfolio.into()
+        );
+        // $2000, even split across accounts, can
+        let large = account_infos(vec![
+            holding(Pubkey::new_unique(), 1500 * DOLLAR),
+            holding(Pubkey::new_unique(), 500 * DOLLAR),
+        ]);
+        assert_eq!(
+            crate::portfolio_value(large, &wallet, &usdc, 1000 * DOLLAR).unwrap(),
+            2000 * DOLLAR
+        );
+
+        // Passing the $500 account twice can't count it as $1000
+        let key = Pubkey::new_unique();
+        let doubled = account_infos(vec![holding(key, 500 * DOLLAR), holding(key, 500 * DOLLAR)]);
+        assert_eq!(
+            crate::portfolio_value(doubled, &wallet, &usdc, 1000 * DOLLAR).unwrap_err(),
+            ErrorCode::InvalidRemainingAccounts.into()
+        );
+
+        // The $1000 policy pays at most its cap, though the wallet held $2000
+        assert!(!logic::claim_within_cap(2000 * DOLLAR, 1000 * DOLLAR));
+        assert!(logic::claim_within_cap(1000 * DOLLAR, 1000 * DOLLAR));
+    }
+
+    #[test]
+    fn user_accounts_live_only_at_the_wallet_pda() {
+        let wallet = Pubkey::new_unique();
+        let (user_pda, bump) = pda(&[b"user", wallet.as_ref()]);
+        let initialize = |user_account: Pubkey| {
+            try_accounts::<crate::InitializeUser>(
+                vec![
+                    TestAccount::empty(user_account),
+                    TestAccount::signer(wallet),
+                    TestAccount::signer(wallet),
+                    TestAccount::program(anchor_lang::system_program::ID),
+                ],
+                &[],
+            )
+            .map(|_| ())
+        };
+        // The wallet's PDA clears the seeds check. Creation then fails here since the
+        // harness doesn't run the system program, which on chain is also what refuses
+        // a second initialization of the same address.
+        let seeds_error: Error = anchor_lang::error::ErrorCode::ConstraintSeeds.into();
+        assert!(initialize(user_pda).is_err_and(|err| err != seeds_error));
+        // A fresh keypair account, or another wallet's PDA, can't stand in for the
+        // wallet's one address
+        for other in [
+            Pubkey::new_unique(),
+            pda(&[b"user", Pubkey::new_unique().as_ref()]).0,
+        ] {
+            assert_eq!(initialize(other).unwrap_err(), seeds_error);
+        }
+
+        let mut account: UserAccount = zeroed(UserAccount::INIT_SPACE);
+        account.wallet = wallet;
+        account.bump = bump;
+        assert!(account.is_pda(&user_pda));
+        // Legacy keypair accounts holding the same state still need migrating
+        assert!(!account.is_pda(&Pubkey::new_unique()));
+    }
+
+    #[test]
+    fn legacy_migration_only_targets_the_signers_pda() {
+        let (wallet, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let migrate = |user_account: Pubkey| {
+            let mut config: ProtocolConfig = zeroed(ProtocolConfig::INIT_SPACE);
+            try_accounts::<crate::MigrateLegacyUser>(
+                vec![
+                    TestAccount::with_data(Pubkey::new_unique(), crate::ID, Vec::new()),
+                    TestAccount::empty(user_account),
+                    config_account(&mut config),
+                    TestAccount::signer(wallet),
+                    TestAccount::program(anchor_lang::system_program::ID),
+                ],
+                &[],
+            )
+            .map(|_| ())
+        };
+        let err = migrate(pda(&[b"user", other.as_ref()]).0).unwrap_err();
+        assert_eq!(err, anchor_lang::error::ErrorCode::ConstraintSeeds.into());
+    }
+
+    #[test]
+    fn nominated_admin_has_no_power_until_accepted() {
+        let (admin, nominee) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let set_param = |config: &mut ProtocolConfig, signer: Pubkey| {
+            try_accounts::<crate::SetConfigParam>(
+                vec![config_account(config), TestAccount::signer(signer), none()],
+                &[],
+            )
+            .map(|_| ())
+        };
+        let has_one_error: Error = anchor_lang::error::ErrorCode::ConstraintHasOne.into();
+
+        let mut config: ProtocolConfig = zeroed(ProtocolConfig::INIT_SPACE);
+        config.admin = admin;
+        config.pending_admin = Some(nominee);
+        assert!(set_param(&mut config, admin).is_ok());
+        assert_eq!(set_param(&mut config, nominee).unwrap_err(), has_one_error);
+
+        // After accept_admin the roles swap
+        config.admin = nominee;
+        config.pending_admin = None;
+        assert!(set_param(&mut config, nominee).is_ok());
+        assert_eq!(set_param(&mut config, admin).unwrap_err(), has_one_error);
+    }
+
+    // A value of `T` built from zeroed bytes: every field zero, every enum its first
+    // variant. Fixed-size types come out at their full size.
+    fn value<T: AnchorDeserialize + Space>() -> T {
+        T::deserialize(&mut &vec![0u8; T::INIT_SPACE][..]).unwrap()
+    }
+
+    // A maximal instance must serialize to exactly the space allocated for it
+    fn assert_fills_space<T: AccountSerialize + Space>(account: &T) {
+        let mut data = Vec::new();
+        account.try_serialize(&mut data).unwrap();
+        assert_eq!(data.len(), 8 + T::INIT_SPACE);
+    }
+
+    #[test]
+    fn maximal_user_accounts_fit_their_space() {
+        let mut user: UserAccount = zeroed(UserAccount::INIT_SPACE);
+        user.preferences.watchlist = vec![Pubkey::default(); crate::MAX_WATCHLIST_TOKENS];
+        user.score_history = vec![value::<crate::Score>(); crate::MAX_SCORE_HISTORY];
+        user.pending_score = Some(0);
+        user.linked_nfts = vec![Pubkey::default(); crate::MAX_LINKED_NFTS];
+        user.linked_claims = vec![Pubkey::default(); crate::MAX_LINKED_CLAIMS];
+        user.last_action_log = Some(value());
+        user.subscription_reference = Some([0; 16]);
+        user.held_score = Some(value());
+        assert_fills_space(&user);
+
+        let mut history: crate::ScoreHistory = zeroed(crate::ScoreHistory::INIT_SPACE);
+        history.scores = vec![value::<crate::ArchivedScore>(); crate::MAX_ARCHIVED_SCORES];
+        assert_fills_space(&history);
+
+        let mut covered: crate::CoveredTokenList = zeroed(crate::CoveredTokenList::INIT_SPACE);
+        covered.covered_tokens = vec![value::<crate::CoveredToken>(); crate::MAX_COVERED_TOKENS];
+        assert_fills_space(&covered);
+    }
+
+    #[test]
+    fn maximal_protocol_accounts_fit_their_space() {
+        let mut config: ProtocolConfig = zeroed(ProtocolConfig::INIT_SPACE);
+        config.lending_allowlist = vec![Pubkey::default(); crate::MAX_LENDING_PROGRAMS];
+        config.pending_admin = Some(Pubkey::default());
+        config.cpi_allowlist = vec![Pubkey::default(); crate::MAX_CPI_ALLOWLIST];
+        config.stream_allowlist = vec![Pubkey::default(); crate::MAX_STREAM_PROGRAMS];
+        config.transfer_hook_allowlist = vec![Pubkey::default(); crate::MAX_TRANSFER_HOOK_PROGRAMS];
+        config.multisig_allowlist = vec![Pubkey::default(); crate::MAX_MULTISIG_PROGRAMS];
+        config.yield_allowlist = vec![Pubkey::default(); crate::MAX_YIELD_PROGRAMS];
+        config.swap_program_allowlist = vec![Pubkey::default(); crate::MAX_SWAP_PROGRAMS];
+        assert_fills_space(&config);
+
+        let mut registry: crate::OracleRegistry = zeroed(crate::OracleRegistry::INIT_SPACE);
+        registry.fallback_oracles = vec![Pubkey::default(); crate::MAX_FALLBACK_ORACLES];
+        registry.fallback_heartbeats = vec![0; crate::MAX_FALLBACK_ORACLES];
+        registry.invalidator_oracles = vec![Pubkey::default(); crate::MAX_INVALIDATOR_ORACLES];
+        assert_fills_space(&registry);
+
+        let mut treasury: crate::Treasury = zeroed(crate::Treasury::INIT_SPACE);
+        treasury.dex_allowlist = vec![Pubkey::default(); crate::MAX_DEX_PROGRAMS];
+        assert_fills_space(&treasury);
+
+        let mut attestors: crate::AttestorRegistry = zeroed(crate::AttestorRegistry::INIT_SPACE);
+        attestors.attestors = vec![Pubkey::default(); crate::MAX_ATTESTORS];
+        assert_fills_space(&attestors);
+
+        let mut report: crate::AggregationReport = zeroed(crate::AggregationReport::INIT_SPACE);
+        report.participating_oracles = vec![Pubkey::default(); crate::MAX_CONSENSUS_ORACLES];
+        report.submitted_scores = vec![0; crate::MAX_CONSENSUS_ORACLES];
+        assert_fills_space(&report);
+
+        let mut waitlist: crate::MintWaitlist = zeroed(crate::MintWaitlist::INIT_SPACE);
+        waitlist.entries = vec![value::<crate::WaitlistEntry>(); crate::MAX_WAITLIST_ENTRIES];
+        assert_fills_space(&waitlist);
+    }
+
+    #[test]
+    fn maximal_governance_accounts_fit_their_space() {
+        let mut council: crate::EmergencyCouncil = zeroed(crate::EmergencyCouncil::INIT_SPACE);
+        council.members = vec![Pubkey::default(); crate::MAX_EMERGENCY_COUNCIL];
+        assert_fills_space(&council);
+
+        let mut rotation: crate::SignerRotationProposal =
+            zeroed(crate::SignerRotationProposal::INIT_SPACE);
+        rotation.confirmations = vec![Pubkey::default(); crate::MAX_EMERGENCY_COUNCIL];
+        assert_fills_space(&rotation);
+
+        // BuybackAndBurn is the largest proposal type
+        let mut proposal: crate::Proposal = zeroed(crate::Proposal::INIT_SPACE);
+        proposal.proposal_type = crate::ProposalType::BuybackAndBurn {
+            dex_program: Pubkey::default(),
+            pool: Pubkey::default(),
+            iris_mint: Pubkey::default(),
+            max_amount_in: 0,
+            min_iris_out: 0,
+        };
+        proposal.snapshot = Some(value());
+        assert_fills_space(&proposal);
+    }
+
+    #[test]
+    fn maximal_policy_and_claim_accounts_fit_their_space() {
+        let mut nft: crate::InsuranceNFT = zeroed(crate::InsuranceNFT::INIT_SPACE);
+        nft.compressed_leaf = Some(value());
+        nft.premium_reference = Some([0; 16]);
+        assert_fills_space(&nft);
+
+        let mut claim: crate::Claim = zeroed(crate::Claim::INIT_SPACE);
+        claim.proof = vec![0; crate::MAX_CLAIM_PROOF_LEN];
+        claim.payout_reference = Some([0; 16]);
+        claim.payout_schedule = Some(value());
+        claim.slot_anchor = Some(value());
+        assert_fills_space(&claim);
+
+        let mut custody: crate::EvidenceChainOfCustody =
+            zeroed(crate::EvidenceChainOfCustody::INIT_SPACE);
+        custody.proof = vec![0; crate::MAX_CLAIM_PROOF_LEN];
+        custody.entries = vec![value::<crate::CustodyEntry>(); crate::MAX_CUSTODY_ENTRIES];
+        assert_fills_space(&custody);
+
+        let mut vote: crate::ClaimVoteRecord = zeroed(crate::ClaimVoteRecord::INIT_SPACE);
+        vote.approved_amount = Some(0);
+        assert_fills_space(&vote);
+    }
+
+    #[test]
+    fn event_positions_strictly_increase_across_instructions() {
+        capture_events();
+        let mut config: ProtocolConfig = zeroed(ProtocolConfig::INIT_SPACE);
+        let donor = Pubkey::new_unique();
+
+        // Two events from one instruction
+        let mut events = crate::next_event_seq(&mut config).unwrap();
+        emit_seq!(events, PoolDonation { donor, amount: 1 });
+        emit_seq!(events, PoolDonation { donor, amount: 2 });
+
+        // Then one each from a pool entering haircut mode and another donation
+        let mut events = crate::next_event_seq(&mut config).unwrap();
+        let mut pool: InsurancePool = zeroed(InsurancePool::INIT_SPACE);
+        pool.approved_liabilities = 1_000;
+        assert!(pool::assert_pool_solvency(&mut pool, 500, 0, &mut events));
+        let mut events = crate::next_event_seq(&mut config).unwrap();
+        emit_seq!(events, PoolDonation { donor, amount: 3 });
+
+        // Every event leads with (seq, seq_index) after its discriminator
+        let positions: Vec<(u64, u8)> = emitted_events()
+            .iter()
+            .map(|data| {
+                (
+                    u64::from_le_bytes(data[8..16].try_into().unwrap()),
+                    data[16],
+                )
+            })
+            .collect();
+        assert_eq!(positions, [(1, 0), (1, 1), (2, 0), (3, 0)]);
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(config.event_seq, 3);
+    }
+
+    #[test]
+    fn indexed_events_log_the_wallet_under_the_index_prefix() {
+        capture_events();
+        let mut config: ProtocolConfig = zeroed(ProtocolConfig::INIT_SPACE);
+        let (first, second) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut events = crate::next_event_seq(&mut config).unwrap();
+        emit_indexed!(
+            events,
+            first,
+            PoolDonation {
+                donor: first,
+                amount: 1
+            }
+        );
+        emit_seq!(
+            events,
+            PoolDonation {
+                donor: first,
+                amount: 2
+            }
+        );
+        emit_indexed!(
+            events,
+            second,
+            PoolDonation {
+                donor: second,
+                amount: 3
+            }
+        );
+
+        // One index line per indexed event, each naming its wallet after the prefix
+        assert_eq!(
+            logged_messages(),
+            [
+                format!("IRIS_INDEX wallet={first}"),
+                format!("IRIS_INDEX wallet={second}"),
+            ]
+        );
+        assert!(logged_messages()
+            .iter()
+            .all(|line| line.starts_with(crate::INDEX_LOG_PREFIX)));
+        assert_eq!(emitted_events().len(), 3);
+    }
+
+    // The treasury PDA, collecting `mint` in a vault it owns
+    struct TreasuryFixture {
+        key: Pubkey,
+        treasury: crate::Treasury,
+        mint: Pubkey,
+    }
+
+    impl TreasuryFixture {
+        fn new() -> Self {
+            let (key, bump) = pda(&[b"treasury"]);
+            let mut treasury: crate::Treasury = zeroed(crate::Treasury::INIT_SPACE);
+            treasury.bump = bump;
+            treasury.mint = Pubkey::new_unique();
+            treasury.vault = Pubkey::new_unique();
+            Self {
+                key,
+                mint: treasury.mint,
+                treasury,
+            }
+        }
+
+        fn account(&self) -> TestAccount {
+            TestAccount::program_owned(self.key, &self.treasury)
+        }
+
+        fn vault(&self) -> TestAccount {
+            TestAccount::token_account(self.treasury.vault, self.mint, self.key, 0)
+        }
+
+        // An account of the treasury's mint that the attacker owns
+        fn attacker_account(&self) -> TestAccount {
+            TestAccount::token_account(Pubkey::new_unique(), self.mint, Pubkey::new_unique(), 0)
+        }
+    }
+
+    // The wallet's user PDA
+    fn user_account(wallet: Pubkey) -> TestAccount {
+        let (key, bump) = pda(&[b"user", wallet.as_ref()]);
+        let mut account: UserAccount = zeroed(UserAccount::INIT_SPACE);
+        account.wallet = wallet;
+        account.bump = bump;
+        // Allocated at full size, as initialize_user leaves it, so handlers can grow it
+        let mut user = TestAccount::program_owned(key, &account);
+        user.data.resize(8 + UserAccount::INIT_SPACE, 0);
+        user
+    }
+
+    fn config() -> TestAccount {
+        config_account(&mut zeroed(ProtocolConfig::INIT_SPACE))
+    }
+
+    // Program id in an optional slot leaves it empty
+    fn none() -> TestAccount {
+        TestAccount::program(crate::ID)
+    }
+
+    fn check<T>(accounts: Vec<TestAccount>) -> Result<()>
+    where
+        T: Accounts<'static, <T as Bumps>::Bumps> + Bumps,
+        <T as Bumps>::Bumps: Default,
+    {
+        try_accounts::<T>(accounts, &[]).map(|_| ())
+    }
+
+    #[test]
+    fn subscription_and_penalty_payments_only_reach_the_treasury() {
+        let fixture = TreasuryFixture::new();
+        let user = Pubkey::new_unique();
+        let subscribe = |treasury_account: TestAccount| {
+            check::<crate::Subscribe>(vec![
+                user_account(user),
+                TestAccount::token_account(Pubkey::new_unique(), fixture.mint, user, 100),
+                config(),
+                fixture.account(),
+                treasury_account,
+                TestAccount::mint(fixture.mint),
+                none(),
+                none(),
+                none(),
+                TestAccount::signer(user),
+                TestAccount::program(spl_token::ID),
+                none(),
+                none(),
+                none(),
+                none(),
+                TestAccount::with_data(
+                    pda(&[b"__event_authority"]).0,
+                    anchor_lang::system_program::ID,
+                    Vec::new(),
+                ),
+                TestAccount::program(crate::ID),
+            ])
+        };
+        let pay_penalty = |treasury_account: TestAccount| {
+            check::<crate::PayClaimPenalty>(vec![
+                user_account(user),
+                TestAccount::token_account(Pubkey::new_unique(), fixture.mint, user, 100),
+                config(),
+                fixture.account(),
+                treasury_account,
+                TestAccount::mint(fixture.mint),
+                TestAccount::signer(user),
+                TestAccount::program(spl_token::ID),
+            ])
+        };
+        let invalid_treasury: Error = ErrorCode::InvalidTreasury.into();
+        assert!(subscribe(fixture.vault()).is_ok());
+        assert_eq!(
+            subscribe(fixture.attacker_account()).unwrap_err(),
+            invalid_treasury
+        );
+        assert!(pay_penalty(fixture.vault()).is_ok());
+        assert_eq!(
+            pay_penalty(fixture.attacker_account()).unwrap_err(),
+            invalid_treasury
+        );
+    }
+
+    #[test]
+    fn rebates_only_reach_the_policyholder() {
+        let fixture = TreasuryFixture::new();
+        let user = Pubkey::new_unique();
+        let rebate = |vault_account: TestAccount, user_token_account: TestAccount| {
+            check::<crate::ClaimNoClaimRebate>(vec![
+                user_account(user),
+                TestAccount::empty(pda(&[b"claim_history", user.as_ref()]).0),
+                config(),
+                fixture.account(),
+                vault_account,
+                user_token_account,
+                TestAccount::signer(user),
+                TestAccount::mint(fixture.mint),
+                TestAccount::program(spl_token::ID),
+                none(),
+            ])
+        };
+        let user_token_account =
+            || TestAccount::token_account(Pubkey::new_unique(), fixture.mint, user, 0);
+        assert!(rebate(fixture.vault(), user_token_account()).is_ok());
+
+        // Refunded from an account posing as the treasury vault
+        assert_eq!(
+            rebate(fixture.attacker_account(), user_token_account()).unwrap_err(),
+            ErrorCode::InvalidTreasuryVault.into()
+        );
+        // Refunded into someone else's account, or one of another mint
+        assert_eq!(
+            rebate(fixture.vault(), fixture.attacker_account()).unwrap_err(),
+            ErrorCode::InvalidTokenOwner.into()
+        );
+        let wrong_mint =
+            TestAccount::token_account(Pubkey::new_unique(), Pubkey::new_unique(), user, 0);
+        assert_eq!(
+            rebate(fixture.vault(), wrong_mint).unwrap_err(),
+            ErrorCode::InvalidMint.into()
+        );
+    }
+
+    #[test]
+    fn rebates_are_paid_only_for_clean_periods() {
+        const DAY: i64 = 24 * 60 * 60;
+        let fixture = TreasuryFixture::new();
+        let user = Pubkey::new_unique();
+        let claim_rebate = |scores: Vec<crate::Score>| {
+            let (key, bump) = pda(&[b"user", user.as_ref()]);
+            let mut account: UserAccount = zeroed(UserAccount::INIT_SPACE);
+            account.wallet = user;
+            account.bump = bump;
+            account.plan_price = 10_000;
+            account.subscription_started_at = 0;
+            account.subscription_expiry = 30 * DAY;
+            account.preferences.risk_threshold = 80;
+            account.score_history = scores;
+            let mut config: ProtocolConfig = zeroed(ProtocolConfig::INIT_SPACE);
+            config.rebate_bps = 1_000;
+            let user_tokens =
+                TestAccount::token_account(Pubkey::new_unique(), fixture.mint, user, 0);
+            let user_tokens_key = user_tokens.key;
+            let infos = account_infos(vec![
+                TestAccount::program_owned(key, &account),
+                TestAccount::empty(pda(&[b"claim_history", user.as_ref()]).0),
+                config_account(&mut config),
+                fixture.account(),
+                TestAccount::token_account(
+                    fixture.treasury.vault,
+                    fixture.mint,
+                    fixture.key,
+                    50_000,
+                ),
+                user_tokens,
+                TestAccount::signer(user),
+                TestAccount::mint(fixture.mint),
+                TestAccount::program(spl_token::ID),
+                none(),
+            ]);
+            set_clock(31 * DAY);
+            run::<crate::ClaimNoClaimRebate>(infos.to_vec(), |ctx| {
+                crate::iris_anchor::claim_no_claim_rebate(ctx, None)
+            })
+            .map(|()| {
+                let received = infos
+                    .iter()
+                    .find(|info| *info.key == user_tokens_key)
+                    .unwrap();
+                token_balance(received)
+            })
+        };
+        let score = |value, day| crate::Score {
+            value,
+            timestamp: day * DAY,
+        };
+
+        // Scores stayed under the alert threshold all period: 10% of the price comes back
+        assert_eq!(
+            claim_rebate(vec![score(40, 3), score(79, 20)]).unwrap(),
+            1_000
+        );
+        // A score at the threshold during the period forfeits it...
+        assert_eq!(
+            claim_rebate(vec![score(40, 3), score(80, 20)]).unwrap_err(),
+            ErrorCode::RiskThresholdCrossed.into()
+        );
+        // ...while one before the period began doesn't count against it
+        assert_eq!(
+            claim_rebate(vec![score(95, -2), score(40, 3)]).unwrap(),
+            1_000
+        );
+    }
+
+    // Run update_risk_score for `wallet`, signed by `oracle` for the `chain_id`
+    // deployment, against a program configured for `protocol_chain_id`. The registry is
+    // read back afterwards.
+    fn submit_score(
+        registry: &mut crate::OracleRegistry,
+        wallet: Pubkey,
+        oracle: Pubkey,
+        chain_id: u8,
+        protocol_chain_id: u8,
+        now: i64,
+    ) -> Result<()> {
+        let message = crate::ScoreMessage {
+            wallet,
+            score: 40,
+            category: crate::wire::SCORE_CATEGORY_OVERALL,
+            token: None,
+            timestamp: now,
+            nonce: now as u64,
+            chain_id,
+        };
+        let signed = Instruction::new_with_bytes(
+            anchor_lang::solana_program::ed25519_program::ID,
+            &crate::ed25519::tests::instruction_data(
+                &oracle,
+                &crate::wire::encode_score_message(&crate::ID, &message),
+            ),
+            Vec::new(),
+        );
+        let mut config: ProtocolConfig = zeroed(ProtocolConfig::INIT_SPACE);
+        config.protocol_chain_id = protocol_chain_id;
+        let (registry_key, bump) = pda(&[b"oracle_registry"]);
+        registry.bump = bump;
+        let infos = account_infos(vec![
+            user_account(wallet),
+            config_account(&mut config),
+            TestAccount::program_owned(registry_key, registry),
+            none(),
+            none(),
+            none(),
+            none(),
+            TestAccount::instructions(&[signed]),
+            TestAccount::signer(oracle),
+        ]);
+        set_clock(now);
+        let result = run::<crate::UpdateRiskScore>(infos.to_vec(), |ctx| {
+            crate::iris_anchor::update_risk_score(ctx, message.score, now, chain_id, [7; 64])
+        });
+        *registry = Account::<crate::OracleRegistry>::try_from(&infos[2])
+            .unwrap()
+            .into_inner();
+        result
+    }
+
+    #[test]
+    fn scores_signed_for_another_chain_are_rejected() {
+        const MAINNET: u8 = 1;
+        const DEVNET: u8 = 2;
+        let oracle = Pubkey::new_unique();
+        let mut registry: crate::OracleRegistry = zeroed(crate::OracleRegistry::INIT_SPACE);
+        registry.primary_oracle = oracle;
+        registry.heartbeat_interval = 60;
+        let wallet = Pubkey::new_unique();
+
+        // The oracle's devnet signature verifies, but a mainnet deployment refuses it
+        assert_eq!(
+            submit_score(&mut registry, wallet, oracle, DEVNET, MAINNET, 1_000).unwrap_err(),
+            ErrorCode::ChainIdMismatch.into()
+        );
+        // The same report signed for mainnet is held for application there
+        submit_score(&mut registry, wallet, oracle, MAINNET, MAINNET, 1_000).unwrap();
+        assert!(submit_score(&mut registry, wallet, oracle, DEVNET, DEVNET, 1_000).is_ok());
+    }
+
+    #[test]
+    fn fallback_oracle_takes_over_after_two_missed_heartbeats() {
+        let (primary, fallback) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut registry: crate::OracleRegistry = zeroed(crate::OracleRegistry::INIT_SPACE);
+        registry.primary_oracle = primary;
+        registry.current_active_oracle = primary;
+        registry.fallback_oracles = vec![fallback];
+        registry.fallback_heartbeats = vec![0];
+        registry.heartbeat_interval = 60;
+        let emitted = |event: [u8; 8]| emitted_events().iter().any(|data| data.starts_with(&event));
+        let mut submit = |oracle, now| {
+            capture_events();
+            submit_score(&mut registry, Pubkey::new_unique(), oracle, 1, 1, now)
+                .map(|()| registry.current_active_oracle)
+        };
+
+        // One heartbeat missed: the primary is still live, so the fallback is refused
+        assert_eq!(submit(primary, 0).unwrap(), primary);
+        assert_eq!(
+            submit(fallback, 90).unwrap_err(),
+            ErrorCode::InactiveOracle.into()
+        );
+        // Two missed: the first live fallback's score is accepted in its place
+        assert_eq!(submit(fallback, 150).unwrap(), fallback);
+        assert!(emitted(crate::OracleFallbackActivated::DISCRIMINATOR));
+        assert_eq!(submit(fallback, 160).unwrap(), fallback);
+        // Once the primary reports again it takes back over
+        assert_eq!(submit(primary, 170).unwrap(), primary);
+        assert!(emitted(crate::PrimaryOracleRestored::DISCRIMINATOR));
+        assert_eq!(
+            submit(fallback, 180).unwrap_err(),
+            ErrorCode::InactiveOracle.into()
+        );
+    }
+
+    #[test]
+    fn pool_stats_use_a_fresh_apy_and_reject_a_stale_one() {
+        const DAY: i64 = 24 * 60 * 60;
+        let (mint, vault) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (pool_key, mut pool) = shared_pool(mint, vault);
+        pool.epoch_started_at = 9 * DAY;
+        let apy_oracle = Pubkey::new_unique();
+        let (registry_key, bump) = pda(&[b"oracle_registry"]);
+        let mut registry: crate::OracleRegistry = zeroed(crate::OracleRegistry::INIT_SPACE);
+        registry.bump = bump;
+        registry.apy_oracle = apy_oracle;
+        let infos = account_infos(vec![
+            TestAccount::program_owned(pool_key, &pool),
+            TestAccount::token_account(vault, mint, pool_key, 1_000_000),
+            config(),
+            TestAccount::program_owned(registry_key, &registry),
+            TestAccount::signer(apy_oracle),
+            TestAccount::signer(Pubkey::new_unique()),
+        ]);
+        let update_apy = |signer: usize, now| {
+            set_clock(now);
+            run::<crate::UpdatePoolApy>(
+                vec![infos[0].clone(), infos[3].clone(), infos[signer].clone()],
+                |ctx| crate::iris_anchor::update_pool_apy(ctx, 750),
+            )
+        };
+        let read_apy = |now| -> Result<u16> {
+            set_clock(now);
+            capture_events();
+            run::<crate::ReadInsurancePoolStats>(infos[..3].to_vec(), |ctx| {
+                crate::iris_anchor::read_insurance_pool_stats(ctx)
+            })?;
+            let events = emitted_events();
+            let (_, mut fields) = events.last().unwrap().split_at(8);
+            Ok(crate::InsurancePoolStats::deserialize(&mut fields)
+                .unwrap()
+                .apy_bps)
+        };
+
+        // Only the registered APY oracle may post
+        assert_eq!(
+            update_apy(5, 10 * DAY).unwrap_err(),
+            ErrorCode::UnknownOracle.into()
+        );
+        // A reading posted an hour ago is fresh and reported as the pool's APY
+        update_apy(4, 10 * DAY).unwrap();
+        assert_eq!(read_apy(10 * DAY + 60 * 60), Ok(750));
+        // Once it is more than a day old the stats refuse to report it
+        assert_eq!(
+            read_apy(11 * DAY + 1).unwrap_err(),
+            ErrorCode::StaleApyOracle.into()
+        );
+    }
+
+    #[test]
+    fn snapshot_votes_ignore_stake_changed_after_the_snapshot() {
+        const DAY: i64 = 24 * 60 * 60;
+        let (proposal_key, proposal_bump) = pda(&[b"proposal", 3u64.to_le_bytes().as_ref()]);
+        let mut proposal: crate::Proposal = zeroed(crate::Proposal::INIT_SPACE);
+        proposal.id = 3;
+        proposal.bump = proposal_bump;
+        proposal.status = crate::ProposalStatus::Voting;
+        proposal.voting_ends_at = 60 * DAY;
+        proposal.snapshot = Some(crate::SnapshotProposal {
+            minimum_stake_for_snapshot_bps: 100,
+            snapshot_taken: false,
+            total_weight: 0,
+        });
+        let authority = Pubkey::new_unique();
+        let (governance_key, governance_bump) = pda(&[b"governance"]);
+        let mut governance: crate::Governance = zeroed(crate::Governance::INIT_SPACE);
+        governance.bump = governance_bump;
+        governance.authority = authority;
+        let (distributor_key, distributor_bump) = pda(&[b"fee_distributor"]);
+        let mut distributor: crate::FeeDistributor = zeroed(crate::FeeDistributor::INIT_SPACE);
+        distributor.bump = distributor_bump;
+        distributor.total_staked = 1_000;
+
+        // Three stakers, all staked long enough to vote at full weight; the third holds
+        // enough that the first two alone stay short of quorum
+        let voters = [
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        ];
+        let mut accounts = vec![
+            TestAccount::program_owned(proposal_key, &proposal),
+            TestAccount::program_owned(governance_key, &governance),
+            TestAccount::program_owned(distributor_key, &distributor),
+            config(),
+            TestAccount::signer(authority),
+            TestAccount::program(anchor_lang::system_program::ID),
+        ];
+        for (voter, amount) in voters.iter().zip([300, 200, 500]) {
+            let (key, bump) = pda(&[b"fee_stake", voter.as_ref()]);
+            let mut stake: crate::FeeStake = zeroed(crate::FeeStake::INIT_SPACE);
+            stake.owner = *voter;
+            stake.amount = amount;
+            stake.bump = bump;
+            accounts.push(TestAccount::program_owned(key, &stake));
+            let snapshot = pda(&[b"snapshot", proposal_key.as_ref(), voter.as_ref()]).0;
+            accounts.push(TestAccount::with_data(
+                snapshot,
+                crate::ID,
+                vec![0; 8 + crate::Snapshot::INIT_SPACE],
+            ));
+        }
+        for voter in &voters[..2] {
+            let record = pda(&[b"proposal_vote", proposal_key.as_ref(), voter.as_ref()]).0;
+            accounts.push(TestAccount::signer(*voter));
+            accounts.push(TestAccount::with_data(
+                record,
+                crate::ID,
+                vec![0; 8 + crate::ProposalVoteRecord::INIT_SPACE],
+            ));
+        }
+        let infos = account_infos(accounts);
+        let pick = |indices: &[usize]| indices.iter().map(|&i| infos[i].clone()).collect();
+
+        set_clock(40 * DAY);
+        run::<crate::TakeVoteSnapshot>(pick(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11]), |ctx| {
+            crate::iris_anchor::take_vote_snapshot(ctx)
+        })
+        .unwrap();
+
+        // After the snapshot the first voter stakes far more and the second unstakes
+        // almost everything
+        for (stake_info, amount) in [(&infos[6], 10_000), (&infos[8], 1)] {
+            let mut stake = Account::<crate::FeeStake>::try_from(stake_info).unwrap();
+            stake.amount = amount;
+            stake.exit(&crate::ID).unwrap();
+        }
+        let vote = |stake: usize, voter: usize, choice| {
+            run::<crate::VoteOnProposal>(
+                pick(&[0, voter + 1, 1, stake + 1, stake, 3, voter, 5]),
+                |ctx| crate::iris_anchor::vote_on_proposal(ctx, choice),
+            )
+        };
+        vote(6, 12, crate::VoteChoice::Approve).unwrap();
+        vote(8, 14, crate::VoteChoice::Reject).unwrap();
+
+        // Both votes count at their snapshot weight, so the vote is still open: at the
+        // current stakes the approval would have passed
+        let proposal = Account::<crate::Proposal>::try_from(&infos[0]).unwrap();
+        assert_eq!(proposal.approval_votes, 300);
+        assert_eq!(proposal.rejection_votes, 200);
+        assert_eq!(proposal.snapshot.as_ref().unwrap().total_weight, 1_000);
+        assert!(proposal.status == crate::ProposalStatus::Voting);
+    }
+
+    #[test]
+    fn batch_expiry_only_touches_lapsed_subscriptions() {
+        const DAY: i64 = 24 * 60 * 60;
+        let keeper = Pubkey::new_unique();
+        let mut config: ProtocolConfig = zeroed(ProtocolConfig::INIT_SPACE);
+        config.subscription_grace_secs = 2 * DAY;
+        config.maintenance_fund_lamports = 1_000_000;
+        let (stats_key, bump) = pda(&[b"keeper_stats", keeper.as_ref()]);
+        let mut stats: crate::KeeperStats = zeroed(crate::KeeperStats::INIT_SPACE);
+        stats.keeper = keeper;
+        stats.bump = bump;
+        let subscription = |active_sub, subscription_expiry| {
+            let mut account: UserAccount = zeroed(UserAccount::INIT_SPACE);
+            account.wallet = Pubkey::new_unique();
+            account.active_sub = active_sub;
+            account.subscription_expiry = subscription_expiry;
+            TestAccount::program_owned(Pubkey::new_unique(), &account)
+        };
+        let infos = account_infos(vec![
+            config_account(&mut config),
+            TestAccount::signer(keeper),
+            TestAccount::program_owned(stats_key, &stats),
+            none(),
+            TestAccount::program(anchor_lang::system_program::ID),
+            // Lapsed past its grace period
+            subscription(true, 10 * DAY),
+            // Still valid
+            subscription(true, 30 * DAY),
+            // Expired, but still inside its grace period
+            subscription(true, 19 * DAY),
+            // Lapsed, and already marked inactive
+            subscription(false, 5 * DAY),
+            // Lapsed past its grace period
+            subscription(true, 17 * DAY),
+        ]);
+        let before: Vec<Vec<u8>> = infos[5..]
+            .iter()
+            .map(|info| info.data.borrow().to_vec())
+            .collect();
+
+        set_clock(20 * DAY);
+        capture_events();
+        run::<crate::BatchExpireSubscriptions>(infos.to_vec(), |ctx| {
+            crate::iris_anchor::batch_expire_subscriptions(ctx)
+        })
+        .unwrap();
+
+        let active: Vec<bool> = infos[5..]
+            .iter()
+            .map(|info| Account::<UserAccount>::try_from(info).unwrap().active_sub)
+            .collect();
+        assert_eq!(active, [false, true, true, false, false]);
+        // The accounts left alone weren't rewritten at all
+        for i in [1, 2, 3] {
+            assert_eq!(infos[5 + i].data.borrow().to_vec(), before[i]);
+        }
+        let expired = emitted_events()
+            .iter()
+            .filter(|data| data.starts_with(&crate::SubscriptionAutoExpired::DISCRIMINATOR))
+            .count();
+        assert_eq!(expired, 2);
+        let completed = emitted_events()
+            .into_iter()
+            .find(|data| data.starts_with(&crate::BatchExpirationCompleted::DISCRIMINATOR))
+            .unwrap();
+        let completed = crate::BatchExpirationCompleted::deserialize(&mut &completed[8..]).unwrap();
+        assert_eq!(completed.count, 2);
+    }
+
+    #[test]
+    fn sponsored_user_accounts_refund_rent_to_the_sponsor() {
+        const FUNDED: u64 = 1_000_000_000;
+        let (user, sponsor) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let user_pda = pda(&[b"user", user.as_ref()]).0;
+        let rent = Rent::default().minimum_balance(8 + UserAccount::INIT_SPACE);
+        let infos = account_infos(vec![
+            // Allocated but unfunded, so initialization creates it from the payer
+            TestAccount {
+                lamports: 0,
+                ..TestAccount::with_data(user_pda, crate::ID, vec![0; 8 + UserAccount::INIT_SPACE])
+            },
+            TestAccount::signer(user),
+            TestAccount::signer(sponsor),
+            TestAccount::program(anchor_lang::system_program::ID),
+            config(),
+            none(),
+        ]);
+        let initialize = |payer: usize| {
+            let preferences = zeroed::<UserAccount>(UserAccount::INIT_SPACE).preferences;
+            run::<crate::InitializeUser>(
+                vec![
+                    infos[0].clone(),
+                    infos[1].clone(),
+                    infos[payer].clone(),
+                    infos[3].clone(),
+                ],
+                |ctx| crate::iris_anchor::initialize_user(ctx, preferences),
+            )
+        };
+        let close = |rent_recipient: usize| {
+            run::<crate::CloseUserAccount>(
+                vec![
+                    infos[0].clone(),
+                    infos[5].clone(),
+                    infos[4].clone(),
+                    infos[1].clone(),
+                    infos[rent_recipient].clone(),
+                ],
+                crate::iris_anchor::close_user_account,
+            )
+        };
+        let user_account = || Account::<UserAccount>::try_from(&infos[0]).unwrap();
+
+        // The sponsor funds the account, the user is still the wallet it belongs to
+        initialize(2).unwrap();
+        assert_eq!(user_account().wallet, user);
+        assert_eq!(user_account().rent_payer, sponsor);
+        assert_eq!(infos[2].lamports(), FUNDED - rent);
+        assert_eq!(infos[1].lamports(), FUNDED);
+
+        // On close the rent goes back to the sponsor, not the wallet
+        assert_eq!(
+            close(1).unwrap_err(),
+            ErrorCode::InvalidRentRecipient.into()
+        );
+        close(2).unwrap();
+        assert_eq!(infos[0].lamports(), 0);
+        assert_eq!(infos[2].lamports(), FUNDED);
+        assert_eq!(infos[1].lamports(), FUNDED);
+    }
+
+    #[test]
+    fn burned_policies_refund_rent_to_whoever_funded_them() {
+        const FUNDED: u64 = 1_000_000_000;
+        let (owner, sponsor) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (mint, vault, nft_mint) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let (pool_key, pool) = shared_pool(mint, vault);
+        let burn = |rent_payer: Pubkey, rent_recipient: Pubkey| {
+            let mut policy: crate::InsuranceNFT = zeroed(crate::InsuranceNFT::INIT_SPACE);
+            policy.version = crate::InsuranceNFT::CURRENT_VERSION;
+            policy.owner = owner;
+            policy.pool = pool_key;
+            policy.token_mint = nft_mint;
+            policy.rent_payer = rent_payer;
+            let infos = account_infos(vec![
+                user_account(owner),
+                TestAccount::program_owned(Pubkey::new_unique(), &policy),
+                TestAccount::program_owned(pool_key, &pool),
+                TestAccount::mint(nft_mint),
+                TestAccount::token_account(Pubkey::new_unique(), nft_mint, owner, 1),
+                TestAccount::signer(owner),
+                TestAccount::signer(rent_recipient),
+                config(),
+                TestAccount::program(spl_token::ID),
+            ]);
+            run::<crate::BurnExpiredNft>(infos.to_vec(), crate::iris_anchor::burn_expired_nft)
+                .map(|()| (infos[6].lamports() - FUNDED, infos[5].lamports()))
+        };
+
+        // A sponsored policy's rent goes back to the sponsor, not the owner
+        assert_eq!(
+            burn(sponsor, owner).unwrap_err(),
+            ErrorCode::InvalidRentRecipient.into()
+        );
+        assert_eq!(burn(sponsor, sponsor).unwrap(), (FUNDED, FUNDED));
+        // One the owner paid for, or minted before the payer was recorded, refunds the owner
+        assert_eq!(burn(owner, owner).unwrap().0, FUNDED);
+        assert_eq!(burn(Pubkey::default(), owner).unwrap().0, FUNDED);
+        assert_eq!(
+            burn(Pubkey::default(), sponsor).unwrap_err(),
+            ErrorCode::InvalidRentRecipient.into()
+        );
+    }
+
+    #[test]
+    fn self_funded_user_accounts_refund_rent_to_the_wallet() {
+        const FUNDED: u64 = 1_000_000_000;
+        let user = Pubkey::new_unique();
+        let user_pda = pda(&[b"user", user.as_ref()]).0;
+        let rent = Rent::default().minimum_balance(8 + UserAccount::INIT_SPACE);
+        let infos = account_infos(vec![
+            TestAccount {
+                lamports: 0,
+                ..TestAccount::with_data(user_pda, crate::ID, vec![0; 8 + UserAccount::INIT_SPACE])
+            },
+            TestAccount::signer(user),
+            TestAccount::program(anchor_lang::system_program::ID),
+            config(),
+            none(),
+        ]);
+
+        // The same wallet signs as user and payer
+        let preferences = zeroed::<UserAccount>(UserAccount::INIT_SPACE).preferences;
+        run::<crate::InitializeUser>(
+            vec![
+                infos[0].clone(),
+                infos[1].clone(),
+                infos[1].clone(),
+                infos[2].clone(),
+            ],
+            |ctx| crate::iris_anchor::initialize_user(ctx, preferences),
+        )
+        .unwrap();
+        let user_account = Account::<UserAccount>::try_from(&infos[0]).unwrap();
+        assert_eq!(user_account.wallet, user);
+        assert_eq!(user_account.rent_payer, user);
+        assert_eq!(infos[1].lamports(), FUNDED - rent);
+
+        run::<crate::CloseUserAccount>(
+            vec![
+                infos[0].clone(),
+                infos[4].clone(),
+                infos[3].clone(),
+                infos[1].clone(),
+                infos[1].clone(),
+            ],
+            crate::iris_anchor::close_user_account,
+        )
+        .unwrap();
+        assert_eq!(infos[1].lamports(), FUNDED);
+    }
+
+    #[test]
+    fn custody_log_follows_every_handoff_and_seals_on_submission() {
+        use crate::{CustodyAction, EvidenceChainOfCustody};
+        let claim_id = Pubkey::new_unique();
+        let (investigator, claimant) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let custody_key = pda(&[b"evidence_custody", claim_id.as_ref()]).0;
+        let infos = account_infos(vec![
+            TestAccount {
+                lamports: 0,
+                ..TestAccount::with_data(
+                    custody_key,
+                    crate::ID,
+                    vec![0; 8 + EvidenceChainOfCustody::INIT_SPACE],
+                )
+            },
+            config(),
+            TestAccount::signer(investigator),
+            TestAccount::signer(claimant),
+            TestAccount::program(anchor_lang::system_program::ID),
+        ]);
+        let custody = || Account::<EvidenceChainOfCustody>::try_from(&infos[0]).unwrap();
+        let update = |signer: usize, proof: &[u8]| {
+            set_clock(custody().entries.len() as i64 * 60);
+            run::<crate::UpdateClaimEvidence>(
+                vec![infos[0].clone(), infos[1].clone(), infos[signer].clone()],
+                |ctx| crate::iris_anchor::update_claim_evidence(ctx, proof.to_vec()),
+            )
+        };
+        let transfer = |signer: usize, to: Pubkey| {
+            set_clock(custody().entries.len() as i64 * 60);
+            run::<crate::UpdateClaimEvidence>(
+                vec![infos[0].clone(), infos[1].clone(), infos[signer].clone()],
+                |ctx| crate::iris_anchor::transfer_claim_evidence(ctx, to),
+            )
+        };
+
+        set_clock(0);
+        let proof = b"tx log".to_vec();
+        let args = crate::instruction::OpenClaimEvidence {
+            claim_id,
+            proof: proof.clone(),
+        };
+        run_with_args::<crate::OpenClaimEvidence>(
+            vec![
+                infos[0].clone(),
+                infos[1].clone(),
+                infos[2].clone(),
+                infos[4].clone(),
+            ],
+            &args.data()[8..],
+            |ctx| crate::iris_anchor::open_claim_evidence(ctx, claim_id, proof),
+        )
+        .unwrap();
+        update(2, b"tx log, annotated").unwrap();
+        transfer(2, claimant).unwrap();
+        // Once handed off, only the new custodian can touch it
+        assert_eq!(
+            update(2, b"tampered").unwrap_err(),
+            ErrorCode::NotEvidenceCustodian.into()
+        );
+        update(3, b"tx log, annotated, signed").unwrap();
+
+        // initiate_claim seals the log with the claimant's submission
+        set_clock(custody().entries.len() as i64 * 60);
+        let mut sealed = custody();
+        let mut events = crate::next_event_seq(&mut zeroed(ProtocolConfig::INIT_SPACE)).unwrap();
+        assert_eq!(
+            crate::submit_custody(&mut sealed, &claimant, b"tx log", &mut events).unwrap_err(),
+            ErrorCode::EvidenceMismatch.into()
+        );
+        crate::submit_custody(
+            &mut sealed,
+            &claimant,
+            b"tx log, annotated, signed",
+            &mut events,
+        )
+        .unwrap();
+        sealed.exit(&crate::ID).unwrap();
+
+        // No further changes once submitted, even by the custodian
+        assert_eq!(
+            update(3, b"after the fact").unwrap_err(),
+            ErrorCode::EvidenceAlreadySubmitted.into()
+        );
+        assert_eq!(
+            transfer(3, investigator).unwrap_err(),
+            ErrorCode::EvidenceAlreadySubmitted.into()
+        );
+
+        // Every step is logged in order, each by whoever held the evidence then, and a
+        // handoff or submission carries the digest of the evidence as last modified
+        let entries = custody().into_inner().entries;
+        let actions: Vec<CustodyAction> = entries.iter().map(|entry| entry.action).collect();
+        assert!(
+            actions
+                == [
+                    CustodyAction::Created,
+                    CustodyAction::Modified,
+                    CustodyAction::Transferred,
+                    CustodyAction::Modified,
+                    CustodyAction::Submitted,
+                ]
+        );
+        let handlers: Vec<Pubkey> = entries.iter().map(|entry| entry.handler).collect();
+        assert_eq!(
+            handlers,
+            [investigator, investigator, claimant, claimant, claimant]
+        );
+        assert_eq!(entries[2].hash, entries[1].hash);
+        assert_eq!(entries[4].hash, entries[3].hash);
+        let digest = |proof: &[u8]| anchor_lang::solana_program::hash::hash(proof).to_bytes();
+        assert_eq!(entries[0].hash, digest(b"tx log"));
+        assert_eq!(entries[3].hash, digest(b"tx log, annotated, signed"));
+        assert!(entries
+            .windows(2)
+            .all(|pair| pair[0].timestamp < pair[1].timestamp));
+        assert!(custody().submitted);
+    }
+
+    #[test]
+    fn keeper_reimbursement_rejects_spoofed_accounts() {
+        let keeper = Pubkey::new_unique();
+        let stats_for = |keeper: Pubkey| {
+            let (key, bump) = pda(&[b"keeper_stats", keeper.as_ref()]);
+            let mut stats: crate::KeeperStats = zeroed(crate::KeeperStats::INIT_SPACE);
+            stats.keeper = keeper;
+            stats.bump = bump;
+            TestAccount::program_owned(key, &stats)
+        };
+        let expire = |stats: TestAccount, instructions: TestAccount| {
+            check::<crate::ExpireSubscription>(vec![
+                user_account(Pubkey::new_unique()),
+                config(),
+                TestAccount::signer(keeper),
+                stats,
+                instructions,
+                TestAccount::program(anchor_lang::system_program::ID),
+            ])
+        };
+        let sysvar = || {
+            TestAccount::with_data(
+                anchor_lang::solana_program::sysvar::instructions::ID,
+                anchor_lang::solana_program::sysvar::ID,
+                Vec::new(),
+            )
+        };
+        assert!(expire(stats_for(keeper), sysvar()).is_ok());
+
+        // A fake instructions sysvar could claim any priority fee bid
+        let fake_sysvar = TestAccount::with_data(
+            Pubkey::new_unique(),
+            anchor_lang::solana_program::sysvar::ID,
+            Vec::new(),
+        );
+        assert_eq!(
+            expire(stats_for(keeper), fake_sysvar).unwrap_err(),
+            anchor_lang::error::ErrorCode::ConstraintAddress.into()
+        );
+        // Another keeper's stats would charge that keeper's epoch budget
+        assert_eq!(
+            expire(stats_for(Pubkey::new_unique()), sysvar()).unwrap_err(),
+            anchor_lang::error::ErrorCode::ConstraintSeeds.into()
+        );
+    }
+
+    #[test]
+    fn stream_renewals_only_pay_the_treasury() {
+        let fixture = TreasuryFixture::new();
+        let (stream, stream_program) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut policy: crate::InsuranceNFT = zeroed(crate::InsuranceNFT::INIT_SPACE);
+        policy.stream_config.stream_id = stream;
+        policy.stream_config.stream_program = stream_program;
+        let renew = |treasury_account: TestAccount| {
+            check::<crate::ProcessStreamRenewal>(vec![
+                TestAccount::program_owned(Pubkey::new_unique(), &policy),
+                config(),
+                fixture.account(),
+                treasury_account,
+                TestAccount::with_data(stream, stream_program, Vec::new()),
+                TestAccount::program(stream_program),
+                TestAccount::signer(Pubkey::new_unique()),
+            ])
+        };
+        assert!(renew(fixture.vault()).is_ok());
+        assert_eq!(
+            renew(fixture.attacker_account()).unwrap_err(),
+            ErrorCode::InvalidTreasury.into()
+        );
+    }
+
+    #[test]
+    fn marketplace_fees_only_reach_the_treasury() {
+        let fixture = TreasuryFixture::new();
+        let (seller, buyer, nft_mint) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let policy_key = Pubkey::new_unique();
+        let mut policy: crate::InsuranceNFT = zeroed(crate::InsuranceNFT::INIT_SPACE);
+        policy.owner = seller;
+        let (listing_key, listing_bump) = pda(&[b"nft_listing", policy_key.as_ref()]);
+        let mut listing: crate::NftListing = zeroed(crate::NftListing::INIT_SPACE);
+        listing.nft_mint = nft_mint;
+        listing.seller = seller;
+        listing.bump = listing_bump;
+        let listing_vault = pda(&[b"listing_vault", policy_key.as_ref()]).0;
+        let buy = |treasury_account: TestAccount| {
+            check::<crate::BuyListedNft>(vec![
+                TestAccount::program_owned(listing_key, &listing),
+                TestAccount::program_owned(policy_key, &policy),
+                TestAccount::token_account(listing_vault, nft_mint, listing_vault, 1),
+                TestAccount::mint(nft_mint),
+                TestAccount::token_account(Pubkey::new_unique(), nft_mint, buyer, 0),
+                TestAccount::token_account(Pubkey::new_unique(), fixture.mint, buyer, 100),
+                TestAccount::token_account(Pubkey::new_unique(), fixture.mint, seller, 0),
+                user_account(seller),
+                user_account(buyer),
+                fixture.account(),
+                treasury_account,
+                TestAccount::mint(fixture.mint),
+                config(),
+                TestAccount::with_data(seller, anchor_lang::system_program::ID, Vec::new()),
+                TestAccount::signer(buyer),
+                TestAccount::program(spl_token::ID),
+            ])
+        };
+        assert!(buy(fixture.vault()).is_ok());
+        assert_eq!(
+            buy(fixture.attacker_account()).unwrap_err(),
+            ErrorCode::InvalidTreasury.into()
+        );
+    }
+
+    #[test]
+    fn buybacks_only_deliver_to_the_treasury() {
+        let fixture = TreasuryFixture::new();
+        let iris_mint = Pubkey::new_unique();
+        let (proposal_key, proposal_bump) = pda(&[b"proposal", 7u64.to_le_bytes().as_ref()]);
+        let mut proposal: crate::Proposal = zeroed(crate::Proposal::INIT_SPACE);
+        proposal.id = 7;
+        proposal.bump = proposal_bump;
+        let (governance_key, governance_bump) = pda(&[b"governance"]);
+        let mut governance: crate::Governance = zeroed(crate::Governance::INIT_SPACE);
+        governance.bump = governance_bump;
+        let buyback = |iris_account: TestAccount| {
+            check::<crate::BuybackAndBurn>(vec![
+                TestAccount::program_owned(proposal_key, &proposal),
+                TestAccount::program_owned(governance_key, &governance),
+                config(),
+                fixture.account(),
+                fixture.vault(),
+                iris_account,
+                TestAccount::mint(iris_mint),
+                TestAccount::program(Pubkey::new_unique()),
+                TestAccount::signer(Pubkey::new_unique()),
+                TestAccount::program(spl_token::ID),
+            ])
+        };
+        let treasury_iris =
+            TestAccount::token_account(Pubkey::new_unique(), iris_mint, fixture.key, 0);
+        assert!(buyback(treasury_iris).is_ok());
+        let attacker_iris =
+            TestAccount::token_account(Pubkey::new_unique(), iris_mint, Pubkey::new_unique(), 0);
+        assert_eq!(
+            buyback(attacker_iris).unwrap_err(),
+            ErrorCode::InvalidTreasury.into()
+        );
+        // The treasury's own account of another mint doesn't take the bought tokens either
+        assert_eq!(
+            buyback(fixture.vault()).unwrap_err(),
+            ErrorCode::InvalidTreasury.into()
+        );
+    }
+
+    // A program-owned ClaimVoteRecord on `claim` proposing `amount`
+    fn vote_record(claim: Pubkey, amount: Option<u64>) -> TestAccount {
+        let mut record: crate::ClaimVoteRecord = zeroed(crate::ClaimVoteRecord::INIT_SPACE);
+        record.claim = claim;
+        record.voter = Pubkey::new_unique();
+        record.approved_amount = amount;
+        TestAccount::program_owned(Pubkey::new_unique(), &record)
+    }
+
+    #[test]
+    fn partial_approval_pays_the_median_of_proposed_amounts() {
+        let claim_key = Pubkey::new_unique();
+        let mut claim: crate::Claim = zeroed(crate::Claim::INIT_SPACE);
+        claim.amount = 1_000;
+        let infos = account_infos(vec![
+            TestAccount::program_owned(claim_key, &claim),
+            vote_record(claim_key, Some(900)),
+            vote_record(claim_key, Some(300)),
+            vote_record(claim_key, Some(600)),
+        ]);
+        let mut claim = Account::<crate::Claim>::try_from(&infos[0]).unwrap();
+
+        // The quorum-reaching vote's own amount joins the earlier voters' records
+        let mut amounts = crate::proposed_claim_amounts(&claim, &infos[2..]).unwrap();
+        amounts.push(900);
+        claim.approved_amount = logic::median_amount(&mut amounts);
+        assert_eq!(claim.payable_amount(), 600);
+        // Before a partial approval the claim pays what was requested
+        claim.approved_amount = 0;
+        assert_eq!(claim.payable_amount(), 1_000);
+
+        // A record listed twice, a record on another claim, or one without an amount
+        // can't stuff the ballot
+        let invalid: Error = ErrorCode::InvalidRemainingAccounts.into();
+        let repeated = [infos[1].clone(), infos[1].clone()];
+        let repeated: &'static [AccountInfo<'static>] = Box::leak(Box::new(repeated));
+        assert_eq!(
+            crate::proposed_claim_amounts(&claim, repeated).unwrap_err(),
+            invalid
+        );
+        let others = account_infos(vec![vote_record(Pubkey::new_unique(), Some(1))]);
+        assert_eq!(
+            crate::proposed_claim_amounts(&claim, others).unwrap_err(),
+            invalid
+        );
+        let plain = account_infos(vec![vote_record(claim_key, None)]);
+        assert_eq!(
+            crate::proposed_claim_amounts(&claim, plain).unwrap_err(),
+            invalid
+        );
+    }
+
+    // Validate `T` over `infos` and run `handler` on it, then write the accounts back as
+    // the runtime does when an instruction succeeds
+    fn run<T>(
+        infos: Vec<AccountInfo<'static>>,
+        handler: impl FnOnce(Context<'_, '_, 'static, 'static, T>) -> Result<()>,
+    ) -> Result<()>
+    where
+        T: Accounts<'static, <T as Bumps>::Bumps> + AccountsExit<'static> + Bumps,
+        <T as Bumps>::Bumps: Default,
+    {
+        run_with_args(infos, &[], handler)
+    }
+
+    // As run, for contexts that read the instruction's arguments (`ix_data` without
+    // the discriminator)
+    fn run_with_args<T>(
+        infos: Vec<AccountInfo<'static>>,
+        ix_data: &[u8],
+        handler: impl FnOnce(Context<'_, '_, 'static, 'static, T>) -> Result<()>,
+    ) -> Result<()>
+    where
+        T: Accounts<'static, <T as Bumps>::Bumps> + AccountsExit<'static> + Bumps,
+        <T as Bumps>::Bumps: Default,
+    {
+        install_syscalls();
+        let mut infos: &'static [AccountInfo<'static>] = Box::leak(infos.into_boxed_slice());
+        let mut bumps = Default::default();
+        let mut accounts = T::try_accounts(
+            &crate::ID,
+            &mut infos,
+            ix_data,
+            &mut bumps,
+            &mut BTreeSet::new(),
+        )?;
+        handler(Context::new(&crate::ID, &mut accounts, infos, bumps))?;
+        accounts.exit(&crate::ID)
+    }
+
+    fn token_balance(info: &AccountInfo) -> u64 {
+        spl_token::state::Account::unpack(&info.try_borrow_data().unwrap())
+            .unwrap()
+            .amount
+    }
+
+    // Signed movement of one ledger event into (+) or out of (-) the pool vault and the
+    // treasury vault
+    struct Entry {
+        ledger_seq: u64,
+        pool: i128,
+        treasury: i128,
+    }
+
+    // The ledger events among `events`, decoded
+    fn ledger(events: &[Vec<u8>]) -> Vec<Entry> {
+        use crate::{PoolDeposit, PoolWithdrawal, TreasuryCredit, TreasuryDebit};
+        events
+            .iter()
+            .filter_map(|data| {
+                let (discriminator, mut fields) = data.split_at(8);
+                let entry = |ledger_seq, pool, treasury| {
+                    Some(Entry {
+                        ledger_seq,
+                        pool,
+                        treasury,
+                    })
+                };
+                match discriminator {
+                    d if d == PoolDeposit::DISCRIMINATOR => {
+                        let event = PoolDeposit::deserialize(&mut fields).unwrap();
+                        entry(event.ledger_seq, event.amount as i128, 0)
+                    }
+                    d if d == PoolWithdrawal::DISCRIMINATOR => {
+                        let event = PoolWithdrawal::deserialize(&mut fields).unwrap();
+                        entry(event.ledger_seq, -(event.amount as i128), 0)
+                    }
+                    d if d == TreasuryCredit::DISCRIMINATOR => {
+                        let event = TreasuryCredit::deserialize(&mut fields).unwrap();
+                        entry(event.ledger_seq, 0, event.amount as i128)
+                    }
+                    d if d == TreasuryDebit::DISCRIMINATOR => {
+                        let event = TreasuryDebit::deserialize(&mut fields).unwrap();
+                        entry(event.ledger_seq, 0, -(event.amount as i128))
+                    }
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn ledger_replay_has_no_gaps_and_matches_balances() {
+        let (mint, lp, subscriber, recipient) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let pool_vault = Pubkey::new_unique();
+        let (pool_key, pool) = shared_pool(mint, pool_vault);
+        let fixture = TreasuryFixture::new();
+        let mut config: ProtocolConfig = zeroed(ProtocolConfig::INIT_SPACE);
+        config.max_withdrawal_bps_per_epoch = 10_000;
+        config.max_pool_withdrawal_bps_per_epoch = 10_000;
+        let token =
+            |owner, amount| TestAccount::token_account(Pubkey::new_unique(), mint, owner, amount);
+        let treasury_token = |owner, amount| {
+            TestAccount::token_account(Pubkey::new_unique(), fixture.mint, owner, amount)
+        };
+
+        let infos = account_infos(vec![
+            TestAccount::program_owned(pool_key, &pool),
+            config_account(&mut config),
+            TestAccount::token_account(pool_vault, mint, pool_key, 0),
+            // Not yet opened: the first deposit fills it in
+            TestAccount::program_owned(
+                pda(&[b"lp_position", pool_key.as_ref(), lp.as_ref()]).0,
+                &zeroed::<crate::LpPosition>(crate::LpPosition::INIT_SPACE),
+            ),
+            none(),
+            token(lp, 5_000_000),
+            TestAccount::signer(lp),
+            TestAccount::mint(mint),
+            TestAccount::program(spl_token::ID),
+            TestAccount::program(anchor_lang::system_program::ID),
+            fixture.account(),
+            fixture.vault(),
+            TestAccount::mint(fixture.mint),
+            treasury_token(subscriber, 50_000),
+            TestAccount::signer(subscriber),
+            treasury_token(recipient, 0),
+        ]);
+        let [pool_info, config_info, pool_vault_info, position, no_epoch, lp_tokens, lp_signer, pool_mint, token_program, system_program, treasury_info, treasury_vault_info, treasury_mint, subscriber_tokens, subscriber_signer, recipient_tokens] =
+            infos
+        else {
+            unreachable!()
+        };
+        let deposit_accounts = || {
+            vec![
+                pool_info.clone(),
+                config_info.clone(),
+                pool_vault_info.clone(),
+                position.clone(),
+                no_epoch.clone(),
+                lp_tokens.clone(),
+                lp_signer.clone(),
+                pool_mint.clone(),
+                token_program.clone(),
+                system_program.clone(),
+            ]
+        };
+        let withdraw_accounts = || {
+            let mut accounts = deposit_accounts();
+            accounts.pop();
+            accounts
+        };
+        let treasury_ix = |amount: u64, credit: bool| -> Result<()> {
+            let mut config = Account::<ProtocolConfig>::try_from(config_info)?;
+            let mut treasury = Account::<crate::Treasury>::try_from(treasury_info)?;
+            let mut vault = InterfaceAccount::<TokenAccount>::try_from(treasury_vault_info)?;
+            let mint = InterfaceAccount::<Mint>::try_from(treasury_mint)?;
+            let program = Interface::<TokenInterface>::try_from(token_program)?;
+            let mut events = crate::next_event_seq(&mut config)?;
+            if credit {
+                // A subscription fee, moved in before it is accounted for
+                let payer = InterfaceAccount::<TokenAccount>::try_from(subscriber_tokens)?;
+                crate::tokens::transfer_in(
+                    &program,
+                    &payer,
+                    &mut vault,
+                    &mint,
+                    subscriber_signer.clone(),
+                    amount,
+                )?;
+                crate::treasury::credit(
+                    &mut treasury,
+                    &mut config,
+                    &mut events,
+                    subscriber,
+                    amount,
+                    crate::LedgerInstruction::Subscribe,
+                )?;
+            } else {
+                crate::treasury::debit(
+                    &mut treasury,
+                    &vault,
+                    &mint,
+                    recipient_tokens.clone(),
+                    &program,
+                    &mut config,
+                    &mut events,
+                    amount,
+                    crate::LedgerInstruction::DistributeFees,
+                    None,
+                )?;
+            }
+            treasury.exit(&crate::ID)?;
+            config.exit(&crate::ID)
+        };
+
+        capture_events();
+        run::<crate::DepositToPool>(deposit_accounts(), |ctx| {
+            crate::iris_anchor::deposit_to_pool(ctx, 3_000_000)
+        })
+        .unwrap();
+        treasury_ix(20_000, true).unwrap();
+        run::<crate::DepositToPool>(deposit_accounts(), |ctx| {
+            crate::iris_anchor::deposit_to_pool(ctx, 1_000_000)
+        })
+        .unwrap();
+        treasury_ix(7_500, false).unwrap();
+        let shares = Account::<crate::LpPosition>::try_from(position)
+            .unwrap()
+            .shares;
+        run::<crate::WithdrawFromPool>(withdraw_accounts(), |ctx| {
+            crate::iris_anchor::withdraw_from_pool(ctx, shares / 2)
+        })
+        .unwrap();
+        treasury_ix(5_000, true).unwrap();
+
+        let ledger = ledger(&emitted_events());
+        assert_eq!(ledger.len(), 6);
+        // Positions run 1, 2, 3... with nothing skipped or repeated
+        let seqs: Vec<u64> = ledger.iter().map(|entry| entry.ledger_seq).collect();
+        assert_eq!(seqs, (1..=6).collect::<Vec<_>>());
+        let config = Account::<ProtocolConfig>::try_from(config_info).unwrap();
+        assert_eq!(config.ledger_seq, 6);
+
+        // Replaying the amounts gives the vault balances the transfers left
+        let pool_total: i128 = ledger.iter().map(|entry| entry.pool).sum();
+        let treasury_total: i128 = ledger.iter().map(|entry| entry.treasury).sum();
+        assert_eq!(pool_total, token_balance(pool_vault_info) as i128);
+        assert_eq!(treasury_total, token_balance(treasury_vault_info) as i128);
+        assert_eq!(token_balance(lp_tokens) as i128, 5_000_000 - pool_total);
+        assert_eq!(token_balance(recipient_tokens), 7_500);
+        let treasury = Account::<crate::Treasury>::try_from(treasury_info).unwrap();
+        assert_eq!(
+            treasury.total_inflows as i128 - treasury.total_outflows as i128,
+            treasury_total
+        );
+    }
+
+    #[test]
+    fn top_up_batch_fits_one_v0_transaction() {
+        let payer = Pubkey::new_unique();
+        let items = 16;
+        let (ix, per_item) = top_up_shortfalls_ix(items);
+        let fixed = ix.accounts.len() - per_item.len();
+        assert!(items <= logic::batch_max_items(fixed, crate::ProtocolConfig::TOP_UP_BATCH_STRIDE));
+
+        // Inline, 32 per-item keys overflow the packet
+        assert!(legacy_transaction_size(&payer, std::slice::from_ref(&ix)) > PACKET_DATA_SIZE);
+
+        let (message, size) = v0_transaction(&payer, &[ix], per_item.clone());
+        assert!(size <= PACKET_DATA_SIZE);
+        let VersionedMessage::V0(message) = message else {
+            unreachable!()
+        };
+        assert_eq!(message.address_table_lookups.len(), 1);
+        assert_eq!(
+            message.address_table_lookups[0].writable_indexes.len(),
+            per_item.len()
+        );
+        assert!(per_item
+            .iter()
+            .all(|key| !message.account_keys.contains(key)));
+    }
+}
@@ -0,0 +1,149 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_spl::token_2022::spl_token_2022::extension::transfer_hook::TransferHook;
+use anchor_spl::token_2022::spl_token_2022::extension::{
+    BaseStateWithExtensions, StateWithExtensions,
+};
+use anchor_spl::token_2022::spl_token_2022::state::Mint as MintState;
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
+
+use crate::ErrorCode;
+
+// Token movements for both the legacy token program and Token-2022. Everything goes
+// through transfer_checked, which Token-2022 requires for mints with extensions.
+
+// Move `amount` out of an account the caller controls (the signer or a program PDA)
+pub fn transfer<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    from: AccountInfo<'info>,
+    to: AccountInfo<'info>,
+    mint: &InterfaceAccount<'info, Mint>,
+    authority: AccountInfo<'info>,
+    amount: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    token_interface::transfer_checked(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            TransferChecked {
+                from,
+                mint: mint.to_account_info(),
+                to,
+                authority,
+            },
+            signer_seeds,
+        ),
+        amount,
+        mint.decimals,
+    )
+}
+
+// Move `amount` from a user into a protocol account and check that all of it arrived.
+// Mints that charge a transfer fee deliver less, which the protocol's accounting can't
+// absorb, so those transfers are rejected.
+pub fn transfer_in<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    from: &InterfaceAccount<'info, TokenAccount>,
+    to: &mut InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    authority: AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    let balance_before = to.amount;
+    transfer(
+        token_program,
+        from.to_account_info(),
+        to.to_account_info(),
+        mint,
+        authority,
+        amount,
+        &[],
+    )?;
+    to.reload()?;
+    require!(
+        to.amount.saturating_sub(balance_before) == amount,
+        ErrorCode::TransferFeeNotSupported
+    );
+    Ok(())
+}
+
+// Transfer hook program a mint's data names, if any. Legacy mints have no extensions.
+pub fn transfer_hook_program(mint_data: &[u8]) -> Result<Option<Pubkey>> {
+    if mint_data.len() <= MintState::LEN {
+        return Ok(None);
+    }
+    let mint = StateWithExtensions::<MintState>::unpack(mint_data)?;
+    Ok(mint
+        .get_extension::<TransferHook>()
+        .ok()
+        .and_then(|hook| Option::<Pubkey>::from(hook.program_id)))
+}
+
+// Fail for mints whose transfer hook runs a program outside `allowlist`
+pub fn require_approved_hook(mint: &InterfaceAccount<Mint>, allowlist: &[Pubkey]) -> Result<()> {
+    let data = mint.to_account_info().try_borrow_data()?.to_vec();
+    if let Some(program) = transfer_hook_program(&data)? {
+        require!(
+            allowlist.contains(&program),
+            ErrorCode::UnapprovedTransferHook
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::solana_program::program_option::COption;
+    use anchor_spl::token_2022::spl_token_2022::extension::{
+        BaseStateWithExtensionsMut, ExtensionType, StateWithExtensionsMut,
+    };
+
+    fn legacy_mint() -> Vec<u8> {
+        let mut data = vec![0; MintState::LEN];
+        MintState {
+            mint_authority: COption::None,
+            supply: 1_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        }
+        .pack_into_slice(&mut data);
+        data
+    }
+
+    fn token_2022_mint(hook: Option<Pubkey>) -> Vec<u8> {
+        let len =
+            ExtensionType::try_calculate_account_len::<MintState>(&[ExtensionType::TransferHook])
+                .unwrap();
+        let mut data = vec![0; len];
+        let mut mint =
+            StateWithExtensionsMut::<MintState>::unpack_uninitialized(&mut data).unwrap();
+        let extension = mint.init_extension::<TransferHook>(true).unwrap();
+        extension.program_id = hook.try_into().unwrap();
+        mint.base = MintState {
+            decimals: 6,
+            is_initialized: true,
+            ..Default::default()
+        };
+        mint.pack_base();
+        mint.init_account_type().unwrap();
+        data
+    }
+
+    #[test]
+    fn legacy_mints_have_no_hook() {
+        assert_eq!(transfer_hook_program(&legacy_mint()).unwrap(), None);
+    }
+
+    #[test]
+    fn token_2022_hook_program_is_read() {
+        let hook = Pubkey::new_unique();
+        assert_eq!(
+            transfer_hook_program(&token_2022_mint(Some(hook))).unwrap(),
+            Some(hook)
+        );
+        // The extension without a program configured runs no hook
+        assert_eq!(transfer_hook_program(&token_2022_mint(None)).unwrap(), None);
+    }
+}
@@ -0,0 +1,102 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::{
+    next_ledger_seq, tokens, ErrorCode, EventSeq, LedgerInstruction, ProtocolConfig, Treasury,
+    TreasuryCredit, TreasuryDebit,
+};
+
+// Fail unless `balance_after` keeps the treasury at or above the configured floor
+pub fn ensure_reserve(config: &ProtocolConfig, balance_after: u64) -> Result<()> {
+    require!(
+        balance_after >= config.treasury_reserve_floor,
+        ErrorCode::ReserveFloorBreached
+    );
+    Ok(())
+}
+
+// True when `account` is a treasury-owned token account of `mint`. Every context that
+// credits the treasury pins its destination with this.
+pub fn is_treasury(treasury: &Account<Treasury>, account: &TokenAccount, mint: &Pubkey) -> bool {
+    account.owner == treasury.key() && account.mint == *mint
+}
+
+// Account for tokens the caller has already moved into the treasury vault
+pub fn credit(
+    treasury: &mut Treasury,
+    config: &mut ProtocolConfig,
+    events: &mut EventSeq,
+    counterparty: Pubkey,
+    amount: u64,
+    instruction: LedgerInstruction,
+) -> Result<()> {
+    treasury.total_inflows = treasury
+        .total_inflows
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    emit_seq!(
+        events,
+        TreasuryCredit {
+            ledger_seq: next_ledger_seq(config)?,
+            instruction,
+            mint: treasury.mint,
+            counterparty,
+            amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        }
+    );
+    Ok(())
+}
+
+// The single path for moving tokens out of the treasury vault. Every outflow goes
+// through here so the reserve floor and outflow accounting can't be skipped.
+#[allow(clippy::too_many_arguments)]
+pub fn debit<'info>(
+    treasury: &mut Account<'info, Treasury>,
+    vault: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    destination: AccountInfo<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+    config: &mut ProtocolConfig,
+    events: &mut EventSeq,
+    amount: u64,
+    instruction: LedgerInstruction,
+    reference: Option<[u8; 16]>,
+) -> Result<()> {
+    let balance_after = vault
+        .amount
+        .checked_sub(amount)
+        .ok_or(ErrorCode::ReserveFloorBreached)?;
+    ensure_reserve(config, balance_after)?;
+
+    treasury.total_outflows = treasury
+        .total_outflows
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    emit_seq!(
+        events,
+        TreasuryDebit {
+            ledger_seq: next_ledger_seq(config)?,
+            instruction,
+            mint: treasury.mint,
+            counterparty: destination.key(),
+            amount,
+            reference,
+            timestamp: Clock::get()?.unix_timestamp,
+        }
+    );
+
+    let bump = [treasury.bump];
+    let signer_seeds: &[&[&[u8]]] = &[&[b"treasury", &bump]];
+    tokens::transfer(
+        token_program,
+        vault.to_account_info(),
+        destination,
+        mint,
+        treasury.to_account_info(),
+        amount,
+        signer_seeds,
+    )
+}
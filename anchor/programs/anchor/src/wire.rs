@@ -0,0 +1,186 @@
+use anchor_lang::prelude::*;
+
+use crate::ScoreMessage;
+
+// Canonical bytes of a signed score, shared with the off-chain oracle. The layout is
+// fixed here rather than left to Borsh so both sides can be checked against the
+// vectors in tests/vectors/score_message.txt. Integers are little-endian.
+//
+//   version    u8        WIRE_VERSION
+//   domain     11 bytes  SCORE_DOMAIN_TAG || chain_id
+//   program_id 32 bytes  the program the score is meant for
+//   wallet     32 bytes
+//   score      u8
+//   category   u8
+//   token      u8 tag    0 for a wallet-level score, 1 followed by the 32-byte mint
+//   timestamp  i64
+//   nonce      u64
+//
+// Any change to the layout bumps WIRE_VERSION.
+pub const WIRE_VERSION: u8 = 1;
+pub const SCORE_DOMAIN_TAG: &[u8; 10] = b"IRIS_SCORE";
+
+// Category of a score covering the whole wallet
+pub const SCORE_CATEGORY_OVERALL: u8 = 0;
+
+const PUBKEY_LEN: usize = 32;
+const HEADER_LEN: usize = 1 + SCORE_DOMAIN_TAG.len() + 1;
+
+pub fn encode_score_message(program_id: &Pubkey, message: &ScoreMessage) -> Vec<u8> {
+    let mut data = Vec::with_capacity(HEADER_LEN + 2 * PUBKEY_LEN + 3 + PUBKEY_LEN + 16);
+    data.push(WIRE_VERSION);
+    data.extend_from_slice(SCORE_DOMAIN_TAG);
+    data.push(message.chain_id);
+    data.extend_from_slice(program_id.as_ref());
+    data.extend_from_slice(message.wallet.as_ref());
+    data.push(message.score);
+    data.push(message.category);
+    match message.token {
+        Some(token) => {
+            data.push(1);
+            data.extend_from_slice(token.as_ref());
+        }
+        None => data.push(0),
+    }
+    data.extend_from_slice(&message.timestamp.to_le_bytes());
+    data.extend_from_slice(&message.nonce.to_le_bytes());
+    data
+}
+
+// (program_id, message) of bytes in exactly the current layout; None for anything else,
+// including trailing bytes
+pub fn decode_score_message(data: &[u8]) -> Option<(Pubkey, ScoreMessage)> {
+    let mut reader = Reader { data };
+    if reader.u8()? != WIRE_VERSION || reader.take(SCORE_DOMAIN_TAG.len())? != SCORE_DOMAIN_TAG {
+        return None;
+    }
+    let chain_id = reader.u8()?;
+    let program_id = reader.pubkey()?;
+    let wallet = reader.pubkey()?;
+    let score = reader.u8()?;
+    let category = reader.u8()?;
+    let token = match reader.u8()? {
+        0 => None,
+        1 => Some(reader.pubkey()?),
+        _ => return None,
+    };
+    let timestamp = i64::from_le_bytes(reader.take(8)?.try_into().ok()?);
+    let nonce = u64::from_le_bytes(reader.take(8)?.try_into().ok()?);
+    if !reader.data.is_empty() {
+        return None;
+    }
+    Some((
+        program_id,
+        ScoreMessage {
+            wallet,
+            score,
+            category,
+            token,
+            timestamp,
+            nonce,
+            chain_id,
+        },
+    ))
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.data.len() < len {
+            return None;
+        }
+        let (head, rest) = self.data.split_at(len);
+        self.data = rest;
+        Some(head)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|b| b[0])
+    }
+
+    fn pubkey(&mut self) -> Option<Pubkey> {
+        Pubkey::try_from(self.take(PUBKEY_LEN)?).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    // One vector per line: name chain_id program_id wallet score category token timestamp
+    // nonce hex, with "-" for no token
+    const VECTORS: &str = include_str!("../../../tests/vectors/score_message.txt");
+
+    fn vectors() -> Vec<(String, Pubkey, ScoreMessage, Vec<u8>)> {
+        VECTORS
+            .lines()
+            .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let f: Vec<&str> = line.split_whitespace().collect();
+                assert_eq!(f.len(), 10, "{line}");
+                let message = ScoreMessage {
+                    wallet: Pubkey::from_str(f[3]).unwrap(),
+                    score: f[4].parse().unwrap(),
+                    category: f[5].parse().unwrap(),
+                    token: (f[6] != "-").then(|| Pubkey::from_str(f[6]).unwrap()),
+                    timestamp: f[7].parse().unwrap(),
+                    nonce: f[8].parse().unwrap(),
+                    chain_id: f[1].parse().unwrap(),
+                };
+                let bytes = (0..f[9].len())
+                    .step_by(2)
+                    .map(|i| u8::from_str_radix(&f[9][i..i + 2], 16).unwrap())
+                    .collect();
+                (
+                    f[0].to_string(),
+                    Pubkey::from_str(f[2]).unwrap(),
+                    message,
+                    bytes,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn matches_the_shared_vectors() {
+        let vectors = vectors();
+        assert!(vectors.len() >= 3);
+        for (name, program_id, message, bytes) in vectors {
+            assert_eq!(encode_score_message(&program_id, &message), bytes, "{name}");
+            assert_eq!(
+                decode_score_message(&bytes),
+                Some((program_id, message)),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_other_layouts() {
+        let (_, program_id, message, bytes) = vectors().remove(0);
+        assert_eq!(bytes[0], WIRE_VERSION);
+
+        let mut other_version = bytes.clone();
+        other_version[0] = WIRE_VERSION + 1;
+        assert_eq!(decode_score_message(&other_version), None);
+
+        let mut other_domain = bytes.clone();
+        other_domain[1] ^= 0x20;
+        assert_eq!(decode_score_message(&other_domain), None);
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(decode_score_message(&trailing), None);
+        assert_eq!(decode_score_message(&bytes[..bytes.len() - 1]), None);
+
+        // The token tag is 0 or 1
+        let tag_at = HEADER_LEN + 2 * PUBKEY_LEN + 2;
+        let mut bad_tag = encode_score_message(&program_id, &message);
+        bad_tag[tag_at] = 2;
+        assert_eq!(decode_score_message(&bad_tag), None);
+    }
+}
@@ -0,0 +1,334 @@
+use anchor::{
+    Claim, ClaimStaking, FeeDistributor, Governance, InsuranceNFT, InsurancePool, Proposal,
+    ProtocolConfig, Treasury, UserAccount,
+};
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AccountDeserialize, InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::signature::{Keypair, Signature};
+
+use crate::{build_instruction, deserialize, pda, sign, Error, Result, Rpc};
+
+// Builders for each of the program's instructions, grouped by area. Each derives the
+// PDAs it needs, reads whatever else it must from chain, signs with the keypairs it is
+// given and sends.
+mod admin;
+mod claims;
+#[cfg(test)]
+mod fixture;
+mod governance;
+mod oracles;
+mod policies;
+mod pools;
+mod staking;
+mod users;
+
+pub struct IrisClient<R> {
+    rpc: R,
+    program_id: Pubkey,
+}
+
+impl<R: Rpc> IrisClient<R> {
+    pub fn new(rpc: R) -> Self {
+        Self::with_program_id(rpc, anchor::ID)
+    }
+
+    // For a deployment at an address other than the one the program declares
+    pub fn with_program_id(rpc: R, program_id: Pubkey) -> Self {
+        Self { rpc, program_id }
+    }
+
+    pub fn program_id(&self) -> Pubkey {
+        self.program_id
+    }
+
+    pub fn rpc(&self) -> &R {
+        &self.rpc
+    }
+
+    pub fn instruction(
+        &self,
+        accounts: impl ToAccountMetas,
+        args: impl InstructionData,
+    ) -> Instruction {
+        build_instruction(self.program_id, accounts, args)
+    }
+
+    // Send `instructions` in one transaction. The first signer pays the fee.
+    pub async fn send(
+        &self,
+        signers: &[&Keypair],
+        instructions: &[Instruction],
+    ) -> Result<Signature> {
+        let blockhash = self.rpc.latest_blockhash().await?;
+        let transaction = sign(instructions, signers, blockhash)?;
+        self.rpc.send_transaction(&transaction).await
+    }
+
+    pub async fn fetch<T: AccountDeserialize>(&self, address: &Pubkey) -> Result<T> {
+        let account = self
+            .rpc
+            .get_account(address)
+            .await?
+            .ok_or(Error::AccountNotFound(*address))?;
+        deserialize(address, &account)
+    }
+
+    // Owner of an account, used to tell legacy token accounts from Token-2022 ones
+    async fn owner(&self, address: &Pubkey) -> Result<Pubkey> {
+        let account = self
+            .rpc
+            .get_account(address)
+            .await?
+            .ok_or(Error::AccountNotFound(*address))?;
+        Ok(account.owner)
+    }
+
+    pub async fn config(&self) -> Result<ProtocolConfig> {
+        self.fetch(&pda::config(&self.program_id)).await
+    }
+
+    pub async fn user_account(&self, wallet: &Pubkey) -> Result<UserAccount> {
+        self.fetch(&pda::user_account(&self.program_id, wallet))
+            .await
+    }
+
+    pub async fn insurance_nft(&self, address: &Pubkey) -> Result<InsuranceNFT> {
+        self.fetch(address).await
+    }
+
+    pub async fn claim(&self, address: &Pubkey) -> Result<Claim> {
+        self.fetch(address).await
+    }
+
+    pub async fn pool(&self, tier: u8) -> Result<InsurancePool> {
+        self.fetch(&pda::pool(&self.program_id, tier)).await
+    }
+
+    pub async fn treasury(&self) -> Result<Treasury> {
+        self.fetch(&pda::treasury(&self.program_id)).await
+    }
+
+    pub async fn governance(&self) -> Result<Governance> {
+        self.fetch(&pda::governance(&self.program_id)).await
+    }
+
+    pub async fn proposal(&self, id: u64) -> Result<Proposal> {
+        self.fetch(&pda::proposal(&self.program_id, id)).await
+    }
+
+    pub async fn fee_distributor(&self) -> Result<FeeDistributor> {
+        self.fetch(&pda::fee_distributor(&self.program_id)).await
+    }
+
+    // Quorum weight and unstake cooldown now in force for claim votes
+    pub async fn claim_staking(&self) -> Result<ClaimStaking> {
+        self.fetch(&pda::claim_staking(&self.program_id)).await
+    }
+
+    // Associated token account of `owner` for `mint`, under whichever token program
+    // owns the mint
+    async fn token_account(&self, owner: &Pubkey, mint: &Pubkey) -> Result<Pubkey> {
+        let token_program = self.owner(mint).await?;
+        Ok(get_associated_token_address_with_program_id(
+            owner,
+            mint,
+            &token_program,
+        ))
+    }
+
+    // `address` if the account exists, for optional accounts the program only reads
+    // when they do
+    async fn existing(&self, address: Pubkey) -> Result<Option<Pubkey>> {
+        Ok(self.rpc.get_account(&address).await?.map(|_| address))
+    }
+
+    // Event accounts of instructions that emit through a self-CPI
+    fn event_authority(&self) -> Pubkey {
+        pda::event_authority(&self.program_id)
+    }
+}
+
+// `instruction` with `accounts` appended as its remaining accounts
+fn with_remaining(
+    mut instruction: Instruction,
+    accounts: impl IntoIterator<Item = AccountMeta>,
+) -> Instruction {
+    instruction.accounts.extend(accounts);
+    instruction
+}
+
+// Mark `signer` as signing `instruction`, for accounts the program takes unchecked but
+// passes on as an authority
+fn require_signature(instruction: &mut Instruction, signer: &Pubkey) {
+    for meta in instruction.accounts.iter_mut() {
+        if meta.pubkey == *signer {
+            meta.is_signer = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::local::block_on;
+    use anchor::{instruction, RiskParams};
+    use anchor_lang::{AccountSerialize, Discriminator};
+    use solana_sdk::account::Account;
+    use solana_sdk::hash::Hash;
+    use solana_sdk::signature::Signer;
+    use solana_sdk::transaction::Transaction;
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+
+    // Node stand-in that records what it is sent
+    #[derive(Default)]
+    struct MockRpc {
+        accounts: HashMap<Pubkey, Account>,
+        sent: RefCell<Vec<Transaction>>,
+    }
+
+    impl MockRpc {
+        fn insert<T: AccountSerialize>(&mut self, address: Pubkey, owner: Pubkey, value: &T) {
+            let mut data = Vec::new();
+            value.try_serialize(&mut data).unwrap();
+            self.accounts.insert(
+                address,
+                Account {
+                    data,
+                    owner,
+                    ..Account::default()
+                },
+            );
+        }
+    }
+
+    impl Rpc for MockRpc {
+        async fn latest_blockhash(&self) -> Result<Hash> {
+            Ok(Hash::new_unique())
+        }
+
+        async fn send_transaction(&self, transaction: &Transaction) -> Result<Signature> {
+            transaction
+                .verify()
+                .map_err(|err| Error::Rpc(err.to_string()))?;
+            self.sent.borrow_mut().push(transaction.clone());
+            Ok(transaction.signatures[0])
+        }
+
+        async fn get_account(&self, address: &Pubkey) -> Result<Option<Account>> {
+            Ok(self.accounts.get(address).cloned())
+        }
+    }
+
+    fn preferences() -> RiskParams {
+        RiskParams {
+            risk_threshold: 70,
+            watchlist: vec![Pubkey::new_unique()],
+            auto_swap: false,
+            auto_freeze: true,
+            score_ema_alpha: 50,
+        }
+    }
+
+    #[test]
+    fn initialize_user_signs_and_derives_the_user_pda() {
+        let client = IrisClient::new(MockRpc::default());
+        let user = Keypair::new();
+        let signature = block_on(client.initialize_user(&user, preferences())).unwrap();
+
+        let sent = client.rpc().sent.borrow();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].signatures[0], signature);
+        let message = &sent[0].message;
+        assert_eq!(message.account_keys[0], user.pubkey());
+
+        let ix = &message.instructions[0];
+        assert_eq!(
+            message.account_keys[ix.program_id_index as usize],
+            anchor::ID
+        );
+        assert_eq!(&ix.data[..8], &instruction::InitializeUser::DISCRIMINATOR);
+        let user_account = message.account_keys[ix.accounts[0] as usize];
+        assert_eq!(
+            user_account,
+            Pubkey::find_program_address(&[b"user", user.pubkey().as_ref()], &anchor::ID).0
+        );
+    }
+
+    #[test]
+    fn subscribe_pays_from_the_associated_account() {
+        let mut rpc = MockRpc::default();
+        let treasury = Treasury {
+            mint: Pubkey::new_unique(),
+            vault: Pubkey::new_unique(),
+            bump: 255,
+            total_inflows: 0,
+            total_outflows: 0,
+            dex_allowlist: Vec::new(),
+        };
+        rpc.insert(pda::treasury(&anchor::ID), anchor::ID, &treasury);
+        rpc.accounts.insert(
+            treasury.mint,
+            Account {
+                owner: anchor_spl::token::ID,
+                ..Account::default()
+            },
+        );
+
+        let client = IrisClient::new(rpc);
+        let user = Keypair::new();
+        block_on(client.subscribe(&user, 1, 30, 1_000)).unwrap();
+
+        let sent = client.rpc().sent.borrow();
+        let message = &sent[0].message;
+        let ix = &message.instructions[0];
+        let key = |i: usize| message.account_keys[ix.accounts[i] as usize];
+        let payment = get_associated_token_address_with_program_id(
+            &user.pubkey(),
+            &treasury.mint,
+            &anchor_spl::token::ID,
+        );
+        assert_eq!(key(1), payment);
+        assert_eq!(key(4), treasury.vault);
+        assert_eq!(key(5), treasury.mint);
+    }
+
+    #[test]
+    fn reads_fail_cleanly() {
+        let mut rpc = MockRpc::default();
+        let claim = Pubkey::new_unique();
+        // An account of another type
+        rpc.insert(
+            claim,
+            anchor::ID,
+            &Treasury {
+                mint: Pubkey::new_unique(),
+                vault: Pubkey::new_unique(),
+                bump: 255,
+                total_inflows: 0,
+                total_outflows: 0,
+                dex_allowlist: Vec::new(),
+            },
+        );
+        let client = IrisClient::new(rpc);
+
+        assert!(
+            matches!(block_on(client.claim(&claim)), Err(Error::InvalidAccountData(a)) if a == claim)
+        );
+        let missing = Pubkey::new_unique();
+        assert!(
+            matches!(block_on(client.insurance_nft(&missing)), Err(Error::AccountNotFound(a)) if a == missing)
+        );
+    }
+
+    #[test]
+    fn send_requires_a_fee_payer() {
+        let client = IrisClient::new(MockRpc::default());
+        assert!(matches!(
+            block_on(client.send(&[], &[])),
+            Err(Error::Signing(_))
+        ));
+    }
+}
@@ -0,0 +1,319 @@
+use anchor::{accounts, instruction, ActionLogEntry, ConfigParam};
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::system_program;
+use solana_sdk::instruction::AccountMeta;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+
+use super::{with_remaining, IrisClient};
+use crate::{pda, Result, Rpc};
+
+// Protocol configuration: setup, the admin key and its handover, the CPI allowlists,
+// the action log and the settings governance owns
+impl<R: Rpc> IrisClient<R> {
+    // Must be signed by the program's upgrade authority
+    pub async fn initialize_config(
+        &self,
+        admin: &Keypair,
+        protocol_chain_id: u8,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::InitializeConfig {
+                config: pda::config(&self.program_id),
+                admin: admin.pubkey(),
+                program_data: pda::program_data(&self.program_id),
+                system_program: system_program::ID,
+            },
+            instruction::InitializeConfig { protocol_chain_id },
+        );
+        self.send(&[admin], &[ix]).await
+    }
+
+    pub async fn set_config_param(&self, admin: &Keypair, param: ConfigParam) -> Result<Signature> {
+        let ix = self.instruction(
+            self.config_admin_accounts(admin),
+            instruction::SetConfigParam { param },
+        );
+        self.send(&[admin], &[ix]).await
+    }
+
+    pub async fn nominate_admin(&self, admin: &Keypair, new_admin: Pubkey) -> Result<Signature> {
+        let ix = self.instruction(
+            self.config_admin_accounts(admin),
+            instruction::NominateAdmin { new_admin },
+        );
+        self.send(&[admin], &[ix]).await
+    }
+
+    pub async fn cancel_admin_nomination(&self, admin: &Keypair) -> Result<Signature> {
+        let ix = self.instruction(
+            self.config_admin_accounts(admin),
+            instruction::CancelAdminNomination {},
+        );
+        self.send(&[admin], &[ix]).await
+    }
+
+    pub async fn accept_admin(&self, new_admin: &Keypair) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::AcceptAdmin {
+                config: pda::config(&self.program_id),
+                new_admin: new_admin.pubkey(),
+                program_data: pda::program_data(&self.program_id),
+                instructions: None,
+            },
+            instruction::AcceptAdmin {},
+        );
+        self.send(&[new_admin], &[ix]).await
+    }
+
+    pub async fn set_lending_allowlist(
+        &self,
+        admin: &Keypair,
+        programs: Vec<Pubkey>,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::SetLendingAllowlist {
+                config: pda::config(&self.program_id),
+                admin: admin.pubkey(),
+                instructions: None,
+            },
+            instruction::SetLendingAllowlist { programs },
+        );
+        self.send(&[admin], &[ix]).await
+    }
+
+    pub async fn set_stream_allowlist(
+        &self,
+        admin: &Keypair,
+        programs: Vec<Pubkey>,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            self.config_admin_accounts(admin),
+            instruction::SetStreamAllowlist { programs },
+        );
+        self.send(&[admin], &[ix]).await
+    }
+
+    pub async fn set_transfer_hook_allowlist(
+        &self,
+        admin: &Keypair,
+        programs: Vec<Pubkey>,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            self.config_admin_accounts(admin),
+            instruction::SetTransferHookAllowlist { programs },
+        );
+        self.send(&[admin], &[ix]).await
+    }
+
+    pub async fn set_multisig_allowlist(
+        &self,
+        admin: &Keypair,
+        programs: Vec<Pubkey>,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            self.config_admin_accounts(admin),
+            instruction::SetMultisigAllowlist { programs },
+        );
+        self.send(&[admin], &[ix]).await
+    }
+
+    pub async fn set_yield_allowlist(
+        &self,
+        admin: &Keypair,
+        programs: Vec<Pubkey>,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            self.config_admin_accounts(admin),
+            instruction::SetYieldAllowlist { programs },
+        );
+        self.send(&[admin], &[ix]).await
+    }
+
+    fn config_admin_accounts(&self, admin: &Keypair) -> accounts::SetConfigParam {
+        accounts::SetConfigParam {
+            config: pda::config(&self.program_id),
+            admin: admin.pubkey(),
+            instructions: None,
+        }
+    }
+
+    // Create the tree admin actions are logged to. `merkle_tree` must already be
+    // allocated for the compression program at the given size.
+    pub async fn init_action_log_tree(
+        &self,
+        admin: &Keypair,
+        merkle_tree: Pubkey,
+        max_depth: u32,
+        max_buffer_size: u32,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::InitActionLogTree {
+                config: pda::config(&self.program_id),
+                admin: admin.pubkey(),
+                merkle_tree,
+                log_authority: pda::log_authority(&self.program_id),
+                compression_program: anchor::compression::ACCOUNT_COMPRESSION_ID,
+                noop_program: anchor::compression::NOOP_ID,
+                instructions: None,
+            },
+            instruction::InitActionLogTree {
+                max_depth,
+                max_buffer_size,
+            },
+        );
+        self.send(&[admin], &[ix]).await
+    }
+
+    // Check `entry` against the action log under `root`; `proof_nodes` run leaf to root
+    pub async fn verify_action_log(
+        &self,
+        payer: &Keypair,
+        root: [u8; 32],
+        entry: ActionLogEntry,
+        proof_nodes: &[Pubkey],
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::VerifyActionLog {
+                config: pda::config(&self.program_id),
+                merkle_tree: self.config().await?.action_log_tree.merkle_tree,
+                compression_program: anchor::compression::ACCOUNT_COMPRESSION_ID,
+            },
+            instruction::VerifyActionLog { root, entry },
+        );
+        let proof = proof_nodes
+            .iter()
+            .map(|node| AccountMeta::new_readonly(*node, false));
+        self.send(&[payer], &[with_remaining(ix, proof)]).await
+    }
+
+    pub async fn set_sub_pool_mode(
+        &self,
+        authority: &Keypair,
+        isolated_tiers: bool,
+        cross_subsidy_bps: u16,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            self.governed_config_accounts(authority),
+            instruction::SetSubPoolMode {
+                isolated_tiers,
+                cross_subsidy_bps,
+            },
+        );
+        self.send(&[authority], &[ix]).await
+    }
+
+    pub async fn set_vote_incentives(
+        &self,
+        authority: &Keypair,
+        vote_incentive_per_claim: u64,
+        max_vote_incentive_total: u64,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            self.governed_config_accounts(authority),
+            instruction::SetVoteIncentives {
+                vote_incentive_per_claim,
+                max_vote_incentive_total,
+            },
+        );
+        self.send(&[authority], &[ix]).await
+    }
+
+    pub async fn update_max_oracle_count(
+        &self,
+        authority: &Keypair,
+        new_max: u8,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            self.governed_config_accounts(authority),
+            instruction::UpdateMaxOracleCount { new_max },
+        );
+        self.send(&[authority], &[ix]).await
+    }
+
+    pub async fn set_cpi_guard(
+        &self,
+        authority: &Keypair,
+        enabled: bool,
+        allowlist: Vec<Pubkey>,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            self.governed_config_accounts(authority),
+            instruction::SetCpiGuard { enabled, allowlist },
+        );
+        self.send(&[authority], &[ix]).await
+    }
+
+    pub async fn set_solvency_buffer(
+        &self,
+        authority: &Keypair,
+        expected_loss_bps: u16,
+        solvency_buffer_bps: u16,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            self.governed_config_accounts(authority),
+            instruction::SetSolvencyBuffer {
+                expected_loss_bps,
+                solvency_buffer_bps,
+            },
+        );
+        self.send(&[authority], &[ix]).await
+    }
+
+    // Config settings changed by a governance authority rather than the admin
+    fn governed_config_accounts(&self, authority: &Keypair) -> accounts::SetSubPoolMode {
+        accounts::SetSubPoolMode {
+            config: pda::config(&self.program_id),
+            governance: pda::governance(&self.program_id),
+            authority: authority.pubkey(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::fixture::bootstrap;
+    use crate::local::block_on;
+    use solana_sdk::native_token::LAMPORTS_PER_SOL;
+    use solana_sdk::signature::{Keypair, Signer};
+
+    #[test]
+    fn bootstrap_claims_the_config_for_the_upgrade_authority() {
+        let protocol = bootstrap();
+        let config = block_on(protocol.client.config()).unwrap();
+        assert_eq!(config.admin, protocol.admin.pubkey());
+        assert_eq!(config.protocol_chain_id, 1);
+        let pool = block_on(protocol.client.pool(0)).unwrap();
+        assert_eq!(pool.payout_mint, protocol.usdc);
+    }
+
+    #[test]
+    fn admin_handover_completes_once_the_nominee_holds_the_upgrade_key() {
+        let protocol = bootstrap();
+        let client = &protocol.client;
+        let nominee = Keypair::new();
+        client.rpc().airdrop(nominee.pubkey(), LAMPORTS_PER_SOL);
+
+        block_on(client.nominate_admin(&protocol.admin, nominee.pubkey())).unwrap();
+        assert!(block_on(client.accept_admin(&nominee)).is_err());
+
+        client.rpc().set_upgrade_authority(nominee.pubkey());
+        block_on(client.accept_admin(&nominee)).unwrap();
+        let config = block_on(client.config()).unwrap();
+        assert_eq!(config.admin, nominee.pubkey());
+        assert_eq!(config.pending_admin, None);
+    }
+
+    #[test]
+    fn governed_settings_need_the_governance_authority() {
+        let protocol = bootstrap();
+        let client = &protocol.client;
+        let outsider = Keypair::new();
+        client.rpc().airdrop(outsider.pubkey(), LAMPORTS_PER_SOL);
+
+        assert!(block_on(client.set_sub_pool_mode(&outsider, true, 500)).is_err());
+        block_on(client.set_sub_pool_mode(&protocol.admin, true, 500)).unwrap();
+        let config = block_on(client.config()).unwrap();
+        assert!(config.isolated_tiers);
+        assert_eq!(config.cross_subsidy_bps, 500);
+    }
+}
@@ -0,0 +1,691 @@
+use anchor::{
+    accounts, instruction, AutoVoteRules, InsurancePool, LossType, ReinsurancePool, SlotAnchor,
+    VoteChoice, YieldEscrow,
+};
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::{system_program, sysvar};
+use anchor_spl::token_interface::TokenAccount;
+use solana_sdk::instruction::AccountMeta;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+
+use super::{with_remaining, IrisClient};
+use crate::{pda, Result, Rpc};
+
+// Claims from evidence to payout, the votes that decide them and the escrow a
+// pending claim's reserve can earn yield in
+impl<R: Rpc> IrisClient<R> {
+    // Start a custody log for the evidence of the claim that will be `claim_id`
+    pub async fn open_claim_evidence(
+        &self,
+        handler: &Keypair,
+        claim_id: Pubkey,
+        proof: Vec<u8>,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::OpenClaimEvidence {
+                evidence_custody: pda::evidence_custody(&self.program_id, &claim_id),
+                config: pda::config(&self.program_id),
+                handler: handler.pubkey(),
+                system_program: system_program::ID,
+            },
+            instruction::OpenClaimEvidence { claim_id, proof },
+        );
+        self.send(&[handler], &[ix]).await
+    }
+
+    pub async fn update_claim_evidence(
+        &self,
+        custodian: &Keypair,
+        claim_id: Pubkey,
+        new_proofs: Vec<u8>,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            self.custody_accounts(custodian, &claim_id),
+            instruction::UpdateClaimEvidence { new_proofs },
+        );
+        self.send(&[custodian], &[ix]).await
+    }
+
+    pub async fn transfer_claim_evidence(
+        &self,
+        custodian: &Keypair,
+        claim_id: Pubkey,
+        new_custodian: Pubkey,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            self.custody_accounts(custodian, &claim_id),
+            instruction::TransferClaimEvidence { new_custodian },
+        );
+        self.send(&[custodian], &[ix]).await
+    }
+
+    fn custody_accounts(
+        &self,
+        custodian: &Keypair,
+        claim_id: &Pubkey,
+    ) -> accounts::UpdateClaimEvidence {
+        accounts::UpdateClaimEvidence {
+            evidence_custody: pda::evidence_custody(&self.program_id, claim_id),
+            config: pda::config(&self.program_id),
+            custodian: custodian.pubkey(),
+        }
+    }
+
+    pub async fn initialize_attestor_registry(
+        &self,
+        admin: &Keypair,
+        attestors: Vec<Pubkey>,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::InitializeAttestorRegistry {
+                attestor_registry: pda::attestor_registry(&self.program_id),
+                config: pda::config(&self.program_id),
+                admin: admin.pubkey(),
+                system_program: system_program::ID,
+                instructions: None,
+            },
+            instruction::InitializeAttestorRegistry { attestors },
+        );
+        self.send(&[admin], &[ix]).await
+    }
+
+    pub async fn set_attestors(
+        &self,
+        admin: &Keypair,
+        attestors: Vec<Pubkey>,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::SetAttestors {
+                attestor_registry: pda::attestor_registry(&self.program_id),
+                config: pda::config(&self.program_id),
+                admin: admin.pubkey(),
+                instructions: None,
+            },
+            instruction::SetAttestors { attestors },
+        );
+        self.send(&[admin], &[ix]).await
+    }
+
+    pub async fn attest_loss(
+        &self,
+        attestor: &Keypair,
+        wallet: Pubkey,
+        loss_type: LossType,
+        evidence_hash: [u8; 32],
+        claim_amount: u64,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::AttestLoss {
+                attestor_registry: pda::attestor_registry(&self.program_id),
+                loss_attestation: pda::loss_attestation(&self.program_id, &wallet),
+                config: pda::config(&self.program_id),
+                attestor: attestor.pubkey(),
+                system_program: system_program::ID,
+            },
+            instruction::AttestLoss {
+                wallet,
+                loss_type,
+                evidence_hash,
+                claim_amount,
+            },
+        );
+        self.send(&[attestor], &[ix]).await
+    }
+
+    // Claim against an SPL policy, moving its NFT from the user's associated account
+    // into `claim_escrow`. `claim` is the new claim account. A custody log or loss
+    // attestation on record for it is picked up.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn initiate_claim(
+        &self,
+        user: &Keypair,
+        claim: &Keypair,
+        insurance_nft: Pubkey,
+        claim_amount: u64,
+        proof: Vec<u8>,
+        claim_escrow: Pubkey,
+        slot_anchor: Option<SlotAnchor>,
+    ) -> Result<Signature> {
+        let policy = self.insurance_nft(&insurance_nft).await?;
+        let mut claim_accounts = self
+            .claim_accounts(
+                user,
+                claim,
+                insurance_nft,
+                policy.tier,
+                slot_anchor.is_some(),
+            )
+            .await?;
+        claim_accounts.insurance_nft_account = Some(
+            self.token_account(&user.pubkey(), &policy.token_mint)
+                .await?,
+        );
+        claim_accounts.claim_escrow = Some(claim_escrow);
+        claim_accounts.nft_mint = Some(policy.token_mint);
+        claim_accounts.token_program = self.owner(&policy.token_mint).await?;
+        let ix = self.instruction(
+            claim_accounts,
+            instruction::InitiateClaim {
+                claim_amount,
+                proof,
+                cnft_root: None,
+                slot_anchor,
+            },
+        );
+        self.send(&[user, claim], &[ix]).await
+    }
+
+    // Claim against a compressed policy, delegating its leaf to the escrow PDA.
+    // `cnft_root` is the policy tree's current root and `proof_nodes` the leaf's proof.
+    // The leaf's current delegate is taken to be the user, as it is unless they have
+    // delegated it.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn initiate_cnft_claim(
+        &self,
+        user: &Keypair,
+        claim: &Keypair,
+        insurance_nft: Pubkey,
+        claim_amount: u64,
+        proof: Vec<u8>,
+        cnft_root: [u8; 32],
+        proof_nodes: &[Pubkey],
+        slot_anchor: Option<SlotAnchor>,
+    ) -> Result<Signature> {
+        let policy = self.insurance_nft(&insurance_nft).await?;
+        let merkle_tree = policy
+            .compressed_leaf
+            .as_ref()
+            .map(|leaf| leaf.merkle_tree)
+            .unwrap_or_default();
+        let mut claim_accounts = self
+            .claim_accounts(
+                user,
+                claim,
+                insurance_nft,
+                policy.tier,
+                slot_anchor.is_some(),
+            )
+            .await?;
+        claim_accounts.cnft_escrow = Some(pda::cnft_escrow(&self.program_id));
+        claim_accounts.previous_leaf_delegate = Some(user.pubkey());
+        claim_accounts.tree_config = Some(pda::tree_config(&merkle_tree));
+        claim_accounts.merkle_tree = Some(merkle_tree);
+        claim_accounts.log_wrapper = Some(anchor::compression::NOOP_ID);
+        claim_accounts.compression_program = Some(anchor::compression::ACCOUNT_COMPRESSION_ID);
+        claim_accounts.bubblegum_program = Some(anchor::cnft::BUBBLEGUM_ID);
+        let ix = self.instruction(
+            claim_accounts,
+            instruction::InitiateClaim {
+                claim_amount,
+                proof,
+                cnft_root: Some(cnft_root),
+                slot_anchor,
+            },
+        );
+        let nodes = proof_nodes
+            .iter()
+            .map(|node| AccountMeta::new_readonly(*node, false));
+        self.send(&[user, claim], &[with_remaining(ix, nodes)])
+            .await
+    }
+
+    // Accounts every claim takes, with the policy-kind specific ones left unset
+    async fn claim_accounts(
+        &self,
+        user: &Keypair,
+        claim: &Keypair,
+        insurance_nft: Pubkey,
+        tier: u8,
+        slot_anchored: bool,
+    ) -> Result<accounts::InitiateClaim> {
+        let wallet = user.pubkey();
+        let config = self.config().await?;
+        Ok(accounts::InitiateClaim {
+            insurance_nft,
+            insurance_nft_account: None,
+            claim: claim.pubkey(),
+            claim_number_index: pda::claim_number(&self.program_id, config.next_claim_number),
+            claim_history: pda::claim_history(&self.program_id, &wallet),
+            evidence_custody: self
+                .existing(pda::evidence_custody(&self.program_id, &claim.pubkey()))
+                .await?,
+            claim_escrow: None,
+            cnft_escrow: None,
+            previous_leaf_delegate: None,
+            tree_config: None,
+            merkle_tree: None,
+            log_wrapper: None,
+            compression_program: None,
+            bubblegum_program: None,
+            user_account: pda::user_account(&self.program_id, &wallet),
+            config: pda::config(&self.program_id),
+            governance: pda::governance(&self.program_id),
+            loss_attestation: self
+                .existing(pda::loss_attestation(&self.program_id, &wallet))
+                .await?,
+            activity: Some(pda::wallet_activity(&self.program_id, &wallet)),
+            pool: pda::pool(&self.program_id, config.pool_tier_for(tier)),
+            user: wallet,
+            payer: wallet,
+            nft_mint: None,
+            token_program: anchor_spl::token::ID,
+            system_program: system_program::ID,
+            instructions: None,
+            slot_hashes: slot_anchored.then_some(sysvar::slot_hashes::ID),
+        })
+    }
+
+    pub async fn unlink_resolved_claim(
+        &self,
+        claimant: &Keypair,
+        claim: Pubkey,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::UnlinkResolvedClaim {
+                user_account: pda::user_account(&self.program_id, &claimant.pubkey()),
+                claim,
+                claimant: claimant.pubkey(),
+            },
+            instruction::UnlinkResolvedClaim {},
+        );
+        self.send(&[claimant], &[ix]).await
+    }
+
+    // Once this vote brings the partial votes to quorum, the records of every earlier
+    // voter who proposed an amount are needed; `partial_voters` names them
+    pub async fn vote_on_claim(
+        &self,
+        voter: &Keypair,
+        claim: Pubkey,
+        vote: VoteChoice,
+        approved_amount: Option<u64>,
+        partial_voters: &[Pubkey],
+    ) -> Result<Signature> {
+        let pool = self.claim(&claim).await?.pool;
+        let ix = self.instruction(
+            accounts::VoteOnClaim {
+                claim,
+                vote_record: pda::claim_vote(&self.program_id, &claim, &voter.pubkey()),
+                governance: pda::governance(&self.program_id),
+                stake: pda::claim_stake(&self.program_id, &voter.pubkey()),
+                staking: pda::claim_staking(&self.program_id),
+                delegation: pda::delegation(&self.program_id, &voter.pubkey()),
+                config: pda::config(&self.program_id),
+                pool,
+                voter: voter.pubkey(),
+                system_program: system_program::ID,
+                instructions: None,
+                event_authority: self.event_authority(),
+                program: self.program_id,
+            },
+            instruction::VoteOnClaim {
+                claim_id: claim,
+                vote,
+                approved_amount,
+            },
+        );
+        let records = partial_voters.iter().map(|partial_voter| {
+            AccountMeta::new_readonly(
+                pda::claim_vote(&self.program_id, &claim, partial_voter),
+                false,
+            )
+        });
+        self.send(&[voter], &[with_remaining(ix, records)]).await
+    }
+
+    pub async fn invalidate_claim(
+        &self,
+        oracle: &Keypair,
+        claim: Pubkey,
+        reason_hash: [u8; 32],
+    ) -> Result<Signature> {
+        let filed = self.claim(&claim).await?;
+        let ix = self.instruction(
+            accounts::InvalidateClaim {
+                claim,
+                claimant_account: pda::user_account(&self.program_id, &filed.claimant),
+                pool: filed.pool,
+                oracle_registry: pda::oracle_registry(&self.program_id),
+                config: pda::config(&self.program_id),
+                oracle: oracle.pubkey(),
+            },
+            instruction::InvalidateClaim {
+                claim_id: claim,
+                reason_hash,
+            },
+        );
+        self.send(&[oracle], &[ix]).await
+    }
+
+    // Paid from the user's associated account of the treasury mint
+    pub async fn pay_claim_penalty(&self, user: &Keypair) -> Result<Signature> {
+        let wallet = user.pubkey();
+        let treasury = self.treasury().await?;
+        let ix = self.instruction(
+            accounts::PayClaimPenalty {
+                user_account: pda::user_account(&self.program_id, &wallet),
+                payment_account: self.token_account(&wallet, &treasury.mint).await?,
+                config: pda::config(&self.program_id),
+                treasury: pda::treasury(&self.program_id),
+                treasury_account: treasury.vault,
+                payment_mint: treasury.mint,
+                user: wallet,
+                token_program: self.owner(&treasury.mint).await?,
+            },
+            instruction::PayClaimPenalty {},
+        );
+        self.send(&[user], &[ix]).await
+    }
+
+    pub async fn close_claim_voting(&self, payer: &Keypair, claim: Pubkey) -> Result<Signature> {
+        let pool = self.claim(&claim).await?.pool;
+        let ix = self.instruction(
+            accounts::CloseClaimVoting {
+                claim,
+                config: pda::config(&self.program_id),
+                pool,
+                event_authority: self.event_authority(),
+                program: self.program_id,
+            },
+            instruction::CloseClaimVoting {},
+        );
+        self.send(&[payer], &[ix]).await
+    }
+
+    // Pay `voters` their share of the claim's vote incentive, into their associated
+    // accounts of the incentive mint
+    pub async fn distribute_vote_incentives(
+        &self,
+        payer: &Keypair,
+        claim: Pubkey,
+        voters: &[Pubkey],
+    ) -> Result<Signature> {
+        let vault = pda::vote_incentive_vault(&self.program_id);
+        let incentive_mint = self.fetch::<TokenAccount>(&vault).await?.mint;
+        let ix = self.instruction(
+            accounts::DistributeVoteIncentives {
+                claim,
+                config: pda::config(&self.program_id),
+                governance: pda::governance(&self.program_id),
+                vault,
+                incentive_mint,
+                token_program: self.owner(&incentive_mint).await?,
+            },
+            instruction::DistributeVoteIncentives { claim_id: claim },
+        );
+        let mut pairs = Vec::with_capacity(2 * voters.len());
+        for voter in voters {
+            pairs.push(AccountMeta::new(
+                pda::claim_vote(&self.program_id, &claim, voter),
+                false,
+            ));
+            pairs.push(AccountMeta::new(
+                self.token_account(voter, &incentive_mint).await?,
+                false,
+            ));
+        }
+        self.send(&[payer], &[with_remaining(ix, pairs)]).await
+    }
+
+    // Pay an approved claim into the claimant's associated account of the payout mint,
+    // drawing on reinsurance when the protocol has it. A `reference` is recorded in a
+    // memo alongside the payout.
+    pub async fn execute_payout(
+        &self,
+        payer: &Keypair,
+        claim: Pubkey,
+        reference: Option<[u8; 16]>,
+    ) -> Result<Signature> {
+        let filed = self.claim(&claim).await?;
+        let pool: InsurancePool = self.fetch(&filed.pool).await?;
+        let reinsurance_address = pda::reinsurance_pool(&self.program_id);
+        let reinsurance = match self.existing(reinsurance_address).await? {
+            Some(address) => Some(self.fetch::<ReinsurancePool>(&address).await?),
+            None => None,
+        };
+        let ix = self.instruction(
+            accounts::ExecutePayout {
+                pool: filed.pool,
+                config: pda::config(&self.program_id),
+                vault: pool.vault,
+                claim,
+                claimant_token_account: self
+                    .token_account(&filed.claimant, &pool.payout_mint)
+                    .await?,
+                payout_mint: pool.payout_mint,
+                token_program: self.owner(&pool.payout_mint).await?,
+                memo_program: reference.map(|_| anchor_spl::memo::ID),
+                reinsurance_pool: reinsurance.as_ref().map(|_| reinsurance_address),
+                reinsurance_vault: reinsurance.as_ref().map(|reinsurance| reinsurance.vault),
+                activity: Some(pda::wallet_activity(&self.program_id, &filed.claimant)),
+                event_authority: self.event_authority(),
+                program: self.program_id,
+            },
+            instruction::ExecutePayout { reference },
+        );
+        self.send(&[payer], &[ix]).await
+    }
+
+    // Paid into the claimant's associated account of the payout mint
+    pub async fn claim_next_installment(
+        &self,
+        claimant: &Keypair,
+        claim: Pubkey,
+    ) -> Result<Signature> {
+        let filed = self.claim(&claim).await?;
+        let pool: InsurancePool = self.fetch(&filed.pool).await?;
+        let ix = self.instruction(
+            accounts::ClaimNextInstallment {
+                pool: filed.pool,
+                config: pda::config(&self.program_id),
+                vault: pool.vault,
+                claim,
+                claimant: claimant.pubkey(),
+                claimant_token_account: self
+                    .token_account(&filed.claimant, &pool.payout_mint)
+                    .await?,
+                payout_mint: pool.payout_mint,
+                token_program: self.owner(&pool.payout_mint).await?,
+                activity: Some(pda::wallet_activity(&self.program_id, &filed.claimant)),
+            },
+            instruction::ClaimNextInstallment {},
+        );
+        self.send(&[claimant], &[ix]).await
+    }
+
+    // Top up `claims` paid short by the tier's pool, each into its claimant's associated
+    // account of the payout mint
+    pub async fn top_up_shortfalls(
+        &self,
+        payer: &Keypair,
+        tier: u8,
+        claims: &[Pubkey],
+    ) -> Result<Signature> {
+        let pool = self.pool(tier).await?;
+        let ix = self.instruction(
+            accounts::TopUpShortfalls {
+                pool: pda::pool(&self.program_id, tier),
+                config: pda::config(&self.program_id),
+                vault: pool.vault,
+                payout_mint: pool.payout_mint,
+                token_program: self.owner(&pool.payout_mint).await?,
+                event_authority: self.event_authority(),
+                program: self.program_id,
+            },
+            instruction::TopUpShortfalls {},
+        );
+        let mut pairs = Vec::with_capacity(2 * claims.len());
+        for claim in claims {
+            let claimant = self.claim(claim).await?.claimant;
+            pairs.push(AccountMeta::new(*claim, false));
+            pairs.push(AccountMeta::new(
+                self.token_account(&claimant, &pool.payout_mint).await?,
+                false,
+            ));
+        }
+        self.send(&[payer], &[with_remaining(ix, pairs)]).await
+    }
+
+    pub async fn configure_protocol_delegate(
+        &self,
+        authority: &Keypair,
+        protocol_delegate: Pubkey,
+        auto_vote_rules: AutoVoteRules,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::ConfigureProtocolDelegate {
+                governance: pda::governance(&self.program_id),
+                authority: authority.pubkey(),
+            },
+            instruction::ConfigureProtocolDelegate {
+                protocol_delegate,
+                auto_vote_rules,
+            },
+        );
+        self.send(&[authority], &[ix]).await
+    }
+
+    // Hand the delegator's claim votes to `delegatee`
+    pub async fn delegate_vote(&self, delegator: &Keypair, delegatee: Pubkey) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::DelegateVote {
+                delegation: pda::delegation(&self.program_id, &delegator.pubkey()),
+                stake: pda::claim_stake(&self.program_id, &delegator.pubkey()),
+                governance: pda::governance(&self.program_id),
+                config: pda::config(&self.program_id),
+                delegator: delegator.pubkey(),
+                system_program: system_program::ID,
+            },
+            instruction::DelegateVote { delegatee },
+        );
+        self.send(&[delegator], &[ix]).await
+    }
+
+    pub async fn revoke_delegation(&self, delegator: &Keypair) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::RevokeDelegation {
+                delegation: pda::delegation(&self.program_id, &delegator.pubkey()),
+                governance: pda::governance(&self.program_id),
+                delegator: delegator.pubkey(),
+            },
+            instruction::RevokeDelegation {},
+        );
+        self.send(&[delegator], &[ix]).await
+    }
+
+    pub async fn assess_claim_fraud(
+        &self,
+        authority: &Keypair,
+        claim: Pubkey,
+        fraud_score: u8,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::AssessClaimFraud {
+                claim,
+                governance: pda::governance(&self.program_id),
+                authority: authority.pubkey(),
+            },
+            instruction::AssessClaimFraud { fraud_score },
+        );
+        self.send(&[authority], &[ix]).await
+    }
+
+    // Cast the protocol delegate's vote on the claim from its fraud assessment
+    pub async fn execute_protocol_vote(
+        &self,
+        keeper: &Keypair,
+        claim: Pubkey,
+    ) -> Result<Signature> {
+        let pool = self.claim(&claim).await?.pool;
+        let ix = self.instruction(
+            accounts::ExecuteProtocolVote {
+                claim,
+                governance: pda::governance(&self.program_id),
+                staking: pda::claim_staking(&self.program_id),
+                pool,
+                config: pda::config(&self.program_id),
+                keeper: keeper.pubkey(),
+                event_authority: self.event_authority(),
+                program: self.program_id,
+            },
+            instruction::ExecuteProtocolVote { claim_id: claim },
+        );
+        self.send(&[keeper], &[ix]).await
+    }
+
+    // Move a pending claim's reserve into `yield_program` while it is decided.
+    // `deposit_accounts` and `deposit_data` form the yield program's deposit
+    // instruction.
+    pub async fn open_yield_escrow(
+        &self,
+        authority: &Keypair,
+        claim: Pubkey,
+        yield_program: Pubkey,
+        deposit_data: Vec<u8>,
+        deposit_accounts: &[AccountMeta],
+    ) -> Result<Signature> {
+        let filed = self.claim(&claim).await?;
+        let pool: InsurancePool = self.fetch(&filed.pool).await?;
+        let ix = self.instruction(
+            accounts::OpenYieldEscrow {
+                yield_escrow: pda::yield_escrow(&self.program_id, &claim),
+                escrow_vault: pda::yield_escrow_vault(&self.program_id, &claim),
+                claim,
+                insurance_nft: filed.insurance_nft,
+                pool: filed.pool,
+                vault: pool.vault,
+                payout_mint: pool.payout_mint,
+                yield_program,
+                config: pda::config(&self.program_id),
+                governance: pda::governance(&self.program_id),
+                authority: authority.pubkey(),
+                token_program: self.owner(&pool.payout_mint).await?,
+                system_program: system_program::ID,
+            },
+            instruction::OpenYieldEscrow {
+                yield_protocol: yield_program,
+                deposit_data,
+            },
+        );
+        self.send(
+            &[authority],
+            &[with_remaining(ix, deposit_accounts.to_vec())],
+        )
+        .await
+    }
+
+    // `withdraw_accounts` and `withdraw_data` form the yield program's withdraw
+    // instruction
+    pub async fn settle_yield_escrow(
+        &self,
+        authority: &Keypair,
+        claim: Pubkey,
+        withdraw_data: Vec<u8>,
+        withdraw_accounts: &[AccountMeta],
+    ) -> Result<Signature> {
+        let escrow_address = pda::yield_escrow(&self.program_id, &claim);
+        let escrow: YieldEscrow = self.fetch(&escrow_address).await?;
+        let pool: InsurancePool = self.fetch(&escrow.pool).await?;
+        let ix = self.instruction(
+            accounts::SettleYieldEscrow {
+                yield_escrow: escrow_address,
+                escrow_vault: escrow.vault,
+                claim,
+                pool: escrow.pool,
+                vault: pool.vault,
+                payout_mint: pool.payout_mint,
+                yield_program: escrow.yield_protocol,
+                config: pda::config(&self.program_id),
+                governance: pda::governance(&self.program_id),
+                authority: authority.pubkey(),
+                token_program: self.owner(&pool.payout_mint).await?,
+            },
+            instruction::SettleYieldEscrow { withdraw_data },
+        );
+        self.send(
+            &[authority],
+            &[with_remaining(ix, withdraw_accounts.to_vec())],
+        )
+        .await
+    }
+}
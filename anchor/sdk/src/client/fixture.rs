@@ -0,0 +1,91 @@
+// A protocol set up through the builders on a LocalRpc, which the builder modules'
+// tests drive end to end in place of a local validator
+
+use anchor::RiskParams;
+use anchor_lang::prelude::Pubkey;
+use anchor_spl::associated_token::get_associated_token_address;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::signature::{Keypair, Signer};
+
+use super::IrisClient;
+use crate::local::{block_on, LocalRpc};
+
+pub const START: i64 = 1_700_000_000;
+pub const DAY: i64 = 86_400;
+
+pub struct Protocol {
+    pub client: IrisClient<LocalRpc>,
+    // Upgrade authority, config admin and governance authority
+    pub admin: Keypair,
+    // Payout mint of the shared pool and the treasury
+    pub usdc: Pubkey,
+    pub iris: Pubkey,
+}
+
+// Config, governance, treasury and the shared pool, as a deployment would set them up
+pub fn bootstrap() -> Protocol {
+    let rpc = LocalRpc::new();
+    rpc.warp_to(START);
+    let admin = Keypair::new();
+    rpc.airdrop(admin.pubkey(), 100 * LAMPORTS_PER_SOL);
+    rpc.set_upgrade_authority(admin.pubkey());
+    let usdc = Pubkey::new_unique();
+    rpc.create_mint(usdc, admin.pubkey(), 6);
+    let iris = Pubkey::new_unique();
+    rpc.create_mint(iris, admin.pubkey(), 9);
+
+    let client = IrisClient::new(rpc);
+    block_on(client.initialize_config(&admin, 1)).unwrap();
+    block_on(client.initialize_governance(&admin, 1, 3 * DAY, 0, 1)).unwrap();
+    block_on(client.initialize_treasury(&admin, usdc)).unwrap();
+    block_on(client.initialize_pool(&admin, 0, usdc)).unwrap();
+    Protocol {
+        client,
+        admin,
+        usdc,
+        iris,
+    }
+}
+
+impl Protocol {
+    // A funded wallet with an associated account of each mint holding `tokens`
+    pub fn wallet(&self, tokens: u64) -> Keypair {
+        let wallet = Keypair::new();
+        let rpc = self.client.rpc();
+        rpc.airdrop(wallet.pubkey(), 10 * LAMPORTS_PER_SOL);
+        for mint in [self.usdc, self.iris] {
+            rpc.create_token_account(
+                self.ata(&wallet.pubkey(), &mint),
+                mint,
+                wallet.pubkey(),
+                tokens,
+            );
+        }
+        wallet
+    }
+
+    // A wallet as above, registered with the protocol
+    pub fn user(&self, tokens: u64) -> Keypair {
+        let user = self.wallet(tokens);
+        block_on(self.client.initialize_user(&user, preferences())).unwrap();
+        user
+    }
+
+    pub fn ata(&self, owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+        get_associated_token_address(owner, mint)
+    }
+
+    pub fn balance(&self, owner: &Pubkey, mint: &Pubkey) -> u64 {
+        self.client.rpc().token_balance(&self.ata(owner, mint))
+    }
+}
+
+pub fn preferences() -> RiskParams {
+    RiskParams {
+        risk_threshold: 70,
+        watchlist: Vec::new(),
+        auto_swap: false,
+        auto_freeze: true,
+        score_ema_alpha: 50,
+    }
+}
@@ -0,0 +1,518 @@
+use anchor::{accounts, instruction, InsurancePool, ProposalType, ProposalUrgency, VoteChoice};
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::{bpf_loader_upgradeable, system_program, sysvar};
+use solana_sdk::instruction::AccountMeta;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+
+use super::{with_remaining, IrisClient};
+use crate::{pda, Error, Result, Rpc};
+
+// Governance: its parameters, proposals from creation through voting to execution, the
+// treasury's DEX allowlist and the emergency council. Proposals are named by id.
+impl<R: Rpc> IrisClient<R> {
+    pub async fn initialize_governance(
+        &self,
+        admin: &Keypair,
+        quorum: u64,
+        voting_duration: i64,
+        timelock_duration: i64,
+        emergency_quorum: u64,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::InitializeGovernance {
+                governance: pda::governance(&self.program_id),
+                config: pda::config(&self.program_id),
+                admin: admin.pubkey(),
+                system_program: system_program::ID,
+            },
+            instruction::InitializeGovernance {
+                quorum,
+                voting_duration,
+                timelock_duration,
+                emergency_quorum,
+            },
+        );
+        self.send(&[admin], &[ix]).await
+    }
+
+    pub async fn update_governance_quorum(
+        &self,
+        authority: &Keypair,
+        quorum: u64,
+        emergency_quorum: u64,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            self.governance_params_accounts(authority),
+            instruction::UpdateGovernanceQuorum {
+                quorum,
+                emergency_quorum,
+            },
+        );
+        self.send(&[authority], &[ix]).await
+    }
+
+    pub async fn update_voting_duration(
+        &self,
+        authority: &Keypair,
+        voting_duration: i64,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            self.governance_params_accounts(authority),
+            instruction::UpdateVotingDuration { voting_duration },
+        );
+        self.send(&[authority], &[ix]).await
+    }
+
+    fn governance_params_accounts(&self, authority: &Keypair) -> accounts::UpdateGovernanceParams {
+        accounts::UpdateGovernanceParams {
+            governance: pda::governance(&self.program_id),
+            config: pda::config(&self.program_id),
+            authority: authority.pubkey(),
+        }
+    }
+
+    // The new proposal takes the next id, which is returned with the signature
+    pub async fn create_proposal(
+        &self,
+        proposer: &Keypair,
+        proposal_type: ProposalType,
+        snapshot_min_stake_bps: Option<u16>,
+        urgency: ProposalUrgency,
+    ) -> Result<(u64, Signature)> {
+        let id = self.governance().await?.proposal_count;
+        let ix = self.instruction(
+            accounts::CreateProposal {
+                proposal: pda::proposal(&self.program_id, id),
+                governance: pda::governance(&self.program_id),
+                treasury: pda::treasury(&self.program_id),
+                config: pda::config(&self.program_id),
+                emergency_council: self
+                    .existing(pda::emergency_council(&self.program_id))
+                    .await?,
+                proposer: proposer.pubkey(),
+                system_program: system_program::ID,
+            },
+            instruction::CreateProposal {
+                proposal_type,
+                snapshot_min_stake_bps,
+                urgency,
+            },
+        );
+        Ok((id, self.send(&[proposer], &[ix]).await?))
+    }
+
+    // Capture the fee stakes of `owners` for a snapshot proposal. Owners under the
+    // proposal's minimum stake are skipped by the program.
+    pub async fn take_vote_snapshot(
+        &self,
+        authority: &Keypair,
+        proposal_id: u64,
+        owners: &[Pubkey],
+    ) -> Result<Signature> {
+        let proposal = pda::proposal(&self.program_id, proposal_id);
+        let ix = self.instruction(
+            accounts::TakeVoteSnapshot {
+                proposal,
+                governance: pda::governance(&self.program_id),
+                distributor: pda::fee_distributor(&self.program_id),
+                config: pda::config(&self.program_id),
+                authority: authority.pubkey(),
+                system_program: system_program::ID,
+            },
+            instruction::TakeVoteSnapshot {},
+        );
+        let pairs = owners.iter().flat_map(|owner| {
+            [
+                AccountMeta::new_readonly(pda::fee_stake(&self.program_id, owner), false),
+                AccountMeta::new(
+                    pda::vote_snapshot(&self.program_id, &proposal, owner),
+                    false,
+                ),
+            ]
+        });
+        self.send(&[authority], &[with_remaining(ix, pairs)]).await
+    }
+
+    // On a snapshot proposal the vote is weighed by the voter's captured stake
+    pub async fn vote_on_proposal(
+        &self,
+        voter: &Keypair,
+        proposal_id: u64,
+        vote: VoteChoice,
+    ) -> Result<Signature> {
+        let wallet = voter.pubkey();
+        let proposal = pda::proposal(&self.program_id, proposal_id);
+        let snapshot = self.proposal(proposal_id).await?.snapshot.is_some();
+        let ix = self.instruction(
+            accounts::VoteOnProposal {
+                proposal,
+                vote_record: pda::proposal_vote(&self.program_id, &proposal, &wallet),
+                governance: pda::governance(&self.program_id),
+                snapshot: snapshot
+                    .then(|| pda::vote_snapshot(&self.program_id, &proposal, &wallet)),
+                stake: snapshot.then(|| pda::fee_stake(&self.program_id, &wallet)),
+                config: pda::config(&self.program_id),
+                voter: wallet,
+                system_program: system_program::ID,
+            },
+            instruction::VoteOnProposal { vote },
+        );
+        self.send(&[voter], &[ix]).await
+    }
+
+    // Close a proposal whose voting ended without reaching quorum; anyone may call it
+    pub async fn close_expired_proposal(
+        &self,
+        payer: &Keypair,
+        proposal_id: u64,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::CloseExpiredProposal {
+                proposal: pda::proposal(&self.program_id, proposal_id),
+                governance: pda::governance(&self.program_id),
+                config: pda::config(&self.program_id),
+            },
+            instruction::CloseExpiredProposal {},
+        );
+        self.send(&[payer], &[ix]).await
+    }
+
+    pub async fn cancel_execution(
+        &self,
+        cancellation_authority: &Keypair,
+        proposal_id: u64,
+        reason: [u8; 128],
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::CancelExecution {
+                proposal: pda::proposal(&self.program_id, proposal_id),
+                governance: pda::governance(&self.program_id),
+                config: pda::config(&self.program_id),
+                cancellation_authority: cancellation_authority.pubkey(),
+            },
+            instruction::CancelExecution { reason },
+        );
+        self.send(&[cancellation_authority], &[ix]).await
+    }
+
+    // Execute a passed buyback through the approved DEX. IRIS lands in the treasury's
+    // associated account before it is burned. `route_accounts` are the DEX's accounts
+    // for the swap, including the approved pool.
+    pub async fn buyback_and_burn(
+        &self,
+        executor: &Keypair,
+        proposal_id: u64,
+        max_amount_in: u64,
+        min_iris_out: u64,
+        route_data: Vec<u8>,
+        route_accounts: &[AccountMeta],
+    ) -> Result<Signature> {
+        let proposal = pda::proposal(&self.program_id, proposal_id);
+        let (dex_program, iris_mint) = match self.proposal(proposal_id).await?.proposal_type {
+            ProposalType::BuybackAndBurn {
+                dex_program,
+                iris_mint,
+                ..
+            } => (dex_program, iris_mint),
+            _ => return Err(Error::WrongProposalType(proposal)),
+        };
+        let treasury = pda::treasury(&self.program_id);
+        let ix = self.instruction(
+            accounts::BuybackAndBurn {
+                proposal,
+                governance: pda::governance(&self.program_id),
+                config: pda::config(&self.program_id),
+                treasury,
+                treasury_vault: self.treasury().await?.vault,
+                treasury_iris_account: self.token_account(&treasury, &iris_mint).await?,
+                iris_mint,
+                dex_program,
+                executor: executor.pubkey(),
+                token_program: self.owner(&iris_mint).await?,
+            },
+            instruction::BuybackAndBurn {
+                max_amount_in,
+                min_iris_out,
+                route_data,
+            },
+        );
+        self.send(&[executor], &[with_remaining(ix, route_accounts.to_vec())])
+            .await
+    }
+
+    pub async fn execute_reserve_floor_proposal(
+        &self,
+        executor: &Keypair,
+        proposal_id: u64,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            self.config_proposal_accounts(executor, proposal_id),
+            instruction::ExecuteReserveFloorProposal {},
+        );
+        self.send(&[executor], &[ix]).await
+    }
+
+    pub async fn execute_swap_allowlist_proposal(
+        &self,
+        executor: &Keypair,
+        proposal_id: u64,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            self.config_proposal_accounts(executor, proposal_id),
+            instruction::ExecuteSwapAllowlistProposal {},
+        );
+        self.send(&[executor], &[ix]).await
+    }
+
+    fn config_proposal_accounts(
+        &self,
+        executor: &Keypair,
+        proposal_id: u64,
+    ) -> accounts::ExecuteReserveFloorProposal {
+        accounts::ExecuteReserveFloorProposal {
+            proposal: pda::proposal(&self.program_id, proposal_id),
+            governance: pda::governance(&self.program_id),
+            config: pda::config(&self.program_id),
+            executor: executor.pubkey(),
+        }
+    }
+
+    pub async fn execute_governance_params_proposal(
+        &self,
+        executor: &Keypair,
+        proposal_id: u64,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::ExecuteGovernanceParamsProposal {
+                proposal: pda::proposal(&self.program_id, proposal_id),
+                governance: pda::governance(&self.program_id),
+                config: pda::config(&self.program_id),
+                executor: executor.pubkey(),
+            },
+            instruction::ExecuteGovernanceParamsProposal {},
+        );
+        self.send(&[executor], &[ix]).await
+    }
+
+    pub async fn execute_set_admin_proposal(
+        &self,
+        executor: &Keypair,
+        proposal_id: u64,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::ExecuteSetAdminProposal {
+                proposal: pda::proposal(&self.program_id, proposal_id),
+                governance: pda::governance(&self.program_id),
+                config: pda::config(&self.program_id),
+                program_data: pda::program_data(&self.program_id),
+                executor: executor.pubkey(),
+            },
+            instruction::ExecuteSetAdminProposal {},
+        );
+        self.send(&[executor], &[ix]).await
+    }
+
+    // Hand program upgrades to governance once the upgrade authority is the
+    // program's PDA
+    pub async fn enable_governed_upgrades(&self, admin: &Keypair) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::EnableGovernedUpgrades {
+                config: pda::config(&self.program_id),
+                admin: admin.pubkey(),
+                program_data: pda::program_data(&self.program_id),
+                instructions: None,
+            },
+            instruction::EnableGovernedUpgrades {},
+        );
+        self.send(&[admin], &[ix]).await
+    }
+
+    // Upgrade the program from `buffer`, returning the buffer's lamports to `spill`
+    pub async fn execute_program_upgrade(
+        &self,
+        executor: &Keypair,
+        proposal_id: u64,
+        buffer: Pubkey,
+        spill: Pubkey,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::ExecuteProgramUpgrade {
+                proposal: pda::proposal(&self.program_id, proposal_id),
+                governance: pda::governance(&self.program_id),
+                config: pda::config(&self.program_id),
+                program_data: pda::program_data(&self.program_id),
+                program: self.program_id,
+                buffer,
+                spill,
+                upgrade_authority: pda::upgrade_authority(&self.program_id),
+                loader: bpf_loader_upgradeable::ID,
+                rent: sysvar::rent::ID,
+                clock: sysvar::clock::ID,
+                executor: executor.pubkey(),
+            },
+            instruction::ExecuteProgramUpgrade {},
+        );
+        self.send(&[executor], &[ix]).await
+    }
+
+    pub async fn write_off_shortfall(
+        &self,
+        executor: &Keypair,
+        proposal_id: u64,
+        claim: Pubkey,
+    ) -> Result<Signature> {
+        let pool = self.claim(&claim).await?.pool;
+        let ix = self.instruction(
+            accounts::WriteOffShortfall {
+                proposal: pda::proposal(&self.program_id, proposal_id),
+                governance: pda::governance(&self.program_id),
+                pool,
+                vault: self.fetch::<InsurancePool>(&pool).await?.vault,
+                claim,
+                config: pda::config(&self.program_id),
+                executor: executor.pubkey(),
+            },
+            instruction::WriteOffShortfall {},
+        );
+        self.send(&[executor], &[ix]).await
+    }
+
+    pub async fn set_dex_allowlist(
+        &self,
+        authority: &Keypair,
+        programs: Vec<Pubkey>,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::SetDexAllowlist {
+                treasury: pda::treasury(&self.program_id),
+                governance: pda::governance(&self.program_id),
+                authority: authority.pubkey(),
+            },
+            instruction::SetDexAllowlist { programs },
+        );
+        self.send(&[authority], &[ix]).await
+    }
+
+    pub async fn set_emergency_council(
+        &self,
+        authority: &Keypair,
+        members: Vec<Pubkey>,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::SetEmergencyCouncil {
+                emergency_council: pda::emergency_council(&self.program_id),
+                governance: pda::governance(&self.program_id),
+                authority: authority.pubkey(),
+                system_program: system_program::ID,
+            },
+            instruction::SetEmergencyCouncil { members },
+        );
+        self.send(&[authority], &[ix]).await
+    }
+
+    // Propose replacing `old_signer` on the emergency council; `signer` is a member
+    pub async fn rotate_multisig_signer(
+        &self,
+        signer: &Keypair,
+        old_signer: Pubkey,
+        new_signer: Pubkey,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::RotateMultisigSigner {
+                rotation: pda::signer_rotation(&self.program_id, &old_signer, &new_signer),
+                emergency_council: pda::emergency_council(&self.program_id),
+                config: pda::config(&self.program_id),
+                signer: signer.pubkey(),
+                system_program: system_program::ID,
+            },
+            instruction::RotateMultisigSigner {
+                old_signer,
+                new_signer,
+            },
+        );
+        self.send(&[signer], &[ix]).await
+    }
+
+    pub async fn confirm_signer_rotation(
+        &self,
+        signer: &Keypair,
+        old_signer: Pubkey,
+        new_signer: Pubkey,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::ConfirmSignerRotation {
+                rotation: pda::signer_rotation(&self.program_id, &old_signer, &new_signer),
+                emergency_council: pda::emergency_council(&self.program_id),
+                config: pda::config(&self.program_id),
+                signer: signer.pubkey(),
+            },
+            instruction::ConfirmSignerRotation {},
+        );
+        self.send(&[signer], &[ix]).await
+    }
+
+    pub async fn create_promo_code(
+        &self,
+        authority: &Keypair,
+        code: [u8; 16],
+        discount_bps: u16,
+        max_uses: u32,
+        expiry: i64,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::CreatePromoCode {
+                promo_code: pda::promo_code(&self.program_id, &code),
+                governance: pda::governance(&self.program_id),
+                authority: authority.pubkey(),
+                system_program: system_program::ID,
+            },
+            instruction::CreatePromoCode {
+                code,
+                discount_bps,
+                max_uses,
+                expiry,
+            },
+        );
+        self.send(&[authority], &[ix]).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::fixture::{bootstrap, DAY};
+    use crate::local::block_on;
+    use anchor::{ProposalStatus, ProposalType, ProposalUrgency, VoteChoice};
+    use solana_sdk::native_token::LAMPORTS_PER_SOL;
+    use solana_sdk::signature::{Keypair, Signer};
+
+    #[test]
+    fn an_approved_proposal_changes_the_voting_duration() {
+        let protocol = bootstrap();
+        let client = &protocol.client;
+        let outsider = Keypair::new();
+        client.rpc().airdrop(outsider.pubkey(), LAMPORTS_PER_SOL);
+
+        let (id, _) = block_on(client.create_proposal(
+            &protocol.admin,
+            ProposalType::UpdateVotingDuration {
+                voting_duration: 5 * DAY,
+            },
+            None,
+            ProposalUrgency::Normal,
+        ))
+        .unwrap();
+        assert_eq!(id, 0);
+        assert!(block_on(client.vote_on_proposal(&outsider, id, VoteChoice::Approve)).is_err());
+        assert!(block_on(client.execute_governance_params_proposal(&protocol.admin, id)).is_err());
+
+        // A quorum of one closes the vote on the admin's approval
+        block_on(client.vote_on_proposal(&protocol.admin, id, VoteChoice::Approve)).unwrap();
+        assert!(block_on(client.proposal(id)).unwrap().status == ProposalStatus::Approved);
+        block_on(client.execute_governance_params_proposal(&protocol.admin, id)).unwrap();
+        assert!(block_on(client.proposal(id)).unwrap().status == ProposalStatus::Executed);
+        assert_eq!(
+            block_on(client.governance()).unwrap().voting_duration,
+            5 * DAY
+        );
+    }
+}
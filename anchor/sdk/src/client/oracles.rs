@@ -0,0 +1,418 @@
+use anchor::{accounts, instruction, wire, InsuranceNFT, OracleScore, ScoreHistory, ScoreMessage};
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::{ed25519_program, system_program, sysvar};
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::signature::{Keypair, Signature, Signer};
+
+use super::{with_remaining, IrisClient};
+use crate::{pda, Result, Rpc};
+
+// Ed25519 instruction data layout: the signature count and a padding byte, then the
+// offsets of the signature, key and message, each within this instruction's own data
+const OFFSETS_LEN: usize = 2 + 14;
+const CURRENT_INSTRUCTION: u16 = u16::MAX;
+
+// Score reports, the oracle registry and the feeds oracles keep up to date
+impl<R: Rpc> IrisClient<R> {
+    // Sign `score` for `wallet` as `oracle` and submit it, with the ed25519 instruction
+    // the program checks the signature through. The score is archived and checked
+    // against the wallet's covered tokens when those accounts exist.
+    pub async fn update_risk_score(
+        &self,
+        oracle: &Keypair,
+        wallet: Pubkey,
+        score: u8,
+        timestamp: i64,
+    ) -> Result<Signature> {
+        let chain_id = self.config().await?.protocol_chain_id;
+        let (signature, verify) = self.sign_score(oracle, &wallet, score, timestamp, chain_id);
+        let ix = self.instruction(
+            accounts::UpdateRiskScore {
+                user_account: pda::user_account(&self.program_id, &wallet),
+                config: pda::config(&self.program_id),
+                oracle_registry: pda::oracle_registry(&self.program_id),
+                score_archive: self
+                    .existing(pda::score_history(&self.program_id, &wallet))
+                    .await?,
+                covered_tokens: self
+                    .existing(pda::covered_tokens(&self.program_id, &wallet))
+                    .await?,
+                insurance_nft: None,
+                activity: Some(pda::wallet_activity(&self.program_id, &wallet)),
+                instructions: Some(sysvar::instructions::ID),
+                oracle: oracle.pubkey(),
+            },
+            instruction::UpdateRiskScore {
+                score,
+                timestamp,
+                chain_id,
+                signature,
+            },
+        );
+        self.send(&[oracle], &[verify, ix]).await
+    }
+
+    // One report per oracle, `reports[i]` signed by `oracles[i]`; the first oracle pays.
+    // Wallets with a score archive also get an aggregation report of the update.
+    pub async fn update_risk_score_consensus(
+        &self,
+        oracles: &[&Keypair],
+        wallet: Pubkey,
+        reports: &[u8],
+        timestamp: i64,
+    ) -> Result<Signature> {
+        let chain_id = self.config().await?.protocol_chain_id;
+        let archive_address = pda::score_history(&self.program_id, &wallet);
+        let archive = match self.existing(archive_address).await? {
+            Some(address) => Some(self.fetch::<ScoreHistory>(&address).await?),
+            None => None,
+        };
+        let payer = oracles.first().map(|oracle| oracle.pubkey());
+
+        let mut instructions = Vec::with_capacity(reports.len() + 1);
+        let mut scores = Vec::with_capacity(reports.len());
+        for (oracle, score) in oracles.iter().zip(reports) {
+            let (signature, verify) = self.sign_score(oracle, &wallet, *score, timestamp, chain_id);
+            instructions.push(verify);
+            scores.push(OracleScore {
+                score: *score,
+                signature,
+            });
+        }
+        let ix = self.instruction(
+            accounts::UpdateRiskScoreConsensus {
+                user_account: pda::user_account(&self.program_id, &wallet),
+                config: pda::config(&self.program_id),
+                oracle_registry: pda::oracle_registry(&self.program_id),
+                score_archive: archive.as_ref().map(|_| archive_address),
+                covered_tokens: self
+                    .existing(pda::covered_tokens(&self.program_id, &wallet))
+                    .await?,
+                aggregation_report: archive.as_ref().map(|archive| {
+                    pda::aggregation_report(&self.program_id, &wallet, archive.reports_count)
+                }),
+                activity: Some(pda::wallet_activity(&self.program_id, &wallet)),
+                instructions: Some(sysvar::instructions::ID),
+                payer: archive.as_ref().and(payer),
+                system_program: archive.as_ref().map(|_| system_program::ID),
+            },
+            instruction::UpdateRiskScoreConsensus {
+                scores,
+                timestamp,
+                chain_id,
+            },
+        );
+        let signers = oracles
+            .iter()
+            .map(|oracle| AccountMeta::new_readonly(oracle.pubkey(), true));
+        instructions.push(with_remaining(ix, signers));
+        self.send(oracles, &instructions).await
+    }
+
+    // The ed25519 instruction verifying `oracle`'s signature over the score message,
+    // and the signature itself
+    fn sign_score(
+        &self,
+        oracle: &Keypair,
+        wallet: &Pubkey,
+        score: u8,
+        timestamp: i64,
+        chain_id: u8,
+    ) -> ([u8; 64], Instruction) {
+        let message = wire::encode_score_message(
+            &self.program_id,
+            &ScoreMessage {
+                wallet: *wallet,
+                score,
+                category: wire::SCORE_CATEGORY_OVERALL,
+                token: None,
+                timestamp,
+                nonce: timestamp as u64,
+                chain_id,
+            },
+        );
+        let signature: [u8; 64] = oracle.sign_message(&message).into();
+        (
+            signature,
+            ed25519_instruction(&oracle.pubkey(), &signature, &message),
+        )
+    }
+
+    pub async fn apply_pending_score(&self, payer: &Keypair, wallet: Pubkey) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::ApplyPendingScore {
+                user_account: pda::user_account(&self.program_id, &wallet),
+                config: pda::config(&self.program_id),
+                score_archive: self
+                    .existing(pda::score_history(&self.program_id, &wallet))
+                    .await?,
+                covered_tokens: self
+                    .existing(pda::covered_tokens(&self.program_id, &wallet))
+                    .await?,
+                activity: Some(pda::wallet_activity(&self.program_id, &wallet)),
+            },
+            instruction::ApplyPendingScore {},
+        );
+        self.send(&[payer], &[ix]).await
+    }
+
+    pub async fn resolve_score_dispute(
+        &self,
+        authority: &Keypair,
+        wallet: Pubkey,
+        upheld: bool,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::ResolveScoreDispute {
+                user_account: pda::user_account(&self.program_id, &wallet),
+                oracle_dispute: pda::oracle_dispute(&self.program_id, &wallet),
+                wallet,
+                config: pda::config(&self.program_id),
+                governance: pda::governance(&self.program_id),
+                authority: authority.pubkey(),
+            },
+            instruction::ResolveScoreDispute { upheld },
+        );
+        self.send(&[authority], &[ix]).await
+    }
+
+    // The report is the transaction's return data
+    pub async fn get_aggregation_report(
+        &self,
+        payer: &Keypair,
+        wallet: Pubkey,
+        index: u32,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::GetAggregationReport {
+                aggregation_report: pda::aggregation_report(&self.program_id, &wallet, index),
+                config: pda::config(&self.program_id),
+            },
+            instruction::GetAggregationReport { index },
+        );
+        self.send(&[payer], &[ix]).await
+    }
+
+    pub async fn initialize_oracle_registry(
+        &self,
+        admin: &Keypair,
+        primary_oracle: Pubkey,
+        heartbeat_interval: i64,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::InitializeOracleRegistry {
+                oracle_registry: pda::oracle_registry(&self.program_id),
+                config: pda::config(&self.program_id),
+                admin: admin.pubkey(),
+                system_program: system_program::ID,
+                instructions: None,
+            },
+            instruction::InitializeOracleRegistry {
+                primary_oracle,
+                heartbeat_interval,
+            },
+        );
+        self.send(&[admin], &[ix]).await
+    }
+
+    pub async fn set_fallback_oracles(
+        &self,
+        admin: &Keypair,
+        fallback_oracles: Vec<Pubkey>,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            self.registry_admin_accounts(admin),
+            instruction::SetFallbackOracles { fallback_oracles },
+        );
+        self.send(&[admin], &[ix]).await
+    }
+
+    pub async fn register_oracle(&self, admin: &Keypair, oracle: Pubkey) -> Result<Signature> {
+        let ix = self.instruction(
+            self.registry_admin_accounts(admin),
+            instruction::RegisterOracle { oracle },
+        );
+        self.send(&[admin], &[ix]).await
+    }
+
+    pub async fn deregister_oracle(&self, admin: &Keypair, oracle: Pubkey) -> Result<Signature> {
+        let ix = self.instruction(
+            self.registry_admin_accounts(admin),
+            instruction::DeregisterOracle { oracle },
+        );
+        self.send(&[admin], &[ix]).await
+    }
+
+    pub async fn set_apy_oracle(&self, admin: &Keypair, apy_oracle: Pubkey) -> Result<Signature> {
+        let ix = self.instruction(
+            self.registry_admin_accounts(admin),
+            instruction::SetApyOracle { apy_oracle },
+        );
+        self.send(&[admin], &[ix]).await
+    }
+
+    pub async fn set_invalidator_oracles(
+        &self,
+        admin: &Keypair,
+        oracles: Vec<Pubkey>,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            self.registry_admin_accounts(admin),
+            instruction::SetInvalidatorOracles { oracles },
+        );
+        self.send(&[admin], &[ix]).await
+    }
+
+    fn registry_admin_accounts(&self, admin: &Keypair) -> accounts::SetFallbackOracles {
+        accounts::SetFallbackOracles {
+            oracle_registry: pda::oracle_registry(&self.program_id),
+            config: pda::config(&self.program_id),
+            admin: admin.pubkey(),
+            instructions: None,
+        }
+    }
+
+    // With `insurance_nft`, the heartbeat also checks that policy for a coverage gap
+    pub async fn oracle_heartbeat(
+        &self,
+        oracle: &Keypair,
+        insurance_nft: Option<Pubkey>,
+    ) -> Result<Signature> {
+        let user_account = match insurance_nft {
+            Some(address) => {
+                let policy: InsuranceNFT = self.insurance_nft(&address).await?;
+                Some(pda::user_account(&self.program_id, &policy.owner))
+            }
+            None => None,
+        };
+        let ix = self.instruction(
+            accounts::OracleHeartbeat {
+                oracle_registry: pda::oracle_registry(&self.program_id),
+                config: pda::config(&self.program_id),
+                user_account,
+                insurance_nft,
+                oracle: oracle.pubkey(),
+            },
+            instruction::OracleHeartbeat {},
+        );
+        self.send(&[oracle], &[ix]).await
+    }
+
+    pub async fn coverage_gap_check(
+        &self,
+        caller: &Keypair,
+        insurance_nft: Pubkey,
+    ) -> Result<Signature> {
+        let policy = self.insurance_nft(&insurance_nft).await?;
+        let ix = self.instruction(
+            accounts::CoverageGapCheck {
+                user_account: pda::user_account(&self.program_id, &policy.owner),
+                insurance_nft,
+                config: pda::config(&self.program_id),
+                caller: caller.pubkey(),
+            },
+            instruction::CoverageGapCheck {},
+        );
+        self.send(&[caller], &[ix]).await
+    }
+
+    pub async fn update_pool_apy(
+        &self,
+        apy_oracle: &Keypair,
+        tier: u8,
+        new_apy_bps: u16,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::UpdatePoolApy {
+                pool: pda::pool(&self.program_id, tier),
+                oracle_registry: pda::oracle_registry(&self.program_id),
+                apy_oracle: apy_oracle.pubkey(),
+            },
+            instruction::UpdatePoolApy { new_apy_bps },
+        );
+        self.send(&[apy_oracle], &[ix]).await
+    }
+
+    // Publish the pool's TVL to the protocol's Switchboard feed
+    pub async fn publish_pool_tvl(&self, keeper: &Keypair, tier: u8) -> Result<Signature> {
+        let config = self.config().await?;
+        let pool = pda::pool(&self.program_id, tier);
+        let ix = self.instruction(
+            accounts::PublishPoolTvl {
+                pool,
+                vault: self.pool(tier).await?.vault,
+                config: pda::config(&self.program_id),
+                tvl_feed: config.tvl_feed,
+                tvl_publisher: pda::tvl_publisher(&self.program_id),
+                switchboard_program: anchor::switchboard::SWITCHBOARD_V2_ID,
+                keeper: keeper.pubkey(),
+            },
+            instruction::PublishPoolTvl {},
+        );
+        self.send(&[keeper], &[ix]).await
+    }
+}
+
+// Ed25519 program instruction verifying one signature, with the key, signature and
+// message all inline
+fn ed25519_instruction(signer: &Pubkey, signature: &[u8; 64], message: &[u8]) -> Instruction {
+    let pubkey_offset = OFFSETS_LEN;
+    let signature_offset = pubkey_offset + 32;
+    let message_offset = signature_offset + 64;
+    let mut data = vec![1, 0];
+    for field in [
+        signature_offset as u16,
+        CURRENT_INSTRUCTION,
+        pubkey_offset as u16,
+        CURRENT_INSTRUCTION,
+        message_offset as u16,
+        message.len() as u16,
+        CURRENT_INSTRUCTION,
+    ] {
+        data.extend_from_slice(&field.to_le_bytes());
+    }
+    data.extend_from_slice(signer.as_ref());
+    data.extend_from_slice(signature);
+    data.extend_from_slice(message);
+    Instruction {
+        program_id: ed25519_program::ID,
+        accounts: Vec::new(),
+        data,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::fixture::{bootstrap, START};
+    use crate::local::block_on;
+    use anchor::ProtocolConfig;
+    use solana_sdk::signature::Signer;
+
+    #[test]
+    fn a_signed_score_is_held_then_applied() {
+        let protocol = bootstrap();
+        let client = &protocol.client;
+        let oracle = protocol.wallet(0);
+        let impostor = protocol.wallet(0);
+        let user = protocol.user(0);
+        block_on(client.initialize_oracle_registry(&protocol.admin, oracle.pubkey(), 3_600))
+            .unwrap();
+
+        assert!(block_on(client.update_risk_score(&impostor, user.pubkey(), 40, START)).is_err());
+        // The program finds the oracle's signature in the ed25519 instruction ahead of it
+        block_on(client.update_risk_score(&oracle, user.pubkey(), 40, START)).unwrap();
+        let held = block_on(client.user_account(&user.pubkey()))
+            .unwrap()
+            .held_score
+            .unwrap();
+        assert_eq!((held.value, held.oracle), (40, oracle.pubkey()));
+
+        client
+            .rpc()
+            .warp_to(START + ProtocolConfig::SCORE_DISPUTE_WINDOW_SECS);
+        block_on(client.apply_pending_score(&oracle, user.pubkey())).unwrap();
+        let account = block_on(client.user_account(&user.pubkey())).unwrap();
+        assert!(account.held_score.is_none());
+        assert_eq!(account.score_history.last().unwrap().value, 40);
+    }
+}
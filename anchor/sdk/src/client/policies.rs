@@ -0,0 +1,706 @@
+use anchor::{accounts, instruction, InsurancePool, MintWaitlist, NftListing};
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::system_program;
+use solana_sdk::instruction::AccountMeta;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+
+use super::{require_signature, with_remaining, IrisClient};
+use crate::{pda, Error, Result, Rpc};
+
+// Policies: pricing, minting, the waitlist, and what an owner can do with a policy NFT.
+// Where the program values the wallet's holdings, `portfolio` lists its token accounts
+// of the pool's payout mint.
+impl<R: Rpc> IrisClient<R> {
+    // The quote is the transaction's return data
+    pub async fn quote_premium(
+        &self,
+        payer: &Keypair,
+        wallet: Pubkey,
+        tier: u8,
+        payout_cap: u64,
+        duration: u64,
+        portfolio: &[Pubkey],
+    ) -> Result<Signature> {
+        let pool_tier = self.config().await?.pool_tier_for(tier);
+        let ix = self.instruction(
+            accounts::QuotePremium {
+                user_account: pda::user_account(&self.program_id, &wallet),
+                config: pda::config(&self.program_id),
+                pool: pda::pool(&self.program_id, pool_tier),
+                pool_vault: self.pool(pool_tier).await?.vault,
+            },
+            instruction::QuotePremium {
+                tier,
+                payout_cap,
+                duration,
+            },
+        );
+        self.send(&[payer], &[with_remaining(ix, readonly(portfolio))])
+            .await
+    }
+
+    // The state is the transaction's return data
+    pub async fn get_coverage_state(
+        &self,
+        payer: &Keypair,
+        insurance_nft: Pubkey,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::ReadPolicy { insurance_nft },
+            instruction::GetCoverageState {},
+        );
+        self.send(&[payer], &[ix]).await
+    }
+
+    // The quote is the transaction's return data, counting the reinsurance tranche when
+    // the protocol has one
+    pub async fn quote_payout(&self, payer: &Keypair, claim: Pubkey) -> Result<Signature> {
+        let pool_address = self.claim(&claim).await?.pool;
+        let pool: InsurancePool = self.fetch(&pool_address).await?;
+        let ix = self.instruction(
+            accounts::QuotePayout {
+                pool: pool_address,
+                config: pda::config(&self.program_id),
+                vault: pool.vault,
+                claim,
+                reinsurance_pool: self
+                    .existing(pda::reinsurance_pool(&self.program_id))
+                    .await?,
+            },
+            instruction::QuotePayout {},
+        );
+        self.send(&[payer], &[ix]).await
+    }
+
+    // Buy a policy as an SPL NFT. `mint` is the NFT's mint, with `mint_authority` as
+    // its authority, and the user's associated account of it must already exist. The
+    // premium is paid from the user's associated account of the pool's payout mint;
+    // `insurance_nft` is the new policy account.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn mint_insurance_nft(
+        &self,
+        user: &Keypair,
+        insurance_nft: &Keypair,
+        mint: Pubkey,
+        mint_authority: &Keypair,
+        tier: u8,
+        payout_cap: u64,
+        duration: u64,
+        max_premium: u64,
+        portfolio: &[Pubkey],
+        reference: Option<[u8; 16]>,
+    ) -> Result<Signature> {
+        let wallet = user.pubkey();
+        let config = self.config().await?;
+        let pool_tier = config.pool_tier_for(tier);
+        let pool = self.pool(pool_tier).await?;
+        let token_program = self.owner(&pool.payout_mint).await?;
+        let mut ix = self.instruction(
+            accounts::MintInsuranceNft {
+                user_account: pda::user_account(&self.program_id, &wallet),
+                insurance_nft: insurance_nft.pubkey(),
+                policy_number_index: pda::policy_number(
+                    &self.program_id,
+                    config.next_policy_number,
+                ),
+                insurance_nft_account: self.token_account(&wallet, &mint).await?,
+                mint,
+                config: pda::config(&self.program_id),
+                pool: pda::pool(&self.program_id, pool_tier),
+                pool_vault: pool.vault,
+                premium_token_account: self.token_account(&wallet, &pool.payout_mint).await?,
+                governance_mint: None,
+                governance_token_account: None,
+                user: wallet,
+                payer: wallet,
+                iris_authority: mint_authority.pubkey(),
+                payout_mint: pool.payout_mint,
+                token_program,
+                memo_program: reference.map(|_| anchor_spl::memo::ID),
+                system_program: system_program::ID,
+                associated_token_program: anchor_spl::associated_token::ID,
+            },
+            instruction::MintInsuranceNft {
+                tier,
+                payout_cap,
+                duration,
+                max_premium,
+                burn_for_waiver: None,
+                reference,
+            },
+        );
+        require_signature(&mut ix, &mint_authority.pubkey());
+        let ix = with_remaining(ix, readonly(portfolio));
+        if mint_authority.pubkey() == wallet {
+            self.send(&[user, insurance_nft], &[ix]).await
+        } else {
+            self.send(&[user, insurance_nft, mint_authority], &[ix])
+                .await
+        }
+    }
+
+    // Create the Bubblegum tree compressed policies are minted into. `merkle_tree` must
+    // already be allocated for the compression program at the given size.
+    pub async fn init_policy_tree(
+        &self,
+        admin: &Keypair,
+        merkle_tree: Pubkey,
+        max_depth: u32,
+        max_buffer_size: u32,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::InitPolicyTree {
+                config: pda::config(&self.program_id),
+                admin: admin.pubkey(),
+                tree_config: pda::tree_config(&merkle_tree),
+                merkle_tree,
+                tree_authority: pda::cnft_tree_authority(&self.program_id),
+                log_wrapper: anchor::compression::NOOP_ID,
+                compression_program: anchor::compression::ACCOUNT_COMPRESSION_ID,
+                bubblegum_program: anchor::cnft::BUBBLEGUM_ID,
+                system_program: system_program::ID,
+                instructions: None,
+            },
+            instruction::InitPolicyTree {
+                max_depth,
+                max_buffer_size,
+            },
+        );
+        self.send(&[admin], &[ix]).await
+    }
+
+    // Buy a policy as a compressed NFT in the protocol's policy tree. `policy_id` is the
+    // Bubblegum asset id of the leaf about to be minted.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn mint_insurance_cnft(
+        &self,
+        user: &Keypair,
+        policy_id: Pubkey,
+        tier: u8,
+        payout_cap: u64,
+        duration: u64,
+        max_premium: u64,
+        portfolio: &[Pubkey],
+        reference: Option<[u8; 16]>,
+    ) -> Result<Signature> {
+        let wallet = user.pubkey();
+        let config = self.config().await?;
+        let pool_tier = config.pool_tier_for(tier);
+        let pool = self.pool(pool_tier).await?;
+        let ix = self.instruction(
+            accounts::MintInsuranceCnft {
+                user_account: pda::user_account(&self.program_id, &wallet),
+                insurance_nft: pda::compressed_policy(&self.program_id, &policy_id),
+                policy_number_index: pda::policy_number(
+                    &self.program_id,
+                    config.next_policy_number,
+                ),
+                config: pda::config(&self.program_id),
+                pool: pda::pool(&self.program_id, pool_tier),
+                pool_vault: pool.vault,
+                premium_token_account: self.token_account(&wallet, &pool.payout_mint).await?,
+                tree_config: pda::tree_config(&config.policy_tree),
+                merkle_tree: config.policy_tree,
+                tree_authority: pda::cnft_tree_authority(&self.program_id),
+                log_wrapper: anchor::compression::NOOP_ID,
+                compression_program: anchor::compression::ACCOUNT_COMPRESSION_ID,
+                bubblegum_program: anchor::cnft::BUBBLEGUM_ID,
+                user: wallet,
+                payer: wallet,
+                payout_mint: pool.payout_mint,
+                token_program: self.owner(&pool.payout_mint).await?,
+                memo_program: reference.map(|_| anchor_spl::memo::ID),
+                system_program: system_program::ID,
+            },
+            instruction::MintInsuranceCnft {
+                tier,
+                payout_cap,
+                duration,
+                max_premium,
+                policy_id,
+                reference,
+            },
+        );
+        self.send(&[user], &[with_remaining(ix, readonly(portfolio))])
+            .await
+    }
+
+    // Burn an expired SPL policy from the owner's associated account and close it
+    pub async fn burn_expired_nft(
+        &self,
+        owner: &Keypair,
+        insurance_nft: Pubkey,
+    ) -> Result<Signature> {
+        let policy = self.insurance_nft(&insurance_nft).await?;
+        let ix = self.instruction(
+            accounts::BurnExpiredNft {
+                user_account: pda::user_account(&self.program_id, &owner.pubkey()),
+                insurance_nft,
+                pool: policy.pool,
+                nft_mint: policy.token_mint,
+                owner_nft_account: self
+                    .token_account(&owner.pubkey(), &policy.token_mint)
+                    .await?,
+                owner: owner.pubkey(),
+                rent_recipient: policy.rent_recipient(),
+                config: pda::config(&self.program_id),
+                token_program: self.owner(&policy.token_mint).await?,
+            },
+            instruction::BurnExpiredNft {},
+        );
+        self.send(&[owner], &[ix]).await
+    }
+
+    // Close an expired policy without burning its NFT
+    pub async fn force_close_expired_nft_before_close(
+        &self,
+        owner: &Keypair,
+        insurance_nft: Pubkey,
+    ) -> Result<Signature> {
+        let policy = self.insurance_nft(&insurance_nft).await?;
+        let ix = self.instruction(
+            accounts::ForceCloseExpiredNft {
+                user_account: pda::user_account(&self.program_id, &owner.pubkey()),
+                insurance_nft,
+                pool: policy.pool,
+                owner: owner.pubkey(),
+                rent_recipient: policy.rent_recipient(),
+                config: pda::config(&self.program_id),
+            },
+            instruction::ForceCloseExpiredNftBeforeClose {},
+        );
+        self.send(&[owner], &[ix]).await
+    }
+
+    pub async fn generate_coverage_certificate(
+        &self,
+        owner: &Keypair,
+        insurance_nft: Pubkey,
+        recipient: Pubkey,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::GenerateCoverageCertificate {
+                insurance_nft,
+                coverage_certificate: pda::coverage_certificate(
+                    &self.program_id,
+                    &insurance_nft,
+                    &recipient,
+                ),
+                config: pda::config(&self.program_id),
+                owner: owner.pubkey(),
+                system_program: system_program::ID,
+            },
+            instruction::GenerateCoverageCertificate { recipient },
+        );
+        self.send(&[owner], &[ix]).await
+    }
+
+    pub async fn revoke_coverage_certificate(
+        &self,
+        owner: &Keypair,
+        insurance_nft: Pubkey,
+        recipient: Pubkey,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::RevokeCoverageCertificate {
+                coverage_certificate: pda::coverage_certificate(
+                    &self.program_id,
+                    &insurance_nft,
+                    &recipient,
+                ),
+                config: pda::config(&self.program_id),
+                owner: owner.pubkey(),
+            },
+            instruction::RevokeCoverageCertificate {},
+        );
+        self.send(&[owner], &[ix]).await
+    }
+
+    // Fails unless the certificate still vouches for a valid policy
+    pub async fn verify_certificate(
+        &self,
+        payer: &Keypair,
+        insurance_nft: Pubkey,
+        recipient: Pubkey,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::VerifyCertificate {
+                coverage_certificate: pda::coverage_certificate(
+                    &self.program_id,
+                    &insurance_nft,
+                    &recipient,
+                ),
+                insurance_nft,
+            },
+            instruction::VerifyCertificate {},
+        );
+        self.send(&[payer], &[ix]).await
+    }
+
+    pub async fn initialize_mint_waitlist(
+        &self,
+        authority: &Keypair,
+        tier: u8,
+    ) -> Result<Signature> {
+        let pool_address = pda::pool(&self.program_id, tier);
+        let pool = self.pool(tier).await?;
+        let ix = self.instruction(
+            accounts::InitializeMintWaitlist {
+                pool: pool_address,
+                mint_waitlist: pda::mint_waitlist(&self.program_id, &pool_address),
+                vault: pda::waitlist_vault(&self.program_id, &pool_address),
+                payout_mint: pool.payout_mint,
+                governance: pda::governance(&self.program_id),
+                authority: authority.pubkey(),
+                token_program: self.owner(&pool.payout_mint).await?,
+                system_program: system_program::ID,
+            },
+            instruction::InitializeMintWaitlist {},
+        );
+        self.send(&[authority], &[ix]).await
+    }
+
+    // Queue for a policy while minting is halted, escrowing the premium from the user's
+    // associated account of the payout mint
+    pub async fn join_mint_waitlist(
+        &self,
+        user: &Keypair,
+        tier: u8,
+        payout_cap: u64,
+        duration: u64,
+        portfolio: &[Pubkey],
+    ) -> Result<Signature> {
+        let wallet = user.pubkey();
+        let pool_tier = self.config().await?.pool_tier_for(tier);
+        let pool_address = pda::pool(&self.program_id, pool_tier);
+        let pool = self.pool(pool_tier).await?;
+        let ix = self.instruction(
+            accounts::JoinMintWaitlist {
+                user_account: pda::user_account(&self.program_id, &wallet),
+                config: pda::config(&self.program_id),
+                pool: pool_address,
+                pool_vault: pool.vault,
+                mint_waitlist: pda::mint_waitlist(&self.program_id, &pool_address),
+                waitlist_vault: pda::waitlist_vault(&self.program_id, &pool_address),
+                premium_token_account: self.token_account(&wallet, &pool.payout_mint).await?,
+                user: wallet,
+                payout_mint: pool.payout_mint,
+                token_program: self.owner(&pool.payout_mint).await?,
+            },
+            instruction::JoinMintWaitlist {
+                tier,
+                payout_cap,
+                duration,
+            },
+        );
+        self.send(&[user], &[with_remaining(ix, readonly(portfolio))])
+            .await
+    }
+
+    // Mint the policy at the head of the tier's waitlist. As for mint_insurance_nft,
+    // `mint` is the NFT's mint under `mint_authority` and the waiting wallet's
+    // associated account of it must exist.
+    pub async fn process_waitlist(
+        &self,
+        keeper: &Keypair,
+        tier: u8,
+        insurance_nft: &Keypair,
+        mint: Pubkey,
+        mint_authority: &Keypair,
+    ) -> Result<Signature> {
+        let config = self.config().await?;
+        let pool_address = pda::pool(&self.program_id, tier);
+        let pool = self.pool(tier).await?;
+        let waitlist_address = pda::mint_waitlist(&self.program_id, &pool_address);
+        let waitlist: MintWaitlist = self.fetch(&waitlist_address).await?;
+        let entry = waitlist
+            .entries
+            .first()
+            .ok_or(Error::WaitlistEmpty(waitlist_address))?;
+        let mut ix = self.instruction(
+            accounts::ProcessWaitlist {
+                pool: pool_address,
+                pool_vault: pool.vault,
+                config: pda::config(&self.program_id),
+                mint_waitlist: waitlist_address,
+                waitlist_vault: waitlist.vault,
+                user_account: pda::user_account(&self.program_id, &entry.wallet),
+                insurance_nft: insurance_nft.pubkey(),
+                policy_number_index: pda::policy_number(
+                    &self.program_id,
+                    config.next_policy_number,
+                ),
+                insurance_nft_account: self.token_account(&entry.wallet, &mint).await?,
+                mint,
+                keeper: keeper.pubkey(),
+                iris_authority: mint_authority.pubkey(),
+                payout_mint: pool.payout_mint,
+                token_program: self.owner(&pool.payout_mint).await?,
+                system_program: system_program::ID,
+            },
+            instruction::ProcessWaitlist {},
+        );
+        require_signature(&mut ix, &mint_authority.pubkey());
+        if mint_authority.pubkey() == keeper.pubkey() {
+            self.send(&[keeper, insurance_nft], &[ix]).await
+        } else {
+            self.send(&[keeper, insurance_nft, mint_authority], &[ix])
+                .await
+        }
+    }
+
+    // Refund the lapsed entry at the head of the tier's waitlist to the waiting wallet's
+    // associated account
+    pub async fn expire_waitlist_entry(&self, payer: &Keypair, tier: u8) -> Result<Signature> {
+        let pool_address = pda::pool(&self.program_id, tier);
+        let payout_mint = self.pool(tier).await?.payout_mint;
+        let waitlist_address = pda::mint_waitlist(&self.program_id, &pool_address);
+        let waitlist: MintWaitlist = self.fetch(&waitlist_address).await?;
+        let entry = waitlist
+            .entries
+            .first()
+            .ok_or(Error::WaitlistEmpty(waitlist_address))?;
+        let ix = self.instruction(
+            accounts::ExpireWaitlistEntry {
+                mint_waitlist: waitlist_address,
+                waitlist_vault: waitlist.vault,
+                refund_token_account: self.token_account(&entry.wallet, &payout_mint).await?,
+                config: pda::config(&self.program_id),
+                payout_mint,
+                token_program: self.owner(&payout_mint).await?,
+            },
+            instruction::ExpireWaitlistEntry {},
+        );
+        self.send(&[payer], &[ix]).await
+    }
+
+    // Escrow the seller's policy NFT, from their associated account, for sale at `price`
+    pub async fn list_nft_for_sale(
+        &self,
+        seller: &Keypair,
+        insurance_nft: Pubkey,
+        price: u64,
+        duration: i64,
+    ) -> Result<Signature> {
+        let policy = self.insurance_nft(&insurance_nft).await?;
+        let ix = self.instruction(
+            accounts::ListNftForSale {
+                insurance_nft,
+                listing: pda::nft_listing(&self.program_id, &insurance_nft),
+                listing_vault: pda::listing_vault(&self.program_id, &insurance_nft),
+                seller_nft_account: self
+                    .token_account(&seller.pubkey(), &policy.token_mint)
+                    .await?,
+                nft_mint: policy.token_mint,
+                config: pda::config(&self.program_id),
+                seller: seller.pubkey(),
+                token_program: self.owner(&policy.token_mint).await?,
+                system_program: system_program::ID,
+            },
+            instruction::ListNftForSale { price, duration },
+        );
+        self.send(&[seller], &[ix]).await
+    }
+
+    // Buy a listed policy. The buyer pays from, and receives the NFT into, their
+    // associated accounts of the treasury mint and the NFT mint.
+    pub async fn buy_listed_nft(
+        &self,
+        buyer: &Keypair,
+        insurance_nft: Pubkey,
+    ) -> Result<Signature> {
+        let wallet = buyer.pubkey();
+        let listing_address = pda::nft_listing(&self.program_id, &insurance_nft);
+        let listing: NftListing = self.fetch(&listing_address).await?;
+        let treasury_address = pda::treasury(&self.program_id);
+        let treasury = self.treasury().await?;
+        let ix = self.instruction(
+            accounts::BuyListedNft {
+                listing: listing_address,
+                insurance_nft,
+                listing_vault: pda::listing_vault(&self.program_id, &insurance_nft),
+                nft_mint: listing.nft_mint,
+                buyer_nft_account: self.token_account(&wallet, &listing.nft_mint).await?,
+                buyer_payment_account: self.token_account(&wallet, &treasury.mint).await?,
+                seller_payment_account: self.token_account(&listing.seller, &treasury.mint).await?,
+                seller_user_account: pda::user_account(&self.program_id, &listing.seller),
+                buyer_user_account: pda::user_account(&self.program_id, &wallet),
+                treasury: treasury_address,
+                treasury_account: treasury.vault,
+                payment_mint: treasury.mint,
+                config: pda::config(&self.program_id),
+                seller: listing.seller,
+                buyer: wallet,
+                token_program: self.owner(&treasury.mint).await?,
+            },
+            instruction::BuyListedNft {},
+        );
+        self.send(&[buyer], &[ix]).await
+    }
+
+    pub async fn cancel_nft_listing(
+        &self,
+        seller: &Keypair,
+        insurance_nft: Pubkey,
+    ) -> Result<Signature> {
+        let listing: NftListing = self
+            .fetch(&pda::nft_listing(&self.program_id, &insurance_nft))
+            .await?;
+        let ix = self.instruction(
+            accounts::CancelNftListing {
+                listing: pda::nft_listing(&self.program_id, &insurance_nft),
+                insurance_nft,
+                listing_vault: pda::listing_vault(&self.program_id, &insurance_nft),
+                nft_mint: listing.nft_mint,
+                seller_nft_account: self
+                    .token_account(&seller.pubkey(), &listing.nft_mint)
+                    .await?,
+                config: pda::config(&self.program_id),
+                seller: seller.pubkey(),
+                token_program: self.owner(&listing.nft_mint).await?,
+            },
+            instruction::CancelNftListing {},
+        );
+        self.send(&[seller], &[ix]).await
+    }
+
+    // Pledge the owner's policy NFT against a loan at `loan` on `lending_program`.
+    // `loan_accounts` and `loan_data` form the lender's instruction.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn collateralize_nft(
+        &self,
+        owner: &Keypair,
+        insurance_nft: Pubkey,
+        lending_program: Pubkey,
+        loan: Pubkey,
+        loan_amount: u64,
+        loan_data: Vec<u8>,
+        loan_accounts: &[AccountMeta],
+    ) -> Result<Signature> {
+        let policy = self.insurance_nft(&insurance_nft).await?;
+        let ix = self.instruction(
+            accounts::CollateralizeNft {
+                insurance_nft,
+                config: pda::config(&self.program_id),
+                owner_nft_account: self
+                    .token_account(&owner.pubkey(), &policy.token_mint)
+                    .await?,
+                collateral_escrow: pda::collateral_escrow(&self.program_id, &insurance_nft),
+                collateral_authority: pda::collateral_authority(&self.program_id, &insurance_nft),
+                nft_mint: policy.token_mint,
+                loan,
+                lending_program,
+                owner: owner.pubkey(),
+                token_program: self.owner(&policy.token_mint).await?,
+                system_program: system_program::ID,
+            },
+            instruction::CollateralizeNft {
+                lending_protocol: lending_program,
+                loan_amount,
+                loan_data,
+            },
+        );
+        self.send(&[owner], &[with_remaining(ix, loan_accounts.to_vec())])
+            .await
+    }
+
+    // `repay_accounts` and `repay_data` form the lender's repay or release instruction
+    pub async fn release_nft_collateral(
+        &self,
+        owner: &Keypair,
+        insurance_nft: Pubkey,
+        lending_program: Pubkey,
+        repay_data: Vec<u8>,
+        repay_accounts: &[AccountMeta],
+    ) -> Result<Signature> {
+        let policy = self.insurance_nft(&insurance_nft).await?;
+        let ix = self.instruction(
+            accounts::ReleaseNftCollateral {
+                insurance_nft,
+                owner_nft_account: self
+                    .token_account(&owner.pubkey(), &policy.token_mint)
+                    .await?,
+                collateral_escrow: pda::collateral_escrow(&self.program_id, &insurance_nft),
+                collateral_authority: pda::collateral_authority(&self.program_id, &insurance_nft),
+                lending_program,
+                config: pda::config(&self.program_id),
+                owner: owner.pubkey(),
+                nft_mint: policy.token_mint,
+                token_program: self.owner(&policy.token_mint).await?,
+            },
+            instruction::ReleaseNftCollateral { repay_data },
+        );
+        self.send(&[owner], &[with_remaining(ix, repay_accounts.to_vec())])
+            .await
+    }
+
+    // Renew the policy from a payment stream at `stream` on `stream_program`.
+    // `stream_accounts` and `stream_data` form the stream program's create instruction.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn setup_renewal_stream(
+        &self,
+        owner: &Keypair,
+        insurance_nft: Pubkey,
+        stream_program: Pubkey,
+        stream: Pubkey,
+        rate_per_second: u64,
+        stream_data: Vec<u8>,
+        stream_accounts: &[AccountMeta],
+    ) -> Result<Signature> {
+        let policy = self.insurance_nft(&insurance_nft).await?;
+        let pool: InsurancePool = self.fetch(&policy.pool).await?;
+        let ix = self.instruction(
+            accounts::SetupRenewalStream {
+                insurance_nft,
+                config: pda::config(&self.program_id),
+                pool: policy.pool,
+                pool_vault: pool.vault,
+                stream,
+                stream_program,
+                owner: owner.pubkey(),
+            },
+            instruction::SetupRenewalStream {
+                stream_program,
+                rate_per_second,
+                stream_data,
+            },
+        );
+        self.send(&[owner], &[with_remaining(ix, stream_accounts.to_vec())])
+            .await
+    }
+
+    // `withdraw_accounts` and `withdraw_data` form the stream program's withdraw
+    // instruction
+    pub async fn process_stream_renewal(
+        &self,
+        keeper: &Keypair,
+        insurance_nft: Pubkey,
+        withdraw_data: Vec<u8>,
+        withdraw_accounts: &[AccountMeta],
+    ) -> Result<Signature> {
+        let policy = self.insurance_nft(&insurance_nft).await?;
+        let treasury = self.treasury().await?;
+        let ix = self.instruction(
+            accounts::ProcessStreamRenewal {
+                insurance_nft,
+                config: pda::config(&self.program_id),
+                treasury: pda::treasury(&self.program_id),
+                treasury_account: treasury.vault,
+                stream: policy.stream_config.stream_id,
+                stream_program: policy.stream_config.stream_program,
+                keeper: keeper.pubkey(),
+            },
+            instruction::ProcessStreamRenewal { withdraw_data },
+        );
+        self.send(&[keeper], &[with_remaining(ix, withdraw_accounts.to_vec())])
+            .await
+    }
+}
+
+// The wallet's token accounts, passed read-only for the program to value
+fn readonly(accounts: &[Pubkey]) -> Vec<AccountMeta> {
+    accounts
+        .iter()
+        .map(|address| AccountMeta::new_readonly(*address, false))
+        .collect()
+}
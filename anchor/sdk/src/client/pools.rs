@@ -0,0 +1,500 @@
+use anchor::{
+    accounts, instruction, InsurancePool, LpBonusConfig, LpPosition, ReinsurancePool, TierConfig,
+};
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::system_program;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+
+use super::IrisClient;
+use crate::{pda, Result, Rpc};
+
+// Pools: their tier settings, LP deposits and withdrawals, epochs, the reinsurance
+// tranche and the solvency gates. LP token movements use the owner's associated
+// account of the pool's payout mint.
+impl<R: Rpc> IrisClient<R> {
+    pub async fn initialize_pool(
+        &self,
+        authority: &Keypair,
+        tier: u8,
+        payout_mint: Pubkey,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::InitializePool {
+                pool: pda::pool(&self.program_id, tier),
+                vault: pda::pool_vault(&self.program_id, tier),
+                payout_mint,
+                governance: pda::governance(&self.program_id),
+                authority: authority.pubkey(),
+                token_program: self.owner(&payout_mint).await?,
+                system_program: system_program::ID,
+            },
+            instruction::InitializePool { tier },
+        );
+        self.send(&[authority], &[ix]).await
+    }
+
+    pub async fn set_tier_config(
+        &self,
+        admin: &Keypair,
+        tier: u8,
+        tier_config: TierConfig,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::SetTierConfig {
+                config: pda::config(&self.program_id),
+                admin: admin.pubkey(),
+                instructions: None,
+            },
+            instruction::SetTierConfig { tier, tier_config },
+        );
+        self.send(&[admin], &[ix]).await
+    }
+
+    pub async fn set_fee_waiver(
+        &self,
+        admin: &Keypair,
+        governance_mint: Pubkey,
+        fee_waiver_burn_rate: u64,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::SetFeeWaiver {
+                config: pda::config(&self.program_id),
+                admin: admin.pubkey(),
+                instructions: None,
+            },
+            instruction::SetFeeWaiver {
+                governance_mint,
+                fee_waiver_burn_rate,
+            },
+        );
+        self.send(&[admin], &[ix]).await
+    }
+
+    pub async fn set_rebate_bps(&self, admin: &Keypair, rebate_bps: u16) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::SetRebateBps {
+                config: pda::config(&self.program_id),
+                admin: admin.pubkey(),
+                instructions: None,
+            },
+            instruction::SetRebateBps { rebate_bps },
+        );
+        self.send(&[admin], &[ix]).await
+    }
+
+    pub async fn set_pool_epoch_length(
+        &self,
+        admin: &Keypair,
+        min_pool_epoch_secs: i64,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            self.epoch_admin_accounts(admin),
+            instruction::SetPoolEpochLength {
+                min_pool_epoch_secs,
+            },
+        );
+        self.send(&[admin], &[ix]).await
+    }
+
+    pub async fn set_withdrawal_limits(
+        &self,
+        admin: &Keypair,
+        max_withdrawal_bps_per_epoch: u16,
+        max_pool_withdrawal_bps_per_epoch: u16,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            self.epoch_admin_accounts(admin),
+            instruction::SetWithdrawalLimits {
+                max_withdrawal_bps_per_epoch,
+                max_pool_withdrawal_bps_per_epoch,
+            },
+        );
+        self.send(&[admin], &[ix]).await
+    }
+
+    pub async fn set_lp_lockup(
+        &self,
+        admin: &Keypair,
+        lp_lockup_secs: i64,
+        early_withdrawal_penalty_bps: u16,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            self.epoch_admin_accounts(admin),
+            instruction::SetLpLockup {
+                lp_lockup_secs,
+                early_withdrawal_penalty_bps,
+            },
+        );
+        self.send(&[admin], &[ix]).await
+    }
+
+    fn epoch_admin_accounts(&self, admin: &Keypair) -> accounts::SetPoolEpochLength {
+        accounts::SetPoolEpochLength {
+            config: pda::config(&self.program_id),
+            admin: admin.pubkey(),
+            instructions: None,
+        }
+    }
+
+    pub async fn set_lp_bonus_config(
+        &self,
+        admin: &Keypair,
+        lp_bonus: LpBonusConfig,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::SetLpBonusConfig {
+                config: pda::config(&self.program_id),
+                admin: admin.pubkey(),
+                instructions: None,
+            },
+            instruction::SetLpBonusConfig { lp_bonus },
+        );
+        self.send(&[admin], &[ix]).await
+    }
+
+    pub async fn deposit_to_pool(
+        &self,
+        depositor: &Keypair,
+        tier: u8,
+        amount: u64,
+    ) -> Result<Signature> {
+        let owner = depositor.pubkey();
+        let pool_address = pda::pool(&self.program_id, tier);
+        let pool = self.pool(tier).await?;
+        let ix = self.instruction(
+            accounts::DepositToPool {
+                pool: pool_address,
+                config: pda::config(&self.program_id),
+                vault: pool.vault,
+                position: pda::lp_position(&self.program_id, &pool_address, &owner),
+                last_epoch: self.last_epoch(&pool_address, &pool, &owner).await?,
+                depositor_token_account: self.token_account(&owner, &pool.payout_mint).await?,
+                depositor: owner,
+                payout_mint: pool.payout_mint,
+                token_program: self.owner(&pool.payout_mint).await?,
+                system_program: system_program::ID,
+            },
+            instruction::DepositToPool { amount },
+        );
+        self.send(&[depositor], &[ix]).await
+    }
+
+    // Add to the pool's assets without taking shares
+    pub async fn donate_to_pool(
+        &self,
+        donor: &Keypair,
+        tier: u8,
+        amount: u64,
+    ) -> Result<Signature> {
+        let pool = self.pool(tier).await?;
+        let ix = self.instruction(
+            accounts::DonateToPool {
+                pool: pda::pool(&self.program_id, tier),
+                config: pda::config(&self.program_id),
+                vault: pool.vault,
+                donor_token_account: self
+                    .token_account(&donor.pubkey(), &pool.payout_mint)
+                    .await?,
+                donor: donor.pubkey(),
+                payout_mint: pool.payout_mint,
+                token_program: self.owner(&pool.payout_mint).await?,
+            },
+            instruction::DonateToPool { amount },
+        );
+        self.send(&[donor], &[ix]).await
+    }
+
+    pub async fn withdraw_from_pool(
+        &self,
+        owner: &Keypair,
+        tier: u8,
+        shares: u64,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            self.lp_accounts(owner, tier).await?,
+            instruction::WithdrawFromPool { shares },
+        );
+        self.send(&[owner], &[ix]).await
+    }
+
+    pub async fn request_early_withdrawal(
+        &self,
+        owner: &Keypair,
+        tier: u8,
+        shares: u64,
+        penalty_accepted: bool,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            self.lp_accounts(owner, tier).await?,
+            instruction::RequestEarlyWithdrawal {
+                shares,
+                penalty_accepted,
+            },
+        );
+        self.send(&[owner], &[ix]).await
+    }
+
+    pub async fn claim_lp_rewards(&self, owner: &Keypair, tier: u8) -> Result<Signature> {
+        let lp = self.lp_accounts(owner, tier).await?;
+        let ix = self.instruction(
+            accounts::ClaimLpRewards {
+                pool: lp.pool,
+                config: lp.config,
+                vault: lp.vault,
+                position: lp.position,
+                last_epoch: lp.last_epoch,
+                owner_token_account: lp.owner_token_account,
+                owner: lp.owner,
+                payout_mint: lp.payout_mint,
+                token_program: lp.token_program,
+            },
+            instruction::ClaimLpRewards {},
+        );
+        self.send(&[owner], &[ix]).await
+    }
+
+    async fn lp_accounts(&self, owner: &Keypair, tier: u8) -> Result<accounts::WithdrawFromPool> {
+        let wallet = owner.pubkey();
+        let pool_address = pda::pool(&self.program_id, tier);
+        let pool = self.pool(tier).await?;
+        Ok(accounts::WithdrawFromPool {
+            pool: pool_address,
+            config: pda::config(&self.program_id),
+            vault: pool.vault,
+            position: pda::lp_position(&self.program_id, &pool_address, &wallet),
+            last_epoch: self.last_epoch(&pool_address, &pool, &wallet).await?,
+            owner_token_account: self.token_account(&wallet, &pool.payout_mint).await?,
+            owner: wallet,
+            payout_mint: pool.payout_mint,
+            token_program: self.owner(&pool.payout_mint).await?,
+        })
+    }
+
+    // Record of the epoch `owner`'s position last settled in, once that epoch has
+    // closed. The program needs it to settle the position's rewards.
+    async fn last_epoch(
+        &self,
+        pool_address: &Pubkey,
+        pool: &InsurancePool,
+        owner: &Pubkey,
+    ) -> Result<Option<Pubkey>> {
+        let position = pda::lp_position(&self.program_id, pool_address, owner);
+        if self.existing(position).await?.is_none() {
+            return Ok(None);
+        }
+        let position: LpPosition = self.fetch(&position).await?;
+        Ok((position.last_update_epoch < pool.current_epoch)
+            .then(|| pda::pool_epoch(&self.program_id, pool_address, position.last_update_epoch)))
+    }
+
+    pub async fn advance_pool_epoch(&self, cranker: &Keypair, tier: u8) -> Result<Signature> {
+        let pool_address = pda::pool(&self.program_id, tier);
+        let current_epoch = self.pool(tier).await?.current_epoch;
+        let ix = self.instruction(
+            accounts::AdvancePoolEpoch {
+                pool: pool_address,
+                config: pda::config(&self.program_id),
+                epoch_record: pda::pool_epoch(&self.program_id, &pool_address, current_epoch),
+                cranker: cranker.pubkey(),
+                system_program: system_program::ID,
+            },
+            instruction::AdvancePoolEpoch {},
+        );
+        self.send(&[cranker], &[ix]).await
+    }
+
+    // The stats are the transaction's return data
+    pub async fn read_insurance_pool_stats(&self, payer: &Keypair, tier: u8) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::ReadInsurancePoolStats {
+                pool: pda::pool(&self.program_id, tier),
+                vault: self.pool(tier).await?.vault,
+                config: pda::config(&self.program_id),
+            },
+            instruction::ReadInsurancePoolStats {},
+        );
+        self.send(&[payer], &[ix]).await
+    }
+
+    // Move `amount` of the donor tier's vault into the recipient tier's
+    pub async fn backstop_sub_pool(
+        &self,
+        authority: &Keypair,
+        donor_tier: u8,
+        recipient_tier: u8,
+        amount: u64,
+    ) -> Result<Signature> {
+        let donor = self.pool(donor_tier).await?;
+        let ix = self.instruction(
+            accounts::BackstopSubPool {
+                donor_pool: pda::pool(&self.program_id, donor_tier),
+                donor_vault: donor.vault,
+                recipient_pool: pda::pool(&self.program_id, recipient_tier),
+                recipient_vault: self.pool(recipient_tier).await?.vault,
+                config: pda::config(&self.program_id),
+                governance: pda::governance(&self.program_id),
+                authority: authority.pubkey(),
+                payout_mint: donor.payout_mint,
+                token_program: self.owner(&donor.payout_mint).await?,
+            },
+            instruction::BackstopSubPool { amount },
+        );
+        self.send(&[authority], &[ix]).await
+    }
+
+    pub async fn exit_haircut_mode(&self, authority: &Keypair, tier: u8) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::ExitHaircutMode {
+                pool: pda::pool(&self.program_id, tier),
+                governance: pda::governance(&self.program_id),
+                config: pda::config(&self.program_id),
+                authority: authority.pubkey(),
+            },
+            instruction::ExitHaircutMode {},
+        );
+        self.send(&[authority], &[ix]).await
+    }
+
+    // Halt minting on the tier if it is undercollateralized; anyone may call it
+    pub async fn assert_and_gate(&self, payer: &Keypair, tier: u8) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::AssertAndGate {
+                pool: pda::pool(&self.program_id, tier),
+                vault: self.pool(tier).await?.vault,
+                config: pda::config(&self.program_id),
+            },
+            instruction::AssertAndGate {},
+        );
+        self.send(&[payer], &[ix]).await
+    }
+
+    pub async fn override_minting_halt(&self, authority: &Keypair, tier: u8) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::OverrideMintingHalt {
+                pool: pda::pool(&self.program_id, tier),
+                vault: self.pool(tier).await?.vault,
+                governance: pda::governance(&self.program_id),
+                config: pda::config(&self.program_id),
+                authority: authority.pubkey(),
+            },
+            instruction::OverrideMintingHalt {},
+        );
+        self.send(&[authority], &[ix]).await
+    }
+
+    pub async fn initialize_reinsurance_pool(
+        &self,
+        authority: &Keypair,
+        payout_mint: Pubkey,
+        activation_threshold: u64,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::InitializeReinsurancePool {
+                reinsurance_pool: pda::reinsurance_pool(&self.program_id),
+                vault: pda::reinsurance_vault(&self.program_id),
+                payout_mint,
+                governance: pda::governance(&self.program_id),
+                authority: authority.pubkey(),
+                token_program: self.owner(&payout_mint).await?,
+                system_program: system_program::ID,
+            },
+            instruction::InitializeReinsurancePool {
+                activation_threshold,
+            },
+        );
+        self.send(&[authority], &[ix]).await
+    }
+
+    pub async fn deposit_to_reinsurance(
+        &self,
+        authority: &Keypair,
+        amount: u64,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            self.reinsurance_accounts(authority).await?,
+            instruction::DepositToReinsurance { amount },
+        );
+        self.send(&[authority], &[ix]).await
+    }
+
+    pub async fn withdraw_from_reinsurance(
+        &self,
+        authority: &Keypair,
+        amount: u64,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            self.reinsurance_accounts(authority).await?,
+            instruction::WithdrawFromReinsurance { amount },
+        );
+        self.send(&[authority], &[ix]).await
+    }
+
+    async fn reinsurance_accounts(&self, authority: &Keypair) -> Result<accounts::FundReinsurance> {
+        let reinsurance_pool = pda::reinsurance_pool(&self.program_id);
+        let pool: ReinsurancePool = self.fetch(&reinsurance_pool).await?;
+        Ok(accounts::FundReinsurance {
+            reinsurance_pool,
+            vault: pool.vault,
+            authority_token_account: self
+                .token_account(&authority.pubkey(), &pool.payout_mint)
+                .await?,
+            payout_mint: pool.payout_mint,
+            config: pda::config(&self.program_id),
+            governance: pda::governance(&self.program_id),
+            authority: authority.pubkey(),
+            token_program: self.owner(&pool.payout_mint).await?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::fixture::{bootstrap, DAY, START};
+    use crate::local::block_on;
+    use crate::pda;
+    use anchor::{LpPosition, PoolEpoch};
+    use solana_sdk::signature::Signer;
+
+    const USDC: u64 = 1_000_000;
+
+    #[test]
+    fn an_lp_deposits_across_an_epoch_and_withdraws_after_the_lockup() {
+        let protocol = bootstrap();
+        let client = &protocol.client;
+        let lp = protocol.wallet(1_500 * USDC);
+        let pool = pda::pool(&client.program_id(), 0);
+        let position = pda::lp_position(&client.program_id(), &pool, &lp.pubkey());
+
+        block_on(client.deposit_to_pool(&lp, 0, 1_000 * USDC)).unwrap();
+        let vault = block_on(client.pool(0)).unwrap().vault;
+        assert_eq!(client.rpc().token_balance(&vault), 1_000 * USDC);
+
+        let cranker = protocol.wallet(0);
+        client.rpc().warp_to(START + 8 * DAY);
+        block_on(client.advance_pool_epoch(&cranker, 0)).unwrap();
+        assert_eq!(block_on(client.pool(0)).unwrap().current_epoch, 1);
+        let record: PoolEpoch =
+            block_on(client.fetch(&pda::pool_epoch(&client.program_id(), &pool, 0))).unwrap();
+        assert_eq!(record.epoch, 0);
+
+        // The position last settled in the closed epoch, so its record goes along
+        block_on(client.deposit_to_pool(&lp, 0, 500 * USDC)).unwrap();
+        let settled: LpPosition = block_on(client.fetch(&position)).unwrap();
+        assert_eq!(settled.last_update_epoch, 1);
+
+        assert!(block_on(client.withdraw_from_pool(&lp, 0, settled.shares)).is_err());
+        client.rpc().warp_to(START + 40 * DAY);
+        block_on(client.withdraw_from_pool(&lp, 0, settled.shares)).unwrap();
+        assert_eq!(
+            block_on(client.fetch::<LpPosition>(&position))
+                .unwrap()
+                .shares,
+            0
+        );
+        // Less the shares the first deposit into an empty pool leaves unowned
+        assert_eq!(
+            protocol.balance(&lp.pubkey(), &protocol.usdc),
+            1_500 * USDC - 1_000
+        );
+    }
+}
@@ -0,0 +1,320 @@
+use anchor::{accounts, instruction};
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::system_program;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+
+use super::IrisClient;
+use crate::{pda, Result, Rpc};
+
+// The treasury and the two IRIS stakes: fee sharing, and the stake that weighs claim
+// votes. Tokens move through the owner's associated accounts.
+impl<R: Rpc> IrisClient<R> {
+    pub async fn initialize_treasury(
+        &self,
+        authority: &Keypair,
+        mint: Pubkey,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::InitializeTreasury {
+                treasury: pda::treasury(&self.program_id),
+                vault: pda::treasury_vault(&self.program_id),
+                mint,
+                governance: pda::governance(&self.program_id),
+                authority: authority.pubkey(),
+                token_program: self.owner(&mint).await?,
+                system_program: system_program::ID,
+            },
+            instruction::InitializeTreasury {},
+        );
+        self.send(&[authority], &[ix]).await
+    }
+
+    // Fees are paid out in the treasury's mint to stakers of `iris_mint`
+    pub async fn initialize_fee_distributor(
+        &self,
+        authority: &Keypair,
+        iris_mint: Pubkey,
+        fee_share_bps: u16,
+        unstake_cooldown: i64,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::InitializeFeeDistributor {
+                distributor: pda::fee_distributor(&self.program_id),
+                stake_vault: pda::fee_stake_vault(&self.program_id),
+                reward_vault: pda::fee_reward_vault(&self.program_id),
+                iris_mint,
+                reward_mint: self.treasury().await?.mint,
+                treasury: pda::treasury(&self.program_id),
+                governance: pda::governance(&self.program_id),
+                authority: authority.pubkey(),
+                token_program: self.owner(&iris_mint).await?,
+                system_program: system_program::ID,
+            },
+            instruction::InitializeFeeDistributor {
+                fee_share_bps,
+                unstake_cooldown,
+            },
+        );
+        self.send(&[authority], &[ix]).await
+    }
+
+    pub async fn stake_iris(&self, owner: &Keypair, amount: u64) -> Result<Signature> {
+        let wallet = owner.pubkey();
+        let distributor = self.fee_distributor().await?;
+        let ix = self.instruction(
+            accounts::StakeIris {
+                distributor: pda::fee_distributor(&self.program_id),
+                stake: pda::fee_stake(&self.program_id, &wallet),
+                stake_vault: distributor.stake_vault,
+                owner_iris_account: self.token_account(&wallet, &distributor.iris_mint).await?,
+                user_account: pda::user_account(&self.program_id, &wallet),
+                config: pda::config(&self.program_id),
+                owner: wallet,
+                iris_mint: distributor.iris_mint,
+                token_program: self.owner(&distributor.iris_mint).await?,
+                system_program: system_program::ID,
+            },
+            instruction::StakeIris { amount },
+        );
+        self.send(&[owner], &[ix]).await
+    }
+
+    // Move the treasury's fee share into the reward vault; anyone may crank it
+    pub async fn distribute_fees(&self, payer: &Keypair) -> Result<Signature> {
+        let treasury = self.treasury().await?;
+        let ix = self.instruction(
+            accounts::DistributeFees {
+                distributor: pda::fee_distributor(&self.program_id),
+                config: pda::config(&self.program_id),
+                treasury: pda::treasury(&self.program_id),
+                treasury_vault: treasury.vault,
+                reward_vault: self.fee_distributor().await?.reward_vault,
+                treasury_mint: treasury.mint,
+                token_program: self.owner(&treasury.mint).await?,
+            },
+            instruction::DistributeFees {},
+        );
+        self.send(&[payer], &[ix]).await
+    }
+
+    pub async fn claim_fee_share(&self, owner: &Keypair) -> Result<Signature> {
+        let wallet = owner.pubkey();
+        let reward_vault = self.fee_distributor().await?.reward_vault;
+        let reward_mint = self.treasury().await?.mint;
+        let ix = self.instruction(
+            accounts::ClaimFeeShare {
+                distributor: pda::fee_distributor(&self.program_id),
+                stake: pda::fee_stake(&self.program_id, &wallet),
+                reward_vault,
+                destination: self.token_account(&wallet, &reward_mint).await?,
+                config: pda::config(&self.program_id),
+                owner: wallet,
+                reward_mint,
+                token_program: self.owner(&reward_mint).await?,
+            },
+            instruction::ClaimFeeShare {},
+        );
+        self.send(&[owner], &[ix]).await
+    }
+
+    pub async fn request_unstake(&self, owner: &Keypair, amount: u64) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::RequestUnstake {
+                distributor: pda::fee_distributor(&self.program_id),
+                stake: pda::fee_stake(&self.program_id, &owner.pubkey()),
+                config: pda::config(&self.program_id),
+                owner: owner.pubkey(),
+            },
+            instruction::RequestUnstake { amount },
+        );
+        self.send(&[owner], &[ix]).await
+    }
+
+    pub async fn complete_unstake(&self, owner: &Keypair) -> Result<Signature> {
+        let wallet = owner.pubkey();
+        let distributor = self.fee_distributor().await?;
+        let ix = self.instruction(
+            accounts::CompleteUnstake {
+                distributor: pda::fee_distributor(&self.program_id),
+                stake: pda::fee_stake(&self.program_id, &wallet),
+                stake_vault: distributor.stake_vault,
+                owner_iris_account: self.token_account(&wallet, &distributor.iris_mint).await?,
+                config: pda::config(&self.program_id),
+                owner: wallet,
+                iris_mint: distributor.iris_mint,
+                token_program: self.owner(&distributor.iris_mint).await?,
+            },
+            instruction::CompleteUnstake {},
+        );
+        self.send(&[owner], &[ix]).await
+    }
+
+    pub async fn initialize_claim_staking(
+        &self,
+        authority: &Keypair,
+        iris_mint: Pubkey,
+        quorum_weight: u64,
+        unstake_cooldown: i64,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::InitializeClaimStaking {
+                staking: pda::claim_staking(&self.program_id),
+                vault: pda::claim_stake_vault(&self.program_id),
+                iris_mint,
+                governance: pda::governance(&self.program_id),
+                authority: authority.pubkey(),
+                token_program: self.owner(&iris_mint).await?,
+                system_program: system_program::ID,
+            },
+            instruction::InitializeClaimStaking {
+                quorum_weight,
+                unstake_cooldown,
+            },
+        );
+        self.send(&[authority], &[ix]).await
+    }
+
+    pub async fn update_claim_staking(
+        &self,
+        authority: &Keypair,
+        quorum_weight: u64,
+        unstake_cooldown: i64,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::UpdateClaimStaking {
+                staking: pda::claim_staking(&self.program_id),
+                governance: pda::governance(&self.program_id),
+                authority: authority.pubkey(),
+            },
+            instruction::UpdateClaimStaking {
+                quorum_weight,
+                unstake_cooldown,
+            },
+        );
+        self.send(&[authority], &[ix]).await
+    }
+
+    pub async fn stake_for_claim_votes(&self, owner: &Keypair, amount: u64) -> Result<Signature> {
+        let wallet = owner.pubkey();
+        let staking = self.claim_staking().await?;
+        let ix = self.instruction(
+            accounts::StakeForClaimVotes {
+                staking: pda::claim_staking(&self.program_id),
+                stake: pda::claim_stake(&self.program_id, &wallet),
+                vault: staking.vault,
+                owner_iris_account: self.token_account(&wallet, &staking.iris_mint).await?,
+                user_account: pda::user_account(&self.program_id, &wallet),
+                config: pda::config(&self.program_id),
+                owner: wallet,
+                iris_mint: staking.iris_mint,
+                token_program: self.owner(&staking.iris_mint).await?,
+                system_program: system_program::ID,
+            },
+            instruction::StakeForClaimVotes { amount },
+        );
+        self.send(&[owner], &[ix]).await
+    }
+
+    // Refused while the stake is delegated
+    pub async fn request_claim_unstake(&self, owner: &Keypair, amount: u64) -> Result<Signature> {
+        let wallet = owner.pubkey();
+        let ix = self.instruction(
+            accounts::RequestClaimUnstake {
+                staking: pda::claim_staking(&self.program_id),
+                stake: pda::claim_stake(&self.program_id, &wallet),
+                delegation: pda::delegation(&self.program_id, &wallet),
+                config: pda::config(&self.program_id),
+                owner: wallet,
+            },
+            instruction::RequestClaimUnstake { amount },
+        );
+        self.send(&[owner], &[ix]).await
+    }
+
+    pub async fn complete_claim_unstake(&self, owner: &Keypair) -> Result<Signature> {
+        let wallet = owner.pubkey();
+        let staking = self.claim_staking().await?;
+        let ix = self.instruction(
+            accounts::CompleteClaimUnstake {
+                staking: pda::claim_staking(&self.program_id),
+                stake: pda::claim_stake(&self.program_id, &wallet),
+                vault: staking.vault,
+                owner_iris_account: self.token_account(&wallet, &staking.iris_mint).await?,
+                config: pda::config(&self.program_id),
+                owner: wallet,
+                iris_mint: staking.iris_mint,
+                token_program: self.owner(&staking.iris_mint).await?,
+            },
+            instruction::CompleteClaimUnstake {},
+        );
+        self.send(&[owner], &[ix]).await
+    }
+
+    // The vault claim voters' IRIS incentives are paid from
+    pub async fn initialize_vote_incentive_vault(&self, authority: &Keypair) -> Result<Signature> {
+        let iris_mint = self.fee_distributor().await?.iris_mint;
+        let ix = self.instruction(
+            accounts::InitializeVoteIncentiveVault {
+                governance: pda::governance(&self.program_id),
+                distributor: pda::fee_distributor(&self.program_id),
+                vault: pda::vote_incentive_vault(&self.program_id),
+                iris_mint,
+                authority: authority.pubkey(),
+                token_program: self.owner(&iris_mint).await?,
+                system_program: system_program::ID,
+            },
+            instruction::InitializeVoteIncentiveVault {},
+        );
+        self.send(&[authority], &[ix]).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::fixture::{bootstrap, DAY, START};
+    use crate::local::block_on;
+    use crate::pda;
+    use anchor::FeeStake;
+    use solana_sdk::signature::Signer;
+
+    const USDC: u64 = 1_000_000;
+    const IRIS: u64 = 1_000_000_000;
+
+    #[test]
+    fn a_staker_earns_the_fee_share_and_unstakes_after_the_cooldown() {
+        let protocol = bootstrap();
+        let client = &protocol.client;
+        block_on(client.initialize_fee_distributor(&protocol.admin, protocol.iris, 2_000, 7 * DAY))
+            .unwrap();
+
+        // Staking needs a subscription old enough to carry governance weight
+        let staker = protocol.user(100 * IRIS);
+        block_on(client.subscribe(&staker, 1, 30 * DAY as u64, 10 * USDC)).unwrap();
+        assert!(block_on(client.stake_iris(&staker, 100 * IRIS)).is_err());
+        client.rpc().warp_to(START + 7 * DAY);
+        block_on(client.stake_iris(&staker, 100 * IRIS)).unwrap();
+        let stake_address = pda::fee_stake(&client.program_id(), &staker.pubkey());
+
+        // Both subscriptions' 20 USDC reach the treasury; 20% of it is shared
+        let subscriber = protocol.user(10 * USDC);
+        block_on(client.subscribe(&subscriber, 1, 30 * DAY as u64, 10 * USDC)).unwrap();
+        block_on(client.distribute_fees(&subscriber)).unwrap();
+        block_on(client.claim_fee_share(&staker)).unwrap();
+        assert_eq!(
+            protocol.balance(&staker.pubkey(), &protocol.usdc),
+            100 * IRIS - 10 * USDC + 4 * USDC
+        );
+
+        block_on(client.request_unstake(&staker, 100 * IRIS)).unwrap();
+        let stake: FeeStake = block_on(client.fetch(&stake_address)).unwrap();
+        assert_eq!(stake.unstake_available_at, START + 14 * DAY);
+        assert!(block_on(client.complete_unstake(&staker)).is_err());
+        client.rpc().warp_to(START + 14 * DAY);
+        block_on(client.complete_unstake(&staker)).unwrap();
+        assert_eq!(
+            protocol.balance(&staker.pubkey(), &protocol.iris),
+            100 * IRIS
+        );
+        assert_eq!(block_on(client.fee_distributor()).unwrap().total_staked, 0);
+    }
+}
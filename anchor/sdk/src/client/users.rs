@@ -0,0 +1,544 @@
+use anchor::{accounts, instruction, ProtectionAction, RiskParams, Score, Treasury, UserAccount};
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::{system_program, sysvar};
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+use solana_sdk::instruction::AccountMeta;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+
+use super::{with_remaining, IrisClient};
+use crate::{pda, Result, Rpc};
+
+// The wallet's own account: setup, preferences, subscription and protection
+impl<R: Rpc> IrisClient<R> {
+    pub async fn initialize_user(
+        &self,
+        user: &Keypair,
+        preferences: RiskParams,
+    ) -> Result<Signature> {
+        let wallet = user.pubkey();
+        let ix = self.instruction(
+            accounts::InitializeUser {
+                user_account: pda::user_account(&self.program_id, &wallet),
+                user: wallet,
+                payer: wallet,
+                system_program: system_program::ID,
+            },
+            instruction::InitializeUser { preferences },
+        );
+        self.send(&[user], &[ix]).await
+    }
+
+    pub async fn set_max_auto_swap_bps(
+        &self,
+        user: &Keypair,
+        max_auto_swap_amount_bps: u16,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::SetMaxAutoSwapBps {
+                user_account: pda::user_account(&self.program_id, &user.pubkey()),
+                user: user.pubkey(),
+            },
+            instruction::SetMaxAutoSwapBps {
+                max_auto_swap_amount_bps,
+            },
+        );
+        self.send(&[user], &[ix]).await
+    }
+
+    pub async fn pause_watchlist_alerts(&self, user: &Keypair, duration: i64) -> Result<Signature> {
+        let ix = self.instruction(
+            self.watchlist_alert_accounts(user),
+            instruction::PauseWatchlistAlerts { duration },
+        );
+        self.send(&[user], &[ix]).await
+    }
+
+    pub async fn resume_watchlist_alerts(&self, user: &Keypair) -> Result<Signature> {
+        let ix = self.instruction(
+            self.watchlist_alert_accounts(user),
+            instruction::ResumeWatchlistAlerts {},
+        );
+        self.send(&[user], &[ix]).await
+    }
+
+    fn watchlist_alert_accounts(&self, user: &Keypair) -> accounts::SetWatchlistAlertsPaused {
+        accounts::SetWatchlistAlertsPaused {
+            user_account: pda::user_account(&self.program_id, &user.pubkey()),
+            config: pda::config(&self.program_id),
+            user: user.pubkey(),
+        }
+    }
+
+    pub async fn watchlist_alert(&self, user: &Keypair, token: Pubkey) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::WatchlistAlert {
+                user_account: pda::user_account(&self.program_id, &user.pubkey()),
+                config: pda::config(&self.program_id),
+                user: user.pubkey(),
+            },
+            instruction::WatchlistAlert { token },
+        );
+        self.send(&[user], &[ix]).await
+    }
+
+    pub async fn add_covered_token(
+        &self,
+        user: &Keypair,
+        token: Pubkey,
+        max_amount: u64,
+    ) -> Result<Signature> {
+        let wallet = user.pubkey();
+        let ix = self.instruction(
+            accounts::AddCoveredToken {
+                user_account: pda::user_account(&self.program_id, &wallet),
+                covered_tokens: pda::covered_tokens(&self.program_id, &wallet),
+                config: pda::config(&self.program_id),
+                user: wallet,
+                system_program: system_program::ID,
+            },
+            instruction::AddCoveredToken { token, max_amount },
+        );
+        self.send(&[user], &[ix]).await
+    }
+
+    pub async fn remove_covered_token(&self, user: &Keypair, token: Pubkey) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::RemoveCoveredToken {
+                covered_tokens: pda::covered_tokens(&self.program_id, &user.pubkey()),
+                config: pda::config(&self.program_id),
+                user: user.pubkey(),
+            },
+            instruction::RemoveCoveredToken { token },
+        );
+        self.send(&[user], &[ix]).await
+    }
+
+    // Move the user's keypair-based account at `legacy_user_account` into their PDA
+    pub async fn migrate_legacy_user(
+        &self,
+        user: &Keypair,
+        legacy_user_account: Pubkey,
+    ) -> Result<Signature> {
+        let wallet = user.pubkey();
+        let ix = self.instruction(
+            accounts::MigrateLegacyUser {
+                legacy_user_account,
+                user_account: pda::user_account(&self.program_id, &wallet),
+                config: pda::config(&self.program_id),
+                user: wallet,
+                system_program: system_program::ID,
+            },
+            instruction::MigrateLegacyUser { wallet },
+        );
+        self.send(&[user], &[ix]).await
+    }
+
+    // Rewrite the user account at `account` in the current layout, `payer` funding
+    // any growth
+    pub async fn migrate_user_account_layout(
+        &self,
+        payer: &Keypair,
+        account: Pubkey,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            self.layout_migration_accounts(payer, account),
+            instruction::MigrateUserAccountLayout {},
+        );
+        self.send(&[payer], &[ix]).await
+    }
+
+    pub async fn migrate_insurance_nft_layout(
+        &self,
+        payer: &Keypair,
+        account: Pubkey,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            self.layout_migration_accounts(payer, account),
+            instruction::MigrateInsuranceNftLayout {},
+        );
+        self.send(&[payer], &[ix]).await
+    }
+
+    pub async fn migrate_claim_layout(
+        &self,
+        payer: &Keypair,
+        account: Pubkey,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            self.layout_migration_accounts(payer, account),
+            instruction::MigrateClaimLayout {},
+        );
+        self.send(&[payer], &[ix]).await
+    }
+
+    fn layout_migration_accounts(
+        &self,
+        payer: &Keypair,
+        account: Pubkey,
+    ) -> accounts::MigrateAccountLayout {
+        accounts::MigrateAccountLayout {
+            account,
+            config: pda::config(&self.program_id),
+            payer: payer.pubkey(),
+            system_program: system_program::ID,
+        }
+    }
+
+    // Close the user's account, refunding its rent to whoever funded it. The claim
+    // history is closed with it when the wallet has one.
+    pub async fn close_user_account(&self, user: &Keypair) -> Result<Signature> {
+        let wallet = user.pubkey();
+        let user_account: UserAccount = self.user_account(&wallet).await?;
+        let ix = self.instruction(
+            accounts::CloseUserAccount {
+                user_account: pda::user_account(&self.program_id, &wallet),
+                claim_history: self
+                    .existing(pda::claim_history(&self.program_id, &wallet))
+                    .await?,
+                config: pda::config(&self.program_id),
+                user: wallet,
+                rent_recipient: user_account.rent_recipient(),
+            },
+            instruction::CloseUserAccount {},
+        );
+        self.send(&[user], &[ix]).await
+    }
+
+    // Pay for `plan_id` from the user's associated account of the treasury mint
+    pub async fn subscribe(
+        &self,
+        user: &Keypair,
+        plan_id: u8,
+        duration: u64,
+        payment_amount: u64,
+    ) -> Result<Signature> {
+        let wallet = user.pubkey();
+        let treasury_address = pda::treasury(&self.program_id);
+        let treasury: Treasury = self.fetch(&treasury_address).await?;
+        let token_program = self.owner(&treasury.mint).await?;
+        let ix = self.instruction(
+            accounts::Subscribe {
+                user_account: pda::user_account(&self.program_id, &wallet),
+                payment_account: get_associated_token_address_with_program_id(
+                    &wallet,
+                    &treasury.mint,
+                    &token_program,
+                ),
+                config: pda::config(&self.program_id),
+                treasury: treasury_address,
+                treasury_account: treasury.vault,
+                payment_mint: treasury.mint,
+                promo_code_account: None,
+                governance_mint: None,
+                governance_token_account: None,
+                user: wallet,
+                token_program,
+                memo_program: None,
+                instructions: None,
+                activity: Some(pda::wallet_activity(&self.program_id, &wallet)),
+                system_program: Some(system_program::ID),
+                event_authority: self.event_authority(),
+                program: self.program_id,
+            },
+            instruction::Subscribe {
+                plan_id,
+                duration,
+                payment_amount,
+                promo_code: None,
+                burn_for_waiver: None,
+                reference: None,
+                quote: None,
+            },
+        );
+        self.send(&[user], &[ix]).await
+    }
+
+    // Paid into the user's associated account of the treasury mint. A `reference` is
+    // recorded in a memo alongside the transfer.
+    pub async fn claim_no_claim_rebate(
+        &self,
+        user: &Keypair,
+        reference: Option<[u8; 16]>,
+    ) -> Result<Signature> {
+        let wallet = user.pubkey();
+        let treasury_address = pda::treasury(&self.program_id);
+        let treasury: Treasury = self.fetch(&treasury_address).await?;
+        let token_program = self.owner(&treasury.mint).await?;
+        let ix = self.instruction(
+            accounts::ClaimNoClaimRebate {
+                user_account: pda::user_account(&self.program_id, &wallet),
+                claim_history: pda::claim_history(&self.program_id, &wallet),
+                config: pda::config(&self.program_id),
+                treasury: treasury_address,
+                treasury_vault: treasury.vault,
+                user_token_account: get_associated_token_address_with_program_id(
+                    &wallet,
+                    &treasury.mint,
+                    &token_program,
+                ),
+                user: wallet,
+                treasury_mint: treasury.mint,
+                token_program,
+                memo_program: reference.map(|_| anchor_spl::memo::ID),
+            },
+            instruction::ClaimNoClaimRebate { reference },
+        );
+        self.send(&[user], &[ix]).await
+    }
+
+    // Add lamports to the pool keepers are reimbursed from
+    pub async fn fund_maintenance(&self, funder: &Keypair, amount: u64) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::FundMaintenance {
+                config: pda::config(&self.program_id),
+                funder: funder.pubkey(),
+                system_program: system_program::ID,
+            },
+            instruction::FundMaintenance { amount },
+        );
+        self.send(&[funder], &[ix]).await
+    }
+
+    // Expire the lapsed subscriptions among `wallets`, at most MAX_EXPIRY_BATCH of them
+    pub async fn batch_expire_subscriptions(
+        &self,
+        keeper: &Keypair,
+        wallets: &[Pubkey],
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::BatchExpireSubscriptions {
+                config: pda::config(&self.program_id),
+                keeper: keeper.pubkey(),
+                keeper_stats: pda::keeper_stats(&self.program_id, &keeper.pubkey()),
+                instructions: Some(sysvar::instructions::ID),
+                system_program: system_program::ID,
+            },
+            instruction::BatchExpireSubscriptions {},
+        );
+        let user_accounts = wallets
+            .iter()
+            .map(|wallet| AccountMeta::new(pda::user_account(&self.program_id, wallet), false));
+        self.send(&[keeper], &[with_remaining(ix, user_accounts)])
+            .await
+    }
+
+    pub async fn expire_subscription(&self, keeper: &Keypair, wallet: Pubkey) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::ExpireSubscription {
+                user_account: pda::user_account(&self.program_id, &wallet),
+                config: pda::config(&self.program_id),
+                keeper: keeper.pubkey(),
+                keeper_stats: pda::keeper_stats(&self.program_id, &keeper.pubkey()),
+                instructions: Some(sysvar::instructions::ID),
+                system_program: system_program::ID,
+            },
+            instruction::ExpireSubscription {},
+        );
+        self.send(&[keeper], &[ix]).await
+    }
+
+    // Dispute the score the user's account is holding before it applies
+    pub async fn dispute_pending_score(
+        &self,
+        user: &Keypair,
+        reason: [u8; 64],
+    ) -> Result<Signature> {
+        let wallet = user.pubkey();
+        let ix = self.instruction(
+            accounts::DisputePendingScore {
+                user_account: pda::user_account(&self.program_id, &wallet),
+                oracle_dispute: pda::oracle_dispute(&self.program_id, &wallet),
+                config: pda::config(&self.program_id),
+                user: wallet,
+                system_program: system_program::ID,
+            },
+            instruction::DisputePendingScore { reason },
+        );
+        self.send(&[user], &[ix]).await
+    }
+
+    pub async fn init_score_history(&self, payer: &Keypair, wallet: Pubkey) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::InitScoreHistory {
+                score_archive: pda::score_history(&self.program_id, &wallet),
+                user_account: pda::user_account(&self.program_id, &wallet),
+                payer: payer.pubkey(),
+                system_program: system_program::ID,
+            },
+            instruction::InitScoreHistory {},
+        );
+        self.send(&[payer], &[ix]).await
+    }
+
+    // The score is the transaction's return data; `payer` only pays the fee
+    pub async fn get_effective_score(&self, payer: &Keypair, wallet: Pubkey) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::ReadUserAccount {
+                user_account: pda::user_account(&self.program_id, &wallet),
+            },
+            instruction::GetEffectiveScore {},
+        );
+        self.send(&[payer], &[ix]).await
+    }
+
+    // Fails unless `score` is leaf `leaf_index` of the wallet's score hash chain
+    pub async fn verify_score_inclusion(
+        &self,
+        payer: &Keypair,
+        wallet: Pubkey,
+        score: Score,
+        merkle_proof: Vec<[u8; 32]>,
+        leaf_index: u32,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::VerifyScoreInclusion {
+                user_account: pda::user_account(&self.program_id, &wallet),
+            },
+            instruction::VerifyScoreInclusion {
+                score,
+                merkle_proof,
+                leaf_index,
+            },
+        );
+        self.send(&[payer], &[ix]).await
+    }
+
+    // Swaps and freezes need `token` on the wallet's covered list. Swaps route through
+    // `swap_program` and sell from the user's associated account of `token`; other
+    // actions ignore it. The action is logged to the compressed tree
+    // when the protocol has one, and to a fresh ActionLog account otherwise.
+    pub async fn trigger_protection(
+        &self,
+        user: &Keypair,
+        action: ProtectionAction,
+        token: Pubkey,
+        amount: u64,
+        swap_program: Option<Pubkey>,
+    ) -> Result<Signature> {
+        let wallet = user.pubkey();
+        let config = self.config().await?;
+        let compressed = config.action_log_tree.compressed;
+        let action_log = (!compressed).then(Keypair::new);
+
+        let swap = matches!(action, ProtectionAction::Swap);
+        let swap_token_account = if swap {
+            Some(self.token_account(&wallet, &token).await?)
+        } else {
+            None
+        };
+
+        let ix = self.instruction(
+            accounts::TriggerProtection {
+                user_account: pda::user_account(&self.program_id, &wallet),
+                covered_tokens: matches!(action, ProtectionAction::Swap | ProtectionAction::Freeze)
+                    .then(|| pda::covered_tokens(&self.program_id, &wallet)),
+                config: pda::config(&self.program_id),
+                action_log: action_log.as_ref().map(Keypair::pubkey),
+                merkle_tree: compressed.then_some(config.action_log_tree.merkle_tree),
+                log_authority: compressed.then(|| pda::log_authority(&self.program_id)),
+                compression_program: compressed
+                    .then_some(anchor::compression::ACCOUNT_COMPRESSION_ID),
+                noop_program: compressed.then_some(anchor::compression::NOOP_ID),
+                covered_mint: swap.then_some(token),
+                swap_token_account,
+                swap_program: swap_program.filter(|_| swap),
+                activity: Some(pda::wallet_activity(&self.program_id, &wallet)),
+                user: wallet,
+                payer: wallet,
+                system_program: system_program::ID,
+                event_authority: self.event_authority(),
+                program: self.program_id,
+            },
+            instruction::TriggerProtection {
+                action_type: action,
+                token,
+                amount,
+                i_know_what_im_doing: false,
+            },
+        );
+        match &action_log {
+            Some(action_log) => self.send(&[user, action_log], &[ix]).await,
+            None => self.send(&[user], &[ix]).await,
+        }
+    }
+
+    // The estimate is the transaction's return data
+    pub async fn estimate_protection_cost(
+        &self,
+        payer: &Keypair,
+        action: ProtectionAction,
+        token: Pubkey,
+        amount: u64,
+    ) -> Result<Signature> {
+        let ix = self.instruction(
+            accounts::EstimateProtectionCost {
+                config: pda::config(&self.program_id),
+            },
+            instruction::EstimateProtectionCost {
+                action_type: action,
+                token,
+                amount,
+            },
+        );
+        self.send(&[payer], &[ix]).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::fixture::{bootstrap, DAY, START};
+    use crate::local::block_on;
+    use crate::pda;
+    use anchor_lang::prelude::Pubkey;
+    use solana_sdk::signature::Signer;
+
+    const USDC: u64 = 1_000_000;
+
+    #[test]
+    fn a_subscription_is_paid_into_the_treasury_and_lapses_for_a_keeper() {
+        let protocol = bootstrap();
+        let client = &protocol.client;
+        let user = protocol.user(100 * USDC);
+
+        block_on(client.subscribe(&user, 1, 30 * DAY as u64, 10 * USDC)).unwrap();
+        let account = block_on(client.user_account(&user.pubkey())).unwrap();
+        assert!(account.active_sub);
+        assert_eq!(account.subscription_expiry, START + 30 * DAY);
+        assert_eq!(protocol.balance(&user.pubkey(), &protocol.usdc), 90 * USDC);
+        let vault = pda::treasury_vault(&client.program_id());
+        assert_eq!(client.rpc().token_balance(&vault), 10 * USDC);
+        assert_eq!(
+            block_on(client.treasury()).unwrap().total_inflows,
+            10 * USDC
+        );
+
+        // Within the grace period the subscription still stands
+        let keeper = protocol.wallet(0);
+        client.rpc().warp_to(START + 31 * DAY);
+        assert!(block_on(client.expire_subscription(&keeper, user.pubkey())).is_err());
+        client.rpc().warp_to(START + 34 * DAY);
+        block_on(client.expire_subscription(&keeper, user.pubkey())).unwrap();
+        assert!(
+            !block_on(client.user_account(&user.pubkey()))
+                .unwrap()
+                .active_sub
+        );
+    }
+
+    #[test]
+    fn covered_tokens_are_added_and_removed_by_the_user() {
+        let protocol = bootstrap();
+        let client = &protocol.client;
+        let user = protocol.user(0);
+        let token = Pubkey::new_unique();
+
+        block_on(client.add_covered_token(&user, token, 5_000)).unwrap();
+        let covered = pda::covered_tokens(&client.program_id(), &user.pubkey());
+        let list: anchor::CoveredTokenList = block_on(client.fetch(&covered)).unwrap();
+        assert!(list.covered_tokens.iter().any(|entry| entry.mint == token));
+
+        block_on(client.remove_covered_token(&user, token)).unwrap();
+        let list: anchor::CoveredTokenList = block_on(client.fetch(&covered)).unwrap();
+        assert!(list.covered_tokens.iter().all(|entry| entry.mint != token));
+    }
+}
@@ -0,0 +1,115 @@
+use anchor_lang::event::EVENT_IX_TAG_LE;
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AnchorDeserialize, Discriminator, Event};
+use solana_sdk::instruction::Instruction;
+
+use crate::pda;
+
+// Decoding of events the program delivers through a self-CPI (`emit_cpi!` and
+// `emit_cpi_seq!`). Those never reach the logs; an indexer finds them among a
+// transaction's inner instructions.
+
+// `E` if `instruction` is the program at `program_id` delivering one, signed by its
+// event authority. Anything else, including another event type, gives None.
+pub fn decode_cpi_event<E: Event + Discriminator + AnchorDeserialize>(
+    program_id: &Pubkey,
+    instruction: &Instruction,
+) -> Option<E> {
+    if instruction.program_id != *program_id {
+        return None;
+    }
+    let authority = instruction.accounts.first()?;
+    if authority.pubkey != pda::event_authority(program_id) || !authority.is_signer {
+        return None;
+    }
+    let data = instruction.data.strip_prefix(EVENT_IX_TAG_LE.as_slice())?;
+    let mut data = data.strip_prefix(E::DISCRIMINATOR.as_slice())?;
+    E::deserialize(&mut data).ok()
+}
+
+// Every `E` delivered among `inner_instructions`, in emission order
+pub fn decode_cpi_events<E: Event + Discriminator + AnchorDeserialize>(
+    program_id: &Pubkey,
+    inner_instructions: &[Instruction],
+) -> Vec<E> {
+    inner_instructions
+        .iter()
+        .filter_map(|instruction| decode_cpi_event(program_id, instruction))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::local::{block_on, LocalRpc};
+    use crate::{accounts, instruction, IrisClient};
+    use anchor::{
+        Claim, ClaimResolved, ClaimStatus, InsurancePool, ProtocolConfig, SubscriptionEvent,
+    };
+    use anchor_lang::{AccountDeserialize, Space};
+    use solana_sdk::signature::{Keypair, Signer};
+
+    // A zeroed `T`, as a freshly created account would deserialize
+    fn zeroed<T: AccountDeserialize + Space>() -> T {
+        T::try_deserialize_unchecked(&mut vec![0; 8 + T::INIT_SPACE].as_slice()).unwrap()
+    }
+
+    #[test]
+    fn claim_resolved_is_decoded_from_the_emit_cpi_seq_inner_instruction() {
+        let rpc = LocalRpc::new();
+        let program_id = anchor::ID;
+        let payer = Keypair::new();
+        rpc.airdrop(payer.pubkey(), 1_000_000_000);
+
+        let config_address = pda::config(&program_id);
+        let mut config: ProtocolConfig = zeroed();
+        config.bump = Pubkey::find_program_address(&[b"config"], &program_id).1;
+        rpc.set_program_account(config_address, &config);
+        let (pool_address, pool_bump) = Pubkey::find_program_address(&[b"pool"], &program_id);
+        let mut pool: InsurancePool = zeroed();
+        pool.bump = pool_bump;
+        rpc.set_program_account(pool_address, &pool);
+        let claim_address = Pubkey::new_unique();
+        let mut claim: Claim = zeroed();
+        claim.pool = pool_address;
+        claim.voting_ends_at = 100;
+        claim.claim_number = 7;
+        rpc.set_program_account(claim_address, &claim);
+        rpc.warp_to(200);
+
+        let client = IrisClient::new(rpc);
+        let close = client.instruction(
+            accounts::CloseClaimVoting {
+                claim: claim_address,
+                config: config_address,
+                pool: pool_address,
+                event_authority: pda::event_authority(&program_id),
+                program: program_id,
+            },
+            instruction::CloseClaimVoting {},
+        );
+        block_on(client.send(&[&payer], &[close])).unwrap();
+        let claim = block_on(client.claim(&claim_address)).unwrap();
+        assert_eq!(claim.status, ClaimStatus::Rejected);
+
+        let executed = client.rpc().executed();
+        let inner = &executed[0].inner_instructions;
+        let resolved: Vec<ClaimResolved> = decode_cpi_events(&program_id, inner);
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].claim_id, claim_address);
+        assert_eq!(resolved[0].status, ClaimStatus::Rejected);
+        assert_eq!(resolved[0].claim_number, 7);
+        assert_eq!(resolved[0].timestamp, 200);
+        assert_eq!(resolved[0].seq, 1);
+
+        // Delivered by CPI only, never through the logs
+        let logged = ClaimResolved::DISCRIMINATOR;
+        assert!(!executed[0]
+            .events
+            .iter()
+            .any(|data| data.starts_with(&logged)));
+        // The same bytes from another program, or read as another event, are not it
+        assert!(decode_cpi_event::<ClaimResolved>(&Pubkey::new_unique(), &inner[0]).is_none());
+        assert!(decode_cpi_event::<SubscriptionEvent>(&program_id, &inner[0]).is_none());
+    }
+}
@@ -0,0 +1,114 @@
+// Client for the Iris program. Every instruction has a builder on `IrisClient` that
+// derives its PDAs and looks up the accounts it needs. The typed account and
+// argument structs Anchor generates (re-exported here as `accounts` and
+// `instruction`) can still be sent through `IrisClient::send` directly.
+//
+// The client doesn't pick an RPC library: anything implementing `Rpc` will do.
+
+use std::fmt;
+use std::future::Future;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AccountDeserialize, InstructionData, ToAccountMetas};
+use solana_sdk::account::Account;
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::signature::{Keypair, Signature, Signer, SignerError};
+use solana_sdk::transaction::Transaction;
+
+pub use anchor::{accounts, instruction};
+
+mod client;
+pub mod events;
+#[cfg(test)]
+mod local;
+pub mod pda;
+
+pub use client::IrisClient;
+
+#[derive(Debug)]
+pub enum Error {
+    // The RPC node failed or rejected the request
+    Rpc(String),
+    AccountNotFound(Pubkey),
+    // The account exists but isn't the expected type or layout
+    InvalidAccountData(Pubkey),
+    // The mint waitlist has no entry to act on
+    WaitlistEmpty(Pubkey),
+    // The proposal is not of the type the builder executes
+    WrongProposalType(Pubkey),
+    Signing(SignerError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Rpc(message) => write!(f, "rpc error: {message}"),
+            Error::AccountNotFound(address) => write!(f, "account {address} not found"),
+            Error::InvalidAccountData(address) => {
+                write!(f, "account {address} could not be deserialized")
+            }
+            Error::WaitlistEmpty(address) => write!(f, "mint waitlist {address} is empty"),
+            Error::WrongProposalType(address) => {
+                write!(f, "proposal {address} is of another type")
+            }
+            Error::Signing(err) => write!(f, "signing failed: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<SignerError> for Error {
+    fn from(err: SignerError) -> Self {
+        Error::Signing(err)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+// The node access the client needs
+pub trait Rpc {
+    fn latest_blockhash(&self) -> impl Future<Output = Result<Hash>>;
+
+    // Submit a signed transaction and wait for it to be confirmed
+    fn send_transaction(
+        &self,
+        transaction: &Transaction,
+    ) -> impl Future<Output = Result<Signature>>;
+
+    // None when the account doesn't exist
+    fn get_account(&self, address: &Pubkey) -> impl Future<Output = Result<Option<Account>>>;
+}
+
+// The instruction for `args` on `accounts`, against `program_id`
+pub fn build_instruction(
+    program_id: Pubkey,
+    accounts: impl ToAccountMetas,
+    args: impl InstructionData,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: accounts.to_account_metas(None),
+        data: args.data(),
+    }
+}
+
+// Sign `instructions` with the fee payer first, then any other required signers
+fn sign(
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+    blockhash: Hash,
+) -> Result<Transaction> {
+    let payer = signers
+        .first()
+        .ok_or(Error::Signing(SignerError::NotEnoughSigners))?;
+    let mut transaction = Transaction::new_with_payer(instructions, Some(&payer.pubkey()));
+    transaction.try_sign(signers, blockhash)?;
+    Ok(transaction)
+}
+
+fn deserialize<T: AccountDeserialize>(address: &Pubkey, account: &Account) -> Result<T> {
+    T::try_deserialize(&mut account.data.as_slice())
+        .map_err(|_| Error::InvalidAccountData(*address))
+}
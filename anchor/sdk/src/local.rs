@@ -0,0 +1,587 @@
+// In-process stand-in for a local validator, for tests that send real transactions.
+// Each instruction runs through the program's entrypoint against an account store.
+// System account creation and transfers, and token account set-up, transfers, mint-tos
+// and burns made by CPI are carried out; every CPI is recorded as an inner instruction. Other
+// programs' instructions at the top level are refused.
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::sync::Once;
+
+use anchor_lang::prelude::{AccountInfo, Clock, ProgramError, Pubkey, Rent};
+use anchor_lang::solana_program::bpf_loader_upgradeable::{self, UpgradeableLoaderState};
+use anchor_lang::solana_program::entrypoint::ProgramResult;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_lang::solana_program::program_stubs::{self, SyscallStubs};
+use anchor_lang::solana_program::program_utils::limited_deserialize;
+use anchor_lang::solana_program::system_instruction::SystemInstruction;
+use anchor_lang::solana_program::sysvar::instructions::{
+    self as sysvar_instructions, construct_instructions_data, store_current_index,
+    BorrowedAccountMeta, BorrowedInstruction,
+};
+use anchor_lang::solana_program::{ed25519_program, system_program};
+use anchor_lang::{AccountSerialize, Space};
+use anchor_spl::token::spl_token;
+use anchor_spl::token::spl_token::instruction::TokenInstruction;
+use solana_sdk::account::Account;
+use solana_sdk::compute_budget;
+use solana_sdk::hash::Hash;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::Transaction;
+
+use crate::{Error, Result, Rpc};
+
+// What a confirmed transaction left behind for indexers
+pub struct Executed {
+    pub signature: Signature,
+    pub inner_instructions: Vec<Instruction>,
+    pub events: Vec<Vec<u8>>,
+}
+
+pub struct LocalRpc {
+    accounts: RefCell<HashMap<Pubkey, Account>>,
+    executed: RefCell<Vec<Executed>>,
+}
+
+// Room an account may grow by within one instruction, as on chain
+const MAX_PERMITTED_DATA_INCREASE: usize = 10 * 1024;
+
+impl LocalRpc {
+    pub fn new() -> Self {
+        install_syscalls();
+        let rpc = Self {
+            accounts: RefCell::new(HashMap::new()),
+            executed: RefCell::new(Vec::new()),
+        };
+        for program in [
+            anchor::ID,
+            system_program::ID,
+            spl_token::ID,
+            anchor_spl::token_2022::ID,
+        ] {
+            rpc.set_account(
+                program,
+                Account {
+                    lamports: 1,
+                    executable: true,
+                    owner: anchor_lang::solana_program::bpf_loader_upgradeable::ID,
+                    ..Account::default()
+                },
+            );
+        }
+        rpc
+    }
+
+    pub fn set_account(&self, address: Pubkey, account: Account) {
+        self.accounts.borrow_mut().insert(address, account);
+    }
+
+    // A program account holding `value`, rent-exempt at its full size
+    pub fn set_program_account<T: AccountSerialize + Space>(&self, address: Pubkey, value: &T) {
+        let mut data = Vec::new();
+        value.try_serialize(&mut data).unwrap();
+        data.resize(data.len().max(8 + T::INIT_SPACE), 0);
+        self.set_account(
+            address,
+            Account {
+                lamports: Rent::default().minimum_balance(data.len()),
+                data,
+                owner: anchor::ID,
+                ..Account::default()
+            },
+        );
+    }
+
+    // The program's upgrade data, naming the key allowed to claim its config
+    pub fn set_upgrade_authority(&self, upgrade_authority: Pubkey) {
+        let state = UpgradeableLoaderState::ProgramData {
+            slot: 0,
+            upgrade_authority_address: Some(upgrade_authority),
+        };
+        let data = bincode::serialize(&state).unwrap();
+        self.set_account(
+            crate::pda::program_data(&anchor::ID),
+            Account {
+                lamports: Rent::default().minimum_balance(data.len()),
+                data,
+                owner: bpf_loader_upgradeable::ID,
+                ..Account::default()
+            },
+        );
+    }
+
+    // A legacy token mint with `authority` as its mint authority
+    pub fn create_mint(&self, address: Pubkey, authority: Pubkey, decimals: u8) {
+        let mint = spl_token::state::Mint {
+            mint_authority: Some(authority).into(),
+            decimals,
+            is_initialized: true,
+            ..spl_token::state::Mint::default()
+        };
+        self.set_token_state(address, mint);
+    }
+
+    // A legacy token account of `mint` holding `amount`, which is added to the supply
+    pub fn create_token_account(&self, address: Pubkey, mint: Pubkey, owner: Pubkey, amount: u64) {
+        let account = spl_token::state::Account {
+            mint,
+            owner,
+            amount,
+            state: spl_token::state::AccountState::Initialized,
+            ..spl_token::state::Account::default()
+        };
+        self.set_token_state(address, account);
+        let mut accounts = self.accounts.borrow_mut();
+        let mint = accounts.get_mut(&mint).expect("mint exists");
+        let mut state = spl_token::state::Mint::unpack(&mint.data).unwrap();
+        state.supply += amount;
+        state.pack_into_slice(&mut mint.data);
+    }
+
+    // Balance of the token account at `address`
+    pub fn token_balance(&self, address: &Pubkey) -> u64 {
+        let account = self.account(address).expect("token account exists");
+        spl_token::state::Account::unpack(&account.data)
+            .unwrap()
+            .amount
+    }
+
+    fn set_token_state<T: Pack>(&self, address: Pubkey, state: T) {
+        let mut data = vec![0; T::LEN];
+        state.pack_into_slice(&mut data);
+        self.set_account(
+            address,
+            Account {
+                lamports: Rent::default().minimum_balance(data.len()),
+                data,
+                owner: spl_token::ID,
+                ..Account::default()
+            },
+        );
+    }
+
+    pub fn airdrop(&self, address: Pubkey, lamports: u64) {
+        let mut accounts = self.accounts.borrow_mut();
+        let account = accounts.entry(address).or_insert_with(|| Account {
+            owner: system_program::ID,
+            ..Account::default()
+        });
+        account.lamports += lamports;
+    }
+
+    pub fn account(&self, address: &Pubkey) -> Option<Account> {
+        self.accounts.borrow().get(address).cloned()
+    }
+
+    // Set the unix time the clock sysvar reports
+    pub fn warp_to(&self, unix_timestamp: i64) {
+        NOW.with(|now| now.set(unix_timestamp));
+    }
+
+    pub fn executed(&self) -> std::cell::Ref<'_, Vec<Executed>> {
+        self.executed.borrow()
+    }
+
+    // Run every instruction of `transaction` in order. Nothing is stored unless all
+    // of them succeed.
+    fn execute(&self, transaction: &Transaction) -> Result<Executed> {
+        transaction
+            .verify()
+            .map_err(|err| Error::Rpc(err.to_string()))?;
+        let message = &transaction.message;
+        let keys = &message.account_keys;
+        let instructions: Vec<Instruction> = message
+            .instructions
+            .iter()
+            .map(|ix| Instruction {
+                program_id: keys[ix.program_id_index as usize],
+                accounts: ix
+                    .accounts
+                    .iter()
+                    .map(|&i| {
+                        let i = i as usize;
+                        let pubkey = keys[i];
+                        if message.is_writable(i) {
+                            anchor_lang::solana_program::instruction::AccountMeta::new(
+                                pubkey,
+                                message.is_signer(i),
+                            )
+                        } else {
+                            anchor_lang::solana_program::instruction::AccountMeta::new_readonly(
+                                pubkey,
+                                message.is_signer(i),
+                            )
+                        }
+                    })
+                    .collect(),
+                data: ix.data.clone(),
+            })
+            .collect();
+
+        let mut slots: Vec<Slot> = keys
+            .iter()
+            .enumerate()
+            .map(|(i, key)| {
+                let account = if *key == sysvar_instructions::ID {
+                    Account {
+                        owner: anchor_lang::solana_program::sysvar::ID,
+                        ..Account::default()
+                    }
+                } else {
+                    self.account(key).unwrap_or_else(|| Account {
+                        owner: system_program::ID,
+                        ..Account::default()
+                    })
+                };
+                Slot::new(*key, account, message.is_signer(i), message.is_writable(i))
+            })
+            .collect();
+        if let Some(slot) = slots.iter_mut().find(|s| s.key == sysvar_instructions::ID) {
+            slot.set_data(instructions_sysvar(&instructions, 0));
+        }
+
+        capture();
+        for (index, ix) in instructions.iter().enumerate() {
+            if let Some(slot) = slots.iter_mut().find(|s| s.key == sysvar_instructions::ID) {
+                slot.set_data(instructions_sysvar(&instructions, index));
+            }
+            if ix.program_id == compute_budget::ID || ix.program_id == ed25519_program::ID {
+                continue;
+            }
+            if ix.program_id != anchor::ID {
+                return Err(Error::Rpc(format!(
+                    "program {} is not loaded",
+                    ix.program_id
+                )));
+            }
+            // Realloc changes an account's length in place; assign writes the owner
+            // straight into its slot
+            let lengths: Vec<usize> = {
+                let infos: Vec<AccountInfo> = slots.iter_mut().map(Slot::info).collect();
+                let accounts: Vec<AccountInfo> = ix
+                    .accounts
+                    .iter()
+                    .map(|meta| {
+                        infos
+                            .iter()
+                            .find(|info| *info.key == meta.pubkey)
+                            .unwrap()
+                            .clone()
+                    })
+                    .collect();
+                anchor::entry(&ix.program_id, &accounts, &ix.data).map_err(|err| {
+                    Error::Rpc(format!(
+                        "instruction {index} failed: {err}; logs: {:?}",
+                        logs()
+                    ))
+                })?;
+                infos.iter().map(AccountInfo::data_len).collect()
+            };
+            for (slot, len) in slots.iter_mut().zip(lengths) {
+                slot.len = len;
+            }
+        }
+
+        let mut accounts = self.accounts.borrow_mut();
+        for slot in slots.iter().filter(|slot| slot.is_writable) {
+            if slot.lamports == 0 {
+                accounts.remove(&slot.key);
+                continue;
+            }
+            let account = Account {
+                lamports: slot.lamports,
+                data: slot.data().to_vec(),
+                owner: slot.owner,
+                executable: slot.executable,
+                rent_epoch: 0,
+            };
+            accounts.insert(slot.key, account);
+        }
+        let (inner_instructions, events) = take_captured();
+        Ok(Executed {
+            signature: transaction.signatures[0],
+            inner_instructions,
+            events,
+        })
+    }
+}
+
+impl Rpc for LocalRpc {
+    async fn latest_blockhash(&self) -> Result<Hash> {
+        Ok(Hash::new_unique())
+    }
+
+    async fn send_transaction(&self, transaction: &Transaction) -> Result<Signature> {
+        let executed = self.execute(transaction)?;
+        let signature = executed.signature;
+        self.executed.borrow_mut().push(executed);
+        Ok(signature)
+    }
+
+    async fn get_account(&self, address: &Pubkey) -> Result<Option<Account>> {
+        Ok(self.account(address))
+    }
+}
+
+// An account as the runtime lays it out for a program: the original data length sits
+// just before the key, and the data is preceded by its length and followed by room to
+// grow, which realloc relies on
+#[repr(C)]
+struct Slot {
+    _padding: u32,
+    original_data_len: u32,
+    key: Pubkey,
+    owner: Pubkey,
+    lamports: u64,
+    is_signer: bool,
+    is_writable: bool,
+    executable: bool,
+    len: usize,
+    buffer: Vec<u8>,
+}
+
+impl Slot {
+    fn new(key: Pubkey, account: Account, is_signer: bool, is_writable: bool) -> Self {
+        let mut slot = Self {
+            _padding: 0,
+            original_data_len: 0,
+            key,
+            owner: account.owner,
+            lamports: account.lamports,
+            is_signer,
+            is_writable,
+            executable: account.executable,
+            len: 0,
+            buffer: Vec::new(),
+        };
+        slot.set_data(account.data);
+        slot
+    }
+
+    fn set_data(&mut self, data: Vec<u8>) {
+        self.len = data.len();
+        self.original_data_len = data.len() as u32;
+        self.buffer = vec![0; 8 + data.len() + MAX_PERMITTED_DATA_INCREASE];
+        self.buffer[8..8 + data.len()].copy_from_slice(&data);
+    }
+
+    fn data(&self) -> &[u8] {
+        &self.buffer[8..8 + self.len]
+    }
+
+    fn info(&mut self) -> AccountInfo<'_> {
+        let len = self.len;
+        AccountInfo::new(
+            &self.key,
+            self.is_signer,
+            self.is_writable,
+            &mut self.lamports,
+            &mut self.buffer[8..8 + len],
+            &self.owner,
+            self.executable,
+            0,
+        )
+    }
+}
+
+// Instructions sysvar data for `instructions` with `current` executing
+fn instructions_sysvar(instructions: &[Instruction], current: usize) -> Vec<u8> {
+    let borrowed: Vec<BorrowedInstruction> = instructions
+        .iter()
+        .map(|ix| BorrowedInstruction {
+            program_id: &ix.program_id,
+            accounts: ix
+                .accounts
+                .iter()
+                .map(|meta| BorrowedAccountMeta {
+                    pubkey: &meta.pubkey,
+                    is_signer: meta.is_signer,
+                    is_writable: meta.is_writable,
+                })
+                .collect(),
+            data: &ix.data,
+        })
+        .collect();
+    let mut data = construct_instructions_data(&borrowed);
+    store_current_index(&mut data, current as u16);
+    data
+}
+
+thread_local! {
+    static NOW: Cell<i64> = const { Cell::new(0) };
+    static INNER: RefCell<Vec<Instruction>> = const { RefCell::new(Vec::new()) };
+    static EVENTS: RefCell<Vec<Vec<u8>>> = const { RefCell::new(Vec::new()) };
+    static LOGS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+fn capture() {
+    INNER.with(|inner| inner.borrow_mut().clear());
+    EVENTS.with(|events| events.borrow_mut().clear());
+    LOGS.with(|logs| logs.borrow_mut().clear());
+}
+
+fn logs() -> Vec<String> {
+    LOGS.with(|logs| logs.borrow().clone())
+}
+
+fn take_captured() -> (Vec<Instruction>, Vec<Vec<u8>>) {
+    (
+        INNER.with(|inner| inner.take()),
+        EVENTS.with(|events| events.take()),
+    )
+}
+
+struct LocalSyscalls;
+
+impl SyscallStubs for LocalSyscalls {
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { *(var_addr as *mut Rent) = Rent::default() };
+        0
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = Clock {
+            unix_timestamp: NOW.with(Cell::get),
+            ..Clock::default()
+        };
+        unsafe { *(var_addr as *mut Clock) = clock };
+        0
+    }
+
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        _signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        INNER.with(|inner| inner.borrow_mut().push(instruction.clone()));
+        let account = |index: usize| {
+            let key = instruction
+                .accounts
+                .get(index)
+                .ok_or(ProgramError::NotEnoughAccountKeys)?
+                .pubkey;
+            account_infos
+                .iter()
+                .find(|info| *info.key == key)
+                .ok_or(ProgramError::NotEnoughAccountKeys)
+        };
+        if instruction.program_id == system_program::ID {
+            return match limited_deserialize(&instruction.data, 1232)
+                .map_err(|_| ProgramError::InvalidInstructionData)?
+            {
+                SystemInstruction::CreateAccount {
+                    lamports,
+                    space,
+                    owner,
+                } => {
+                    move_lamports(account(0)?, account(1)?, lamports)?;
+                    account(1)?.realloc(space as usize, true)?;
+                    account(1)?.assign(&owner);
+                    Ok(())
+                }
+                SystemInstruction::Transfer { lamports } => {
+                    move_lamports(account(0)?, account(1)?, lamports)
+                }
+                SystemInstruction::Allocate { space } => {
+                    account(0)?.realloc(space as usize, true)?;
+                    Ok(())
+                }
+                SystemInstruction::Assign { owner } => {
+                    account(0)?.assign(&owner);
+                    Ok(())
+                }
+                _ => Ok(()),
+            };
+        }
+        if instruction.program_id == spl_token::ID {
+            return match TokenInstruction::unpack(&instruction.data)? {
+                TokenInstruction::Transfer { amount } => {
+                    move_tokens(account(0)?, account(1)?, amount)
+                }
+                TokenInstruction::TransferChecked { amount, .. } => {
+                    move_tokens(account(0)?, account(2)?, amount)
+                }
+                TokenInstruction::MintTo { amount }
+                | TokenInstruction::MintToChecked { amount, .. } => {
+                    update_supply(account(0)?, |supply| supply.checked_add(amount))?;
+                    update_balance(account(1)?, |balance| balance.checked_add(amount))
+                }
+                TokenInstruction::Burn { amount }
+                | TokenInstruction::BurnChecked { amount, .. } => {
+                    update_balance(account(0)?, |balance| balance.checked_sub(amount))?;
+                    update_supply(account(1)?, |supply| supply.checked_sub(amount))
+                }
+                TokenInstruction::InitializeAccount3 { owner }
+                | TokenInstruction::InitializeAccount2 { owner } => {
+                    let state = spl_token::state::Account {
+                        mint: *account(1)?.key,
+                        owner,
+                        state: spl_token::state::AccountState::Initialized,
+                        ..spl_token::state::Account::default()
+                    };
+                    state.pack_into_slice(&mut account(0)?.try_borrow_mut_data()?);
+                    Ok(())
+                }
+                _ => Ok(()),
+            };
+        }
+        Ok(())
+    }
+
+    fn sol_log(&self, message: &str) {
+        LOGS.with(|logs| logs.borrow_mut().push(message.to_string()));
+    }
+
+    fn sol_log_data(&self, data: &[&[u8]]) {
+        EVENTS.with(|events| events.borrow_mut().push(data.concat()));
+    }
+}
+
+fn move_lamports(from: &AccountInfo, to: &AccountInfo, lamports: u64) -> ProgramResult {
+    let left = from
+        .lamports()
+        .checked_sub(lamports)
+        .ok_or(ProgramError::InsufficientFunds)?;
+    **from.try_borrow_mut_lamports()? = left;
+    **to.try_borrow_mut_lamports()? += lamports;
+    Ok(())
+}
+
+fn move_tokens(from: &AccountInfo, to: &AccountInfo, amount: u64) -> ProgramResult {
+    update_balance(from, |balance| balance.checked_sub(amount))?;
+    update_balance(to, |balance| balance.checked_add(amount))
+}
+
+fn update_balance(info: &AccountInfo, update: impl Fn(u64) -> Option<u64>) -> ProgramResult {
+    let mut data = info.try_borrow_mut_data()?;
+    let mut account = spl_token::state::Account::unpack(&data)?;
+    account.amount = update(account.amount).ok_or(ProgramError::InsufficientFunds)?;
+    account.pack_into_slice(&mut data);
+    Ok(())
+}
+
+fn update_supply(info: &AccountInfo, update: impl Fn(u64) -> Option<u64>) -> ProgramResult {
+    let mut data = info.try_borrow_mut_data()?;
+    let mut mint = spl_token::state::Mint::unpack(&data)?;
+    mint.supply = update(mint.supply).ok_or(ProgramError::InsufficientFunds)?;
+    mint.pack_into_slice(&mut data);
+    Ok(())
+}
+
+fn install_syscalls() {
+    static SYSCALLS: Once = Once::new();
+    SYSCALLS.call_once(|| {
+        program_stubs::set_syscall_stubs(Box::new(LocalSyscalls));
+    });
+}
+
+// LocalRpc never suspends, so polling once is enough
+pub fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    use std::task::{Context, Poll, Waker};
+    match std::pin::pin!(future).poll(&mut Context::from_waker(Waker::noop())) {
+        Poll::Ready(output) => output,
+        Poll::Pending => panic!("rpc future suspended"),
+    }
+}
@@ -0,0 +1,324 @@
+use anchor::{InsurancePool, WalletActivity};
+use anchor_lang::prelude::Pubkey;
+
+// Addresses of the program's PDAs, mirroring the seeds in the program's account
+// contexts. Each takes the program id so the client can target any deployment.
+
+fn find(seeds: &[&[u8]], program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(seeds, program_id).0
+}
+
+pub fn config(program_id: &Pubkey) -> Pubkey {
+    find(&[b"config"], program_id)
+}
+
+pub fn governance(program_id: &Pubkey) -> Pubkey {
+    find(&[b"governance"], program_id)
+}
+
+pub fn treasury(program_id: &Pubkey) -> Pubkey {
+    find(&[b"treasury"], program_id)
+}
+
+// Token account holding the treasury's funds, owned by the treasury PDA
+pub fn treasury_vault(program_id: &Pubkey) -> Pubkey {
+    find(&[b"treasury_vault"], program_id)
+}
+
+pub fn oracle_registry(program_id: &Pubkey) -> Pubkey {
+    find(&[b"oracle_registry"], program_id)
+}
+
+pub fn fee_distributor(program_id: &Pubkey) -> Pubkey {
+    find(&[b"fee_distributor"], program_id)
+}
+
+pub fn user_account(program_id: &Pubkey, wallet: &Pubkey) -> Pubkey {
+    find(&[b"user", wallet.as_ref()], program_id)
+}
+
+pub fn score_history(program_id: &Pubkey, wallet: &Pubkey) -> Pubkey {
+    find(&[b"score_history", wallet.as_ref()], program_id)
+}
+
+pub fn claim_history(program_id: &Pubkey, wallet: &Pubkey) -> Pubkey {
+    find(&[b"claim_history", wallet.as_ref()], program_id)
+}
+
+pub fn fee_stake(program_id: &Pubkey, owner: &Pubkey) -> Pubkey {
+    find(&[b"fee_stake", owner.as_ref()], program_id)
+}
+
+// IRIS staked to vote on claims, separate from the fee-share stake
+pub fn claim_staking(program_id: &Pubkey) -> Pubkey {
+    find(&[b"claim_staking"], program_id)
+}
+
+pub fn claim_stake(program_id: &Pubkey, owner: &Pubkey) -> Pubkey {
+    find(&[b"claim_stake", owner.as_ref()], program_id)
+}
+
+pub fn delegation(program_id: &Pubkey, delegator: &Pubkey) -> Pubkey {
+    find(&[b"delegation", delegator.as_ref()], program_id)
+}
+
+pub fn covered_tokens(program_id: &Pubkey, wallet: &Pubkey) -> Pubkey {
+    find(&[b"covered_tokens", wallet.as_ref()], program_id)
+}
+
+pub fn wallet_activity(program_id: &Pubkey, wallet: &Pubkey) -> Pubkey {
+    find(&[WalletActivity::SEED, wallet.as_ref()], program_id)
+}
+
+// Tier 0 is the shared pool
+pub fn pool(program_id: &Pubkey, tier: u8) -> Pubkey {
+    find(&[b"pool", InsurancePool::tier_seed(&tier)], program_id)
+}
+
+pub fn pool_vault(program_id: &Pubkey, tier: u8) -> Pubkey {
+    find(
+        &[b"pool_vault", InsurancePool::tier_seed(&tier)],
+        program_id,
+    )
+}
+
+pub fn lp_position(program_id: &Pubkey, pool: &Pubkey, owner: &Pubkey) -> Pubkey {
+    find(&[b"lp_position", pool.as_ref(), owner.as_ref()], program_id)
+}
+
+pub fn proposal(program_id: &Pubkey, id: u64) -> Pubkey {
+    find(&[b"proposal", id.to_le_bytes().as_ref()], program_id)
+}
+
+pub fn proposal_vote(program_id: &Pubkey, proposal: &Pubkey, voter: &Pubkey) -> Pubkey {
+    find(
+        &[b"proposal_vote", proposal.as_ref(), voter.as_ref()],
+        program_id,
+    )
+}
+
+pub fn claim_vote(program_id: &Pubkey, claim: &Pubkey, voter: &Pubkey) -> Pubkey {
+    find(&[b"claim_vote", claim.as_ref(), voter.as_ref()], program_id)
+}
+
+pub fn nft_listing(program_id: &Pubkey, insurance_nft: &Pubkey) -> Pubkey {
+    find(&[b"nft_listing", insurance_nft.as_ref()], program_id)
+}
+
+pub fn listing_vault(program_id: &Pubkey, insurance_nft: &Pubkey) -> Pubkey {
+    find(&[b"listing_vault", insurance_nft.as_ref()], program_id)
+}
+
+pub fn policy_number(program_id: &Pubkey, number: u64) -> Pubkey {
+    find(
+        &[b"policy_number", number.to_le_bytes().as_ref()],
+        program_id,
+    )
+}
+
+pub fn claim_number(program_id: &Pubkey, number: u64) -> Pubkey {
+    find(
+        &[b"claim_number", number.to_le_bytes().as_ref()],
+        program_id,
+    )
+}
+
+// Signer of the program's self-CPI events, passed to every instruction that emits them
+pub fn event_authority(program_id: &Pubkey) -> Pubkey {
+    find(&[b"__event_authority"], program_id)
+}
+
+// Write authority over the compressed action log tree
+pub fn log_authority(program_id: &Pubkey) -> Pubkey {
+    find(&[anchor::compression::LOG_AUTHORITY_SEED], program_id)
+}
+
+// Holder of the upgrade authority once program upgrades are governed
+pub fn upgrade_authority(program_id: &Pubkey) -> Pubkey {
+    find(
+        &[anchor::ProtocolConfig::UPGRADE_AUTHORITY_SEED],
+        program_id,
+    )
+}
+
+// The program's ProgramData account under the upgradeable loader
+pub fn program_data(program_id: &Pubkey) -> Pubkey {
+    find(
+        &[program_id.as_ref()],
+        &anchor_lang::solana_program::bpf_loader_upgradeable::ID,
+    )
+}
+
+pub fn reinsurance_pool(program_id: &Pubkey) -> Pubkey {
+    find(&[b"reinsurance_pool"], program_id)
+}
+
+pub fn reinsurance_vault(program_id: &Pubkey) -> Pubkey {
+    find(&[b"reinsurance_vault"], program_id)
+}
+
+pub fn emergency_council(program_id: &Pubkey) -> Pubkey {
+    find(&[b"emergency_council"], program_id)
+}
+
+pub fn attestor_registry(program_id: &Pubkey) -> Pubkey {
+    find(&[b"attestor_registry"], program_id)
+}
+
+// IRIS paid out to claim voters, held by the governance PDA
+pub fn vote_incentive_vault(program_id: &Pubkey) -> Pubkey {
+    find(&[b"vote_incentive_vault"], program_id)
+}
+
+pub fn fee_stake_vault(program_id: &Pubkey) -> Pubkey {
+    find(&[b"fee_stake_vault"], program_id)
+}
+
+pub fn fee_reward_vault(program_id: &Pubkey) -> Pubkey {
+    find(&[b"fee_reward_vault"], program_id)
+}
+
+pub fn claim_stake_vault(program_id: &Pubkey) -> Pubkey {
+    find(&[b"claim_stake_vault"], program_id)
+}
+
+pub fn mint_waitlist(program_id: &Pubkey, pool: &Pubkey) -> Pubkey {
+    find(&[b"mint_waitlist", pool.as_ref()], program_id)
+}
+
+// Premiums escrowed for waitlisted mints, owned by the waitlist PDA
+pub fn waitlist_vault(program_id: &Pubkey, pool: &Pubkey) -> Pubkey {
+    find(&[b"waitlist_vault", pool.as_ref()], program_id)
+}
+
+pub fn pool_epoch(program_id: &Pubkey, pool: &Pubkey, epoch: u64) -> Pubkey {
+    find(
+        &[b"pool_epoch", pool.as_ref(), epoch.to_le_bytes().as_ref()],
+        program_id,
+    )
+}
+
+pub fn keeper_stats(program_id: &Pubkey, keeper: &Pubkey) -> Pubkey {
+    find(&[b"keeper_stats", keeper.as_ref()], program_id)
+}
+
+pub fn oracle_dispute(program_id: &Pubkey, wallet: &Pubkey) -> Pubkey {
+    find(&[b"oracle_dispute", wallet.as_ref()], program_id)
+}
+
+// Reports are numbered per wallet from 0, in the order its score archive records them
+pub fn aggregation_report(program_id: &Pubkey, wallet: &Pubkey, index: u32) -> Pubkey {
+    find(
+        &[b"aggregation_report", wallet.as_ref(), &index.to_le_bytes()],
+        program_id,
+    )
+}
+
+pub fn loss_attestation(program_id: &Pubkey, wallet: &Pubkey) -> Pubkey {
+    find(&[b"loss_attestation", wallet.as_ref()], program_id)
+}
+
+pub fn evidence_custody(program_id: &Pubkey, claim: &Pubkey) -> Pubkey {
+    find(&[b"evidence_custody", claim.as_ref()], program_id)
+}
+
+pub fn yield_escrow(program_id: &Pubkey, claim: &Pubkey) -> Pubkey {
+    find(&[b"yield_escrow", claim.as_ref()], program_id)
+}
+
+pub fn yield_escrow_vault(program_id: &Pubkey, claim: &Pubkey) -> Pubkey {
+    find(&[b"yield_escrow_vault", claim.as_ref()], program_id)
+}
+
+// Token account holding a policy NFT pledged as loan collateral
+pub fn collateral_escrow(program_id: &Pubkey, insurance_nft: &Pubkey) -> Pubkey {
+    find(&[b"collateral_escrow", insurance_nft.as_ref()], program_id)
+}
+
+// Owner of the collateral escrow, signing for it towards the lending program
+pub fn collateral_authority(program_id: &Pubkey, insurance_nft: &Pubkey) -> Pubkey {
+    find(&[b"collateral", insurance_nft.as_ref()], program_id)
+}
+
+pub fn coverage_certificate(
+    program_id: &Pubkey,
+    insurance_nft: &Pubkey,
+    recipient: &Pubkey,
+) -> Pubkey {
+    find(
+        &[
+            b"coverage_certificate",
+            insurance_nft.as_ref(),
+            recipient.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+pub fn signer_rotation(program_id: &Pubkey, old_signer: &Pubkey, new_signer: &Pubkey) -> Pubkey {
+    find(
+        &[b"signer_rotation", old_signer.as_ref(), new_signer.as_ref()],
+        program_id,
+    )
+}
+
+pub fn promo_code(program_id: &Pubkey, code: &[u8; 16]) -> Pubkey {
+    find(&[b"promo", code.as_ref()], program_id)
+}
+
+// Policy account of a compressed policy, keyed by the id chosen at mint
+pub fn compressed_policy(program_id: &Pubkey, policy_id: &Pubkey) -> Pubkey {
+    find(&[b"policy", policy_id.as_ref()], program_id)
+}
+
+pub fn vote_snapshot(program_id: &Pubkey, proposal: &Pubkey, voter: &Pubkey) -> Pubkey {
+    find(
+        &[b"snapshot", proposal.as_ref(), voter.as_ref()],
+        program_id,
+    )
+}
+
+// Creator of the compressed policy tree, and signer of every mint into it
+pub fn cnft_tree_authority(program_id: &Pubkey) -> Pubkey {
+    find(&[anchor::cnft::TREE_AUTHORITY_SEED], program_id)
+}
+
+// Delegate of a compressed policy while a claim on it is open
+pub fn cnft_escrow(program_id: &Pubkey) -> Pubkey {
+    find(&[anchor::cnft::ESCROW_SEED], program_id)
+}
+
+// Bubblegum's config account for `merkle_tree`
+pub fn tree_config(merkle_tree: &Pubkey) -> Pubkey {
+    find(&[merkle_tree.as_ref()], &anchor::cnft::BUBBLEGUM_ID)
+}
+
+// Signer of the pool TVL the program publishes to its Switchboard feed
+pub fn tvl_publisher(program_id: &Pubkey) -> Pubkey {
+    find(&[anchor::switchboard::PUBLISHER_SEED], program_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_pool_keeps_the_untiered_address() {
+        let program_id = anchor::ID;
+        assert_eq!(pool(&program_id, 0), find(&[b"pool"], &program_id));
+        assert_eq!(
+            pool_vault(&program_id, 0),
+            find(&[b"pool_vault"], &program_id)
+        );
+        assert_ne!(pool(&program_id, 1), pool(&program_id, 0));
+    }
+
+    #[test]
+    fn treasury_accounts_are_program_derived() {
+        // Off-curve, so no keypair can sign for them
+        let program_id = anchor::ID;
+        assert!(!treasury(&program_id).is_on_curve());
+        assert!(!treasury_vault(&program_id).is_on_curve());
+        assert_ne!(treasury_vault(&program_id), treasury(&program_id));
+    }
+}